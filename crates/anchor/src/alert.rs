@@ -0,0 +1,61 @@
+use reality_core::checkpoint::Checkpoint;
+use reqwest::Client;
+use serde::Serialize;
+use tracing::{error, warn};
+
+use crate::{config::AlertConfig, store::RootConflict};
+
+/// Payload POSTed to every alert webhook.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Alert {
+    /// Consecutive failed rounds reached the configured threshold.
+    AnchoringFailing {
+        consecutive_failures: u32,
+        last_error: String,
+    },
+    /// A round succeeded again after `AnchoringFailing` fired.
+    AnchoringRecovered { failed_rounds: u32 },
+    /// The log contradicted an anchored checkpoint; fired immediately.
+    RootConflict { conflict: RootConflict },
+    /// In witness mode, the log served a checkpoint that does not extend the
+    /// last one this witness cosigned.
+    WitnessInconsistent {
+        previous: Checkpoint,
+        observed: Checkpoint,
+        reason: String,
+    },
+}
+
+pub struct Alerter {
+    config: AlertConfig,
+    client: Client,
+}
+
+impl Alerter {
+    pub fn new(config: AlertConfig, client: Client) -> Self {
+        Self { config, client }
+    }
+
+    pub fn threshold(&self) -> u32 {
+        self.config.failure_threshold
+    }
+
+    /// Deliver `alert` to every configured webhook. Delivery failures are
+    /// logged; they never stop the anchoring loop.
+    pub async fn fire(&self, alert: &Alert) {
+        error!(?alert, "ALERT");
+        for url in &self.config.webhooks {
+            let sent = self
+                .client
+                .post(url)
+                .json(alert)
+                .send()
+                .await
+                .and_then(|resp| resp.error_for_status());
+            if let Err(err) = sent {
+                warn!(?err, %url, "failed to deliver alert");
+            }
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Context;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use reality_core::StoredAnchor;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// When to move confirmed anchors out of SQLite into archive segments.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArchiveConfig {
+    /// Archive confirmed anchors older than this many days; disabled when unset.
+    pub after_days: Option<u64>,
+    /// Segment directory; defaults to `<data_dir>/archive`.
+    pub dir: Option<PathBuf>,
+    pub max_per_segment: usize,
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self {
+            after_days: None,
+            dir: None,
+            max_per_segment: 10_000,
+        }
+    }
+}
+
+impl ArchiveConfig {
+    pub fn threshold(&self) -> Option<Duration> {
+        self.after_days
+            .map(|days| Duration::from_secs(days.saturating_mul(86_400)))
+    }
+}
+
+/// One gzip-compressed archive file. `prev_sha256` is the SHA-256 of the
+/// previous segment's file, so segments form a hash chain whose head is kept
+/// in the store.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ArchiveSegment {
+    pub seq: u64,
+    pub prev_sha256: Option<String>,
+    pub anchors: Vec<StoredAnchor>,
+}
+
+pub fn segment_file(seq: u64) -> String {
+    format!("anchors-{seq:06}.json.gz")
+}
+
+/// Write `segment` into `dir` and return its file name and SHA-256. The file
+/// is renamed into place so a crash never leaves a truncated segment.
+pub fn write_segment(dir: &Path, segment: &ArchiveSegment) -> anyhow::Result<(String, String)> {
+    std::fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&serde_json::to_vec(segment)?)?;
+    let bytes = encoder.finish()?;
+
+    let name = segment_file(segment.seq);
+    let tmp = dir.join(format!("{name}.tmp"));
+    std::fs::write(&tmp, &bytes).with_context(|| format!("write {}", tmp.display()))?;
+    std::fs::rename(&tmp, dir.join(&name))?;
+    Ok((name, sha256_hex(&bytes)))
+}
+
+/// Read a segment and check it hashes to `expected_sha256`.
+pub fn read_segment(path: &Path, expected_sha256: &str) -> anyhow::Result<ArchiveSegment> {
+    let bytes = std::fs::read(path).with_context(|| format!("read {}", path.display()))?;
+    anyhow::ensure!(
+        sha256_hex(&bytes).eq_ignore_ascii_case(expected_sha256),
+        "archive segment {} does not match its recorded hash",
+        path.display()
+    );
+    let mut json = Vec::new();
+    GzDecoder::new(bytes.as_slice()).read_to_end(&mut json)?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reality_core::{AnchorRecord, AnchorStatus};
+
+    #[test]
+    fn segment_round_trip_detects_tampering() {
+        let dir = std::env::temp_dir().join(format!("reality-archive-{}", std::process::id()));
+        let segment = ArchiveSegment {
+            seq: 1,
+            prev_sha256: None,
+            anchors: vec![StoredAnchor {
+                record: AnchorRecord::new("aa".into(), 1, "5".into(), "t".into()),
+                status: AnchorStatus::Confirmed,
+                signature: None,
+            }],
+        };
+        let (name, sha) = write_segment(&dir, &segment).expect("write");
+        assert_eq!(read_segment(&dir.join(&name), &sha).unwrap(), segment);
+        assert!(read_segment(&dir.join(&name), &"00".repeat(32)).is_err());
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,232 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use reality_core::AnchorRecord;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// Header of a detached OpenTimestamps proof, followed by a version byte.
+const OTS_MAGIC: &[u8] = b"\x00OpenTimestamps\x00\x00Proof\x00\xbf\x89\xe2\xe8\x84\xe8\x92\x94";
+const OTS_VERSION: u8 = 0x01;
+const OTS_OP_SHA256: u8 = 0x08;
+
+/// One historical anchor from an external system, as listed in an import file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImportEntry {
+    pub kind: ImportKind,
+    pub root: String,
+    pub size: u64,
+    pub timestamp_nanos: String,
+    /// Bitcoin transaction id; required for `bitcoin`, ignored otherwise.
+    #[serde(default)]
+    pub txid: Option<String>,
+    /// Proof bytes as hex (an `.ots` file or a DER TimeStampToken).
+    #[serde(default)]
+    pub proof_hex: Option<String>,
+    /// Proof file, relative to the import file.
+    #[serde(default)]
+    pub proof_file: Option<PathBuf>,
+    /// Recorded as-is in the anchor's metadata; not verified.
+    #[serde(default)]
+    pub network: Option<String>,
+    #[serde(default)]
+    pub block_height: Option<u64>,
+    #[serde(default)]
+    pub receipt_uri: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportKind {
+    Ots,
+    Bitcoin,
+    Tsa,
+}
+
+impl ImportKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportKind::Ots => "ots",
+            ImportKind::Bitcoin => "bitcoin",
+            ImportKind::Tsa => "tsa",
+        }
+    }
+}
+
+/// An import entry whose proof has been checked against its claimed root.
+/// Only that is checked: an OTS proof's path to a Bitcoin block and a TSA
+/// token's signature are not, so imports are stored as
+/// [`reality_core::AnchorStatus::Pending`] and never as confirmed.
+#[derive(Debug, Clone)]
+pub struct VerifiedImport {
+    pub record: AnchorRecord,
+    pub kind: ImportKind,
+    pub proof: Option<Vec<u8>>,
+}
+
+pub fn read_entries(path: &Path) -> anyhow::Result<Vec<ImportEntry>> {
+    let raw = std::fs::read_to_string(path)
+        .with_context(|| format!("read import file {}", path.display()))?;
+    serde_json::from_str(&raw).with_context(|| format!("parse import file {}", path.display()))
+}
+
+/// Check that the entry's proof commits to its root. Whether the root is part
+/// of the log's history is checked separately against logd.
+pub fn verify_entry(entry: &ImportEntry, base_dir: &Path) -> anyhow::Result<VerifiedImport> {
+    let root = decode_root(&entry.root)?;
+    entry
+        .timestamp_nanos
+        .parse::<i64>()
+        .context("timestamp_nanos is not an integer")?;
+
+    let proof = match (&entry.proof_hex, &entry.proof_file) {
+        (Some(hex_str), _) => Some(hex::decode(hex_str.trim()).context("proof_hex is not hex")?),
+        (None, Some(file)) => {
+            let path = base_dir.join(file);
+            Some(std::fs::read(&path).with_context(|| format!("read proof {}", path.display()))?)
+        }
+        (None, None) => None,
+    };
+
+    let txid = match entry.kind {
+        ImportKind::Ots => {
+            let proof = proof.as_deref().context("ots import requires a proof")?;
+            verify_ots(proof, &root)?;
+            proof_id(proof)
+        }
+        ImportKind::Tsa => {
+            let proof = proof.as_deref().context("tsa import requires a proof")?;
+            verify_tsa(proof, &root)?;
+            proof_id(proof)
+        }
+        ImportKind::Bitcoin => {
+            let txid = entry
+                .txid
+                .as_deref()
+                .context("bitcoin import requires a txid")?
+                .to_ascii_lowercase();
+            anyhow::ensure!(
+                txid.len() == 64 && hex::decode(&txid).is_ok(),
+                "bitcoin txid must be 32 bytes of hex"
+            );
+            // The transaction itself cannot be inspected offline.
+            txid
+        }
+    };
+
+    let mut record = AnchorRecord::new(
+        hex::encode(root),
+        entry.size,
+        entry.timestamp_nanos.clone(),
+        txid,
+    );
+    record.metadata.backend = Some(entry.kind.as_str().to_string());
+    record.metadata.network = entry.network.clone();
+    record.metadata.block_height = entry.block_height;
+    record.metadata.receipt_uri = entry.receipt_uri.clone();
+    Ok(VerifiedImport {
+        record,
+        kind: entry.kind,
+        proof,
+    })
+}
+
+/// An OTS proof must stamp the root directly with SHA-256. Its operations
+/// and attestations are not followed.
+fn verify_ots(proof: &[u8], root: &[u8; 32]) -> anyhow::Result<()> {
+    let rest = proof
+        .strip_prefix(OTS_MAGIC)
+        .context("not an OpenTimestamps proof")?;
+    let (&version, rest) = rest.split_first().context("truncated ots proof")?;
+    anyhow::ensure!(version == OTS_VERSION, "unsupported ots version {version}");
+    let (&op, rest) = rest.split_first().context("truncated ots proof")?;
+    anyhow::ensure!(op == OTS_OP_SHA256, "ots proof does not use sha256");
+    anyhow::ensure!(
+        rest.len() >= 32 && rest[..32] == root[..],
+        "ots proof stamps a different digest"
+    );
+    Ok(())
+}
+
+/// A TimeStampToken commits to the root through its messageImprint, which
+/// carries the digest as a 32-byte DER OCTET STRING. The TSA's CMS signature
+/// is not validated here.
+fn verify_tsa(token: &[u8], root: &[u8; 32]) -> anyhow::Result<()> {
+    let mut needle = vec![0x04, 0x20];
+    needle.extend_from_slice(root);
+    anyhow::ensure!(
+        token.windows(needle.len()).any(|w| w == needle),
+        "timestamp token does not imprint the root"
+    );
+    Ok(())
+}
+
+fn proof_id(proof: &[u8]) -> String {
+    hex::encode(Sha256::digest(proof))
+}
+
+fn decode_root(value: &str) -> anyhow::Result<[u8; 32]> {
+    let bytes = hex::decode(value).context("root is not hex")?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("root must be 32 bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(kind: ImportKind, root: [u8; 32], proof: Option<Vec<u8>>) -> ImportEntry {
+        ImportEntry {
+            kind,
+            root: hex::encode(root),
+            size: 3,
+            timestamp_nanos: "1700000000000000000".into(),
+            txid: None,
+            proof_hex: proof.map(hex::encode),
+            proof_file: None,
+            network: None,
+            block_height: None,
+            receipt_uri: None,
+        }
+    }
+
+    fn ots(digest: [u8; 32], attestation: &[u8]) -> Vec<u8> {
+        let mut proof = OTS_MAGIC.to_vec();
+        proof.extend([OTS_VERSION, OTS_OP_SHA256]);
+        proof.extend(digest);
+        proof.push(0x00);
+        proof.extend(attestation);
+        proof
+    }
+
+    #[test]
+    fn ots_proof_must_stamp_root() {
+        // A Bitcoin attestation tag anywhere in the proof proves nothing.
+        let attestation = [0x05, 0x88, 0x96, 0x0d, 0x73, 0xd7, 0x19, 0x01];
+        let root = [7u8; 32];
+        let imported = verify_entry(
+            &entry(ImportKind::Ots, root, Some(ots(root, &attestation))),
+            Path::new("."),
+        )
+        .expect("valid");
+        assert_eq!(imported.record.root, hex::encode(root));
+
+        let other = entry(ImportKind::Ots, root, Some(ots([8u8; 32], &[])));
+        assert!(verify_entry(&other, Path::new(".")).is_err());
+    }
+
+    #[test]
+    fn tsa_token_must_imprint_root() {
+        let root = [9u8; 32];
+        let mut token = vec![0x30, 0x82, 0x01, 0x00, 0x04, 0x20];
+        token.extend(root);
+        assert!(verify_entry(&entry(ImportKind::Tsa, root, Some(token)), Path::new(".")).is_ok());
+        assert!(verify_entry(
+            &entry(ImportKind::Tsa, root, Some(vec![0x04, 0x20])),
+            Path::new(".")
+        )
+        .is_err());
+    }
+}
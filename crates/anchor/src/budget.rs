@@ -0,0 +1,118 @@
+use reality_core::{digest, receipt::batch_leaf, InclusionProof, RootResponse};
+use serde::Deserialize;
+use sha2::Sha256;
+
+/// Spending limits for backends that charge per anchor. Fees are in the
+/// backend's smallest unit (satoshi, gwei, ...).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BudgetConfig {
+    /// Refuse to submit when a single anchor would cost more than this.
+    pub max_fee_per_anchor: Option<u64>,
+    /// Stop submitting for the rest of the UTC day once this would be exceeded.
+    pub max_daily_spend: Option<u64>,
+    /// Fraction of `max_daily_spend` after which checkpoints are held back and
+    /// anchored together once `batch_size` have queued up.
+    pub batch_pressure: f64,
+    pub batch_size: usize,
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            max_fee_per_anchor: None,
+            max_daily_spend: None,
+            batch_pressure: 0.5,
+            batch_size: 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Plan {
+    /// Submit every queued checkpoint now, batched if more than one.
+    Submit,
+    /// Keep the checkpoints queued and try again next round.
+    Wait(String),
+    /// The anchor can never be submitted under this configuration.
+    Refuse(String),
+}
+
+impl BudgetConfig {
+    pub fn plan(&self, fee: u64, spent_today: u64, queued: usize) -> Plan {
+        if let Some(max_fee) = self.max_fee_per_anchor {
+            if fee > max_fee {
+                return Plan::Refuse(format!("fee {fee} exceeds max_fee_per_anchor {max_fee}"));
+            }
+        }
+        let Some(daily) = self.max_daily_spend else {
+            return Plan::Submit;
+        };
+        if spent_today.saturating_add(fee) > daily {
+            return Plan::Wait(format!(
+                "daily budget exhausted ({spent_today} of {daily} spent)"
+            ));
+        }
+        let pressure = spent_today as f64 / daily.max(1) as f64;
+        if pressure >= self.batch_pressure && queued < self.batch_size {
+            return Plan::Wait(format!(
+                "batching under budget pressure ({queued}/{} queued)",
+                self.batch_size
+            ));
+        }
+        Plan::Submit
+    }
+}
+
+/// Merkle root over a batch of checkpoints plus each checkpoint's inclusion
+/// proof, so every member can later show it is covered by the batch anchor.
+/// Leaves are `leaf_hash("{size}:{root}")`, hashed with SHA-256 like the
+/// receipts that check them.
+pub fn batch(checkpoints: &[RootResponse]) -> (String, Vec<InclusionProof>) {
+    let leaves: Vec<[u8; 32]> = checkpoints.iter().map(checkpoint_leaf).collect();
+    let proofs = (0..leaves.len())
+        .map(|i| digest::make_proof::<Sha256>(&leaves, i).expect("index within batch"))
+        .collect();
+    (hex::encode(digest::root::<Sha256>(&leaves)), proofs)
+}
+
+pub fn checkpoint_leaf(checkpoint: &RootResponse) -> [u8; 32] {
+    batch_leaf(checkpoint.size, &checkpoint.root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plan_batches_under_pressure_and_waits_when_exhausted() {
+        let budget = BudgetConfig {
+            max_fee_per_anchor: Some(50),
+            max_daily_spend: Some(100),
+            batch_pressure: 0.5,
+            batch_size: 3,
+        };
+        assert_eq!(budget.plan(10, 0, 1), Plan::Submit);
+        assert!(matches!(budget.plan(60, 0, 1), Plan::Refuse(_)));
+        assert!(matches!(budget.plan(10, 50, 2), Plan::Wait(_)));
+        assert_eq!(budget.plan(10, 50, 3), Plan::Submit);
+        assert!(matches!(budget.plan(10, 95, 3), Plan::Wait(_)));
+    }
+
+    #[test]
+    fn batch_proofs_cover_every_checkpoint() {
+        let checkpoints: Vec<RootResponse> = (1..=3)
+            .map(|size| RootResponse {
+                root: format!("{size:064x}"),
+                size,
+            })
+            .collect();
+        let (root, proofs) = batch(&checkpoints);
+        for (cp, proof) in checkpoints.iter().zip(&proofs) {
+            assert_eq!(proof.root, root);
+            assert_eq!(proof.leaf, hex::encode(checkpoint_leaf(cp)));
+            let verified = reality_core::verify(&reality_core::VerifyRequest::from(proof));
+            assert!(verified.valid);
+        }
+    }
+}
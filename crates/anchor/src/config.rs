@@ -0,0 +1,403 @@
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
+
+use anyhow::Context;
+use clap::{Parser, Subcommand, ValueEnum};
+use reality_config::Layered;
+use serde::Deserialize;
+
+use crate::{archive::ArchiveConfig, budget::BudgetConfig};
+
+/// Command-line flags. Every flag can also be set through the environment;
+/// anything left unset falls back to the config file and then to defaults.
+#[derive(Debug, Parser)]
+#[command(name = "reality-anchor", version, about = "Anchors RealityLog roots")]
+pub struct Cli {
+    /// Path to a TOML config file.
+    #[arg(long, env = "REALITY_ANCHOR_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Anchor roots on the configured backends, or act as a witness.
+    #[arg(long, value_enum, env = "REALITY_ANCHOR_MODE")]
+    pub mode: Option<Mode>,
+    /// Hex ed25519 key the log must have signed checkpoints with (witness mode).
+    #[arg(long)]
+    pub log_public_key: Option<String>,
+    /// Base URL of the logd API.
+    #[arg(long, env = "REALITY_LOG_API")]
+    pub api_url: Option<String>,
+    /// Directory holding the anchor store.
+    #[arg(long, env = "REALITY_LOG_DIR")]
+    pub data_dir: Option<PathBuf>,
+    /// Seconds between anchoring rounds.
+    #[arg(long, env = "REALITY_ANCHOR_INTERVAL_SECS")]
+    pub interval_secs: Option<u64>,
+    /// Address for the anchor query API.
+    #[arg(long, env = "REALITY_ANCHOR_LISTEN")]
+    pub listen: Option<SocketAddr>,
+    /// Bearer token sent to the logd API.
+    #[arg(long, env = "REALITY_LOG_API_TOKEN", hide_env_values = true)]
+    pub api_token: Option<String>,
+    /// Bearer token for the anchor API's admin routes, which are off without one.
+    #[arg(long, env = "REALITY_ANCHOR_ADMIN_TOKEN", hide_env_values = true)]
+    pub admin_token: Option<String>,
+    /// Signing key spec (`file:<path>` or a `pkcs11:` URI) used to sign anchors.
+    #[arg(long, env = "REALITY_ANCHOR_SIGNER", hide_env_values = true)]
+    pub signer: Option<String>,
+    /// Maximum attempts per fetch or publication before giving up on a round.
+    #[arg(long)]
+    pub max_attempts: Option<u32>,
+    /// Webhook URLs that receive every new anchor record (repeatable).
+    #[arg(long = "publish-webhook")]
+    pub publish_webhooks: Vec<String>,
+    /// Consecutive failed rounds before alerts fire.
+    #[arg(long)]
+    pub alert_threshold: Option<u32>,
+    /// Webhook URLs that receive alerts (repeatable).
+    #[arg(long = "alert-webhook")]
+    pub alert_webhooks: Vec<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Merge historical anchors (OTS proofs, Bitcoin txids, TSA tokens) from
+    /// a JSON file after checking each root against the log's history.
+    Import {
+        /// JSON array of import entries.
+        file: PathBuf,
+    },
+}
+
+/// Fully resolved daemon configuration.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub mode: Mode,
+    pub api_url: String,
+    pub data_dir: PathBuf,
+    pub interval_secs: u64,
+    pub listen: SocketAddr,
+    pub retry: RetryPolicy,
+    pub credentials: Credentials,
+    pub backends: Vec<BackendConfig>,
+    pub publish: Vec<PublishTarget>,
+    pub alerts: AlertConfig,
+    pub budget: BudgetConfig,
+    pub archive: ArchiveConfig,
+    pub witness: WitnessConfig,
+    /// Signing key spec; anchors are left unsigned when unset. Required in
+    /// witness mode, where it signs cosignatures.
+    pub signer: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// Write new roots to the configured backends.
+    #[default]
+    Anchor,
+    /// Check each signed checkpoint is consistent with the last one seen and
+    /// post a cosignature to logd's `/witness` instead of anchoring.
+    Witness,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WitnessConfig {
+    /// Only cosign checkpoints signed by this log key. Without it the key is
+    /// pinned on first use.
+    pub log_public_key: Option<String>,
+}
+
+/// Exponential backoff applied to root fetches and publications.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Credentials {
+    /// Bearer token for the logd API.
+    pub api_token: Option<String>,
+    /// File containing the bearer token; takes precedence over `api_token`.
+    pub api_token_file: Option<PathBuf>,
+    /// Bearer token for `POST /archive/restore`; the route answers 404
+    /// without one.
+    pub admin_token: Option<String>,
+}
+
+/// When and where to raise alerts about a log that is going unanchored.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertConfig {
+    /// Consecutive failed rounds before an alert fires.
+    pub failure_threshold: u32,
+    /// URLs that receive alert events as JSON POSTs, e.g. a pager's
+    /// generic webhook integration.
+    pub webhooks: Vec<String>,
+}
+
+/// Anchoring backends. Only the simulated backend exists today; it derives
+/// `txid = sha256("{tree_size}:{root}:{timestamp_nanos}")`.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum BackendConfig {
+    Simulated {
+        /// Pretend cost per anchor, for exercising `[budget]`.
+        #[serde(default)]
+        fee: u64,
+    },
+}
+
+impl BackendConfig {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BackendConfig::Simulated { .. } => "simulated",
+        }
+    }
+
+    /// Expected cost of one anchor transaction.
+    pub fn fee(&self) -> u64 {
+        match self {
+            BackendConfig::Simulated { fee } => *fee,
+        }
+    }
+}
+
+/// Where anchor records are published in addition to the local store.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum PublishTarget {
+    /// POST each record as JSON to `url`.
+    Webhook { url: String },
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            mode: Mode::default(),
+            api_url: "http://127.0.0.1:8080".to_string(),
+            data_dir: PathBuf::from("data"),
+            interval_secs: 60,
+            listen: SocketAddr::from(([127, 0, 0, 1], 8081)),
+            retry: RetryPolicy::default(),
+            credentials: Credentials::default(),
+            backends: vec![BackendConfig::Simulated { fee: 0 }],
+            publish: Vec::new(),
+            alerts: AlertConfig::default(),
+            budget: BudgetConfig::default(),
+            archive: ArchiveConfig::default(),
+            witness: WitnessConfig::default(),
+            signer: None,
+        }
+    }
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            webhooks: Vec::new(),
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 10_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `attempt` (1-based), doubling each time.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u64 << attempt.saturating_sub(1).min(16);
+        let ms = self
+            .initial_backoff_ms
+            .saturating_mul(factor)
+            .min(self.max_backoff_ms);
+        Duration::from_millis(ms)
+    }
+}
+
+impl Layered<Cli> for Config {
+    fn file(cli: &Cli) -> Option<PathBuf> {
+        cli.config.clone()
+    }
+
+    fn apply(&mut self, cli: Cli) -> anyhow::Result<()> {
+        if let Some(mode) = cli.mode {
+            self.mode = mode;
+        }
+        if let Some(key) = cli.log_public_key {
+            self.witness.log_public_key = Some(key);
+        }
+        if let Some(api_url) = cli.api_url {
+            self.api_url = api_url;
+        }
+        if let Some(data_dir) = cli.data_dir {
+            self.data_dir = data_dir;
+        }
+        if let Some(interval) = cli.interval_secs {
+            self.interval_secs = interval;
+        }
+        if let Some(listen) = cli.listen {
+            self.listen = listen;
+        }
+        if let Some(signer) = cli.signer {
+            self.signer = Some(signer);
+        }
+        if let Some(token) = cli.api_token {
+            self.credentials.api_token = Some(token);
+        }
+        if let Some(token) = cli.admin_token {
+            self.credentials.admin_token = Some(token);
+        }
+        if let Some(max_attempts) = cli.max_attempts {
+            self.retry.max_attempts = max_attempts;
+        }
+        for url in cli.publish_webhooks {
+            self.publish.push(PublishTarget::Webhook { url });
+        }
+        if let Some(threshold) = cli.alert_threshold {
+            self.alerts.failure_threshold = threshold;
+        }
+        self.alerts.webhooks.extend(cli.alert_webhooks);
+        Ok(())
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(self.interval_secs > 0, "interval_secs must be positive");
+        anyhow::ensure!(
+            self.retry.max_attempts > 0,
+            "retry.max_attempts must be positive"
+        );
+        anyhow::ensure!(
+            self.alerts.failure_threshold > 0,
+            "alerts.failure_threshold must be positive"
+        );
+        anyhow::ensure!(
+            self.budget.batch_size > 0,
+            "budget.batch_size must be positive"
+        );
+        anyhow::ensure!(
+            !self.backends.is_empty(),
+            "at least one backend is required"
+        );
+        anyhow::ensure!(
+            self.archive.max_per_segment > 0,
+            "archive.max_per_segment must be positive"
+        );
+        anyhow::ensure!(
+            self.mode != Mode::Witness || self.signer.is_some(),
+            "witness mode needs a signer for its cosignatures"
+        );
+        Ok(())
+    }
+}
+
+impl Config {
+    pub fn archive_dir(&self) -> PathBuf {
+        self.archive
+            .dir
+            .clone()
+            .unwrap_or_else(|| self.data_dir.join("archive"))
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+
+    /// Upper bound on one loop round: the sleep plus every retry backoff.
+    pub fn round_budget(&self) -> Duration {
+        let backoff: Duration = (1..self.retry.max_attempts)
+            .map(|attempt| self.retry.backoff(attempt))
+            .sum();
+        self.interval() + backoff
+    }
+
+    /// Resolve the API token, reading `api_token_file` if configured.
+    pub fn api_token(&self) -> anyhow::Result<Option<String>> {
+        if let Some(path) = &self.credentials.api_token_file {
+            let token = std::fs::read_to_string(path)
+                .with_context(|| format!("read token file {}", path.display()))?;
+            return Ok(Some(token.trim().to_string()));
+        }
+        Ok(self.credentials.api_token.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_full_config_file() {
+        let config: Config = toml::from_str(
+            r#"
+            api_url = "http://log:8080"
+            interval_secs = 5
+            listen = "0.0.0.0:9000"
+
+            [retry]
+            max_attempts = 7
+
+            [[backends]]
+            kind = "simulated"
+
+            [[publish]]
+            kind = "webhook"
+            url = "http://hooks/anchor"
+            "#,
+        )
+        .expect("config");
+
+        assert_eq!(config.api_url, "http://log:8080");
+        assert_eq!(config.interval_secs, 5);
+        assert_eq!(config.listen.port(), 9000);
+        assert_eq!(config.retry.max_attempts, 7);
+        assert_eq!(config.retry.initial_backoff_ms, 500);
+        assert_eq!(config.backends, vec![BackendConfig::Simulated { fee: 0 }]);
+        assert_eq!(
+            config.publish,
+            vec![PublishTarget::Webhook {
+                url: "http://hooks/anchor".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn flags_override_file_values() {
+        let cli = Cli::parse_from([
+            "reality-anchor",
+            "--interval-secs",
+            "10",
+            "--publish-webhook",
+            "http://a",
+        ]);
+        let mut config = Config::default();
+        config.apply(cli).unwrap();
+        assert_eq!(config.interval_secs, 10);
+        assert_eq!(config.publish.len(), 1);
+    }
+
+    #[test]
+    fn backoff_doubles_and_caps() {
+        let retry = RetryPolicy {
+            max_attempts: 5,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 350,
+        };
+        assert_eq!(retry.backoff(1), Duration::from_millis(100));
+        assert_eq!(retry.backoff(2), Duration::from_millis(200));
+        assert_eq!(retry.backoff(3), Duration::from_millis(350));
+    }
+}
@@ -1,82 +1,577 @@
-use std::{env, path::PathBuf, time::Duration};
+mod alert;
+mod archive;
+mod backfill;
+mod budget;
+mod config;
+mod server;
+mod status;
+mod store;
+mod witness;
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex, MutexGuard},
+};
 
 use anyhow::Context;
-use reality_core::{AnchorRecord, RootResponse};
+use clap::Parser;
+use reality_config::Layered;
+use reality_core::{
+    checkpoint::Checkpoint,
+    receipt::simulated_txid,
+    signer::{open_signer, DetachedSignature, Signer},
+    AnchorRecord, AnchorStatus, RootResponse,
+};
 use reqwest::Client;
-use sha2::{Digest, Sha256};
 use time::OffsetDateTime;
 use tokio::time::sleep;
-use tracing::{info, warn};
+use tracing::{error, info, warn};
+
+use crate::{
+    alert::{Alert, Alerter},
+    budget::Plan,
+    config::{BackendConfig, Cli, Command, Config, Mode, PublishTarget},
+    server::{ApiState, SharedStore},
+    status::{AlertTransition, DaemonStatus, SharedStatus},
+    store::{AnchorStore, Batch, RootConflict, Submission},
+};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
 
-    let api = env::var("REALITY_LOG_API").unwrap_or_else(|_| "http://127.0.0.1:8080".to_string());
-    let data_dir =
-        PathBuf::from(env::var("REALITY_LOG_DIR").unwrap_or_else(|_| "data".to_string()));
-    tokio::fs::create_dir_all(&data_dir)
+    let mut cli = Cli::parse();
+    let command = cli.command.take();
+    let config = Config::load(cli)?;
+    let api_token = config.api_token()?;
+    tokio::fs::create_dir_all(&config.data_dir)
         .await
         .context("create data dir")?;
-    let anchors_path = data_dir.join("anchors.json");
 
-    let mut anchors: Vec<AnchorRecord> = read_json(&anchors_path).await?.unwrap_or_default();
-    if anchors.is_empty() {
-        ensure_file(&anchors_path).await?;
+    let mut store = AnchorStore::open(&config.data_dir.join("anchors.sqlite3"))?;
+    store.set_archive_dir(config.archive_dir());
+    let legacy: Vec<AnchorRecord> = read_json(&config.data_dir.join("anchors.json"))
+        .await?
+        .unwrap_or_default();
+    let imported = store.import_legacy(&legacy)?;
+    if imported > 0 {
+        info!(imported, "imported legacy anchors.json");
+    }
+
+    if let Some(Command::Import { file }) = command {
+        let client = Client::builder().build()?;
+        return import(&client, &config, api_token.as_deref(), &mut store, &file).await;
     }
-    let mut last_anchor = anchors.last().cloned();
+
+    let last_seen = store.latest()?.map(|stored| RootResponse {
+        root: stored.record.root,
+        size: stored.record.size,
+    });
+    let store = Arc::new(Mutex::new(store));
+    let status = match config.mode {
+        Mode::Anchor => DaemonStatus::new(config.backends.iter().map(|b| b.kind().to_string())),
+        Mode::Witness => DaemonStatus::new(["witness".to_string()]),
+    }
+    .shared();
+
+    let api_state = ApiState {
+        store: store.clone(),
+        status: status.clone(),
+        stale_after: config.round_budget() * 3,
+        admin_token: config.credentials.admin_token.as_deref().map(Arc::from),
+    };
+    let listen = config.listen;
+    tokio::spawn(async move {
+        if let Err(err) = server::serve(listen, api_state).await {
+            error!(?err, "anchor API stopped");
+        }
+    });
+
+    let signer = match &config.signer {
+        Some(spec) => {
+            let signer = open_signer(spec).context("open anchor signer")?;
+            info!(public_key = %hex::encode(signer.public_key()), "signing key loaded");
+            Some(Arc::from(signer))
+        }
+        None => None,
+    };
 
     let client = Client::builder().build()?;
+    let mut anchorer = Anchorer {
+        signer,
+        alerter: Alerter::new(config.alerts.clone(), client.clone()),
+        client,
+        api_token,
+        store,
+        status: status.clone(),
+        last_seen,
+        last_inconsistent: None,
+        config,
+    };
+    info!(
+        api = %anchorer.config.api_url,
+        interval_secs = anchorer.config.interval_secs,
+        mode = ?anchorer.config.mode,
+        "anchor daemon started"
+    );
 
     loop {
-        match fetch_root(&client, &api).await {
-            Ok(root) => {
-                let is_new = last_anchor
-                    .as_ref()
-                    .map(|a| a.root != root.root || a.size != root.size)
-                    .unwrap_or(true);
-
-                if is_new {
-                    let timestamp = OffsetDateTime::now_utc().unix_timestamp_nanos().to_string();
-                    let txid = compute_txid(root.size, &root.root, &timestamp);
-                    let record = AnchorRecord {
-                        root: root.root.clone(),
-                        size: root.size,
-                        timestamp_nanos: timestamp,
-                        txid,
-                    };
-                    anchors.push(record.clone());
-                    write_json(&anchors_path, &anchors).await?;
-                    last_anchor = Some(record.clone());
-                    info!(
-                        root = %record.root,
-                        size = record.size,
-                        txid = %record.txid,
-                        "anchored new root"
-                    );
+        let outcome = match anchorer.config.mode {
+            Mode::Anchor => anchorer.round().await,
+            Mode::Witness => anchorer.witness_round().await,
+        };
+        anchorer.finish_round(outcome).await;
+        anchorer.archive_old();
+        sleep(anchorer.config.interval()).await;
+    }
+}
+
+struct Anchorer {
+    config: Config,
+    client: Client,
+    api_token: Option<String>,
+    store: SharedStore,
+    status: SharedStatus,
+    alerter: Alerter,
+    signer: Option<Arc<dyn Signer>>,
+    last_seen: Option<RootResponse>,
+    /// Last checkpoint refused in witness mode, so each is alerted once.
+    last_inconsistent: Option<Checkpoint>,
+}
+
+impl Anchorer {
+    /// Fetch the current root and anchor it on every backend if it is new.
+    async fn round(&mut self) -> anyhow::Result<()> {
+        let root = self
+            .with_retry("fetch root", || {
+                fetch_root(
+                    &self.client,
+                    &self.config.api_url,
+                    self.api_token.as_deref(),
+                )
+            })
+            .await
+            .context("fetch root")?;
+
+        let is_new = self
+            .last_seen
+            .as_ref()
+            .map(|a| a.root != root.root || a.size != root.size)
+            .unwrap_or(true);
+        let mut conflict = self.store().check_root(&root)?;
+        if conflict.is_none() && is_new {
+            conflict = self.history_conflict(&root).await?;
+        }
+        if let Some(conflict) = conflict {
+            error!(
+                kind = ?conflict.kind,
+                observed_root = %conflict.observed_root,
+                observed_size = conflict.observed_size,
+                anchored_root = %conflict.anchored.root,
+                anchored_size = conflict.anchored.size,
+                anchored_txid = %conflict.anchored.txid,
+                "ALERT: log root contradicts an anchored checkpoint; refusing to anchor"
+            );
+            let now = OffsetDateTime::now_utc().unix_timestamp_nanos() as i64;
+            let first_sighting = self.store().record_conflict(&conflict, now)?;
+            if first_sighting {
+                self.alerter
+                    .fire(&Alert::RootConflict {
+                        conflict: conflict.clone(),
+                    })
+                    .await;
+            }
+            anyhow::bail!(
+                "refused to anchor: {:?} against anchor {}",
+                conflict.kind,
+                conflict.anchored.txid
+            );
+        }
+
+        if is_new {
+            let keep = self.config.budget.batch_size;
+            for backend in &self.config.backends {
+                self.store().queue(backend.kind(), &root, keep)?;
+            }
+            self.last_seen = Some(root);
+        }
+
+        let day = OffsetDateTime::now_utc().date().to_string();
+        for backend in self.config.backends.clone() {
+            let checkpoints = self.store().pending(backend.kind())?;
+            if checkpoints.is_empty() {
+                continue;
+            }
+            let spent = self.store().spent_on(&day, backend.kind())?;
+            match self
+                .config
+                .budget
+                .plan(backend.fee(), spent, checkpoints.len())
+            {
+                Plan::Submit => {}
+                Plan::Wait(reason) => {
+                    info!(backend = backend.kind(), %reason, "holding checkpoints");
+                    continue;
                 }
+                Plan::Refuse(reason) => anyhow::bail!("{}: {reason}", backend.kind()),
             }
+
+            let submission = match self.submit(&backend, &checkpoints) {
+                Ok(submission) => submission,
+                Err(err) => {
+                    self.status
+                        .lock()
+                        .expect("status poisoned")
+                        .backend_result(backend.kind(), Some(format!("{err:#}")));
+                    return Err(err);
+                }
+            };
+            self.store()
+                .record_submission(backend.kind(), &day, backend.fee(), &submission)?;
+            let anchored = self.finish_records(&backend, submission);
+            for record in &anchored {
+                if let Some(signature) = self.sign(record.signed_message()).await? {
+                    self.store().insert_signature(&record.txid, &signature)?;
+                }
+            }
+            for record in anchored {
+                self.publish(&record).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Compare the log's root at every anchored size up to `root`, archived
+    /// anchors included, so a rewritten prefix is caught after the log grows
+    /// past it.
+    async fn history_conflict(&self, root: &RootResponse) -> anyhow::Result<Option<RootConflict>> {
+        let anchored = self.store().anchored_roots(root.size)?;
+        let mut logged: Option<RootResponse> = None;
+        for (size, anchored_root) in anchored {
+            if logged.as_ref().map(|l| l.size) != Some(size) {
+                let path = format!("root/{size}");
+                logged = Some(
+                    self.with_retry("fetch historical root", || {
+                        fetch_root_path(
+                            &self.client,
+                            &self.config.api_url,
+                            &path,
+                            self.api_token.as_deref(),
+                        )
+                    })
+                    .await
+                    .with_context(|| format!("fetch root at size {size}"))?,
+                );
+            }
+            let logged = logged.as_ref().expect("root fetched");
+            if !logged.root.eq_ignore_ascii_case(&anchored_root) {
+                return self.store().fork_at(size, &logged.root);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Anchor `checkpoints` in one transaction. A batch anchors the Merkle
+    /// root of its checkpoints; each member is recorded as `{batch_txid}/{i}`
+    /// with its proof of membership. Nothing is stored until the caller
+    /// records the submission.
+    fn submit(
+        &self,
+        backend: &BackendConfig,
+        checkpoints: &[RootResponse],
+    ) -> anyhow::Result<Submission> {
+        if let [single] = checkpoints {
+            let mut record = anchor_with(backend, single);
+            record.metadata.signing_identity = self.signing_identity();
+            return Ok(Submission {
+                records: vec![record],
+                batch: None,
+            });
+        }
+
+        let (batch_root, proofs) = budget::batch(checkpoints);
+        let batch = anchor_with(
+            backend,
+            &RootResponse {
+                root: batch_root.clone(),
+                size: checkpoints.len() as u64,
+            },
+        );
+        let mut records = Vec::with_capacity(checkpoints.len());
+        for (i, checkpoint) in checkpoints.iter().enumerate() {
+            let mut record = AnchorRecord::new(
+                checkpoint.root.clone(),
+                checkpoint.size,
+                batch.timestamp_nanos.clone(),
+                format!("{}/{i}", batch.txid),
+            );
+            record.metadata = batch.metadata.clone();
+            record.metadata.signing_identity = self.signing_identity();
+            records.push(record);
+        }
+        info!(
+            batch_txid = %batch.txid,
+            batch_root = %batch_root,
+            members = records.len(),
+            "anchored checkpoint batch"
+        );
+        Ok(Submission {
+            records,
+            batch: Some(Batch {
+                txid: batch.txid,
+                root: batch_root,
+                proofs,
+            }),
+        })
+    }
+
+    /// Sign `message` with the anchor key, if there is one, on the blocking
+    /// pool: the signer may wait on an HSM or a KMS round trip.
+    async fn sign(&self, message: Vec<u8>) -> anyhow::Result<Option<DetachedSignature>> {
+        let Some(signer) = self.signer.clone() else {
+            return Ok(None);
+        };
+        let signature = tokio::task::spawn_blocking(move || {
+            DetachedSignature::create(signer.as_ref(), &message)
+        })
+        .await?
+        .context("sign anchor")?;
+        Ok(Some(signature))
+    }
+
+    fn signing_identity(&self) -> Option<String> {
+        self.signer
+            .as_ref()
+            .map(|signer| format!("ed25519:{}", hex::encode(signer.public_key())))
+    }
+
+    fn finish_records(&self, backend: &BackendConfig, submission: Submission) -> Vec<AnchorRecord> {
+        let records = submission.records;
+        for record in &records {
+            self.status
+                .lock()
+                .expect("status poisoned")
+                .anchored(backend.kind(), record);
+            info!(
+                root = %record.root,
+                size = record.size,
+                txid = %record.txid,
+                "anchored new root"
+            );
+        }
+        records
+    }
+
+    /// Record the round's outcome and fire or clear the failure alert.
+    async fn finish_round(&self, outcome: anyhow::Result<()>) {
+        let error = outcome.err().map(|err| {
+            warn!(?err, "anchoring round failed");
+            format!("{err:#}")
+        });
+        let transition = self
+            .status
+            .lock()
+            .expect("status poisoned")
+            .round_finished(error.clone(), self.alerter.threshold());
+        match transition {
+            AlertTransition::Raised => {
+                let consecutive_failures = self.alerter.threshold();
+                self.alerter
+                    .fire(&Alert::AnchoringFailing {
+                        consecutive_failures,
+                        last_error: error.unwrap_or_default(),
+                    })
+                    .await;
+            }
+            AlertTransition::Cleared { failed_rounds } => {
+                self.alerter
+                    .fire(&Alert::AnchoringRecovered { failed_rounds })
+                    .await;
+            }
+            AlertTransition::None => {}
+        }
+    }
+
+    /// Move confirmed anchors past `[archive] after_days` into a segment.
+    fn archive_old(&self) {
+        let Some(threshold) = self.config.archive.threshold() else {
+            return;
+        };
+        let cutoff = OffsetDateTime::now_utc() - threshold;
+        let cutoff = cutoff.unix_timestamp_nanos() as i64;
+        match self
+            .store
+            .lock()
+            .expect("anchor store poisoned")
+            .rotate(cutoff, self.config.archive.max_per_segment)
+        {
+            Ok(0) => {}
+            Ok(archived) => info!(archived, "archived old anchors"),
+            Err(err) => warn!(?err, "failed to archive anchors"),
+        }
+    }
+
+    fn store(&self) -> MutexGuard<'_, AnchorStore> {
+        self.store.lock().expect("anchor store poisoned")
+    }
+
+    async fn publish(&self, record: &AnchorRecord) {
+        for target in &self.config.publish {
+            match target {
+                PublishTarget::Webhook { url } => {
+                    let sent = self
+                        .with_retry("publish webhook", || async {
+                            self.client
+                                .post(url)
+                                .json(record)
+                                .send()
+                                .await?
+                                .error_for_status()?;
+                            Ok(())
+                        })
+                        .await;
+                    if let Err(err) = sent {
+                        warn!(?err, %url, "failed to publish anchor");
+                    }
+                }
+            }
+        }
+    }
+
+    async fn with_retry<T, F, Fut>(&self, what: &str, mut op: F) -> anyhow::Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = anyhow::Result<T>>,
+    {
+        let policy = &self.config.retry;
+        let mut attempt = 1;
+        loop {
+            match op().await {
+                Ok(value) => {
+                    self.set_pending_retries(0);
+                    return Ok(value);
+                }
+                Err(err) if attempt < policy.max_attempts => {
+                    let delay = policy.backoff(attempt);
+                    warn!(?err, attempt, ?delay, "{what} failed, retrying");
+                    self.set_pending_retries(policy.max_attempts - attempt);
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(err) => {
+                    self.set_pending_retries(0);
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    fn set_pending_retries(&self, pending: u32) {
+        self.status.lock().expect("status poisoned").pending_retries = pending;
+    }
+}
+
+/// Verify and merge historical anchors from `file`. Entries whose proof does
+/// not commit to their root, or whose root is not the log's root at that size,
+/// are skipped and reported.
+async fn import(
+    client: &Client,
+    config: &Config,
+    token: Option<&str>,
+    store: &mut AnchorStore,
+    file: &std::path::Path,
+) -> anyhow::Result<()> {
+    let entries = backfill::read_entries(file)?;
+    let base_dir = file.parent().unwrap_or_else(|| std::path::Path::new("."));
+    let (mut imported, mut duplicate, mut rejected) = (0, 0, 0);
+
+    for (i, entry) in entries.iter().enumerate() {
+        let verified = match backfill::verify_entry(entry, base_dir) {
+            Ok(v) => v,
             Err(err) => {
-                warn!(?err, "failed to fetch root");
+                warn!(index = i, ?err, "rejected import entry");
+                rejected += 1;
+                continue;
             }
+        };
+        let path = format!("root/{}", verified.record.size);
+        let logged = fetch_root_path(client, &config.api_url, &path, token)
+            .await
+            .with_context(|| format!("fetch historical root for size {}", verified.record.size))?;
+        if !logged.root.eq_ignore_ascii_case(&verified.record.root) {
+            warn!(
+                index = i,
+                size = verified.record.size,
+                claimed = %verified.record.root,
+                logged = %logged.root,
+                "rejected import entry: root not in log history"
+            );
+            rejected += 1;
+            continue;
+        }
+
+        if store.insert_external(
+            &verified.record,
+            AnchorStatus::Pending,
+            verified.kind.as_str(),
+            verified.proof.as_deref(),
+        )? {
+            imported += 1;
+        } else {
+            duplicate += 1;
         }
+    }
+
+    info!(imported, duplicate, rejected, "import finished");
+    anyhow::ensure!(rejected == 0, "{rejected} import entries were rejected");
+    Ok(())
+}
 
-        sleep(Duration::from_secs(60)).await;
+fn anchor_with(backend: &BackendConfig, root: &RootResponse) -> AnchorRecord {
+    match backend {
+        BackendConfig::Simulated { .. } => {
+            let timestamp = OffsetDateTime::now_utc().unix_timestamp_nanos().to_string();
+            let txid = simulated_txid(root.size, &root.root, &timestamp);
+            let mut record = AnchorRecord::new(root.root.clone(), root.size, timestamp, txid);
+            record.metadata.backend = Some(backend.kind().to_string());
+            record
+        }
     }
 }
 
-async fn fetch_root(client: &Client, base: &str) -> anyhow::Result<RootResponse> {
-    let url = format!("{}/root", base.trim_end_matches('/'));
-    let resp = client.get(url).send().await?.error_for_status()?;
-    Ok(resp.json::<RootResponse>().await?)
+async fn fetch_root(
+    client: &Client,
+    base: &str,
+    token: Option<&str>,
+) -> anyhow::Result<RootResponse> {
+    fetch_root_path(client, base, "root", token).await
 }
 
-fn compute_txid(size: u64, root: &str, timestamp: &str) -> String {
-    let payload = format!("{}:{}:{}", size, root, timestamp);
-    let mut hasher = Sha256::new();
-    hasher.update(payload.as_bytes());
-    let digest: [u8; 32] = hasher.finalize().into();
-    hex::encode(digest)
+async fn fetch_root_path(
+    client: &Client,
+    base: &str,
+    path: &str,
+    token: Option<&str>,
+) -> anyhow::Result<RootResponse> {
+    get_json(client, base, path, token).await
+}
+
+async fn get_json<T>(
+    client: &Client,
+    base: &str,
+    path: &str,
+    token: Option<&str>,
+) -> anyhow::Result<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let url = format!("{}/{}", base.trim_end_matches('/'), path);
+    let mut req = client.get(url);
+    if let Some(token) = token {
+        req = req.bearer_auth(token);
+    }
+    let resp = req.send().await?.error_for_status()?;
+    Ok(resp.json::<T>().await?)
 }
 
 async fn read_json<T>(path: &PathBuf) -> anyhow::Result<Option<T>>
@@ -95,19 +590,3 @@ where
         Err(err) => Err(err.into()),
     }
 }
-
-async fn write_json<T>(path: &PathBuf, value: &T) -> anyhow::Result<()>
-where
-    T: serde::Serialize,
-{
-    let json = serde_json::to_string_pretty(value)?;
-    tokio::fs::write(path, json).await?;
-    Ok(())
-}
-
-async fn ensure_file(path: &PathBuf) -> anyhow::Result<()> {
-    if tokio::fs::metadata(path).await.is_err() {
-        tokio::fs::write(path, b"[]").await?;
-    }
-    Ok(())
-}
@@ -0,0 +1,318 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    routing::{get, post},
+    Json, Router,
+};
+use reality_core::{receipt::AnchorReceipt, AnchorQuery, StoredAnchor};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tokio::net::TcpListener;
+use tracing::{error, info};
+
+use crate::{
+    status::{DaemonStatus, SharedStatus},
+    store::{AnchorStore, RestoreRequest, RootConflict, SegmentInfo},
+};
+
+pub type SharedStore = Arc<Mutex<AnchorStore>>;
+
+#[derive(Clone)]
+pub struct ApiState {
+    pub store: SharedStore,
+    pub status: SharedStatus,
+    /// How long the loop may go without finishing a round before /healthz fails.
+    pub stale_after: Duration,
+    /// Bearer token of the routes that change the store, which are off
+    /// without one.
+    pub admin_token: Option<Arc<str>>,
+}
+
+impl ApiState {
+    /// Refuse a request without the admin token, and every request when
+    /// none is configured.
+    fn authorize_admin(&self, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+        let token = self.admin_token.as_ref().ok_or((
+            StatusCode::NOT_FOUND,
+            "admin routes are not configured".to_string(),
+        ))?;
+        let given = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        if Sha256::digest(given) != Sha256::digest(token.as_bytes()) {
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                "missing or wrong admin token".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Serve the anchor query and health API until the process exits.
+pub async fn serve(addr: SocketAddr, state: ApiState) -> anyhow::Result<()> {
+    let app = router(state);
+    let listener = TcpListener::bind(addr).await?;
+    info!(%addr, "anchor API listening");
+    axum::serve(listener, app.into_make_service()).await?;
+    Ok(())
+}
+
+fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/status", get(status))
+        .route("/metrics", get(metrics))
+        .route("/anchors", get(anchors))
+        .route("/receipt/*txid", get(receipt))
+        .route("/conflicts", get(conflicts))
+        .route("/archive", get(archive))
+        .route("/archive/restore", post(restore))
+        .with_state(state)
+}
+
+async fn healthz(State(state): State<ApiState>) -> (StatusCode, &'static str) {
+    let live = state
+        .status
+        .lock()
+        .expect("status poisoned")
+        .is_live(state.stale_after);
+    if live {
+        (StatusCode::OK, "ok")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "anchor loop stalled")
+    }
+}
+
+async fn status(State(state): State<ApiState>) -> Json<DaemonStatus> {
+    Json(state.status.lock().expect("status poisoned").clone())
+}
+
+async fn metrics(State(state): State<ApiState>) -> String {
+    state.status.lock().expect("status poisoned").metrics()
+}
+
+async fn anchors(
+    State(state): State<ApiState>,
+    Query(query): Query<AnchorQuery>,
+) -> Result<Json<Vec<StoredAnchor>>, (StatusCode, String)> {
+    let result = state
+        .store
+        .lock()
+        .expect("anchor store poisoned")
+        .query(&query);
+    match result {
+        Ok(records) => Ok(Json(records)),
+        Err(err) => {
+            error!(?err, "failed to query anchors");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to query anchors".into(),
+            ))
+        }
+    }
+}
+
+/// An anchor with everything needed to check it offline. Batch member txids
+/// contain a `/`, hence the wildcard path.
+async fn receipt(
+    State(state): State<ApiState>,
+    Path(txid): Path<String>,
+) -> Result<Json<AnchorReceipt>, (StatusCode, String)> {
+    let result = state
+        .store
+        .lock()
+        .expect("anchor store poisoned")
+        .receipt(&txid);
+    match result {
+        Ok(Some(receipt)) => Ok(Json(receipt)),
+        Ok(None) => Err((StatusCode::NOT_FOUND, "unknown txid".into())),
+        Err(err) => {
+            error!(?err, "failed to load receipt");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to load receipt".into(),
+            ))
+        }
+    }
+}
+
+async fn conflicts(
+    State(state): State<ApiState>,
+) -> Result<Json<Vec<RootConflict>>, (StatusCode, String)> {
+    let result = state
+        .store
+        .lock()
+        .expect("anchor store poisoned")
+        .conflicts();
+    match result {
+        Ok(conflicts) => Ok(Json(conflicts)),
+        Err(err) => {
+            error!(?err, "failed to read conflicts");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to read conflicts".into(),
+            ))
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ArchiveReport {
+    segments: Vec<SegmentInfo>,
+    /// Whether every segment matches its hash and links to its predecessor.
+    chain_valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+async fn archive(
+    State(state): State<ApiState>,
+) -> Result<Json<ArchiveReport>, (StatusCode, String)> {
+    let store = state.store.lock().expect("anchor store poisoned");
+    let segments = store.segments().map_err(|err| {
+        error!(?err, "failed to list archive segments");
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "failed to list archive segments".to_string(),
+        )
+    })?;
+    let verified = store.verify_archive();
+    Ok(Json(ArchiveReport {
+        segments,
+        chain_valid: verified.is_ok(),
+        error: verified.err().map(|err| format!("{err:#}")),
+    }))
+}
+
+/// Needs the admin token: it rewrites the hot table.
+async fn restore(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Json(request): Json<RestoreRequest>,
+) -> Result<Json<Vec<StoredAnchor>>, (StatusCode, String)> {
+    state.authorize_admin(&headers)?;
+    let result = state
+        .store
+        .lock()
+        .expect("anchor store poisoned")
+        .restore(&request);
+    match result {
+        Ok(restored) => Ok(Json(restored)),
+        Err(err) => {
+            error!(?err, "failed to restore archived anchors");
+            Err((StatusCode::BAD_REQUEST, format!("{err:#}")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{Request, Response},
+    };
+    use reality_core::AnchorRecord;
+    use tower_service::Service;
+
+    use super::*;
+
+    async fn get(app: &mut Router, path: &str) -> (StatusCode, String) {
+        let request = Request::get(path).body(Body::empty()).unwrap();
+        let response: Response<Body> = app.call(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn healthz_fails_once_the_loop_stalls() {
+        let status = DaemonStatus::new(["simulated".to_string()]).shared();
+        let state = |stale_after| ApiState {
+            store: Arc::new(Mutex::new(AnchorStore::in_memory().unwrap())),
+            status: status.clone(),
+            stale_after,
+            admin_token: None,
+        };
+        let mut live = router(state(Duration::from_secs(60)));
+        assert_eq!(
+            get(&mut live, "/healthz").await,
+            (StatusCode::OK, "ok".into())
+        );
+        let mut stalled = router(state(Duration::ZERO));
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert_eq!(
+            get(&mut stalled, "/healthz").await.0,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        {
+            let mut status = status.lock().unwrap();
+            status.backend_result("simulated", Some("backend down".into()));
+            status.round_finished(Some("backend down".into()), 3);
+        }
+        let (code, body) = get(&mut live, "/status").await;
+        assert_eq!(code, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert!(json["started_at"].is_string());
+        assert!(json["last_round_at"].is_string());
+        assert_eq!(json["last_error"], "backend down");
+        assert_eq!(json["consecutive_failures"], 1);
+        assert_eq!(json["alerting"], false);
+        assert_eq!(json["backends"][0]["kind"], "simulated");
+        assert_eq!(json["backends"][0]["healthy"], false);
+        assert_eq!(json["backends"][0]["last_error"], "backend down");
+        assert!(json.get("last_round").is_none());
+
+        let record = AnchorRecord::new("aa".into(), 1, "1".into(), "tx".into());
+        status.lock().unwrap().anchored("simulated", &record);
+        let json: serde_json::Value =
+            serde_json::from_str(&get(&mut live, "/status").await.1).unwrap();
+        assert_eq!(json["backends"][0]["healthy"], true);
+        assert!(json["backends"][0]["last_error"].is_null());
+    }
+
+    async fn restore(app: &mut Router, token: Option<&str>) -> StatusCode {
+        let mut request =
+            Request::post("/archive/restore").header(header::CONTENT_TYPE, "application/json");
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let request = request.body(Body::from(r#"{"txid":"tx"}"#)).unwrap();
+        let response: Response<Body> = app.call(request).await.unwrap();
+        response.status()
+    }
+
+    #[tokio::test]
+    async fn restore_needs_the_admin_token() {
+        let state = |admin_token: Option<&str>| ApiState {
+            store: Arc::new(Mutex::new(AnchorStore::in_memory().unwrap())),
+            status: DaemonStatus::new(["simulated".to_string()]).shared(),
+            stale_after: Duration::from_secs(60),
+            admin_token: admin_token.map(Arc::from),
+        };
+        let mut closed = router(state(None));
+        assert_eq!(
+            restore(&mut closed, Some("s3cret")).await,
+            StatusCode::NOT_FOUND
+        );
+
+        let mut app = router(state(Some("s3cret")));
+        assert_eq!(restore(&mut app, None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            restore(&mut app, Some("wrong")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(restore(&mut app, Some("s3cret")).await, StatusCode::OK);
+    }
+}
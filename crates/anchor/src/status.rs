@@ -0,0 +1,210 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use reality_core::{checkpoint::Checkpoint, AnchorRecord};
+use serde::Serialize;
+use time::OffsetDateTime;
+
+pub type SharedStatus = Arc<Mutex<DaemonStatus>>;
+
+/// Live view of the anchoring loop, reported at `/status`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DaemonStatus {
+    pub started_at: String,
+    pub last_round_at: Option<String>,
+    pub last_success: Option<AnchorRecord>,
+    pub last_success_at: Option<String>,
+    /// Latest checkpoint cosigned in witness mode.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_cosigned: Option<Checkpoint>,
+    pub last_error: Option<String>,
+    pub pending_retries: u32,
+    pub consecutive_failures: u32,
+    /// Set once `consecutive_failures` reaches the alert threshold.
+    pub alerting: bool,
+    pub backends: Vec<BackendState>,
+    #[serde(skip)]
+    last_round: Instant,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertTransition {
+    None,
+    Raised,
+    Cleared { failed_rounds: u32 },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendState {
+    pub kind: String,
+    pub healthy: bool,
+    pub last_error: Option<String>,
+}
+
+impl DaemonStatus {
+    pub fn new(backends: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            started_at: now_rfc3339(),
+            last_round_at: None,
+            last_success: None,
+            last_success_at: None,
+            last_cosigned: None,
+            last_error: None,
+            pending_retries: 0,
+            consecutive_failures: 0,
+            alerting: false,
+            backends: backends
+                .into_iter()
+                .map(|kind| BackendState {
+                    kind,
+                    healthy: true,
+                    last_error: None,
+                })
+                .collect(),
+            last_round: Instant::now(),
+        }
+    }
+
+    pub fn shared(self) -> SharedStatus {
+        Arc::new(Mutex::new(self))
+    }
+
+    /// Mark the end of a loop iteration and report the alert transition it
+    /// caused, if any.
+    pub fn round_finished(&mut self, error: Option<String>, threshold: u32) -> AlertTransition {
+        self.last_round = Instant::now();
+        self.last_round_at = Some(now_rfc3339());
+        let transition = match &error {
+            Some(_) => {
+                self.consecutive_failures += 1;
+                if !self.alerting && self.consecutive_failures >= threshold {
+                    self.alerting = true;
+                    AlertTransition::Raised
+                } else {
+                    AlertTransition::None
+                }
+            }
+            None => {
+                let failed_rounds = self.consecutive_failures;
+                self.consecutive_failures = 0;
+                if std::mem::take(&mut self.alerting) {
+                    AlertTransition::Cleared { failed_rounds }
+                } else {
+                    AlertTransition::None
+                }
+            }
+        };
+        self.last_error = error;
+        transition
+    }
+
+    pub fn anchored(&mut self, backend: &str, record: &AnchorRecord) {
+        self.last_success = Some(record.clone());
+        self.last_success_at = Some(now_rfc3339());
+        self.backend_result(backend, None);
+    }
+
+    pub fn cosigned(&mut self, checkpoint: &Checkpoint) {
+        self.last_cosigned = Some(checkpoint.clone());
+        self.last_success_at = Some(now_rfc3339());
+        self.backend_result("witness", None);
+    }
+
+    pub fn backend_result(&mut self, backend: &str, error: Option<String>) {
+        if let Some(state) = self.backends.iter_mut().find(|b| b.kind == backend) {
+            state.healthy = error.is_none();
+            state.last_error = error;
+        }
+    }
+
+    /// The loop is considered wedged once no round has finished for `stale_after`.
+    pub fn is_live(&self, stale_after: Duration) -> bool {
+        self.last_round.elapsed() <= stale_after
+    }
+
+    /// Prometheus text exposition of the loop's health.
+    pub fn metrics(&self) -> String {
+        let last_success = self
+            .last_success
+            .as_ref()
+            .map(|r| &r.timestamp_nanos)
+            .or(self.last_cosigned.as_ref().map(|c| &c.timestamp_nanos))
+            .and_then(|nanos| nanos.parse::<i128>().ok())
+            .map(|nanos| nanos as f64 / 1e9)
+            .unwrap_or(0.0);
+        format!(
+            "# TYPE reality_anchor_consecutive_failures gauge\n\
+             reality_anchor_consecutive_failures {}\n\
+             # TYPE reality_anchor_alert_active gauge\n\
+             reality_anchor_alert_active {}\n\
+             # TYPE reality_anchor_pending_retries gauge\n\
+             reality_anchor_pending_retries {}\n\
+             # TYPE reality_anchor_last_success_timestamp_seconds gauge\n\
+             reality_anchor_last_success_timestamp_seconds {}\n",
+            self.consecutive_failures,
+            u8::from(self.alerting),
+            self.pending_retries,
+            last_success,
+        )
+    }
+}
+
+fn now_rfc3339() -> String {
+    OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alert_raises_once_at_threshold_and_clears_on_success() {
+        let mut status = DaemonStatus::new(["simulated".to_string()]);
+        assert_eq!(
+            status.round_finished(Some("down".into()), 2),
+            AlertTransition::None
+        );
+        assert_eq!(
+            status.round_finished(Some("down".into()), 2),
+            AlertTransition::Raised
+        );
+        assert_eq!(
+            status.round_finished(Some("down".into()), 2),
+            AlertTransition::None
+        );
+        assert!(status.metrics().contains("reality_anchor_alert_active 1"));
+        assert_eq!(
+            status.round_finished(None, 2),
+            AlertTransition::Cleared { failed_rounds: 3 }
+        );
+        assert!(!status.alerting);
+    }
+
+    #[test]
+    fn liveness_follows_the_last_round() {
+        let stale_after = Duration::from_secs(60);
+        let ago = |secs| {
+            Instant::now()
+                .checked_sub(Duration::from_secs(secs))
+                .unwrap()
+        };
+
+        // A loop that has not finished a round yet counts from startup.
+        let mut status = DaemonStatus::new(["simulated".to_string()]);
+        assert!(status.last_round_at.is_none());
+        assert!(status.is_live(stale_after));
+        status.last_round = ago(61);
+        assert!(!status.is_live(stale_after));
+
+        status.round_finished(None, 3);
+        assert!(status.is_live(stale_after));
+        status.last_round = ago(59);
+        assert!(status.is_live(stale_after));
+        status.last_round = ago(120);
+        assert!(!status.is_live(stale_after));
+    }
+}
@@ -0,0 +1,1269 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use reality_core::{
+    checkpoint::{Checkpoint, SignedCheckpoint},
+    receipt::{AnchorReceipt, BatchMembership},
+    signer::DetachedSignature,
+    AnchorMetadata, AnchorQuery, AnchorRecord, AnchorStatus, InclusionProof, RootResponse,
+    StoredAnchor,
+};
+use rusqlite::{params, types::Value, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::archive::{self, ArchiveSegment};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS anchors (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    root TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    timestamp_nanos INTEGER NOT NULL,
+    txid TEXT NOT NULL UNIQUE,
+    status TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS anchors_root ON anchors (root);
+CREATE INDEX IF NOT EXISTS anchors_time ON anchors (timestamp_nanos);
+CREATE INDEX IF NOT EXISTS anchors_status ON anchors (status);
+CREATE INDEX IF NOT EXISTS anchors_size ON anchors (size);
+CREATE TABLE IF NOT EXISTS conflicts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,
+    observed_root TEXT NOT NULL,
+    observed_size INTEGER NOT NULL,
+    anchored_txid TEXT NOT NULL,
+    detected_at_nanos INTEGER NOT NULL,
+    UNIQUE (kind, observed_root, observed_size, anchored_txid)
+);
+CREATE TABLE IF NOT EXISTS anchor_signatures (
+    txid TEXT PRIMARY KEY REFERENCES anchors (txid),
+    public_key TEXT NOT NULL,
+    signature TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS cosigned (
+    size INTEGER PRIMARY KEY,
+    root TEXT NOT NULL,
+    timestamp_nanos TEXT NOT NULL,
+    log_public_key TEXT NOT NULL,
+    log_signature TEXT NOT NULL,
+    public_key TEXT NOT NULL,
+    signature TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS spend (
+    day TEXT NOT NULL,
+    backend TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    PRIMARY KEY (day, backend)
+);
+CREATE TABLE IF NOT EXISTS pending (
+    backend TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    root TEXT NOT NULL,
+    PRIMARY KEY (backend, size, root)
+);
+CREATE TABLE IF NOT EXISTS batch_members (
+    txid TEXT PRIMARY KEY REFERENCES anchors (txid),
+    batch_txid TEXT NOT NULL,
+    batch_root TEXT NOT NULL,
+    proof TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS archive_segments (
+    seq INTEGER PRIMARY KEY,
+    file TEXT NOT NULL,
+    sha256 TEXT NOT NULL,
+    prev_sha256 TEXT,
+    count INTEGER NOT NULL,
+    first_nanos INTEGER NOT NULL,
+    last_nanos INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS archived_anchors (
+    txid TEXT PRIMARY KEY,
+    root TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    seq INTEGER NOT NULL REFERENCES archive_segments (seq)
+);
+CREATE INDEX IF NOT EXISTS archived_anchors_root ON archived_anchors (root);
+CREATE INDEX IF NOT EXISTS archived_anchors_size ON archived_anchors (size);
+CREATE TABLE IF NOT EXISTS restored_anchors (
+    txid TEXT PRIMARY KEY,
+    restored_at_nanos INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS external_proofs (
+    txid TEXT PRIMARY KEY REFERENCES anchors (txid),
+    kind TEXT NOT NULL,
+    proof BLOB
+);
+";
+
+/// Columns added to `anchors` for version 2 records; stores created before
+/// them are migrated in place and their rows read back as version 1.
+const V2_COLUMNS: &[(&str, &str)] = &[
+    ("version", "INTEGER NOT NULL DEFAULT 1"),
+    ("backend", "TEXT"),
+    ("network", "TEXT"),
+    ("block_height", "INTEGER"),
+    ("confirmations", "INTEGER"),
+    ("receipt_uri", "TEXT"),
+    ("signing_identity", "TEXT"),
+];
+
+/// Everything `read_record` needs, in order.
+macro_rules! record_columns {
+    () => {
+        "a.root, a.size, a.timestamp_nanos, a.txid, a.version, a.backend, a.network, \
+         a.block_height, a.confirmations, a.receipt_uri, a.signing_identity"
+    };
+}
+const RECORD_COLUMN_COUNT: usize = 11;
+
+const SELECT_ANCHORS: &str = concat!(
+    "SELECT ",
+    record_columns!(),
+    ", a.status, s.public_key, s.signature
+     FROM anchors a LEFT JOIN anchor_signatures s ON s.txid = a.txid"
+);
+
+const DEFAULT_LIMIT: u32 = 1000;
+
+/// Why a fetched root was refused.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictKind {
+    /// The log reports fewer leaves than an earlier anchor covered.
+    Regression,
+    /// The log reports a different root for a size that was already anchored.
+    Fork,
+}
+
+impl ConflictKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ConflictKind::Regression => "regression",
+            ConflictKind::Fork => "fork",
+        }
+    }
+}
+
+/// Evidence that the log contradicted an earlier anchor.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct RootConflict {
+    pub kind: ConflictKind,
+    pub observed_root: String,
+    pub observed_size: u64,
+    pub anchored: AnchorRecord,
+}
+
+/// An archive segment as indexed in the store.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct SegmentInfo {
+    pub seq: u64,
+    pub file: String,
+    pub sha256: String,
+    pub prev_sha256: Option<String>,
+    pub count: u64,
+    pub first_nanos: i64,
+    pub last_nanos: i64,
+}
+
+/// What one submission to a backend anchored.
+#[derive(Debug, Clone)]
+pub struct Submission {
+    pub records: Vec<AnchorRecord>,
+    /// The transaction the records share, when they were batched.
+    pub batch: Option<Batch>,
+}
+
+/// A batch transaction: its root over the checkpoints, and each record's
+/// proof of membership, in record order.
+#[derive(Debug, Clone)]
+pub struct Batch {
+    pub txid: String,
+    pub root: String,
+    pub proofs: Vec<InclusionProof>,
+}
+
+/// Which archived anchors to fault back in.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RestoreRequest {
+    #[serde(default)]
+    pub txid: Option<String>,
+    #[serde(default)]
+    pub root: Option<String>,
+}
+
+/// SQLite-backed anchor history. Old confirmed anchors can be moved out into
+/// hash-chained segments under `archive_dir`; SQLite keeps an index of them.
+pub struct AnchorStore {
+    conn: Connection,
+    archive_dir: PathBuf,
+}
+
+impl AnchorStore {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("open anchor store {}", path.display()))?;
+        Self::init(conn)
+    }
+
+    #[cfg(test)]
+    pub fn in_memory() -> anyhow::Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> anyhow::Result<Self> {
+        conn.execute_batch(SCHEMA).context("create anchor schema")?;
+        migrate(&conn).context("migrate anchor schema")?;
+        Ok(Self {
+            conn,
+            archive_dir: PathBuf::from("archive"),
+        })
+    }
+
+    pub fn set_archive_dir(&mut self, dir: PathBuf) {
+        self.archive_dir = dir;
+    }
+
+    /// Import the legacy anchors.json array the first time the store is opened.
+    pub fn import_legacy(&mut self, records: &[AnchorRecord]) -> anyhow::Result<usize> {
+        if self.count()? > 0 {
+            return Ok(0);
+        }
+        let tx = self.conn.transaction()?;
+        for record in records {
+            insert(&tx, record, AnchorStatus::Confirmed)?;
+        }
+        tx.commit()?;
+        Ok(records.len())
+    }
+
+    #[cfg(test)]
+    pub fn insert(&self, record: &AnchorRecord, status: AnchorStatus) -> anyhow::Result<()> {
+        insert(&self.conn, record, status)
+    }
+
+    /// Merge an anchor imported from an external system, keeping its proof.
+    /// Returns false if an anchor with the same txid already exists.
+    pub fn insert_external(
+        &mut self,
+        record: &AnchorRecord,
+        status: AnchorStatus,
+        kind: &str,
+        proof: Option<&[u8]>,
+    ) -> anyhow::Result<bool> {
+        let tx = self.conn.transaction()?;
+        let exists: bool = tx.query_row(
+            "SELECT EXISTS (SELECT 1 FROM anchors WHERE txid = ?1)",
+            params![record.txid],
+            |row| row.get(0),
+        )?;
+        if exists {
+            return Ok(false);
+        }
+        insert(&tx, record, status)?;
+        tx.execute(
+            "INSERT INTO external_proofs (txid, kind, proof) VALUES (?1, ?2, ?3)",
+            params![record.txid, kind, proof],
+        )?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    pub fn insert_signature(
+        &self,
+        txid: &str,
+        signature: &DetachedSignature,
+    ) -> anyhow::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO anchor_signatures (txid, public_key, signature)
+             VALUES (?1, ?2, ?3)",
+            params![txid, signature.public_key, signature.signature],
+        )?;
+        Ok(())
+    }
+
+    /// Total fees recorded for `backend` on the UTC `day` (YYYY-MM-DD).
+    pub fn spent_on(&self, day: &str, backend: &str) -> anyhow::Result<u64> {
+        let spent: Option<i64> = self
+            .conn
+            .query_row(
+                "SELECT amount FROM spend WHERE day = ?1 AND backend = ?2",
+                params![day, backend],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(spent.unwrap_or(0) as u64)
+    }
+
+    /// Record a submission to `backend` in one transaction: its anchors and
+    /// batch membership, `fee` added to `day`'s spend, and the end of the
+    /// backend's queue. A crash part way through leaves the checkpoints
+    /// queued and unpaid rather than anchored twice or never counted.
+    pub fn record_submission(
+        &self,
+        backend: &str,
+        day: &str,
+        fee: u64,
+        submission: &Submission,
+    ) -> anyhow::Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for (i, record) in submission.records.iter().enumerate() {
+            insert(&tx, record, AnchorStatus::Confirmed)?;
+            if let Some(batch) = &submission.batch {
+                let proof = batch.proofs.get(i).context("batch proof missing")?;
+                insert_batch_member(&tx, &record.txid, &batch.txid, &batch.root, proof)?;
+            }
+        }
+        add_spend(&tx, day, backend, fee)?;
+        clear_pending(&tx, backend)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Queue `root` for `backend`, keeping only the newest `keep`
+    /// checkpoints queued. Anchoring a checkpoint covers every earlier one
+    /// through consistency proofs, so dropping older ones loses detail, not
+    /// coverage. A root already queued stays queued once.
+    pub fn queue(&self, backend: &str, root: &RootResponse, keep: usize) -> anyhow::Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO pending (backend, size, root) VALUES (?1, ?2, ?3)",
+            params![backend, root.size as i64, root.root],
+        )?;
+        self.conn.execute(
+            "DELETE FROM pending WHERE backend = ?1 AND rowid NOT IN (
+                 SELECT rowid FROM pending WHERE backend = ?1
+                 ORDER BY size DESC LIMIT ?2
+             )",
+            params![backend, keep.max(1) as i64],
+        )?;
+        Ok(())
+    }
+
+    /// Checkpoints queued for `backend`, oldest first.
+    pub fn pending(&self, backend: &str) -> anyhow::Result<Vec<RootResponse>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT root, size FROM pending WHERE backend = ?1 ORDER BY size")?;
+        let rows = stmt.query_map(params![backend], |row| {
+            Ok(RootResponse {
+                root: row.get(0)?,
+                size: row.get::<_, i64>(1)? as u64,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// The anchor with `txid` plus its batch membership, if it was batched.
+    pub fn receipt(&self, txid: &str) -> anyhow::Result<Option<AnchorReceipt>> {
+        let Some(anchor) = self
+            .conn
+            .query_row(
+                &format!("{SELECT_ANCHORS} WHERE a.txid = ?1"),
+                [txid],
+                read_row,
+            )
+            .optional()?
+        else {
+            return Ok(None);
+        };
+        let batch = self
+            .conn
+            .query_row(
+                "SELECT batch_txid, batch_root, proof FROM batch_members WHERE txid = ?1",
+                [txid],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get::<_, String>(2)?)),
+            )
+            .optional()?
+            .map(|(batch_txid, batch_root, proof)| {
+                anyhow::Ok(BatchMembership {
+                    batch_txid,
+                    batch_root,
+                    proof: serde_json::from_str(&proof)?,
+                })
+            })
+            .transpose()?;
+        Ok(Some(AnchorReceipt { anchor, batch }))
+    }
+
+    /// Record a log checkpoint together with this witness's cosignature.
+    pub fn insert_cosigned(
+        &self,
+        signed: &SignedCheckpoint,
+        witness: &DetachedSignature,
+    ) -> anyhow::Result<()> {
+        let checkpoint = &signed.checkpoint;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO cosigned
+             (size, root, timestamp_nanos, log_public_key, log_signature, public_key, signature)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                checkpoint.size as i64,
+                checkpoint.root.to_ascii_lowercase(),
+                checkpoint.timestamp_nanos,
+                signed.signature.public_key,
+                signed.signature.signature,
+                witness.public_key,
+                witness.signature,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The largest checkpoint cosigned so far, as the log signed it; the
+    /// next one must extend it.
+    pub fn latest_cosigned(&self) -> anyhow::Result<Option<SignedCheckpoint>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT size, root, timestamp_nanos, log_public_key, log_signature
+                 FROM cosigned ORDER BY size DESC LIMIT 1",
+                [],
+                |row| {
+                    Ok(SignedCheckpoint {
+                        checkpoint: Checkpoint {
+                            size: row.get::<_, i64>(0)? as u64,
+                            root: row.get(1)?,
+                            timestamp_nanos: row.get(2)?,
+                        },
+                        signature: DetachedSignature {
+                            public_key: row.get(3)?,
+                            signature: row.get(4)?,
+                        },
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    pub fn count(&self) -> anyhow::Result<u64> {
+        let count: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM anchors", [], |row| row.get(0))?;
+        Ok(count as u64)
+    }
+
+    pub fn latest(&self) -> anyhow::Result<Option<StoredAnchor>> {
+        self.conn
+            .query_row(
+                &format!("{SELECT_ANCHORS} ORDER BY a.id DESC LIMIT 1"),
+                [],
+                read_row,
+            )
+            .optional()
+            .map_err(Into::into)
+    }
+
+    /// Compare a freshly fetched root against every anchored checkpoint.
+    pub fn check_root(&self, root: &RootResponse) -> anyhow::Result<Option<RootConflict>> {
+        let largest = self
+            .conn
+            .query_row(
+                &format!("{SELECT_ANCHORS} ORDER BY a.size DESC, a.id ASC LIMIT 1"),
+                [],
+                read_row,
+            )
+            .optional()?;
+        if let Some(largest) = largest {
+            if largest.record.size > root.size {
+                return Ok(Some(RootConflict {
+                    kind: ConflictKind::Regression,
+                    observed_root: root.root.clone(),
+                    observed_size: root.size,
+                    anchored: largest.record,
+                }));
+            }
+        }
+
+        let forked = self
+            .conn
+            .query_row(
+                &format!(
+                    "{SELECT_ANCHORS} WHERE a.size = ?1 AND a.root != ?2 ORDER BY a.id ASC LIMIT 1"
+                ),
+                params![root.size as i64, root.root.to_ascii_lowercase()],
+                read_row,
+            )
+            .optional()?;
+        Ok(forked.map(|anchor| RootConflict {
+            kind: ConflictKind::Fork,
+            observed_root: root.root.clone(),
+            observed_size: root.size,
+            anchored: anchor.record,
+        }))
+    }
+
+    /// Every distinct anchored checkpoint up to `max_size`, archived ones
+    /// included, smallest first.
+    pub fn anchored_roots(&self, max_size: u64) -> anyhow::Result<Vec<(u64, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT size, root FROM anchors WHERE size <= ?1
+             UNION SELECT size, root FROM archived_anchors WHERE size <= ?1
+             ORDER BY size ASC",
+        )?;
+        let rows = stmt.query_map(params![max_size as i64], |row| {
+            Ok((row.get::<_, i64>(0)? as u64, row.get(1)?))
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// The fork shown by the log's root `logged` at an anchored `size`, if
+    /// an anchor of that size has another root. An archived anchor is
+    /// restored first, so the conflict cites a hot anchor that rotation keeps.
+    pub fn fork_at(&self, size: u64, logged: &str) -> anyhow::Result<Option<RootConflict>> {
+        let logged = logged.to_ascii_lowercase();
+        let find = || {
+            self.conn
+                .query_row(
+                    &format!(
+                        "{SELECT_ANCHORS} WHERE a.size = ?1 AND a.root != ?2 \
+                         ORDER BY a.id ASC LIMIT 1"
+                    ),
+                    params![size as i64, logged],
+                    read_row,
+                )
+                .optional()
+        };
+        let mut anchor = find()?;
+        if anchor.is_none() {
+            let archived: Option<String> = self
+                .conn
+                .query_row(
+                    "SELECT txid FROM archived_anchors WHERE size = ?1 AND root != ?2
+                     ORDER BY seq ASC LIMIT 1",
+                    params![size as i64, logged],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            if let Some(txid) = archived {
+                self.restore(&RestoreRequest {
+                    txid: Some(txid),
+                    root: None,
+                })?;
+                anchor = find()?;
+            }
+        }
+        Ok(anchor.map(|anchor| RootConflict {
+            kind: ConflictKind::Fork,
+            observed_root: logged,
+            observed_size: size,
+            anchored: anchor.record,
+        }))
+    }
+
+    /// Persist conflict evidence; repeated sightings of the same conflict are
+    /// stored once. Returns true the first time a conflict is seen.
+    pub fn record_conflict(
+        &self,
+        conflict: &RootConflict,
+        detected_at_nanos: i64,
+    ) -> anyhow::Result<bool> {
+        let inserted = self.conn.execute(
+            "INSERT OR IGNORE INTO conflicts
+             (kind, observed_root, observed_size, anchored_txid, detected_at_nanos)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                conflict.kind.as_str(),
+                conflict.observed_root,
+                conflict.observed_size as i64,
+                conflict.anchored.txid,
+                detected_at_nanos
+            ],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// All recorded conflicts, oldest first.
+    pub fn conflicts(&self) -> anyhow::Result<Vec<RootConflict>> {
+        let mut stmt = self.conn.prepare(concat!(
+            "SELECT c.kind, c.observed_root, c.observed_size, ",
+            record_columns!(),
+            " FROM conflicts c JOIN anchors a ON a.txid = c.anchored_txid
+             ORDER BY c.id ASC"
+        ))?;
+        let rows = stmt.query_map([], |row| {
+            let kind: String = row.get(0)?;
+            let observed_size: i64 = row.get(2)?;
+            Ok(RootConflict {
+                kind: if kind == "fork" {
+                    ConflictKind::Fork
+                } else {
+                    ConflictKind::Regression
+                },
+                observed_root: row.get(1)?,
+                observed_size: observed_size as u64,
+                anchored: read_record(row, 3)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Move confirmed anchors older than `cutoff_nanos` into a new archive
+    /// segment. The newest and the largest anchors stay hot so `check_root`
+    /// still sees them, as do anchors cited by a conflict and anchors restored
+    /// since the cutoff. Returns how many anchors left the hot table.
+    pub fn rotate(&mut self, cutoff_nanos: i64, max: usize) -> anyhow::Result<usize> {
+        let candidates = {
+            let mut stmt = self.conn.prepare(&format!(
+                "{SELECT_ANCHORS}
+                 WHERE a.status = 'confirmed' AND a.timestamp_nanos < ?1
+                   AND a.id != (SELECT MAX(id) FROM anchors)
+                   AND a.size < (SELECT MAX(size) FROM anchors)
+                   AND a.txid NOT IN (SELECT anchored_txid FROM conflicts)
+                   AND a.txid NOT IN
+                       (SELECT txid FROM restored_anchors WHERE restored_at_nanos >= ?1)
+                 ORDER BY a.id ASC LIMIT ?2"
+            ))?;
+            let rows = stmt.query_map(params![cutoff_nanos, max as i64], read_row)?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+        if candidates.is_empty() {
+            return Ok(0);
+        }
+
+        // Anchors restored earlier are already in a segment; only drop them.
+        let mut fresh = Vec::new();
+        for anchor in &candidates {
+            let archived: bool = self.conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM archived_anchors WHERE txid = ?1)",
+                [&anchor.record.txid],
+                |row| row.get(0),
+            )?;
+            if !archived {
+                fresh.push(anchor.clone());
+            }
+        }
+
+        let segment = if fresh.is_empty() {
+            None
+        } else {
+            let head = self.segments()?.pop();
+            let segment = ArchiveSegment {
+                seq: head.as_ref().map(|h| h.seq + 1).unwrap_or(1),
+                prev_sha256: head.map(|h| h.sha256),
+                anchors: fresh,
+            };
+            let (file, sha256) = archive::write_segment(&self.archive_dir, &segment)?;
+            Some((segment, file, sha256))
+        };
+
+        let tx = self.conn.transaction()?;
+        if let Some((segment, file, sha256)) = &segment {
+            let nanos = |a: &StoredAnchor| a.record.timestamp_nanos.parse::<i64>().unwrap_or(0);
+            tx.execute(
+                "INSERT INTO archive_segments
+                 (seq, file, sha256, prev_sha256, count, first_nanos, last_nanos)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    segment.seq as i64,
+                    file,
+                    sha256,
+                    segment.prev_sha256,
+                    segment.anchors.len() as i64,
+                    segment.anchors.iter().map(nanos).min().unwrap_or(0),
+                    segment.anchors.iter().map(nanos).max().unwrap_or(0),
+                ],
+            )?;
+            for anchor in &segment.anchors {
+                tx.execute(
+                    "INSERT INTO archived_anchors (txid, root, size, seq) VALUES (?1, ?2, ?3, ?4)",
+                    params![
+                        anchor.record.txid,
+                        anchor.record.root,
+                        anchor.record.size as i64,
+                        segment.seq as i64
+                    ],
+                )?;
+            }
+        }
+        for anchor in &candidates {
+            for table in ["anchors", "anchor_signatures", "restored_anchors"] {
+                tx.execute(
+                    &format!("DELETE FROM {table} WHERE txid = ?1"),
+                    [&anchor.record.txid],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(candidates.len())
+    }
+
+    /// Fault archived anchors matching `request` back into the hot table,
+    /// checking each segment against its recorded hash first. Restored
+    /// anchors stay hot for a full archive period.
+    pub fn restore(&self, request: &RestoreRequest) -> anyhow::Result<Vec<StoredAnchor>> {
+        anyhow::ensure!(
+            request.txid.is_some() || request.root.is_some(),
+            "restore needs a txid or a root"
+        );
+        let wanted: Vec<(String, u64)> = {
+            let mut stmt = self.conn.prepare(
+                "SELECT txid, seq FROM archived_anchors
+                 WHERE (?1 IS NULL OR txid = ?1) AND (?2 IS NULL OR root = ?2)
+                   AND txid NOT IN (SELECT txid FROM anchors)
+                 ORDER BY seq ASC",
+            )?;
+            let rows = stmt.query_map(
+                params![
+                    request.txid,
+                    request.root.as_ref().map(|r| r.to_ascii_lowercase())
+                ],
+                |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as u64)),
+            )?;
+            rows.collect::<Result<_, _>>()?
+        };
+
+        let segments = self.segments()?;
+        let now = OffsetDateTime::now_utc().unix_timestamp_nanos() as i64;
+        let mut restored = Vec::new();
+        let tx = self.conn.unchecked_transaction()?;
+        let mut loaded: Option<ArchiveSegment> = None;
+        for (txid, seq) in wanted {
+            if loaded.as_ref().map(|s| s.seq) != Some(seq) {
+                let info = segments
+                    .iter()
+                    .find(|s| s.seq == seq)
+                    .with_context(|| format!("archive segment {seq} is not indexed"))?;
+                loaded = Some(archive::read_segment(
+                    &self.archive_dir.join(&info.file),
+                    &info.sha256,
+                )?);
+            }
+            let segment = loaded.as_ref().expect("segment loaded");
+            let anchor = segment
+                .anchors
+                .iter()
+                .find(|a| a.record.txid == txid)
+                .with_context(|| format!("anchor {txid} missing from segment {seq}"))?;
+            insert(&tx, &anchor.record, anchor.status)?;
+            if let Some(signature) = &anchor.signature {
+                tx.execute(
+                    "INSERT OR REPLACE INTO anchor_signatures (txid, public_key, signature)
+                     VALUES (?1, ?2, ?3)",
+                    params![txid, signature.public_key, signature.signature],
+                )?;
+            }
+            tx.execute(
+                "INSERT OR REPLACE INTO restored_anchors (txid, restored_at_nanos) VALUES (?1, ?2)",
+                params![txid, now],
+            )?;
+            restored.push(anchor.clone());
+        }
+        tx.commit()?;
+        Ok(restored)
+    }
+
+    /// Archive segments, oldest first.
+    pub fn segments(&self) -> anyhow::Result<Vec<SegmentInfo>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT seq, file, sha256, prev_sha256, count, first_nanos, last_nanos
+             FROM archive_segments ORDER BY seq ASC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(SegmentInfo {
+                seq: row.get::<_, i64>(0)? as u64,
+                file: row.get(1)?,
+                sha256: row.get(2)?,
+                prev_sha256: row.get(3)?,
+                count: row.get::<_, i64>(4)? as u64,
+                first_nanos: row.get(5)?,
+                last_nanos: row.get(6)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Re-read every segment and check the hash chain from the first segment
+    /// to the head recorded in the store.
+    pub fn verify_archive(&self) -> anyhow::Result<()> {
+        let mut prev: Option<String> = None;
+        for info in self.segments()? {
+            let segment = archive::read_segment(&self.archive_dir.join(&info.file), &info.sha256)?;
+            anyhow::ensure!(
+                segment.seq == info.seq && segment.prev_sha256 == prev,
+                "archive segment {} does not link to its predecessor",
+                info.seq
+            );
+            prev = Some(info.sha256);
+        }
+        Ok(())
+    }
+
+    /// List anchors matching `query`, oldest first.
+    pub fn query(&self, query: &AnchorQuery) -> anyhow::Result<Vec<StoredAnchor>> {
+        let mut sql = format!("{SELECT_ANCHORS} WHERE 1 = 1");
+        let mut args: Vec<Value> = Vec::new();
+        if let Some(root) = &query.root {
+            sql.push_str(" AND a.root = ?");
+            args.push(Value::Text(root.to_ascii_lowercase()));
+        }
+        if let Some(from) = query.from_nanos {
+            sql.push_str(" AND a.timestamp_nanos >= ?");
+            args.push(Value::Integer(from));
+        }
+        if let Some(to) = query.to_nanos {
+            sql.push_str(" AND a.timestamp_nanos <= ?");
+            args.push(Value::Integer(to));
+        }
+        if let Some(status) = query.status {
+            sql.push_str(" AND a.status = ?");
+            args.push(Value::Text(status.as_str().to_string()));
+        }
+        sql.push_str(" ORDER BY a.id ASC LIMIT ?");
+        args.push(Value::Integer(query.limit.unwrap_or(DEFAULT_LIMIT) as i64));
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(args), read_row)?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+}
+
+fn insert(conn: &Connection, record: &AnchorRecord, status: AnchorStatus) -> anyhow::Result<()> {
+    let timestamp: i64 = record
+        .timestamp_nanos
+        .parse()
+        .context("anchor timestamp is not an integer")?;
+    let meta = &record.metadata;
+    conn.execute(
+        "INSERT OR IGNORE INTO anchors
+         (root, size, timestamp_nanos, txid, status, version, backend, network,
+          block_height, confirmations, receipt_uri, signing_identity)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            record.root,
+            record.size as i64,
+            timestamp,
+            record.txid,
+            status.as_str(),
+            record.version,
+            meta.backend,
+            meta.network,
+            meta.block_height.map(|h| h as i64),
+            meta.confirmations.map(|c| c as i64),
+            meta.receipt_uri,
+            meta.signing_identity,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Remember which batch transaction covers `txid`, with its proof of
+/// membership in the batch root.
+fn insert_batch_member(
+    conn: &Connection,
+    txid: &str,
+    batch_txid: &str,
+    batch_root: &str,
+    proof: &InclusionProof,
+) -> anyhow::Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO batch_members (txid, batch_txid, batch_root, proof)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![txid, batch_txid, batch_root, serde_json::to_string(proof)?],
+    )?;
+    Ok(())
+}
+
+fn add_spend(conn: &Connection, day: &str, backend: &str, amount: u64) -> anyhow::Result<()> {
+    conn.execute(
+        "INSERT INTO spend (day, backend, amount) VALUES (?1, ?2, ?3)
+         ON CONFLICT (day, backend) DO UPDATE SET amount = amount + excluded.amount",
+        params![day, backend, amount as i64],
+    )?;
+    Ok(())
+}
+
+/// Drop `backend`'s queued checkpoints once they are anchored.
+fn clear_pending(conn: &Connection, backend: &str) -> anyhow::Result<()> {
+    conn.execute("DELETE FROM pending WHERE backend = ?1", params![backend])?;
+    Ok(())
+}
+
+fn migrate(conn: &Connection) -> anyhow::Result<()> {
+    let existing: Vec<String> = conn
+        .prepare("SELECT name FROM pragma_table_info('anchors')")?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+    for (name, decl) in V2_COLUMNS {
+        if !existing.iter().any(|c| c == name) {
+            conn.execute_batch(&format!("ALTER TABLE anchors ADD COLUMN {name} {decl}"))?;
+        }
+    }
+    Ok(())
+}
+
+/// Read `record_columns!()` starting at column `at`.
+fn read_record(row: &rusqlite::Row<'_>, at: usize) -> rusqlite::Result<AnchorRecord> {
+    let size: i64 = row.get(at + 1)?;
+    let timestamp: i64 = row.get(at + 2)?;
+    let block_height: Option<i64> = row.get(at + 7)?;
+    let confirmations: Option<i64> = row.get(at + 8)?;
+    Ok(AnchorRecord {
+        root: row.get(at)?,
+        size: size as u64,
+        timestamp_nanos: timestamp.to_string(),
+        txid: row.get(at + 3)?,
+        version: row.get(at + 4)?,
+        metadata: AnchorMetadata {
+            backend: row.get(at + 5)?,
+            network: row.get(at + 6)?,
+            block_height: block_height.map(|h| h as u64),
+            confirmations: confirmations.map(|c| c as u64),
+            receipt_uri: row.get(at + 9)?,
+            signing_identity: row.get(at + 10)?,
+        },
+    })
+}
+
+fn read_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<StoredAnchor> {
+    let status: String = row.get(RECORD_COLUMN_COUNT)?;
+    let public_key: Option<String> = row.get(RECORD_COLUMN_COUNT + 1)?;
+    let signature: Option<String> = row.get(RECORD_COLUMN_COUNT + 2)?;
+    Ok(StoredAnchor {
+        record: read_record(row, 0)?,
+        status: AnchorStatus::parse(&status).unwrap_or(AnchorStatus::Failed),
+        signature: public_key
+            .zip(signature)
+            .map(|(public_key, signature)| DetachedSignature {
+                public_key,
+                signature,
+            }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(size: u64, root: &str, ts: i64) -> AnchorRecord {
+        AnchorRecord::new(root.into(), size, ts.to_string(), format!("tx-{size}-{ts}"))
+    }
+
+    #[test]
+    fn query_filters_by_root_time_and_status() {
+        let store = AnchorStore::in_memory().expect("store");
+        store
+            .insert(&record(1, "aa", 100), AnchorStatus::Confirmed)
+            .unwrap();
+        store
+            .insert(&record(2, "bb", 200), AnchorStatus::Pending)
+            .unwrap();
+        store
+            .insert(&record(3, "cc", 300), AnchorStatus::Confirmed)
+            .unwrap();
+
+        let by_root = store
+            .query(&AnchorQuery {
+                root: Some("BB".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(by_root.len(), 1);
+        assert_eq!(by_root[0].record.size, 2);
+
+        let by_time = store
+            .query(&AnchorQuery {
+                from_nanos: Some(150),
+                to_nanos: Some(300),
+                status: Some(AnchorStatus::Confirmed),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(by_time.len(), 1);
+        assert_eq!(by_time[0].record.root, "cc");
+
+        assert_eq!(store.latest().unwrap().unwrap().record.size, 3);
+    }
+
+    #[test]
+    fn queued_checkpoints_survive_a_restart() {
+        let path = std::env::temp_dir().join(format!("anchor-queue-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let root = |size, root: &str| RootResponse {
+            root: root.into(),
+            size,
+        };
+        let store = AnchorStore::open(&path).unwrap();
+        for (size, hash) in [(1, "aa"), (2, "bb"), (3, "cc"), (3, "cc")] {
+            store.queue("simulated", &root(size, hash), 8).unwrap();
+        }
+        drop(store);
+
+        let store = AnchorStore::open(&path).unwrap();
+        let queued = store.pending("simulated").unwrap();
+        assert_eq!(queued, [root(1, "aa"), root(2, "bb"), root(3, "cc")]);
+        assert!(store.pending("other").unwrap().is_empty());
+        // Past the cap the newest checkpoints win.
+        store.queue("simulated", &root(4, "dd"), 2).unwrap();
+        assert_eq!(
+            store.pending("simulated").unwrap(),
+            [root(3, "cc"), root(4, "dd")]
+        );
+        clear_pending(&store.conn, "simulated").unwrap();
+        drop(store);
+        assert!(AnchorStore::open(&path)
+            .unwrap()
+            .pending("simulated")
+            .unwrap()
+            .is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn check_root_detects_regression_and_fork() {
+        let store = AnchorStore::in_memory().expect("store");
+        store
+            .insert(&record(2, "aa", 1), AnchorStatus::Confirmed)
+            .unwrap();
+        store
+            .insert(&record(4, "bb", 2), AnchorStatus::Confirmed)
+            .unwrap();
+
+        let root = |size, root: &str| RootResponse {
+            root: root.into(),
+            size,
+        };
+        assert!(store.check_root(&root(5, "cc")).unwrap().is_none());
+        assert!(store.check_root(&root(4, "bb")).unwrap().is_none());
+
+        let regression = store.check_root(&root(3, "dd")).unwrap().unwrap();
+        assert_eq!(regression.kind, ConflictKind::Regression);
+        assert_eq!(regression.anchored.size, 4);
+
+        let fork = store.check_root(&root(4, "ee")).unwrap().unwrap();
+        assert_eq!(fork.kind, ConflictKind::Fork);
+        assert_eq!(fork.anchored.root, "bb");
+
+        assert!(store.record_conflict(&fork, 10).unwrap());
+        assert!(!store.record_conflict(&fork, 11).unwrap());
+        assert_eq!(store.conflicts().unwrap(), vec![fork]);
+    }
+
+    #[test]
+    fn forks_below_the_fetched_size_are_found_in_the_archive() {
+        let dir = std::env::temp_dir().join(format!("reality-fork-{}", std::process::id()));
+        let mut store = AnchorStore::in_memory().expect("store");
+        store.set_archive_dir(dir.clone());
+        for (size, root, ts) in [(2, "aa", 10), (4, "bb", 20), (6, "cc", 30)] {
+            store
+                .insert(&record(size, root, ts), AnchorStatus::Confirmed)
+                .unwrap();
+        }
+        assert_eq!(store.rotate(15, 100).unwrap(), 1);
+
+        // The log rewrote its first two entries and grew past every anchor.
+        let grown = RootResponse {
+            root: "ff".into(),
+            size: 8,
+        };
+        assert!(store.check_root(&grown).unwrap().is_none());
+        assert_eq!(
+            store.anchored_roots(grown.size).unwrap(),
+            [(2, "aa".into()), (4, "bb".into()), (6, "cc".into())]
+        );
+        assert!(store.fork_at(4, "BB").unwrap().is_none());
+
+        let fork = store.fork_at(2, "dd").unwrap().unwrap();
+        assert_eq!(fork.kind, ConflictKind::Fork);
+        assert_eq!((fork.observed_size, fork.observed_root.as_str()), (2, "dd"));
+        assert_eq!(fork.anchored.root, "aa");
+        assert!(store.record_conflict(&fork, 40).unwrap());
+        assert_eq!(store.conflicts().unwrap(), vec![fork]);
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn latest_cosigned_is_the_largest_checkpoint() {
+        use reality_core::{checkpoint::Checkpoint, signer::KeyFileSigner};
+
+        let store = AnchorStore::in_memory().expect("store");
+        assert!(store.latest_cosigned().unwrap().is_none());
+        let log = KeyFileSigner::from_seed([1u8; 32]);
+        let witness = KeyFileSigner::from_seed([2u8; 32]);
+        for size in [4, 9, 6] {
+            let checkpoint = Checkpoint {
+                size,
+                root: format!("{size:064x}"),
+                timestamp_nanos: size.to_string(),
+            };
+            let cosigned = checkpoint.clone().cosign(&witness).unwrap();
+            let signed = checkpoint.sign(&log).unwrap();
+            store.insert_cosigned(&signed, &cosigned.witness).unwrap();
+        }
+        let latest = store.latest_cosigned().unwrap().expect("cosigned");
+        assert_eq!(latest.checkpoint.size, 9);
+        assert!(latest.verify());
+    }
+
+    #[test]
+    fn migrates_v1_stores_in_place() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE anchors (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                root TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                timestamp_nanos INTEGER NOT NULL,
+                txid TEXT NOT NULL UNIQUE,
+                status TEXT NOT NULL
+            );
+            INSERT INTO anchors (root, size, timestamp_nanos, txid, status)
+            VALUES ('aa', 1, 100, 'old', 'confirmed');",
+        )
+        .unwrap();
+        let store = AnchorStore::init(conn).expect("migrated store");
+
+        let mut v2 = record(2, "bb", 200);
+        v2.metadata.backend = Some("simulated".into());
+        v2.metadata.confirmations = Some(6);
+        store.insert(&v2, AnchorStatus::Confirmed).unwrap();
+
+        let all = store.query(&AnchorQuery::default()).unwrap();
+        assert_eq!(all[0].record.version, 1);
+        assert_eq!(all[0].record.metadata, AnchorMetadata::default());
+        assert_eq!(all[1].record, v2);
+    }
+
+    #[test]
+    fn rotation_archives_old_anchors_and_restores_on_demand() {
+        let dir = std::env::temp_dir().join(format!("reality-rotate-{}", std::process::id()));
+        let mut store = AnchorStore::in_memory().expect("store");
+        store.set_archive_dir(dir.clone());
+        for size in 1..=4 {
+            store
+                .insert(
+                    &record(size, &format!("{size:02x}"), size as i64 * 10),
+                    AnchorStatus::Confirmed,
+                )
+                .unwrap();
+        }
+
+        assert_eq!(store.rotate(25, 100).unwrap(), 2);
+        assert_eq!(store.count().unwrap(), 2);
+        store
+            .insert(&record(5, "05", 50), AnchorStatus::Confirmed)
+            .unwrap();
+        assert_eq!(store.rotate(35, 100).unwrap(), 1);
+        assert_eq!(store.segments().unwrap().len(), 2);
+        store.verify_archive().expect("chain");
+
+        let restored = store
+            .restore(&RestoreRequest {
+                txid: None,
+                root: Some("02".into()),
+            })
+            .unwrap();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].record.size, 2);
+        assert_eq!(store.count().unwrap(), 3);
+        // Restored anchors are not re-archived before the next period.
+        assert_eq!(store.rotate(35, 100).unwrap(), 0);
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn spend_accumulates_per_day_and_backend() {
+        let store = AnchorStore::in_memory().expect("store");
+        add_spend(&store.conn, "2026-01-01", "simulated", 5).unwrap();
+        add_spend(&store.conn, "2026-01-01", "simulated", 7).unwrap();
+        add_spend(&store.conn, "2026-01-02", "simulated", 1).unwrap();
+        assert_eq!(store.spent_on("2026-01-01", "simulated").unwrap(), 12);
+        assert_eq!(store.spent_on("2026-01-03", "simulated").unwrap(), 0);
+    }
+
+    #[test]
+    fn batch_receipts_verify() {
+        use reality_core::receipt::{simulated_txid, ReceiptContext};
+
+        let store = AnchorStore::in_memory().expect("store");
+        let checkpoints: Vec<RootResponse> = (1..=2)
+            .map(|size| RootResponse {
+                root: format!("{size:064x}"),
+                size,
+            })
+            .collect();
+        let (batch_root, proofs) = crate::budget::batch(&checkpoints);
+        let batch_txid = simulated_txid(2, &batch_root, "9");
+        for (i, (cp, proof)) in checkpoints.iter().zip(&proofs).enumerate() {
+            let txid = format!("{batch_txid}/{i}");
+            let record = AnchorRecord::new(cp.root.clone(), cp.size, "9".into(), txid.clone());
+            store.insert(&record, AnchorStatus::Confirmed).unwrap();
+            insert_batch_member(&store.conn, &txid, &batch_txid, &batch_root, proof).unwrap();
+        }
+
+        let receipt = store.receipt(&format!("{batch_txid}/1")).unwrap().unwrap();
+        assert_eq!(receipt.batch.as_ref().unwrap().batch_root, batch_root);
+        assert!(
+            receipt
+                .verify(&ReceiptContext::default())
+                .unwrap()
+                .txid_checked
+        );
+        assert!(store.receipt("missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn submissions_are_recorded_all_or_nothing() {
+        let store = AnchorStore::in_memory().expect("store");
+        let checkpoints: Vec<RootResponse> = (1..=2)
+            .map(|size| RootResponse {
+                root: format!("{size:064x}"),
+                size,
+            })
+            .collect();
+        for checkpoint in &checkpoints {
+            store.queue("simulated", checkpoint, 8).unwrap();
+        }
+        let (root, proofs) = crate::budget::batch(&checkpoints);
+        let mut submission = Submission {
+            records: checkpoints
+                .iter()
+                .enumerate()
+                .map(|(i, cp)| {
+                    AnchorRecord::new(cp.root.clone(), cp.size, "9".into(), format!("b/{i}"))
+                })
+                .collect(),
+            batch: Some(Batch {
+                txid: "b".into(),
+                root,
+                proofs,
+            }),
+        };
+
+        // The second record cannot be stored, so nothing is.
+        submission.records[1].timestamp_nanos = "soon".into();
+        assert!(store
+            .record_submission("simulated", "2026-01-01", 5, &submission)
+            .is_err());
+        assert_eq!(store.count().unwrap(), 0);
+        assert!(store.receipt("b/0").unwrap().is_none());
+        assert_eq!(store.spent_on("2026-01-01", "simulated").unwrap(), 0);
+        assert_eq!(store.pending("simulated").unwrap(), checkpoints);
+
+        submission.records[1].timestamp_nanos = "9".into();
+        store
+            .record_submission("simulated", "2026-01-01", 5, &submission)
+            .unwrap();
+        assert_eq!(store.count().unwrap(), 2);
+        assert_eq!(
+            store
+                .receipt("b/1")
+                .unwrap()
+                .unwrap()
+                .batch
+                .unwrap()
+                .batch_txid,
+            "b"
+        );
+        assert_eq!(store.spent_on("2026-01-01", "simulated").unwrap(), 5);
+        assert!(store.pending("simulated").unwrap().is_empty());
+    }
+
+    #[test]
+    fn legacy_import_runs_once() {
+        let mut store = AnchorStore::in_memory().expect("store");
+        let legacy = vec![record(1, "aa", 1), record(2, "bb", 2)];
+        assert_eq!(store.import_legacy(&legacy).unwrap(), 2);
+        assert_eq!(store.import_legacy(&legacy).unwrap(), 0);
+        assert_eq!(store.count().unwrap(), 2);
+    }
+}
@@ -0,0 +1,137 @@
+use anyhow::Context;
+use reality_core::{
+    checkpoint::{Checkpoint, SignedCheckpoint},
+    consistency::{verify_consistency, ConsistencyProof},
+};
+use tracing::{error, info};
+
+use crate::{alert::Alert, get_json, Anchorer};
+
+impl Anchorer {
+    /// Fetch the log's signed checkpoint, check that it extends the last one
+    /// this witness cosigned, and post a cosignature back to logd.
+    pub(crate) async fn witness_round(&mut self) -> anyhow::Result<()> {
+        let signed: SignedCheckpoint = self
+            .with_retry("fetch checkpoint", || {
+                get_json(
+                    &self.client,
+                    &self.config.api_url,
+                    "checkpoint",
+                    self.api_token.as_deref(),
+                )
+            })
+            .await
+            .context("fetch checkpoint")?;
+
+        let previous = self.store().latest_cosigned()?;
+        let log_key = self
+            .config
+            .witness
+            .log_public_key
+            .clone()
+            .or_else(|| previous.as_ref().map(|p| p.signature.public_key.clone()));
+        let signature_ok = match &log_key {
+            Some(key) => signed.verify_with(key),
+            None => signed.verify(),
+        };
+        anyhow::ensure!(signature_ok, "checkpoint is not signed by the log key");
+
+        if let Some(previous) = &previous {
+            if let Some(reason) = self.inconsistency(&previous.checkpoint, &signed).await? {
+                error!(
+                    previous_size = previous.checkpoint.size,
+                    previous_root = %previous.checkpoint.root,
+                    observed_size = signed.checkpoint.size,
+                    observed_root = %signed.checkpoint.root,
+                    %reason,
+                    "ALERT: log checkpoint is inconsistent with its cosigned history; refusing to cosign"
+                );
+                if self.last_inconsistent.as_ref() != Some(&signed.checkpoint) {
+                    self.last_inconsistent = Some(signed.checkpoint.clone());
+                    self.alerter
+                        .fire(&Alert::WitnessInconsistent {
+                            previous: previous.checkpoint.clone(),
+                            observed: signed.checkpoint.clone(),
+                            reason: reason.clone(),
+                        })
+                        .await;
+                }
+                anyhow::bail!("refused to cosign: {reason}");
+            }
+        }
+
+        let witness = self.signer.clone().context("witness mode needs a signer")?;
+        let checkpoint = signed.checkpoint.clone();
+        let cosigned = tokio::task::spawn_blocking(move || checkpoint.cosign(witness.as_ref()))
+            .await?
+            .context("cosign checkpoint")?;
+        self.store().insert_cosigned(&signed, &cosigned.witness)?;
+
+        let url = format!("{}/witness", self.config.api_url.trim_end_matches('/'));
+        self.with_retry("post cosignature", || async {
+            let mut req = self.client.post(&url).json(&cosigned);
+            if let Some(token) = &self.api_token {
+                req = req.bearer_auth(token);
+            }
+            req.send().await?.error_for_status()?;
+            Ok(())
+        })
+        .await
+        .context("post cosignature")?;
+
+        self.status
+            .lock()
+            .expect("status poisoned")
+            .cosigned(&signed.checkpoint);
+        info!(
+            size = signed.checkpoint.size,
+            root = %signed.checkpoint.root,
+            "cosigned checkpoint"
+        );
+        Ok(())
+    }
+
+    /// Why `observed` does not extend `previous`, if it does not. Transport
+    /// errors are returned as errors, not inconsistencies.
+    async fn inconsistency(
+        &self,
+        previous: &Checkpoint,
+        observed: &SignedCheckpoint,
+    ) -> anyhow::Result<Option<String>> {
+        let observed = &observed.checkpoint;
+        if observed.size < previous.size {
+            return Ok(Some(format!(
+                "log shrank from {} to {} leaves",
+                previous.size, observed.size
+            )));
+        }
+        if observed.size == previous.size {
+            return Ok((!observed.root.eq_ignore_ascii_case(&previous.root))
+                .then(|| format!("root changed at size {}", observed.size)));
+        }
+
+        let path = format!("consistency?old={}&new={}", previous.size, observed.size);
+        let proof: ConsistencyProof = self
+            .with_retry("fetch consistency proof", || {
+                get_json(
+                    &self.client,
+                    &self.config.api_url,
+                    &path,
+                    self.api_token.as_deref(),
+                )
+            })
+            .await
+            .context("fetch consistency proof")?;
+        let matches = proof.old_size == previous.size
+            && proof.new_size == observed.size
+            && proof.old_root.eq_ignore_ascii_case(&previous.root)
+            && proof.new_root.eq_ignore_ascii_case(&observed.root);
+        if !matches || !verify_consistency(&proof) {
+            return Ok(Some(format!(
+                "no valid consistency proof from size {} to {}",
+                previous.size, observed.size
+            )));
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,384 @@
+//! Axum integration that admits only data provably in a RealityLog.
+//!
+//! A client sends the entry's payload as the request body, and its receipt
+//! in the `RealityLog-Receipt` header: the inclusion proof and signed
+//! checkpoint from logd's `/bundle/N`, as base64 JSON (see
+//! [`Receipt::header_value`]). A request is admitted when the body hashes
+//! to the proof's leaf, the proof leads to the checkpoint's root, and the
+//! checkpoint is signed by one of the [`ReceiptPolicy`]'s log keys.
+//!
+//! [`VerifiedEntry<T>`] checks one handler's request and parses the payload
+//! as JSON. [`ReceiptLayer`] checks every request to the routes it wraps
+//! and leaves the body for the handler.
+//!
+//! ```no_run
+//! use axum::{routing::post, Router};
+//! use reality_axum::{ReceiptPolicy, VerifiedEntry};
+//!
+//! async fn ingest(entry: VerifiedEntry<serde_json::Value>) -> String {
+//!     format!("entry {} is in the log", entry.receipt.proof.index)
+//! }
+//!
+//! let app: Router = Router::new()
+//!     .route("/ingest", post(ingest))
+//!     .with_state(ReceiptPolicy::new("<hex log key>"));
+//! ```
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use axum::{
+    async_trait,
+    body::{Body, Bytes},
+    extract::{FromRef, FromRequest, Request},
+    http::{HeaderMap, HeaderName, StatusCode},
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reality_core::{
+    bundle::{BundleError, EntryBundle},
+    checkpoint::SignedCheckpoint,
+    leaf::LeafMeta,
+    InclusionProof,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
+
+/// The header a request carries its receipt in.
+pub const RECEIPT_HEADER: HeaderName = HeaderName::from_static("realitylog-receipt");
+
+/// Largest body read by default, as for axum's own extractors.
+const DEFAULT_MAX_BODY: usize = 2 * 1024 * 1024;
+
+/// An entry's receipt without its payload, which travels as the body.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Receipt {
+    pub proof: InclusionProof,
+    pub checkpoint: SignedCheckpoint,
+    /// Set when the entry has a v2 leaf.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<LeafMeta>,
+}
+
+impl Receipt {
+    /// The `RealityLog-Receipt` header value for this receipt.
+    pub fn header_value(&self) -> String {
+        STANDARD.encode(serde_json::to_vec(self).expect("receipts serialize"))
+    }
+
+    fn from_headers(headers: &HeaderMap) -> Result<Self, Rejection> {
+        let value = headers
+            .get(RECEIPT_HEADER)
+            .ok_or(Rejection::MissingReceipt)?;
+        let json = STANDARD
+            .decode(value.as_bytes())
+            .map_err(|err| Rejection::MalformedReceipt(err.to_string()))?;
+        serde_json::from_slice(&json).map_err(|err| Rejection::MalformedReceipt(err.to_string()))
+    }
+}
+
+impl From<EntryBundle> for Receipt {
+    fn from(bundle: EntryBundle) -> Self {
+        Self {
+            proof: bundle.proof,
+            checkpoint: bundle.checkpoint,
+            meta: bundle.meta,
+        }
+    }
+}
+
+/// Which receipts are accepted: those whose checkpoint is signed by one of
+/// the log keys.
+#[derive(Debug, Clone)]
+pub struct ReceiptPolicy {
+    keys: Arc<Vec<String>>,
+    max_body: usize,
+}
+
+impl ReceiptPolicy {
+    /// Accept receipts from the log with hex public key `log_key`.
+    pub fn new(log_key: impl Into<String>) -> Self {
+        Self {
+            keys: Arc::new(vec![log_key.into().to_ascii_lowercase()]),
+            max_body: DEFAULT_MAX_BODY,
+        }
+    }
+
+    /// Also accept receipts from the log with hex public key `log_key`.
+    pub fn with_key(mut self, log_key: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.keys).push(log_key.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Refuse bodies larger than `bytes` (2 MiB by default).
+    pub fn max_body(mut self, bytes: usize) -> Self {
+        self.max_body = bytes;
+        self
+    }
+
+    /// Check that `receipt` proves `body` is in one of the trusted logs.
+    pub fn check(&self, body: &[u8], receipt: &Receipt) -> Result<(), Rejection> {
+        let payload = std::str::from_utf8(body).map_err(|_| Rejection::NotUtf8)?;
+        let bundle = EntryBundle {
+            payload: payload.to_string(),
+            meta: receipt.meta.clone(),
+            proof: receipt.proof.clone(),
+            checkpoint: receipt.checkpoint.clone(),
+        };
+        bundle.verify(None).map_err(Rejection::Invalid)?;
+        let key = receipt.checkpoint.signature.public_key.to_ascii_lowercase();
+        if !self.keys.contains(&key) {
+            return Err(Rejection::Invalid(BundleError::UntrustedKey(key)));
+        }
+        Ok(())
+    }
+
+    /// Read and check a whole request, returning its body and receipt.
+    async fn admit(&self, headers: &HeaderMap, body: Body) -> Result<(Bytes, Receipt), Rejection> {
+        let receipt = Receipt::from_headers(headers)?;
+        let body = axum::body::to_bytes(body, self.max_body)
+            .await
+            .map_err(|_| Rejection::TooLarge(self.max_body))?;
+        self.check(&body, &receipt)?;
+        Ok((body, receipt))
+    }
+}
+
+/// Why a request was refused.
+#[derive(Debug, Error)]
+pub enum Rejection {
+    #[error("the request carries no RealityLog-Receipt header")]
+    MissingReceipt,
+    #[error("the RealityLog-Receipt header is not base64 JSON: {0}")]
+    MalformedReceipt(String),
+    #[error("the body is unreadable or larger than {0} bytes")]
+    TooLarge(usize),
+    #[error("the body is not UTF-8, so it cannot be a log entry")]
+    NotUtf8,
+    #[error("the receipt does not prove the body is in the log: {0}")]
+    Invalid(BundleError),
+    #[error("the entry is not valid JSON for this route: {0}")]
+    Payload(String),
+}
+
+impl IntoResponse for Rejection {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Rejection::MissingReceipt | Rejection::Invalid(_) => StatusCode::FORBIDDEN,
+            Rejection::TooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Rejection::Payload(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Rejection::MalformedReceipt(_) | Rejection::NotUtf8 => StatusCode::BAD_REQUEST,
+        };
+        (status, self.to_string()).into_response()
+    }
+}
+
+/// A request body proven to be a log entry, parsed as JSON into `T`.
+#[derive(Debug, Clone)]
+pub struct VerifiedEntry<T> {
+    pub payload: T,
+    pub receipt: Receipt,
+}
+
+#[async_trait]
+impl<T, S> FromRequest<S> for VerifiedEntry<T>
+where
+    T: DeserializeOwned,
+    ReceiptPolicy: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Rejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let policy = ReceiptPolicy::from_ref(state);
+        let (parts, body) = req.into_parts();
+        let (body, receipt) = policy.admit(&parts.headers, body).await?;
+        let payload =
+            serde_json::from_slice(&body).map_err(|err| Rejection::Payload(err.to_string()))?;
+        Ok(Self { payload, receipt })
+    }
+}
+
+/// Refuses every request without a valid receipt for its body. Admitted
+/// requests reach the inner service with their body intact and the
+/// verified [`Receipt`] in their extensions.
+#[derive(Debug, Clone)]
+pub struct ReceiptLayer {
+    policy: ReceiptPolicy,
+}
+
+impl ReceiptLayer {
+    pub fn new(policy: ReceiptPolicy) -> Self {
+        Self { policy }
+    }
+}
+
+impl<S> tower_layer::Layer<S> for ReceiptLayer {
+    type Service = ReceiptService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ReceiptService {
+            inner,
+            policy: self.policy.clone(),
+        }
+    }
+}
+
+/// The service [`ReceiptLayer`] wraps routes in.
+#[derive(Debug, Clone)]
+pub struct ReceiptService<S> {
+    inner: S,
+    policy: ReceiptPolicy,
+}
+
+impl<S> tower_service::Service<Request> for ReceiptService<S>
+where
+    S: tower_service::Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        // Call the instance that was polled ready, and leave a fresh clone
+        // for the next request.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let policy = self.policy.clone();
+        Box::pin(async move {
+            let (mut parts, body) = req.into_parts();
+            let (body, receipt) = match policy.admit(&parts.headers, body).await {
+                Ok(admitted) => admitted,
+                Err(rejection) => return Ok(rejection.into_response()),
+            };
+            parts.extensions.insert(receipt);
+            inner
+                .call(Request::from_parts(parts, Body::from(body)))
+                .await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::post, Extension, Router};
+    use reality_core::{
+        checkpoint::Checkpoint,
+        leaf_hash, make_proof,
+        signer::{KeyFileSigner, Signer},
+    };
+    use tower_layer::Layer;
+    use tower_service::Service;
+
+    fn receipt(payloads: &[&str], index: usize, seed: u8) -> Receipt {
+        let leaves: Vec<_> = payloads.iter().map(|p| leaf_hash(p.as_bytes())).collect();
+        let proof = make_proof(&leaves, index).unwrap();
+        let checkpoint = Checkpoint {
+            size: proof.size,
+            root: proof.root.clone(),
+            timestamp_nanos: "1700000000000000000".into(),
+        }
+        .sign(&KeyFileSigner::from_seed([seed; 32]))
+        .unwrap();
+        Receipt {
+            proof,
+            checkpoint,
+            meta: None,
+        }
+    }
+
+    fn request(body: &str, receipt: Option<&Receipt>) -> Request {
+        let mut builder = Request::post("/ingest");
+        if let Some(receipt) = receipt {
+            builder = builder.header(RECEIPT_HEADER, receipt.header_value());
+        }
+        builder.body(Body::from(body.to_string())).unwrap()
+    }
+
+    async fn call<S>(app: &mut S, req: Request) -> (StatusCode, String)
+    where
+        S: Service<Request, Response = Response>,
+        S::Error: std::fmt::Debug,
+    {
+        let response = app.call(req).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn admits_only_payloads_with_valid_receipts() {
+        let key = hex::encode(KeyFileSigner::from_seed([3u8; 32]).public_key());
+        let policy = ReceiptPolicy::new(key.to_uppercase());
+        let payloads = [r#"{"n":0}"#, r#"{"n":1}"#, "not json"];
+        let good = receipt(&payloads, 1, 3);
+
+        async fn ingest(entry: VerifiedEntry<serde_json::Value>) -> String {
+            format!("{} {}", entry.payload["n"], entry.receipt.proof.index)
+        }
+        let mut app = Router::new()
+            .route("/ingest", post(ingest))
+            .with_state(policy.clone());
+        assert_eq!(
+            call(&mut app, request(payloads[1], Some(&good))).await,
+            (StatusCode::OK, "1 1".to_string())
+        );
+        let cases = [
+            (request(payloads[0], Some(&good)), StatusCode::FORBIDDEN),
+            (request(payloads[1], None), StatusCode::FORBIDDEN),
+            (
+                request(payloads[1], Some(&receipt(&payloads, 1, 4))),
+                StatusCode::FORBIDDEN,
+            ),
+            (
+                request(payloads[2], Some(&receipt(&payloads, 2, 3))),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            ),
+        ];
+        for (req, status) in cases {
+            assert_eq!(call(&mut app, req).await.0, status);
+        }
+        let garbled = Request::post("/ingest")
+            .header(RECEIPT_HEADER, "%%%")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(call(&mut app, garbled).await.0, StatusCode::BAD_REQUEST);
+
+        async fn raw(Extension(receipt): Extension<Receipt>, body: String) -> String {
+            format!("{} {body}", receipt.proof.index)
+        }
+        let mut layered = ReceiptLayer::new(policy.clone().max_body(16))
+            .layer(Router::new().route("/ingest", post(raw)));
+        let last = receipt(&payloads, 2, 3);
+        assert_eq!(
+            call(&mut layered, request(payloads[2], Some(&last))).await,
+            (StatusCode::OK, "2 not json".to_string())
+        );
+        assert_eq!(
+            call(&mut layered, request(payloads[1], Some(&last)))
+                .await
+                .0,
+            StatusCode::FORBIDDEN
+        );
+        let long = "x".repeat(17);
+        let long_receipt = receipt(&[&long], 0, 3);
+        assert_eq!(
+            call(&mut layered, request(&long, Some(&long_receipt)))
+                .await
+                .0,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+}
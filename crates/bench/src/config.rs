@@ -0,0 +1,280 @@
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::Context;
+use clap::Parser;
+use reality_config::Layered;
+use serde::Deserialize;
+
+use crate::workload::Mix;
+
+/// Largest batch logd accepts in one `/append/batch` request.
+pub const MAX_BATCH: usize = 1000;
+
+/// Command-line flags. Anything left unset falls back to the config file and
+/// then to defaults.
+#[derive(Debug, Parser)]
+#[command(
+    name = "reality-bench",
+    version,
+    about = "Load generator that measures logd's latency and throughput"
+)]
+pub struct Cli {
+    /// Path to a TOML config file.
+    #[arg(long, env = "REALITY_BENCH_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// logd instance to load, as `URL` or `NAME=URL`. Repeat it to run the
+    /// same workload against each in turn and compare them.
+    #[arg(long = "target", value_name = "[NAME=]URL")]
+    pub targets: Vec<String>,
+    /// Bearer token sent with every request.
+    #[arg(long, env = "REALITY_BENCH_TOKEN")]
+    pub token: Option<String>,
+    /// Operations to run, with optional weights, such as `append` or
+    /// `append=1,prove=8,verify=1`. Operations are `append`, `batch`,
+    /// `prove` and `verify`.
+    #[arg(long)]
+    pub workload: Option<String>,
+    /// Requests in flight at once.
+    #[arg(long)]
+    pub concurrency: Option<usize>,
+    /// Seconds to measure for, per target.
+    #[arg(long)]
+    pub duration_secs: Option<u64>,
+    /// Seconds of load before measuring starts.
+    #[arg(long)]
+    pub warmup_secs: Option<u64>,
+    /// Bytes in each appended payload.
+    #[arg(long)]
+    pub payload_size: Option<usize>,
+    /// Draw payload sizes uniformly from `--payload-size` up to this.
+    #[arg(long)]
+    pub payload_size_max: Option<usize>,
+    /// Entries in each `batch` request.
+    #[arg(long)]
+    pub batch_size: Option<usize>,
+    /// Entries appended to each target before the run, so `prove` and
+    /// `verify` have something to read.
+    #[arg(long)]
+    pub prefill: Option<u64>,
+    /// Seed for operation choice, payloads and indices; every target gets
+    /// the same sequence.
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Print the results as JSON.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Fully resolved benchmark configuration.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub targets: Vec<Target>,
+    pub token: Option<String>,
+    pub workload: String,
+    pub concurrency: usize,
+    pub duration_secs: u64,
+    pub warmup_secs: u64,
+    pub payload_size: usize,
+    pub payload_size_max: Option<usize>,
+    pub batch_size: usize,
+    pub prefill: u64,
+    pub seed: u64,
+    pub json: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Target {
+    pub name: String,
+    pub url: String,
+    /// Overrides the global token for this target.
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            targets: vec![Target {
+                name: "logd".into(),
+                url: "http://127.0.0.1:8080".into(),
+                token: None,
+            }],
+            token: None,
+            workload: "append".into(),
+            concurrency: 16,
+            duration_secs: 10,
+            warmup_secs: 0,
+            payload_size: 256,
+            payload_size_max: None,
+            batch_size: 100,
+            prefill: 0,
+            seed: 1,
+            json: false,
+        }
+    }
+}
+
+impl Layered<Cli> for Config {
+    fn file(cli: &Cli) -> Option<PathBuf> {
+        cli.config.clone()
+    }
+
+    fn apply(&mut self, cli: Cli) -> anyhow::Result<()> {
+        self.json |= cli.json;
+        if !cli.targets.is_empty() {
+            self.targets = cli
+                .targets
+                .iter()
+                .map(|target| parse_target(target))
+                .collect::<anyhow::Result<_>>()?;
+        }
+        if let Some(token) = cli.token {
+            self.token = Some(token);
+        }
+        if let Some(workload) = cli.workload {
+            self.workload = workload;
+        }
+        if let Some(concurrency) = cli.concurrency {
+            self.concurrency = concurrency;
+        }
+        if let Some(duration_secs) = cli.duration_secs {
+            self.duration_secs = duration_secs;
+        }
+        if let Some(warmup_secs) = cli.warmup_secs {
+            self.warmup_secs = warmup_secs;
+        }
+        if let Some(payload_size) = cli.payload_size {
+            self.payload_size = payload_size;
+        }
+        if let Some(payload_size_max) = cli.payload_size_max {
+            self.payload_size_max = Some(payload_size_max);
+        }
+        if let Some(batch_size) = cli.batch_size {
+            self.batch_size = batch_size;
+        }
+        if let Some(prefill) = cli.prefill {
+            self.prefill = prefill;
+        }
+        if let Some(seed) = cli.seed {
+            self.seed = seed;
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(!self.targets.is_empty(), "at least one target is required");
+        for (i, target) in self.targets.iter().enumerate() {
+            anyhow::ensure!(!target.url.is_empty(), "target {i} has no url");
+            anyhow::ensure!(
+                !self.targets[..i].iter().any(|t| t.name == target.name),
+                "target name {:?} is used twice",
+                target.name
+            );
+        }
+        self.mix()?;
+        anyhow::ensure!(self.concurrency > 0, "concurrency must be positive");
+        anyhow::ensure!(self.duration_secs > 0, "duration_secs must be positive");
+        anyhow::ensure!(self.payload_size > 0, "payload_size must be positive");
+        if let Some(max) = self.payload_size_max {
+            anyhow::ensure!(
+                max >= self.payload_size,
+                "payload_size_max must be at least payload_size"
+            );
+        }
+        anyhow::ensure!(
+            (1..=MAX_BATCH).contains(&self.batch_size),
+            "batch_size must be between 1 and {MAX_BATCH}"
+        );
+        Ok(())
+    }
+}
+
+impl Config {
+    pub fn mix(&self) -> anyhow::Result<Mix> {
+        self.workload
+            .parse()
+            .map_err(anyhow::Error::msg)
+            .with_context(|| format!("invalid workload {:?}", self.workload))
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.duration_secs)
+    }
+
+    pub fn warmup(&self) -> Duration {
+        Duration::from_secs(self.warmup_secs)
+    }
+
+    /// Smallest and largest payload, in bytes.
+    pub fn payload_sizes(&self) -> (usize, usize) {
+        (
+            self.payload_size,
+            self.payload_size_max.unwrap_or(self.payload_size),
+        )
+    }
+}
+
+/// `NAME=URL`, or a bare URL that names itself.
+fn parse_target(raw: &str) -> anyhow::Result<Target> {
+    let (name, url) = match raw.split_once('=') {
+        Some((name, url)) if !name.contains("://") => (name, url),
+        _ => (raw, raw),
+    };
+    anyhow::ensure!(!name.is_empty(), "target {raw:?} has an empty name");
+    Ok(Target {
+        name: name.into(),
+        url: url.trim_end_matches('/').into(),
+        token: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_replace_the_configured_targets() {
+        let mut config: Config = toml::from_str(
+            r#"
+            workload = "prove"
+            payload_size = 64
+
+            [[targets]]
+            name = "disk"
+            url = "http://10.0.0.1:8080"
+            token = "secret"
+            "#,
+        )
+        .expect("config");
+        assert!(config.validate().is_ok());
+        assert_eq!(config.targets[0].token.as_deref(), Some("secret"));
+
+        config
+            .apply(Cli::parse_from([
+                "reality-bench",
+                "--target",
+                "disk=http://127.0.0.1:8080/",
+                "--target",
+                "http://127.0.0.1:8081",
+                "--payload-size-max",
+                "1024",
+            ]))
+            .unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.targets[0].name, "disk");
+        assert_eq!(config.targets[0].url, "http://127.0.0.1:8080");
+        assert_eq!(config.targets[1].name, "http://127.0.0.1:8081");
+        assert_eq!(config.payload_sizes(), (64, 1024));
+
+        config.targets[1].name = "disk".into();
+        assert!(config.validate().is_err());
+        config.targets.pop();
+        config.workload = "append=1,scan=2".into();
+        assert!(config.validate().is_err());
+        config.workload = "batch".into();
+        config.batch_size = MAX_BATCH + 1;
+        assert!(config.validate().is_err());
+    }
+}
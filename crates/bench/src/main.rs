@@ -0,0 +1,161 @@
+//! `reality-bench`: drives append, proof and verify workloads against logd
+//! and reports latency percentiles and throughput.
+//!
+//! Each target gets the same run: the same workers, seed and payloads, one
+//! target after another. logd has one storage engine, so storage setups are
+//! compared by pointing targets at instances whose data directories sit on
+//! different disks or filesystems. Appends are real; point it at a scratch
+//! log.
+
+mod config;
+mod stats;
+mod workload;
+
+use std::{sync::Arc, time::Instant};
+
+use anyhow::Context;
+use clap::Parser;
+use reality_client::{Client, RetryPolicy};
+use reality_config::Layered;
+use reality_core::{BatchEntry, VerifyRequest};
+use tracing::info;
+
+use crate::{
+    config::{Cli, Config, Target, MAX_BATCH},
+    stats::{Recorder, TargetReport},
+    workload::{Mix, Op, Rng, Worker},
+};
+
+/// Proofs fetched for the `verify` workload to send.
+const VERIFY_POOL: u64 = 64;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .init();
+
+    let config = Config::load(Cli::parse())?;
+    let mix = Arc::new(config.mix()?);
+    let mut reports = Vec::new();
+    for target in &config.targets {
+        let report = run(&config, target, mix.clone())
+            .await
+            .with_context(|| format!("benchmark {}", target.name))?;
+        reports.push(report);
+    }
+
+    if config.json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        print!("{}", stats::render(&reports));
+    }
+    Ok(())
+}
+
+async fn run(config: &Config, target: &Target, mix: Arc<Mix>) -> anyhow::Result<TargetReport> {
+    let mut builder = Client::builder(&target.url).retry(RetryPolicy::none());
+    if let Some(token) = target.token.as_ref().or(config.token.as_ref()) {
+        builder = builder.token(token);
+    }
+    let client = builder.build()?;
+
+    let mut rng = Rng::new(config.seed);
+    prefill(&client, &mut rng, config).await?;
+    let size_before = client
+        .root()
+        .await
+        .with_context(|| format!("read the tree head of {}", target.url))?
+        .size;
+    anyhow::ensure!(
+        size_before > 0 || !mix.reads(),
+        "{} is empty, so there is nothing to prove; pass --prefill",
+        target.url
+    );
+    let proofs = Arc::new(if mix.ops().any(|op| op == Op::Verify) {
+        verify_pool(&client, &mut rng, size_before).await?
+    } else {
+        Vec::new()
+    });
+
+    info!(
+        target = %target.name,
+        workload = %config.workload,
+        concurrency = config.concurrency,
+        "running"
+    );
+    let measure_from = Instant::now() + config.warmup();
+    let end = measure_from + config.duration();
+    let workers: Vec<_> = (0..config.concurrency)
+        .map(|i| {
+            let worker = Worker {
+                client: client.clone(),
+                mix: mix.clone(),
+                rng: Rng::new(config.seed.wrapping_add(1 + i as u64)),
+                payload_sizes: config.payload_sizes(),
+                batch_size: config.batch_size,
+                log_size: size_before,
+                proofs: proofs.clone(),
+            };
+            tokio::spawn(worker.run(measure_from, end))
+        })
+        .collect();
+    let mut recorder = Recorder::default();
+    for worker in workers {
+        recorder.merge(worker.await?);
+    }
+    let measured = Instant::now().saturating_duration_since(measure_from);
+
+    let size_after = client.root().await?.size;
+    Ok(TargetReport {
+        name: target.name.clone(),
+        url: target.url.clone(),
+        concurrency: config.concurrency,
+        measured_secs: measured.as_secs_f64(),
+        size_before,
+        size_after,
+        ops: recorder.report(measured, config.batch_size),
+    })
+}
+
+/// Append `config.prefill` entries in full batches.
+async fn prefill(client: &Client, rng: &mut Rng, config: &Config) -> anyhow::Result<()> {
+    if config.prefill == 0 {
+        return Ok(());
+    }
+    info!(entries = config.prefill, url = %client.base_url(), "prefilling");
+    let started = Instant::now();
+    let mut left = config.prefill;
+    while left > 0 {
+        let count = left.min(MAX_BATCH as u64);
+        let entries = (0..count)
+            .map(|_| BatchEntry {
+                payload: rng.payload(config.payload_sizes()),
+                idempotency_key: None,
+                labels: Default::default(),
+                signature: None,
+            })
+            .collect();
+        client.append_batch(entries).await.context("prefill")?;
+        left -= count;
+    }
+    info!(
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "prefilled"
+    );
+    Ok(())
+}
+
+/// Proofs for up to `VERIFY_POOL` random entries of the first `size`.
+async fn verify_pool(
+    client: &Client,
+    rng: &mut Rng,
+    size: u64,
+) -> anyhow::Result<Vec<VerifyRequest>> {
+    let mut proofs = Vec::new();
+    for _ in 0..size.min(VERIFY_POOL) {
+        let proof = client.prove(rng.below(size)).await?;
+        proofs.push(VerifyRequest::from(&proof));
+    }
+    Ok(proofs)
+}
@@ -0,0 +1,293 @@
+//! Latency samples and the reports made from them.
+
+use std::{collections::BTreeMap, fmt::Write, time::Duration};
+
+use serde::Serialize;
+
+use crate::workload::Op;
+
+/// What one worker saw, per operation.
+#[derive(Debug, Default)]
+pub struct Recorder {
+    ops: BTreeMap<Op, Samples>,
+}
+
+#[derive(Debug, Default)]
+struct Samples {
+    /// Latencies of the requests that succeeded, in microseconds.
+    micros: Vec<u64>,
+    errors: u64,
+    first_error: Option<String>,
+}
+
+impl Recorder {
+    pub fn record(&mut self, op: Op, elapsed: Duration, result: anyhow::Result<()>) {
+        let samples = self.ops.entry(op).or_default();
+        match result {
+            Ok(()) => samples
+                .micros
+                .push(u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX)),
+            Err(err) => {
+                samples.errors += 1;
+                samples
+                    .first_error
+                    .get_or_insert_with(|| format!("{err:#}"));
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: Recorder) {
+        for (op, theirs) in other.ops {
+            let ours = self.ops.entry(op).or_default();
+            ours.micros.extend(theirs.micros);
+            ours.errors += theirs.errors;
+            if ours.first_error.is_none() {
+                ours.first_error = theirs.first_error;
+            }
+        }
+    }
+
+    /// One report per operation over `measured` of wall time. `batch_size`
+    /// turns batch requests into entries.
+    pub fn report(self, measured: Duration, batch_size: usize) -> Vec<OpReport> {
+        let secs = measured.as_secs_f64().max(f64::EPSILON);
+        self.ops
+            .into_iter()
+            .map(|(op, mut samples)| {
+                samples.micros.sort_unstable();
+                let count = samples.micros.len() as u64;
+                let entries = match op {
+                    Op::Append => Some(count),
+                    Op::Batch => Some(count * batch_size as u64),
+                    Op::Prove | Op::Verify => None,
+                };
+                OpReport {
+                    op,
+                    count,
+                    errors: samples.errors,
+                    first_error: samples.first_error,
+                    ops_per_sec: count as f64 / secs,
+                    entries_per_sec: entries.map(|entries| entries as f64 / secs),
+                    latency_ms: Latency::of(&samples.micros),
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OpReport {
+    pub op: Op,
+    /// Requests that succeeded.
+    pub count: u64,
+    pub errors: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_error: Option<String>,
+    pub ops_per_sec: f64,
+    /// Entries appended per second, for `append` and `batch`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entries_per_sec: Option<f64>,
+    pub latency_ms: Latency,
+}
+
+/// Latencies of successful requests, in milliseconds. All zero when none
+/// succeeded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Latency {
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub p999: f64,
+    pub max: f64,
+}
+
+impl Latency {
+    /// Nearest-rank percentiles of `sorted` microsecond samples.
+    fn of(sorted: &[u64]) -> Self {
+        let Some(&max) = sorted.last() else {
+            return Self::default();
+        };
+        let ms = |micros: u64| micros as f64 / 1000.0;
+        // `per_mille` of the samples are at most the result.
+        let rank = |per_mille: usize| {
+            let rank = (per_mille * sorted.len()).div_ceil(1000);
+            ms(sorted[rank.clamp(1, sorted.len()) - 1])
+        };
+        let total: u128 = sorted.iter().map(|&micros| u128::from(micros)).sum();
+        Self {
+            mean: total as f64 / sorted.len() as f64 / 1000.0,
+            p50: rank(500),
+            p90: rank(900),
+            p99: rank(990),
+            p999: rank(999),
+            max: ms(max),
+        }
+    }
+}
+
+/// The results of one target.
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetReport {
+    pub name: String,
+    pub url: String,
+    pub concurrency: usize,
+    pub measured_secs: f64,
+    /// Log size after any prefill, before the run.
+    pub size_before: u64,
+    pub size_after: u64,
+    pub ops: Vec<OpReport>,
+}
+
+impl TargetReport {
+    fn op(&self, op: Op) -> Option<&OpReport> {
+        self.ops.iter().find(|report| report.op == op)
+    }
+}
+
+pub fn render(reports: &[TargetReport]) -> String {
+    let mut out = String::new();
+    for report in reports {
+        let _ = writeln!(
+            out,
+            "{}  {}\n{} workers, {:.1} s measured, log grew from {} to {} entries",
+            report.name,
+            report.url,
+            report.concurrency,
+            report.measured_secs,
+            report.size_before,
+            report.size_after
+        );
+        let _ = writeln!(
+            out,
+            "  {:<8} {:>9} {:>7} {:>10} {:>10} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8}",
+            "op",
+            "count",
+            "errors",
+            "ops/s",
+            "entries/s",
+            "mean",
+            "p50",
+            "p90",
+            "p99",
+            "p99.9",
+            "max"
+        );
+        for op in &report.ops {
+            let entries = op
+                .entries_per_sec
+                .map_or_else(|| "-".to_string(), |rate| format!("{rate:.1}"));
+            let l = op.latency_ms;
+            let _ = writeln!(
+                out,
+                "  {:<8} {:>9} {:>7} {:>10.1} {:>10} {:>8.2} {:>8.2} {:>8.2} {:>8.2} {:>8.2} {:>8.2}",
+                op.op, op.count, op.errors, op.ops_per_sec, entries, l.mean, l.p50, l.p90, l.p99, l.p999, l.max
+            );
+        }
+        for op in report.ops.iter().filter(|op| op.errors > 0) {
+            if let Some(err) = &op.first_error {
+                let _ = writeln!(out, "  first {} error: {err}", op.op);
+            }
+        }
+        let _ = writeln!(out, "  latencies in ms\n");
+    }
+    if reports.len() > 1 {
+        out += &compare(reports);
+    }
+    out
+}
+
+/// A comparison row's label and the value it shows.
+type Row = (&'static str, fn(&OpReport) -> f64);
+
+/// Throughput and p99 latency of every target side by side, relative to
+/// the first.
+fn compare(reports: &[TargetReport]) -> String {
+    let mut ops: Vec<Op> = reports
+        .iter()
+        .flat_map(|report| report.ops.iter().map(|op| op.op))
+        .collect();
+    ops.sort_unstable();
+    ops.dedup();
+    let base = &reports[0];
+
+    let mut out = format!("compared with {}\n  {:<14}", base.name, "");
+    for report in reports {
+        let _ = write!(out, " {:>20}", report.name);
+    }
+    out.push('\n');
+    let rows: [Row; 2] = [
+        ("ops/s", |r| r.ops_per_sec),
+        ("p99 ms", |r| r.latency_ms.p99),
+    ];
+    for op in ops {
+        for (label, value) in rows {
+            let _ = write!(out, "  {:<14}", format!("{op} {label}"));
+            let base = base.op(op).map(value);
+            for report in reports {
+                let cell = match report.op(op).map(value) {
+                    Some(v) => match base.filter(|&base| base > 0.0) {
+                        Some(base) => format!("{v:.2} ({:.2}x)", v / base),
+                        None => format!("{v:.2}"),
+                    },
+                    None => "-".into(),
+                };
+                let _ = write!(out, " {cell:>20}");
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_percentiles_and_compares_targets() {
+        let mut recorder = Recorder::default();
+        for micros in 1..=1000 {
+            recorder.record(Op::Prove, Duration::from_micros(micros), Ok(()));
+        }
+        let mut other = Recorder::default();
+        other.record(Op::Batch, Duration::from_millis(5), Ok(()));
+        other.record(Op::Batch, Duration::ZERO, Err(anyhow::anyhow!("429")));
+        recorder.merge(other);
+
+        let ops = recorder.report(Duration::from_secs(2), 100);
+        assert_eq!(ops[0].op, Op::Batch);
+        assert_eq!((ops[0].count, ops[0].errors), (1, 1));
+        assert_eq!(ops[0].entries_per_sec, Some(50.0));
+        assert_eq!(ops[0].first_error.as_deref(), Some("429"));
+        let prove = &ops[1];
+        assert_eq!(prove.ops_per_sec, 500.0);
+        assert_eq!(prove.entries_per_sec, None);
+        assert_eq!(
+            prove.latency_ms,
+            Latency {
+                mean: 0.5005,
+                p50: 0.5,
+                p90: 0.9,
+                p99: 0.99,
+                p999: 0.999,
+                max: 1.0,
+            }
+        );
+
+        let target = |name: &str, ops: Vec<OpReport>| TargetReport {
+            name: name.into(),
+            url: format!("http://{name}"),
+            concurrency: 4,
+            measured_secs: 2.0,
+            size_before: 10,
+            size_after: 110,
+            ops,
+        };
+        let mut faster = ops.clone();
+        faster[1].ops_per_sec = 1000.0;
+        let text = render(&[target("disk", ops), target("tmpfs", faster)]);
+        assert!(text.contains("first batch error: 429"));
+        assert!(text.contains("1000.00 (2.00x)"), "{text}");
+    }
+}
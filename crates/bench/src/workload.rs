@@ -0,0 +1,245 @@
+//! The operations a benchmark sends and the workers that send them.
+
+use std::{fmt, str::FromStr, sync::Arc, time::Instant};
+
+use reality_client::Client;
+use reality_core::{BatchEntry, VerifyRequest};
+use serde::Serialize;
+
+use crate::stats::Recorder;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Op {
+    /// `POST /append` with one payload.
+    Append,
+    /// `POST /append/batch` with `batch_size` payloads.
+    Batch,
+    /// `GET /prove/N` for an entry that existed when the run started.
+    Prove,
+    /// `POST /verify` with a proof fetched before the run.
+    Verify,
+}
+
+impl Op {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Op::Append => "append",
+            Op::Batch => "batch",
+            Op::Prove => "prove",
+            Op::Verify => "verify",
+        }
+    }
+
+    /// Whether the operation reads entries the log must already hold.
+    pub fn reads(self) -> bool {
+        matches!(self, Op::Prove | Op::Verify)
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Op {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "append" => Ok(Op::Append),
+            "batch" => Ok(Op::Batch),
+            "prove" => Ok(Op::Prove),
+            "verify" => Ok(Op::Verify),
+            other => Err(format!(
+                "unknown operation {other:?}; expected append, batch, prove or verify"
+            )),
+        }
+    }
+}
+
+/// Operations with their relative weights, parsed from
+/// `append=1,prove=8`. A bare name has weight 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mix {
+    ops: Vec<(Op, u32)>,
+    total: u64,
+}
+
+impl Mix {
+    pub fn ops(&self) -> impl Iterator<Item = Op> + '_ {
+        self.ops.iter().map(|&(op, _)| op)
+    }
+
+    pub fn reads(&self) -> bool {
+        self.ops().any(Op::reads)
+    }
+
+    fn pick(&self, rng: &mut Rng) -> Op {
+        let mut ticket = rng.below(self.total);
+        for &(op, weight) in &self.ops {
+            if ticket < u64::from(weight) {
+                return op;
+            }
+            ticket -= u64::from(weight);
+        }
+        unreachable!("ticket is below the total weight")
+    }
+}
+
+impl FromStr for Mix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ops: Vec<(Op, u32)> = Vec::new();
+        for part in s.split(',').map(str::trim) {
+            let (name, weight) = match part.split_once('=') {
+                Some((name, weight)) => {
+                    let weight = weight
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("weight {weight:?} is not a number"))?;
+                    (name.trim(), weight)
+                }
+                None => (part, 1),
+            };
+            let op: Op = name.parse()?;
+            if ops.iter().any(|&(seen, _)| seen == op) {
+                return Err(format!("{op} is listed twice"));
+            }
+            if weight > 0 {
+                ops.push((op, weight));
+            }
+        }
+        let total = ops.iter().map(|&(_, weight)| u64::from(weight)).sum();
+        if total == 0 {
+            return Err("no operation has a positive weight".into());
+        }
+        Ok(Self { ops, total })
+    }
+}
+
+/// xorshift64*, seeded through splitmix64. Benchmarks need repeatable
+/// sequences, not good randomness.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Self((z ^ (z >> 31)) | 1)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A value in `0..bound`; `bound` must be positive.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+
+    /// Random lowercase letters and digits, `min` to `max` of them.
+    pub fn payload(&mut self, (min, max): (usize, usize)) -> String {
+        const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+        let len = min + self.below((max - min) as u64 + 1) as usize;
+        (0..len)
+            .map(|_| ALPHABET[self.below(ALPHABET.len() as u64) as usize] as char)
+            .collect()
+    }
+}
+
+/// One of the concurrent request loops of a run.
+pub struct Worker {
+    pub client: Client,
+    pub mix: Arc<Mix>,
+    pub rng: Rng,
+    pub payload_sizes: (usize, usize),
+    pub batch_size: usize,
+    /// Entries in the log when the run started; `prove` picks among them.
+    pub log_size: u64,
+    /// Proofs for `verify` to send.
+    pub proofs: Arc<Vec<VerifyRequest>>,
+}
+
+impl Worker {
+    /// Send requests one after another until `end`, recording those that
+    /// start after `measure_from`.
+    pub async fn run(mut self, measure_from: Instant, end: Instant) -> Recorder {
+        let mut recorder = Recorder::default();
+        while Instant::now() < end {
+            let op = self.mix.pick(&mut self.rng);
+            let started = Instant::now();
+            let result = self.send(op).await;
+            if started >= measure_from {
+                recorder.record(op, started.elapsed(), result);
+            }
+        }
+        recorder
+    }
+
+    async fn send(&mut self, op: Op) -> anyhow::Result<()> {
+        match op {
+            Op::Append => {
+                let payload = self.rng.payload(self.payload_sizes);
+                self.client.append(payload).await?;
+            }
+            Op::Batch => {
+                let entries = (0..self.batch_size)
+                    .map(|_| BatchEntry {
+                        payload: self.rng.payload(self.payload_sizes),
+                        idempotency_key: None,
+                        labels: Default::default(),
+                        signature: None,
+                    })
+                    .collect();
+                self.client.append_batch(entries).await?;
+            }
+            Op::Prove => {
+                let index = self.rng.below(self.log_size);
+                self.client.prove(index).await?;
+            }
+            Op::Verify => {
+                let proof = &self.proofs[self.rng.below(self.proofs.len() as u64) as usize];
+                let response = self.client.verify(proof).await?;
+                anyhow::ensure!(response.valid, "logd rejected a proof it served");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mixes_pick_by_weight() {
+        let mix: Mix = "append=1, prove=3,verify=0".parse().unwrap();
+        assert_eq!(mix.ops().collect::<Vec<_>>(), [Op::Append, Op::Prove]);
+        assert!(mix.reads());
+        assert!(!"batch".parse::<Mix>().unwrap().reads());
+        assert!("append,append".parse::<Mix>().is_err());
+        assert!("prove=0".parse::<Mix>().is_err());
+        assert!("prove=x".parse::<Mix>().is_err());
+
+        let mut rng = Rng::new(7);
+        let proves = (0..4000)
+            .filter(|_| mix.pick(&mut rng) == Op::Prove)
+            .count();
+        assert!((2800..3200).contains(&proves), "{proves}");
+
+        let mut a = Rng::new(7);
+        let mut b = Rng::new(7);
+        let payload = a.payload((10, 20));
+        assert_eq!(payload, b.payload((10, 20)));
+        assert!((10..=20).contains(&payload.len()));
+        assert_eq!(a.payload((5, 5)).len(), 5);
+    }
+}
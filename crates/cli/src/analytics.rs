@@ -0,0 +1,290 @@
+//! `rl export-parquet`: entry metadata as a Parquet file for analytics
+//! tools. Each row is one entry's index, append time, leaf hash, payload
+//! length and labels; payloads stay out of the file. The file's key-value
+//! metadata records the size and root of the tree the rows rebuild, so a
+//! query result can be tied back to the log and checked with a proof.
+//!
+//! Writing Parquet needs the `parquet` feature.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use reality_core::{
+    checkpoint::Checkpoint,
+    leaf::{entry_leaf, LeafMeta},
+};
+use reality_log::seal;
+use serde::{Deserialize, Serialize};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::backup;
+
+/// Key-value metadata keys for the tree the rows belong to.
+#[cfg(feature = "parquet")]
+pub const SIZE_KEY: &str = "reality.size";
+#[cfg(feature = "parquet")]
+pub const ROOT_KEY: &str = "reality.root";
+
+/// The part of a logd entry the export reads.
+#[derive(Deserialize)]
+struct StoredEntry {
+    payload: String,
+    leaf: String,
+    appended_at: String,
+    /// Holds the labels, which only v2 leaves carry.
+    #[serde(default)]
+    meta: Option<LeafMeta>,
+}
+
+/// One row of the export.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    pub index: u64,
+    /// Microseconds since the epoch, UTC.
+    pub appended_at: i64,
+    pub leaf: String,
+    pub payload_length: u64,
+    pub labels: BTreeMap<String, String>,
+}
+
+/// What `rl export-parquet` wrote.
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub out: PathBuf,
+    pub size: u64,
+    pub root: String,
+}
+
+/// Read logd's data directory, check every leaf against its payload, and
+/// write the rows to `out`.
+pub fn export(data_dir: &Path, out: &Path) -> anyhow::Result<Summary> {
+    anyhow::ensure!(
+        !out.exists(),
+        "{} already exists; choose another --out",
+        out.display()
+    );
+    let (rows, checkpoint) = rows(data_dir)?;
+    write(out, &rows, &checkpoint)?;
+    Ok(Summary {
+        out: out.to_path_buf(),
+        size: checkpoint.size,
+        root: checkpoint.root,
+    })
+}
+
+/// The rows of the log in `data_dir`, and the checkpoint they rebuild.
+pub fn rows(data_dir: &Path) -> anyhow::Result<(Vec<Row>, Checkpoint)> {
+    let (files, checkpoint) = backup::snapshot(data_dir)?;
+    let entries: Vec<StoredEntry> = backup::parse(&files, backup::ENTRIES)?;
+    let rows = entries
+        .into_iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let appended_at =
+                OffsetDateTime::parse(&entry.appended_at, &Rfc3339).with_context(|| {
+                    format!("entry {index} was appended at {:?}", entry.appended_at)
+                })?;
+            Ok(Row {
+                index: index as u64,
+                appended_at: (appended_at.unix_timestamp_nanos() / 1000) as i64,
+                leaf: entry.leaf.to_ascii_lowercase(),
+                payload_length: payload_length(&entry),
+                labels: entry.meta.map(|meta| meta.labels).unwrap_or_default(),
+            })
+        })
+        .collect::<anyhow::Result<_>>()?;
+    Ok((rows, checkpoint))
+}
+
+/// Length of the entry's plaintext, which for a payload encrypted at rest
+/// is shorter than what is stored.
+fn payload_length(entry: &StoredEntry) -> u64 {
+    let hash = hex::encode(entry_leaf(entry.payload.as_bytes(), entry.meta.as_ref()));
+    match seal::plaintext_len(&entry.payload) {
+        Some(len) if !entry.leaf.eq_ignore_ascii_case(&hash) => len,
+        _ => entry.payload.len() as u64,
+    }
+}
+
+#[cfg(feature = "parquet")]
+fn write(out: &Path, rows: &[Row], checkpoint: &Checkpoint) -> anyhow::Result<()> {
+    use std::sync::Arc;
+
+    use arrow_array::{
+        builder::{MapBuilder, StringBuilder},
+        Array, ArrayRef, RecordBatch, StringArray, TimestampMicrosecondArray, UInt64Array,
+    };
+    use arrow_schema::{DataType, Field, Schema, TimeUnit};
+    use parquet::{
+        arrow::ArrowWriter,
+        basic::Compression,
+        file::{metadata::KeyValue, properties::WriterProperties},
+    };
+
+    /// Rows per record batch, so memory stays bounded for large logs.
+    const BATCH: usize = 65_536;
+
+    let mut labels = MapBuilder::new(None, StringBuilder::new(), StringBuilder::new());
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("index", DataType::UInt64, false),
+        Field::new(
+            "appended_at",
+            DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+            false,
+        ),
+        Field::new("leaf", DataType::Utf8, false),
+        Field::new("payload_length", DataType::UInt64, false),
+        Field::new("labels", labels.finish().data_type().clone(), false),
+    ]));
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_key_value_metadata(Some(vec![
+            KeyValue::new(SIZE_KEY.to_string(), checkpoint.size.to_string()),
+            KeyValue::new(ROOT_KEY.to_string(), checkpoint.root.clone()),
+        ]))
+        .build();
+
+    let partial = out.with_extension("partial");
+    let file =
+        std::fs::File::create(&partial).with_context(|| format!("create {}", partial.display()))?;
+    let mut writer = ArrowWriter::try_new(file, schema.clone(), Some(properties))?;
+    for chunk in rows.chunks(BATCH) {
+        for row in chunk {
+            for (key, value) in &row.labels {
+                labels.keys().append_value(key);
+                labels.values().append_value(value);
+            }
+            labels.append(true)?;
+        }
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(UInt64Array::from_iter_values(chunk.iter().map(|r| r.index))),
+            Arc::new(
+                TimestampMicrosecondArray::from_iter_values(chunk.iter().map(|r| r.appended_at))
+                    .with_timezone("UTC"),
+            ),
+            Arc::new(StringArray::from_iter_values(
+                chunk.iter().map(|r| r.leaf.as_str()),
+            )),
+            Arc::new(UInt64Array::from_iter_values(
+                chunk.iter().map(|r| r.payload_length),
+            )),
+            Arc::new(labels.finish()),
+        ];
+        writer.write(&RecordBatch::try_new(schema.clone(), columns)?)?;
+    }
+    writer.close()?;
+    std::fs::rename(&partial, out).with_context(|| format!("write {}", out.display()))
+}
+
+#[cfg(not(feature = "parquet"))]
+fn write(_out: &Path, _rows: &[Row], _checkpoint: &Checkpoint) -> anyhow::Result<()> {
+    anyhow::bail!("rl was built without the parquet feature; rebuild it with --features parquet")
+}
+
+pub fn describe(summary: &Summary) -> String {
+    format!(
+        "exported {} entries to {}\nroot  {}\n",
+        summary.size,
+        summary.out.display(),
+        summary.root
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reality_core::leaf_hash;
+
+    #[test]
+    fn reads_rows_and_writes_parquet() {
+        let dir = std::env::temp_dir().join(format!("rl-parquet-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let entries: Vec<_> = ["a", "bb", "ccc"]
+            .into_iter()
+            .enumerate()
+            .map(|(i, payload)| {
+                let mut entry = serde_json::json!({
+                    "payload": payload,
+                    "leaf": hex::encode(leaf_hash(payload.as_bytes())),
+                    "appended_at": format!("2026-01-01T00:00:0{i}.5Z"),
+                });
+                if i == 1 {
+                    let meta = LeafMeta {
+                        sequence: 1,
+                        labels: BTreeMap::from([
+                            ("env".into(), "prod".into()),
+                            ("service".into(), "checkout".into()),
+                        ]),
+                        ..LeafMeta::default()
+                    };
+                    entry["leaf"] = hex::encode(meta.leaf(payload.as_bytes())).into();
+                    entry["meta"] = serde_json::to_value(meta).unwrap();
+                }
+                entry
+            })
+            .collect();
+        let leaves: Vec<_> = entries.iter().map(|e| e["leaf"].clone()).collect();
+        std::fs::write(
+            dir.join(backup::LEAVES),
+            serde_json::to_string(&leaves).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(backup::ENTRIES),
+            serde_json::to_string(&entries).unwrap(),
+        )
+        .unwrap();
+
+        let (rows, checkpoint) = rows(&dir).unwrap();
+        assert_eq!(checkpoint.size, 3);
+        assert_eq!(rows[2].payload_length, 3);
+        let sealed = StoredEntry {
+            payload: "sealed:1:AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA".into(),
+            leaf: hex::encode(leaf_hash(b"secret")),
+            appended_at: String::new(),
+            meta: None,
+        };
+        assert_eq!(payload_length(&sealed), 5);
+        assert_eq!(rows[1].appended_at, 1_767_225_601_500_000);
+        assert_eq!(rows[1].labels["service"], "checkout");
+        assert!(rows[0].labels.is_empty());
+
+        let out = dir.join("entries.parquet");
+        let written = export(&dir, &out);
+        #[cfg(feature = "parquet")]
+        {
+            use parquet::file::reader::{FileReader, SerializedFileReader};
+
+            assert_eq!(written.unwrap().root, checkpoint.root);
+            let reader = SerializedFileReader::new(std::fs::File::open(&out).unwrap()).unwrap();
+            let metadata = reader.metadata().file_metadata();
+            assert_eq!(metadata.num_rows(), 3);
+            let pairs = metadata.key_value_metadata().unwrap();
+            let root = pairs.iter().find(|pair| pair.key == ROOT_KEY).unwrap();
+            assert_eq!(root.value.as_deref(), Some(checkpoint.root.as_str()));
+            let batch = parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder::try_new(
+                std::fs::File::open(&out).unwrap(),
+            )
+            .unwrap()
+            .build()
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+            let labels = batch["labels"]
+                .as_any()
+                .downcast_ref::<arrow_array::MapArray>()
+                .unwrap();
+            assert_eq!(labels.value_length(0), 0);
+            assert_eq!(labels.value_length(1), 2);
+            assert!(export(&dir, &out).is_err(), "exists");
+        }
+        #[cfg(not(feature = "parquet"))]
+        assert!(written.is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,143 @@
+//! `rl verify-report`: offline checks of a monitor's signed audit report.
+
+use reality_core::audit::SignedAuditReport;
+
+use crate::receipt::{verdict, ReceiptVerdict};
+
+const REPORT_CHECKS: [(&str, &str); 4] = [
+    ("signature", "report signature is valid"),
+    ("key", "report is signed by the trusted monitor key"),
+    ("previous", "report directly follows the previous report"),
+    (
+        "findings",
+        "no discrepancies or failed anchors were recorded",
+    ),
+];
+
+pub fn verify_report(
+    report: &SignedAuditReport,
+    monitor_key: Option<&str>,
+    previous: Option<&SignedAuditReport>,
+) -> ReceiptVerdict {
+    let key = &report.signature.public_key;
+    let problems: usize = report
+        .report
+        .logs
+        .iter()
+        .map(|log| {
+            log.discrepancies.len()
+                + log.anchors.failed.len()
+                + usize::from(log.anchors.error.is_some())
+        })
+        .sum();
+    let failed = if !report.verify() {
+        Some((0, "report signature is invalid".to_string()))
+    } else if monitor_key.is_some_and(|trusted| !key.eq_ignore_ascii_case(trusted)) {
+        Some((1, format!("report is signed by {key}")))
+    } else if let Some(previous) = previous.filter(|previous| !follows(report, previous)) {
+        Some((
+            2,
+            format!(
+                "report does not follow the one ending {}",
+                previous.report.period_end_nanos
+            ),
+        ))
+    } else if problems > 0 {
+        Some((3, format!("problems recorded: {problems}; see the report")))
+    } else {
+        None
+    };
+    let skipped = |step| match step {
+        1 if monitor_key.is_none() => {
+            Some("no --monitor-key given; any valid signature was accepted")
+        }
+        2 if previous.is_none() => Some("no --previous report given"),
+        _ => None,
+    };
+    verdict("audit_report", &REPORT_CHECKS, failed, skipped)
+}
+
+/// Whether `report` starts where `previous` ended and records its digest.
+fn follows(report: &SignedAuditReport, previous: &SignedAuditReport) -> bool {
+    report.report.period_start_nanos == previous.report.period_end_nanos
+        && report.report.previous.as_deref() == Some(previous.digest().as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::receipt::Status;
+    use reality_core::{
+        audit::{AnchorAudit, AuditReport, Discrepancy, LogAudit, AUDIT_REPORT_VERSION},
+        signer::{KeyFileSigner, Signer},
+        RootResponse,
+    };
+
+    fn report(start: &str, end: &str, previous: Option<String>) -> AuditReport {
+        AuditReport {
+            version: AUDIT_REPORT_VERSION,
+            period_start_nanos: start.into(),
+            period_end_nanos: end.into(),
+            previous,
+            logs: vec![LogAudit {
+                log: "prod".into(),
+                url: "http://log".into(),
+                checkpoints_observed: 1,
+                latest: None,
+                discrepancies: Vec::new(),
+                anchors: AnchorAudit::default(),
+            }],
+        }
+    }
+
+    #[test]
+    fn checks_signature_key_chain_and_findings() {
+        let monitor = KeyFileSigner::from_seed([6; 32]);
+        let key = hex::encode(monitor.public_key());
+        let first = report("0", "10", None).sign(&monitor).unwrap();
+        let second = report("10", "20", Some(first.digest()))
+            .sign(&monitor)
+            .unwrap();
+
+        let verdict = verify_report(&second, Some(&key), Some(&first));
+        assert!(verdict.valid);
+        let verdict = verify_report(&first, None, None);
+        assert!(verdict.valid);
+        assert_eq!(verdict.checks[1].status, Status::Skipped);
+
+        let gap = report("15", "20", Some(first.digest()))
+            .sign(&monitor)
+            .unwrap();
+        assert_eq!(
+            verify_report(&gap, Some(&key), Some(&first)).checks[2].status,
+            Status::Fail
+        );
+
+        let mut troubled = report("10", "20", None);
+        troubled.logs[0].discrepancies.push(Discrepancy {
+            detected_at_nanos: "12".into(),
+            trusted: RootResponse {
+                size: 4,
+                root: "aa".repeat(32),
+            },
+            observed: RootResponse {
+                size: 4,
+                root: "bb".repeat(32),
+            },
+            reason: "fork".into(),
+        });
+        let verdict = verify_report(&troubled.sign(&monitor).unwrap(), Some(&key), None);
+        assert!(!verdict.valid);
+        assert_eq!(
+            verdict.checks[3].detail,
+            "problems recorded: 1; see the report"
+        );
+
+        let mut forged = second;
+        forged.report.logs[0].checkpoints_observed = 9;
+        assert_eq!(
+            verify_report(&forged, Some(&key), None).checks[0].status,
+            Status::Fail
+        );
+    }
+}
@@ -0,0 +1,551 @@
+//! `rl backup` and `rl restore`: copy logd's data directory into a single
+//! file and back, checking every leaf against its payload and the tree root
+//! against the checkpoint taken at backup time.
+//!
+//! A backup is JSON: a manifest naming each file with its length and
+//! SHA-256 and the checkpoint of the log it holds, the SHA-256 of the
+//! manifest's compact JSON, and the files themselves. With a signer the
+//! checkpoint is signed like logd's `/checkpoint`, so a restore can insist
+//! on the log's key.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use reality_core::{
+    checkpoint::{Checkpoint, SignedCheckpoint},
+    leaf::{entry_leaf, LeafMeta},
+    redaction::SignedTombstone,
+    root,
+    signer::{DetachedSignature, Signer},
+};
+use reality_log::seal;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const FORMAT: &str = "reality-backup/v1";
+
+/// logd's files: the first two hold the log, the rest are kept when present.
+pub const LEAVES: &str = "leaves.json";
+pub const ENTRIES: &str = "entries.json";
+const OPTIONAL: [&str; 7] = [
+    "anchors.json",
+    "witnesses.json",
+    "payload-keys.json",
+    "redactions/leaves.json",
+    "redactions/entries.json",
+    "days/leaves.json",
+    "days/entries.json",
+];
+
+/// Reads of a data directory that logd is writing to, before giving up on
+/// finding its files in step.
+const ATTEMPTS: u32 = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Backup {
+    pub format: String,
+    pub manifest: Manifest,
+    /// Hex SHA-256 of `manifest` as compact JSON.
+    pub manifest_hash: String,
+    /// Contents of each file in the manifest, by name.
+    pub files: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub created_at_nanos: String,
+    /// The log the backup holds.
+    pub checkpoint: Checkpoint,
+    /// The log key's signature over `checkpoint`, when made with a signer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<DetachedSignature>,
+    pub files: Vec<FileDigest>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileDigest {
+    pub name: String,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+/// The part of a logd entry a restore checks.
+#[derive(Deserialize)]
+struct StoredEntry {
+    payload: String,
+    leaf: String,
+    #[serde(default)]
+    redaction: Option<SignedTombstone>,
+    #[serde(default)]
+    meta: Option<LeafMeta>,
+}
+
+/// What `rl backup` wrote.
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub out: PathBuf,
+    pub size: u64,
+    pub root: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signed_by: Option<String>,
+    pub manifest_hash: String,
+    pub files: Vec<FileDigest>,
+}
+
+/// What `rl restore` checked, and where it put the files.
+#[derive(Debug, Serialize)]
+pub struct Restored {
+    pub size: u64,
+    pub root: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signed_by: Option<String>,
+    pub manifest_hash: String,
+    pub files: Vec<FileDigest>,
+    /// `None` for `--dry-run`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_dir: Option<PathBuf>,
+}
+
+/// Snapshot `data_dir` into `out`, signing the checkpoint with `signer`.
+pub fn create(data_dir: &Path, out: &Path, signer: Option<&dyn Signer>) -> anyhow::Result<Summary> {
+    anyhow::ensure!(
+        !out.exists(),
+        "{} already exists; choose another --out",
+        out.display()
+    );
+    let (files, checkpoint) = snapshot(data_dir)?;
+    let signature = signer
+        .map(|signer| checkpoint.clone().sign(signer))
+        .transpose()
+        .context("sign the checkpoint")?
+        .map(|signed| signed.signature);
+    let manifest = Manifest {
+        created_at_nanos: now().as_nanos().to_string(),
+        checkpoint,
+        signature,
+        files: files
+            .iter()
+            .map(|(name, contents)| digest(name, contents))
+            .collect(),
+    };
+    let backup = Backup {
+        format: FORMAT.into(),
+        manifest_hash: manifest_hash(&manifest)?,
+        manifest,
+        files,
+    };
+
+    let partial = with_suffix(out, "partial");
+    std::fs::write(&partial, serde_json::to_vec(&backup)?)
+        .with_context(|| format!("write {}", partial.display()))?;
+    std::fs::rename(&partial, out).with_context(|| format!("write {}", out.display()))?;
+
+    let manifest = backup.manifest;
+    Ok(Summary {
+        out: out.to_path_buf(),
+        size: manifest.checkpoint.size,
+        root: manifest.checkpoint.root,
+        signed_by: manifest.signature.map(|signature| signature.public_key),
+        manifest_hash: backup.manifest_hash,
+        files: manifest.files,
+    })
+}
+
+/// Check the backup in `file` and, unless `data_dir` is `None`, write its
+/// files to `data_dir`, which must be missing or empty. The written files
+/// are read back and checked again before they are moved into place.
+pub fn restore(
+    file: &Path,
+    data_dir: Option<&Path>,
+    log_key: Option<&str>,
+) -> anyhow::Result<Restored> {
+    let raw = std::fs::read(file).with_context(|| format!("read {}", file.display()))?;
+    let backup: Backup =
+        serde_json::from_slice(&raw).with_context(|| format!("parse {}", file.display()))?;
+    verify(&backup, log_key)?;
+
+    if let Some(data_dir) = data_dir {
+        write_verified(&backup, data_dir)?;
+    }
+    let manifest = backup.manifest;
+    Ok(Restored {
+        size: manifest.checkpoint.size,
+        root: manifest.checkpoint.root,
+        signed_by: manifest.signature.map(|signature| signature.public_key),
+        manifest_hash: backup.manifest_hash,
+        files: manifest.files,
+        data_dir: data_dir.map(Path::to_path_buf),
+    })
+}
+
+/// Check the manifest hash, every file against the manifest, every leaf
+/// against its entry's payload, the root against the checkpoint, and the
+/// checkpoint's signature.
+pub fn verify(backup: &Backup, log_key: Option<&str>) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        backup.format == FORMAT,
+        "unsupported backup format {:?}",
+        backup.format
+    );
+    let manifest = &backup.manifest;
+    anyhow::ensure!(
+        manifest_hash(manifest)?.eq_ignore_ascii_case(&backup.manifest_hash),
+        "manifest does not match its hash"
+    );
+    check_files(manifest, &backup.files)?;
+
+    let checkpoint = check_log(&backup.files)?;
+    anyhow::ensure!(
+        checkpoint.size == manifest.checkpoint.size,
+        "backup holds {} entries but its checkpoint has {}",
+        checkpoint.size,
+        manifest.checkpoint.size
+    );
+    anyhow::ensure!(
+        checkpoint
+            .root
+            .eq_ignore_ascii_case(&manifest.checkpoint.root),
+        "entries lead to root {}, not the checkpoint's {}",
+        checkpoint.root,
+        manifest.checkpoint.root
+    );
+
+    match (&manifest.signature, log_key) {
+        (Some(signature), _) => {
+            let signed = SignedCheckpoint {
+                checkpoint: manifest.checkpoint.clone(),
+                signature: signature.clone(),
+            };
+            anyhow::ensure!(signed.verify(), "checkpoint signature is invalid");
+            if let Some(log_key) = log_key {
+                anyhow::ensure!(
+                    signed.verify_with(log_key),
+                    "checkpoint is signed by {}, not {log_key}",
+                    signature.public_key
+                );
+            }
+        }
+        (None, Some(_)) => anyhow::bail!("--log-key given but the checkpoint is not signed"),
+        (None, None) => {}
+    }
+    Ok(())
+}
+
+pub fn describe(summary: &Summary) -> String {
+    let mut out = format!(
+        "backed up {} entries to {}\nroot      {}\n",
+        summary.size,
+        summary.out.display(),
+        summary.root
+    );
+    if let Some(key) = &summary.signed_by {
+        out += &format!("signed by {key}\n");
+    }
+    out += &format!("manifest  {}\n", summary.manifest_hash);
+    out + &describe_files(&summary.files)
+}
+
+pub fn describe_restored(restored: &Restored) -> String {
+    let mut out = match &restored.data_dir {
+        Some(dir) => format!("restored {} entries to {}\n", restored.size, dir.display()),
+        None => format!(
+            "verified {} entries; nothing written (--dry-run)\n",
+            restored.size
+        ),
+    };
+    out += &format!("root      {}\n", restored.root);
+    match &restored.signed_by {
+        Some(key) => out += &format!("signed by {key}\n"),
+        None => out += "checkpoint not signed\n",
+    }
+    out += &format!("manifest  {}\n", restored.manifest_hash);
+    out + &describe_files(&restored.files)
+}
+
+fn describe_files(files: &[FileDigest]) -> String {
+    files
+        .iter()
+        .map(|file| {
+            format!(
+                "  {:<16} {:>10} bytes  {}\n",
+                file.name, file.bytes, file.sha256
+            )
+        })
+        .collect()
+}
+
+/// Read logd's files from `data_dir` and the checkpoint they make. logd
+/// writes `leaves.json` and then `entries.json`, so a read can land between
+/// the two; it is retried until both agree.
+pub fn snapshot(data_dir: &Path) -> anyhow::Result<(BTreeMap<String, String>, Checkpoint)> {
+    let mut attempt = 1;
+    loop {
+        let files = read_files(data_dir)?;
+        match check_log(&files) {
+            Ok(checkpoint) => return Ok((files, checkpoint)),
+            Err(err) if attempt == ATTEMPTS => {
+                return Err(err.context(format!(
+                    "{} is not a consistent log; if logd is busy, stop it and retry",
+                    data_dir.display()
+                )))
+            }
+            Err(_) => {
+                attempt += 1;
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        }
+    }
+}
+
+fn read_files(data_dir: &Path) -> anyhow::Result<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+    for name in [LEAVES, ENTRIES] {
+        let path = data_dir.join(name);
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("read {}; is this logd's data directory?", path.display()))?;
+        files.insert(name.to_string(), contents);
+    }
+    for name in OPTIONAL {
+        let path = data_dir.join(name);
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                files.insert(name.to_string(), contents);
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err).with_context(|| format!("read {}", path.display())),
+        }
+    }
+    Ok(files)
+}
+
+/// The unsigned checkpoint of the log in `files`, after checking that each
+/// entry's payload hashes to its leaf.
+fn check_log(files: &BTreeMap<String, String>) -> anyhow::Result<Checkpoint> {
+    let leaves: Vec<String> = parse(files, LEAVES)?;
+    let entries: Vec<StoredEntry> = parse(files, ENTRIES)?;
+    anyhow::ensure!(
+        leaves.len() == entries.len(),
+        "{LEAVES} has {} leaves but {ENTRIES} has {} entries",
+        leaves.len(),
+        entries.len()
+    );
+    let mut hashes = Vec::with_capacity(leaves.len());
+    for (index, (leaf, entry)) in leaves.iter().zip(&entries).enumerate() {
+        let mut hash = entry_leaf(entry.payload.as_bytes(), entry.meta.as_ref());
+        // An encrypted or redacted payload cannot be checked, but its leaf
+        // must still agree with the tree.
+        let unreadable = entry.redaction.is_some() || seal::is_sealed(&entry.payload);
+        if unreadable && !entry.leaf.eq_ignore_ascii_case(&hex::encode(hash)) {
+            hex::decode_to_slice(&entry.leaf, &mut hash)
+                .with_context(|| format!("entry {index}: leaf is not a 32-byte hex hash"))?;
+        }
+        if let Some(tombstone) = &entry.redaction {
+            anyhow::ensure!(
+                tombstone.covers(index as u64, &hash) && entry.payload.is_empty(),
+                "entry {index}: its redaction tombstone does not match it"
+            );
+        }
+        let expected = hex::encode(hash);
+        anyhow::ensure!(
+            leaf.eq_ignore_ascii_case(&expected) && entry.leaf.eq_ignore_ascii_case(&expected),
+            "entry {index}: payload hashes to {expected}, but the stored leaf is {leaf}"
+        );
+        hashes.push(hash);
+    }
+    Ok(Checkpoint {
+        size: hashes.len() as u64,
+        root: hex::encode(root(&hashes)),
+        timestamp_nanos: now().as_nanos().to_string(),
+    })
+}
+
+fn check_files(manifest: &Manifest, files: &BTreeMap<String, String>) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        manifest.files.len() == files.len(),
+        "manifest lists {} files but the backup holds {}",
+        manifest.files.len(),
+        files.len()
+    );
+    for listed in &manifest.files {
+        let contents = files
+            .get(&listed.name)
+            .with_context(|| format!("{} is in the manifest but not the backup", listed.name))?;
+        let actual = digest(&listed.name, contents);
+        anyhow::ensure!(
+            actual.bytes == listed.bytes && actual.sha256.eq_ignore_ascii_case(&listed.sha256),
+            "{} does not match the manifest",
+            listed.name
+        );
+    }
+    Ok(())
+}
+
+/// Write the files to a staging directory next to `data_dir`, check what
+/// landed on disk, then move it into place.
+fn write_verified(backup: &Backup, data_dir: &Path) -> anyhow::Result<()> {
+    match std::fs::read_dir(data_dir) {
+        Ok(mut existing) => anyhow::ensure!(
+            existing.next().is_none(),
+            "{} is not empty; restore into a new directory",
+            data_dir.display()
+        ),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err).with_context(|| format!("read {}", data_dir.display())),
+    }
+
+    let staging = with_suffix(data_dir, &format!("restoring-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&staging);
+    std::fs::create_dir_all(&staging).with_context(|| format!("create {}", staging.display()))?;
+    let result = (|| {
+        for (name, contents) in &backup.files {
+            let path = staging.join(name);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("create {}", parent.display()))?;
+            }
+            std::fs::write(&path, contents)
+                .and_then(|()| std::fs::File::open(&path)?.sync_all())
+                .with_context(|| format!("write {}", path.display()))?;
+        }
+        let written = read_files(&staging)?;
+        check_files(&backup.manifest, &written)
+            .and_then(|()| check_log(&written).map(drop))
+            .context("restored files do not match the backup")?;
+        if data_dir.exists() {
+            std::fs::remove_dir(data_dir)
+                .with_context(|| format!("replace {}", data_dir.display()))?;
+        }
+        std::fs::rename(&staging, data_dir)
+            .with_context(|| format!("move {} to {}", staging.display(), data_dir.display()))
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_dir_all(&staging);
+    }
+    result
+}
+
+pub fn parse<T: serde::de::DeserializeOwned>(
+    files: &BTreeMap<String, String>,
+    name: &str,
+) -> anyhow::Result<T> {
+    let contents = files
+        .get(name)
+        .with_context(|| format!("backup has no {name}"))?;
+    serde_json::from_str(contents).with_context(|| format!("parse {name}"))
+}
+
+fn digest(name: &str, contents: &str) -> FileDigest {
+    FileDigest {
+        name: name.to_string(),
+        bytes: contents.len() as u64,
+        sha256: hex::encode(Sha256::digest(contents.as_bytes())),
+    }
+}
+
+fn manifest_hash(manifest: &Manifest) -> anyhow::Result<String> {
+    Ok(hex::encode(Sha256::digest(serde_json::to_vec(manifest)?)))
+}
+
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// `path` with `.suffix` appended to its file name.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reality_core::{leaf_hash, signer::KeyFileSigner};
+
+    fn entry(payload: &str) -> serde_json::Value {
+        serde_json::json!({
+            "payload": payload,
+            "leaf": hex::encode(leaf_hash(payload.as_bytes())),
+            "appended_at": "2026-01-01T00:00:00Z",
+        })
+    }
+
+    #[test]
+    fn restores_a_backup_and_rejects_tampering() {
+        let dir = std::env::temp_dir().join(format!("rl-backup-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let data = dir.join("data");
+        std::fs::create_dir_all(&data).unwrap();
+        let entries: Vec<_> = ["a", "b", "c"].into_iter().map(entry).collect();
+        let leaves: Vec<_> = entries.iter().map(|e| e["leaf"].clone()).collect();
+        std::fs::write(data.join(LEAVES), serde_json::to_string(&leaves).unwrap()).unwrap();
+        std::fs::write(data.join(ENTRIES), serde_json::to_string(&entries).unwrap()).unwrap();
+        std::fs::write(data.join("anchors.json"), "[]").unwrap();
+
+        let signer = KeyFileSigner::from_seed([3u8; 32]);
+        let key = hex::encode(signer.public_key());
+        let out = dir.join("log.backup");
+        let summary = create(&data, &out, Some(&signer)).unwrap();
+        assert_eq!(summary.size, 3);
+        assert_eq!(summary.files.len(), 3);
+        assert_eq!(summary.signed_by.as_deref(), Some(key.as_str()));
+        assert!(create(&data, &out, None).is_err());
+
+        assert!(restore(&out, Some(&data), None).is_err(), "not empty");
+        let restored_dir = dir.join("restored");
+        let restored = restore(&out, Some(&restored_dir), Some(&key)).unwrap();
+        assert_eq!(restored.root, summary.root);
+        assert_eq!(
+            std::fs::read(restored_dir.join(ENTRIES)).unwrap(),
+            std::fs::read(data.join(ENTRIES)).unwrap()
+        );
+        assert!(restore(&out, None, Some(&"00".repeat(32))).is_err());
+
+        let backup: Backup = serde_json::from_slice(&std::fs::read(&out).unwrap()).unwrap();
+        let mut tampered = backup.clone();
+        let forged = vec![entry("a"), entry("x"), entry("c")];
+        tampered
+            .files
+            .insert(ENTRIES.into(), serde_json::to_string(&forged).unwrap());
+        let err = verify(&tampered, None).unwrap_err();
+        assert!(
+            err.to_string().contains("does not match the manifest"),
+            "{err}"
+        );
+
+        tampered.manifest.files = tampered
+            .files
+            .iter()
+            .map(|(name, contents)| digest(name, contents))
+            .collect();
+        tampered.manifest_hash = manifest_hash(&tampered.manifest).unwrap();
+        let err = verify(&tampered, None).unwrap_err();
+        assert!(err.to_string().starts_with("entry 1:"), "{err}");
+        verify(&backup, None).unwrap();
+
+        // Encrypted and redacted payloads are taken on their leaves.
+        let sealed = dir.join("sealed");
+        let keys = reality_log::seal::PayloadKeys::new(1, [4u8; 32]);
+        let mut log = reality_log::Log::open_sealed(&sealed, keys).unwrap();
+        log.append("secret").unwrap();
+        log.append("personal").unwrap();
+        log.redact(1, "erasure request", &KeyFileSigner::from_seed([5u8; 32]))
+            .unwrap();
+        let head = log.root();
+        let summary = create(&sealed, &dir.join("sealed.backup"), None).unwrap();
+        assert_eq!(summary.root, head.root);
+        let mut files = read_files(&sealed).unwrap();
+        let entries = files[ENTRIES].replace("erasure request", "other");
+        files.insert(ENTRIES.into(), entries);
+        let err = check_log(&files).unwrap_err();
+        assert!(err.to_string().contains("tombstone"), "{err}");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
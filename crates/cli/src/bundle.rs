@@ -0,0 +1,259 @@
+//! `rl bundle create` and `rl bundle verify`: `.rlproof` files, the
+//! single-file receipts from `reality_core::rlproof`.
+
+use std::path::PathBuf;
+
+use anyhow::Context;
+use reality_client::Client;
+use reality_core::{
+    consistency::ConsistencyProof,
+    receipt::AnchorReceipt,
+    rlproof::{AnchorLink, CosignatureLink, Payload, ProofFile, ProofFileError, TrustedKeys},
+};
+use serde::Serialize;
+
+use crate::receipt::{bundle_step, verdict, ReceiptVerdict, BUNDLE_CHECKS};
+
+pub struct Create {
+    pub index: u64,
+    pub out: PathBuf,
+    pub detach: bool,
+    pub anchors: Vec<AnchorReceipt>,
+    pub witnesses: bool,
+}
+
+/// What `rl bundle create` wrote.
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub out: PathBuf,
+    pub index: u64,
+    pub size: u64,
+    pub root: String,
+    pub detached: bool,
+    pub anchors: usize,
+    pub cosignatures: usize,
+    pub bytes: usize,
+}
+
+impl Create {
+    /// Fetch the entry's `/bundle`, link each anchor and cosignature to its
+    /// checkpoint with a consistency proof where the sizes differ, and write
+    /// the file once it verifies.
+    pub async fn run(self, api: &Client) -> anyhow::Result<Summary> {
+        let index = self.index;
+        let bundle = api.bundle(index).await?;
+        bundle.verify(None).with_context(|| {
+            format!("logd served a bundle for entry {index} that does not verify")
+        })?;
+        let size = bundle.checkpoint.checkpoint.size;
+        let mut file = ProofFile::from_bundle(bundle, self.detach);
+
+        for receipt in self.anchors {
+            let record = &receipt.anchor.record;
+            anyhow::ensure!(
+                record.size > index,
+                "anchor {} covers {} entries, not entry {index}",
+                record.txid,
+                record.size
+            );
+            let consistency = link(api, size, record.size).await?;
+            file.anchors.push(AnchorLink {
+                receipt,
+                consistency,
+            });
+        }
+        if self.witnesses {
+            for cosignature in api.witnesses().await? {
+                let witnessed = cosignature.checkpoint.size;
+                if witnessed <= index || witnessed > size {
+                    continue;
+                }
+                let consistency = link(api, size, witnessed).await?;
+                file.cosignatures.push(CosignatureLink {
+                    cosignature,
+                    consistency,
+                });
+            }
+        }
+
+        file.verify(&TrustedKeys::default(), None)
+            .context("the assembled .rlproof does not verify")?;
+        let bytes = file.to_bytes()?;
+        std::fs::write(&self.out, &bytes)
+            .with_context(|| format!("write {}", self.out.display()))?;
+        Ok(Summary {
+            out: self.out,
+            index,
+            size,
+            root: file.checkpoint.checkpoint.root,
+            detached: self.detach,
+            anchors: file.anchors.len(),
+            cosignatures: file.cosignatures.len(),
+            bytes: bytes.len(),
+        })
+    }
+}
+
+/// A consistency proof between the checkpoint's tree and one of `other`
+/// leaves, unless they are the same tree.
+async fn link(api: &Client, size: u64, other: u64) -> anyhow::Result<Option<ConsistencyProof>> {
+    if size == other {
+        return Ok(None);
+    }
+    let proof = api
+        .consistency(size.min(other), Some(size.max(other)))
+        .await
+        .with_context(|| format!("fetch a consistency proof between {size} and {other}"))?;
+    Ok(Some(proof))
+}
+
+pub fn describe(summary: &Summary) -> String {
+    let payload = if summary.detached {
+        "payload digest"
+    } else {
+        "payload"
+    };
+    format!(
+        "wrote {} ({} bytes)\n\
+         entry {} in a tree of {}, with its {payload}\n\
+         root  {}\n\
+         {} anchors, {} cosignatures\n",
+        summary.out.display(),
+        summary.bytes,
+        summary.index,
+        summary.size,
+        summary.root,
+        summary.anchors,
+        summary.cosignatures
+    )
+}
+
+/// Spell out `ProofFile::verify` like `rl verify-receipt`: the entry checks,
+/// then one check per anchor and per cosignature.
+pub fn verify(file: &ProofFile, keys: &TrustedKeys, payload: Option<&[u8]>) -> ReceiptVerdict {
+    let result = file.verify(keys, payload);
+    let anchors = file.anchors.len();
+    let mut steps: Vec<(&'static str, String)> = BUNDLE_CHECKS
+        .iter()
+        .map(|&(name, passed)| (name, passed.to_string()))
+        .collect();
+    if matches!(file.payload, Payload::Digest(_)) {
+        steps[0].1 = "payload matches the digest and hashes to the proof's leaf".into();
+    }
+    if let Some(submitter) = result
+        .as_ref()
+        .ok()
+        .and_then(|report| report.submitter.as_ref())
+    {
+        steps[0].1 += &format!("; signed by submitter {submitter}");
+    }
+    for (index, anchor) in file.anchors.iter().enumerate() {
+        let record = &anchor.receipt.anchor.record;
+        let mut passed = format!(
+            "{index}: txid {} anchors a tree of {} that holds the entry",
+            record.txid, record.size
+        );
+        let report = result.as_ref().ok().map(|report| report.anchors[index]);
+        if report.is_some_and(|report| !report.txid_checked) {
+            passed += "; its txid cannot be recomputed offline";
+        }
+        steps.push(("anchor", passed));
+    }
+    for (index, link) in file.cosignatures.iter().enumerate() {
+        let cosignature = &link.cosignature;
+        steps.push((
+            "witness",
+            format!(
+                "{index}: {} cosigned a tree of {} that holds the entry",
+                cosignature.witness.public_key, cosignature.checkpoint.size
+            ),
+        ));
+    }
+
+    let failed = result.as_ref().err().map(|err| {
+        let step = match err {
+            ProofFileError::DigestMismatch | ProofFileError::BadSubmitterSignature => 0,
+            ProofFileError::Entry(err) => bundle_step(err),
+            ProofFileError::Anchor { index, .. } | ProofFileError::AnchorNotLinked(index) => {
+                BUNDLE_CHECKS.len() + index
+            }
+            ProofFileError::BadCosignature(index)
+            | ProofFileError::UntrustedWitness { index, .. }
+            | ProofFileError::CosignatureNotLinked(index) => BUNDLE_CHECKS.len() + anchors + index,
+        };
+        (step, err.to_string())
+    });
+    let payload_checked = matches!(file.payload, Payload::Inline(_)) || payload.is_some();
+    let skipped = |step| match step {
+        0 if !payload_checked => {
+            Some("the file holds only the payload digest; pass --payload to check it")
+        }
+        4 if keys.log_key.is_none() => Some("no --log-key given; any valid signature was accepted"),
+        _ => None,
+    };
+    let steps: Vec<(&'static str, &str)> = steps
+        .iter()
+        .map(|(name, passed)| (*name, passed.as_str()))
+        .collect();
+    verdict("rlproof", &steps, failed, skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::receipt::Status;
+    use reality_core::{
+        bundle::EntryBundle, checkpoint::Checkpoint, leaf_hash, make_proof, signer::KeyFileSigner,
+    };
+
+    #[test]
+    fn reports_each_link_of_a_proof_file() {
+        let leaves: Vec<[u8; 32]> = ["a", "b", "c"].map(|p| leaf_hash(p.as_bytes())).into();
+        let proof = make_proof(&leaves, 2).unwrap();
+        let checkpoint = Checkpoint {
+            size: 3,
+            root: proof.root.clone(),
+            timestamp_nanos: "1".into(),
+        };
+        let mut file = ProofFile::from_bundle(
+            EntryBundle {
+                payload: "c".into(),
+                meta: None,
+                proof,
+                checkpoint: checkpoint
+                    .clone()
+                    .sign(&KeyFileSigner::from_seed([8; 32]))
+                    .unwrap(),
+            },
+            true,
+        );
+        file.cosignatures.push(CosignatureLink {
+            cosignature: checkpoint
+                .cosign(&KeyFileSigner::from_seed([9; 32]))
+                .unwrap(),
+            consistency: None,
+        });
+        let statuses = |verdict: &ReceiptVerdict| -> Vec<Status> {
+            verdict.checks.iter().map(|check| check.status).collect()
+        };
+
+        let verdict = verify(&file, &TrustedKeys::default(), None);
+        assert!(verdict.valid);
+        assert_eq!(statuses(&verdict)[0], Status::Skipped);
+        assert_eq!(verdict.checks[5].name, "witness");
+
+        let verdict = verify(&file, &TrustedKeys::default(), Some(b"c"));
+        assert_eq!(statuses(&verdict)[..2], [Status::Pass, Status::Pass]);
+
+        let keys = TrustedKeys {
+            witness_keys: vec!["00".repeat(32)],
+            ..TrustedKeys::default()
+        };
+        let verdict = verify(&file, &keys, Some(b"c"));
+        assert!(!verdict.valid);
+        assert_eq!(statuses(&verdict)[5], Status::Fail);
+        assert!(verify(&file, &keys, Some(b"x")).checks[0]
+            .detail
+            .contains("digest"));
+    }
+}
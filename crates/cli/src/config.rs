@@ -0,0 +1,651 @@
+use std::{env, path::PathBuf};
+
+use anyhow::Context;
+use clap::{Args, Parser, Subcommand};
+use reality_client::Client;
+use reality_config::Layered;
+use serde::Deserialize;
+
+use crate::keys::Role;
+
+/// Command-line flags. The API settings can also come from the environment
+/// and then from the config file.
+#[derive(Debug, Parser)]
+#[command(
+    name = "rl",
+    version,
+    about = "Command-line client for a RealityLog log"
+)]
+pub struct Cli {
+    /// Path to a TOML config file. Defaults to `reality/cli.toml` under
+    /// `$XDG_CONFIG_HOME` (or `~/.config`) when that file exists.
+    #[arg(long, global = true, env = "REALITY_CLI_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Base URL of the logd API.
+    #[arg(long, global = true, env = "REALITY_LOG_API")]
+    pub api_url: Option<String>,
+    /// Bearer token sent to the logd API.
+    #[arg(
+        long,
+        global = true,
+        env = "REALITY_LOG_API_TOKEN",
+        hide_env_values = true
+    )]
+    pub api_token: Option<String>,
+    /// Print JSON instead of text.
+    #[arg(long, global = true)]
+    pub json: bool,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Append an entry to the log, or every record read from stdin.
+    Append {
+        /// Entry payload.
+        #[arg(long, required_unless_present = "stdin", conflicts_with = "stdin")]
+        payload: Option<String>,
+        /// Read payloads from stdin instead, one per line unless `--blob`.
+        #[arg(long)]
+        stdin: bool,
+        /// Append each non-empty line as its own entry (the default).
+        #[arg(long, requires = "stdin")]
+        lines: bool,
+        /// Append the whole input as a single entry.
+        #[arg(long, requires = "stdin", conflicts_with = "lines")]
+        blob: bool,
+        /// Label the entry, such as `--label service=checkout`. Repeatable;
+        /// logd must write v2 leaves.
+        #[arg(long = "label", value_name = "KEY=VALUE", requires = "payload", value_parser = parse_label)]
+        labels: Vec<(String, String)>,
+        /// Sign the entry as its submitter, so its v2 leaf names who
+        /// submitted it: `file:<path>` with a hex ed25519 seed, or a PKCS#11
+        /// URI.
+        #[arg(long, requires = "payload")]
+        submitter_key: Option<String>,
+    },
+    /// Show the current tree head, or the root of the first `--size` leaves.
+    Root {
+        #[arg(long)]
+        size: Option<u64>,
+        /// Compute the root from logd's data directory instead of asking
+        /// logd. The leaves are streamed from disk, so memory stays small
+        /// for logs of any size.
+        #[arg(long)]
+        data_dir: Option<PathBuf>,
+    },
+    /// Fetch the inclusion proof for an entry.
+    Prove { index: u64 },
+    /// Verify an inclusion proof offline. Reads JSON (an `InclusionProof`
+    /// or `VerifyRequest`) or a binary `/prove/N/bin` proof; `-` is stdin.
+    Verify {
+        file: PathBuf,
+        /// Also ask the log whether the proof's root is its root at that size.
+        #[arg(long)]
+        against_log: bool,
+    },
+    /// Ask logd whether it holds a file's exact contents, and check the
+    /// bundle it answers with here; `-` is stdin.
+    VerifyPayload {
+        #[arg(required_unless_present = "sha256")]
+        file: Option<PathBuf>,
+        /// Look up a payload by its hex SHA-256 instead, which needs the
+        /// read token when logd has one.
+        #[arg(long, conflicts_with = "file")]
+        sha256: Option<String>,
+        /// Hex key the bundle's checkpoint must be signed by.
+        #[arg(long)]
+        log_key: Option<String>,
+        /// Also write the bundle here, for `rl verify-receipt` later.
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Follow the log, printing each new entry's leaf hash as it arrives.
+    Tail {
+        /// Start from this entry instead of the current end of the log.
+        #[arg(long)]
+        from: Option<u64>,
+        /// Seconds between polls of `/root`.
+        #[arg(long, default_value_t = 2)]
+        interval: u64,
+        /// Check each new tree head against the previous one with a
+        /// consistency proof, and stop if the log rewrote its history.
+        #[arg(long)]
+        verify: bool,
+    },
+    /// Check that two sources claiming to serve the same log, such as logd
+    /// and a mirror, present one history rather than a fork.
+    Compare {
+        /// Base URL of the first source.
+        a: String,
+        /// Base URL of the second source.
+        b: String,
+        /// Hex key both sources' checkpoints must be signed by.
+        #[arg(long)]
+        log_key: Option<String>,
+        /// Also write the comparison, with the heads and proofs it rests
+        /// on, to this file as JSON.
+        #[arg(long)]
+        evidence: Option<PathBuf>,
+    },
+    /// Export the log as C2SP tlog tiles and a signed checkpoint, for
+    /// Trillian Tessera, Sunlight or Go tlog tooling.
+    Export {
+        /// Directory that receives `checkpoint` and `tile/`.
+        #[arg(long)]
+        out: PathBuf,
+        /// Checkpoint origin line, also used as the note key name.
+        #[arg(long)]
+        origin: String,
+        /// Key that signs the checkpoint: `file:<path>` with a hex ed25519
+        /// seed, or a PKCS#11 URI.
+        #[arg(long, env = "REALITY_EXPORT_SIGNER", hide_env_values = true)]
+        signer: String,
+        /// Export the first `size` entries instead of the whole log.
+        #[arg(long)]
+        size: Option<u64>,
+        /// Write only hash tiles and the checkpoint, without entry bundles.
+        #[arg(long)]
+        no_entries: bool,
+    },
+    /// Export the log as JSON Lines, one entry per line with its inclusion
+    /// proof against logd's signed checkpoint, for offline re-verification.
+    ExportJsonl {
+        /// File to write, such as `log.jsonl`.
+        #[arg(long)]
+        out: PathBuf,
+        /// Hex key logd's checkpoint must be signed by.
+        #[arg(long)]
+        log_key: Option<String>,
+    },
+    /// Export entry metadata (index, append time, leaf, payload length and
+    /// labels) from logd's data directory as Parquet, for analytics.
+    ExportParquet {
+        /// logd's `REALITY_LOG_DIR`.
+        #[arg(long, env = "REALITY_LOG_DIR", default_value = "data")]
+        data_dir: PathBuf,
+        /// Parquet file to create.
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Render the log as a static website: paginated entries, each entry's
+    /// proof as JSON, the signed checkpoint, and in-browser verification.
+    Site {
+        /// Directory to write the site to.
+        #[arg(long)]
+        out: PathBuf,
+        /// Title of every page.
+        #[arg(long, default_value = "RealityLog")]
+        title: String,
+        /// Entries per page.
+        #[arg(long, default_value_t = 100)]
+        page_size: usize,
+        /// Hex key the pages check the checkpoint against, and that logd's
+        /// checkpoint must be signed by now.
+        #[arg(long)]
+        log_key: Option<String>,
+        /// `wasm-pack build web/wasm-core --target web` output directory to
+        /// copy into the site, so readers can verify in the browser.
+        #[arg(long)]
+        wasm: Option<PathBuf>,
+    },
+    /// Generate a Solidity library that verifies this log's inclusion
+    /// proofs on-chain, and a Foundry test of it against proofs built here.
+    Solidity {
+        /// Directory to write `<name>.sol` and `<name>.t.sol` to.
+        #[arg(long)]
+        out: PathBuf,
+        /// Name of the library.
+        #[arg(long, default_value = "RealityLogVerifier")]
+        name: String,
+    },
+    /// Append a file's name, size and SHA-256, and write an `.rlproof`
+    /// receipt for it next to the file. The file's contents stay local.
+    Notarize {
+        path: PathBuf,
+        /// Who is notarizing the file, recorded in the entry.
+        #[arg(long)]
+        submitter: Option<String>,
+        /// Receipt to write; `<path>.rlproof` by default.
+        #[arg(long)]
+        out: Option<PathBuf>,
+        #[command(flatten)]
+        wait: WaitArgs,
+    },
+    /// Write a manifest of every file under a directory with its SHA-256,
+    /// append the manifest's digest, and write an `.rlproof` receipt.
+    NotarizeDir {
+        dir: PathBuf,
+        /// Who is notarizing the directory, recorded in the entry.
+        #[arg(long)]
+        submitter: Option<String>,
+        /// Manifest to write; `<dir>.manifest.json` next to the directory by
+        /// default.
+        #[arg(long)]
+        manifest: Option<PathBuf>,
+        /// Receipt to write; `<dir>.rlproof` next to the directory by default.
+        #[arg(long)]
+        out: Option<PathBuf>,
+        #[command(flatten)]
+        wait: WaitArgs,
+    },
+    /// Append a git commit, its tree and the tags pointing at it, and write
+    /// an `.rlproof` receipt for it that can be committed to the repository.
+    GitAnchor {
+        /// Commit or tag to anchor.
+        #[arg(long, default_value = "HEAD")]
+        rev: String,
+        /// The repository.
+        #[arg(long, default_value = ".")]
+        repo: PathBuf,
+        /// Name recorded for the repository, such as its public URL.
+        #[arg(long)]
+        name: Option<String>,
+        /// Receipt to write; `<repo>/.reality/<commit>.rlproof` by default.
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Notarize the manifest digest of an OCI image, or check that an image
+    /// or a running container is a notarized one.
+    Image {
+        #[command(subcommand)]
+        command: ImageCommand,
+    },
+    /// Create or check a `.rlproof` file, a single-file receipt for one entry.
+    Bundle {
+        #[command(subcommand)]
+        command: BundleCommand,
+    },
+    /// Create, rotate and publish signing keys for logd, witnesses, the
+    /// anchor daemon and monitors.
+    Keys {
+        #[command(subcommand)]
+        command: KeysCommand,
+    },
+    /// Snapshot logd's data directory into one file, with the checkpoint of
+    /// the log it holds and a manifest of SHA-256 digests. Run it on the
+    /// logd host.
+    Backup {
+        /// logd's `REALITY_LOG_DIR`.
+        #[arg(long, env = "REALITY_LOG_DIR", default_value = "data")]
+        data_dir: PathBuf,
+        /// Backup file to create.
+        #[arg(long)]
+        out: PathBuf,
+        /// Key that signs the checkpoint, normally logd's own: `file:<path>`
+        /// or a PKCS#11 URI.
+        #[arg(long, env = "REALITY_LOG_SIGNER", hide_env_values = true)]
+        signer: Option<String>,
+    },
+    /// Check a backup, re-hashing every entry and the root, and write it to
+    /// a new data directory.
+    Restore {
+        file: PathBuf,
+        /// Directory to restore into; it must be missing or empty.
+        #[arg(long, required_unless_present = "dry_run")]
+        data_dir: Option<PathBuf>,
+        /// Hex key the checkpoint must be signed by.
+        #[arg(long)]
+        log_key: Option<String>,
+        /// Only check the backup.
+        #[arg(long, conflicts_with = "data_dir")]
+        dry_run: bool,
+    },
+    /// Verify a stored receipt entirely offline: an `EntryBundle` from
+    /// `/bundle/N`, an anchored proof from `/prove/N?at_anchor=<txid>` or an
+    /// anchor receipt from the anchor daemon's `/receipt`.
+    VerifyReceipt {
+        file: PathBuf,
+        /// Hex key the bundle's checkpoint must be signed by.
+        #[arg(long)]
+        log_key: Option<String>,
+        /// Hex key the anchor must be signed by.
+        #[arg(long)]
+        anchor_key: Option<String>,
+        /// Inclusion proof (JSON) that must lead to the anchored root.
+        #[arg(long)]
+        proof: Option<PathBuf>,
+        /// The previous anchor (record or receipt JSON).
+        #[arg(long)]
+        previous: Option<PathBuf>,
+        /// Consistency proof (JSON) from the previous anchor to this one.
+        #[arg(long, requires = "previous")]
+        consistency: Option<PathBuf>,
+    },
+    /// Check a directory against a manifest from `rl notarize-dir`, file by
+    /// file, and optionally the receipt that notarized the manifest.
+    VerifyManifest {
+        manifest: PathBuf,
+        /// The directory the manifest lists.
+        #[arg(long)]
+        dir: PathBuf,
+        /// The `.rlproof` receipt from `rl notarize-dir`.
+        #[arg(long)]
+        receipt: Option<PathBuf>,
+        /// Hex key the receipt's checkpoint must be signed by.
+        #[arg(long, requires = "receipt")]
+        log_key: Option<String>,
+        /// Hex key the receipt's anchors must be signed by.
+        #[arg(long, requires = "receipt")]
+        anchor_key: Option<String>,
+    },
+    /// Verify a JSON Lines export from `rl export-jsonl` offline; `-` is
+    /// stdin.
+    VerifyJsonl {
+        file: PathBuf,
+        /// Hex key the export's checkpoint must be signed by.
+        #[arg(long)]
+        log_key: Option<String>,
+    },
+    /// Verify a monitor's signed audit report offline.
+    VerifyReport {
+        file: PathBuf,
+        /// Hex key the report must be signed by.
+        #[arg(long)]
+        monitor_key: Option<String>,
+        /// The report before it, which it must directly follow.
+        #[arg(long)]
+        previous: Option<PathBuf>,
+    },
+}
+
+/// How `rl notarize` and `rl notarize-dir` wait for an anchor.
+#[derive(Debug, Args)]
+pub struct WaitArgs {
+    /// Wait for a confirmed anchor that covers the entry and put it in the
+    /// receipt.
+    #[arg(long)]
+    pub wait_anchor: bool,
+    /// The anchor daemon's API, to fetch the anchor's full receipt from;
+    /// without it logd's `/anchors` is polled.
+    #[arg(long, env = "REALITY_ANCHOR_API", requires = "wait_anchor")]
+    pub anchor_api: Option<String>,
+    /// Seconds to wait for the anchor.
+    #[arg(long, default_value_t = 300, requires = "wait_anchor")]
+    pub wait_timeout: u64,
+}
+
+/// Credentials for a registry that does not allow anonymous pulls.
+#[derive(Debug, Args)]
+pub struct RegistryArgs {
+    #[arg(long, env = "REALITY_REGISTRY_USER", requires = "registry_password")]
+    pub registry_user: Option<String>,
+    #[arg(
+        long,
+        env = "REALITY_REGISTRY_PASSWORD",
+        hide_env_values = true,
+        requires = "registry_user"
+    )]
+    pub registry_password: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ImageCommand {
+    /// Resolve an image reference to its manifest digest at the registry,
+    /// append the digest and tags, and write an `.rlproof` receipt.
+    Notarize {
+        /// Image reference, such as `ghcr.io/org/app:1.4`.
+        reference: String,
+        /// Another tag that must point at the same manifest, recorded with
+        /// the reference's own; repeatable.
+        #[arg(long)]
+        tag: Vec<String>,
+        /// Who is notarizing the image, recorded in the entry.
+        #[arg(long)]
+        submitter: Option<String>,
+        /// Receipt to write; `<name>-<digest prefix>.rlproof` by default.
+        #[arg(long)]
+        out: Option<PathBuf>,
+        #[command(flatten)]
+        registry: RegistryArgs,
+        #[command(flatten)]
+        wait: WaitArgs,
+    },
+    /// Check a receipt from `rl image notarize`, and that a digest, an image
+    /// reference as the registry resolves it now, or a container's image is
+    /// the notarized image. Needs no logd.
+    #[command(group(
+        clap::ArgGroup::new("source")
+            .required(true)
+            .args(["digest", "image", "container"])
+    ))]
+    Verify {
+        receipt: PathBuf,
+        /// A manifest digest, such as a Kubernetes pod's `imageID`.
+        #[arg(long)]
+        digest: Option<String>,
+        /// An image reference to resolve at the registry.
+        #[arg(long)]
+        image: Option<String>,
+        /// A container, whose image's digests are read from its runtime.
+        #[arg(long)]
+        container: Option<String>,
+        /// The container runtime's CLI, such as `docker` or `podman`.
+        #[arg(long, default_value = "docker", requires = "container")]
+        runtime: String,
+        /// Hex key the receipt's checkpoint must be signed by.
+        #[arg(long)]
+        log_key: Option<String>,
+        /// Hex key the receipt's anchors must be signed by.
+        #[arg(long)]
+        anchor_key: Option<String>,
+        #[command(flatten)]
+        registry: RegistryArgs,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum BundleCommand {
+    /// Write a `.rlproof` file for an entry: its payload, inclusion proof and
+    /// a signed checkpoint, plus any anchors and witness cosignatures.
+    Create {
+        index: u64,
+        /// File to write, such as `entry-42.rlproof`.
+        #[arg(long)]
+        out: PathBuf,
+        /// Store only the payload's SHA-256 digest, not the payload.
+        #[arg(long)]
+        detach: bool,
+        /// Anchor receipt (JSON from the anchor daemon's `/receipt`) to
+        /// include; repeatable.
+        #[arg(long)]
+        anchor: Vec<PathBuf>,
+        /// Include logd's witness cosignatures of trees that hold the entry.
+        #[arg(long)]
+        witnesses: bool,
+    },
+    /// Verify a `.rlproof` file offline; `-` is stdin.
+    Verify {
+        file: PathBuf,
+        /// The payload, for a file that holds only its digest.
+        #[arg(long)]
+        payload: Option<PathBuf>,
+        /// Hex key the checkpoint must be signed by.
+        #[arg(long)]
+        log_key: Option<String>,
+        /// Hex key every anchor must be signed by.
+        #[arg(long)]
+        anchor_key: Option<String>,
+        /// Hex key of a trusted witness; repeatable. Without it any valid
+        /// cosignature is accepted.
+        #[arg(long)]
+        witness_key: Vec<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum KeysCommand {
+    /// Create a key file, encrypted to a passphrase unless `--unencrypted`,
+    /// and its hex public key in `<out>.pub`.
+    Generate {
+        /// The service the key is for.
+        #[arg(long, value_enum)]
+        role: Role,
+        /// Key file to create.
+        #[arg(long)]
+        out: PathBuf,
+        /// Name for the C2SP note verifier key, such as the log's origin.
+        #[arg(long)]
+        name: Option<String>,
+        /// Store the seed in plain hex.
+        #[arg(long, conflicts_with = "passphrase_file")]
+        unencrypted: bool,
+        /// File holding the passphrase; defaults to `REALITY_KEY_PASSPHRASE`
+        /// or `REALITY_KEY_PASSPHRASE_FILE`.
+        #[arg(long)]
+        passphrase_file: Option<PathBuf>,
+    },
+    /// Replace a key, keeping the old file and writing a rotation statement
+    /// signed by both keys.
+    Rotate {
+        file: PathBuf,
+        #[arg(long, value_enum)]
+        role: Role,
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        passphrase_file: Option<PathBuf>,
+        /// Encrypt the new key to this passphrase instead of the old one.
+        #[arg(long)]
+        new_passphrase_file: Option<PathBuf>,
+    },
+    /// Print a key's public half in the forms the services take.
+    ExportPublic {
+        file: PathBuf,
+        #[arg(long, value_enum)]
+        role: Role,
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        passphrase_file: Option<PathBuf>,
+        /// Print only the hex public key.
+        #[arg(long, conflicts_with = "name")]
+        hex: bool,
+    },
+}
+
+/// Resolved client configuration.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub api_url: String,
+    /// Bearer token for the logd API.
+    pub api_token: Option<String>,
+    /// File containing the bearer token; takes precedence over `api_token`.
+    pub api_token_file: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            api_url: "http://127.0.0.1:8080".into(),
+            api_token: None,
+            api_token_file: None,
+        }
+    }
+}
+
+impl Layered<&Cli> for Config {
+    fn file(cli: &&Cli) -> Option<PathBuf> {
+        cli.config.clone().or_else(default_path)
+    }
+
+    fn apply(&mut self, cli: &Cli) -> anyhow::Result<()> {
+        if let Some(api_url) = &cli.api_url {
+            self.api_url = api_url.clone();
+        }
+        if let Some(token) = &cli.api_token {
+            self.api_token = Some(token.clone());
+        }
+        Ok(())
+    }
+}
+
+impl Config {
+    pub fn client(&self) -> anyhow::Result<Client> {
+        self.client_for(&self.api_url)
+    }
+
+    /// A client for `url` with the configured token.
+    pub fn client_for(&self, url: &str) -> anyhow::Result<Client> {
+        let mut builder = Client::builder(url);
+        if let Some(token) = self.api_token()? {
+            builder = builder.token(token);
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Resolve the API token, reading `api_token_file` if configured.
+    pub fn api_token(&self) -> anyhow::Result<Option<String>> {
+        if let Some(path) = &self.api_token_file {
+            let token = std::fs::read_to_string(path)
+                .with_context(|| format!("read token file {}", path.display()))?;
+            return Ok(Some(token.trim().to_string()));
+        }
+        Ok(self.api_token.clone())
+    }
+}
+
+fn default_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    let path = base.join("reality").join("cli.toml");
+    path.exists().then_some(path)
+}
+
+fn parse_label(raw: &str) -> Result<(String, String), String> {
+    let (key, value) = raw
+        .split_once('=')
+        .ok_or_else(|| format!("{raw:?} is not KEY=VALUE"))?;
+    Ok((key.to_string(), value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_override_the_config_file() {
+        let mut config: Config = toml::from_str(
+            r#"
+            api_url = "http://log:8080"
+            api_token = "from-file"
+            "#,
+        )
+        .expect("config");
+        let cli = Cli::parse_from(["rl", "root", "--api-token", "from-flag", "--json"]);
+        config.apply(&cli).unwrap();
+
+        assert_eq!(config.api_url, "http://log:8080");
+        assert_eq!(config.api_token().unwrap().as_deref(), Some("from-flag"));
+        assert!(cli.json);
+        assert!(matches!(
+            cli.command,
+            Command::Root {
+                size: None,
+                data_dir: None
+            }
+        ));
+    }
+
+    #[test]
+    fn labels_are_key_value_pairs() {
+        let cli = Cli::parse_from([
+            "rl",
+            "append",
+            "--payload",
+            "x",
+            "--label",
+            "service=checkout",
+            "--label",
+            "region=eu=west",
+        ]);
+        let Command::Append { labels, .. } = cli.command else {
+            panic!("parsed {:?}", cli.command);
+        };
+        assert_eq!(labels[1], ("region".into(), "eu=west".into()));
+        assert!(Cli::try_parse_from(["rl", "append", "--payload", "x", "--label", "env"]).is_err());
+    }
+}
@@ -0,0 +1,201 @@
+//! `rl export`: write the log in the C2SP `tlog-tiles` layout with a signed
+//! `tlog-checkpoint`, so Trillian Tessera, Sunlight or Go tlog tooling can
+//! read it. See `reality_core::tlog` for how the trees relate.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use reality_client::Client;
+use reality_core::{
+    leaf::entry_leaf,
+    root,
+    signer::Signer,
+    tile::TILE_WIDTH,
+    tlog::{entry_bundles, hash_tiles, rfc6962_root, sign_checkpoint, verifier_key, TileFile},
+};
+use serde::Serialize;
+
+pub struct Export {
+    pub out: PathBuf,
+    pub origin: String,
+    pub signer: Box<dyn Signer>,
+    pub size: Option<u64>,
+    pub entries: bool,
+}
+
+/// What was written, and the key that verifies the checkpoint.
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub size: u64,
+    /// This log's root at `size`, as logd reports it.
+    pub log_root: String,
+    /// RFC 6962 root of the same leaves, as in the exported checkpoint.
+    pub tlog_root: String,
+    pub hash_tiles: usize,
+    pub entry_bundles: usize,
+    pub verifier_key: String,
+}
+
+impl Export {
+    /// Fetch leaf hashes, and payloads unless `entries` is off, from logd's
+    /// tiles and write the export. The leaves must rebuild the root logd
+    /// reports at that size.
+    pub async fn run(&self, api: &Client) -> anyhow::Result<Summary> {
+        let head = match self.size {
+            Some(size) => api.root_at(size).await?,
+            None => api.root().await?,
+        };
+        let (leaves, payloads) = fetch(api, head.size, &head.root, self.entries).await?;
+        let mut summary = self.write(&leaves, payloads.as_deref())?;
+        summary.log_root = head.root;
+        Ok(summary)
+    }
+
+    /// Write tiles and bundles, then the checkpoint, so a reader of the
+    /// directory never sees a checkpoint for tiles that are not there yet.
+    fn write(&self, leaves: &[[u8; 32]], payloads: Option<&[String]>) -> anyhow::Result<Summary> {
+        let tiles = hash_tiles(leaves);
+        let bundles = match payloads {
+            Some(payloads) => entry_bundles(payloads)?,
+            None => Vec::new(),
+        };
+        for file in tiles.iter().chain(&bundles) {
+            write_file(&self.out, file)?;
+        }
+
+        let tlog_root = rfc6962_root(leaves);
+        let size = leaves.len() as u64;
+        let checkpoint = sign_checkpoint(&self.origin, size, &tlog_root, self.signer.as_ref())?;
+        write_file(
+            &self.out,
+            &TileFile {
+                path: "checkpoint".into(),
+                data: checkpoint.into_bytes(),
+            },
+        )?;
+        Ok(Summary {
+            size,
+            log_root: hex::encode(root(leaves)),
+            tlog_root: hex::encode(tlog_root),
+            hash_tiles: tiles.len(),
+            entry_bundles: bundles.len(),
+            verifier_key: verifier_key(&self.origin, &self.signer.public_key())?,
+        })
+    }
+}
+
+/// The first `size` leaf hashes from logd's tiles, checked against
+/// `root_hex`, and with `entries` the payloads, checked against their
+/// leaves.
+pub async fn fetch(
+    api: &Client,
+    size: u64,
+    root_hex: &str,
+    entries: bool,
+) -> anyhow::Result<(Vec<[u8; 32]>, Option<Vec<String>>)> {
+    let size = size as usize;
+    let tiles = size.div_ceil(TILE_WIDTH) as u64;
+
+    let mut leaves = Vec::with_capacity(size);
+    for index in 0..tiles {
+        let tile = api.tile(index).await?;
+        for leaf in &tile.leaves {
+            let hash = hex::decode(leaf)
+                .ok()
+                .and_then(|hash| <[u8; 32]>::try_from(hash).ok())
+                .with_context(|| format!("tile {index} holds {leaf:?}"))?;
+            leaves.push(hash);
+        }
+    }
+    leaves.truncate(size);
+    anyhow::ensure!(
+        leaves.len() == size && hex::encode(root(&leaves)) == root_hex.to_ascii_lowercase(),
+        "logd's tiles do not rebuild its root at size {size}"
+    );
+    if !entries {
+        return Ok((leaves, None));
+    }
+
+    let mut payloads = Vec::with_capacity(size);
+    let mut metas = Vec::with_capacity(size);
+    for index in 0..tiles {
+        let tile = api.entry_tile(index).await?;
+        let count = tile.payloads.len();
+        metas.extend(
+            tile.meta
+                .into_iter()
+                .chain(std::iter::repeat(None))
+                .take(count),
+        );
+        payloads.extend(tile.payloads);
+    }
+    payloads.truncate(size);
+    anyhow::ensure!(
+        payloads.len() == size,
+        "logd's entry tiles hold {} of {size} entries",
+        payloads.len()
+    );
+    for (index, ((payload, meta), leaf)) in payloads.iter().zip(&metas).zip(&leaves).enumerate() {
+        anyhow::ensure!(
+            entry_leaf(payload.as_bytes(), meta.as_ref()) == *leaf,
+            "entry {index} does not match its leaf hash"
+        );
+    }
+    Ok((leaves, Some(payloads)))
+}
+
+fn write_file(out: &Path, file: &TileFile) -> anyhow::Result<()> {
+    let path = out.join(&file.path);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+    }
+    std::fs::write(&path, &file.data).with_context(|| format!("write {}", path.display()))
+}
+
+pub fn describe(summary: &Summary) -> String {
+    format!(
+        "exported {} entries: {} hash tiles, {} entry bundles\n\
+         log root   {}\n\
+         tlog root  {}\n\
+         verifier   {}\n",
+        summary.size,
+        summary.hash_tiles,
+        summary.entry_bundles,
+        summary.log_root,
+        summary.tlog_root,
+        summary.verifier_key
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reality_core::{leaf_hash, signer::KeyFileSigner, tlog::verify_checkpoint};
+
+    #[test]
+    fn writes_tiles_bundles_and_a_verifiable_checkpoint() {
+        let out = std::env::temp_dir().join(format!("rl-export-{}", std::process::id()));
+        let payloads: Vec<String> = (0..300).map(|i| format!("entry {i}")).collect();
+        let leaves: Vec<[u8; 32]> = payloads.iter().map(|p| leaf_hash(p.as_bytes())).collect();
+        let export = Export {
+            out: out.clone(),
+            origin: "example.com/log".into(),
+            signer: Box::new(KeyFileSigner::from_seed([7; 32])),
+            size: None,
+            entries: true,
+        };
+        let summary = export.write(&leaves, Some(&payloads)).unwrap();
+        assert_eq!((summary.hash_tiles, summary.entry_bundles), (3, 2));
+
+        let note = std::fs::read_to_string(out.join("checkpoint")).unwrap();
+        let checkpoint = verify_checkpoint(&note, &summary.verifier_key).unwrap();
+        assert_eq!(checkpoint.size, 300);
+        assert_eq!(hex::encode(checkpoint.root), summary.tlog_root);
+        let level0 = std::fs::read(out.join("tile/0/001.p/44")).unwrap();
+        assert_eq!(level0[..32], leaves[256]);
+        let bundle = std::fs::read(out.join("tile/entries/000")).unwrap();
+        assert!(bundle.starts_with(b"\x00\x07entry 0\x00\x07entry 1"));
+        assert!(out.join("tile/1/000.p/1").exists());
+        std::fs::remove_dir_all(&out).unwrap();
+    }
+}
@@ -0,0 +1,268 @@
+//! `rl git-anchor`: append a commit of a git repository to the log, with its
+//! tree and the tags that point at it, and write an `.rlproof` receipt that
+//! can be committed back to the repository. The receipt proves the commit,
+//! and so the release it was tagged as, existed when it was appended.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::Context;
+use reality_client::Client;
+use reality_core::BatchEntry;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::bundle::{self, Create};
+
+/// The type of the entries `rl git-anchor` appends.
+pub const ENTRY_TYPE: &str = "git-anchor";
+
+pub struct GitAnchor {
+    pub repo: PathBuf,
+    pub rev: String,
+    pub name: Option<String>,
+    pub out: Option<PathBuf>,
+}
+
+/// The appended entry. It holds nothing but what git already fixed, so the
+/// same commit and tags always give the same payload.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnchoredCommit {
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Name of the repository, as given with `--name`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    /// `sha1` or `sha256`.
+    pub object_format: String,
+    pub commit: String,
+    pub tree: String,
+    /// The commit's `gpgsig`, for a signed commit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<AnchoredTag>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnchoredTag {
+    pub name: String,
+    /// The tag object of an annotated tag; absent for a lightweight one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// What `rl git-anchor` did.
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub commit: String,
+    pub tags: Vec<String>,
+    pub index: u64,
+    /// The commit was appended before, with the same tags, at `index`.
+    pub duplicate: bool,
+    pub receipt: bundle::Summary,
+}
+
+impl GitAnchor {
+    pub async fn run(self, api: &Client) -> anyhow::Result<Summary> {
+        let anchored = read_commit(&self.repo, &self.rev, self.name)?;
+        let payload = serde_json::to_string(&anchored)?;
+        // Keyed by content, so anchoring the same commit again finds the
+        // entry it already has instead of adding another.
+        let key = format!("{ENTRY_TYPE}:{}", hex::encode(Sha256::digest(&payload)));
+        let response = api
+            .append_batch(vec![BatchEntry {
+                payload,
+                idempotency_key: Some(key),
+                labels: Default::default(),
+                signature: None,
+            }])
+            .await?;
+        let appended = response
+            .entries
+            .first()
+            .context("logd acknowledged no entry")?;
+
+        let out = match self.out {
+            Some(out) => out,
+            None => {
+                let dir = self.repo.join(".reality");
+                std::fs::create_dir_all(&dir)
+                    .with_context(|| format!("create {}", dir.display()))?;
+                dir.join(format!("{}.rlproof", anchored.commit))
+            }
+        };
+        let receipt = Create {
+            index: appended.index,
+            out,
+            detach: false,
+            anchors: Vec::new(),
+            witnesses: false,
+        }
+        .run(api)
+        .await
+        .context("write the receipt; logd needs REALITY_LOG_SIGNER to sign one")?;
+        Ok(Summary {
+            commit: anchored.commit,
+            tags: anchored.tags.into_iter().map(|tag| tag.name).collect(),
+            index: appended.index,
+            duplicate: appended.duplicate,
+            receipt,
+        })
+    }
+}
+
+pub fn describe(summary: &Summary) -> String {
+    let mut out = format!(
+        "{} commit {} as entry {}\n",
+        if summary.duplicate {
+            "already anchored"
+        } else {
+            "anchored"
+        },
+        summary.commit,
+        summary.index
+    );
+    if !summary.tags.is_empty() {
+        out += &format!("tags  {}\n", summary.tags.join(", "));
+    }
+    out + &bundle::describe(&summary.receipt)
+}
+
+/// The commit `rev` names in `repo`, with the tags that point at it.
+fn read_commit(repo: &Path, rev: &str, name: Option<String>) -> anyhow::Result<AnchoredCommit> {
+    let commit = git(
+        repo,
+        &["rev-parse", "--verify", &format!("{rev}^{{commit}}")],
+    )?;
+    let tree = git(repo, &["rev-parse", &format!("{commit}^{{tree}}")])?;
+    let object_format = git(repo, &["rev-parse", "--show-object-format"])?;
+    let signature = commit_signature(&git(repo, &["cat-file", "commit", &commit])?);
+
+    let refs = git(
+        repo,
+        &[
+            "for-each-ref",
+            "--points-at",
+            &commit,
+            "--format=%(objecttype) %(objectname) %(refname:strip=2)",
+            "refs/tags",
+        ],
+    )?;
+    let mut tags = Vec::new();
+    for line in refs.lines() {
+        let mut fields = line.splitn(3, ' ');
+        let (Some(kind), Some(object), Some(tag)) = (fields.next(), fields.next(), fields.next())
+        else {
+            anyhow::bail!("unexpected git for-each-ref output: {line}");
+        };
+        tags.push(if kind == "tag" {
+            AnchoredTag {
+                name: tag.to_string(),
+                object: Some(object.to_string()),
+                signature: tag_signature(&git(repo, &["cat-file", "tag", object])?),
+            }
+        } else {
+            AnchoredTag {
+                name: tag.to_string(),
+                object: None,
+                signature: None,
+            }
+        });
+    }
+    Ok(AnchoredCommit {
+        kind: ENTRY_TYPE.to_string(),
+        repository: name,
+        object_format,
+        commit,
+        tree,
+        signature,
+        tags,
+    })
+}
+
+/// Run git in `repo` and return its trimmed output.
+fn git(repo: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .context("run git; is it installed?")?;
+    anyhow::ensure!(
+        output.status.success(),
+        "git {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(String::from_utf8(output.stdout)
+        .context("git printed non-UTF-8 output")?
+        .trim_end()
+        .to_string())
+}
+
+/// The `gpgsig` header of a raw commit object. Its continuation lines start
+/// with a space, and the headers end at the first empty line.
+fn commit_signature(raw: &str) -> Option<String> {
+    let mut lines = raw
+        .lines()
+        .take_while(|line| !line.is_empty())
+        .skip_while(|line| !line.starts_with("gpgsig ") && !line.starts_with("gpgsig-sha256 "));
+    let first = lines.next()?.split_once(' ')?.1;
+    let rest = lines.map_while(|line| line.strip_prefix(' '));
+    Some(
+        std::iter::once(first)
+            .chain(rest)
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+}
+
+/// The signature git appends to a signed tag's message.
+fn tag_signature(raw: &str) -> Option<String> {
+    const BEGIN: [&str; 3] = [
+        "-----BEGIN PGP SIGNATURE-----",
+        "-----BEGIN SSH SIGNATURE-----",
+        "-----BEGIN SIGNED MESSAGE-----",
+    ];
+    let start = BEGIN.iter().filter_map(|begin| raw.find(begin)).min()?;
+    Some(raw[start..].trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_commit_and_tag_signatures() {
+        let commit = "tree 4b825dc642cb6eb9a060e54bf8d69288fbee4904\n\
+                      author A <a@example.com> 1700000000 +0000\n\
+                      committer A <a@example.com> 1700000000 +0000\n\
+                      gpgsig -----BEGIN SSH SIGNATURE-----\n \
+                      U1NIU0lH\n \
+                      -----END SSH SIGNATURE-----\n\
+                      \n\
+                      message\n \
+                      indented body line\n";
+        assert_eq!(
+            commit_signature(commit).unwrap(),
+            "-----BEGIN SSH SIGNATURE-----\nU1NIU0lH\n-----END SSH SIGNATURE-----"
+        );
+        assert_eq!(
+            commit_signature("tree abc\n\ngpgsig in the message\n"),
+            None
+        );
+
+        let tag = "object abc\ntype commit\ntag v1.0\n\nRelease 1.0\n\
+                   -----BEGIN PGP SIGNATURE-----\n\niQE\n-----END PGP SIGNATURE-----\n";
+        assert_eq!(
+            tag_signature(tag).unwrap(),
+            "-----BEGIN PGP SIGNATURE-----\n\niQE\n-----END PGP SIGNATURE-----"
+        );
+        assert_eq!(tag_signature("object abc\n\nunsigned\n"), None);
+    }
+}
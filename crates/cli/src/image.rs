@@ -0,0 +1,583 @@
+//! `rl image`: notarize the manifest digest an OCI image reference resolves
+//! to, and later check that an image, such as the one a container runs, is
+//! the notarized one.
+//!
+//! The digest is the SHA-256 of the manifest bytes the registry serves for
+//! the reference, computed here rather than taken from the registry's
+//! `Docker-Content-Digest` header. For a multi-platform image that is the
+//! digest of its index, which is also what a runtime records for an image
+//! pulled by tag.
+
+use std::{collections::BTreeMap, fmt, path::PathBuf, process::Command};
+
+use anyhow::Context;
+use reality_client::Client;
+use reality_core::rlproof::{Payload, ProofFile, TrustedKeys};
+use reqwest::{header, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    bundle,
+    notarize::{self, AnchorWait, Recorded},
+    receipt::{self, ReceiptVerdict, Status},
+};
+
+/// The type of the entries `rl image notarize` appends.
+pub const ENTRY_TYPE: &str = "oci-image";
+
+/// Docker Hub's name in references, and the host that serves its API.
+const DOCKER_HUB: &str = "docker.io";
+const DOCKER_HUB_API: &str = "https://registry-1.docker.io";
+
+/// Manifest media types asked for, so that a registry serves the index of a
+/// multi-platform image rather than picking a platform.
+const ACCEPT: &str = "application/vnd.oci.image.index.v1+json, \
+                      application/vnd.oci.image.manifest.v1+json, \
+                      application/vnd.docker.distribution.manifest.list.v2+json, \
+                      application/vnd.docker.distribution.manifest.v2+json";
+
+/// An image reference, normalized the way Docker does: `alpine` is
+/// `docker.io/library/alpine:latest`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub registry: String,
+    pub repository: String,
+    pub tag: Option<String>,
+    pub digest: Option<String>,
+}
+
+impl Reference {
+    pub fn parse(raw: &str) -> anyhow::Result<Self> {
+        let (name, digest) = match raw.split_once('@') {
+            Some((name, digest)) => (name, Some(digest.to_string())),
+            None => (raw, None),
+        };
+        let (registry, path) = match name.split_once('/') {
+            Some((host, path))
+                if host.contains('.') || host.contains(':') || host == "localhost" =>
+            {
+                (host, path)
+            }
+            _ => (DOCKER_HUB, name),
+        };
+        let registry = if registry == "index.docker.io" {
+            DOCKER_HUB
+        } else {
+            registry
+        };
+        // A tag follows the last `:` of the last path component; earlier
+        // ones belong to the registry's port.
+        let (repository, tag) = match path.rsplit_once(':') {
+            Some((repository, tag)) if !tag.contains('/') => (repository, Some(tag.to_string())),
+            _ => (path, None),
+        };
+        anyhow::ensure!(
+            !repository.is_empty() && !repository.split('/').any(str::is_empty),
+            "{raw} is not an image reference"
+        );
+        if let Some(digest) = &digest {
+            anyhow::ensure!(
+                digest.starts_with("sha256:"),
+                "{raw}: only sha256 digests are supported"
+            );
+        }
+        let repository = if registry == DOCKER_HUB && !repository.contains('/') {
+            format!("library/{repository}")
+        } else {
+            repository.to_string()
+        };
+        let tag = match (&tag, &digest) {
+            (None, None) => Some("latest".to_string()),
+            _ => tag,
+        };
+        Ok(Self {
+            registry: registry.to_string(),
+            repository,
+            tag,
+            digest,
+        })
+    }
+
+    /// The base URL of the registry's API. Registries on the loopback
+    /// interface are spoken to in plain HTTP, as Docker does.
+    fn api_base(&self) -> String {
+        if self.registry == DOCKER_HUB {
+            return DOCKER_HUB_API.to_string();
+        }
+        let host = match self.registry.rsplit_once(':') {
+            Some((host, port)) if port.bytes().all(|b| b.is_ascii_digit()) => host,
+            _ => &self.registry,
+        };
+        let scheme = match host {
+            "localhost" | "127.0.0.1" | "[::1]" => "http",
+            _ => "https",
+        };
+        format!("{scheme}://{}", self.registry)
+    }
+}
+
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.registry, self.repository)?;
+        if let Some(tag) = &self.tag {
+            write!(f, ":{tag}")?;
+        }
+        if let Some(digest) = &self.digest {
+            write!(f, "@{digest}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads manifests from registries, anonymously or with a username and
+/// password, following the token flow of the distribution spec.
+pub struct Registry {
+    http: reqwest::Client,
+    credentials: Option<(String, String)>,
+}
+
+/// What a reference resolved to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolved {
+    /// `sha256:<hex>` of the manifest.
+    pub digest: String,
+    pub media_type: Option<String>,
+}
+
+impl Registry {
+    pub fn new(credentials: Option<(String, String)>) -> anyhow::Result<Self> {
+        let http = reqwest::Client::builder()
+            .user_agent(concat!("rl/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        Ok(Self { http, credentials })
+    }
+
+    /// Resolve `reference` at `tag_or_digest`, which must be its own tag or
+    /// digest or another tag of the same repository.
+    pub async fn resolve(
+        &self,
+        reference: &Reference,
+        tag_or_digest: &str,
+    ) -> anyhow::Result<Resolved> {
+        let url = format!(
+            "{}/v2/{}/manifests/{tag_or_digest}",
+            reference.api_base(),
+            reference.repository
+        );
+        let mut response = self.get(&url, None).await?;
+        if response.status() == StatusCode::UNAUTHORIZED {
+            let challenge = response
+                .headers()
+                .get(header::WWW_AUTHENTICATE)
+                .and_then(|value| value.to_str().ok())
+                .context("the registry asked for credentials without saying how")?
+                .to_string();
+            let auth = self.authorize(&challenge).await?;
+            response = self.get(&url, Some(&auth)).await?;
+        }
+        let status = response.status();
+        anyhow::ensure!(
+            status.is_success(),
+            "the registry answered {status} for {url}"
+        );
+        let header_digest = header_value(&response, "docker-content-digest");
+        let media_type = header_value(&response, header::CONTENT_TYPE.as_str()).map(|value| {
+            value
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        });
+        let body = response.bytes().await.context("read the manifest")?;
+        let digest = format!("sha256:{}", hex::encode(Sha256::digest(&body)));
+        if let Some(claimed) = header_digest.filter(|claimed| claimed.starts_with("sha256:")) {
+            anyhow::ensure!(
+                claimed.eq_ignore_ascii_case(&digest),
+                "the registry says {url} is {claimed}, but its manifest hashes to {digest}"
+            );
+        }
+        if tag_or_digest.starts_with("sha256:") {
+            anyhow::ensure!(
+                tag_or_digest.eq_ignore_ascii_case(&digest),
+                "the registry served a manifest for {tag_or_digest} that hashes to {digest}"
+            );
+        }
+        Ok(Resolved { digest, media_type })
+    }
+
+    async fn get(&self, url: &str, auth: Option<&Auth>) -> anyhow::Result<reqwest::Response> {
+        let mut request = self.http.get(url).header(header::ACCEPT, ACCEPT);
+        request = match auth {
+            Some(Auth::Bearer(token)) => request.bearer_auth(token),
+            Some(Auth::Basic(user, password)) => request.basic_auth(user, Some(password)),
+            None => request,
+        };
+        request.send().await.with_context(|| format!("fetch {url}"))
+    }
+
+    /// Answer a `WWW-Authenticate` challenge: fetch a bearer token from the
+    /// realm it names, or send the credentials as they are.
+    async fn authorize(&self, challenge: &str) -> anyhow::Result<Auth> {
+        let (scheme, params) = parse_challenge(challenge)
+            .with_context(|| format!("unexpected registry challenge: {challenge}"))?;
+        if scheme.eq_ignore_ascii_case("basic") {
+            let (user, password) = self
+                .credentials
+                .clone()
+                .context("the registry needs a username and password")?;
+            return Ok(Auth::Basic(user, password));
+        }
+        anyhow::ensure!(
+            scheme.eq_ignore_ascii_case("bearer"),
+            "unsupported registry authentication scheme {scheme}"
+        );
+        let realm = params
+            .get("realm")
+            .context("the registry's bearer challenge names no realm")?;
+        let query: Vec<_> = ["service", "scope"]
+            .into_iter()
+            .filter_map(|key| Some((key, params.get(key)?.as_str())))
+            .collect();
+        let mut request = self.http.get(realm).query(&query);
+        if let Some((user, password)) = &self.credentials {
+            request = request.basic_auth(user, Some(password));
+        }
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("fetch a token from {realm}"))?;
+        let status = response.status();
+        anyhow::ensure!(
+            status.is_success(),
+            "{realm} answered {status} when asked for a token"
+        );
+        let token: TokenResponse = response.json().await.context("parse the registry token")?;
+        token
+            .token
+            .or(token.access_token)
+            .map(Auth::Bearer)
+            .context("the registry's token response holds no token")
+    }
+}
+
+enum Auth {
+    Bearer(String),
+    Basic(String, String),
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    token: Option<String>,
+    access_token: Option<String>,
+}
+
+fn header_value(response: &reqwest::Response, name: &str) -> Option<String> {
+    let value = response.headers().get(name)?.to_str().ok()?;
+    Some(value.to_string())
+}
+
+/// The scheme and parameters of a `WWW-Authenticate` header such as
+/// `Bearer realm="https://auth.example/token",scope="repository:a:pull,push"`.
+fn parse_challenge(raw: &str) -> Option<(String, BTreeMap<String, String>)> {
+    let (scheme, rest) = raw.trim().split_once(' ').unwrap_or((raw.trim(), ""));
+    let mut params = BTreeMap::new();
+    let mut rest = rest.trim_start();
+    while !rest.is_empty() {
+        let (key, after) = rest.split_once('=')?;
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let (value, after) = quoted.split_once('"')?;
+                (value, after)
+            }
+            None => after
+                .split_once(',')
+                .map_or((after, ""), |(value, after)| (value, after)),
+        };
+        params.insert(key.trim().to_ascii_lowercase(), value.to_string());
+        rest = after.trim_start_matches([',', ' ']);
+    }
+    Some((scheme.to_string(), params))
+}
+
+pub struct NotarizeImage {
+    pub reference: String,
+    /// More tags that must point at the same manifest.
+    pub tags: Vec<String>,
+    pub submitter: Option<String>,
+    pub out: Option<PathBuf>,
+    pub registry: Registry,
+    pub wait: Option<AnchorWait>,
+}
+
+/// The appended entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageEntry {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub registry: String,
+    pub repository: String,
+    /// Tags that pointed at the manifest when it was notarized, sorted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// `sha256:<hex>` of the manifest.
+    pub digest: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submitter: Option<String>,
+}
+
+impl ImageEntry {
+    /// The image by digest, such as `docker.io/library/alpine@sha256:…`.
+    pub fn pinned(&self) -> String {
+        format!("{}/{}@{}", self.registry, self.repository, self.digest)
+    }
+}
+
+/// What `rl image notarize` did.
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    #[serde(flatten)]
+    pub image: ImageEntry,
+    #[serde(flatten)]
+    pub recorded: Recorded,
+}
+
+impl NotarizeImage {
+    pub async fn run(self, api: &Client) -> anyhow::Result<Summary> {
+        let reference = Reference::parse(&self.reference)?;
+        let at = reference
+            .digest
+            .as_deref()
+            .or(reference.tag.as_deref())
+            .expect("a parsed reference has a tag or digest");
+        let resolved = self.registry.resolve(&reference, at).await?;
+
+        let mut tags: Vec<String> = reference.tag.iter().cloned().collect();
+        for tag in &self.tags {
+            let other = self.registry.resolve(&reference, tag).await?;
+            anyhow::ensure!(
+                other.digest == resolved.digest,
+                "{}:{tag} is {}, not {}",
+                reference.repository,
+                other.digest,
+                resolved.digest
+            );
+            tags.push(tag.clone());
+        }
+        tags.sort();
+        tags.dedup();
+
+        let image = ImageEntry {
+            kind: ENTRY_TYPE.to_string(),
+            registry: reference.registry,
+            repository: reference.repository,
+            tags,
+            digest: resolved.digest,
+            media_type: resolved.media_type,
+            submitter: self.submitter,
+        };
+        let out = self.out.unwrap_or_else(|| {
+            let name = image.repository.rsplit('/').next().unwrap_or_default();
+            let hex = image.digest.trim_start_matches("sha256:");
+            PathBuf::from(format!("{name}-{}.rlproof", &hex[..12]))
+        });
+        let payload = serde_json::to_string(&image)?;
+        let recorded = notarize::record(api, ENTRY_TYPE, payload, out, self.wait.as_ref()).await?;
+        Ok(Summary { image, recorded })
+    }
+}
+
+pub fn describe(summary: &Summary) -> String {
+    let image = &summary.image;
+    let mut out = format!(
+        "{} {} as entry {}\n",
+        if summary.recorded.duplicate {
+            "already notarized"
+        } else {
+            "notarized"
+        },
+        image.pinned(),
+        summary.recorded.index
+    );
+    if !image.tags.is_empty() {
+        out += &format!("tags    {}\n", image.tags.join(", "));
+    }
+    if let Some(media_type) = &image.media_type {
+        out += &format!("type    {media_type}\n");
+    }
+    if let Some(submitter) = &image.submitter {
+        out += &format!("by      {submitter}\n");
+    }
+    out + &notarize::describe_recorded(&summary.recorded)
+}
+
+/// The digests of the image a container runs, from its runtime's record of
+/// where the image was pulled from.
+pub fn container_digests(runtime: &str, container: &str) -> anyhow::Result<Vec<String>> {
+    let image = run(
+        runtime,
+        &[
+            "inspect",
+            "--type",
+            "container",
+            "--format",
+            "{{.Image}}",
+            container,
+        ],
+    )?;
+    let repo_digests = run(
+        runtime,
+        &[
+            "image",
+            "inspect",
+            "--format",
+            "{{json .RepoDigests}}",
+            &image,
+        ],
+    )?;
+    let repo_digests: Option<Vec<String>> =
+        serde_json::from_str(&repo_digests).context("parse the image's RepoDigests")?;
+    let digests: Vec<String> = repo_digests
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|pinned| Some(pinned.split_once('@')?.1.to_string()))
+        .collect();
+    anyhow::ensure!(
+        !digests.is_empty(),
+        "container {container} runs image {image}, which has no registry digest; \
+         it was built or loaded locally rather than pulled"
+    );
+    Ok(digests)
+}
+
+fn run(program: &str, args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("run {program}; is it installed?"))?;
+    anyhow::ensure!(
+        output.status.success(),
+        "{program} {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(String::from_utf8(output.stdout)
+        .with_context(|| format!("{program} printed non-UTF-8 output"))?
+        .trim()
+        .to_string())
+}
+
+/// Outcome of `rl image verify`.
+#[derive(Debug, Serialize)]
+pub struct ImageVerdict {
+    pub valid: bool,
+    /// The notarized image.
+    pub image: ImageEntry,
+    /// What was checked: a digest, an image reference or a container.
+    pub source: String,
+    /// The digests `source` has; it matches if any is the notarized one.
+    pub observed: Vec<String>,
+    pub matches: bool,
+    pub receipt: ReceiptVerdict,
+}
+
+/// Check the receipt, and that one of the `observed` digests is the image
+/// it notarized.
+pub fn verify(
+    file: &ProofFile,
+    keys: &TrustedKeys,
+    source: String,
+    observed: Vec<String>,
+) -> anyhow::Result<ImageVerdict> {
+    let image = match &file.payload {
+        Payload::Inline(payload) => serde_json::from_str::<ImageEntry>(payload)
+            .ok()
+            .filter(|image| image.kind == ENTRY_TYPE),
+        Payload::Digest(_) => None,
+    }
+    .context("the receipt's entry is not an image from `rl image notarize`")?;
+    let receipt = bundle::verify(file, keys, None);
+    let matches = observed
+        .iter()
+        .any(|digest| digest.eq_ignore_ascii_case(&image.digest));
+    Ok(ImageVerdict {
+        valid: receipt.valid && matches,
+        image,
+        source,
+        observed,
+        matches,
+        receipt,
+    })
+}
+
+pub fn describe_verdict(verdict: &ImageVerdict) -> String {
+    let mut out = format!(
+        "notarized  {}\nchecked    {}\n",
+        verdict.image.pinned(),
+        verdict.source
+    );
+    for digest in &verdict.observed {
+        out += &format!("  has      {digest}\n");
+    }
+    out += &receipt::describe_checks(&verdict.receipt);
+    out += &receipt::describe_check(
+        if verdict.matches {
+            Status::Pass
+        } else {
+            Status::Fail
+        },
+        "image",
+        if verdict.matches {
+            "it is the notarized image"
+        } else {
+            "it is not the notarized image"
+        },
+    );
+    out += if verdict.valid {
+        "RESULT: PASS\n"
+    } else {
+        "RESULT: FAIL\n"
+    };
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_references_and_parses_challenges() {
+        let alpine = Reference::parse("alpine").unwrap();
+        assert_eq!(alpine.to_string(), "docker.io/library/alpine:latest");
+        assert_eq!(alpine.api_base(), DOCKER_HUB_API);
+
+        let digest = format!("sha256:{}", "ab".repeat(32));
+        let pinned = Reference::parse(&format!("localhost:5000/team/app@{digest}")).unwrap();
+        assert_eq!(pinned.registry, "localhost:5000");
+        assert_eq!(pinned.repository, "team/app");
+        assert_eq!(pinned.tag, None);
+        assert_eq!(pinned.digest, Some(digest));
+        assert_eq!(pinned.api_base(), "http://localhost:5000");
+
+        let ghcr = Reference::parse("ghcr.io/org/tool:v1.2").unwrap();
+        assert_eq!(ghcr.repository, "org/tool");
+        assert_eq!(ghcr.tag.as_deref(), Some("v1.2"));
+        assert_eq!(ghcr.api_base(), "https://ghcr.io");
+        assert!(Reference::parse("ghcr.io//tool").is_err());
+        assert!(Reference::parse("alpine@md5:00").is_err());
+
+        let (scheme, params) = parse_challenge(
+            r#"Bearer realm="https://auth.docker.io/token",service="registry.docker.io",scope="repository:library/alpine:pull,push""#,
+        )
+        .unwrap();
+        assert_eq!(scheme, "Bearer");
+        assert_eq!(params["realm"], "https://auth.docker.io/token");
+        assert_eq!(params["scope"], "repository:library/alpine:pull,push");
+        let (scheme, params) = parse_challenge("Basic realm=registry").unwrap();
+        assert_eq!(scheme, "Basic");
+        assert_eq!(params["realm"], "registry");
+    }
+}
@@ -0,0 +1,96 @@
+//! `rl export-jsonl` and `rl verify-jsonl`: the log as JSON Lines with each
+//! entry's inclusion proof against a signed checkpoint, in the format of
+//! `reality_core::jsonl`.
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use reality_client::Client;
+use reality_core::jsonl::{self, Summary};
+use serde::Serialize;
+
+use crate::export;
+
+/// What `rl export-jsonl` wrote.
+#[derive(Debug, Serialize)]
+pub struct Exported {
+    pub out: PathBuf,
+    #[serde(flatten)]
+    pub summary: Summary,
+}
+
+/// Fetch logd's signed checkpoint and the entries under it, and write the
+/// export to `out`. The entries must rebuild the checkpoint's root.
+pub async fn export(api: &Client, out: &Path, log_key: Option<&str>) -> anyhow::Result<Exported> {
+    let checkpoint = api
+        .checkpoint()
+        .await
+        .context("fetch the signed checkpoint; the export needs logd to have a signer")?;
+    anyhow::ensure!(
+        checkpoint.verify(),
+        "logd's checkpoint signature is invalid"
+    );
+    if let Some(key) = log_key {
+        anyhow::ensure!(
+            checkpoint.verify_with(key),
+            "logd's checkpoint is signed by {}, not {key}",
+            checkpoint.signature.public_key
+        );
+    }
+    let head = &checkpoint.checkpoint;
+    let (_, payloads) = export::fetch(api, head.size, &head.root, true).await?;
+    let file = File::create(out).with_context(|| format!("create {}", out.display()))?;
+    jsonl::write(
+        BufWriter::new(file),
+        &checkpoint,
+        &payloads.unwrap_or_default(),
+    )
+    .with_context(|| format!("write {}", out.display()))?;
+    Ok(Exported {
+        out: out.to_path_buf(),
+        summary: Summary {
+            size: head.size,
+            root: head.root.clone(),
+            signed_by: checkpoint.signature.public_key.clone(),
+        },
+    })
+}
+
+/// Check every line of an export; `-` is stdin.
+pub fn verify(file: &Path, log_key: Option<&str>) -> anyhow::Result<Summary> {
+    let summary = if file == Path::new("-") {
+        jsonl::verify_export(std::io::stdin().lock(), log_key)
+    } else {
+        let input = File::open(file).with_context(|| format!("open {}", file.display()))?;
+        jsonl::verify_export(BufReader::new(input), log_key)
+    };
+    summary.with_context(|| format!("{} does not verify", file.display()))
+}
+
+pub fn describe_export(exported: &Exported) -> String {
+    format!(
+        "exported {} entries to {}\n{}",
+        exported.summary.size,
+        exported.out.display(),
+        describe_head(&exported.summary)
+    )
+}
+
+pub fn describe(summary: &Summary) -> String {
+    format!(
+        "verified {} entries\n{}",
+        summary.size,
+        describe_head(summary)
+    )
+}
+
+fn describe_head(summary: &Summary) -> String {
+    format!(
+        "root       {}\nsigned by  {}\n",
+        summary.root, summary.signed_by
+    )
+}
@@ -0,0 +1,326 @@
+//! `rl keys`: create, rotate and publish the ed25519 keys that logd,
+//! witnesses, the anchor daemon and monitors sign with. Key files are the
+//! `reality_core::keyfile` format every `file:` signer spec reads.
+
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use clap::ValueEnum;
+use reality_core::{
+    keyfile,
+    signer::{KeyFileSigner, KeyRotation, Signer},
+    tlog,
+};
+use serde::Serialize;
+
+/// The service a key signs for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// logd's checkpoint key.
+    Log,
+    /// A witness's cosigning key, for `reality-witness` or the anchor
+    /// daemon in witness mode.
+    Witness,
+    /// The anchor daemon's key for signed anchors.
+    Anchor,
+    /// A monitor's key for audit reports.
+    Monitor,
+}
+
+impl Role {
+    fn as_str(self) -> &'static str {
+        match self {
+            Role::Log => "log",
+            Role::Witness => "witness",
+            Role::Anchor => "anchor",
+            Role::Monitor => "monitor",
+        }
+    }
+
+    /// The environment variable that hands the key to its service.
+    fn signer_env(self) -> &'static str {
+        match self {
+            Role::Log => "REALITY_LOG_SIGNER",
+            Role::Witness => "REALITY_WITNESS_SIGNER",
+            Role::Anchor => "REALITY_ANCHOR_SIGNER",
+            Role::Monitor => "REALITY_MONITOR_REPORT_SIGNER",
+        }
+    }
+
+    /// Where verifiers put the public key to trust it.
+    fn trusted_by(self) -> &'static [&'static str] {
+        match self {
+            Role::Log => &[
+                "rl verify-receipt / bundle verify --log-key",
+                "monitor [[logs]] log_public_key",
+                "witness [[logs]] public_key",
+                "anchor --log-public-key (witness mode)",
+            ],
+            Role::Witness => &[
+                "logd REALITY_LOG_WITNESSES (comma-separated)",
+                "rl bundle verify --witness-key",
+            ],
+            Role::Anchor => &[
+                "rl verify-receipt / bundle verify --anchor-key",
+                "monitor [report] anchor_key",
+            ],
+            Role::Monitor => &["rl verify-report --monitor-key"],
+        }
+    }
+}
+
+/// A key's public half in the forms the services take.
+#[derive(Debug, Serialize)]
+pub struct PublicMaterial {
+    pub role: Role,
+    /// Hex ed25519 public key.
+    pub public_key: String,
+    /// C2SP note verifier key, for `rl export` checkpoints; needs a name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verifier_key: Option<String>,
+    /// Signer spec for the service, and the variable it goes in.
+    pub signer: String,
+    pub signer_env: &'static str,
+    pub encrypted: bool,
+    pub trusted_by: &'static [&'static str],
+}
+
+/// What `rl keys rotate` did.
+#[derive(Debug, Serialize)]
+pub struct Rotation {
+    /// Where the old key file now lives.
+    pub retired: PathBuf,
+    /// The signed `KeyRotation` statement, also written next to the key.
+    pub statement_file: PathBuf,
+    pub statement: KeyRotation,
+    pub key: PublicMaterial,
+}
+
+/// Write a new key to `out`, encrypted to `passphrase` unless it is `None`,
+/// and its hex public key to `<out>.pub`.
+pub fn generate(
+    out: &Path,
+    role: Role,
+    name: Option<&str>,
+    passphrase: Option<&str>,
+) -> anyhow::Result<PublicMaterial> {
+    anyhow::ensure!(
+        !out.exists(),
+        "{} already exists; use `rl keys rotate` to replace it",
+        out.display()
+    );
+    let seed = new_seed()?;
+    write_key(out, &seed, passphrase)?;
+    material(out, &seed, role, name, passphrase.is_some())
+}
+
+/// The public material of the key file at `path`.
+pub fn export_public(
+    path: &Path,
+    role: Role,
+    name: Option<&str>,
+    passphrase: Option<&str>,
+) -> anyhow::Result<PublicMaterial> {
+    let seed = read(path, passphrase)?;
+    let encrypted = keyfile::is_encrypted(&std::fs::read(path)?);
+    material(path, &seed, role, name, encrypted)
+}
+
+/// Replace the key at `path` with a new one. The old file is kept as
+/// `<path>.<unix seconds>.retired`, and a `KeyRotation` signed by both keys
+/// is written to `<path>.<unix seconds>.rotation.json`. The new key is
+/// encrypted if the old one was, to `new_passphrase` or else the old
+/// passphrase.
+pub fn rotate(
+    path: &Path,
+    role: Role,
+    name: Option<&str>,
+    passphrase: Option<&str>,
+    new_passphrase: Option<&str>,
+) -> anyhow::Result<Rotation> {
+    let old = KeyFileSigner::from_seed(read(path, passphrase)?);
+    let encrypted = keyfile::is_encrypted(&std::fs::read(path)?);
+    let new_passphrase = if encrypted {
+        new_passphrase.or(passphrase)
+    } else {
+        None
+    };
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+    let seed = new_seed()?;
+    let statement = KeyRotation::sign(
+        role.as_str(),
+        now.as_nanos().to_string(),
+        &old,
+        &KeyFileSigner::from_seed(seed),
+    )?;
+    let retired = sibling(path, &format!("{}.retired", now.as_secs()));
+    let statement_file = sibling(path, &format!("{}.rotation.json", now.as_secs()));
+    std::fs::rename(path, &retired)
+        .with_context(|| format!("move {} to {}", path.display(), retired.display()))?;
+    if let Err(err) = write_key(path, &seed, new_passphrase) {
+        let _ = std::fs::rename(&retired, path);
+        return Err(err);
+    }
+    std::fs::write(
+        &statement_file,
+        serde_json::to_string_pretty(&statement)? + "\n",
+    )
+    .with_context(|| format!("write {}", statement_file.display()))?;
+
+    Ok(Rotation {
+        retired,
+        statement_file,
+        statement,
+        key: material(path, &seed, role, name, encrypted)?,
+    })
+}
+
+pub fn describe(key: &PublicMaterial) -> String {
+    let mut out = format!(
+        "{} key {}\npublic key  {}\n",
+        key.role.as_str(),
+        if key.encrypted {
+            "(encrypted)"
+        } else {
+            "(not encrypted)"
+        },
+        key.public_key
+    );
+    if let Some(verifier_key) = &key.verifier_key {
+        out += &format!("verifier    {verifier_key}\n");
+    }
+    out += &format!("signer      {}={}\n", key.signer_env, key.signer);
+    if key.encrypted {
+        out += "            with REALITY_KEY_PASSPHRASE or REALITY_KEY_PASSPHRASE_FILE set\n";
+    }
+    out += "trusted by\n";
+    for place in key.trusted_by {
+        out += &format!("  {place}\n");
+    }
+    out
+}
+
+pub fn describe_rotation(rotation: &Rotation) -> String {
+    format!(
+        "rotated {} key {} -> {}\nold key file  {}\nstatement     {}\n\n{}",
+        rotation.statement.role,
+        rotation.statement.old.public_key,
+        rotation.statement.new.public_key,
+        rotation.retired.display(),
+        rotation.statement_file.display(),
+        describe(&rotation.key)
+    )
+}
+
+fn new_seed() -> anyhow::Result<[u8; 32]> {
+    let mut seed = [0u8; 32];
+    getrandom::getrandom(&mut seed).context("read random bytes for a key")?;
+    Ok(seed)
+}
+
+fn read(path: &Path, passphrase: Option<&str>) -> anyhow::Result<[u8; 32]> {
+    keyfile::read_seed(path, passphrase).with_context(|| format!("read key {}", path.display()))
+}
+
+/// Create `path` readable by its owner only, holding `seed`, and `<path>.pub`.
+fn write_key(path: &Path, seed: &[u8; 32], passphrase: Option<&str>) -> anyhow::Result<()> {
+    let contents = match passphrase {
+        Some(passphrase) => keyfile::encrypt_seed(seed, passphrase)?,
+        None => keyfile::plain_seed(seed),
+    };
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("create {}", path.display()))?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+
+    let public_key = KeyFileSigner::from_seed(*seed).public_key();
+    let public = sibling(path, "pub");
+    std::fs::write(&public, hex::encode(public_key) + "\n")
+        .with_context(|| format!("write {}", public.display()))
+}
+
+fn material(
+    path: &Path,
+    seed: &[u8; 32],
+    role: Role,
+    name: Option<&str>,
+    encrypted: bool,
+) -> anyhow::Result<PublicMaterial> {
+    let public_key = KeyFileSigner::from_seed(*seed).public_key();
+    let path = std::path::absolute(path)?;
+    Ok(PublicMaterial {
+        role,
+        public_key: hex::encode(public_key),
+        verifier_key: name
+            .map(|name| tlog::verifier_key(name, &public_key))
+            .transpose()?,
+        signer: format!("file:{}", path.display()),
+        signer_env: role.signer_env(),
+        encrypted,
+        trusted_by: role.trusted_by(),
+    })
+}
+
+/// `path` with `.suffix` appended to its file name.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reality_core::signer::open_signer;
+
+    #[test]
+    fn generates_exports_and_rotates_keys() {
+        let dir = std::env::temp_dir().join(format!("rl-keys-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("log.key");
+
+        let key = generate(&path, Role::Log, Some("example.com/log"), None).unwrap();
+        assert!(!key.encrypted);
+        assert_eq!(key.signer_env, "REALITY_LOG_SIGNER");
+        assert!(key.verifier_key.unwrap().starts_with("example.com/log+"));
+        let signer = open_signer(&key.signer).unwrap();
+        assert_eq!(hex::encode(signer.public_key()), key.public_key);
+        assert_eq!(
+            std::fs::read_to_string(sibling(&path, "pub"))
+                .unwrap()
+                .trim(),
+            key.public_key
+        );
+        assert!(generate(&path, Role::Log, None, None).is_err());
+
+        let exported = export_public(&path, Role::Log, None, None).unwrap();
+        assert_eq!(exported.public_key, key.public_key);
+
+        let rotation = rotate(&path, Role::Log, None, None, None).unwrap();
+        assert!(rotation.statement.verify());
+        assert_eq!(rotation.statement.old.public_key, key.public_key);
+        assert_eq!(rotation.statement.new.public_key, rotation.key.public_key);
+        assert_ne!(rotation.key.public_key, key.public_key);
+        assert_eq!(
+            export_public(&rotation.retired, Role::Log, None, None)
+                .unwrap()
+                .public_key,
+            key.public_key
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
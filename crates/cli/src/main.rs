@@ -0,0 +1,860 @@
+//! `rl`: command-line client for a RealityLog logd instance.
+
+mod analytics;
+mod audit;
+mod backup;
+mod bundle;
+mod config;
+mod export;
+mod git_anchor;
+mod image;
+mod jsonl;
+mod keys;
+mod manifest;
+mod notarize;
+mod pipe;
+mod receipt;
+mod site;
+mod solidity;
+mod tail;
+
+use std::{io::Read, path::Path, process::ExitCode, time::Duration};
+
+use anyhow::Context;
+use clap::Parser;
+use reality_client::compare::{self, Comparison, Verdict};
+use reality_config::Layered;
+use reality_core::{
+    bundle::{PayloadEvidence, PayloadLookup},
+    keyfile,
+    receipt::ReceiptContext,
+    rlproof::{ProofFile, TrustedKeys},
+    signer::open_signer,
+    verify, AppendResponse, Direction, InclusionProof, RootResponse, VerifyRequest, VerifyResponse,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    bundle::Create,
+    config::{
+        BundleCommand, Cli, Command, Config, ImageCommand, KeysCommand, RegistryArgs, WaitArgs,
+    },
+    export::Export,
+    git_anchor::GitAnchor,
+    image::{NotarizeImage, Registry},
+    manifest::NotarizeDir,
+    notarize::{AnchorWait, Notarize},
+    pipe::Records,
+    receipt::{Receipt, ReceiptVerdict},
+    site::Site,
+    tail::Tail,
+};
+
+/// Outcome of `rl verify`.
+#[derive(Debug, Serialize)]
+struct VerifyReport {
+    #[serde(flatten)]
+    result: VerifyResponse,
+    index: u64,
+    /// Whether logd reports the proof's root at its size (`--against-log`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    log_agrees: Option<bool>,
+}
+
+impl VerifyReport {
+    fn passed(&self) -> bool {
+        self.result.valid && self.log_agrees != Some(false)
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<ExitCode> {
+    let cli = Cli::parse();
+    // Offline: no config, token or network needed.
+    match &cli.command {
+        Command::VerifyReceipt { .. } => return verify_receipt(&cli),
+        Command::VerifyReport {
+            file,
+            monitor_key,
+            previous,
+        } => {
+            let report = read_json(file)?;
+            let previous = previous.as_deref().map(read_json).transpose()?;
+            let verdict = audit::verify_report(&report, monitor_key.as_deref(), previous.as_ref());
+            return finish(&cli, &verdict);
+        }
+        Command::Bundle {
+            command: BundleCommand::Verify { .. },
+        } => return verify_proof_file(&cli),
+        Command::VerifyManifest { .. } => return verify_manifest(&cli),
+        Command::VerifyJsonl { file, log_key } => {
+            let summary = jsonl::verify(file, log_key.as_deref())?;
+            emit(cli.json, &summary, jsonl::describe)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Command::Image {
+            command: ImageCommand::Verify { .. },
+        } => return verify_image(&cli).await,
+        Command::Keys { command } => {
+            manage_keys(cli.json, command)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Command::Solidity { out, name } => {
+            let generated = solidity::generate(out, name)?;
+            emit(cli.json, &generated, solidity::describe)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Command::Root {
+            size,
+            data_dir: Some(data_dir),
+        } => {
+            let head = match size {
+                Some(size) => reality_log::stream::root_at(data_dir, *size)?,
+                None => reality_log::stream::root(data_dir)?,
+            };
+            emit(cli.json, &head, describe_root)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Command::ExportParquet { data_dir, out } => {
+            let summary = analytics::export(data_dir, out)?;
+            emit(cli.json, &summary, analytics::describe)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Command::Backup {
+            data_dir,
+            out,
+            signer,
+        } => {
+            let signer = signer
+                .as_deref()
+                .map(open_signer)
+                .transpose()
+                .context("open checkpoint signer")?;
+            let summary = backup::create(data_dir, out, signer.as_deref())?;
+            emit(cli.json, &summary, backup::describe)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Command::Restore {
+            file,
+            data_dir,
+            log_key,
+            dry_run: _,
+        } => {
+            let restored = backup::restore(file, data_dir.as_deref(), log_key.as_deref())?;
+            emit(cli.json, &restored, backup::describe_restored)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        _ => {}
+    }
+    let config = Config::load(&cli)?;
+    let api = config.client()?;
+
+    match &cli.command {
+        Command::Append {
+            payload: Some(payload),
+            labels,
+            submitter_key,
+            ..
+        } => {
+            let labels = labels.iter().cloned().collect();
+            let appended = match submitter_key {
+                Some(spec) => {
+                    let submitter = open_signer(spec).context("open submitter key")?;
+                    api.append_signed(payload.clone(), labels, submitter.as_ref())
+                        .await?
+                }
+                None => api.append_labelled(payload.clone(), labels).await?,
+            };
+            emit(cli.json, &appended, describe_append)?;
+        }
+        Command::Append { blob, .. } => {
+            let records = Records::new(std::io::stdin().lock(), *blob);
+            pipe::append_all(&api, records, cli.json).await?;
+        }
+        Command::Root { size, .. } => {
+            let head = match size {
+                Some(size) => api.root_at(*size).await?,
+                None => api.root().await?,
+            };
+            emit(cli.json, &head, describe_root)?;
+        }
+        Command::Prove { index } => {
+            let proof = api.prove(*index).await?;
+            anyhow::ensure!(
+                reality_client::Client::verify_local(&proof).valid,
+                "logd served a proof for entry {index} that does not verify"
+            );
+            emit(cli.json, &proof, describe_proof)?;
+        }
+        Command::Verify { file, against_log } => {
+            let (request, size) = read_proof(file)?;
+            let result = verify(&request);
+            let log_agrees = match (against_log, size) {
+                (false, _) => None,
+                (true, None) => anyhow::bail!(
+                    "--against-log needs the proof's tree size; pass an InclusionProof"
+                ),
+                (true, Some(size)) => {
+                    let head = api.root_at(size).await?;
+                    Some(head.root.eq_ignore_ascii_case(&request.root))
+                }
+            };
+            let report = VerifyReport {
+                result,
+                index: request.index,
+                log_agrees,
+            };
+            emit(cli.json, &report, describe_verify)?;
+            if !report.passed() {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Command::VerifyPayload { .. } => return verify_payload(&cli, &api).await,
+        Command::Tail {
+            from,
+            interval,
+            verify,
+        } => {
+            let tail = Tail {
+                from: *from,
+                interval: Duration::from_secs(*interval),
+                verify: *verify,
+            };
+            tail.run(&api, cli.json).await?;
+        }
+        Command::Compare {
+            a,
+            b,
+            log_key,
+            evidence,
+        } => {
+            let comparison = compare::compare(
+                &config.client_for(a)?,
+                &config.client_for(b)?,
+                log_key.as_deref(),
+            )
+            .await?;
+            if let Some(file) = evidence {
+                std::fs::write(file, serde_json::to_string_pretty(&comparison)? + "\n")
+                    .with_context(|| format!("write {}", file.display()))?;
+            }
+            emit(cli.json, &comparison, describe_comparison)?;
+            if !comparison.consistent() {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Command::Export {
+            out,
+            origin,
+            signer,
+            size,
+            no_entries,
+        } => {
+            let export = Export {
+                out: out.clone(),
+                origin: origin.clone(),
+                signer: open_signer(signer).context("open checkpoint signer")?,
+                size: *size,
+                entries: !no_entries,
+            };
+            let summary = export.run(&api).await?;
+            emit(cli.json, &summary, export::describe)?;
+        }
+        Command::ExportJsonl { out, log_key } => {
+            let exported = jsonl::export(&api, out, log_key.as_deref()).await?;
+            emit(cli.json, &exported, jsonl::describe_export)?;
+        }
+        Command::Site {
+            out,
+            title,
+            page_size,
+            log_key,
+            wasm,
+        } => {
+            let site = Site {
+                out: out.clone(),
+                title: title.clone(),
+                page_size: *page_size,
+                log_key: log_key.clone(),
+                wasm: wasm.clone(),
+            };
+            let summary = site.run(&api).await?;
+            emit(cli.json, &summary, site::describe)?;
+        }
+        Command::Notarize {
+            path,
+            submitter,
+            out,
+            wait,
+        } => {
+            let notarize = Notarize {
+                path: path.clone(),
+                submitter: submitter.clone(),
+                out: out.clone(),
+                wait: anchor_wait(&config, wait)?,
+            };
+            let summary = notarize.run(&api).await?;
+            emit(cli.json, &summary, notarize::describe)?;
+        }
+        Command::NotarizeDir {
+            dir,
+            submitter,
+            manifest,
+            out,
+            wait,
+        } => {
+            let notarize = NotarizeDir {
+                dir: dir.clone(),
+                submitter: submitter.clone(),
+                manifest: manifest.clone(),
+                out: out.clone(),
+                wait: anchor_wait(&config, wait)?,
+            };
+            let summary = notarize.run(&api).await?;
+            emit(cli.json, &summary, manifest::describe)?;
+        }
+        Command::GitAnchor {
+            rev,
+            repo,
+            name,
+            out,
+        } => {
+            let anchor = GitAnchor {
+                repo: repo.clone(),
+                rev: rev.clone(),
+                name: name.clone(),
+                out: out.clone(),
+            };
+            let summary = anchor.run(&api).await?;
+            emit(cli.json, &summary, git_anchor::describe)?;
+        }
+        Command::Image {
+            command:
+                ImageCommand::Notarize {
+                    reference,
+                    tag,
+                    submitter,
+                    out,
+                    registry,
+                    wait,
+                },
+        } => {
+            let notarize = NotarizeImage {
+                reference: reference.clone(),
+                tags: tag.clone(),
+                submitter: submitter.clone(),
+                out: out.clone(),
+                registry: registry_client(registry)?,
+                wait: anchor_wait(&config, wait)?,
+            };
+            let summary = notarize.run(&api).await?;
+            emit(cli.json, &summary, image::describe)?;
+        }
+        Command::Bundle {
+            command:
+                BundleCommand::Create {
+                    index,
+                    out,
+                    detach,
+                    anchor,
+                    witnesses,
+                },
+        } => {
+            let create = Create {
+                index: *index,
+                out: out.clone(),
+                detach: *detach,
+                anchors: anchor
+                    .iter()
+                    .map(|file| read_json(file))
+                    .collect::<anyhow::Result<_>>()?,
+                witnesses: *witnesses,
+            };
+            let summary = create.run(&api).await?;
+            emit(cli.json, &summary, bundle::describe)?;
+        }
+        Command::VerifyReceipt { .. }
+        | Command::VerifyReport { .. }
+        | Command::VerifyManifest { .. }
+        | Command::VerifyJsonl { .. }
+        | Command::Image {
+            command: ImageCommand::Verify { .. },
+        }
+        | Command::Keys { .. }
+        | Command::ExportParquet { .. }
+        | Command::Solidity { .. }
+        | Command::Backup { .. }
+        | Command::Restore { .. }
+        | Command::Bundle {
+            command: BundleCommand::Verify { .. },
+        } => unreachable!("handled offline above"),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn verify_payload(cli: &Cli, api: &reality_client::Client) -> anyhow::Result<ExitCode> {
+    let Command::VerifyPayload {
+        file,
+        sha256,
+        log_key,
+        out,
+    } = &cli.command
+    else {
+        unreachable!("called for verify-payload only");
+    };
+    let lookup = match (file, sha256) {
+        (Some(file), _) => PayloadLookup {
+            payload: Some(String::from_utf8(read_input(file)?).context("payloads are UTF-8 text")?),
+            sha256: None,
+        },
+        (None, sha256) => PayloadLookup {
+            payload: None,
+            sha256: sha256.clone(),
+        },
+    };
+    let wanted = match (&lookup.payload, &lookup.sha256) {
+        (Some(payload), _) => hex::encode(Sha256::digest(payload)),
+        (None, sha256) => sha256.clone().unwrap_or_default().to_ascii_lowercase(),
+    };
+    let evidence = api.verify_payload(&lookup).await?;
+    // The bundle proves whatever entry logd chose, so check it is this one.
+    anyhow::ensure!(
+        hex::encode(Sha256::digest(&evidence.bundle.payload)) == wanted,
+        "logd answered with entry {}, which holds another payload",
+        evidence.index
+    );
+    if let Some(out) = out {
+        std::fs::write(out, serde_json::to_string_pretty(&evidence.bundle)? + "\n")
+            .with_context(|| format!("write {}", out.display()))?;
+    }
+    if !cli.json {
+        print!("{}", describe_evidence(&evidence));
+    }
+    finish(
+        cli,
+        &receipt::verify_bundle(&evidence.bundle, log_key.as_deref()),
+    )
+}
+
+fn describe_evidence(evidence: &PayloadEvidence) -> String {
+    let mut text = format!(
+        "found in entry {}, appended {}\n",
+        evidence.index, evidence.appended_at
+    );
+    if !evidence.later.is_empty() {
+        let later: Vec<String> = evidence.later.iter().map(u64::to_string).collect();
+        text += &format!("also in entries {}\n", later.join(", "));
+    }
+    text
+}
+
+fn verify_receipt(cli: &Cli) -> anyhow::Result<ExitCode> {
+    let Command::VerifyReceipt {
+        file,
+        log_key,
+        anchor_key,
+        proof,
+        previous,
+        consistency,
+    } = &cli.command
+    else {
+        unreachable!("called for verify-receipt only");
+    };
+    let verdict = match Receipt::parse(&read_input(file)?)? {
+        Receipt::Entry(bundle) => receipt::verify_bundle(&bundle, log_key.as_deref()),
+        Receipt::Anchor(anchor) => {
+            let context = ReceiptContext {
+                proof: proof.as_deref().map(read_json).transpose()?,
+                previous: previous.as_deref().map(read_json).transpose()?,
+                consistency: consistency.as_deref().map(read_json).transpose()?,
+                anchor_key: anchor_key.clone(),
+            };
+            receipt::verify_anchor(&anchor, &context)
+        }
+        Receipt::Anchored(anchored) => receipt::verify_anchored(&anchored, anchor_key.as_deref()),
+    };
+    finish(cli, &verdict)
+}
+
+fn verify_proof_file(cli: &Cli) -> anyhow::Result<ExitCode> {
+    let Command::Bundle {
+        command:
+            BundleCommand::Verify {
+                file,
+                payload,
+                log_key,
+                anchor_key,
+                witness_key,
+            },
+    } = &cli.command
+    else {
+        unreachable!("called for bundle verify only");
+    };
+    let proof_file = ProofFile::from_bytes(&read_input(file)?)
+        .with_context(|| format!("decode {}", file.display()))?;
+    let payload = payload.as_deref().map(read_input).transpose()?;
+    let keys = TrustedKeys {
+        log_key: log_key.clone(),
+        anchor_key: anchor_key.clone(),
+        witness_keys: witness_key.clone(),
+    };
+    let verdict = bundle::verify(&proof_file, &keys, payload.as_deref());
+    finish(cli, &verdict)
+}
+
+fn verify_manifest(cli: &Cli) -> anyhow::Result<ExitCode> {
+    let Command::VerifyManifest {
+        manifest,
+        dir,
+        receipt,
+        log_key,
+        anchor_key,
+    } = &cli.command
+    else {
+        unreachable!("called for verify-manifest only");
+    };
+    let receipt = receipt
+        .as_deref()
+        .map(|file| {
+            ProofFile::from_bytes(&read_input(file)?)
+                .with_context(|| format!("decode {}", file.display()))
+        })
+        .transpose()?;
+    let keys = TrustedKeys {
+        log_key: log_key.clone(),
+        anchor_key: anchor_key.clone(),
+        witness_keys: Vec::new(),
+    };
+    let verdict = manifest::verify(
+        &read_input(manifest)?,
+        dir,
+        receipt.as_ref().map(|file| (file, &keys)),
+    )?;
+    emit(cli.json, &verdict, manifest::describe_verdict)?;
+    Ok(if verdict.valid {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+async fn verify_image(cli: &Cli) -> anyhow::Result<ExitCode> {
+    let Command::Image {
+        command:
+            ImageCommand::Verify {
+                receipt,
+                digest,
+                image,
+                container,
+                runtime,
+                log_key,
+                anchor_key,
+                registry,
+            },
+    } = &cli.command
+    else {
+        unreachable!("called for image verify only");
+    };
+    let file = ProofFile::from_bytes(&read_input(receipt)?)
+        .with_context(|| format!("decode {}", receipt.display()))?;
+    let (source, observed) = match (digest, image, container) {
+        (Some(digest), _, _) => (format!("digest {digest}"), vec![digest.clone()]),
+        (_, Some(image), _) => {
+            let reference = image::Reference::parse(image)?;
+            let at = reference
+                .digest
+                .as_deref()
+                .or(reference.tag.as_deref())
+                .expect("a parsed reference has a tag or digest");
+            let resolved = registry_client(registry)?.resolve(&reference, at).await?;
+            (format!("image {reference}"), vec![resolved.digest])
+        }
+        (_, _, Some(container)) => (
+            format!("container {container}"),
+            image::container_digests(runtime, container)?,
+        ),
+        (None, None, None) => unreachable!("clap requires a source"),
+    };
+    let keys = TrustedKeys {
+        log_key: log_key.clone(),
+        anchor_key: anchor_key.clone(),
+        witness_keys: Vec::new(),
+    };
+    let verdict = image::verify(&file, &keys, source, observed)?;
+    emit(cli.json, &verdict, image::describe_verdict)?;
+    Ok(if verdict.valid {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn registry_client(args: &RegistryArgs) -> anyhow::Result<Registry> {
+    let credentials = args
+        .registry_user
+        .clone()
+        .zip(args.registry_password.clone());
+    Registry::new(credentials)
+}
+
+/// The anchor wait `rl notarize` and `rl notarize-dir` were asked for.
+fn anchor_wait(config: &Config, args: &WaitArgs) -> anyhow::Result<Option<AnchorWait>> {
+    if !args.wait_anchor {
+        return Ok(None);
+    }
+    Ok(Some(AnchorWait {
+        anchor_api: args
+            .anchor_api
+            .as_deref()
+            .map(|url| config.client_for(url))
+            .transpose()?,
+        timeout: Duration::from_secs(args.wait_timeout),
+        interval: Duration::from_secs(5),
+    }))
+}
+
+fn manage_keys(json: bool, command: &KeysCommand) -> anyhow::Result<()> {
+    match command {
+        KeysCommand::Generate {
+            role,
+            out,
+            name,
+            unencrypted,
+            passphrase_file,
+        } => {
+            let passphrase = if *unencrypted {
+                None
+            } else {
+                Some(passphrase(passphrase_file.as_deref())?.context(
+                    "set REALITY_KEY_PASSPHRASE, pass --passphrase-file, or pass --unencrypted",
+                )?)
+            };
+            let key = keys::generate(out, *role, name.as_deref(), passphrase.as_deref())?;
+            emit(json, &key, keys::describe)
+        }
+        KeysCommand::Rotate {
+            file,
+            role,
+            name,
+            passphrase_file,
+            new_passphrase_file,
+        } => {
+            let old = passphrase(passphrase_file.as_deref())?;
+            let new = new_passphrase_file
+                .as_deref()
+                .map(read_passphrase)
+                .transpose()?;
+            let rotation =
+                keys::rotate(file, *role, name.as_deref(), old.as_deref(), new.as_deref())?;
+            emit(json, &rotation, keys::describe_rotation)
+        }
+        KeysCommand::ExportPublic {
+            file,
+            role,
+            name,
+            passphrase_file,
+            hex,
+        } => {
+            let passphrase = passphrase(passphrase_file.as_deref())?;
+            let key = keys::export_public(file, *role, name.as_deref(), passphrase.as_deref())?;
+            if *hex {
+                println!("{}", key.public_key);
+                return Ok(());
+            }
+            emit(json, &key, keys::describe)
+        }
+    }
+}
+
+/// The passphrase in `file`, or else the one the services would use.
+fn passphrase(file: Option<&Path>) -> anyhow::Result<Option<String>> {
+    match file {
+        Some(file) => read_passphrase(file).map(Some),
+        None => Ok(keyfile::passphrase_from_env()?),
+    }
+}
+
+fn read_passphrase(file: &Path) -> anyhow::Result<String> {
+    let raw = std::fs::read_to_string(file)
+        .with_context(|| format!("read passphrase {}", file.display()))?;
+    Ok(raw.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Print an offline verdict; the exit code says whether it passed.
+fn finish(cli: &Cli, verdict: &ReceiptVerdict) -> anyhow::Result<ExitCode> {
+    emit(cli.json, verdict, receipt::describe)?;
+    Ok(if verdict.valid {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// Print `value` as JSON with `--json`, otherwise as text.
+fn emit<T: Serialize>(json: bool, value: &T, text: fn(&T) -> String) -> anyhow::Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(value)?);
+    } else {
+        print!("{}", text(value));
+    }
+    Ok(())
+}
+
+/// Read a file, or stdin for `-`.
+fn read_input(file: &Path) -> anyhow::Result<Vec<u8>> {
+    if file == Path::new("-") {
+        let mut bytes = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut bytes)
+            .context("read stdin")?;
+        return Ok(bytes);
+    }
+    std::fs::read(file).with_context(|| format!("read {}", file.display()))
+}
+
+fn read_json<T: DeserializeOwned>(file: &Path) -> anyhow::Result<T> {
+    serde_json::from_slice(&read_input(file)?).with_context(|| format!("parse {}", file.display()))
+}
+
+/// A proof to verify and, when the input carries it, the tree size.
+fn read_proof(file: &Path) -> anyhow::Result<(VerifyRequest, Option<u64>)> {
+    parse_proof(&read_input(file)?)
+}
+
+fn parse_proof(bytes: &[u8]) -> anyhow::Result<(VerifyRequest, Option<u64>)> {
+    if bytes.starts_with(b"RLIP") {
+        let proof = InclusionProof::from_bytes(bytes).context("decode binary proof")?;
+        return Ok((VerifyRequest::from(&proof), Some(proof.size)));
+    }
+    if let Ok(proof) = serde_json::from_slice::<InclusionProof>(bytes) {
+        return Ok((VerifyRequest::from(&proof), Some(proof.size)));
+    }
+    let request = serde_json::from_slice(bytes)
+        .context("expected an InclusionProof or VerifyRequest as JSON, or a binary proof")?;
+    Ok((request, None))
+}
+
+fn describe_append(appended: &AppendResponse) -> String {
+    format!(
+        "appended entry {}\nleaf  {}\nsize  {}\nroot  {}\n",
+        appended.index, appended.leaf, appended.size, appended.root
+    )
+}
+
+fn describe_root(head: &RootResponse) -> String {
+    format!("size  {}\nroot  {}\n", head.size, head.root)
+}
+
+fn describe_proof(proof: &InclusionProof) -> String {
+    let mut text = format!(
+        "entry {} in a tree of {}\nleaf  {}\nroot  {}\n",
+        proof.index, proof.size, proof.leaf, proof.root
+    );
+    if !proof.algorithm.is_default() {
+        text += &format!("hash  {}\n", proof.algorithm);
+    }
+    text += &format!("path  {} steps\n", proof.path.len());
+    for step in &proof.path {
+        let side = match step.direction {
+            Direction::Left => "left ",
+            Direction::Right => "right",
+        };
+        text += &format!("  {side} {}\n", step.hash);
+    }
+    text
+}
+
+fn describe_comparison(comparison: &Comparison) -> String {
+    let mut text = match &comparison.verdict {
+        Verdict::Consistent => "consistent: both sources present the same history\n".to_string(),
+        Verdict::Fork { size } => {
+            format!("FORK: the sources give different roots for the tree of {size} entries\n")
+        }
+        Verdict::Inconsistent { old, new } => format!(
+            "INCONSISTENT: the larger source agrees at size {old} but does not prove that size {new} extends it\n"
+        ),
+    };
+    for head in [&comparison.a, &comparison.b] {
+        let signed = match &head.head.checkpoint {
+            Some(checkpoint) => format!("signed by {}", checkpoint.signature.public_key),
+            None => "unsigned".to_string(),
+        };
+        text += &format!(
+            "{}\n  size  {}\n  root  {}\n  {signed}\n",
+            head.source, head.head.size, head.head.root
+        );
+    }
+    if let Some(root_at) = &comparison.root_at_smaller {
+        text += &format!(
+            "larger source's root at size {}\n  {}\n",
+            root_at.size, root_at.root
+        );
+    }
+    if let Some(proof) = &comparison.consistency {
+        text += &format!(
+            "consistency proof {} -> {}: {} hashes\n",
+            proof.old_size,
+            proof.new_size,
+            proof.path.len()
+        );
+    }
+    text
+}
+
+fn describe_verify(report: &VerifyReport) -> String {
+    let result = &report.result;
+    let mut text = if result.valid {
+        format!(
+            "valid: entry {} is included under root {}\n",
+            report.index, result.expected_root
+        )
+    } else if result.computed_root.is_empty() {
+        "INVALID: leaf and path hashes must be 32 bytes of hex\n".to_string()
+    } else {
+        format!(
+            "INVALID: path leads to {}, not {}\n",
+            result.computed_root, result.expected_root
+        )
+    };
+    match report.log_agrees {
+        Some(true) => text += "the log reports the same root at that size\n",
+        Some(false) => text += "MISMATCH: the log reports a different root at that size\n",
+        None => {}
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reality_core::{leaf_hash, make_proof};
+
+    #[test]
+    fn verifies_json_and_binary_proofs() {
+        let leaves: Vec<[u8; 32]> = (0..5u8).map(|i| leaf_hash(&[i])).collect();
+        let proof = make_proof(&leaves, 3).unwrap();
+
+        let (request, size) = parse_proof(&serde_json::to_vec(&proof).unwrap()).unwrap();
+        assert_eq!(size, Some(5));
+        assert!(verify(&request).valid);
+
+        let (request, size) = parse_proof(&proof.to_bytes().unwrap()).unwrap();
+        assert_eq!(size, Some(5));
+        assert!(verify(&request).valid);
+
+        let bare = serde_json::to_vec(&VerifyRequest::from(&proof)).unwrap();
+        assert_eq!(parse_proof(&bare).unwrap().1, None);
+        assert!(parse_proof(b"{}").is_err());
+
+        let mut tampered = request;
+        tampered.root = "00".repeat(32);
+        let report = VerifyReport {
+            result: verify(&tampered),
+            index: 3,
+            log_agrees: None,
+        };
+        assert!(!report.passed());
+        assert!(describe_verify(&report).starts_with("INVALID: path leads to"));
+    }
+}
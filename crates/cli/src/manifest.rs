@@ -0,0 +1,348 @@
+//! `rl notarize-dir` and `rl verify-manifest`: notarize a directory tree as
+//! one entry holding the digest of a manifest of its files, and later check
+//! the tree, file by file, against that manifest.
+//!
+//! The manifest lists every regular file under the directory by its path
+//! relative to it, with `/` separators, sorted by path, with its size and
+//! SHA-256. Symlinks are neither followed nor listed. The same tree always
+//! gives the same manifest bytes, and the entry holds their SHA-256.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use reality_client::Client;
+use reality_core::rlproof::{Payload, ProofFile, TrustedKeys};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    bundle,
+    notarize::{self, hash_file, AnchorWait, Recorded},
+    receipt::{self, ReceiptVerdict, Status},
+};
+
+pub const FORMAT: &str = "reality-manifest/v1";
+
+/// The type of the entries `rl notarize-dir` appends.
+pub const ENTRY_TYPE: &str = "manifest";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: String,
+    pub files: Vec<FileEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileEntry {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// The appended entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// The directory's name.
+    pub name: String,
+    pub files: u64,
+    pub bytes: u64,
+    /// Hex SHA-256 of the manifest file.
+    pub sha256: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submitter: Option<String>,
+}
+
+impl Manifest {
+    /// Hash every regular file under `dir`.
+    pub fn build(dir: &Path) -> anyhow::Result<Self> {
+        let mut files = Vec::new();
+        walk(dir, "", &mut files)?;
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(Self {
+            format: FORMAT.to_string(),
+            files,
+        })
+    }
+
+    /// The manifest file's contents; its digest is what gets notarized.
+    pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let mut bytes = serde_json::to_vec_pretty(self)?;
+        bytes.push(b'\n');
+        Ok(bytes)
+    }
+}
+
+fn walk(dir: &Path, prefix: &str, files: &mut Vec<FileEntry>) -> anyhow::Result<()> {
+    for entry in std::fs::read_dir(dir).with_context(|| format!("read {}", dir.display()))? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name
+            .to_str()
+            .with_context(|| format!("{} is not UTF-8", entry.path().display()))?;
+        let path = format!("{prefix}{name}");
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            walk(&entry.path(), &format!("{path}/"), files)?;
+        } else if file_type.is_file() {
+            let (size, sha256) = hash_file(&entry.path())?;
+            files.push(FileEntry { path, size, sha256 });
+        }
+    }
+    Ok(())
+}
+
+pub struct NotarizeDir {
+    pub dir: PathBuf,
+    pub submitter: Option<String>,
+    pub manifest: Option<PathBuf>,
+    pub out: Option<PathBuf>,
+    pub wait: Option<AnchorWait>,
+}
+
+/// What `rl notarize-dir` did.
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    #[serde(flatten)]
+    pub entry: ManifestEntry,
+    pub manifest: PathBuf,
+    #[serde(flatten)]
+    pub recorded: Recorded,
+}
+
+impl NotarizeDir {
+    /// Write the manifest, then append its digest and write the receipt.
+    /// Both go next to the directory by default, so neither changes it.
+    pub async fn run(self, api: &Client) -> anyhow::Result<Summary> {
+        let dir = std::fs::canonicalize(&self.dir)
+            .with_context(|| format!("open {}", self.dir.display()))?;
+        let name = dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "/".to_string());
+        let sibling = |extension: &str| dir.with_file_name(format!("{name}.{extension}"));
+        let out = self.out.unwrap_or_else(|| sibling("rlproof"));
+
+        let manifest = Manifest::build(&dir)?;
+        let bytes = manifest.to_bytes()?;
+        let manifest_path = self.manifest.unwrap_or_else(|| sibling("manifest.json"));
+        std::fs::write(&manifest_path, &bytes)
+            .with_context(|| format!("write {}", manifest_path.display()))?;
+
+        let entry = ManifestEntry {
+            kind: ENTRY_TYPE.to_string(),
+            name,
+            files: manifest.files.len() as u64,
+            bytes: manifest.files.iter().map(|file| file.size).sum(),
+            sha256: hex::encode(Sha256::digest(&bytes)),
+            submitter: self.submitter,
+        };
+        let payload = serde_json::to_string(&entry)?;
+        let recorded = notarize::record(api, ENTRY_TYPE, payload, out, self.wait.as_ref()).await?;
+        Ok(Summary {
+            entry,
+            manifest: manifest_path,
+            recorded,
+        })
+    }
+}
+
+pub fn describe(summary: &Summary) -> String {
+    let entry = &summary.entry;
+    let mut out = format!(
+        "{} {} ({} files, {} bytes) as entry {}\nmanifest {} (sha256 {})\n",
+        if summary.recorded.duplicate {
+            "already notarized"
+        } else {
+            "notarized"
+        },
+        entry.name,
+        entry.files,
+        entry.bytes,
+        summary.recorded.index,
+        summary.manifest.display(),
+        entry.sha256
+    );
+    if let Some(submitter) = &entry.submitter {
+        out += &format!("by      {submitter}\n");
+    }
+    out + &notarize::describe_recorded(&summary.recorded)
+}
+
+/// Outcome of `rl verify-manifest`.
+#[derive(Debug, Serialize)]
+pub struct ManifestVerdict {
+    pub valid: bool,
+    /// Hex SHA-256 of the manifest file.
+    pub sha256: String,
+    pub files: usize,
+    /// Listed files whose size or digest differ.
+    pub changed: Vec<String>,
+    pub missing: Vec<String>,
+    /// Files the manifest does not list.
+    pub added: Vec<String>,
+    /// The receipt's checks, then whether it notarized this manifest.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receipt: Option<ReceiptVerdict>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notarized: Option<bool>,
+}
+
+/// Check `dir` against the manifest in `manifest_bytes`, and with a receipt,
+/// that the receipt is valid and its entry holds this manifest's digest.
+pub fn verify(
+    manifest_bytes: &[u8],
+    dir: &Path,
+    receipt: Option<(&ProofFile, &TrustedKeys)>,
+) -> anyhow::Result<ManifestVerdict> {
+    let expected: Manifest =
+        serde_json::from_slice(manifest_bytes).context("parse the manifest")?;
+    anyhow::ensure!(
+        expected.format == FORMAT,
+        "unsupported manifest format {}",
+        expected.format
+    );
+    let sha256 = hex::encode(Sha256::digest(manifest_bytes));
+    let actual = Manifest::build(dir)?;
+    let (changed, missing, added) = diff(&expected, &actual);
+
+    let (receipt, notarized) = match receipt {
+        Some((file, keys)) => {
+            let verdict = bundle::verify(file, keys, None);
+            let notarized = match &file.payload {
+                Payload::Inline(payload) => serde_json::from_str::<ManifestEntry>(payload)
+                    .is_ok_and(|entry| {
+                        entry.kind == ENTRY_TYPE && entry.sha256.eq_ignore_ascii_case(&sha256)
+                    }),
+                Payload::Digest(_) => false,
+            };
+            (Some(verdict), Some(notarized))
+        }
+        None => (None, None),
+    };
+    let valid = changed.is_empty()
+        && missing.is_empty()
+        && added.is_empty()
+        && receipt.as_ref().is_none_or(|verdict| verdict.valid)
+        && notarized != Some(false);
+    Ok(ManifestVerdict {
+        valid,
+        sha256,
+        files: expected.files.len(),
+        changed,
+        missing,
+        added,
+        receipt,
+        notarized,
+    })
+}
+
+/// Changed, missing and added paths, each sorted.
+fn diff(expected: &Manifest, actual: &Manifest) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let actual_files: std::collections::BTreeMap<_, _> = actual
+        .files
+        .iter()
+        .map(|file| (file.path.as_str(), file))
+        .collect();
+    let mut changed = Vec::new();
+    let mut missing = Vec::new();
+    for file in &expected.files {
+        match actual_files.get(file.path.as_str()) {
+            None => missing.push(file.path.clone()),
+            Some(found)
+                if found.size != file.size || !found.sha256.eq_ignore_ascii_case(&file.sha256) =>
+            {
+                changed.push(file.path.clone());
+            }
+            Some(_) => {}
+        }
+    }
+    let listed: std::collections::BTreeSet<_> = expected
+        .files
+        .iter()
+        .map(|file| file.path.as_str())
+        .collect();
+    let added = actual
+        .files
+        .iter()
+        .filter(|file| !listed.contains(file.path.as_str()))
+        .map(|file| file.path.clone())
+        .collect();
+    (changed, missing, added)
+}
+
+pub fn describe_verdict(verdict: &ManifestVerdict) -> String {
+    let mut out = format!(
+        "manifest sha256 {}\n{} of {} files unchanged\n",
+        verdict.sha256,
+        verdict.files - verdict.changed.len() - verdict.missing.len(),
+        verdict.files
+    );
+    for (label, paths) in [
+        ("changed", &verdict.changed),
+        ("missing", &verdict.missing),
+        ("added  ", &verdict.added),
+    ] {
+        for path in paths {
+            out += &format!("  {label}  {path}\n");
+        }
+    }
+    if let (Some(receipt), Some(notarized)) = (&verdict.receipt, verdict.notarized) {
+        out += &receipt::describe_checks(receipt);
+        out += &receipt::describe_check(
+            if notarized {
+                Status::Pass
+            } else {
+                Status::Fail
+            },
+            "manifest",
+            if notarized {
+                "the receipt's entry holds this manifest's digest"
+            } else {
+                "the receipt's entry is not this manifest"
+            },
+        );
+    }
+    out += if verdict.valid {
+        "RESULT: PASS\n"
+    } else {
+        "RESULT: FAIL\n"
+    };
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifests_are_deterministic_and_catch_changes() {
+        let dir = std::env::temp_dir().join(format!("rl-manifest-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("b/c")).unwrap();
+        std::fs::write(dir.join("z.txt"), "z").unwrap();
+        std::fs::write(dir.join("b/c/a.txt"), "a").unwrap();
+        std::fs::write(dir.join("b/a b.txt"), "ab").unwrap();
+
+        let manifest = Manifest::build(&dir).unwrap();
+        let paths: Vec<_> = manifest.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, ["b/a b.txt", "b/c/a.txt", "z.txt"]);
+        let bytes = manifest.to_bytes().unwrap();
+        assert_eq!(Manifest::build(&dir).unwrap().to_bytes().unwrap(), bytes);
+
+        let verdict = verify(&bytes, &dir, None).unwrap();
+        assert!(verdict.valid);
+        assert_eq!(verdict.notarized, None);
+
+        std::fs::write(dir.join("z.txt"), "Z").unwrap();
+        std::fs::remove_file(dir.join("b/c/a.txt")).unwrap();
+        std::fs::write(dir.join("new.txt"), "").unwrap();
+        let verdict = verify(&bytes, &dir, None).unwrap();
+        assert!(!verdict.valid);
+        assert_eq!(verdict.changed, ["z.txt"]);
+        assert_eq!(verdict.missing, ["b/c/a.txt"]);
+        assert_eq!(verdict.added, ["new.txt"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,266 @@
+//! `rl notarize`: append a file's name, size and SHA-256 digest, optionally
+//! wait for an anchor that covers the entry, and write an `.rlproof`
+//! receipt next to the file. The file itself never leaves the machine.
+
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use reality_client::Client;
+use reality_core::{receipt::AnchorReceipt, AnchorQuery, AnchorStatus, BatchEntry};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::bundle::{self, Create};
+
+/// The type of the entries `rl notarize` appends.
+pub const ENTRY_TYPE: &str = "notarization";
+
+/// How far an anchor's timestamp may run behind logd's clock and still be
+/// considered, since the two can run on different hosts.
+const CLOCK_SLACK: Duration = Duration::from_secs(300);
+
+pub struct Notarize {
+    pub path: PathBuf,
+    pub submitter: Option<String>,
+    pub out: Option<PathBuf>,
+    /// Where to look for anchors and fetch their receipts, when waiting.
+    pub wait: Option<AnchorWait>,
+}
+
+pub struct AnchorWait {
+    /// The anchor daemon's API, or `None` to poll logd's `/anchors`.
+    pub anchor_api: Option<Client>,
+    pub timeout: Duration,
+    pub interval: Duration,
+}
+
+/// The appended entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Notarization {
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// The file's name, without its directory.
+    pub filename: String,
+    pub size: u64,
+    /// Hex SHA-256 of the file's contents.
+    pub sha256: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submitter: Option<String>,
+}
+
+/// What `rl notarize` did.
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    #[serde(flatten)]
+    pub notarization: Notarization,
+    #[serde(flatten)]
+    pub recorded: Recorded,
+}
+
+impl Notarize {
+    pub async fn run(self, api: &Client) -> anyhow::Result<Summary> {
+        let (size, sha256) = hash_file(&self.path)?;
+        let filename = self
+            .path
+            .file_name()
+            .with_context(|| format!("{} names no file", self.path.display()))?
+            .to_string_lossy()
+            .into_owned();
+        let notarization = Notarization {
+            kind: ENTRY_TYPE.to_string(),
+            filename,
+            size,
+            sha256,
+            submitter: self.submitter,
+        };
+        let payload = serde_json::to_string(&notarization)?;
+        let out = self
+            .out
+            .unwrap_or_else(|| PathBuf::from(format!("{}.rlproof", self.path.display())));
+        let recorded = record(api, ENTRY_TYPE, payload, out, self.wait.as_ref()).await?;
+        Ok(Summary {
+            notarization,
+            recorded,
+        })
+    }
+}
+
+/// Where an entry went and the receipt written for it.
+#[derive(Debug, Serialize)]
+pub struct Recorded {
+    pub index: u64,
+    /// An entry with the same payload was appended before, at `index`.
+    pub duplicate: bool,
+    /// The txid of the anchor in the receipt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<String>,
+    pub receipt: bundle::Summary,
+}
+
+/// Append `payload`, wait for an anchor if asked to, and write the entry's
+/// `.rlproof` to `out`.
+pub async fn record(
+    api: &Client,
+    kind: &str,
+    payload: String,
+    out: PathBuf,
+    wait: Option<&AnchorWait>,
+) -> anyhow::Result<Recorded> {
+    // Keyed by content, so recording the same thing again finds the entry
+    // it already has instead of adding another.
+    let key = format!("{kind}:{}", hex::encode(Sha256::digest(&payload)));
+    let since = SystemTime::now().duration_since(UNIX_EPOCH)? - CLOCK_SLACK;
+    let response = api
+        .append_batch(vec![BatchEntry {
+            payload,
+            idempotency_key: Some(key),
+            labels: Default::default(),
+            signature: None,
+        }])
+        .await?;
+    let appended = response
+        .entries
+        .first()
+        .context("logd acknowledged no entry")?;
+    let index = appended.index;
+
+    let anchors = match wait {
+        Some(wait) => {
+            let source = wait.anchor_api.as_ref().unwrap_or(api);
+            let anchor = wait_for_anchor(source, index, since, wait).await?;
+            let receipt = match &wait.anchor_api {
+                Some(anchor_api) => anchor_api
+                    .anchor_receipt(&anchor.anchor.record.txid)
+                    .await
+                    .context("fetch the anchor receipt")?,
+                None => anchor,
+            };
+            vec![receipt]
+        }
+        None => Vec::new(),
+    };
+    let anchor = anchors
+        .first()
+        .map(|receipt| receipt.anchor.record.txid.clone());
+    let receipt = Create {
+        index,
+        out,
+        detach: false,
+        anchors,
+        witnesses: false,
+    }
+    .run(api)
+    .await
+    .context("write the receipt; logd needs REALITY_LOG_SIGNER to sign one")?;
+    Ok(Recorded {
+        index,
+        duplicate: appended.duplicate,
+        anchor,
+        receipt,
+    })
+}
+
+pub fn describe(summary: &Summary) -> String {
+    let notarization = &summary.notarization;
+    let mut out = format!(
+        "{} {} ({} bytes) as entry {}\nsha256  {}\n",
+        if summary.recorded.duplicate {
+            "already notarized"
+        } else {
+            "notarized"
+        },
+        notarization.filename,
+        notarization.size,
+        summary.recorded.index,
+        notarization.sha256
+    );
+    if let Some(submitter) = &notarization.submitter {
+        out += &format!("by      {submitter}\n");
+    }
+    out + &describe_recorded(&summary.recorded)
+}
+
+pub fn describe_recorded(recorded: &Recorded) -> String {
+    let mut out = String::new();
+    if let Some(txid) = &recorded.anchor {
+        out += &format!("anchor  {txid}\n");
+    }
+    out + &bundle::describe(&recorded.receipt)
+}
+
+/// The file's size and hex SHA-256, read in chunks.
+pub fn hash_file(path: &Path) -> anyhow::Result<(u64, String)> {
+    let mut file = File::open(path).with_context(|| format!("open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let read = file
+            .read(&mut buf)
+            .with_context(|| format!("read {}", path.display()))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+        size += read as u64;
+    }
+    Ok((size, hex::encode(hasher.finalize())))
+}
+
+/// Poll `source` until a confirmed anchor covers entry `index`. Without an
+/// anchor daemon to ask, the receipt is the stored anchor alone, which is
+/// enough unless the anchor was batched.
+async fn wait_for_anchor(
+    source: &Client,
+    index: u64,
+    since: Duration,
+    wait: &AnchorWait,
+) -> anyhow::Result<AnchorReceipt> {
+    let query = AnchorQuery {
+        from_nanos: Some(since.as_nanos().try_into()?),
+        status: Some(AnchorStatus::Confirmed),
+        ..Default::default()
+    };
+    let deadline = Instant::now() + wait.timeout;
+    loop {
+        let anchors = source.anchors(&query).await.context("list anchors")?;
+        if let Some(anchor) = anchors
+            .into_iter()
+            .filter(|anchor| anchor.record.size > index)
+            .min_by_key(|anchor| anchor.record.size)
+        {
+            return Ok(AnchorReceipt {
+                anchor,
+                batch: None,
+            });
+        }
+        anyhow::ensure!(
+            Instant::now() + wait.interval < deadline,
+            "no anchor covered entry {index} within {}s; it is appended, and \
+             `rl bundle create {index} --anchor <receipt>` can add one later",
+            wait.timeout.as_secs()
+        );
+        tokio::time::sleep(wait.interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_files_in_chunks() {
+        let path = std::env::temp_dir().join(format!("rl-notarize-{}", std::process::id()));
+        let contents: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+        std::fs::write(&path, &contents).unwrap();
+        let (size, digest) = hash_file(&path).unwrap();
+        assert_eq!(size, 200_000);
+        assert_eq!(digest, hex::encode(Sha256::digest(&contents)));
+        std::fs::remove_file(path).unwrap();
+    }
+}
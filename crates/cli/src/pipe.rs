@@ -0,0 +1,140 @@
+//! `rl append --stdin`: append what a shell pipeline writes, printing each
+//! record's index, leaf and root as soon as logd has it.
+
+use std::io::BufRead;
+
+use anyhow::Context;
+use reality_client::Client;
+use reality_core::AppendResponse;
+
+/// Reads payloads from the input: each non-empty line, or the whole input
+/// once with `blob`.
+pub struct Records<R> {
+    input: R,
+    blob: bool,
+    /// Number of the last line read, for error messages.
+    line: u64,
+    done: bool,
+}
+
+impl<R: BufRead> Records<R> {
+    pub fn new(input: R, blob: bool) -> Self {
+        Self {
+            input,
+            blob,
+            line: 0,
+            done: false,
+        }
+    }
+
+    fn read_blob(&mut self) -> anyhow::Result<String> {
+        let mut bytes = Vec::new();
+        self.input.read_to_end(&mut bytes).context("read stdin")?;
+        String::from_utf8(bytes).context("stdin is not UTF-8")
+    }
+
+    /// The next non-empty line without its line break, or `None` at the end.
+    fn read_line(&mut self) -> anyhow::Result<Option<String>> {
+        loop {
+            let mut bytes = Vec::new();
+            if self
+                .input
+                .read_until(b'\n', &mut bytes)
+                .context("read stdin")?
+                == 0
+            {
+                return Ok(None);
+            }
+            self.line += 1;
+            if bytes.ends_with(b"\n") {
+                bytes.pop();
+                if bytes.ends_with(b"\r") {
+                    bytes.pop();
+                }
+            }
+            if bytes.is_empty() {
+                continue;
+            }
+            let line = self.line;
+            return String::from_utf8(bytes)
+                .map(Some)
+                .with_context(|| format!("line {line} is not UTF-8"));
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = anyhow::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if self.blob {
+            self.done = true;
+            return Some(self.read_blob());
+        }
+        let record = self.read_line().transpose();
+        self.done = !matches!(record, Some(Ok(_)));
+        record
+    }
+}
+
+/// Append every record in order, one request each, and print each result
+/// on its own line. Returns how many were appended.
+pub async fn append_all(
+    api: &Client,
+    records: Records<impl BufRead>,
+    json: bool,
+) -> anyhow::Result<u64> {
+    let mut appended = 0;
+    for payload in records {
+        let response = api
+            .append(payload?)
+            .await
+            .with_context(|| format!("append record {}", appended + 1))?;
+        print_record(json, &response);
+        appended += 1;
+    }
+    Ok(appended)
+}
+
+fn print_record(json: bool, appended: &AppendResponse) {
+    if json {
+        // One object per line so the output can be piped as it arrives.
+        println!(
+            "{}",
+            serde_json::to_string(appended).expect("responses serialize")
+        );
+    } else {
+        println!(
+            "{:>8}  {}  {}",
+            appended.index, appended.leaf, appended.root
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect(input: &[u8], blob: bool) -> Vec<Result<String, String>> {
+        Records::new(input, blob)
+            .map(|record| record.map_err(|err| err.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn splits_lines_or_keeps_the_blob() {
+        assert_eq!(
+            collect(b"one\r\n\ntwo\nthree", false),
+            [Ok("one".into()), Ok("two".into()), Ok("three".into())]
+        );
+        assert_eq!(collect(b"one\n\ntwo\n", true), [Ok("one\n\ntwo\n".into())]);
+        assert_eq!(
+            collect(b"ok\n\xff\nlater\n", false),
+            [Ok("ok".into()), Err("line 2 is not UTF-8".into())]
+        );
+        assert!(collect(b"", false).is_empty());
+    }
+}
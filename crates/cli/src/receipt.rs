@@ -0,0 +1,349 @@
+//! Offline checks for `rl verify-receipt`. Core stops at the first failed
+//! link; this spells the chain out step by step so a reader can see what
+//! held, what broke and what was never checked.
+
+use anyhow::Context;
+use reality_core::{
+    bundle::{BundleError, EntryBundle},
+    receipt::{AnchorReceipt, AnchoredProof, ReceiptContext, ReceiptError},
+};
+use serde::Serialize;
+
+/// A receipt as stored by the user: `/bundle/N`, `/prove/N?at_anchor=<txid>`
+/// or the anchor daemon's `/receipt/<txid>`.
+#[derive(Debug)]
+pub enum Receipt {
+    Entry(Box<EntryBundle>),
+    Anchored(Box<AnchoredProof>),
+    Anchor(Box<AnchorReceipt>),
+}
+
+impl Receipt {
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<Self> {
+        if let Ok(bundle) = serde_json::from_slice(bytes) {
+            return Ok(Self::Entry(Box::new(bundle)));
+        }
+        if let Ok(anchored) = serde_json::from_slice(bytes) {
+            return Ok(Self::Anchored(Box::new(anchored)));
+        }
+        let receipt = serde_json::from_slice(bytes)
+            .context("expected an EntryBundle, AnchoredProof or AnchorReceipt as JSON")?;
+        Ok(Self::Anchor(Box::new(receipt)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Pass,
+    Fail,
+    Skipped,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+}
+
+/// Outcome of `rl verify-receipt`.
+#[derive(Debug, Serialize)]
+pub struct ReceiptVerdict {
+    pub kind: &'static str,
+    pub valid: bool,
+    pub checks: Vec<Check>,
+}
+
+/// Checks in the order `EntryBundle::verify` runs them.
+pub const BUNDLE_CHECKS: [(&str, &str); 5] = [
+    ("payload", "payload hashes to the proof's leaf"),
+    ("inclusion", "inclusion proof leads to its root"),
+    ("checkpoint", "proof root and size match the checkpoint"),
+    ("signature", "checkpoint signature is valid"),
+    ("log key", "checkpoint is signed by the trusted log key"),
+];
+
+/// Checks in the order `AnchorReceipt::verify` runs them.
+const ANCHOR_CHECKS: [(&str, &str); 5] = [
+    ("txid", "txid recomputes from the anchored checkpoint"),
+    ("signature", "anchor signature is valid"),
+    ("inclusion", "inclusion proof leads to the anchored root"),
+    ("previous", "log only grew since the previous anchor"),
+    ("consistency", "consistency proof links the previous anchor"),
+];
+
+/// Checks in the order `AnchoredProof::verify` runs them.
+const ANCHORED_CHECKS: [(&str, &str); 4] = [
+    ("txid", "txid recomputes from the anchored checkpoint"),
+    ("signature", "anchor signature is valid"),
+    ("inclusion", "inclusion proof leads to the anchored root"),
+    (
+        "current",
+        "consistency proof leads on from the anchored root",
+    ),
+];
+
+pub fn verify_bundle(bundle: &EntryBundle, log_key: Option<&str>) -> ReceiptVerdict {
+    let failed = bundle
+        .verify(log_key)
+        .err()
+        .map(|err| (bundle_step(&err), err.to_string()));
+    let skipped = |step| match step {
+        4 if log_key.is_none() => Some("no --log-key given; any valid signature was accepted"),
+        _ => None,
+    };
+    verdict("entry_bundle", &BUNDLE_CHECKS, failed, skipped)
+}
+
+/// The step of `BUNDLE_CHECKS` that failed with `err`.
+pub fn bundle_step(err: &BundleError) -> usize {
+    match err {
+        BundleError::LeafMismatch => 0,
+        BundleError::InvalidProof { .. } => 1,
+        BundleError::CheckpointMismatch => 2,
+        BundleError::BadSignature => 3,
+        BundleError::UntrustedKey(_) => 4,
+    }
+}
+
+pub fn verify_anchor(receipt: &AnchorReceipt, context: &ReceiptContext) -> ReceiptVerdict {
+    let (failed, report) = match receipt.verify(context) {
+        Ok(report) => (None, Some(report)),
+        Err(err) => {
+            let step = match err {
+                ReceiptError::TxidMismatch { .. }
+                | ReceiptError::NotInBatch { .. }
+                | ReceiptError::MissingBatch
+                | ReceiptError::InvalidBatchProof => 0,
+                ReceiptError::BadSignature
+                | ReceiptError::UntrustedKey(_)
+                | ReceiptError::Unsigned => 1,
+                ReceiptError::ProofMismatch | ReceiptError::InvalidProof => 2,
+                ReceiptError::NotAfterPrevious => 3,
+                ReceiptError::InvalidConsistency | ReceiptError::NotFromAnchor => 4,
+            };
+            (Some((step, err.to_string())), None)
+        }
+    };
+    let skipped = |step| match step {
+        0 if report.is_some_and(|report| !report.txid_checked) => {
+            Some("this backend's transactions cannot be recomputed offline")
+        }
+        1 if report.is_some_and(|report| !report.signature_checked) => {
+            Some("the anchor is unsigned")
+        }
+        2 if context.proof.is_none() => Some("no --proof given"),
+        3 if context.previous.is_none() => Some("no --previous anchor given"),
+        4 if context.consistency.is_none() => Some("no --consistency proof given"),
+        _ => None,
+    };
+    verdict("anchor_receipt", &ANCHOR_CHECKS, failed, skipped)
+}
+
+pub fn verify_anchored(anchored: &AnchoredProof, anchor_key: Option<&str>) -> ReceiptVerdict {
+    let (failed, report) = match anchored.verify(anchor_key) {
+        Ok(report) => (None, Some(report)),
+        Err(err) => {
+            let step = match err {
+                ReceiptError::TxidMismatch { .. }
+                | ReceiptError::NotInBatch { .. }
+                | ReceiptError::MissingBatch
+                | ReceiptError::InvalidBatchProof => 0,
+                ReceiptError::BadSignature
+                | ReceiptError::UntrustedKey(_)
+                | ReceiptError::Unsigned => 1,
+                ReceiptError::ProofMismatch | ReceiptError::InvalidProof => 2,
+                ReceiptError::NotAfterPrevious
+                | ReceiptError::InvalidConsistency
+                | ReceiptError::NotFromAnchor => 3,
+            };
+            (Some((step, err.to_string())), None)
+        }
+    };
+    let skipped = |step| match step {
+        0 if report.is_some_and(|report| !report.txid_checked) => {
+            Some("this backend's transactions cannot be recomputed offline")
+        }
+        1 if report.is_some_and(|report| !report.signature_checked) => {
+            Some("the anchor is unsigned")
+        }
+        _ => None,
+    };
+    verdict("anchored_proof", &ANCHORED_CHECKS, failed, skipped)
+}
+
+/// Steps before `failed` passed unless `skipped` says otherwise; steps after
+/// it were never reached.
+pub fn verdict(
+    kind: &'static str,
+    steps: &[(&'static str, &str)],
+    failed: Option<(usize, String)>,
+    skipped: impl Fn(usize) -> Option<&'static str>,
+) -> ReceiptVerdict {
+    let checks = steps
+        .iter()
+        .enumerate()
+        .map(|(step, &(name, passed))| {
+            let (status, detail) = match &failed {
+                Some((at, err)) if *at == step => (Status::Fail, err.clone()),
+                Some((at, _)) if *at < step => (Status::Skipped, "not reached".to_string()),
+                _ => match skipped(step) {
+                    Some(reason) => (Status::Skipped, reason.to_string()),
+                    None => (Status::Pass, passed.to_string()),
+                },
+            };
+            Check {
+                name,
+                status,
+                detail,
+            }
+        })
+        .collect();
+    ReceiptVerdict {
+        kind,
+        valid: failed.is_none(),
+        checks,
+    }
+}
+
+pub fn describe(verdict: &ReceiptVerdict) -> String {
+    let mut text = describe_checks(verdict);
+    text += if verdict.valid {
+        "RESULT: PASS\n"
+    } else {
+        "RESULT: FAIL\n"
+    };
+    text
+}
+
+/// The verdict's kind and checks, without the result line, for commands
+/// that add checks of their own.
+pub fn describe_checks(verdict: &ReceiptVerdict) -> String {
+    let mut text = format!("{}\n", verdict.kind.replace('_', " "));
+    for check in &verdict.checks {
+        text += &describe_check(check.status, check.name, &check.detail);
+    }
+    text
+}
+
+pub fn describe_check(status: Status, name: &str, detail: &str) -> String {
+    let status = match status {
+        Status::Pass => "PASS",
+        Status::Fail => "FAIL",
+        Status::Skipped => "skip",
+    };
+    format!("  {status}  {name:<12}{detail}\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reality_core::{
+        checkpoint::Checkpoint, leaf_hash, make_proof, signer::KeyFileSigner, AnchorMetadata,
+        AnchorRecord, AnchorStatus, StoredAnchor, ANCHOR_RECORD_VERSION,
+    };
+
+    fn statuses(verdict: &ReceiptVerdict) -> Vec<Status> {
+        verdict.checks.iter().map(|check| check.status).collect()
+    }
+
+    #[test]
+    fn reports_each_link_of_a_receipt() {
+        let leaves: Vec<[u8; 32]> = ["a", "b", "c"].map(|p| leaf_hash(p.as_bytes())).into();
+        let proof = make_proof(&leaves, 1).unwrap();
+        let checkpoint = Checkpoint {
+            size: 3,
+            root: proof.root.clone(),
+            timestamp_nanos: "1".into(),
+        }
+        .sign(&KeyFileSigner::from_seed([7u8; 32]))
+        .unwrap();
+        let bundle = EntryBundle {
+            payload: "b".into(),
+            meta: None,
+            proof: proof.clone(),
+            checkpoint,
+        };
+        let json = serde_json::to_vec(&bundle).unwrap();
+        let Receipt::Entry(parsed) = Receipt::parse(&json).unwrap() else {
+            panic!("bundle parsed as an anchor receipt");
+        };
+
+        let verdict = verify_bundle(&parsed, None);
+        assert!(verdict.valid);
+        assert_eq!(statuses(&verdict)[3..], [Status::Pass, Status::Skipped]);
+        assert!(describe(&verdict).ends_with("RESULT: PASS\n"));
+
+        let mut tampered = bundle;
+        tampered.checkpoint.checkpoint.size = 4;
+        let verdict = verify_bundle(&tampered, None);
+        assert!(!verdict.valid);
+        assert_eq!(
+            statuses(&verdict),
+            [
+                Status::Pass,
+                Status::Pass,
+                Status::Fail,
+                Status::Skipped,
+                Status::Skipped
+            ]
+        );
+
+        let timestamp = "1700000000000000000";
+        let anchor = AnchorReceipt {
+            anchor: StoredAnchor {
+                record: AnchorRecord {
+                    version: ANCHOR_RECORD_VERSION,
+                    root: proof.root.clone(),
+                    size: 3,
+                    timestamp_nanos: timestamp.into(),
+                    txid: reality_core::receipt::simulated_txid(3, &proof.root, timestamp),
+                    metadata: AnchorMetadata::default(),
+                },
+                status: AnchorStatus::Confirmed,
+                signature: None,
+            },
+            batch: None,
+        };
+        let json = serde_json::to_vec(&anchor).unwrap();
+        assert!(matches!(Receipt::parse(&json), Ok(Receipt::Anchor(_))));
+        let context = ReceiptContext {
+            proof: Some(proof),
+            ..ReceiptContext::default()
+        };
+        let verdict = verify_anchor(&anchor, &context);
+        assert!(verdict.valid);
+        assert_eq!(
+            statuses(&verdict),
+            [
+                Status::Pass,
+                Status::Skipped,
+                Status::Pass,
+                Status::Skipped,
+                Status::Skipped
+            ]
+        );
+
+        let anchored = AnchoredProof {
+            proof: context.proof.clone().unwrap(),
+            consistency: reality_core::consistency::consistency_proof(&leaves, 3, leaves.len())
+                .unwrap(),
+            anchor,
+        };
+        let json = serde_json::to_vec(&anchored).unwrap();
+        let Ok(Receipt::Anchored(parsed)) = Receipt::parse(&json) else {
+            panic!("anchored proof not recognized");
+        };
+        let verdict = verify_anchored(&parsed, None);
+        assert!(verdict.valid);
+        assert_eq!(
+            statuses(&verdict)[1..],
+            [Status::Skipped, Status::Pass, Status::Pass]
+        );
+        let mut stale = anchored;
+        stale.consistency.old_size = 2;
+        assert_eq!(statuses(&verify_anchored(&stale, None))[3], Status::Fail);
+        assert!(Receipt::parse(b"{}").is_err());
+    }
+}
@@ -0,0 +1,334 @@
+//! `rl site`: render the log as a static website that any static host can
+//! serve. Pages list the entries, each entry's inclusion proof is a
+//! downloadable JSON file, and `checkpoint.json` is the log's signed
+//! checkpoint for the tree every proof leads to. With the wasm package the
+//! pages check all of it in the reader's browser.
+//!
+//! ```text
+//! index.html            checkpoint summary and page index
+//! checkpoint.json       SignedCheckpoint from logd's /checkpoint
+//! entries/<page>.html   entries, numbered from 1
+//! proofs/<index>.json   InclusionProof of each entry
+//! assets/               site.js and style.css
+//! pkg/                  reality-wasm-core built with `--target web`
+//! ```
+
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use reality_client::Client;
+use reality_core::{checkpoint::SignedCheckpoint, MerkleTree};
+use serde::Serialize;
+
+use crate::export;
+
+const SCRIPT: &str = include_str!("site/site.js");
+const STYLE: &str = include_str!("site/style.css");
+/// Files of the wasm package the pages load.
+const WASM_FILES: [&str; 2] = ["reality_wasm_core.js", "reality_wasm_core_bg.wasm"];
+
+pub struct Site {
+    pub out: PathBuf,
+    pub title: String,
+    pub page_size: usize,
+    /// Hex key the pages check the checkpoint against. Without it they can
+    /// only show who signed it.
+    pub log_key: Option<String>,
+    /// `wasm-pack build --target web` output to copy into `pkg/`.
+    pub wasm: Option<PathBuf>,
+}
+
+/// What was written.
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub out: PathBuf,
+    pub size: u64,
+    pub root: String,
+    pub signed_by: String,
+    pub pages: usize,
+    /// Whether the pages can verify in the browser.
+    pub wasm: bool,
+}
+
+impl Site {
+    /// Fetch logd's signed checkpoint and the entries under it, and write the
+    /// site. The entries must rebuild the checkpoint's root.
+    pub async fn run(&self, api: &Client) -> anyhow::Result<Summary> {
+        let checkpoint = api
+            .checkpoint()
+            .await
+            .context("fetch the signed checkpoint; the site needs logd to have a signer")?;
+        anyhow::ensure!(
+            checkpoint.verify(),
+            "logd's checkpoint signature is invalid"
+        );
+        if let Some(key) = &self.log_key {
+            anyhow::ensure!(
+                checkpoint.verify_with(key),
+                "logd's checkpoint is signed by {}, not {key}",
+                checkpoint.signature.public_key
+            );
+        }
+        let head = &checkpoint.checkpoint;
+        let (leaves, payloads) = export::fetch(api, head.size, &head.root, true).await?;
+        let payloads = payloads.unwrap_or_default();
+        self.write(&checkpoint, &leaves, &payloads)
+    }
+
+    /// Write proofs, pages and assets, then `checkpoint.json` and the index
+    /// last, so the published checkpoint never names entries that are not
+    /// there yet.
+    fn write(
+        &self,
+        checkpoint: &SignedCheckpoint,
+        leaves: &[[u8; 32]],
+        payloads: &[String],
+    ) -> anyhow::Result<Summary> {
+        anyhow::ensure!(self.page_size > 0, "--page-size must be at least 1");
+        let tree = MerkleTree::new(leaves);
+        for index in 0..leaves.len() {
+            let proof = tree.prove(index)?;
+            write(
+                &self.out,
+                &format!("proofs/{index}.json"),
+                serde_json::to_string_pretty(&proof)?,
+            )?;
+        }
+
+        let pages = payloads.len().div_ceil(self.page_size).max(1);
+        for page in 1..=pages {
+            let first = (page - 1) * self.page_size;
+            let entries = payloads.iter().enumerate().skip(first).take(self.page_size);
+            let html = self.entries_page(page, pages, entries, leaves);
+            write(&self.out, &format!("entries/{page}.html"), html)?;
+        }
+        write(&self.out, "assets/site.js", SCRIPT)?;
+        write(&self.out, "assets/style.css", STYLE)?;
+        let wasm = match &self.wasm {
+            Some(pkg) => {
+                copy_wasm(pkg, &self.out.join("pkg"))?;
+                true
+            }
+            None => self.out.join("pkg").join(WASM_FILES[1]).exists(),
+        };
+
+        write(
+            &self.out,
+            "checkpoint.json",
+            serde_json::to_string_pretty(checkpoint)?,
+        )?;
+        write(&self.out, "index.html", self.index_page(checkpoint, pages))?;
+        Ok(Summary {
+            out: self.out.clone(),
+            size: checkpoint.checkpoint.size,
+            root: checkpoint.checkpoint.root.clone(),
+            signed_by: checkpoint.signature.public_key.clone(),
+            pages,
+            wasm,
+        })
+    }
+
+    fn index_page(&self, checkpoint: &SignedCheckpoint, pages: usize) -> String {
+        let head = &checkpoint.checkpoint;
+        let mut body = format!(
+            "<section class=\"checkpoint\">\n<h2>Checkpoint</h2>\n<dl>\n\
+             <dt>Entries</dt><dd>{}</dd>\n\
+             <dt>Root</dt><dd><code>{}</code></dd>\n\
+             <dt>Signed at</dt><dd><code>{}</code> ns since the epoch</dd>\n\
+             <dt>Signed by</dt><dd><code>{}</code></dd>\n</dl>\n\
+             <p><a href=\"checkpoint.json\" download>checkpoint.json</a></p>\n\
+             <p class=\"status\" id=\"checkpoint-status\"></p>\n</section>\n",
+            head.size,
+            escape(&head.root),
+            escape(&head.timestamp_nanos),
+            escape(&checkpoint.signature.public_key),
+        );
+        body += "<section>\n<h2>Entries</h2>\n<ol class=\"pages\">\n";
+        for page in 1..=pages {
+            let first = (page - 1) * self.page_size;
+            let last = (page * self.page_size).min(head.size as usize);
+            let range = if first < last {
+                format!("{first}&ndash;{}", last - 1)
+            } else {
+                "empty".into()
+            };
+            let _ = writeln!(
+                body,
+                "<li><a href=\"entries/{page}.html\">Page {page}</a> <span>{range}</span></li>"
+            );
+        }
+        body += "</ol>\n</section>\n";
+        self.page(&escape(&self.title), "", &body)
+    }
+
+    fn entries_page<'a>(
+        &self,
+        page: usize,
+        pages: usize,
+        entries: impl Iterator<Item = (usize, &'a String)>,
+        leaves: &[[u8; 32]],
+    ) -> String {
+        let nav = nav(page, pages);
+        let mut body = nav.clone();
+        body += "<table class=\"entries\">\n\
+                 <tr><th>#</th><th>Payload</th><th>Leaf</th><th>Proof</th></tr>\n";
+        for (index, payload) in entries {
+            let _ = writeln!(
+                body,
+                "<tr id=\"entry-{index}\" data-index=\"{index}\">\
+                 <td>{index}</td>\
+                 <td><pre class=\"payload\">\n{}</pre></td>\
+                 <td><code>{}</code></td>\
+                 <td><a href=\"../proofs/{index}.json\" download>JSON</a> \
+                 <button type=\"button\" class=\"verify\">Verify</button> \
+                 <span class=\"status\"></span></td></tr>",
+                escape(payload),
+                hex::encode(leaves[index]),
+            );
+        }
+        body += "</table>\n";
+        body += &nav;
+        let title = format!("{} &mdash; page {page} of {pages}", escape(&self.title));
+        self.page(&title, "../", &body)
+    }
+
+    /// A page whose links to the site root start with `root`.
+    fn page(&self, title: &str, root: &str, body: &str) -> String {
+        let log_key = self.log_key.as_deref().map(escape).unwrap_or_default();
+        format!(
+            "<!doctype html>\n<html lang=\"en\" data-root=\"{root}\" data-log-key=\"{log_key}\">\n\
+             <head>\n<meta charset=\"utf-8\">\n\
+             <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+             <title>{title}</title>\n\
+             <link rel=\"stylesheet\" href=\"{root}assets/style.css\">\n\
+             <script type=\"module\" src=\"{root}assets/site.js\"></script>\n\
+             </head>\n<body>\n<header><h1><a href=\"{root}index.html\">{}</a></h1></header>\n\
+             <main>\n{body}</main>\n\
+             <footer><p id=\"verifier\">Checking in the browser needs the RealityLog wasm \
+             verifier in <code>pkg/</code>.</p></footer>\n</body>\n</html>\n",
+            escape(&self.title),
+        )
+    }
+}
+
+pub fn describe(summary: &Summary) -> String {
+    let mut text = format!(
+        "wrote {} entries on {} pages to {}\nroot       {}\nsigned by  {}\n",
+        summary.size,
+        summary.pages,
+        summary.out.display(),
+        summary.root,
+        summary.signed_by
+    );
+    if !summary.wasm {
+        text += "no wasm verifier in pkg/; pass --wasm to let readers verify in the browser\n";
+    }
+    text
+}
+
+fn nav(page: usize, pages: usize) -> String {
+    let mut nav = String::from("<nav class=\"pager\">");
+    if page > 1 {
+        let _ = write!(nav, "<a href=\"{}.html\">&larr; previous</a> ", page - 1);
+    }
+    let _ = write!(nav, "<span>page {page} of {pages}</span>");
+    if page < pages {
+        let _ = write!(nav, " <a href=\"{}.html\">next &rarr;</a>", page + 1);
+    }
+    nav + "</nav>\n"
+}
+
+fn copy_wasm(pkg: &Path, to: &Path) -> anyhow::Result<()> {
+    std::fs::create_dir_all(to).with_context(|| format!("create {}", to.display()))?;
+    for name in WASM_FILES {
+        let from = pkg.join(name);
+        std::fs::copy(&from, to.join(name)).with_context(|| {
+            format!(
+                "copy {}; build it with `wasm-pack build web/wasm-core --target web`",
+                from.display()
+            )
+        })?;
+    }
+    Ok(())
+}
+
+fn write(out: &Path, name: &str, contents: impl AsRef<[u8]>) -> anyhow::Result<()> {
+    let path = out.join(name);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("create {}", dir.display()))?;
+    }
+    std::fs::write(&path, contents).with_context(|| format!("write {}", path.display()))
+}
+
+/// Escape `text` for HTML text or attribute values. The text of a `<pre>`
+/// must also start with a newline, which parsers drop, so a payload's own
+/// leading newline survives.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            // Parsers turn a raw CR into LF, which would change the hash
+            // the page computes.
+            '\r' => escaped.push_str("&#13;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reality_core::{
+        checkpoint::Checkpoint, leaf_hash, root, signer::KeyFileSigner, verify, InclusionProof,
+        VerifyRequest,
+    };
+
+    #[test]
+    fn writes_pages_proofs_and_the_checkpoint() {
+        let out = std::env::temp_dir().join(format!("rl-site-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&out);
+        let payloads: Vec<String> = (0..5).map(|i| format!("<b>entry {i}</b>")).collect();
+        let leaves: Vec<[u8; 32]> = payloads.iter().map(|p| leaf_hash(p.as_bytes())).collect();
+        let checkpoint = Checkpoint {
+            size: 5,
+            root: hex::encode(root(&leaves)),
+            timestamp_nanos: "1".into(),
+        }
+        .sign(&KeyFileSigner::from_seed([5; 32]))
+        .unwrap();
+        let site = Site {
+            out: out.clone(),
+            title: "Audit <log>".into(),
+            page_size: 2,
+            log_key: Some(checkpoint.signature.public_key.clone()),
+            wasm: None,
+        };
+        let summary = site.write(&checkpoint, &leaves, &payloads).unwrap();
+        assert_eq!((summary.pages, summary.wasm), (3, false));
+
+        let proof: InclusionProof =
+            serde_json::from_slice(&std::fs::read(out.join("proofs/4.json")).unwrap()).unwrap();
+        assert!(verify(&VerifyRequest::from(&proof)).valid);
+        assert_eq!(proof.root, checkpoint.checkpoint.root);
+        let page = std::fs::read_to_string(out.join("entries/3.html")).unwrap();
+        assert!(page.contains("<pre class=\"payload\">\n&lt;b&gt;entry 4&lt;/b&gt;</pre>"));
+        assert!(page.contains("<a href=\"2.html\">&larr; previous</a>"));
+        assert!(!page.contains("next"));
+        let index = std::fs::read_to_string(out.join("index.html")).unwrap();
+        assert!(index.contains("<title>Audit &lt;log&gt;</title>"));
+        assert!(index.contains("entries/3.html"));
+        let written: SignedCheckpoint =
+            serde_json::from_slice(&std::fs::read(out.join("checkpoint.json")).unwrap()).unwrap();
+        assert_eq!(written, checkpoint);
+        std::fs::remove_dir_all(&out).unwrap();
+    }
+}
@@ -0,0 +1,102 @@
+// Checks a page of an `rl site` in the reader's browser with the RealityLog
+// wasm verifier: the checkpoint's signature, then for each entry that its
+// payload hashes to the proof's leaf and that the proof leads to the
+// checkpoint's root.
+
+const root = document.documentElement.dataset.root ?? "";
+const pinnedKey = document.documentElement.dataset.logKey || null;
+
+let wasm = null;
+let checkpoint = null;
+
+function show(element, ok, text) {
+  element.textContent = text;
+  element.classList.toggle("ok", ok);
+  element.classList.toggle("bad", !ok);
+}
+
+async function fetchJson(path) {
+  const response = await fetch(root + path);
+  if (!response.ok) {
+    throw new Error(`${path}: ${response.status}`);
+  }
+  return response.json();
+}
+
+// The signed checkpoint and whether it verifies, fetched once per page.
+async function loadCheckpoint() {
+  if (checkpoint === null) {
+    const signed = await fetchJson("checkpoint.json");
+    const key = pinnedKey ?? signed.public_key;
+    const result = wasm.verify_checkpoint(signed, key);
+    checkpoint = { signed, result, pinned: pinnedKey !== null };
+  }
+  return checkpoint;
+}
+
+function describeCheckpoint({ result, pinned }) {
+  if (!result.valid) {
+    return `checkpoint refused: ${result.error}`;
+  }
+  return pinned
+    ? "checkpoint signature verified against the log's key"
+    : "checkpoint signature verified against the key it names; compare that key with the one the log publishes";
+}
+
+async function verifyEntry(row) {
+  const status = row.querySelector(".status");
+  try {
+    const { signed, result } = await loadCheckpoint();
+    if (!result.valid) {
+      show(status, false, describeCheckpoint(checkpoint));
+      return;
+    }
+    const proof = await fetchJson(`proofs/${row.dataset.index}.json`);
+    const payload = row.querySelector(".payload").textContent;
+    if (wasm.leaf_hash_of_utf8(payload) !== proof.leaf.toLowerCase()) {
+      show(status, false, "payload does not hash to the proof's leaf");
+      return;
+    }
+    const inclusion = wasm.verify_inclusion(proof);
+    if (!inclusion.valid) {
+      show(status, false, inclusion.error ?? "proof does not verify");
+      return;
+    }
+    if (proof.size !== signed.size || proof.root.toLowerCase() !== signed.root.toLowerCase()) {
+      show(status, false, "proof is for a different tree than the checkpoint");
+      return;
+    }
+    show(status, true, "included under the signed checkpoint");
+  } catch (err) {
+    show(status, false, String(err));
+  }
+}
+
+async function main() {
+  const buttons = document.querySelectorAll("button.verify");
+  try {
+    // Imports resolve against this script, not the page.
+    wasm = await import(new URL(root + "pkg/reality_wasm_core.js", document.baseURI).href);
+    await wasm.default();
+  } catch {
+    buttons.forEach((button) => (button.disabled = true));
+    return;
+  }
+  document.getElementById("verifier").textContent =
+    "Proofs are checked in your browser by the RealityLog wasm verifier.";
+
+  const status = document.getElementById("checkpoint-status");
+  if (status) {
+    try {
+      const loaded = await loadCheckpoint();
+      show(status, loaded.result.valid, describeCheckpoint(loaded));
+    } catch (err) {
+      show(status, false, String(err));
+    }
+  }
+  buttons.forEach((button) =>
+    button.addEventListener("click", () => verifyEntry(button.closest("tr"))),
+  );
+}
+
+main();
@@ -0,0 +1,169 @@
+//! `rl solidity`: a Solidity library that checks this log's inclusion
+//! proofs on-chain, so a contract holding an anchored root can verify single
+//! entries, and a Foundry test that runs it against proofs built by
+//! reality-core. Only SHA-256 logs are supported: the EVM has no SHA3-256
+//! (Keccak-256 pads differently) or BLAKE3 precompile.
+
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use reality_core::{algorithm::HashAlgorithm, make_proof_with};
+use serde::Serialize;
+
+const LIBRARY: &str = include_str!("solidity/Verifier.sol");
+const TESTS: &str = include_str!("solidity/Verifier.t.sol");
+
+/// Largest tree the test vectors cover; every index of every size up to it
+/// is checked.
+const VECTOR_SIZES: usize = 7;
+
+/// What `rl solidity` wrote.
+#[derive(Debug, Serialize)]
+pub struct Generated {
+    pub library: PathBuf,
+    pub tests: PathBuf,
+}
+
+/// Write `<name>.sol` and `<name>.t.sol` to `out`.
+pub fn generate(out: &Path, name: &str) -> anyhow::Result<Generated> {
+    anyhow::ensure!(
+        is_identifier(name),
+        "{name:?} is not a Solidity identifier; use letters, digits and underscores"
+    );
+    std::fs::create_dir_all(out).with_context(|| format!("create {}", out.display()))?;
+    let generated = Generated {
+        library: out.join(format!("{name}.sol")),
+        tests: out.join(format!("{name}.t.sol")),
+    };
+    for (path, source) in [
+        (&generated.library, library(name)),
+        (&generated.tests, tests(name)),
+    ] {
+        std::fs::write(path, source).with_context(|| format!("write {}", path.display()))?;
+    }
+    Ok(generated)
+}
+
+fn library(name: &str) -> String {
+    LIBRARY.replace("__NAME__", name).replace(
+        "__EMPTY_ROOT__",
+        &hex::encode(HashAlgorithm::Sha256.empty_root()),
+    )
+}
+
+fn tests(name: &str) -> String {
+    TESTS
+        .replace("__NAME__", name)
+        .replace("__VECTORS__", &vectors(name))
+}
+
+/// Assertions that every proof core builds for small trees verifies, and
+/// fails for another payload or an index past the end.
+fn vectors(name: &str) -> String {
+    let payloads: Vec<String> = (0..VECTOR_SIZES).map(|i| format!("entry {i}")).collect();
+    let mut code = String::new();
+    for size in 1..=VECTOR_SIZES {
+        let leaves: Vec<_> = payloads[..size]
+            .iter()
+            .map(|payload| HashAlgorithm::Sha256.leaf_hash(payload.as_bytes()))
+            .collect();
+        for (index, payload) in payloads[..size].iter().enumerate() {
+            let proof = make_proof_with(HashAlgorithm::Sha256, &leaves, index)
+                .expect("index is within the tree");
+            let root = &proof.root;
+            let _ = writeln!(code, "        path = new bytes32[]({});", proof.path.len());
+            for (i, step) in proof.path.iter().enumerate() {
+                let _ = writeln!(code, "        path[{i}] = 0x{};", step.hash);
+            }
+            let leaf = &proof.leaf;
+            let lines = [
+                format!("assertTrue({name}.verifyPayload(0x{root}, {size}, {index}, \"{payload}\", path));"),
+                format!("assertFalse({name}.verifyPayload(0x{root}, {size}, {index}, \"{payload}!\", path));"),
+                format!("assertFalse({name}.verify(0x{root}, {size}, {size}, 0x{leaf}, path));"),
+            ];
+            for line in lines {
+                let _ = writeln!(code, "        {line}");
+            }
+        }
+    }
+    code
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+pub fn describe(generated: &Generated) -> String {
+    format!(
+        "wrote {}\nwrote {}\n",
+        generated.library.display(),
+        generated.tests.display()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reality_core::Direction;
+
+    #[test]
+    fn emits_core_hashing_and_index_derived_sides() {
+        let library = library("LogVerifier");
+        assert!(library.contains("library LogVerifier {"));
+        assert!(library.contains(&format!(
+            "EMPTY_ROOT = 0x{};",
+            hex::encode(HashAlgorithm::Sha256.empty_root())
+        )));
+        let tests = tests("LogVerifier");
+        assert!(!library.contains("__") && !tests.contains("__"));
+        let cases = (1..=VECTOR_SIZES).sum::<usize>();
+        assert_eq!(
+            tests
+                .matches("assertTrue(LogVerifier.verifyPayload")
+                .count(),
+            cases
+        );
+
+        // The contract takes each sibling's side from the index instead of
+        // the proof, and requires one sibling per level, which holds for
+        // every proof core builds.
+        for size in 1..=33usize {
+            let leaves: Vec<_> = (0..size)
+                .map(|i| HashAlgorithm::Sha256.leaf_hash(&i.to_be_bytes()))
+                .collect();
+            let levels = (usize::BITS - (size - 1).leading_zeros()) as usize;
+            for index in 0..size {
+                let proof = make_proof_with(HashAlgorithm::Sha256, &leaves, index).unwrap();
+                assert_eq!(proof.path.len(), levels, "size {size}");
+                let mut position = index;
+                for step in &proof.path {
+                    let side = if position % 2 == 1 {
+                        Direction::Left
+                    } else {
+                        Direction::Right
+                    };
+                    assert_eq!(step.direction, side, "size {size} index {index}");
+                    position /= 2;
+                }
+            }
+        }
+
+        assert!(is_identifier("Verifier_2"));
+        assert!(!is_identifier("2Verifier") && !is_identifier("Log-Verifier"));
+        let dir = std::env::temp_dir().join(format!("rl-solidity-{}", std::process::id()));
+        assert!(generate(&dir, "not valid").is_err());
+        let generated = generate(&dir, "LogVerifier").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&generated.library).unwrap(),
+            library
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,60 @@
+// SPDX-License-Identifier: MIT
+// Generated by `rl solidity`. Regenerate it rather than editing it.
+pragma solidity ^0.8.20;
+
+/// @title __NAME__
+/// @notice Checks RealityLog inclusion proofs against a log root, hashing
+/// exactly as reality-core does: SHA-256 over 0x00 || payload for leaves and
+/// 0x01 || left || right for nodes, with an odd last node paired with itself.
+/// Take `root` and `size` from a checkpoint or anchor the contract trusts.
+library __NAME__ {
+    /// @notice Root of a log with no entries.
+    bytes32 internal constant EMPTY_ROOT = 0x__EMPTY_ROOT__;
+
+    function leafHash(bytes memory payload) internal pure returns (bytes32) {
+        return sha256(abi.encodePacked(bytes1(0x00), payload));
+    }
+
+    function nodeHash(bytes32 left, bytes32 right) internal pure returns (bytes32) {
+        return sha256(abi.encodePacked(bytes1(0x01), left, right));
+    }
+
+    /// @notice Number of siblings in a proof for a log of `size` entries.
+    function depth(uint256 size) internal pure returns (uint256 levels) {
+        while (size > 1) {
+            size = (size + 1) / 2;
+            levels++;
+        }
+    }
+
+    /// @notice Whether `path`, the sibling hashes from a proof's `path`, leads
+    /// from `leaf` at `index` to `root` in a log of `size` entries. Each
+    /// sibling's side follows from the index, one bit per level, so the
+    /// proof's directions are not needed.
+    function verify(bytes32 root, uint256 size, uint256 index, bytes32 leaf, bytes32[] memory path)
+        internal
+        pure
+        returns (bool)
+    {
+        if (index >= size || path.length != depth(size)) {
+            return false;
+        }
+        bytes32 node = leaf;
+        for (uint256 i = 0; i < path.length; i++) {
+            node = index % 2 == 1 ? nodeHash(path[i], node) : nodeHash(node, path[i]);
+            index /= 2;
+        }
+        return node == root;
+    }
+
+    /// @notice `verify` for the entry's payload rather than its leaf hash.
+    function verifyPayload(
+        bytes32 root,
+        uint256 size,
+        uint256 index,
+        bytes memory payload,
+        bytes32[] memory path
+    ) internal pure returns (bool) {
+        return verify(root, size, index, leafHash(payload), path);
+    }
+}
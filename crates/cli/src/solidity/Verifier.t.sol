@@ -0,0 +1,16 @@
+// SPDX-License-Identifier: MIT
+// Generated by `rl solidity` from proofs built by reality-core.
+pragma solidity ^0.8.20;
+
+import {Test} from "forge-std/Test.sol";
+import {__NAME__} from "./__NAME__.sol";
+
+contract __NAME__Test is Test {
+    function testEmptyRoot() public {
+        assertEq(__NAME__.EMPTY_ROOT, sha256("EMPTY"));
+    }
+
+    function testCoreProofs() public {
+        bytes32[] memory path;
+__VECTORS__    }
+}
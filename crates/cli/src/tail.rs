@@ -0,0 +1,190 @@
+//! `rl tail`: follow the log by polling `/root` and printing each new entry's
+//! leaf hash from `/tile`.
+
+use std::time::Duration;
+
+use reality_core::{
+    consistency::{verify_consistency, ConsistencyProof},
+    tile::TILE_WIDTH,
+    RootResponse,
+};
+use serde::Serialize;
+
+use reality_client::Client;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    Entry {
+        index: u64,
+        leaf: String,
+    },
+    /// A new tree head, proven to extend the previous one with `--verify`.
+    Head {
+        size: u64,
+        root: String,
+        verified: bool,
+    },
+}
+
+pub struct Tail {
+    pub from: Option<u64>,
+    pub interval: Duration,
+    pub verify: bool,
+}
+
+impl Tail {
+    /// Poll until the process is stopped, or until `--verify` catches the log
+    /// rewriting its history.
+    pub async fn run(&self, api: &Client, json: bool) -> anyhow::Result<()> {
+        let mut head = api.root().await?;
+        let mut next = self.from.unwrap_or(head.size).min(head.size);
+        emit(json, &head_event(&head, false));
+        loop {
+            self.print_entries(api, &mut next, head.size, json).await?;
+            tokio::time::sleep(self.interval).await;
+            let latest = api.root().await?;
+            if latest.size == head.size && latest.root.eq_ignore_ascii_case(&head.root) {
+                continue;
+            }
+            if self.verify {
+                let proof = if latest.size > head.size {
+                    Some(api.consistency(head.size, Some(latest.size)).await?)
+                } else {
+                    None
+                };
+                check_growth(&head, &latest, proof.as_ref()).map_err(anyhow::Error::msg)?;
+            }
+            head = latest;
+            emit(json, &head_event(&head, self.verify));
+        }
+    }
+
+    /// Print entries `next..size`, one tile request per 256 entries.
+    async fn print_entries(
+        &self,
+        api: &Client,
+        next: &mut u64,
+        size: u64,
+        json: bool,
+    ) -> anyhow::Result<()> {
+        let width = TILE_WIDTH as u64;
+        while *next < size {
+            let tile = api.tile(*next / width).await?;
+            let start = tile.index * width;
+            for (index, leaf) in (start..size).zip(tile.leaves) {
+                if index >= *next {
+                    emit(json, &Event::Entry { index, leaf });
+                }
+            }
+            *next = (start + width).min(size);
+        }
+        Ok(())
+    }
+}
+
+fn head_event(head: &RootResponse, verified: bool) -> Event {
+    Event::Head {
+        size: head.size,
+        root: head.root.clone(),
+        verified,
+    }
+}
+
+fn emit(json: bool, event: &Event) {
+    if json {
+        // One object per line so the output can be piped as it arrives.
+        println!(
+            "{}",
+            serde_json::to_string(event).expect("events serialize")
+        );
+        return;
+    }
+    match event {
+        Event::Entry { index, leaf } => println!("{index:>8}  {leaf}"),
+        Event::Head {
+            size,
+            root,
+            verified,
+        } => {
+            let note = if *verified { "  (consistent)" } else { "" };
+            println!("# size {size} root {root}{note}");
+        }
+    }
+}
+
+/// Check that `latest` extends `previous`, given the consistency proof
+/// between them when the log grew.
+fn check_growth(
+    previous: &RootResponse,
+    latest: &RootResponse,
+    proof: Option<&ConsistencyProof>,
+) -> Result<(), String> {
+    if latest.size < previous.size {
+        return Err(format!(
+            "log shrank from {} to {} entries",
+            previous.size, latest.size
+        ));
+    }
+    if latest.size == previous.size {
+        if latest.root.eq_ignore_ascii_case(&previous.root) {
+            return Ok(());
+        }
+        return Err(format!(
+            "root at size {} changed from {} to {}",
+            latest.size, previous.root, latest.root
+        ));
+    }
+    let links = proof.is_some_and(|proof| {
+        proof.old_size == previous.size
+            && proof.new_size == latest.size
+            && proof.old_root.eq_ignore_ascii_case(&previous.root)
+            && proof.new_root.eq_ignore_ascii_case(&latest.root)
+            && verify_consistency(proof)
+    });
+    if !links {
+        return Err(format!(
+            "no valid consistency proof from size {} to {}",
+            previous.size, latest.size
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reality_core::{consistency::consistency_proof, leaf_hash};
+
+    #[test]
+    fn growth_must_be_proven() {
+        let leaves: Vec<[u8; 32]> = (0..7u8).map(|i| leaf_hash(&[i])).collect();
+        let proof = consistency_proof(&leaves, 3, leaves.len()).unwrap();
+        let old = RootResponse {
+            size: 3,
+            root: proof.old_root.clone(),
+        };
+        let new = RootResponse {
+            size: 7,
+            root: proof.new_root.clone(),
+        };
+
+        assert_eq!(check_growth(&old, &new, Some(&proof)), Ok(()));
+        assert_eq!(check_growth(&new, &new, None), Ok(()));
+        assert!(check_growth(&old, &new, None).is_err());
+        let wrong_base = RootResponse {
+            size: 4,
+            ..old.clone()
+        };
+        assert!(check_growth(&wrong_base, &new, Some(&proof)).is_err());
+        assert!(check_growth(&new, &old, None)
+            .unwrap_err()
+            .contains("shrank"));
+
+        let mut forked = new.clone();
+        forked.root = "00".repeat(32);
+        assert!(check_growth(&new, &forked, None)
+            .unwrap_err()
+            .contains("changed"));
+    }
+}
@@ -0,0 +1,246 @@
+//! A synchronous client for tools without an async runtime. It drives the
+//! async [`Client`](crate::Client) on a private single-threaded runtime, so
+//! requests, retries and errors behave identically.
+//!
+//! Calling it from inside an async runtime panics; use the async client
+//! there.
+
+use std::collections::BTreeMap;
+
+use reality_core::{
+    bundle::{EntryBundle, PayloadEvidence, PayloadLookup},
+    checkpoint::{Cosignature, SignedCheckpoint},
+    consistency::ConsistencyProof,
+    day::{DayList, DayReceipt},
+    dsse::{AttestationHit, Envelope},
+    freshness::FreshCheckpoint,
+    handoff::Handoff,
+    leaf::LabelHit,
+    link::{LinkedCheckpoint, LogLink},
+    receipt::{AnchorReceipt, AnchoredProof},
+    redaction::{RedactRequest, RedactionList, SignedTombstone},
+    signer::Signer,
+    tile::{EntryTile, LeafTile},
+    AnchorQuery, AppendResponse, BatchAppendResponse, BatchEntry, InclusionProof, RootResponse,
+    StoredAnchor, VerifyRequest, VerifyResponse,
+};
+use tokio::runtime::{Builder, Runtime};
+
+use crate::{ClientBuilder, Error};
+
+pub struct Client {
+    inner: crate::Client,
+    runtime: Runtime,
+}
+
+impl Client {
+    pub fn new(base_url: impl Into<String>) -> Result<Self, Error> {
+        Self::builder(base_url).build_blocking()
+    }
+
+    /// The async client's builder; finish it with `build_blocking`.
+    pub fn builder(base_url: impl Into<String>) -> ClientBuilder {
+        crate::Client::builder(base_url)
+    }
+
+    pub fn from_async(inner: crate::Client) -> Result<Self, Error> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        Ok(Self { inner, runtime })
+    }
+
+    pub fn base_url(&self) -> &str {
+        self.inner.base_url()
+    }
+
+    pub fn append(&self, payload: impl Into<String>) -> Result<AppendResponse, Error> {
+        self.runtime.block_on(self.inner.append(payload))
+    }
+
+    pub fn append_labelled(
+        &self,
+        payload: impl Into<String>,
+        labels: BTreeMap<String, String>,
+    ) -> Result<AppendResponse, Error> {
+        self.runtime
+            .block_on(self.inner.append_labelled(payload, labels))
+    }
+
+    pub fn append_signed(
+        &self,
+        payload: impl Into<String>,
+        labels: BTreeMap<String, String>,
+        submitter: &dyn Signer,
+    ) -> Result<AppendResponse, Error> {
+        self.runtime
+            .block_on(self.inner.append_signed(payload, labels, submitter))
+    }
+
+    pub fn labelled(&self, key: &str, value: &str) -> Result<Vec<LabelHit>, Error> {
+        self.runtime.block_on(self.inner.labelled(key, value))
+    }
+
+    pub fn signed_by(&self, public_key: &str) -> Result<Vec<LabelHit>, Error> {
+        self.runtime.block_on(self.inner.signed_by(public_key))
+    }
+
+    /// See [`crate::Client::batch_append`].
+    pub fn batch_append<I>(&self, payloads: I) -> Result<Vec<AppendResponse>, Error>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.runtime.block_on(self.inner.batch_append(payloads))
+    }
+
+    /// See [`crate::Client::append_batch`].
+    pub fn append_batch(&self, entries: Vec<BatchEntry>) -> Result<BatchAppendResponse, Error> {
+        self.runtime.block_on(self.inner.append_batch(entries))
+    }
+
+    pub fn append_attestation(&self, envelope: &Envelope) -> Result<BatchAppendResponse, Error> {
+        self.runtime
+            .block_on(self.inner.append_attestation(envelope))
+    }
+
+    pub fn attestations(&self, digest: &str) -> Result<Vec<AttestationHit>, Error> {
+        self.runtime.block_on(self.inner.attestations(digest))
+    }
+
+    pub fn append_sigstore_bundle(
+        &self,
+        bundle: &serde_json::Value,
+    ) -> Result<BatchAppendResponse, Error> {
+        self.runtime
+            .block_on(self.inner.append_sigstore_bundle(bundle))
+    }
+
+    pub fn append_link(&self, link: &LogLink) -> Result<BatchAppendResponse, Error> {
+        self.runtime.block_on(self.inner.append_link(link))
+    }
+
+    pub fn links(&self) -> Result<Vec<LinkedCheckpoint>, Error> {
+        self.runtime.block_on(self.inner.links())
+    }
+
+    pub fn root(&self) -> Result<RootResponse, Error> {
+        self.runtime.block_on(self.inner.root())
+    }
+
+    pub fn root_at(&self, size: u64) -> Result<RootResponse, Error> {
+        self.runtime.block_on(self.inner.root_at(size))
+    }
+
+    pub fn checkpoint(&self) -> Result<SignedCheckpoint, Error> {
+        self.runtime.block_on(self.inner.checkpoint())
+    }
+
+    pub fn freshness(&self, nonce: &str) -> Result<FreshCheckpoint, Error> {
+        self.runtime.block_on(self.inner.freshness(nonce))
+    }
+
+    pub fn handoff(&self) -> Result<Handoff, Error> {
+        self.runtime.block_on(self.inner.handoff())
+    }
+
+    pub fn prove(&self, index: u64) -> Result<InclusionProof, Error> {
+        self.runtime.block_on(self.inner.prove(index))
+    }
+
+    pub fn prove_at(&self, index: u64, size: u64) -> Result<InclusionProof, Error> {
+        self.runtime.block_on(self.inner.prove_at(index, size))
+    }
+
+    pub fn prove_at_anchor(&self, index: u64, txid: &str) -> Result<AnchoredProof, Error> {
+        self.runtime
+            .block_on(self.inner.prove_at_anchor(index, txid))
+    }
+
+    pub fn consistency(&self, old: u64, new: Option<u64>) -> Result<ConsistencyProof, Error> {
+        self.runtime.block_on(self.inner.consistency(old, new))
+    }
+
+    pub fn bundle(&self, index: u64) -> Result<EntryBundle, Error> {
+        self.runtime.block_on(self.inner.bundle(index))
+    }
+
+    pub fn verify_payload(&self, lookup: &PayloadLookup) -> Result<PayloadEvidence, Error> {
+        self.runtime.block_on(self.inner.verify_payload(lookup))
+    }
+
+    pub fn redact(
+        &self,
+        index: u64,
+        request: impl Into<RedactRequest>,
+    ) -> Result<SignedTombstone, Error> {
+        self.runtime.block_on(self.inner.redact(index, request))
+    }
+
+    pub fn redactions(&self, index: Option<u64>) -> Result<RedactionList, Error> {
+        self.runtime.block_on(self.inner.redactions(index))
+    }
+
+    pub fn prove_redaction(&self, position: u64) -> Result<InclusionProof, Error> {
+        self.runtime.block_on(self.inner.prove_redaction(position))
+    }
+
+    pub fn days(&self) -> Result<DayList, Error> {
+        self.runtime.block_on(self.inner.days())
+    }
+
+    pub fn day_receipt(&self, index: u64) -> Result<DayReceipt, Error> {
+        self.runtime.block_on(self.inner.day_receipt(index))
+    }
+
+    pub fn anchors(&self, query: &AnchorQuery) -> Result<Vec<StoredAnchor>, Error> {
+        self.runtime.block_on(self.inner.anchors(query))
+    }
+
+    pub fn anchor_receipt(&self, txid: &str) -> Result<AnchorReceipt, Error> {
+        self.runtime.block_on(self.inner.anchor_receipt(txid))
+    }
+
+    pub fn witnesses(&self) -> Result<Vec<Cosignature>, Error> {
+        self.runtime.block_on(self.inner.witnesses())
+    }
+
+    pub fn tile(&self, index: u64) -> Result<LeafTile, Error> {
+        self.runtime.block_on(self.inner.tile(index))
+    }
+
+    pub fn entry_tile(&self, index: u64) -> Result<EntryTile, Error> {
+        self.runtime.block_on(self.inner.entry_tile(index))
+    }
+
+    pub fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Error> {
+        self.runtime.block_on(self.inner.verify(request))
+    }
+
+    pub fn verify_local(proof: &InclusionProof) -> VerifyResponse {
+        crate::Client::verify_local(proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::atomic::Ordering, time::Duration};
+
+    use crate::{tests::serve, RetryPolicy};
+
+    #[test]
+    fn blocks_on_the_async_client() {
+        let server = tokio::runtime::Runtime::new().unwrap();
+        let (url, hits) = server.block_on(serve());
+        let log = super::Client::builder(url)
+            .token("secret")
+            .retry(RetryPolicy {
+                initial_backoff: Duration::from_millis(1),
+                ..RetryPolicy::default()
+            })
+            .build_blocking()
+            .unwrap();
+
+        assert_eq!(log.root().unwrap().size, 4);
+        assert_eq!(hits.root.load(Ordering::SeqCst), 2);
+        assert!(log.append("entry").is_err());
+    }
+}
@@ -0,0 +1,224 @@
+//! Fork detection across two sources that claim to serve the same log, such
+//! as logd and a mirror, or logd as seen from two networks. Each source's
+//! head is fetched, and the source with the larger tree must show that it
+//! extends the other's: its root at the smaller size has to match, and its
+//! consistency proof has to link the two heads.
+
+use std::cmp::Ordering;
+
+use reality_core::{checkpoint::SignedCheckpoint, consistency::ConsistencyProof, RootResponse};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::{verifying::links, Client, Error, TrustedHead, VerificationError};
+
+/// One source's tree head as it served it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceHead {
+    pub source: String,
+    #[serde(flatten)]
+    pub head: TrustedHead,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "verdict", rename_all = "snake_case")]
+pub enum Verdict {
+    /// One head is, or provably extends, the other.
+    Consistent,
+    /// The sources give different roots for the tree of `size` leaves.
+    Fork { size: u64 },
+    /// The larger source agrees on the smaller root but does not prove that
+    /// its tree extends it.
+    Inconsistent { old: u64, new: u64 },
+}
+
+/// The outcome of [`compare`] and what it was decided on. Serialized, it is
+/// the evidence to hand to whoever runs the log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Comparison {
+    #[serde(flatten)]
+    pub verdict: Verdict,
+    pub a: SourceHead,
+    pub b: SourceHead,
+    /// The larger source's root at the smaller head's size.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root_at_smaller: Option<RootResponse>,
+    /// The larger source's proof from the smaller head to its own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub consistency: Option<ConsistencyProof>,
+}
+
+impl Comparison {
+    pub fn consistent(&self) -> bool {
+        self.verdict == Verdict::Consistent
+    }
+}
+
+/// Compare the logs at `a` and `b`. Heads come from signed checkpoints where
+/// a source has a signer and from `/root` otherwise. A checkpoint must be
+/// validly signed by `log_key`, or without it by the same key as the other
+/// source's checkpoint, so that a fork is evidence against one log.
+pub async fn compare(a: &Client, b: &Client, log_key: Option<&str>) -> Result<Comparison, Error> {
+    let heads = [fetch_head(a, log_key).await?, fetch_head(b, log_key).await?];
+    if let [Some(first), Some(second)] = heads.each_ref().map(|head| head.head.checkpoint.as_ref())
+    {
+        let key = &second.signature.public_key;
+        if !key.eq_ignore_ascii_case(&first.signature.public_key) {
+            return Err(VerificationError::UntrustedKey(key.clone()).into());
+        }
+    }
+    let [a_head, b_head] = heads;
+
+    let (smaller, larger, larger_client) = if a_head.head.size <= b_head.head.size {
+        (&a_head, &b_head, b)
+    } else {
+        (&b_head, &a_head, a)
+    };
+    let (old, new) = (smaller.head.size, larger.head.size);
+    let (root_at_smaller, consistency) = if old == 0 || old == new {
+        (None, None)
+    } else {
+        let root_at = larger_client.root_at(old).await?;
+        let proof = if root_at.root.eq_ignore_ascii_case(&smaller.head.root) {
+            Some(larger_client.consistency(old, Some(new)).await?)
+        } else {
+            None
+        };
+        (Some(root_at), proof)
+    };
+    let verdict = judge(
+        &smaller.head,
+        &larger.head,
+        root_at_smaller.as_ref(),
+        consistency.as_ref(),
+    );
+    Ok(Comparison {
+        verdict,
+        a: a_head,
+        b: b_head,
+        root_at_smaller,
+        consistency,
+    })
+}
+
+/// The verdict on `smaller` and `larger`, given what the larger source said
+/// about the smaller size.
+fn judge(
+    smaller: &TrustedHead,
+    larger: &TrustedHead,
+    root_at_smaller: Option<&RootResponse>,
+    consistency: Option<&ConsistencyProof>,
+) -> Verdict {
+    let size = smaller.size;
+    match size.cmp(&larger.size) {
+        Ordering::Greater => unreachable!("heads are ordered by size"),
+        Ordering::Equal if larger.root.eq_ignore_ascii_case(&smaller.root) => Verdict::Consistent,
+        Ordering::Equal => Verdict::Fork { size },
+        // Every tree extends the empty one.
+        Ordering::Less if size == 0 => Verdict::Consistent,
+        Ordering::Less => match (root_at_smaller, consistency) {
+            (Some(root_at), _)
+                if root_at.size != size || !root_at.root.eq_ignore_ascii_case(&smaller.root) =>
+            {
+                Verdict::Fork { size }
+            }
+            (_, Some(proof)) if links(proof, size, &smaller.root, larger) => Verdict::Consistent,
+            _ => Verdict::Inconsistent {
+                old: size,
+                new: larger.size,
+            },
+        },
+    }
+}
+
+/// `client`'s signed checkpoint, or its `/root` when it has no signer and
+/// no `log_key` is pinned.
+async fn fetch_head(client: &Client, log_key: Option<&str>) -> Result<SourceHead, Error> {
+    let head = match client.checkpoint().await {
+        Ok(checkpoint) => {
+            check_signature(&checkpoint, log_key)?;
+            TrustedHead {
+                size: checkpoint.checkpoint.size,
+                root: checkpoint.checkpoint.root.clone(),
+                checkpoint: Some(checkpoint),
+            }
+        }
+        Err(Error::Status {
+            status: StatusCode::NOT_FOUND,
+            ..
+        }) if log_key.is_none() => {
+            let root = client.root().await?;
+            TrustedHead {
+                size: root.size,
+                root: root.root,
+                checkpoint: None,
+            }
+        }
+        Err(err) => return Err(err),
+    };
+    Ok(SourceHead {
+        source: client.base_url().to_string(),
+        head,
+    })
+}
+
+fn check_signature(
+    checkpoint: &SignedCheckpoint,
+    log_key: Option<&str>,
+) -> Result<(), VerificationError> {
+    if !checkpoint.verify() {
+        return Err(VerificationError::BadSignature);
+    }
+    let key = &checkpoint.signature.public_key;
+    match log_key {
+        Some(pinned) if !key.eq_ignore_ascii_case(pinned) => {
+            Err(VerificationError::UntrustedKey(key.clone()))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reality_core::{consistency::consistency_proof, leaf_hash};
+
+    #[test]
+    fn tells_growth_from_forks() {
+        let leaves: Vec<[u8; 32]> = (0..6u8).map(|i| leaf_hash(&[i])).collect();
+        let proof = consistency_proof(&leaves, 4, leaves.len()).unwrap();
+        let head = |size, root: &str| TrustedHead {
+            size,
+            root: root.to_string(),
+            checkpoint: None,
+        };
+        let old = head(4, &proof.old_root);
+        let new = head(6, &proof.new_root);
+        let root_at = |root: &str| RootResponse {
+            size: 4,
+            root: root.to_string(),
+        };
+
+        assert_eq!(judge(&new, &new, None, None), Verdict::Consistent);
+        assert_eq!(judge(&head(0, ""), &new, None, None), Verdict::Consistent);
+        assert_eq!(
+            judge(&head(6, &proof.old_root), &new, None, None),
+            Verdict::Fork { size: 6 }
+        );
+        assert_eq!(
+            judge(&old, &new, Some(&root_at(&proof.old_root)), Some(&proof)),
+            Verdict::Consistent
+        );
+        assert_eq!(
+            judge(&old, &new, Some(&root_at(&proof.new_root)), None),
+            Verdict::Fork { size: 4 }
+        );
+
+        let mut tampered = proof.clone();
+        tampered.path.pop();
+        assert_eq!(
+            judge(&old, &new, Some(&root_at(&proof.old_root)), Some(&tampered)),
+            Verdict::Inconsistent { old: 4, new: 6 }
+        );
+    }
+}
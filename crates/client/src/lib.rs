@@ -0,0 +1,671 @@
+//! Typed async client for the logd HTTP API.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), reality_client::Error> {
+//! let log = reality_client::Client::builder("http://127.0.0.1:8080")
+//!     .token("secret")
+//!     .build()?;
+//! let appended = log.append("hello world").await?;
+//! let proof = log.prove(appended.index).await?;
+//! assert!(reality_client::Client::verify_local(&proof).valid);
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod compare;
+mod retry;
+mod verifying;
+
+use std::{collections::BTreeMap, time::Duration};
+
+use reality_core::{
+    bundle::{EntryBundle, PayloadEvidence, PayloadLookup},
+    checkpoint::{Cosignature, SignedCheckpoint},
+    consistency::ConsistencyProof,
+    day::{DayList, DayReceipt},
+    dsse::{AttestationHit, Envelope},
+    freshness::{FreshCheckpoint, FreshnessRequest},
+    handoff::Handoff,
+    leaf::{entry_message, LabelHit},
+    link::{LinkedCheckpoint, LogLink},
+    receipt::{AnchorReceipt, AnchoredProof},
+    redaction::{RedactRequest, RedactionList, SignedTombstone},
+    signer::{DetachedSignature, Signer},
+    tile::{EntryTile, LeafTile},
+    verify, AnchorQuery, AppendRequest, AppendResponse, BatchAppendRequest, BatchAppendResponse,
+    BatchEntry, InclusionProof, RootResponse, StoredAnchor, VerifyRequest, VerifyResponse,
+};
+use reqwest::{RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+pub use retry::RetryPolicy;
+pub use verifying::{TrustedHead, VerificationError, VerifyingClient};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("request to logd failed: {0}")]
+    Transport(#[from] reqwest::Error),
+    #[cfg(feature = "blocking")]
+    #[error("failed to start the blocking client's runtime: {0}")]
+    Runtime(#[from] std::io::Error),
+    #[error("logd returned {status}: {body}")]
+    Status { status: StatusCode, body: String },
+    #[error(transparent)]
+    Verification(#[from] VerificationError),
+    #[error("failed to sign the entry: {0}")]
+    Signing(String),
+    /// Entries before the failing one were appended and are returned here.
+    #[error("batch failed after {} appends: {source}", appended.len())]
+    Batch {
+        appended: Vec<AppendResponse>,
+        #[source]
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Whether a request that failed this way may be sent again. Requests
+    /// that change the log are only resent when they never reached logd.
+    fn is_retryable(&self, idempotent: bool) -> bool {
+        match self {
+            Error::Transport(err) if err.is_connect() => true,
+            Error::Transport(err) => idempotent && !err.is_decode() && !err.is_builder(),
+            Error::Status { status, .. } => {
+                idempotent && (status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS)
+            }
+            Error::Batch { .. } | Error::Verification(_) | Error::Signing(_) => false,
+            #[cfg(feature = "blocking")]
+            Error::Runtime(_) => false,
+        }
+    }
+}
+
+pub struct ClientBuilder {
+    base_url: String,
+    token: Option<String>,
+    submitter: Option<String>,
+    timeout: Duration,
+    connect_timeout: Duration,
+    retry: RetryPolicy,
+}
+
+impl ClientBuilder {
+    /// Bearer token sent with every request.
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Name sent as `X-Reality-Submitter`, which logd records with each
+    /// entry appended when configured to.
+    pub fn submitter(mut self, name: impl Into<String>) -> Self {
+        self.submitter = Some(name.into());
+        self
+    }
+
+    /// Limit on each attempt of a request, 30 seconds by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Limit on connecting to logd, 10 seconds by default.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn build(self) -> Result<Client, Error> {
+        let http = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .connect_timeout(self.connect_timeout)
+            .build()?;
+        Ok(Client {
+            http,
+            base: self.base_url.trim_end_matches('/').to_string(),
+            token: self.token,
+            submitter: self.submitter,
+            retry: self.retry,
+        })
+    }
+
+    #[cfg(feature = "blocking")]
+    pub fn build_blocking(self) -> Result<blocking::Client, Error> {
+        blocking::Client::from_async(self.build()?)
+    }
+}
+
+/// A logd instance. Cheap to clone; clones share one connection pool.
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base: String,
+    token: Option<String>,
+    submitter: Option<String>,
+    retry: RetryPolicy,
+}
+
+impl Client {
+    pub fn new(base_url: impl Into<String>) -> Result<Self, Error> {
+        Self::builder(base_url).build()
+    }
+
+    pub fn builder(base_url: impl Into<String>) -> ClientBuilder {
+        ClientBuilder {
+            base_url: base_url.into(),
+            token: None,
+            submitter: None,
+            timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(10),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base
+    }
+
+    pub async fn append(&self, payload: impl Into<String>) -> Result<AppendResponse, Error> {
+        self.append_labelled(payload, BTreeMap::new()).await
+    }
+
+    /// Append `payload` with `labels`, which its v2 leaf commits to. logd
+    /// must run with `REALITY_LOG_LEAF_V2=1`.
+    pub async fn append_labelled(
+        &self,
+        payload: impl Into<String>,
+        labels: BTreeMap<String, String>,
+    ) -> Result<AppendResponse, Error> {
+        let request = AppendRequest {
+            payload: payload.into(),
+            labels,
+            signature: None,
+        };
+        self.send(|| self.http.post(self.url("append")).json(&request), false)
+            .await
+    }
+
+    /// Append `payload` with `labels`, signed by `submitter` so that its v2
+    /// leaf names who submitted it. logd must run with
+    /// `REALITY_LOG_LEAF_V2=1` and, when it registers submitter keys, know
+    /// `submitter`'s.
+    pub async fn append_signed(
+        &self,
+        payload: impl Into<String>,
+        labels: BTreeMap<String, String>,
+        submitter: &dyn Signer,
+    ) -> Result<AppendResponse, Error> {
+        let payload = payload.into();
+        let message = entry_message(payload.as_bytes(), &labels);
+        let signature = DetachedSignature::create(submitter, &message)
+            .map_err(|err| Error::Signing(err.to_string()))?;
+        let request = AppendRequest {
+            payload,
+            labels,
+            signature: Some(signature),
+        };
+        self.send(|| self.http.post(self.url("append")).json(&request), false)
+            .await
+    }
+
+    /// The entries labelled `key` = `value`, oldest first.
+    pub async fn labelled(&self, key: &str, value: &str) -> Result<Vec<LabelHit>, Error> {
+        let label = format!("{key}:{value}");
+        self.send(
+            || {
+                self.http
+                    .get(self.url("entries"))
+                    .query(&[("label", &label)])
+            },
+            true,
+        )
+        .await
+    }
+
+    /// The entries signed by the submitter with hex key `public_key`,
+    /// oldest first.
+    pub async fn signed_by(&self, public_key: &str) -> Result<Vec<LabelHit>, Error> {
+        self.send(
+            || {
+                self.http
+                    .get(self.url("entries"))
+                    .query(&[("signer", public_key)])
+            },
+            true,
+        )
+        .await
+    }
+
+    /// Append `payloads` in order, one request per entry; on failure,
+    /// [`Error::Batch`] carries the entries that were appended. See
+    /// [`Client::append_batch`] for a single all-or-nothing request.
+    pub async fn batch_append<I>(&self, payloads: I) -> Result<Vec<AppendResponse>, Error>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let mut appended = Vec::new();
+        for payload in payloads {
+            match self.append(payload).await {
+                Ok(response) => appended.push(response),
+                Err(err) => {
+                    return Err(Error::Batch {
+                        appended,
+                        source: Box::new(err),
+                    })
+                }
+            }
+        }
+        Ok(appended)
+    }
+
+    /// Append `entries` in one `/append/batch` request; logd appends all of
+    /// them or none. When every entry has an idempotency key the request is
+    /// retried like a read, since logd skips keys it already holds.
+    pub async fn append_batch(
+        &self,
+        entries: Vec<BatchEntry>,
+    ) -> Result<BatchAppendResponse, Error> {
+        let idempotent = entries.iter().all(|entry| entry.idempotency_key.is_some());
+        let request = BatchAppendRequest { entries };
+        self.send(
+            || self.http.post(self.url("append/batch")).json(&request),
+            idempotent,
+        )
+        .await
+    }
+
+    /// Append a DSSE envelope as an attestation. Resending it is safe: logd
+    /// reports it as a duplicate of the first.
+    pub async fn append_attestation(
+        &self,
+        envelope: &Envelope,
+    ) -> Result<BatchAppendResponse, Error> {
+        self.send(
+            || self.http.post(self.url("attestations")).json(envelope),
+            true,
+        )
+        .await
+    }
+
+    /// The in-toto attestations about the artifact with `digest`, such as
+    /// `sha256:<hex>`.
+    pub async fn attestations(&self, digest: &str) -> Result<Vec<AttestationHit>, Error> {
+        self.send(
+            || {
+                self.http
+                    .get(self.url("attestations"))
+                    .query(&[("digest", digest)])
+            },
+            true,
+        )
+        .await
+    }
+
+    /// Append a Sigstore bundle, as read from its JSON file. logd records
+    /// what it verified of it next to it in the entry, and reports a resent
+    /// bundle as a duplicate.
+    pub async fn append_sigstore_bundle(
+        &self,
+        bundle: &serde_json::Value,
+    ) -> Result<BatchAppendResponse, Error> {
+        self.send(|| self.http.post(self.url("sigstore")).json(bundle), true)
+            .await
+    }
+
+    /// Append another log's signed checkpoint as a link. logd checks it
+    /// against the checkpoint linked from that log before, and reports a
+    /// resent link as a duplicate.
+    pub async fn append_link(&self, link: &LogLink) -> Result<BatchAppendResponse, Error> {
+        self.send(|| self.http.post(self.url("links")).json(link), true)
+            .await
+    }
+
+    /// The newest linked checkpoint of each linked log.
+    pub async fn links(&self) -> Result<Vec<LinkedCheckpoint>, Error> {
+        self.get("links").await
+    }
+
+    /// The current tree head.
+    pub async fn root(&self) -> Result<RootResponse, Error> {
+        self.get("root").await
+    }
+
+    /// Root of the first `size` leaves.
+    pub async fn root_at(&self, size: u64) -> Result<RootResponse, Error> {
+        self.get(&format!("root/{size}")).await
+    }
+
+    /// The current tree head signed by the log. Fails with a 404 status when
+    /// logd has no signer.
+    pub async fn checkpoint(&self) -> Result<SignedCheckpoint, Error> {
+        self.get("checkpoint").await
+    }
+
+    /// The current checkpoint bound to `nonce`, hex of 16 to 64 bytes the
+    /// caller has just drawn. Check it with `FreshCheckpoint::verify`.
+    pub async fn freshness(&self, nonce: &str) -> Result<FreshCheckpoint, Error> {
+        let request = FreshnessRequest {
+            nonce: nonce.to_string(),
+        };
+        self.send(
+            || self.http.post(self.url("freshness")).json(&request),
+            true,
+        )
+        .await
+    }
+
+    /// The handoff a promoted standby signed from the old primary's
+    /// checkpoint to its own. Check it with `Handoff::verify` before
+    /// trusting the new key.
+    pub async fn handoff(&self) -> Result<Handoff, Error> {
+        self.get("handoff").await
+    }
+
+    pub async fn prove(&self, index: u64) -> Result<InclusionProof, Error> {
+        self.get(&format!("prove/{index}")).await
+    }
+
+    /// Inclusion proof for entry `index` against the tree of the first
+    /// `size` leaves, so it matches a root fetched earlier.
+    pub async fn prove_at(&self, index: u64, size: u64) -> Result<InclusionProof, Error> {
+        self.get(&format!("prove/{index}?size={size}")).await
+    }
+
+    /// Inclusion proof for entry `index` against the root anchored in
+    /// `txid`, with a consistency proof from that root to the current one.
+    pub async fn prove_at_anchor(&self, index: u64, txid: &str) -> Result<AnchoredProof, Error> {
+        self.send(
+            || {
+                self.http
+                    .get(self.url(&format!("prove/{index}")))
+                    .query(&[("at_anchor", txid)])
+            },
+            true,
+        )
+        .await
+    }
+
+    /// Proof that the tree at `old` leaves is a prefix of the tree at `new`,
+    /// or of the current tree when `new` is `None`.
+    pub async fn consistency(&self, old: u64, new: Option<u64>) -> Result<ConsistencyProof, Error> {
+        let path = match new {
+            Some(new) => format!("consistency?old={old}&new={new}"),
+            None => format!("consistency?old={old}"),
+        };
+        self.get(&path).await
+    }
+
+    pub async fn bundle(&self, index: u64) -> Result<EntryBundle, Error> {
+        self.get(&format!("bundle/{index}")).await
+    }
+
+    /// Delete entry `index`'s payload through logd's admin API, which needs
+    /// the client's token to be the admin token. `request` is a reason or a
+    /// [`RedactRequest`]. Retried like a read, since logd answers a repeat
+    /// with the first tombstone.
+    pub async fn redact(
+        &self,
+        index: u64,
+        request: impl Into<RedactRequest>,
+    ) -> Result<SignedTombstone, Error> {
+        let request = request.into();
+        let path = format!("admin/redact/{index}");
+        self.send(|| self.http.post(self.url(&path)).json(&request), true)
+            .await
+    }
+
+    /// logd's redaction log, or only the tombstone for entry `index`.
+    pub async fn redactions(&self, index: Option<u64>) -> Result<RedactionList, Error> {
+        match index {
+            Some(index) => self.get(&format!("redactions?entry={index}")).await,
+            None => self.get("redactions").await,
+        }
+    }
+
+    /// Inclusion proof for the tombstone at `position` in the redaction log.
+    pub async fn prove_redaction(&self, position: u64) -> Result<InclusionProof, Error> {
+        self.get(&format!("redactions/prove/{position}")).await
+    }
+
+    /// logd's day log: its size and root, and the seal of every closed day.
+    pub async fn days(&self) -> Result<DayList, Error> {
+        self.get("days").await
+    }
+
+    /// Receipt for entry `index` through its day's seal. logd answers `404`
+    /// until the day is sealed.
+    pub async fn day_receipt(&self, index: u64) -> Result<DayReceipt, Error> {
+        self.get(&format!("days/receipt/{index}")).await
+    }
+
+    /// Anchors of this log that match `query`.
+    pub async fn anchors(&self, query: &AnchorQuery) -> Result<Vec<StoredAnchor>, Error> {
+        self.send(|| self.http.get(self.url("anchors")).query(query), true)
+            .await
+    }
+
+    /// The anchor daemon's receipt for the anchor with `txid`. Call this on a
+    /// client for the anchor daemon's API, not logd's.
+    pub async fn anchor_receipt(&self, txid: &str) -> Result<AnchorReceipt, Error> {
+        self.get(&format!("receipt/{txid}")).await
+    }
+
+    /// The latest cosignature logd holds from each witness.
+    pub async fn witnesses(&self) -> Result<Vec<Cosignature>, Error> {
+        self.get("witness").await
+    }
+
+    /// Leaf hashes `index * 256` up to `index * 256 + 255`.
+    pub async fn tile(&self, index: u64) -> Result<LeafTile, Error> {
+        self.get(&format!("tile/{index}")).await
+    }
+
+    /// Payloads of the entries in leaf tile `index`.
+    pub async fn entry_tile(&self, index: u64) -> Result<EntryTile, Error> {
+        self.get(&format!("tile/{index}/entries")).await
+    }
+
+    /// Ask logd's `/verify` to check `request`. Verification needs no log
+    /// state, so this is retried like a read.
+    pub async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Error> {
+        self.send(|| self.http.post(self.url("verify")).json(request), true)
+            .await
+    }
+
+    /// Ask logd's `/verify-payload` for the earliest entry holding
+    /// `lookup`'s payload, with its bundle. Check the bundle with
+    /// [`EntryBundle::verify`] and the log's pinned key rather than trusting
+    /// logd's own check.
+    pub async fn verify_payload(&self, lookup: &PayloadLookup) -> Result<PayloadEvidence, Error> {
+        self.send(
+            || self.http.post(self.url("verify-payload")).json(lookup),
+            true,
+        )
+        .await
+    }
+
+    /// Check a proof here rather than asking logd's `/verify`.
+    pub fn verify_local(proof: &InclusionProof) -> VerifyResponse {
+        verify(&VerifyRequest::from(proof))
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        self.send(|| self.http.get(self.url(path)), true).await
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{path}", self.base)
+    }
+
+    async fn send<T, F>(&self, build: F, idempotent: bool) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        F: Fn() -> RequestBuilder,
+    {
+        let mut attempt = 1;
+        loop {
+            let mut request = build();
+            if let Some(token) = &self.token {
+                request = request.bearer_auth(token);
+            }
+            if let Some(submitter) = &self.submitter {
+                request = request.header("x-reality-submitter", submitter);
+            }
+            let result = match request.send().await {
+                Ok(response) => read(response).await,
+                Err(err) => Err(err.into()),
+            };
+            match result {
+                Err(err) if attempt < self.retry.max_attempts && err.is_retryable(idempotent) => {
+                    tokio::time::sleep(self.retry.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+async fn read<T: DeserializeOwned>(response: reqwest::Response) -> Result<T, Error> {
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(Error::Status {
+            status,
+            body: body.trim().to_string(),
+        });
+    }
+    Ok(response.json().await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use axum::{
+        extract::State,
+        http::{HeaderMap, StatusCode as ServerStatus},
+        routing::{get, post},
+        Json, Router,
+    };
+
+    #[derive(Clone, Default)]
+    pub(crate) struct Hits {
+        pub(crate) root: Arc<AtomicUsize>,
+        pub(crate) append: Arc<AtomicUsize>,
+        pub(crate) batch: Arc<AtomicUsize>,
+    }
+
+    /// `/root` fails once and then needs the token; `/append` always fails;
+    /// `/append/batch` fails every other time.
+    pub(crate) async fn serve() -> (String, Hits) {
+        let hits = Hits::default();
+        let app = Router::new()
+            .route(
+                "/root",
+                get(|State(hits): State<Hits>, headers: HeaderMap| async move {
+                    if hits.root.fetch_add(1, Ordering::SeqCst) == 0 {
+                        return Err((ServerStatus::SERVICE_UNAVAILABLE, "warming up"));
+                    }
+                    match headers.get("authorization") {
+                        Some(value) if value == "Bearer secret" => Ok(Json(RootResponse {
+                            root: "ab".repeat(32),
+                            size: 4,
+                        })),
+                        _ => Err((ServerStatus::UNAUTHORIZED, "missing token")),
+                    }
+                }),
+            )
+            .route(
+                "/append",
+                post(|State(hits): State<Hits>| async move {
+                    hits.append.fetch_add(1, Ordering::SeqCst);
+                    (ServerStatus::INTERNAL_SERVER_ERROR, "persist failure")
+                }),
+            )
+            .route(
+                "/append/batch",
+                post(|State(hits): State<Hits>| async move {
+                    if hits.batch.fetch_add(1, Ordering::SeqCst) % 2 == 0 {
+                        return Err((ServerStatus::SERVICE_UNAVAILABLE, "busy"));
+                    }
+                    Ok(Json(BatchAppendResponse {
+                        entries: Vec::new(),
+                        size: 1,
+                        root: "ab".repeat(32),
+                    }))
+                }),
+            )
+            .with_state(hits.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (format!("http://{addr}/"), hits)
+    }
+
+    #[tokio::test]
+    async fn retries_reads_and_keyed_batches_but_not_appends() {
+        let (url, hits) = serve().await;
+        let retry = RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+        };
+        let log = Client::builder(&url)
+            .token("secret")
+            .retry(retry.clone())
+            .build()
+            .unwrap();
+
+        assert_eq!(log.root().await.unwrap().size, 4);
+        assert_eq!(hits.root.load(Ordering::SeqCst), 2);
+
+        let err = log.batch_append(["a", "b"]).await.unwrap_err();
+        let Error::Batch { appended, source } = err else {
+            panic!("expected a batch error, got {err}");
+        };
+        assert!(appended.is_empty());
+        assert!(matches!(
+            *source,
+            Error::Status { status, ref body }
+                if status == StatusCode::INTERNAL_SERVER_ERROR && body == "persist failure"
+        ));
+        assert_eq!(hits.append.load(Ordering::SeqCst), 1);
+
+        let entry = |key: Option<&str>| BatchEntry {
+            payload: "a".into(),
+            idempotency_key: key.map(String::from),
+            labels: Default::default(),
+            signature: None,
+        };
+        assert!(log.append_batch(vec![entry(Some("k1"))]).await.is_ok());
+        assert_eq!(hits.batch.load(Ordering::SeqCst), 2);
+        assert!(log
+            .append_batch(vec![entry(Some("k2")), entry(None)])
+            .await
+            .is_err());
+        assert_eq!(hits.batch.load(Ordering::SeqCst), 3);
+
+        let anonymous = Client::builder(url).retry(retry).build().unwrap();
+        assert!(matches!(
+            anonymous.root().await,
+            Err(Error::Status {
+                status: StatusCode::UNAUTHORIZED,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,351 @@
+//! "Verify everything" mode: a client that trusts nothing logd says until it
+//! has checked it. It remembers the newest tree head it accepted, moves to a
+//! newer one only with a valid consistency proof, and checks every inclusion
+//! proof against that head before returning it.
+
+use std::cmp::Ordering;
+
+use reality_core::{
+    checkpoint::SignedCheckpoint,
+    consistency::{verify_consistency, ConsistencyProof},
+    leaf::entry_leaf,
+    AppendResponse, InclusionProof,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+use crate::Client;
+
+/// The newest tree head a [`VerifyingClient`] has accepted. Persist it and
+/// pass it to [`VerifyingClient::with_trusted`] to keep the same view of the
+/// log across restarts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustedHead {
+    pub size: u64,
+    pub root: String,
+    /// The log's signed checkpoint for this head, when a log key is pinned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint: Option<SignedCheckpoint>,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum VerificationError {
+    #[error("checkpoint signature is invalid")]
+    BadSignature,
+    #[error("checkpoint is signed by {0}, not the pinned log key")]
+    UntrustedKey(String),
+    #[error("log went back to size {served} after size {accepted} was accepted")]
+    Rollback { accepted: u64, served: u64 },
+    #[error("log changed its root at size {size}")]
+    Fork { size: u64 },
+    #[error("no valid consistency proof from size {old} to {new}")]
+    Inconsistent { old: u64, new: u64 },
+    #[error("inclusion proof for entry {index} does not verify")]
+    InvalidProof { index: u64 },
+    #[error("entry {index} does not hold the appended payload")]
+    LeafMismatch { index: u64 },
+}
+
+/// A [`Client`] that verifies every read. Create one with
+/// [`Client::verifying`].
+#[derive(Debug)]
+pub struct VerifyingClient {
+    client: Client,
+    log_key: Option<String>,
+    /// Held across a whole fetch-and-check so concurrent reads advance the
+    /// head one at a time.
+    trusted: Mutex<Option<TrustedHead>>,
+}
+
+impl Client {
+    /// Verify everything this client reads. With `log_key`, tree heads come
+    /// from signed `/checkpoint`s by that key; without it, from `/root`.
+    pub fn verifying(self, log_key: Option<String>) -> VerifyingClient {
+        VerifyingClient {
+            client: self,
+            log_key,
+            trusted: Mutex::new(None),
+        }
+    }
+}
+
+impl VerifyingClient {
+    /// Start from a previously accepted head. Its checkpoint, if any, is
+    /// checked again rather than trusted.
+    pub fn with_trusted(self, head: TrustedHead) -> Result<Self, crate::Error> {
+        if let Some(checkpoint) = &head.checkpoint {
+            self.check_signature(checkpoint)?;
+            let signed = &checkpoint.checkpoint;
+            if signed.size != head.size || !signed.root.eq_ignore_ascii_case(&head.root) {
+                return Err(VerificationError::BadSignature.into());
+            }
+        }
+        Ok(Self {
+            trusted: Mutex::new(Some(head)),
+            ..self
+        })
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// The newest head accepted so far.
+    pub async fn trusted(&self) -> Option<TrustedHead> {
+        self.trusted.lock().await.clone()
+    }
+
+    /// Fetch the log's current head and accept it if it extends the trusted
+    /// one.
+    pub async fn head(&self) -> Result<TrustedHead, crate::Error> {
+        let mut trusted = self.trusted.lock().await;
+        let served = self.fetch_head().await?;
+        self.accept(&mut trusted, served).await
+    }
+
+    /// Accept `served`, fetched earlier with [`fetch_head`](Self::fetch_head),
+    /// if it extends the trusted head. Callers that split the two steps keep
+    /// the refused head as evidence.
+    pub async fn advance(&self, served: TrustedHead) -> Result<TrustedHead, crate::Error> {
+        let mut trusted = self.trusted.lock().await;
+        self.accept(&mut trusted, served).await
+    }
+
+    async fn accept(
+        &self,
+        trusted: &mut Option<TrustedHead>,
+        served: TrustedHead,
+    ) -> Result<TrustedHead, crate::Error> {
+        if let Some(accepted) = trusted.as_ref() {
+            let proof = if served.size > accepted.size {
+                let proof = self.client.consistency(accepted.size, Some(served.size));
+                Some(proof.await?)
+            } else {
+                None
+            };
+            check_extends(accepted, &served, proof.as_ref())?;
+        }
+        *trusted = Some(served.clone());
+        Ok(served)
+    }
+
+    /// An inclusion proof for entry `index`, returned only once it verifies
+    /// and its root is proven to be part of the trusted head.
+    pub async fn prove(&self, index: u64) -> Result<InclusionProof, crate::Error> {
+        let proof = self.client.prove(index).await?;
+        let head = self.head().await?;
+        let consistency = if proof.size < head.size {
+            Some(self.client.consistency(proof.size, Some(head.size)).await?)
+        } else {
+            None
+        };
+        check_proof(index, &proof, &head, consistency.as_ref())?;
+        Ok(proof)
+    }
+
+    /// Append `payload`, then prove that the log holds it under the trusted
+    /// head.
+    pub async fn append(&self, payload: impl Into<String>) -> Result<AppendResponse, crate::Error> {
+        let payload = payload.into();
+        let appended = self.client.append(payload.clone()).await?;
+        let index = appended.index;
+        let leaf = hex::encode(entry_leaf(payload.as_bytes(), appended.meta.as_ref()));
+        let proof = self.prove(index).await?;
+        if !appended.leaf.eq_ignore_ascii_case(&leaf) || !proof.leaf.eq_ignore_ascii_case(&leaf) {
+            return Err(VerificationError::LeafMismatch { index }.into());
+        }
+        Ok(appended)
+    }
+
+    /// The log's current head, with its signature checked but not yet
+    /// accepted.
+    pub async fn fetch_head(&self) -> Result<TrustedHead, crate::Error> {
+        if self.log_key.is_none() {
+            let head = self.client.root().await?;
+            return Ok(TrustedHead {
+                size: head.size,
+                root: head.root,
+                checkpoint: None,
+            });
+        }
+        let checkpoint = self.client.checkpoint().await?;
+        self.check_signature(&checkpoint)?;
+        Ok(TrustedHead {
+            size: checkpoint.checkpoint.size,
+            root: checkpoint.checkpoint.root.clone(),
+            checkpoint: Some(checkpoint),
+        })
+    }
+
+    fn check_signature(&self, checkpoint: &SignedCheckpoint) -> Result<(), VerificationError> {
+        if !checkpoint.verify() {
+            return Err(VerificationError::BadSignature);
+        }
+        let key = &checkpoint.signature.public_key;
+        match &self.log_key {
+            Some(pinned) if !key.eq_ignore_ascii_case(pinned) => {
+                Err(VerificationError::UntrustedKey(key.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Check that `served` extends `accepted`, given the consistency proof
+/// between them when the log grew.
+fn check_extends(
+    accepted: &TrustedHead,
+    served: &TrustedHead,
+    proof: Option<&ConsistencyProof>,
+) -> Result<(), VerificationError> {
+    match served.size.cmp(&accepted.size) {
+        Ordering::Less => Err(VerificationError::Rollback {
+            accepted: accepted.size,
+            served: served.size,
+        }),
+        Ordering::Equal if !served.root.eq_ignore_ascii_case(&accepted.root) => {
+            Err(VerificationError::Fork { size: served.size })
+        }
+        Ordering::Equal => Ok(()),
+        Ordering::Greater => match proof {
+            Some(proof) if links(proof, accepted.size, &accepted.root, served) => Ok(()),
+            _ => Err(VerificationError::Inconsistent {
+                old: accepted.size,
+                new: served.size,
+            }),
+        },
+    }
+}
+
+/// Check that `proof` verifies and that its tree is, or is a prefix of, the
+/// trusted `head`.
+fn check_proof(
+    index: u64,
+    proof: &InclusionProof,
+    head: &TrustedHead,
+    consistency: Option<&ConsistencyProof>,
+) -> Result<(), VerificationError> {
+    if proof.index != index || !Client::verify_local(proof).valid {
+        return Err(VerificationError::InvalidProof { index });
+    }
+    match proof.size.cmp(&head.size) {
+        Ordering::Greater => Err(VerificationError::Rollback {
+            accepted: proof.size,
+            served: head.size,
+        }),
+        Ordering::Equal if !proof.root.eq_ignore_ascii_case(&head.root) => {
+            Err(VerificationError::Fork { size: head.size })
+        }
+        Ordering::Equal => Ok(()),
+        Ordering::Less => match consistency {
+            Some(consistency) if links(consistency, proof.size, &proof.root, head) => Ok(()),
+            _ => Err(VerificationError::Inconsistent {
+                old: proof.size,
+                new: head.size,
+            }),
+        },
+    }
+}
+
+pub(crate) fn links(
+    proof: &ConsistencyProof,
+    old_size: u64,
+    old_root: &str,
+    new: &TrustedHead,
+) -> bool {
+    proof.old_size == old_size
+        && proof.new_size == new.size
+        && proof.old_root.eq_ignore_ascii_case(old_root)
+        && proof.new_root.eq_ignore_ascii_case(&new.root)
+        && verify_consistency(proof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reality_core::{
+        checkpoint::Checkpoint, consistency::consistency_proof, leaf_hash, make_proof,
+        signer::KeyFileSigner,
+    };
+
+    #[test]
+    fn accepts_only_proven_growth() {
+        let leaves: Vec<[u8; 32]> = (0..6u8).map(|i| leaf_hash(&[i])).collect();
+        let consistency = consistency_proof(&leaves, 4, leaves.len()).unwrap();
+        let head = |size, root: &str| TrustedHead {
+            size,
+            root: root.to_string(),
+            checkpoint: None,
+        };
+        let old = head(4, &consistency.old_root);
+        let new = head(6, &consistency.new_root);
+
+        assert_eq!(check_extends(&old, &new, Some(&consistency)), Ok(()));
+        assert_eq!(
+            check_extends(&old, &new, None),
+            Err(VerificationError::Inconsistent { old: 4, new: 6 })
+        );
+        assert_eq!(
+            check_extends(&new, &old, None),
+            Err(VerificationError::Rollback {
+                accepted: 6,
+                served: 4
+            })
+        );
+        assert_eq!(
+            check_extends(&new, &head(6, &consistency.old_root), None),
+            Err(VerificationError::Fork { size: 6 })
+        );
+
+        let proof = make_proof(&leaves[..4], 2).unwrap();
+        assert_eq!(check_proof(2, &proof, &new, Some(&consistency)), Ok(()));
+        assert_eq!(check_proof(2, &proof, &old, None), Ok(()));
+        assert_eq!(
+            check_proof(3, &proof, &old, None),
+            Err(VerificationError::InvalidProof { index: 3 })
+        );
+        assert_eq!(
+            check_proof(2, &proof, &new, None),
+            Err(VerificationError::Inconsistent { old: 4, new: 6 })
+        );
+    }
+
+    #[test]
+    fn restored_heads_are_checked_again() {
+        let signer = KeyFileSigner::from_seed([3u8; 32]);
+        let key = hex::encode(reality_core::signer::Signer::public_key(&signer));
+        let checkpoint = Checkpoint {
+            size: 2,
+            root: "ab".repeat(32),
+            timestamp_nanos: "1".into(),
+        }
+        .sign(&signer)
+        .unwrap();
+        let head = TrustedHead {
+            size: 2,
+            root: "ab".repeat(32),
+            checkpoint: Some(checkpoint),
+        };
+        let client = || Client::new("http://127.0.0.1:1").unwrap();
+
+        assert!(client()
+            .verifying(Some(key.clone()))
+            .with_trusted(head.clone())
+            .is_ok());
+        assert!(matches!(
+            client()
+                .verifying(Some("00".repeat(32)))
+                .with_trusted(head.clone()),
+            Err(crate::Error::Verification(VerificationError::UntrustedKey(
+                _
+            )))
+        ));
+        let mut tampered = head;
+        tampered.size = 3;
+        assert!(client()
+            .verifying(Some(key))
+            .with_trusted(tampered)
+            .is_err());
+    }
+}
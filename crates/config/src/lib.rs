@@ -0,0 +1,114 @@
+//! Configuration layering shared by the RealityLog daemons and tools:
+//! defaults, then a TOML file, then environment and flags. Each crate keeps
+//! its own fields, flags and validation in its `config` module and
+//! implements [`Layered`] for them.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::de::DeserializeOwned;
+
+/// A configuration resolved from `Flags`, the parsed command line with the
+/// environment already merged in by clap.
+pub trait Layered<Flags>: Default + DeserializeOwned {
+    /// The config file `flags` name, if any. Without one the layering
+    /// starts from [`Default`].
+    fn file(flags: &Flags) -> Option<PathBuf>;
+
+    /// Override the fields `flags` set.
+    fn apply(&mut self, flags: Flags) -> anyhow::Result<()>;
+
+    /// Check the resolved configuration as a whole.
+    fn validate(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Resolve configuration with precedence flags/env > file > defaults.
+    fn load(flags: Flags) -> anyhow::Result<Self> {
+        let mut config = match Self::file(&flags) {
+            Some(path) => from_file(&path)?,
+            None => Self::default(),
+        };
+        config.apply(flags)?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// Parse the TOML config file at `path`.
+pub fn from_file<T: DeserializeOwned>(path: &Path) -> anyhow::Result<T> {
+    let raw =
+        std::fs::read_to_string(path).with_context(|| format!("read config {}", path.display()))?;
+    toml::from_str(&raw).with_context(|| format!("parse config {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(default, deny_unknown_fields)]
+    struct Config {
+        url: String,
+        retries: u32,
+    }
+
+    impl Default for Config {
+        fn default() -> Self {
+            Self {
+                url: "http://127.0.0.1:8080".into(),
+                retries: 3,
+            }
+        }
+    }
+
+    struct Flags {
+        config: Option<PathBuf>,
+        retries: Option<u32>,
+    }
+
+    impl Layered<Flags> for Config {
+        fn file(flags: &Flags) -> Option<PathBuf> {
+            flags.config.clone()
+        }
+
+        fn apply(&mut self, flags: Flags) -> anyhow::Result<()> {
+            if let Some(retries) = flags.retries {
+                self.retries = retries;
+            }
+            Ok(())
+        }
+
+        fn validate(&self) -> anyhow::Result<()> {
+            anyhow::ensure!(self.retries < 10, "retries must be below 10");
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn flags_override_the_file_over_defaults() {
+        let path = std::env::temp_dir().join(format!("reality-config-{}.toml", std::process::id()));
+        std::fs::write(&path, "url = \"https://log.example\"\nretries = 5\n").unwrap();
+        let flags = |retries| Flags {
+            config: Some(path.clone()),
+            retries,
+        };
+
+        let config = Config::load(flags(None)).unwrap();
+        assert_eq!(config.url, "https://log.example");
+        assert_eq!(config.retries, 5);
+        assert_eq!(Config::load(flags(Some(1))).unwrap().retries, 1);
+        assert!(Config::load(flags(Some(12))).is_err());
+        let bare = Config::load(Flags {
+            config: None,
+            retries: None,
+        });
+        assert_eq!(bare.unwrap(), Config::default());
+
+        std::fs::write(&path, "unknown = 1\n").unwrap();
+        let err = Config::load(flags(None)).unwrap_err();
+        assert!(format!("{err:#}").contains("parse config"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,22 @@
+//! Signed checkpoints and witness cosignatures as JSON, as logd's
+//! `POST /witness`, monitors and witnesses receive them from peers.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use reality_core::checkpoint::{Cosignature, SignedCheckpoint};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(signed) = serde_json::from_slice::<SignedCheckpoint>(data) {
+        let valid = signed.verify();
+        let public_key = signed.signature.public_key.clone();
+        assert_eq!(signed.verify_with(&public_key), valid);
+        let json = serde_json::to_vec(&signed).expect("checkpoint serializes");
+        let reparsed: SignedCheckpoint =
+            serde_json::from_slice(&json).expect("checkpoint reparses");
+        assert_eq!(reparsed, signed);
+    }
+    if let Ok(cosignature) = serde_json::from_slice::<Cosignature>(data) {
+        cosignature.verify();
+    }
+});
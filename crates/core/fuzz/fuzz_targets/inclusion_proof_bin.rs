@@ -0,0 +1,17 @@
+//! Binary inclusion proofs, as served by `/prove/N/bin` and embedded in
+//! `.rlproof` files: decoding must reject bad input without panicking, and
+//! anything it accepts must re-encode to a proof that decodes the same.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use reality_core::{verify, InclusionProof, VerifyRequest};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(proof) = InclusionProof::from_bytes(data) else {
+        return;
+    };
+    let bytes = proof.to_bytes().expect("a decoded proof encodes");
+    assert_eq!(InclusionProof::from_bytes(&bytes), Ok(proof.clone()));
+    verify(&VerifyRequest::from(&proof));
+});
@@ -0,0 +1,87 @@
+//! Proofs built from arbitrary trees must verify, and must stop verifying
+//! once any hash in them changes.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use reality_core::{
+    algorithm::HashAlgorithm, make_proof_with, verify, Direction, ProofStep, VerifyRequest,
+};
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    algorithm: HashAlgorithm,
+    leaves: Vec<Vec<u8>>,
+    index: usize,
+    tamper: Tamper,
+}
+
+#[derive(Debug, Arbitrary)]
+enum Tamper {
+    Leaf {
+        bit: u8,
+    },
+    Root {
+        bit: u8,
+    },
+    Step {
+        step: usize,
+        bit: u8,
+    },
+    Drop {
+        step: usize,
+    },
+    Append {
+        direction: Direction,
+        hash: [u8; 32],
+    },
+}
+
+/// Flip bit `bit` (of 256) of a hex hash.
+fn flip(hash: &str, bit: u8) -> String {
+    let mut bytes = hex::decode(hash).expect("proofs hold hex");
+    bytes[usize::from(bit / 8)] ^= 1 << (bit % 8);
+    hex::encode(bytes)
+}
+
+fuzz_target!(|input: Input| {
+    if input.leaves.is_empty() || input.leaves.len() > 1024 {
+        return;
+    }
+    let leaves: Vec<[u8; 32]> = input
+        .leaves
+        .iter()
+        .map(|leaf| input.algorithm.leaf_hash(leaf))
+        .collect();
+    let index = input.index % leaves.len();
+    let proof = make_proof_with(input.algorithm, &leaves, index).expect("index is in range");
+    let mut request = VerifyRequest::from(&proof);
+    assert!(verify(&request).valid, "an honest proof must verify");
+
+    match input.tamper {
+        Tamper::Leaf { bit } => request.leaf = flip(&request.leaf, bit),
+        Tamper::Root { bit } => request.root = flip(&request.root, bit),
+        Tamper::Step { step, bit } => {
+            if request.path.is_empty() {
+                return;
+            }
+            let step = &mut request.path[step % proof.path.len()];
+            step.hash = flip(&step.hash, bit);
+        }
+        Tamper::Drop { step } => {
+            if request.path.is_empty() {
+                return;
+            }
+            request.path.remove(step % proof.path.len());
+        }
+        Tamper::Append { direction, hash } => request.path.push(ProofStep {
+            direction,
+            hash: hex::encode(hash),
+        }),
+    }
+    assert!(
+        !verify(&request).valid,
+        "a tampered proof verified: {request:?}"
+    );
+});
@@ -0,0 +1,21 @@
+//! The body of logd's public `POST /verify`: any bytes, parsed as a
+//! `VerifyRequest` and checked.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use reality_core::{verify, VerifyRequest};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(request) = serde_json::from_slice::<VerifyRequest>(data) else {
+        return;
+    };
+    let response = verify(&request);
+    if response.valid {
+        assert_eq!(response.computed_root, response.expected_root);
+    }
+
+    let json = serde_json::to_vec(&request).expect("request serializes");
+    let reparsed: VerifyRequest = serde_json::from_slice(&json).expect("request reparses");
+    assert_eq!(reparsed, request);
+});
@@ -0,0 +1,46 @@
+//! The same checks on structured values from `reality-core`'s `arbitrary`
+//! feature, which reach the hashing and signature code far more often than
+//! raw bytes do.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use reality_core::{
+    checkpoint::{Cosignature, SignedCheckpoint},
+    consistency::{verify_consistency, ConsistencyProof},
+    verify, InclusionProof, VerifyRequest,
+};
+
+#[derive(Debug, Arbitrary)]
+enum Input {
+    Verify(VerifyRequest),
+    Proof(InclusionProof),
+    Consistency(ConsistencyProof),
+    Checkpoint(SignedCheckpoint),
+    Cosignature(Cosignature),
+}
+
+fuzz_target!(|input: Input| {
+    match input {
+        Input::Verify(request) => {
+            verify(&request);
+        }
+        Input::Proof(proof) => {
+            verify(&VerifyRequest::from(&proof));
+            if let Ok(bytes) = proof.to_bytes() {
+                let decoded = InclusionProof::from_bytes(&bytes).expect("an encoded proof decodes");
+                assert_eq!(decoded.to_bytes(), Ok(bytes));
+            }
+        }
+        Input::Consistency(proof) => {
+            verify_consistency(&proof);
+        }
+        Input::Checkpoint(signed) => {
+            signed.verify();
+        }
+        Input::Cosignature(cosignature) => {
+            cosignature.verify();
+        }
+    }
+});
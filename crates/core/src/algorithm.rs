@@ -0,0 +1,187 @@
+//! Hash functions a log may be built with. SHA-256 is the default and what
+//! logd uses; proofs name their algorithm so verifiers never mix them up.
+//! SHA-512/256 suits deployments that standardize on the SHA-512 family,
+//! and is faster than SHA-256 on 64-bit machines without SHA extensions.
+//!
+//! The hashing itself is generic over the digest (see [`crate::digest`]).
+//! `HashAlgorithm` is the runtime name of a digest: proofs arrive as JSON
+//! or bytes, through the FFI and wasm bindings too, and its methods
+//! dispatch to the generic functions for the digest a proof names. Every
+//! algorithm is always compiled in, since a verifier built without one
+//! would reject valid proofs that name it.
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512_256};
+use sha3::Sha3_256;
+
+use crate::digest;
+
+/// Run `$body` with `$digest` standing for the digest `$algorithm` names.
+macro_rules! with_digest {
+    ($algorithm:expr, $digest:ident => $body:expr) => {
+        match $algorithm {
+            $crate::algorithm::HashAlgorithm::Sha256 => {
+                type $digest = ::sha2::Sha256;
+                $body
+            }
+            $crate::algorithm::HashAlgorithm::Sha3_256 => {
+                type $digest = ::sha3::Sha3_256;
+                $body
+            }
+            $crate::algorithm::HashAlgorithm::Blake3 => {
+                type $digest = $crate::digest::Blake3;
+                $body
+            }
+            $crate::algorithm::HashAlgorithm::Sha512_256 => {
+                type $digest = ::sha2::Sha512_256;
+                $body
+            }
+        }
+    };
+}
+pub(crate) use with_digest;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum HashAlgorithm {
+    #[default]
+    #[serde(rename = "sha256")]
+    Sha256,
+    #[serde(rename = "sha3-256")]
+    Sha3_256,
+    #[serde(rename = "blake3")]
+    Blake3,
+    #[serde(rename = "sha512-256")]
+    Sha512_256,
+}
+
+impl HashAlgorithm {
+    pub const ALL: [HashAlgorithm; 4] =
+        [Self::Sha256, Self::Sha3_256, Self::Blake3, Self::Sha512_256];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha256",
+            Self::Sha3_256 => "sha3-256",
+            Self::Blake3 => "blake3",
+            Self::Sha512_256 => "sha512-256",
+        }
+    }
+
+    /// Proofs leave the field out for the default so SHA-256 payloads keep
+    /// their original shape.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn leaf_hash(self, bytes: &[u8]) -> [u8; 32] {
+        with_digest!(self, D => digest::leaf_hash::<D>(bytes))
+    }
+
+    pub fn node_hash(self, left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        with_digest!(self, D => digest::node_hash::<D>(left, right))
+    }
+
+    pub fn empty_root(self) -> [u8; 32] {
+        with_digest!(self, D => digest::empty_root::<D>())
+    }
+
+    pub(crate) fn parents(self, layer: &[[u8; 32]]) -> Vec<[u8; 32]> {
+        with_digest!(self, D => digest::parents::<D>(layer))
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|alg| alg.as_str().eq_ignore_ascii_case(value))
+            .ok_or_else(|| format!("unknown hash algorithm {value:?}"))
+    }
+}
+
+/// Running state of one of the supported hash functions.
+#[derive(Debug, Clone)]
+pub(crate) enum Hasher {
+    Sha256(Sha256),
+    Sha3_256(Box<Sha3_256>),
+    Blake3(Box<blake3::Hasher>),
+    Sha512_256(Sha512_256),
+}
+
+impl Hasher {
+    pub(crate) fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+            HashAlgorithm::Sha3_256 => Self::Sha3_256(Box::default()),
+            HashAlgorithm::Blake3 => Self::Blake3(Box::default()),
+            HashAlgorithm::Sha512_256 => Self::Sha512_256(Sha512_256::new()),
+        }
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Self::Sha256(h) => h.update(bytes),
+            Self::Sha3_256(h) => h.update(bytes),
+            Self::Blake3(h) => {
+                h.update(bytes);
+            }
+            Self::Sha512_256(h) => h.update(bytes),
+        }
+    }
+
+    pub(crate) fn finalize(self) -> [u8; 32] {
+        match self {
+            Self::Sha256(h) => h.finalize().into(),
+            Self::Sha3_256(h) => h.finalize().into(),
+            Self::Blake3(h) => h.finalize().into(),
+            Self::Sha512_256(h) => h.finalize().into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::digest::{DefaultDigest, LogDigest};
+
+    #[test]
+    fn algorithms_are_domain_separated_and_distinct() {
+        assert_eq!(
+            DefaultDigest::ALGORITHM.leaf_hash(b"a"),
+            crate::leaf_hash(b"a")
+        );
+        let leaves: Vec<[u8; 32]> = HashAlgorithm::ALL
+            .iter()
+            .map(|alg| alg.leaf_hash(b"a"))
+            .collect();
+        assert_ne!(leaves[0], leaves[1]);
+        assert_ne!(leaves[1], leaves[2]);
+        assert_eq!(
+            HashAlgorithm::Sha3_256.leaf_hash(b"a"),
+            <[u8; 32]>::from(Sha3_256::digest(b"\x00a"))
+        );
+        assert_eq!(
+            HashAlgorithm::Sha512_256.leaf_hash(b"a"),
+            <[u8; 32]>::from(Sha512_256::digest(b"\x00a"))
+        );
+        assert_ne!(leaves[0], leaves[3]);
+        assert_eq!("SHA3-256".parse(), Ok(HashAlgorithm::Sha3_256));
+        assert_eq!("sha512-256".parse(), Ok(HashAlgorithm::Sha512_256));
+        assert!("md5".parse::<HashAlgorithm>().is_err());
+        assert_eq!(
+            serde_json::to_string(&HashAlgorithm::Blake3).unwrap(),
+            r#""blake3""#
+        );
+    }
+}
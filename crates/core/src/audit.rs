@@ -0,0 +1,162 @@
+//! Audit reports: what a monitor verified about its logs over a period,
+//! signed so they can be filed as compliance evidence. Each report carries
+//! the digest of the one before it, so a missing report shows.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    signer::{DetachedSignature, Signer, SignerError},
+    RootResponse,
+};
+
+pub const AUDIT_REPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AuditReport {
+    pub version: u32,
+    /// Unix nanoseconds; the period is `[start, end)`.
+    pub period_start_nanos: String,
+    pub period_end_nanos: String,
+    /// `SignedAuditReport::digest` of the previous report.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<String>,
+    pub logs: Vec<LogAudit>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LogAudit {
+    pub log: String,
+    pub url: String,
+    /// Heads verified during the period, each shown by a consistency proof
+    /// to extend the head verified before it.
+    pub checkpoints_observed: u64,
+    /// The latest head verified by the end of the period.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latest: Option<RootResponse>,
+    /// Heads the log served during the period that did not extend it.
+    pub discrepancies: Vec<Discrepancy>,
+    pub anchors: AnchorAudit,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Discrepancy {
+    pub detected_at_nanos: String,
+    pub trusted: RootResponse,
+    pub observed: RootResponse,
+    pub reason: String,
+}
+
+/// The log's anchors from the period, checked against the latest head.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AnchorAudit {
+    pub verified: u64,
+    /// Anchors that could not be checked, such as those of a tree larger
+    /// than any verified head.
+    pub unchecked: Vec<AnchorFinding>,
+    pub failed: Vec<AnchorFinding>,
+    /// Set when the anchors could not be fetched at all.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AnchorFinding {
+    pub txid: String,
+    pub size: u64,
+    pub root: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignedAuditReport {
+    #[serde(flatten)]
+    pub report: AuditReport,
+    pub signature: DetachedSignature,
+}
+
+impl AuditReport {
+    /// The exact bytes covered by the monitor's signature.
+    pub fn signed_message(&self) -> Vec<u8> {
+        let mut message = b"reality-audit-report/v1\n".to_vec();
+        message.extend(serde_json::to_vec(self).expect("report serializes"));
+        message
+    }
+
+    pub fn sign(self, signer: &dyn Signer) -> Result<SignedAuditReport, SignerError> {
+        let signature = DetachedSignature::create(signer, &self.signed_message())?;
+        Ok(SignedAuditReport {
+            report: self,
+            signature,
+        })
+    }
+
+    /// Whether the report records anything that went wrong.
+    pub fn clean(&self) -> bool {
+        self.logs.iter().all(|log| {
+            log.discrepancies.is_empty()
+                && log.anchors.failed.is_empty()
+                && log.anchors.error.is_none()
+        })
+    }
+}
+
+impl SignedAuditReport {
+    /// Check the signature against the embedded public key. Callers must still
+    /// decide whether that key is the monitor's.
+    pub fn verify(&self) -> bool {
+        self.signature.verify(&self.report.signed_message())
+    }
+
+    /// Hex SHA-256 of the report's compact JSON, which the next report
+    /// records as `previous`.
+    pub fn digest(&self) -> String {
+        hex::encode(Sha256::digest(
+            serde_json::to_vec(self).expect("report serializes"),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::KeyFileSigner;
+
+    #[test]
+    fn reports_are_signed_and_chained() {
+        let monitor = KeyFileSigner::from_seed([3u8; 32]);
+        let report = AuditReport {
+            version: AUDIT_REPORT_VERSION,
+            period_start_nanos: "0".into(),
+            period_end_nanos: "10".into(),
+            previous: None,
+            logs: vec![LogAudit {
+                log: "prod".into(),
+                url: "http://log".into(),
+                checkpoints_observed: 2,
+                latest: Some(RootResponse {
+                    size: 4,
+                    root: "ab".repeat(32),
+                }),
+                discrepancies: Vec::new(),
+                anchors: AnchorAudit::default(),
+            }],
+        };
+        assert!(report.clean());
+        let first = report.clone().sign(&monitor).unwrap();
+        assert!(first.verify());
+
+        let json = serde_json::to_string(&first).unwrap();
+        let parsed: SignedAuditReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.digest(), first.digest());
+
+        let mut next = report;
+        next.previous = Some(first.digest());
+        next.logs[0].anchors.error = Some("unreachable".into());
+        assert!(!next.clean());
+        let mut tampered = next.sign(&monitor).unwrap();
+        assert!(tampered.verify());
+        tampered.report.logs[0].checkpoints_observed = 3;
+        assert!(!tampered.verify());
+    }
+}
@@ -0,0 +1,85 @@
+//! References to payloads kept outside the log. A large payload can be stored
+//! in a blob store keyed by its SHA-256, with only a [`BlobRef`] appended in
+//! its place. The leaf then commits to the digest and length, so the blob can
+//! be fetched from anywhere and checked against the entry.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Prefix of [`BlobRef::blob`].
+pub const SHA256: &str = "sha256:";
+
+/// The payload appended in place of a blob.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct BlobRef {
+    /// `sha256:<hex>` of the blob's bytes.
+    pub blob: String,
+    /// Length of the blob in bytes.
+    pub size: u64,
+}
+
+impl BlobRef {
+    pub fn new(content: &[u8]) -> Self {
+        Self {
+            blob: format!("{SHA256}{}", hex::encode(Sha256::digest(content))),
+            size: content.len() as u64,
+        }
+    }
+
+    /// The reference as an entry payload.
+    pub fn to_payload(&self) -> String {
+        serde_json::to_string(self).expect("blob references serialize")
+    }
+
+    /// The reference a payload holds, if it is one.
+    pub fn from_payload(payload: &str) -> Option<Self> {
+        let reference: Self = serde_json::from_str(payload).ok()?;
+        reference.sha256()?;
+        Some(reference)
+    }
+
+    /// Lowercase hex SHA-256 of the blob, the key it is stored under.
+    pub fn sha256(&self) -> Option<&str> {
+        let digest = self.blob.strip_prefix(SHA256)?;
+        (digest.len() == 64
+            && digest
+                .bytes()
+                .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')))
+        .then_some(digest)
+    }
+
+    /// Whether `content` is the blob this refers to.
+    pub fn matches(&self, content: &[u8]) -> bool {
+        *self == Self::new(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_payloads() {
+        let reference = BlobRef::new(b"hello");
+        assert_eq!(
+            reference.to_payload(),
+            r#"{"blob":"sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824","size":5}"#
+        );
+        assert_eq!(
+            BlobRef::from_payload(&reference.to_payload()),
+            Some(reference.clone())
+        );
+        assert!(reference.matches(b"hello") && !reference.matches(b"hello!"));
+
+        for payload in [
+            "hello",
+            r#"{"blob":"sha256:2cf2","size":5}"#,
+            r#"{"blob":"md5:00","size":5}"#,
+            &reference.to_payload().replace("2cf2", "2CF2"),
+            &reference.to_payload().replace('}', r#","extra":1}"#),
+        ] {
+            assert_eq!(BlobRef::from_payload(payload), None, "{payload}");
+        }
+    }
+}
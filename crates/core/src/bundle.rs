@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    checkpoint::SignedCheckpoint,
+    leaf::{entry_leaf_with, LeafMeta},
+    verify, InclusionProof, VerifyRequest,
+};
+
+/// A self-contained receipt for one entry: the payload, its inclusion proof,
+/// and the signed checkpoint the proof leads to.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EntryBundle {
+    pub payload: String,
+    /// Set when the entry has a v2 leaf.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<LeafMeta>,
+    pub proof: InclusionProof,
+    pub checkpoint: SignedCheckpoint,
+}
+
+/// What logd's `POST /verify-payload` looks up: a payload, or the hex
+/// SHA-256 of one for a client that does not send it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PayloadLookup {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+/// logd's answer to a [`PayloadLookup`]: the earliest entry holding the
+/// payload, with a bundle the server has verified against its current
+/// signed checkpoint. The bundle verifies offline just the same.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PayloadEvidence {
+    /// Hex SHA-256 of the payload.
+    pub sha256: String,
+    pub index: u64,
+    pub appended_at: String,
+    /// Later entries holding the same payload.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub later: Vec<u64>,
+    pub bundle: EntryBundle,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum BundleError {
+    #[error("payload does not hash to the proof's leaf")]
+    LeafMismatch,
+    #[error("inclusion proof does not lead to its root (computed {computed})")]
+    InvalidProof { computed: String },
+    #[error("proof root or size does not match the checkpoint")]
+    CheckpointMismatch,
+    #[error("checkpoint signature is invalid")]
+    BadSignature,
+    #[error("checkpoint is signed by {0}, not the trusted log key")]
+    UntrustedKey(String),
+}
+
+impl PayloadLookup {
+    /// Whether the lookup reveals more than a client without the read token
+    /// may learn. A lookup by hash gets the payload back. In a log that
+    /// encrypts payloads, `sealed`, even a lookup by payload confirms that
+    /// a guessed plaintext is in it.
+    pub fn needs_read_token(&self, sealed: bool) -> bool {
+        self.sha256.is_some() || sealed
+    }
+}
+
+impl EntryBundle {
+    /// Check payload → leaf → root → checkpoint → signature. Without
+    /// `trusted_key` any valid signature is accepted, so callers should pin
+    /// the log's key wherever they can.
+    pub fn verify(&self, trusted_key: Option<&str>) -> Result<(), BundleError> {
+        let leaf = entry_leaf_with(
+            self.proof.algorithm,
+            self.payload.as_bytes(),
+            self.meta.as_ref(),
+        );
+        if hex::encode(leaf) != self.proof.leaf.to_ascii_lowercase() {
+            return Err(BundleError::LeafMismatch);
+        }
+        verify_checkpointed(&self.proof, &self.checkpoint, trusted_key)
+    }
+}
+
+/// Check leaf → root → checkpoint → signature for a proof whose payload is
+/// checked elsewhere, or not at all.
+pub fn verify_checkpointed(
+    proof: &InclusionProof,
+    signed: &SignedCheckpoint,
+    trusted_key: Option<&str>,
+) -> Result<(), BundleError> {
+    let response = verify(&VerifyRequest::from(proof));
+    if !response.valid {
+        return Err(BundleError::InvalidProof {
+            computed: response.computed_root,
+        });
+    }
+    let checkpoint = &signed.checkpoint;
+    if !checkpoint.root.eq_ignore_ascii_case(&proof.root) || checkpoint.size != proof.size {
+        return Err(BundleError::CheckpointMismatch);
+    }
+    if !signed.verify() {
+        return Err(BundleError::BadSignature);
+    }
+    let key = &signed.signature.public_key;
+    match trusted_key {
+        Some(trusted) if !key.eq_ignore_ascii_case(trusted) => {
+            Err(BundleError::UntrustedKey(key.clone()))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{checkpoint::Checkpoint, leaf_hash, make_proof, signer::KeyFileSigner};
+
+    #[test]
+    fn bundle_checks_every_link() {
+        let payloads = ["alpha", "beta", "gamma"];
+        let leaves: Vec<[u8; 32]> = payloads.iter().map(|p| leaf_hash(p.as_bytes())).collect();
+        let proof = make_proof(&leaves, 1).expect("proof");
+        let signer = KeyFileSigner::from_seed([4u8; 32]);
+        let checkpoint = Checkpoint {
+            size: 3,
+            root: proof.root.clone(),
+            timestamp_nanos: "1".into(),
+        }
+        .sign(&signer)
+        .expect("sign");
+        let bundle = EntryBundle {
+            payload: "beta".into(),
+            meta: None,
+            proof,
+            checkpoint,
+        };
+        let key = hex::encode(crate::signer::Signer::public_key(&signer));
+        assert_eq!(bundle.verify(Some(&key)), Ok(()));
+        assert!(matches!(
+            bundle.verify(Some(&"00".repeat(32))),
+            Err(BundleError::UntrustedKey(_))
+        ));
+
+        let mut wrong_payload = bundle.clone();
+        wrong_payload.payload = "gamma".into();
+        assert_eq!(wrong_payload.verify(None), Err(BundleError::LeafMismatch));
+
+        let mut wrong_size = bundle.clone();
+        wrong_size.checkpoint.checkpoint.size = 4;
+        assert_eq!(
+            wrong_size.verify(None),
+            Err(BundleError::CheckpointMismatch)
+        );
+
+        let mut forged = bundle;
+        forged.checkpoint.signature.signature = "00".repeat(64);
+        assert_eq!(forged.verify(None), Err(BundleError::BadSignature));
+    }
+
+    #[test]
+    fn lookups_by_hash_or_of_sealed_payloads_need_the_read_token() {
+        let by_payload = PayloadLookup {
+            payload: Some("contract".into()),
+            sha256: None,
+        };
+        let by_hash = PayloadLookup {
+            payload: None,
+            sha256: Some("00".repeat(32)),
+        };
+        assert!(!by_payload.needs_read_token(false));
+        assert!(by_payload.needs_read_token(true));
+        assert!(by_hash.needs_read_token(false));
+        assert!(by_hash.needs_read_token(true));
+    }
+}
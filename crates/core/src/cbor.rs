@@ -0,0 +1,205 @@
+//! The little CBOR (RFC 8949) that COSE receipts need: integers, byte and
+//! text strings, arrays, maps, tags and null, all with definite lengths.
+//! Encoding is deterministic as long as maps are built with their keys in
+//! order.
+
+/// Nesting beyond this is refused when decoding.
+const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Int(i64),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+    Tag(u64, Box<Value>),
+    Null,
+}
+
+impl Value {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write(&mut out);
+        out
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Int(n) if *n >= 0 => header(out, 0, *n as u64),
+            Self::Int(n) => header(out, 1, !*n as u64),
+            Self::Bytes(bytes) => {
+                header(out, 2, bytes.len() as u64);
+                out.extend_from_slice(bytes);
+            }
+            Self::Text(text) => {
+                header(out, 3, text.len() as u64);
+                out.extend_from_slice(text.as_bytes());
+            }
+            Self::Array(items) => {
+                header(out, 4, items.len() as u64);
+                for item in items {
+                    item.write(out);
+                }
+            }
+            Self::Map(entries) => {
+                header(out, 5, entries.len() as u64);
+                for (key, value) in entries {
+                    key.write(out);
+                    value.write(out);
+                }
+            }
+            Self::Tag(tag, value) => {
+                header(out, 6, *tag);
+                value.write(out);
+            }
+            Self::Null => out.push(0xf6),
+        }
+    }
+
+    /// The single value `bytes` holds, or `None` if it holds anything else
+    /// or more.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut input = bytes;
+        let value = read(&mut input, 0)?;
+        input.is_empty().then_some(value)
+    }
+
+    /// The value under integer `key` in a map.
+    pub fn get(&self, key: i64) -> Option<&Value> {
+        let Self::Map(entries) = self else {
+            return None;
+        };
+        entries
+            .iter()
+            .find(|(k, _)| *k == Self::Int(key))
+            .map(|(_, value)| value)
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Self::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Self::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Self::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+fn header(out: &mut Vec<u8>, major: u8, n: u64) {
+    let major = major << 5;
+    match n {
+        0..=23 => out.push(major | n as u8),
+        24..=0xff => out.extend([major | 24, n as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend((n as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend((n as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend(n.to_be_bytes());
+        }
+    }
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if input.len() < len {
+        return None;
+    }
+    let (taken, rest) = input.split_at(len);
+    *input = rest;
+    Some(taken)
+}
+
+fn read(input: &mut &[u8], depth: usize) -> Option<Value> {
+    if depth > MAX_DEPTH {
+        return None;
+    }
+    let initial = take(input, 1)?[0];
+    let (major, info) = (initial >> 5, initial & 0x1f);
+    if major == 7 {
+        return (info == 22).then_some(Value::Null);
+    }
+    let n = match info {
+        0..=23 => u64::from(info),
+        24..=27 => {
+            let bytes = take(input, 1 << (info - 24))?;
+            bytes.iter().fold(0u64, |n, &b| n << 8 | u64::from(b))
+        }
+        // Indefinite lengths and reserved values.
+        _ => return None,
+    };
+    // No item can be longer than what is left of the input.
+    let len = usize::try_from(n).ok().filter(|&len| len <= input.len());
+    Some(match major {
+        0 => Value::Int(i64::try_from(n).ok()?),
+        1 => Value::Int(!i64::try_from(n).ok()?),
+        2 => Value::Bytes(take(input, len?)?.to_vec()),
+        3 => Value::Text(String::from_utf8(take(input, len?)?.to_vec()).ok()?),
+        4 => Value::Array(
+            (0..len?)
+                .map(|_| read(input, depth + 1))
+                .collect::<Option<_>>()?,
+        ),
+        5 => Value::Map(
+            (0..len?)
+                .map(|_| Some((read(input, depth + 1)?, read(input, depth + 1)?)))
+                .collect::<Option<_>>()?,
+        ),
+        _ => Value::Tag(n, Box::new(read(input, depth + 1)?)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_like_rfc_8949_and_round_trips() {
+        // Examples from RFC 8949, appendix A.
+        for (value, hex) in [
+            (Value::Int(0), "00"),
+            (Value::Int(24), "1818"),
+            (Value::Int(1000), "1903e8"),
+            (Value::Int(1_000_000), "1a000f4240"),
+            (Value::Int(-1), "20"),
+            (Value::Int(-1000), "3903e7"),
+            (Value::Bytes(vec![1, 2, 3, 4]), "4401020304"),
+            (Value::Text("IETF".into()), "6449455446"),
+            (
+                Value::Array(vec![Value::Int(1), Value::Array(vec![Value::Int(2)])]),
+                "82018102",
+            ),
+            (Value::Map(vec![(Value::Int(1), Value::Int(2))]), "a10102"),
+            (
+                Value::Tag(1, Box::new(Value::Int(1_363_896_240))),
+                "c11a514b67b0",
+            ),
+            (Value::Null, "f6"),
+        ] {
+            assert_eq!(hex::encode(value.encode()), hex);
+            assert_eq!(Value::decode(&hex::decode(hex).unwrap()), Some(value));
+        }
+
+        assert_eq!(Value::decode(&[0x01, 0x02]), None);
+        assert_eq!(Value::decode(&[0x5f]), None);
+        assert_eq!(Value::decode(&[0x5a, 0xff, 0xff, 0xff, 0xff]), None);
+        assert_eq!(Value::decode(&[0x81; 64]), None);
+    }
+}
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+
+use crate::signer::{DetachedSignature, Signer, SignerError};
+
+/// A tree head: the log's size and root at a moment in time.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub size: u64,
+    pub root: String,
+    pub timestamp_nanos: String,
+}
+
+/// A checkpoint signed by the log (a signed tree head).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct SignedCheckpoint {
+    #[serde(flatten)]
+    pub checkpoint: Checkpoint,
+    #[serde(flatten)]
+    pub signature: DetachedSignature,
+}
+
+/// A witness's signature over a checkpoint it has checked for consistency
+/// with every checkpoint it saw before.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Cosignature {
+    #[serde(flatten)]
+    pub checkpoint: Checkpoint,
+    pub witness: DetachedSignature,
+}
+
+impl Checkpoint {
+    /// The exact bytes covered by the log's signature.
+    pub fn signed_message(&self) -> Vec<u8> {
+        format!(
+            "reality-checkpoint/v1\n{}\n{}\n{}\n",
+            self.size,
+            self.root.to_ascii_lowercase(),
+            self.timestamp_nanos
+        )
+        .into_bytes()
+    }
+
+    /// The bytes a witness signs; distinct from the log's message so one
+    /// signature cannot stand in for the other.
+    pub fn cosigned_message(&self) -> Vec<u8> {
+        let mut message = b"reality-cosignature/v1\n".to_vec();
+        message.extend(self.signed_message());
+        message
+    }
+
+    pub fn cosign(self, witness: &dyn Signer) -> Result<Cosignature, SignerError> {
+        let witness = DetachedSignature::create(witness, &self.cosigned_message())?;
+        Ok(Cosignature {
+            checkpoint: self,
+            witness,
+        })
+    }
+
+    pub fn sign(self, signer: &dyn Signer) -> Result<SignedCheckpoint, SignerError> {
+        let signature = DetachedSignature::create(signer, &self.signed_message())?;
+        Ok(SignedCheckpoint {
+            checkpoint: self,
+            signature,
+        })
+    }
+}
+
+impl SignedCheckpoint {
+    /// Check the signature against the embedded public key. Callers must still
+    /// decide whether that key is one they trust.
+    pub fn verify(&self) -> bool {
+        self.signature.verify(&self.checkpoint.signed_message())
+    }
+
+    /// Check the signature and that it was made by `public_key_hex`.
+    pub fn verify_with(&self, public_key_hex: &str) -> bool {
+        self.signature
+            .public_key
+            .eq_ignore_ascii_case(public_key_hex)
+            && self.verify()
+    }
+}
+
+impl Cosignature {
+    pub fn verify(&self) -> bool {
+        self.witness.verify(&self.checkpoint.cosigned_message())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::KeyFileSigner;
+
+    #[test]
+    fn signed_checkpoint_round_trip() {
+        let signer = KeyFileSigner::from_seed([1u8; 32]);
+        let signed = Checkpoint {
+            size: 4,
+            root: "AB".repeat(32),
+            timestamp_nanos: "1".into(),
+        }
+        .sign(&signer)
+        .expect("sign");
+
+        assert!(signed.verify());
+        assert!(signed.verify_with(&hex::encode(signer.public_key())));
+        assert!(!signed.verify_with(&"00".repeat(32)));
+
+        let json = serde_json::to_string(&signed).unwrap();
+        let mut tampered: SignedCheckpoint = serde_json::from_str(&json).unwrap();
+        assert_eq!(tampered, signed);
+        tampered.checkpoint.size = 5;
+        assert!(!tampered.verify());
+    }
+
+    #[test]
+    fn cosignature_is_not_a_log_signature() {
+        let witness = KeyFileSigner::from_seed([2u8; 32]);
+        let checkpoint = Checkpoint {
+            size: 4,
+            root: "cd".repeat(32),
+            timestamp_nanos: "1".into(),
+        };
+        let cosigned = checkpoint.clone().cosign(&witness).expect("cosign");
+        assert!(cosigned.verify());
+
+        let as_log = SignedCheckpoint {
+            checkpoint,
+            signature: cosigned.witness.clone(),
+        };
+        assert!(!as_log.verify());
+    }
+}
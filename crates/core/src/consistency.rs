@@ -0,0 +1,213 @@
+//! Consistency proofs between two sizes of the same log.
+//!
+//! The tree duplicates an odd last node instead of splitting unevenly, so the
+//! RFC 6962 algorithm does not apply. The proof instead lists the old tree's
+//! frontier (the complete subtrees from the binary decomposition of the old
+//! size, smallest first) followed by the right-hand siblings met while walking
+//! from the smallest frontier subtree to the new root. The verifier rebuilds
+//! both roots from the same frontier, which shows the new tree extends the old.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    algorithm::{with_digest, HashAlgorithm},
+    decode_hash,
+    digest::{self, DefaultDigest},
+    MerkleError,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConsistencyProof {
+    pub old_size: u64,
+    pub old_root: String,
+    pub new_size: u64,
+    pub new_root: String,
+    pub path: Vec<String>,
+    #[serde(default, skip_serializing_if = "HashAlgorithm::is_default")]
+    pub algorithm: HashAlgorithm,
+}
+
+/// Prove that the first `old_size` of `leaves` form a prefix of the first
+/// `new_size`. Leaves past `new_size` are ignored, so a log can prove any
+/// two of its sizes.
+pub fn consistency_proof(
+    leaves: &[[u8; 32]],
+    old_size: usize,
+    new_size: usize,
+) -> Result<ConsistencyProof, MerkleError> {
+    digest::consistency_proof::<DefaultDigest>(leaves, old_size, new_size)
+}
+
+pub fn consistency_proof_with(
+    algorithm: HashAlgorithm,
+    leaves: &[[u8; 32]],
+    old_size: usize,
+    new_size: usize,
+) -> Result<ConsistencyProof, MerkleError> {
+    with_digest!(algorithm, D => digest::consistency_proof::<D>(leaves, old_size, new_size))
+}
+
+/// Check that `proof` links its old root to its new root. Callers must still
+/// compare both roots against checkpoints they trust.
+pub fn verify_consistency(proof: &ConsistencyProof) -> bool {
+    let (Ok(old_size), Ok(new_size)) = (
+        usize::try_from(proof.old_size),
+        usize::try_from(proof.new_size),
+    ) else {
+        return false;
+    };
+    if old_size > new_size {
+        return false;
+    }
+    let algorithm = proof.algorithm;
+    if old_size == 0 {
+        return proof.path.is_empty()
+            && proof
+                .old_root
+                .eq_ignore_ascii_case(&hex::encode(algorithm.empty_root()));
+    }
+
+    let Some(hashes) = proof
+        .path
+        .iter()
+        .map(|h| decode_hash(h))
+        .collect::<Option<Vec<_>>>()
+    else {
+        return false;
+    };
+    let frontier_len = old_size.count_ones() as usize;
+    if hashes.len() < frontier_len {
+        return false;
+    }
+    let (frontier, mut siblings) = {
+        let (f, s) = hashes.split_at(frontier_len);
+        (f, s.iter())
+    };
+    let start = old_size.trailing_zeros() as usize;
+
+    // Old root: the right edge of the old tree either pairs with the next
+    // frontier subtree on its left or is duplicated.
+    let mut lefts = frontier[1..].iter();
+    let mut old = frontier[0];
+    let mut level = start;
+    while below_root(old_size, level) {
+        old = if ((old_size - 1) >> level) & 1 == 1 {
+            match lefts.next() {
+                Some(left) => algorithm.node_hash(left, &old),
+                None => return false,
+            }
+        } else {
+            algorithm.node_hash(&old, &old)
+        };
+        level += 1;
+    }
+
+    // New root: same walk, but right siblings now exist where the tree grew.
+    let mut lefts = frontier[1..].iter();
+    let mut new = frontier[0];
+    let mut level = start;
+    while below_root(new_size, level) {
+        let idx = (old_size - 1) >> level;
+        let width = ((new_size - 1) >> level) + 1;
+        new = if idx & 1 == 1 {
+            match lefts.next() {
+                Some(left) => algorithm.node_hash(left, &new),
+                None => return false,
+            }
+        } else if idx + 1 < width {
+            match siblings.next() {
+                Some(right) => algorithm.node_hash(&new, right),
+                None => return false,
+            }
+        } else {
+            algorithm.node_hash(&new, &new)
+        };
+        level += 1;
+    }
+
+    siblings.next().is_none()
+        && lefts.next().is_none()
+        && hex::encode(old).eq_ignore_ascii_case(&proof.old_root)
+        && hex::encode(new).eq_ignore_ascii_case(&proof.new_root)
+}
+
+/// Whether nodes at `level` of a tree of `size` leaves are below its root.
+/// Sizes near `usize::MAX` reach the full width of `usize`, where a plain
+/// shift would overflow.
+fn below_root(size: usize, level: usize) -> bool {
+    (size - 1)
+        .checked_shr(level as u32)
+        .is_some_and(|rest| rest > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::leaf_hash;
+
+    #[test]
+    fn proofs_verify_for_every_size_pair() {
+        for algorithm in HashAlgorithm::ALL {
+            let leaves: Vec<[u8; 32]> = (0..20)
+                .map(|i| algorithm.leaf_hash(format!("leaf-{i}").as_bytes()))
+                .collect();
+            for new_size in 0..=leaves.len() {
+                for old_size in 0..=new_size {
+                    let proof = consistency_proof_with(algorithm, &leaves, old_size, new_size)
+                        .expect("proof");
+                    assert!(
+                        verify_consistency(&proof),
+                        "{algorithm}: {old_size} -> {new_size}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn proves_between_two_earlier_sizes() {
+        let leaves: Vec<[u8; 32]> = (0..12).map(|i| leaf_hash(&[i])).collect();
+        let proof = consistency_proof(&leaves, 3, 9).expect("proof");
+        assert_eq!((proof.old_size, proof.new_size), (3, 9));
+        assert_eq!(proof.new_root, hex::encode(crate::root(&leaves[..9])));
+        assert!(verify_consistency(&proof));
+        assert_eq!(proof, consistency_proof(&leaves[..9], 3, 9).expect("proof"));
+        assert!(matches!(
+            consistency_proof(&leaves, 9, 3),
+            Err(MerkleError::IndexOutOfRange)
+        ));
+        assert!(matches!(
+            consistency_proof(&leaves, 3, 13),
+            Err(MerkleError::IndexOutOfRange)
+        ));
+    }
+
+    #[test]
+    fn rejects_a_rewritten_prefix() {
+        let leaves: Vec<[u8; 32]> = (0..7).map(|i| leaf_hash(&[i])).collect();
+        let mut proof = consistency_proof(&leaves, 5, leaves.len()).expect("proof");
+
+        let mut forked = leaves[..5].to_vec();
+        forked[1] = leaf_hash(b"forged");
+        proof.old_root = hex::encode(crate::root(&forked));
+        assert!(!verify_consistency(&proof));
+
+        let mut truncated = consistency_proof(&leaves, 5, leaves.len()).expect("proof");
+        truncated.path.pop();
+        assert!(!verify_consistency(&truncated));
+    }
+
+    #[test]
+    fn rejects_sizes_of_the_full_word_without_overflowing() {
+        let hash = "00".repeat(32);
+        let proof = ConsistencyProof {
+            old_size: (1 << 63) + 1,
+            old_root: hash.clone(),
+            new_size: u64::MAX,
+            new_root: hash.clone(),
+            path: vec![hash; 4],
+            algorithm: HashAlgorithm::Sha256,
+        };
+        assert!(!verify_consistency(&proof));
+    }
+}
@@ -0,0 +1,170 @@
+//! Inclusion proofs in Certificate Transparency wire format: the
+//! `get-proof-by-hash` response of RFC 6962, `{ leaf_index, audit_path }`
+//! with base64 hashes. The audit path carries no directions; they follow
+//! from the leaf index, one bit per level.
+//!
+//! The hashes are still this log's. Its tree duplicates an odd last node
+//! where RFC 6962 promotes it, so the path always has one step per level.
+//! Proofs from an RFC 6962 log only decode when no level of its tree is odd.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{algorithm::HashAlgorithm, decode_hash, Direction, InclusionProof, ProofStep};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CtInclusionProof {
+    pub leaf_index: u64,
+    pub audit_path: Vec<String>,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CtError {
+    #[error("hashes must be 32 bytes of base64 or hex")]
+    InvalidHash,
+    #[error("leaf index {index} is outside a tree of size {size}")]
+    IndexOutOfRange { index: u64, size: u64 },
+    #[error("a tree of size {size} needs {expected} audit path hashes, got {found}")]
+    PathLength {
+        size: u64,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl CtInclusionProof {
+    pub fn from_proof(proof: &InclusionProof) -> Result<Self, CtError> {
+        let audit_path = proof
+            .path
+            .iter()
+            .map(|step| decode_hash(&step.hash).map(|hash| STANDARD.encode(hash)))
+            .collect::<Option<_>>()
+            .ok_or(CtError::InvalidHash)?;
+        Ok(Self {
+            leaf_index: proof.index,
+            audit_path,
+        })
+    }
+
+    /// Rebuild the proof that `leaf` is in the tree of `tree_size` leaves
+    /// with root `root`. Both hashes may be base64, as in a CT signed tree
+    /// head, or hex.
+    pub fn to_inclusion_proof(
+        &self,
+        algorithm: HashAlgorithm,
+        leaf: &str,
+        root: &str,
+        tree_size: u64,
+    ) -> Result<InclusionProof, CtError> {
+        let index = self.leaf_index;
+        if index >= tree_size {
+            return Err(CtError::IndexOutOfRange {
+                index,
+                size: tree_size,
+            });
+        }
+        let expected = depth(tree_size);
+        if self.audit_path.len() != expected {
+            return Err(CtError::PathLength {
+                size: tree_size,
+                expected,
+                found: self.audit_path.len(),
+            });
+        }
+        let path = self
+            .audit_path
+            .iter()
+            .zip(0..)
+            .map(|(hash, level)| {
+                let direction = match (index >> level) & 1 {
+                    1 => Direction::Left,
+                    _ => Direction::Right,
+                };
+                Ok(ProofStep {
+                    direction,
+                    hash: hex::encode(ct_hash(hash)?),
+                })
+            })
+            .collect::<Result<_, CtError>>()?;
+        Ok(InclusionProof {
+            index,
+            leaf: hex::encode(ct_hash(leaf)?),
+            path,
+            root: hex::encode(ct_hash(root)?),
+            size: tree_size,
+            algorithm,
+        })
+    }
+}
+
+/// Levels above the leaves in a tree of `size` leaves.
+pub(crate) fn depth(size: u64) -> usize {
+    let mut width = size;
+    let mut levels = 0;
+    while width > 1 {
+        width = width.div_ceil(2);
+        levels += 1;
+    }
+    levels
+}
+
+/// A 32-byte hash as 64 hex digits or standard base64.
+fn ct_hash(value: &str) -> Result<[u8; 32], CtError> {
+    if value.len() == 64 {
+        return decode_hash(value).ok_or(CtError::InvalidHash);
+    }
+    STANDARD
+        .decode(value)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(CtError::InvalidHash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{leaf_hash, make_proof, make_proof_with, verify, VerifyRequest};
+
+    #[test]
+    fn ct_paths_round_trip_at_every_size() {
+        let sha256 = HashAlgorithm::Sha256;
+        let leaves: Vec<[u8; 32]> = (0..9u8).map(|i| sha256.leaf_hash(&[i])).collect();
+        for size in 1..=leaves.len() {
+            for index in 0..size {
+                let proof = make_proof_with(sha256, &leaves[..size], index).unwrap();
+                let ct = CtInclusionProof::from_proof(&proof).unwrap();
+                let root = STANDARD.encode(decode_hash(&proof.root).unwrap());
+                let rebuilt = ct
+                    .to_inclusion_proof(HashAlgorithm::Sha256, &proof.leaf, &root, size as u64)
+                    .unwrap();
+                assert_eq!(rebuilt, proof);
+                assert!(verify(&VerifyRequest::from(&rebuilt)).valid);
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_ct_proofs_are_refused() {
+        let leaves: Vec<[u8; 32]> = (0..5u8).map(|i| leaf_hash(&[i])).collect();
+        let proof = make_proof(&leaves, 2).unwrap();
+        let ct = CtInclusionProof::from_proof(&proof).unwrap();
+        let decode = |ct: &CtInclusionProof, size| {
+            ct.to_inclusion_proof(HashAlgorithm::Sha256, &proof.leaf, &proof.root, size)
+        };
+
+        assert!(matches!(
+            decode(&ct, 9),
+            Err(CtError::PathLength { expected: 4, .. })
+        ));
+        assert!(matches!(
+            decode(&ct, 2),
+            Err(CtError::IndexOutOfRange { .. })
+        ));
+        let mut garbled = ct.clone();
+        garbled.audit_path[0] = "not base64!".into();
+        assert_eq!(decode(&garbled, 5), Err(CtError::InvalidHash));
+        let json = serde_json::to_value(&ct).unwrap();
+        assert!(json["audit_path"].is_array() && json["leaf_index"] == 2);
+    }
+}
@@ -0,0 +1,156 @@
+//! Day buckets. In this mode logd groups entries by the UTC day they were
+//! appended, and once a day is over it appends a [`DaySeal`] with the root
+//! of that day's own tree to a master log. A [`DayReceipt`] for an entry is
+//! then two short proofs, one in its day's tree and one of the seal in the
+//! master log, and a sealed day never changes.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{leaf_hash, verify, InclusionProof, VerifyRequest};
+
+/// One closed day, as appended to the master log.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DaySeal {
+    /// `YYYY-MM-DD`, in UTC.
+    pub day: String,
+    /// Index in the log of the day's first entry.
+    pub first: u64,
+    /// Number of entries in the day.
+    pub size: u64,
+    /// Hex root of the tree of the day's entries alone.
+    pub root: String,
+    /// `sha256:<hex>` of the day's archive in the blob store, when archived.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive: Option<String>,
+}
+
+impl DaySeal {
+    /// The seal as a master log payload.
+    pub fn to_payload(&self) -> String {
+        serde_json::to_string(self).expect("day seals serialize")
+    }
+
+    pub fn from_payload(payload: &str) -> Option<Self> {
+        serde_json::from_str(payload).ok()
+    }
+
+    /// Whether entry `index` of the log falls in this day.
+    pub fn contains(&self, index: u64) -> bool {
+        index >= self.first && index - self.first < self.size
+    }
+}
+
+/// Proof that entry `index` is in a sealed day, and that the day is in the
+/// master log. Compare `master.root` with a master root you trust.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DayReceipt {
+    /// Index of the entry in the log.
+    pub index: u64,
+    /// The master log payload, a [`DaySeal`], exactly as appended.
+    pub seal: String,
+    /// Proof of the entry in the day's tree.
+    pub entry: InclusionProof,
+    /// Proof of the seal in the master log.
+    pub master: InclusionProof,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DayReceiptError {
+    #[error("the seal is not a day seal")]
+    BadSeal,
+    #[error("the entry proof is not for entry {0} of the sealed day")]
+    EntryMismatch(u64),
+    #[error("the entry proof does not verify")]
+    InvalidEntryProof,
+    #[error("the master proof is not for the seal")]
+    SealMismatch,
+    #[error("the master proof does not verify")]
+    InvalidMasterProof,
+}
+
+impl DayReceipt {
+    /// Check both proofs and that they fit together, returning the seal.
+    pub fn verify(&self) -> Result<DaySeal, DayReceiptError> {
+        let seal = DaySeal::from_payload(&self.seal).ok_or(DayReceiptError::BadSeal)?;
+        if !seal.contains(self.index)
+            || self.entry.index != self.index - seal.first
+            || self.entry.size != seal.size
+            || !self.entry.root.eq_ignore_ascii_case(&seal.root)
+        {
+            return Err(DayReceiptError::EntryMismatch(self.index));
+        }
+        if !verify(&VerifyRequest::from(&self.entry)).valid {
+            return Err(DayReceiptError::InvalidEntryProof);
+        }
+        if !self
+            .master
+            .leaf
+            .eq_ignore_ascii_case(&hex::encode(leaf_hash(self.seal.as_bytes())))
+        {
+            return Err(DayReceiptError::SealMismatch);
+        }
+        if !verify(&VerifyRequest::from(&self.master)).valid {
+            return Err(DayReceiptError::InvalidMasterProof);
+        }
+        Ok(seal)
+    }
+}
+
+/// One seal in the master log, at `position`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RecordedDay {
+    pub position: u64,
+    #[serde(flatten)]
+    pub seal: DaySeal,
+}
+
+/// logd's `GET /days`: the master log's size and root, and every seal,
+/// oldest first.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DayList {
+    pub size: u64,
+    pub root: String,
+    pub days: Vec<RecordedDay>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::make_proof;
+
+    #[test]
+    fn receipts_chain_entry_to_master() {
+        let leaves: Vec<_> = ["a", "b", "c"]
+            .iter()
+            .map(|payload| leaf_hash(payload.as_bytes()))
+            .collect();
+        let seal = DaySeal {
+            day: "2026-03-01".into(),
+            first: 10,
+            size: 3,
+            root: hex::encode(crate::root(&leaves)),
+            archive: None,
+        }
+        .to_payload();
+        let master = [leaf_hash(b"earlier day"), leaf_hash(seal.as_bytes())];
+        let receipt = DayReceipt {
+            index: 12,
+            seal: seal.clone(),
+            entry: make_proof(&leaves, 2).unwrap(),
+            master: make_proof(&master, 1).unwrap(),
+        };
+        assert_eq!(receipt.verify().unwrap().day, "2026-03-01");
+
+        let mut shifted = receipt.clone();
+        shifted.index = 11;
+        assert_eq!(shifted.verify(), Err(DayReceiptError::EntryMismatch(11)));
+        let mut other = receipt.clone();
+        other.master = make_proof(&master, 0).unwrap();
+        assert_eq!(other.verify(), Err(DayReceiptError::SealMismatch));
+        let mut forged = receipt;
+        forged.seal = seal.replace("2026-03-01", "2026-03-02");
+        forged.master.leaf = hex::encode(leaf_hash(forged.seal.as_bytes()));
+        assert_eq!(forged.verify(), Err(DayReceiptError::InvalidMasterProof));
+    }
+}
@@ -0,0 +1,285 @@
+//! Tree hashing generic over the digest. `leaf_hash::<Sha512_256>` and the
+//! rest build and prove a log hashed with any [`LogDigest`]; the proofs name
+//! it through `LogDigest::ALGORITHM`, so a verifier recomputes them with the
+//! same hash or rejects them.
+//!
+//! The crate root's `leaf_hash`, `root`, `make_proof` and friends use
+//! [`DefaultDigest`], which is SHA-256 unless the `sha512-256` feature makes
+//! it SHA-512/256. Proofs decoded at runtime are checked through
+//! [`HashAlgorithm`], which dispatches to these functions.
+
+use sha2::{
+    digest::{consts::U32, FixedOutput, HashMarker, Output, OutputSizeUser, Reset, Update},
+    Digest, Sha256, Sha512_256,
+};
+use sha3::Sha3_256;
+
+use crate::{
+    algorithm::HashAlgorithm, consistency::ConsistencyProof, Direction, InclusionProof,
+    MerkleError, ProofStep, EMPTY_SENTINEL, LEAF_PREFIX, NODE_PREFIX,
+};
+
+/// A hash a log can be built with: a `Digest` with a 32-byte output, named
+/// by the algorithm its proofs carry.
+pub trait LogDigest: Digest + OutputSizeUser<OutputSize = U32> {
+    const ALGORITHM: HashAlgorithm;
+}
+
+impl LogDigest for Sha256 {
+    const ALGORITHM: HashAlgorithm = HashAlgorithm::Sha256;
+}
+
+impl LogDigest for Sha3_256 {
+    const ALGORITHM: HashAlgorithm = HashAlgorithm::Sha3_256;
+}
+
+impl LogDigest for Sha512_256 {
+    const ALGORITHM: HashAlgorithm = HashAlgorithm::Sha512_256;
+}
+
+impl LogDigest for Blake3 {
+    const ALGORITHM: HashAlgorithm = HashAlgorithm::Blake3;
+}
+
+/// The digest of the crate root's hashing functions.
+#[cfg(not(feature = "sha512-256"))]
+pub type DefaultDigest = Sha256;
+
+/// The digest of the crate root's hashing functions.
+#[cfg(feature = "sha512-256")]
+pub type DefaultDigest = Sha512_256;
+
+/// BLAKE3 behind the `digest` traits `sha2` uses; the `blake3` crate
+/// implements a newer, incompatible version of them.
+#[derive(Debug, Clone, Default)]
+pub struct Blake3(blake3::Hasher);
+
+impl HashMarker for Blake3 {}
+
+impl OutputSizeUser for Blake3 {
+    type OutputSize = U32;
+}
+
+impl Update for Blake3 {
+    fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+}
+
+impl FixedOutput for Blake3 {
+    fn finalize_into(self, out: &mut Output<Self>) {
+        out.copy_from_slice(self.0.finalize().as_bytes());
+    }
+}
+
+impl Reset for Blake3 {
+    fn reset(&mut self) {
+        self.0.reset();
+    }
+}
+
+pub fn leaf_hash<D: LogDigest>(bytes: &[u8]) -> [u8; 32] {
+    D::new()
+        .chain_update(LEAF_PREFIX)
+        .chain_update(bytes)
+        .finalize()
+        .into()
+}
+
+pub fn node_hash<D: LogDigest>(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    D::new()
+        .chain_update(NODE_PREFIX)
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+pub fn empty_root<D: LogDigest>() -> [u8; 32] {
+    D::digest(EMPTY_SENTINEL).into()
+}
+
+/// The root over `leaves`, which must already be `leaf_hash::<D>`es.
+pub fn root<D: LogDigest>(leaves: &[[u8; 32]]) -> [u8; 32] {
+    if leaves.is_empty() {
+        return empty_root::<D>();
+    }
+
+    let mut layer: Vec<[u8; 32]> = leaves.to_vec();
+    while layer.len() > 1 {
+        layer = parents::<D>(&layer);
+    }
+    layer[0]
+}
+
+/// The layer above `layer`; an odd last node is paired with itself.
+pub(crate) fn parents<D: LogDigest>(layer: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    let mut parents = Vec::with_capacity(layer.len().div_ceil(2));
+    for chunk in layer.chunks(2) {
+        let left = chunk[0];
+        let right = if chunk.len() == 2 { chunk[1] } else { chunk[0] };
+        parents.push(node_hash::<D>(&left, &right));
+    }
+    parents
+}
+
+pub fn inclusion_path<D: LogDigest>(
+    leaves: &[[u8; 32]],
+    index: usize,
+) -> Result<Vec<ProofStep>, MerkleError> {
+    if index >= leaves.len() {
+        return Err(MerkleError::IndexOutOfRange);
+    }
+
+    if leaves.len() <= 1 {
+        return Ok(Vec::new());
+    }
+
+    let mut path = Vec::new();
+    let mut idx = index;
+    let mut layer: Vec<[u8; 32]> = leaves.to_vec();
+
+    while layer.len() > 1 {
+        let is_right = idx % 2 == 1;
+        let sibling_idx = if is_right {
+            idx - 1
+        } else if idx + 1 < layer.len() {
+            idx + 1
+        } else {
+            idx
+        };
+
+        let sibling_hash = layer[sibling_idx];
+        let direction = if is_right {
+            Direction::Left
+        } else {
+            Direction::Right
+        };
+
+        path.push(ProofStep {
+            direction,
+            hash: hex::encode(sibling_hash),
+        });
+
+        layer = parents::<D>(&layer);
+        idx /= 2;
+    }
+
+    Ok(path)
+}
+
+pub fn make_proof<D: LogDigest>(
+    leaves: &[[u8; 32]],
+    index: usize,
+) -> Result<InclusionProof, MerkleError> {
+    let size = leaves.len() as u64;
+    let leaf = leaves
+        .get(index)
+        .ok_or(MerkleError::IndexOutOfRange)?
+        .to_owned();
+    let path = inclusion_path::<D>(leaves, index)?;
+    let root = hex::encode(root::<D>(leaves));
+
+    Ok(InclusionProof {
+        index: index as u64,
+        leaf: hex::encode(leaf),
+        path,
+        root,
+        size,
+        algorithm: D::ALGORITHM,
+    })
+}
+
+/// Prove that the first `old_size` of `leaves` form a prefix of the first
+/// `new_size`; see [`crate::consistency`] for the proof's shape.
+pub fn consistency_proof<D: LogDigest>(
+    leaves: &[[u8; 32]],
+    old_size: usize,
+    new_size: usize,
+) -> Result<ConsistencyProof, MerkleError> {
+    let leaves = leaves.get(..new_size).ok_or(MerkleError::IndexOutOfRange)?;
+    if old_size > leaves.len() {
+        return Err(MerkleError::IndexOutOfRange);
+    }
+    let proof = ConsistencyProof {
+        old_size: old_size as u64,
+        old_root: hex::encode(root::<D>(&leaves[..old_size])),
+        new_size: leaves.len() as u64,
+        new_root: hex::encode(root::<D>(leaves)),
+        path: Vec::new(),
+        algorithm: D::ALGORITHM,
+    };
+    if old_size == 0 {
+        return Ok(proof);
+    }
+
+    let mut layers = vec![leaves.to_vec()];
+    while layers.last().map(Vec::len).unwrap_or(0) > 1 {
+        let next = parents::<D>(layers.last().expect("non-empty"));
+        layers.push(next);
+    }
+
+    let start = old_size.trailing_zeros() as usize;
+    let mut path: Vec<String> = (start..layers.len())
+        .filter(|level| old_size >> level & 1 == 1)
+        .map(|level| hex::encode(layers[level][(old_size >> level) - 1]))
+        .collect();
+    for (level, layer) in layers.iter().enumerate().skip(start) {
+        let idx = (old_size - 1) >> level;
+        if layer.len() == 1 {
+            break;
+        }
+        if idx & 1 == 0 && idx + 1 < layer.len() {
+            path.push(hex::encode(layer[idx + 1]));
+        }
+    }
+
+    Ok(ConsistencyProof { path, ..proof })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{consistency::verify_consistency, verify, VerifyRequest};
+
+    fn proves<D: LogDigest>() {
+        let leaves: Vec<[u8; 32]> = (0..9u8).map(|i| leaf_hash::<D>(&[i])).collect();
+        assert_eq!(leaf_hash::<D>(b"a"), D::ALGORITHM.leaf_hash(b"a"));
+        assert_eq!(root::<D>(&leaves), crate::root_with(D::ALGORITHM, &leaves));
+
+        let proof = make_proof::<D>(&leaves, 4).unwrap();
+        assert_eq!(proof.algorithm, D::ALGORITHM);
+        let request = |algorithm| VerifyRequest {
+            algorithm,
+            ..VerifyRequest::from(&proof)
+        };
+        assert!(verify(&request(D::ALGORITHM)).valid);
+        for other in HashAlgorithm::ALL
+            .into_iter()
+            .filter(|a| *a != D::ALGORITHM)
+        {
+            assert!(!verify(&request(other)).valid, "{other} accepted");
+        }
+
+        let mut consistency = consistency_proof::<D>(&leaves, 3, 9).unwrap();
+        assert!(verify_consistency(&consistency));
+        consistency.algorithm = HashAlgorithm::ALL
+            .into_iter()
+            .find(|a| *a != D::ALGORITHM)
+            .unwrap();
+        assert!(!verify_consistency(&consistency));
+    }
+
+    #[test]
+    fn every_digest_proves_and_names_itself() {
+        proves::<Sha256>();
+        proves::<Sha3_256>();
+        proves::<Sha512_256>();
+        proves::<Blake3>();
+        assert_eq!(
+            leaf_hash::<Blake3>(b"a"),
+            *blake3::hash(b"\x00a").as_bytes()
+        );
+        assert_eq!(crate::leaf_hash(b"a"), leaf_hash::<DefaultDigest>(b"a"));
+    }
+}
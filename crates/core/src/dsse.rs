@@ -0,0 +1,242 @@
+//! DSSE envelopes (Dead Simple Signing Envelope), the wrapping of in-toto
+//! attestations such as SLSA provenance. Signatures cover the envelope's
+//! pre-authentication encoding, `DSSEv1 <len> <type> <len> <payload>`, so
+//! the payload type is signed along with the payload.
+//!
+//! Only ed25519 signatures can be checked here; an envelope signed with
+//! another algorithm is still well formed, just not verifiable by this log.
+
+use std::collections::BTreeMap;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::signer::{verify_signature, Signer, SignerError};
+
+/// The payload type of an in-toto statement.
+pub const IN_TOTO_PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+
+/// Every version of the in-toto statement type starts with this.
+const STATEMENT_TYPE_PREFIX: &str = "https://in-toto.io/Statement/";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Envelope {
+    /// Base64 of the payload.
+    pub payload: String,
+    pub payload_type: String,
+    pub signatures: Vec<EnvelopeSignature>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EnvelopeSignature {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub keyid: String,
+    /// Base64 of the signature.
+    pub sig: String,
+}
+
+/// An in-toto statement: what the attestation is about and what it says.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Statement {
+    #[serde(rename = "_type")]
+    pub statement_type: String,
+    pub subject: Vec<Subject>,
+    pub predicate_type: String,
+    #[serde(default)]
+    pub predicate: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Subject {
+    #[serde(default)]
+    pub name: String,
+    /// Digests by algorithm, such as `sha256` or `gitCommit`.
+    pub digest: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DsseError {
+    #[error("the envelope's {0} is not valid base64")]
+    InvalidBase64(&'static str),
+    #[error("the envelope has no payload type")]
+    MissingPayloadType,
+    #[error("the envelope has no signatures")]
+    Unsigned,
+    #[error("the payload is not an in-toto statement: {0}")]
+    InvalidStatement(String),
+    #[error("the statement has no subjects")]
+    NoSubjects,
+    #[error("subject {0:?} has no digests")]
+    NoDigests(String),
+}
+
+/// The pre-authentication encoding that DSSE signatures cover.
+pub fn pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut encoded = format!(
+        "DSSEv1 {} {payload_type} {} ",
+        payload_type.len(),
+        payload.len()
+    )
+    .into_bytes();
+    encoded.extend_from_slice(payload);
+    encoded
+}
+
+impl Envelope {
+    /// Wrap `payload` and sign it with `signer`.
+    pub fn sign(
+        payload_type: &str,
+        payload: &[u8],
+        signer: &dyn Signer,
+        keyid: &str,
+    ) -> Result<Self, SignerError> {
+        let sig = signer.sign(&pae(payload_type, payload))?;
+        Ok(Self {
+            payload: STANDARD.encode(payload),
+            payload_type: payload_type.to_string(),
+            signatures: vec![EnvelopeSignature {
+                keyid: keyid.to_string(),
+                sig: STANDARD.encode(sig),
+            }],
+        })
+    }
+
+    pub fn payload_bytes(&self) -> Result<Vec<u8>, DsseError> {
+        STANDARD
+            .decode(&self.payload)
+            .map_err(|_| DsseError::InvalidBase64("payload"))
+    }
+
+    /// Check that the envelope is well formed, and for an in-toto payload,
+    /// that it holds a statement with digests to look it up by. Signatures
+    /// are decoded but not verified.
+    pub fn validate(&self) -> Result<Option<Statement>, DsseError> {
+        let payload = self.payload_bytes()?;
+        if self.payload_type.is_empty() {
+            return Err(DsseError::MissingPayloadType);
+        }
+        if self.signatures.is_empty() {
+            return Err(DsseError::Unsigned);
+        }
+        for signature in &self.signatures {
+            STANDARD
+                .decode(&signature.sig)
+                .map_err(|_| DsseError::InvalidBase64("signature"))?;
+        }
+        if self.payload_type != IN_TOTO_PAYLOAD_TYPE {
+            return Ok(None);
+        }
+        let statement: Statement = serde_json::from_slice(&payload)
+            .map_err(|err| DsseError::InvalidStatement(err.to_string()))?;
+        if !statement.statement_type.starts_with(STATEMENT_TYPE_PREFIX) {
+            return Err(DsseError::InvalidStatement(format!(
+                "_type {:?} is not an in-toto statement type",
+                statement.statement_type
+            )));
+        }
+        if statement.subject.is_empty() {
+            return Err(DsseError::NoSubjects);
+        }
+        if let Some(subject) = statement.subject.iter().find(|s| s.digest.is_empty()) {
+            return Err(DsseError::NoDigests(subject.name.clone()));
+        }
+        Ok(Some(statement))
+    }
+
+    /// Whether any signature is a valid ed25519 signature by the hex
+    /// `public_key`.
+    pub fn verify_ed25519(&self, public_key: &str) -> bool {
+        let Some(key) = hex::decode(public_key)
+            .ok()
+            .and_then(|key| <[u8; 32]>::try_from(key).ok())
+        else {
+            return false;
+        };
+        let Ok(payload) = self.payload_bytes() else {
+            return false;
+        };
+        let message = pae(&self.payload_type, &payload);
+        self.signatures.iter().any(|signature| {
+            STANDARD
+                .decode(&signature.sig)
+                .ok()
+                .and_then(|sig| <[u8; 64]>::try_from(sig).ok())
+                .is_some_and(|sig| verify_signature(&key, &message, &sig))
+        })
+    }
+}
+
+impl Subject {
+    /// The subject's digests as `<algorithm>:<value>`, lowercased, which is
+    /// how attestations are looked up.
+    pub fn digests(&self) -> impl Iterator<Item = String> + '_ {
+        self.digest
+            .iter()
+            .map(|(algorithm, value)| format!("{algorithm}:{value}").to_ascii_lowercase())
+    }
+}
+
+/// An attestation in the log, as found by one of its subjects' digests.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AttestationHit {
+    pub index: u64,
+    pub leaf: String,
+    pub predicate_type: String,
+    pub subjects: Vec<Subject>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::KeyFileSigner;
+
+    #[test]
+    fn validates_and_verifies_in_toto_envelopes() {
+        // The example from the DSSE specification's protocol description.
+        assert_eq!(
+            pae("http://example.com/HelloWorld", b"hello world"),
+            b"DSSEv1 29 http://example.com/HelloWorld 11 hello world"
+        );
+
+        let statement = serde_json::json!({
+            "_type": "https://in-toto.io/Statement/v1",
+            "subject": [{"name": "app.tar.gz", "digest": {"sha256": "ABCD"}}],
+            "predicateType": "https://slsa.dev/provenance/v1",
+            "predicate": {"buildDefinition": {}},
+        });
+        let payload = serde_json::to_vec(&statement).unwrap();
+        let signer = KeyFileSigner::from_seed([3u8; 32]);
+        let key = hex::encode(signer.public_key());
+        let envelope = Envelope::sign(IN_TOTO_PAYLOAD_TYPE, &payload, &signer, "ci").unwrap();
+
+        let parsed = envelope.validate().unwrap().unwrap();
+        assert_eq!(parsed.predicate_type, "https://slsa.dev/provenance/v1");
+        assert_eq!(
+            parsed.subject[0].digests().collect::<Vec<_>>(),
+            ["sha256:abcd"]
+        );
+        assert!(envelope.verify_ed25519(&key));
+        assert!(!envelope.verify_ed25519(&"00".repeat(32)));
+
+        let mut retyped = envelope.clone();
+        retyped.payload_type = "application/json".into();
+        assert_eq!(retyped.validate(), Ok(None));
+        assert!(!retyped.verify_ed25519(&key));
+
+        let mut unsigned = envelope.clone();
+        unsigned.signatures.clear();
+        assert_eq!(unsigned.validate(), Err(DsseError::Unsigned));
+
+        let no_subjects = serde_json::to_vec(&serde_json::json!({
+            "_type": "https://in-toto.io/Statement/v0.1",
+            "subject": [],
+            "predicateType": "x",
+        }))
+        .unwrap();
+        let envelope = Envelope::sign(IN_TOTO_PAYLOAD_TYPE, &no_subjects, &signer, "").unwrap();
+        assert_eq!(envelope.validate(), Err(DsseError::NoSubjects));
+    }
+}
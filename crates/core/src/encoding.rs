@@ -0,0 +1,183 @@
+//! Compact binary encoding of inclusion proofs.
+//!
+//! ```text
+//! magic   "RLIP"            4 bytes
+//! version 0x02              1 byte
+//! alg     hash algorithm    1 byte  (0x00 = sha256, 0x01 = sha3-256, 0x02 = blake3,
+//!                                    0x03 = sha512-256)
+//! index   u64 big-endian    8 bytes
+//! size    u64 big-endian    8 bytes
+//! leaf                      32 bytes
+//! root                      32 bytes
+//! steps   u8                1 byte
+//! step    direction, hash   1 + 32 bytes each (0x00 = left, 0x01 = right)
+//! ```
+//!
+//! Version 0x01 has no algorithm byte and is always SHA-256.
+
+use thiserror::Error;
+
+use crate::{algorithm::HashAlgorithm, decode_hash, Direction, InclusionProof, ProofStep};
+
+const MAGIC: &[u8; 4] = b"RLIP";
+const VERSION: u8 = 0x02;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DecodeError {
+    #[error("not a binary inclusion proof")]
+    BadMagic,
+    #[error("unsupported proof encoding version {0}")]
+    UnsupportedVersion(u8),
+    #[error("proof is truncated")]
+    Truncated,
+    #[error("unknown hash algorithm byte {0:#04x}")]
+    BadAlgorithm(u8),
+    #[error("invalid direction byte {0:#04x}")]
+    BadDirection(u8),
+    #[error("unexpected bytes after the proof")]
+    TrailingBytes,
+    #[error("proof hashes must be 32 bytes of hex")]
+    InvalidHash,
+    #[error("proof has more than 255 steps")]
+    TooManySteps,
+}
+
+impl InclusionProof {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, DecodeError> {
+        let steps = u8::try_from(self.path.len()).map_err(|_| DecodeError::TooManySteps)?;
+        let mut out = Vec::with_capacity(87 + self.path.len() * 33);
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.push(match self.algorithm {
+            HashAlgorithm::Sha256 => 0x00,
+            HashAlgorithm::Sha3_256 => 0x01,
+            HashAlgorithm::Blake3 => 0x02,
+            HashAlgorithm::Sha512_256 => 0x03,
+        });
+        out.extend_from_slice(&self.index.to_be_bytes());
+        out.extend_from_slice(&self.size.to_be_bytes());
+        out.extend_from_slice(&decode_hash(&self.leaf).ok_or(DecodeError::InvalidHash)?);
+        out.extend_from_slice(&decode_hash(&self.root).ok_or(DecodeError::InvalidHash)?);
+        out.push(steps);
+        for step in &self.path {
+            out.push(match step.direction {
+                Direction::Left => 0x00,
+                Direction::Right => 0x01,
+            });
+            out.extend_from_slice(&decode_hash(&step.hash).ok_or(DecodeError::InvalidHash)?);
+        }
+        Ok(out)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader(bytes);
+        if reader.take(4)? != MAGIC {
+            return Err(DecodeError::BadMagic);
+        }
+        let algorithm = match reader.byte()? {
+            0x01 => HashAlgorithm::Sha256,
+            VERSION => match reader.byte()? {
+                0x00 => HashAlgorithm::Sha256,
+                0x01 => HashAlgorithm::Sha3_256,
+                0x02 => HashAlgorithm::Blake3,
+                0x03 => HashAlgorithm::Sha512_256,
+                other => return Err(DecodeError::BadAlgorithm(other)),
+            },
+            other => return Err(DecodeError::UnsupportedVersion(other)),
+        };
+        let index = reader.u64()?;
+        let size = reader.u64()?;
+        let leaf = hex::encode(reader.take(32)?);
+        let root = hex::encode(reader.take(32)?);
+        let steps = reader.byte()?;
+        let mut path = Vec::with_capacity(steps as usize);
+        for _ in 0..steps {
+            let direction = match reader.byte()? {
+                0x00 => Direction::Left,
+                0x01 => Direction::Right,
+                other => return Err(DecodeError::BadDirection(other)),
+            };
+            path.push(ProofStep {
+                direction,
+                hash: hex::encode(reader.take(32)?),
+            });
+        }
+        if !reader.0.is_empty() {
+            return Err(DecodeError::TrailingBytes);
+        }
+        Ok(Self {
+            index,
+            leaf,
+            path,
+            root,
+            size,
+            algorithm,
+        })
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        if self.0.len() < n {
+            return Err(DecodeError::Truncated);
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u64(&mut self) -> Result<u64, DecodeError> {
+        let bytes: [u8; 8] = self.take(8)?.try_into().expect("8 bytes");
+        Ok(u64::from_be_bytes(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{leaf_hash, make_proof_with};
+
+    #[test]
+    fn binary_proof_round_trip() {
+        let leaves: Vec<[u8; 32]> = (0..5u8).map(|i| leaf_hash(&[i])).collect();
+        let proof = make_proof_with(HashAlgorithm::Sha256, &leaves, 3).expect("proof");
+        let bytes = proof.to_bytes().expect("encode");
+        assert_eq!(bytes.len(), 87 + proof.path.len() * 33);
+        assert_eq!(InclusionProof::from_bytes(&bytes), Ok(proof.clone()));
+
+        // Version 1 proofs carry no algorithm byte.
+        let mut v1 = bytes.clone();
+        v1.remove(5);
+        v1[4] = 0x01;
+        assert_eq!(InclusionProof::from_bytes(&v1), Ok(proof));
+
+        for (byte, algorithm) in (0u8..).zip(HashAlgorithm::ALL) {
+            let leaves: Vec<[u8; 32]> = (0..5u8).map(|i| algorithm.leaf_hash(&[i])).collect();
+            let proof = make_proof_with(algorithm, &leaves, 1).expect("proof");
+            let bytes = proof.to_bytes().expect("encode");
+            assert_eq!(bytes[5], byte, "{algorithm}");
+            assert_eq!(InclusionProof::from_bytes(&bytes), Ok(proof));
+        }
+
+        assert_eq!(
+            InclusionProof::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(DecodeError::Truncated)
+        );
+        let mut extra = bytes.clone();
+        extra.push(0);
+        assert_eq!(
+            InclusionProof::from_bytes(&extra),
+            Err(DecodeError::TrailingBytes)
+        );
+        assert_eq!(
+            InclusionProof::from_bytes(b"JSON{}"),
+            Err(DecodeError::BadMagic)
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Proof of freshness. A client sends a nonce it has just drawn, and the log
+//! answers with its current checkpoint and a second signature binding that
+//! nonce to it. The checkpoint cannot predate the nonce, so the client knows
+//! the head it holds was current when it asked, not replayed from earlier.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    checkpoint::SignedCheckpoint,
+    signer::{DetachedSignature, Signer, SignerError},
+};
+
+/// Fewest and most bytes in a nonce.
+pub const NONCE_BYTES: std::ops::RangeInclusive<usize> = 16..=64;
+
+/// logd's `POST /freshness` body.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FreshnessRequest {
+    /// Hex of 16 to 64 random bytes.
+    pub nonce: String,
+}
+
+/// The log's current checkpoint, bound to a client's nonce.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FreshCheckpoint {
+    pub nonce: String,
+    pub checkpoint: SignedCheckpoint,
+    /// Over the nonce and the checkpoint, by the checkpoint's key.
+    pub freshness: DetachedSignature,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum FreshnessError {
+    #[error("nonce must be hex of 16 to 64 bytes")]
+    BadNonce,
+    #[error("the statement is for another nonce")]
+    NonceMismatch,
+    #[error("checkpoint signature is invalid")]
+    BadCheckpoint,
+    #[error("freshness signature is invalid")]
+    BadSignature,
+    #[error("checkpoint and freshness signatures are by different keys")]
+    KeyMismatch,
+    #[error("checkpoint is signed by {0}, not the trusted log key")]
+    UntrustedKey(String),
+}
+
+/// Whether `nonce` is hex of an accepted length.
+pub fn valid_nonce(nonce: &str) -> bool {
+    nonce.len().is_multiple_of(2)
+        && NONCE_BYTES.contains(&(nonce.len() / 2))
+        && nonce.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// The exact bytes covered by the freshness signature; distinct from the
+/// checkpoint's message so one signature cannot stand in for the other.
+pub fn signed_message(nonce: &str, checkpoint: &SignedCheckpoint) -> Vec<u8> {
+    let mut message =
+        format!("reality-freshness/v1\n{}\n", nonce.to_ascii_lowercase()).into_bytes();
+    message.extend(checkpoint.checkpoint.signed_message());
+    message
+}
+
+impl FreshCheckpoint {
+    /// Bind `checkpoint` to `nonce` with `signer`, which should be the key
+    /// that signed the checkpoint.
+    pub fn sign(
+        nonce: String,
+        checkpoint: SignedCheckpoint,
+        signer: &dyn Signer,
+    ) -> Result<Self, SignerError> {
+        let freshness = DetachedSignature::create(signer, &signed_message(&nonce, &checkpoint))?;
+        Ok(Self {
+            nonce,
+            checkpoint,
+            freshness,
+        })
+    }
+
+    /// Check that the statement answers `nonce` and that both signatures
+    /// verify under one key, `trusted_key` if given.
+    pub fn verify(&self, nonce: &str, trusted_key: Option<&str>) -> Result<(), FreshnessError> {
+        if !valid_nonce(nonce) {
+            return Err(FreshnessError::BadNonce);
+        }
+        if !self.nonce.eq_ignore_ascii_case(nonce) {
+            return Err(FreshnessError::NonceMismatch);
+        }
+        if !self.checkpoint.verify() {
+            return Err(FreshnessError::BadCheckpoint);
+        }
+        if !self
+            .freshness
+            .verify(&signed_message(&self.nonce, &self.checkpoint))
+        {
+            return Err(FreshnessError::BadSignature);
+        }
+        let key = &self.checkpoint.signature.public_key;
+        if !self.freshness.public_key.eq_ignore_ascii_case(key) {
+            return Err(FreshnessError::KeyMismatch);
+        }
+        match trusted_key {
+            Some(trusted) if !key.eq_ignore_ascii_case(trusted) => {
+                Err(FreshnessError::UntrustedKey(key.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        checkpoint::Checkpoint,
+        signer::{KeyFileSigner, Signer},
+    };
+
+    #[test]
+    fn fresh_checkpoints_answer_one_nonce() {
+        let signer = KeyFileSigner::from_seed([6u8; 32]);
+        let checkpoint = Checkpoint {
+            size: 3,
+            root: "ab".repeat(32),
+            timestamp_nanos: "1".into(),
+        }
+        .sign(&signer)
+        .unwrap();
+        let nonce = "0f".repeat(16);
+        let fresh = FreshCheckpoint::sign(nonce.clone(), checkpoint.clone(), &signer).unwrap();
+        let key = hex::encode(signer.public_key());
+        assert_eq!(
+            fresh.verify(&nonce.to_ascii_uppercase(), Some(&key)),
+            Ok(())
+        );
+        assert_eq!(
+            fresh.verify(&"0e".repeat(16), None),
+            Err(FreshnessError::NonceMismatch)
+        );
+        assert_eq!(fresh.verify("0f", None), Err(FreshnessError::BadNonce));
+        assert!(!valid_nonce(&"zz".repeat(16)) && !valid_nonce(&"0".repeat(33)));
+        assert!(matches!(
+            fresh.verify(&nonce, Some(&"00".repeat(32))),
+            Err(FreshnessError::UntrustedKey(_))
+        ));
+
+        // The checkpoint's own signature does not prove freshness.
+        let mut replayed = fresh.clone();
+        replayed.freshness = checkpoint.signature.clone();
+        assert_eq!(
+            replayed.verify(&nonce, None),
+            Err(FreshnessError::BadSignature)
+        );
+        let other = KeyFileSigner::from_seed([7u8; 32]);
+        let mixed = FreshCheckpoint::sign(nonce.clone(), checkpoint, &other).unwrap();
+        assert_eq!(mixed.verify(&nonce, None), Err(FreshnessError::KeyMismatch));
+    }
+}
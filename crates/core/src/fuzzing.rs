@@ -0,0 +1,115 @@
+//! `Arbitrary` impls for the types logd and verifiers parse from untrusted
+//! input. Hashes, keys and signatures are mostly well-formed hex, so fuzz
+//! inputs get past decoding and into the hashing and signature checks; now
+//! and then they are any string at all.
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::{
+    checkpoint::Checkpoint, consistency::ConsistencyProof, signer::DetachedSignature,
+    InclusionProof, ProofStep, VerifyRequest,
+};
+
+/// Hex of `N` arbitrary bytes, sometimes uppercase, or one time in eight
+/// an arbitrary string.
+fn hex_of<const N: usize>(u: &mut Unstructured<'_>) -> Result<String> {
+    if u.ratio(1, 8)? {
+        return String::arbitrary(u);
+    }
+    let encoded = hex::encode(<[u8; N]>::arbitrary(u)?);
+    Ok(if u.ratio(1, 4)? {
+        encoded.to_ascii_uppercase()
+    } else {
+        encoded
+    })
+}
+
+fn hashes(u: &mut Unstructured<'_>) -> Result<Vec<String>> {
+    let len = u.arbitrary_len::<[u8; 32]>()?;
+    (0..len).map(|_| hex_of::<32>(u)).collect()
+}
+
+impl<'a> Arbitrary<'a> for ProofStep {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self {
+            direction: u.arbitrary()?,
+            hash: hex_of::<32>(u)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for InclusionProof {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self {
+            index: u.arbitrary()?,
+            leaf: hex_of::<32>(u)?,
+            path: u.arbitrary()?,
+            root: hex_of::<32>(u)?,
+            size: u.arbitrary()?,
+            algorithm: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for VerifyRequest {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(VerifyRequest::from(&InclusionProof::arbitrary(u)?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for ConsistencyProof {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self {
+            old_size: u.arbitrary()?,
+            old_root: hex_of::<32>(u)?,
+            new_size: u.arbitrary()?,
+            new_root: hex_of::<32>(u)?,
+            path: hashes(u)?,
+            algorithm: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Checkpoint {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let timestamp_nanos = if u.ratio(1, 8)? {
+            String::arbitrary(u)?
+        } else {
+            u128::arbitrary(u)?.to_string()
+        };
+        Ok(Self {
+            size: u.arbitrary()?,
+            root: hex_of::<32>(u)?,
+            timestamp_nanos,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for DetachedSignature {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self {
+            public_key: hex_of::<32>(u)?,
+            signature: hex_of::<64>(u)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{checkpoint::SignedCheckpoint, verify};
+
+    #[test]
+    fn values_are_mostly_well_formed() {
+        let bytes: Vec<u8> = (0..4096u32).map(|i| (i * 7919 % 251) as u8).collect();
+        let mut u = Unstructured::new(&bytes);
+        let mut decoded = 0;
+        for _ in 0..16 {
+            let request = VerifyRequest::arbitrary(&mut u).unwrap();
+            decoded += usize::from(verify(&request).computed_root.len() == 64);
+            let signed = SignedCheckpoint::arbitrary(&mut u).unwrap();
+            assert!(!signed.verify());
+        }
+        assert!(decoded > 0);
+    }
+}
@@ -0,0 +1,181 @@
+//! Failover handoff. When a standby that replicated a primary is promoted,
+//! it publishes a [`Handoff`]: the last checkpoint the old primary signed
+//! that the standby holds, the standby's first checkpoint as primary, a
+//! consistency proof between the two trees, and a signature by the new key
+//! over both. A client or witness that trusted the old key can check that
+//! the new primary carries on the same log, with nothing dropped or
+//! rewritten, before it trusts the new key.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    checkpoint::SignedCheckpoint,
+    consistency::{verify_consistency, ConsistencyProof},
+    signer::{DetachedSignature, Signer, SignerError},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Handoff {
+    /// The old primary's last checkpoint the new primary holds.
+    pub from: SignedCheckpoint,
+    /// The new primary's first checkpoint, at least as large as `from`.
+    pub to: SignedCheckpoint,
+    /// Shows `from`'s tree is a prefix of `to`'s; absent when both are the
+    /// same size.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub consistency: Option<ConsistencyProof>,
+    /// Over both checkpoints and the old key, by `to`'s key.
+    pub signature: DetachedSignature,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum HandoffError {
+    #[error("the old primary's checkpoint signature is invalid")]
+    BadFrom,
+    #[error("the new primary's checkpoint signature is invalid")]
+    BadTo,
+    #[error("handoff signature is invalid")]
+    BadSignature,
+    #[error("the handoff is not signed by the new primary's checkpoint key")]
+    KeyMismatch,
+    #[error("the old primary's checkpoint is signed by {0}, not the trusted key")]
+    UntrustedFrom(String),
+    #[error("the new primary's checkpoint is signed by {0}, not the expected key")]
+    UntrustedTo(String),
+    #[error("the new primary's tree of {to} is not a prefix-consistent extension of {from}")]
+    Inconsistent { from: u64, to: u64 },
+}
+
+/// The exact bytes covered by the handoff signature.
+pub fn signed_message(from: &SignedCheckpoint, to: &SignedCheckpoint) -> Vec<u8> {
+    let mut message = format!(
+        "reality-handoff/v1\n{}\n",
+        from.signature.public_key.to_ascii_lowercase()
+    )
+    .into_bytes();
+    message.extend(from.checkpoint.signed_message());
+    message.extend(to.checkpoint.signed_message());
+    message
+}
+
+impl Handoff {
+    /// Hand over from `from` to `to`, signing with `signer`, the key that
+    /// signed `to`.
+    pub fn sign(
+        from: SignedCheckpoint,
+        to: SignedCheckpoint,
+        consistency: Option<ConsistencyProof>,
+        signer: &dyn Signer,
+    ) -> Result<Self, SignerError> {
+        let signature = DetachedSignature::create(signer, &signed_message(&from, &to))?;
+        Ok(Self {
+            from,
+            to,
+            consistency,
+            signature,
+        })
+    }
+
+    /// Check every signature, that `to`'s tree extends `from`'s, and that
+    /// the checkpoints are by `old_key` and `new_key` when given.
+    pub fn verify(&self, old_key: Option<&str>, new_key: Option<&str>) -> Result<(), HandoffError> {
+        if !self.from.verify() {
+            return Err(HandoffError::BadFrom);
+        }
+        if !self.to.verify() {
+            return Err(HandoffError::BadTo);
+        }
+        if !self.signature.verify(&signed_message(&self.from, &self.to)) {
+            return Err(HandoffError::BadSignature);
+        }
+        let to_key = &self.to.signature.public_key;
+        if !self.signature.public_key.eq_ignore_ascii_case(to_key) {
+            return Err(HandoffError::KeyMismatch);
+        }
+        let from_key = &self.from.signature.public_key;
+        if old_key.is_some_and(|trusted| !from_key.eq_ignore_ascii_case(trusted)) {
+            return Err(HandoffError::UntrustedFrom(from_key.clone()));
+        }
+        if new_key.is_some_and(|expected| !to_key.eq_ignore_ascii_case(expected)) {
+            return Err(HandoffError::UntrustedTo(to_key.clone()));
+        }
+        let (from, to) = (&self.from.checkpoint, &self.to.checkpoint);
+        let consistent = if from.size == to.size {
+            from.root.eq_ignore_ascii_case(&to.root)
+        } else {
+            self.consistency.as_ref().is_some_and(|proof| {
+                proof.old_size == from.size
+                    && proof.old_root.eq_ignore_ascii_case(&from.root)
+                    && proof.new_size == to.size
+                    && proof.new_root.eq_ignore_ascii_case(&to.root)
+                    && verify_consistency(proof)
+            })
+        };
+        if !consistent {
+            return Err(HandoffError::Inconsistent {
+                from: from.size,
+                to: to.size,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        checkpoint::Checkpoint,
+        consistency::consistency_proof,
+        leaf_hash, root,
+        signer::{KeyFileSigner, Signer},
+    };
+
+    fn signed(leaves: &[[u8; 32]], signer: &KeyFileSigner) -> SignedCheckpoint {
+        Checkpoint {
+            size: leaves.len() as u64,
+            root: hex::encode(root(leaves)),
+            timestamp_nanos: "1".into(),
+        }
+        .sign(signer)
+        .unwrap()
+    }
+
+    #[test]
+    fn handoffs_extend_the_old_primarys_tree() {
+        let leaves: Vec<[u8; 32]> = (0..6u8).map(|i| leaf_hash(&[i])).collect();
+        let old = KeyFileSigner::from_seed([1; 32]);
+        let new = KeyFileSigner::from_seed([2; 32]);
+        let (old_key, new_key) = (hex::encode(old.public_key()), hex::encode(new.public_key()));
+        let from = signed(&leaves[..4], &old);
+        let to = signed(&leaves, &new);
+        let proof = consistency_proof(&leaves, 4, leaves.len()).unwrap();
+        let handoff = Handoff::sign(from.clone(), to.clone(), Some(proof.clone()), &new).unwrap();
+        assert_eq!(handoff.verify(Some(&old_key), Some(&new_key)), Ok(()));
+        assert_eq!(
+            handoff.verify(Some(&new_key), None),
+            Err(HandoffError::UntrustedFrom(old_key.clone()))
+        );
+        let unproven = Handoff::sign(from.clone(), to.clone(), None, &new).unwrap();
+        assert_eq!(
+            unproven.verify(None, None),
+            Err(HandoffError::Inconsistent { from: 4, to: 6 })
+        );
+        let same = Handoff::sign(from.clone(), signed(&leaves[..4], &new), None, &new).unwrap();
+        assert_eq!(same.verify(None, None), Ok(()));
+
+        // A standby that lost an entry cannot prove its tree extends the old one.
+        let mut forked = leaves.clone();
+        forked[2] = leaf_hash(b"rewritten");
+        let forked_to = signed(&forked, &new);
+        let forked_proof = consistency_proof(&forked, 4, forked.len()).unwrap();
+        let forked = Handoff::sign(from.clone(), forked_to, Some(forked_proof), &new).unwrap();
+        assert_eq!(
+            forked.verify(None, None),
+            Err(HandoffError::Inconsistent { from: 4, to: 6 })
+        );
+        let by_old = Handoff::sign(from, to, Some(proof), &old).unwrap();
+        assert_eq!(by_old.verify(None, None), Err(HandoffError::KeyMismatch));
+    }
+}
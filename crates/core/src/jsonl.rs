@@ -0,0 +1,329 @@
+//! Verifiable JSON Lines exports: the whole log as text that downstream
+//! systems can load line by line and re-verify without contacting logd.
+//!
+//! ```text
+//! {"format":"reality-log-jsonl/v1","checkpoint":{...}}
+//! {"index":0,"payload":"...","leaf":"<hex>","path":[{"direction":"right","hash":"<hex>"}, ...]}
+//! ...
+//! ```
+//!
+//! The first line states the signed checkpoint every entry is proven
+//! against. Each following line is one entry, in index order, with the
+//! inclusion path from its leaf to the checkpoint's root. The root and size
+//! are the checkpoint's, so they are not repeated per line.
+
+use std::io::{self, BufRead, Write};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    algorithm::HashAlgorithm,
+    checkpoint::SignedCheckpoint,
+    digest::{DefaultDigest, LogDigest},
+    verify, InclusionProof, MerkleTree, ProofStep, VerifyRequest,
+};
+
+pub const FORMAT: &str = "reality-log-jsonl/v1";
+
+/// The first line of an export.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Header {
+    pub format: String,
+    pub checkpoint: SignedCheckpoint,
+    #[serde(default, skip_serializing_if = "HashAlgorithm::is_default")]
+    pub algorithm: HashAlgorithm,
+}
+
+/// One entry of an export.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Line {
+    pub index: u64,
+    pub payload: String,
+    pub leaf: String,
+    pub path: Vec<ProofStep>,
+}
+
+impl Line {
+    /// The entry's inclusion proof against the header's checkpoint.
+    pub fn proof(&self, header: &Header) -> InclusionProof {
+        InclusionProof {
+            index: self.index,
+            leaf: self.leaf.clone(),
+            path: self.path.clone(),
+            root: header.checkpoint.checkpoint.root.clone(),
+            size: header.checkpoint.checkpoint.size,
+            algorithm: header.algorithm,
+        }
+    }
+}
+
+/// What a verified export holds.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Summary {
+    pub size: u64,
+    pub root: String,
+    pub signed_by: String,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum JsonlError {
+    #[error("failed to read the export: {0}")]
+    Io(String),
+    #[error("line {line} is not valid JSON for the export: {message}")]
+    Json { line: u64, message: String },
+    #[error("the export is {0:?}, not {FORMAT}")]
+    Format(String),
+    #[error("the export holds {payloads} entries but its checkpoint covers {size}")]
+    SizeMismatch { payloads: u64, size: u64 },
+    #[error("checkpoint signature is invalid")]
+    BadSignature,
+    #[error("checkpoint is signed by {0}, not the trusted log key")]
+    UntrustedKey(String),
+    #[error("expected entry {expected}, found entry {found}")]
+    OutOfOrder { expected: u64, found: u64 },
+    #[error("the payload of entry {0} does not hash to its leaf")]
+    LeafMismatch(u64),
+    #[error("entry {index} does not lead to the checkpoint's root (computed {computed})")]
+    InvalidProof { index: u64, computed: String },
+    #[error("the export ends after {found} of {size} entries")]
+    Truncated { found: u64, size: u64 },
+}
+
+/// Write an export of `payloads`, which must be the entries `checkpoint`
+/// covers, in order.
+pub fn write<W: Write>(
+    mut out: W,
+    checkpoint: &SignedCheckpoint,
+    payloads: &[String],
+) -> Result<(), JsonlError> {
+    let size = checkpoint.checkpoint.size;
+    if payloads.len() as u64 != size {
+        return Err(JsonlError::SizeMismatch {
+            payloads: payloads.len() as u64,
+            size,
+        });
+    }
+    let header = Header {
+        format: FORMAT.to_string(),
+        checkpoint: checkpoint.clone(),
+        algorithm: DefaultDigest::ALGORITHM,
+    };
+    let leaves: Vec<[u8; 32]> = payloads
+        .iter()
+        .map(|payload| header.algorithm.leaf_hash(payload.as_bytes()))
+        .collect();
+    let tree = MerkleTree::with(header.algorithm, &leaves);
+    write_line(&mut out, &header)?;
+    for (index, payload) in payloads.iter().enumerate() {
+        let proof = tree.prove(index).expect("index is within the tree");
+        let line = Line {
+            index: proof.index,
+            payload: payload.clone(),
+            leaf: proof.leaf,
+            path: proof.path,
+        };
+        write_line(&mut out, &line)?;
+    }
+    out.flush().map_err(io_error)
+}
+
+fn write_line<W: Write, T: Serialize>(out: &mut W, value: &T) -> Result<(), JsonlError> {
+    serde_json::to_writer(&mut *out, value).map_err(|err| JsonlError::Io(err.to_string()))?;
+    out.write_all(b"\n").map_err(io_error)
+}
+
+/// Checks an export one line at a time, so exports larger than memory can be
+/// verified as they stream in.
+pub struct Verifier {
+    header: Header,
+    next: u64,
+}
+
+impl Verifier {
+    /// Start from the export's first line. Without `trusted_key` any valid
+    /// checkpoint signature is accepted, so callers should pin the log's key
+    /// wherever they can.
+    pub fn new(header: &str, trusted_key: Option<&str>) -> Result<Self, JsonlError> {
+        let header: Header = parse(1, header)?;
+        if header.format != FORMAT {
+            return Err(JsonlError::Format(header.format));
+        }
+        let signed = &header.checkpoint;
+        if !signed.verify() {
+            return Err(JsonlError::BadSignature);
+        }
+        let key = &signed.signature.public_key;
+        if let Some(trusted) = trusted_key {
+            if !key.eq_ignore_ascii_case(trusted) {
+                return Err(JsonlError::UntrustedKey(key.clone()));
+            }
+        }
+        Ok(Self { header, next: 0 })
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Check the next entry line: payload → leaf → root.
+    pub fn check(&mut self, line: &str) -> Result<Line, JsonlError> {
+        let size = self.header.checkpoint.checkpoint.size;
+        let entry: Line = parse(self.next + 2, line)?;
+        if entry.index != self.next || entry.index >= size {
+            return Err(JsonlError::OutOfOrder {
+                expected: self.next,
+                found: entry.index,
+            });
+        }
+        let leaf = self.header.algorithm.leaf_hash(entry.payload.as_bytes());
+        if hex::encode(leaf) != entry.leaf.to_ascii_lowercase() {
+            return Err(JsonlError::LeafMismatch(entry.index));
+        }
+        let response = verify(&VerifyRequest::from(&entry.proof(&self.header)));
+        if !response.valid {
+            return Err(JsonlError::InvalidProof {
+                index: entry.index,
+                computed: response.computed_root,
+            });
+        }
+        self.next += 1;
+        Ok(entry)
+    }
+
+    /// Confirm every entry the checkpoint covers was checked.
+    pub fn finish(self) -> Result<Summary, JsonlError> {
+        let checkpoint = self.header.checkpoint;
+        let size = checkpoint.checkpoint.size;
+        if self.next != size {
+            return Err(JsonlError::Truncated {
+                found: self.next,
+                size,
+            });
+        }
+        Ok(Summary {
+            size,
+            root: checkpoint.checkpoint.root,
+            signed_by: checkpoint.signature.public_key,
+        })
+    }
+}
+
+/// Verify a whole export from `input`.
+pub fn verify_export<R: BufRead>(
+    input: R,
+    trusted_key: Option<&str>,
+) -> Result<Summary, JsonlError> {
+    let mut lines = input.lines();
+    let header = lines
+        .next()
+        .ok_or(JsonlError::Io("the export is empty".to_string()))?
+        .map_err(io_error)?;
+    let mut verifier = Verifier::new(&header, trusted_key)?;
+    for line in lines {
+        let line = line.map_err(io_error)?;
+        if !line.trim().is_empty() {
+            verifier.check(&line)?;
+        }
+    }
+    verifier.finish()
+}
+
+fn parse<'a, T: Deserialize<'a>>(line: u64, text: &'a str) -> Result<T, JsonlError> {
+    serde_json::from_str(text).map_err(|err| JsonlError::Json {
+        line,
+        message: err.to_string(),
+    })
+}
+
+fn io_error(err: io::Error) -> JsonlError {
+    JsonlError::Io(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        checkpoint::Checkpoint,
+        leaf_hash, root,
+        signer::{KeyFileSigner, Signer},
+    };
+
+    fn export(payloads: &[String]) -> (String, String) {
+        let signer = KeyFileSigner::from_seed([4u8; 32]);
+        let leaves: Vec<[u8; 32]> = payloads.iter().map(|p| leaf_hash(p.as_bytes())).collect();
+        let checkpoint = Checkpoint {
+            size: payloads.len() as u64,
+            root: hex::encode(root(&leaves)),
+            timestamp_nanos: "1700000000000000000".into(),
+        }
+        .sign(&signer)
+        .unwrap();
+        let mut out = Vec::new();
+        write(&mut out, &checkpoint, payloads).unwrap();
+        let key = hex::encode(signer.public_key());
+        (String::from_utf8(out).unwrap(), key)
+    }
+
+    #[test]
+    fn exports_verify_and_edits_are_caught() {
+        for size in [0, 1, 2, 5, 8] {
+            let payloads: Vec<String> = (0..size).map(|i| format!("entry {i}")).collect();
+            let (text, key) = export(&payloads);
+            assert_eq!(text.lines().count(), size + 1);
+            let summary = verify_export(text.as_bytes(), Some(&key)).unwrap();
+            assert_eq!(summary.size, size as u64);
+            assert_eq!(summary.signed_by, key);
+        }
+
+        let payloads: Vec<String> = (0..5).map(|i| format!("entry {i}")).collect();
+        let (text, key) = export(&payloads);
+        let lines: Vec<&str> = text.lines().collect();
+        let rejoin = |lines: &[&str]| lines.join("\n");
+
+        let edited = text.replace("\"entry 3\"", "\"entry three\"");
+        assert_eq!(
+            verify_export(edited.as_bytes(), None),
+            Err(JsonlError::LeafMismatch(3))
+        );
+        let sibling = lines[2].rsplit("\"hash\":\"").next().unwrap()[..64].to_string();
+        let forged = text.replacen(&sibling, &"11".repeat(32), 1);
+        assert!(matches!(
+            verify_export(forged.as_bytes(), None),
+            Err(JsonlError::InvalidProof { .. })
+        ));
+        assert_eq!(
+            verify_export(rejoin(&lines[..4]).as_bytes(), None),
+            Err(JsonlError::Truncated { found: 3, size: 5 })
+        );
+        let swapped = [lines[0], lines[2], lines[1], lines[3], lines[4], lines[5]];
+        assert_eq!(
+            verify_export(rejoin(&swapped).as_bytes(), None),
+            Err(JsonlError::OutOfOrder {
+                expected: 0,
+                found: 1
+            })
+        );
+        assert!(matches!(
+            verify_export(text.as_bytes(), Some(&"00".repeat(32))),
+            Err(JsonlError::UntrustedKey(_))
+        ));
+        assert!(verify_export(text.as_bytes(), Some(&key)).is_ok());
+
+        let signer = KeyFileSigner::from_seed([4u8; 32]);
+        let short = Checkpoint {
+            size: 4,
+            root: "00".repeat(32),
+            timestamp_nanos: "0".into(),
+        }
+        .sign(&signer)
+        .unwrap();
+        assert_eq!(
+            write(Vec::new(), &short, &payloads),
+            Err(JsonlError::SizeMismatch {
+                payloads: 5,
+                size: 4
+            })
+        );
+    }
+}
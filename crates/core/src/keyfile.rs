@@ -0,0 +1,120 @@
+//! Signing key files. A key file holds a hex-encoded 32-byte ed25519 seed,
+//! either as plain text or encrypted to a passphrase in the age format
+//! (scrypt recipient), so `age -d` can also open it. Services read the
+//! passphrase of an encrypted file from `REALITY_KEY_PASSPHRASE`, or from
+//! the file named by `REALITY_KEY_PASSPHRASE_FILE`.
+
+use std::path::Path;
+
+use crate::signer::{decode, SignerError};
+
+pub const PASSPHRASE_ENV: &str = "REALITY_KEY_PASSPHRASE";
+pub const PASSPHRASE_FILE_ENV: &str = "REALITY_KEY_PASSPHRASE_FILE";
+
+const AGE_BINARY: &[u8] = b"age-encryption.org/v1";
+const AGE_ARMOR: &[u8] = b"-----BEGIN AGE ENCRYPTED FILE-----";
+
+/// Whether `bytes` are an age file, armored or binary.
+pub fn is_encrypted(bytes: &[u8]) -> bool {
+    let bytes = bytes.trim_ascii_start();
+    bytes.starts_with(AGE_BINARY) || bytes.starts_with(AGE_ARMOR)
+}
+
+/// The passphrase services decrypt key files with, if one is configured.
+pub fn passphrase_from_env() -> Result<Option<String>, SignerError> {
+    if let Some(path) = std::env::var_os(PASSPHRASE_FILE_ENV) {
+        let raw = std::fs::read_to_string(&path).map_err(|source| SignerError::KeyFile {
+            path: path.into(),
+            source,
+        })?;
+        return Ok(Some(raw.trim_end_matches(['\r', '\n']).to_string()));
+    }
+    Ok(std::env::var(PASSPHRASE_ENV).ok())
+}
+
+/// Read the seed in the key file at `path`, decrypting it with `passphrase`
+/// when it is encrypted.
+pub fn read_seed(path: &Path, passphrase: Option<&str>) -> Result<[u8; 32], SignerError> {
+    let bytes = std::fs::read(path).map_err(|source| SignerError::KeyFile {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    if is_encrypted(&bytes) {
+        let passphrase =
+            passphrase.ok_or_else(|| SignerError::PassphraseRequired(path.to_path_buf()))?;
+        return decrypt_seed(&bytes, passphrase);
+    }
+    let raw = std::str::from_utf8(&bytes).map_err(|_| SignerError::InvalidKey)?;
+    decode::<32>(raw.trim()).ok_or(SignerError::InvalidKey)
+}
+
+/// The contents of a key file holding `seed` in plain text.
+pub fn plain_seed(seed: &[u8; 32]) -> String {
+    format!("{}\n", hex::encode(seed))
+}
+
+/// Encrypt `seed` to `passphrase` as an armored age file. scrypt's work
+/// factor is tuned to take about a second on this machine.
+#[cfg(feature = "age")]
+pub fn encrypt_seed(seed: &[u8; 32], passphrase: &str) -> Result<String, SignerError> {
+    encrypt(seed, passphrase, None)
+}
+
+#[cfg(feature = "age")]
+fn encrypt(
+    seed: &[u8; 32],
+    passphrase: &str,
+    work_factor: Option<u8>,
+) -> Result<String, SignerError> {
+    let mut recipient = age::scrypt::Recipient::new(passphrase.to_owned().into());
+    if let Some(work_factor) = work_factor {
+        recipient.set_work_factor(work_factor);
+    }
+    age::encrypt_and_armor(&recipient, plain_seed(seed).as_bytes())
+        .map_err(|err| SignerError::Encryption(err.to_string()))
+}
+
+#[cfg(feature = "age")]
+fn decrypt_seed(bytes: &[u8], passphrase: &str) -> Result<[u8; 32], SignerError> {
+    let identity = age::scrypt::Identity::new(passphrase.to_owned().into());
+    let plain =
+        age::decrypt(&identity, bytes).map_err(|err| SignerError::Encryption(err.to_string()))?;
+    let raw = std::str::from_utf8(&plain).map_err(|_| SignerError::InvalidKey)?;
+    decode::<32>(raw.trim()).ok_or(SignerError::InvalidKey)
+}
+
+#[cfg(not(feature = "age"))]
+fn decrypt_seed(_bytes: &[u8], _passphrase: &str) -> Result<[u8; 32], SignerError> {
+    Err(SignerError::AgeDisabled)
+}
+
+#[cfg(all(test, feature = "age"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypted_key_files_need_their_passphrase() {
+        let dir = std::env::temp_dir().join(format!("reality-keyfile-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let seed = [7u8; 32];
+
+        let plain = dir.join("plain.key");
+        std::fs::write(&plain, plain_seed(&seed)).unwrap();
+        assert_eq!(read_seed(&plain, None).unwrap(), seed);
+
+        let encrypted = dir.join("encrypted.key");
+        let armored = encrypt(&seed, "correct horse", Some(2)).unwrap();
+        assert!(is_encrypted(armored.as_bytes()));
+        std::fs::write(&encrypted, armored).unwrap();
+        assert!(matches!(
+            read_seed(&encrypted, None),
+            Err(SignerError::PassphraseRequired(_))
+        ));
+        assert!(matches!(
+            read_seed(&encrypted, Some("battery staple")),
+            Err(SignerError::Encryption(_))
+        ));
+        assert_eq!(read_seed(&encrypted, Some("correct horse")).unwrap(), seed);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
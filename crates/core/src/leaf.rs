@@ -0,0 +1,363 @@
+//! Leaf formats. A v1 leaf is the hash of the payload alone (`leaf_hash`).
+//! A v2 leaf also commits to what the log recorded about the entry when it
+//! was appended, as [`LeafMeta`]: its sequence number, a hybrid logical
+//! clock reading, the labels it was appended with and, for an entry its
+//! submitter signed, that signature. A v2 leaf hashes
+//!
+//! ```text
+//! 0x02 || u64 big-endian length of meta || meta || payload
+//! ```
+//!
+//! where `meta` is the compact JSON of [`LeafMeta`], fields in declaration
+//! order and absent ones left out. The `0x02` prefix keeps v2 leaves apart
+//! from v1 leaves (`0x00`) and interior nodes (`0x01`).
+//!
+//! `appended_at` is the wall clock and can step backwards across restarts or
+//! NTP corrections. The clock in a v2 leaf cannot: it takes the wall clock
+//! when that is ahead and otherwise counts on from the last reading, so the
+//! order of any two v2 entries can be checked from their leaves alone.
+//!
+//! A submitter signs [`entry_message`] of its payload and labels with its
+//! ed25519 key. With the signature in the leaf, an inclusion proof shows not
+//! only that the log holds the payload but who submitted it.
+//!
+//! A log can also commit the [`Provenance`] of each entry: the name its
+//! client gave, a hash of the client's address and a fingerprint of its API
+//! key. Unlike a signature these are the log's word, but once in the leaf
+//! the log cannot change them later without breaking every proof.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    algorithm::{HashAlgorithm, Hasher},
+    digest::{DefaultDigest, LogDigest},
+    signer::DetachedSignature,
+};
+
+const LEAF_V2_PREFIX: [u8; 1] = [0x02];
+const ENTRY_MESSAGE_PREFIX: &[u8] = b"reality-entry/v1\n";
+
+/// Most labels on one entry.
+pub const MAX_LABELS: usize = 32;
+/// Longest label key, in bytes.
+pub const MAX_LABEL_KEY: usize = 64;
+/// Longest label value, in bytes.
+pub const MAX_LABEL_VALUE: usize = 256;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum LabelError {
+    #[error("an entry may carry at most {MAX_LABELS} labels")]
+    TooMany,
+    #[error("label key {0:?} must be 1 to {MAX_LABEL_KEY} letters, digits, '.', '_', '-' or '/'")]
+    BadKey(String),
+    #[error("the value of label {0:?} is over {MAX_LABEL_VALUE} bytes")]
+    LongValue(String),
+}
+
+/// A hybrid logical clock reading: the largest wall-clock time seen, in
+/// nanoseconds since the Unix epoch, and a counter that orders readings
+/// within it.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Hlc {
+    /// Decimal string, like `timestamp_nanos` elsewhere, so JSON readers
+    /// without 64-bit integers keep every digit.
+    #[serde(with = "nanos")]
+    pub wall_nanos: u64,
+    pub logical: u32,
+}
+
+impl Hlc {
+    /// The reading after `self` for an event at wall-clock `now_nanos`:
+    /// later than `self` whatever the wall clock says.
+    pub fn tick(self, now_nanos: u64) -> Self {
+        if now_nanos > self.wall_nanos {
+            Self {
+                wall_nanos: now_nanos,
+                logical: 0,
+            }
+        } else {
+            match self.logical.checked_add(1) {
+                Some(logical) => Self {
+                    wall_nanos: self.wall_nanos,
+                    logical,
+                },
+                None => Self {
+                    wall_nanos: self.wall_nanos + 1,
+                    logical: 0,
+                },
+            }
+        }
+    }
+}
+
+/// What a v2 leaf commits to besides the payload.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LeafMeta {
+    /// The entry's index, so its position is part of the leaf itself.
+    pub sequence: u64,
+    pub hlc: Hlc,
+    /// Key/value labels such as `service=checkout`, sorted by key.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    /// The submitter's signature over [`entry_message`] of the payload and
+    /// labels; its key is who submitted the entry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<DetachedSignature>,
+    /// Where the entry came from, for the fields the log commits.
+    #[serde(default, skip_serializing_if = "Provenance::is_empty")]
+    pub provenance: Provenance,
+}
+
+/// Where an entry came from, as the log saw the request that appended it.
+/// The log decides which fields it records, and of those which its leaves
+/// commit to; the rest are only stored with the entry.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Provenance {
+    /// The name the submitting client gave.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submitter: Option<String>,
+    /// Hex salted SHA-256 of the client's IP address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_ip: Option<String>,
+    /// Hex SHA-256 of the bearer token the client appended with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+}
+
+impl Provenance {
+    pub fn is_empty(&self) -> bool {
+        self.submitter.is_none() && self.source_ip.is_none() && self.api_key.is_none()
+    }
+}
+
+impl LeafMeta {
+    /// The v2 leaf of `payload` with this metadata.
+    pub fn leaf(&self, payload: &[u8]) -> [u8; 32] {
+        self.leaf_with(DefaultDigest::ALGORITHM, payload)
+    }
+
+    pub fn leaf_with(&self, algorithm: HashAlgorithm, payload: &[u8]) -> [u8; 32] {
+        let meta = serde_json::to_vec(self).expect("leaf metadata serializes");
+        let mut hasher = Hasher::new(algorithm);
+        hasher.update(&LEAF_V2_PREFIX);
+        hasher.update(&(meta.len() as u64).to_be_bytes());
+        hasher.update(&meta);
+        hasher.update(payload);
+        hasher.finalize()
+    }
+
+    /// Hex key of the entry's submitter, if it signed the entry.
+    pub fn signer(&self) -> Option<&str> {
+        self.signature
+            .as_ref()
+            .map(|signature| signature.public_key.as_str())
+    }
+
+    /// Whether the submitter's signature, if any, holds for `payload` and
+    /// these labels. For a payload logd moved to a blob store, pass the
+    /// blob's bytes, which is what the submitter signed.
+    pub fn signature_holds(&self, payload: &[u8]) -> bool {
+        self.signature
+            .as_ref()
+            .is_none_or(|signature| signature.verify(&entry_message(payload, &self.labels)))
+    }
+
+    /// Whether this entry was appended after `earlier` by the same log:
+    /// both its sequence number and its clock are larger.
+    pub fn follows(&self, earlier: &LeafMeta) -> bool {
+        self.sequence > earlier.sequence && self.hlc > earlier.hlc
+    }
+}
+
+/// An entry found by one of its labels or its signer, as logd's
+/// `GET /entries?label=` and `GET /entries?signer=` list it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LabelHit {
+    pub index: u64,
+    pub leaf: String,
+    pub meta: LeafMeta,
+    pub appended_at: String,
+}
+
+/// Check `labels` against the limits above. Keys cannot hold `:`, so
+/// `key:value` splits at the first one.
+pub fn check_labels(labels: &BTreeMap<String, String>) -> Result<(), LabelError> {
+    if labels.len() > MAX_LABELS {
+        return Err(LabelError::TooMany);
+    }
+    for (key, value) in labels {
+        let allowed = |c: char| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '/');
+        if key.is_empty() || key.len() > MAX_LABEL_KEY || !key.chars().all(allowed) {
+            return Err(LabelError::BadKey(key.clone()));
+        }
+        if value.len() > MAX_LABEL_VALUE {
+            return Err(LabelError::LongValue(key.clone()));
+        }
+    }
+    Ok(())
+}
+
+/// What a submitter signs for an entry: `reality-entry/v1\n`, the compact
+/// JSON of `labels`, a newline and the payload.
+pub fn entry_message(payload: &[u8], labels: &BTreeMap<String, String>) -> Vec<u8> {
+    let mut message = ENTRY_MESSAGE_PREFIX.to_vec();
+    message.extend(serde_json::to_vec(labels).expect("labels serialize"));
+    message.push(b'\n');
+    message.extend(payload);
+    message
+}
+
+/// The leaf of `payload`: v2 with `meta`, v1 without.
+pub fn entry_leaf(payload: &[u8], meta: Option<&LeafMeta>) -> [u8; 32] {
+    entry_leaf_with(DefaultDigest::ALGORITHM, payload, meta)
+}
+
+pub fn entry_leaf_with(
+    algorithm: HashAlgorithm,
+    payload: &[u8],
+    meta: Option<&LeafMeta>,
+) -> [u8; 32] {
+    match meta {
+        Some(meta) => meta.leaf_with(algorithm, payload),
+        None => algorithm.leaf_hash(payload),
+    }
+}
+
+mod nanos {
+    use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(nanos: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(nanos)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        let nanos = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        nanos.parse().map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::leaf_hash;
+
+    #[test]
+    fn clocks_never_run_backwards() {
+        let start = Hlc::default().tick(100);
+        assert_eq!(
+            start,
+            Hlc {
+                wall_nanos: 100,
+                logical: 0
+            }
+        );
+        // The wall clock stepped back: the reading still advances.
+        let stepped = start.tick(40);
+        assert_eq!((stepped.wall_nanos, stepped.logical), (100, 1));
+        assert!(stepped > start && stepped.tick(100) > stepped);
+        assert_eq!(stepped.tick(150).logical, 0);
+        let full = Hlc {
+            wall_nanos: 7,
+            logical: u32::MAX,
+        };
+        assert!(full.tick(0) > full);
+    }
+
+    #[test]
+    fn v2_leaves_commit_to_their_metadata() {
+        let meta = LeafMeta {
+            sequence: 4,
+            hlc: Hlc {
+                wall_nanos: 1_700_000_000_000_000_000,
+                logical: 2,
+            },
+            labels: BTreeMap::new(),
+            signature: None,
+            provenance: Provenance::default(),
+        };
+        assert_eq!(
+            serde_json::to_string(&meta).unwrap(),
+            r#"{"sequence":4,"hlc":{"wall_nanos":"1700000000000000000","logical":2}}"#
+        );
+        let leaf = meta.leaf(b"payload");
+        assert_ne!(leaf, leaf_hash(b"payload"));
+        assert_eq!(entry_leaf(b"payload", Some(&meta)), leaf);
+        assert_eq!(entry_leaf(b"payload", None), leaf_hash(b"payload"));
+        let mut moved = meta.clone();
+        moved.sequence = 5;
+        assert_ne!(moved.leaf(b"payload"), leaf);
+
+        assert!(!moved.follows(&meta));
+        moved.hlc = meta.hlc.tick(0);
+        assert!(moved.follows(&meta) && !meta.follows(&moved));
+
+        // Labels are committed too, and only serialized when present.
+        let mut labelled = meta.clone();
+        labelled.labels.insert("service".into(), "checkout".into());
+        assert_ne!(labelled.leaf(b"payload"), leaf);
+        assert!(serde_json::to_string(&labelled)
+            .unwrap()
+            .ends_with(r#","labels":{"service":"checkout"}}"#));
+
+        // So is provenance, field by field.
+        let mut traced = meta.clone();
+        traced.provenance.submitter = Some("billing".into());
+        assert_ne!(traced.leaf(b"payload"), leaf);
+        assert!(serde_json::to_string(&traced)
+            .unwrap()
+            .ends_with(r#","provenance":{"submitter":"billing"}}"#));
+        let submitted = traced.leaf(b"payload");
+        traced.provenance.api_key = Some("ab".into());
+        assert_ne!(traced.leaf(b"payload"), submitted);
+    }
+
+    #[test]
+    fn signed_leaves_name_their_submitter() {
+        use crate::signer::{KeyFileSigner, Signer};
+
+        let submitter = KeyFileSigner::from_seed([8; 32]);
+        let labels = BTreeMap::from([("service".to_string(), "checkout".to_string())]);
+        let message = entry_message(b"payload", &labels);
+        assert_eq!(
+            message,
+            b"reality-entry/v1\n{\"service\":\"checkout\"}\npayload".to_vec()
+        );
+        let mut meta = LeafMeta {
+            labels,
+            ..LeafMeta::default()
+        };
+        let unsigned = meta.leaf(b"payload");
+        assert!(meta.signature_holds(b"payload") && meta.signer().is_none());
+        meta.signature = Some(DetachedSignature::create(&submitter, &message).unwrap());
+        assert_eq!(
+            meta.signer(),
+            Some(hex::encode(submitter.public_key()).as_str())
+        );
+        assert!(meta.signature_holds(b"payload"));
+        assert!(!meta.signature_holds(b"other"));
+        assert_ne!(meta.leaf(b"payload"), unsigned);
+        // Labels are signed too.
+        meta.labels.clear();
+        assert!(!meta.signature_holds(b"payload"));
+    }
+
+    #[test]
+    fn labels_are_checked() {
+        let mut labels = BTreeMap::from([("env".to_string(), "prod:eu".to_string())]);
+        assert_eq!(check_labels(&labels), Ok(()));
+        labels.insert("a:b".into(), String::new());
+        assert_eq!(check_labels(&labels), Err(LabelError::BadKey("a:b".into())));
+        let labels = BTreeMap::from([("k".to_string(), "v".repeat(MAX_LABEL_VALUE + 1))]);
+        assert_eq!(
+            check_labels(&labels),
+            Err(LabelError::LongValue("k".into()))
+        );
+        let labels = (0..=MAX_LABELS)
+            .map(|i| (i.to_string(), String::new()))
+            .collect();
+        assert_eq!(check_labels(&labels), Err(LabelError::TooMany));
+    }
+}
@@ -1,10 +1,42 @@
+pub mod algorithm;
+pub mod audit;
+pub mod blob;
+pub mod bundle;
+mod cbor;
+pub mod checkpoint;
+pub mod consistency;
+pub mod ct;
+pub mod day;
+pub mod digest;
+pub mod dsse;
+pub mod encoding;
+pub mod freshness;
+#[cfg(feature = "arbitrary")]
+mod fuzzing;
+pub mod handoff;
+pub mod jsonl;
+pub mod keyfile;
+pub mod leaf;
+pub mod link;
+pub mod receipt;
+pub mod redaction;
+pub mod rlproof;
+pub mod scitt;
+pub mod signer;
+#[cfg(feature = "aws-kms")]
+pub mod sigv4;
+pub mod tile;
+pub mod tlog;
 pub mod types;
-use sha2::{Digest, Sha256};
-use std::fmt;
+use std::{collections::BTreeMap, fmt};
 
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use algorithm::{with_digest, HashAlgorithm, Hasher};
+use digest::{DefaultDigest, LogDigest};
+use signer::DetachedSignature;
+
 const LEAF_PREFIX: [u8; 1] = [0x00];
 const NODE_PREFIX: [u8; 1] = [0x01];
 const EMPTY_SENTINEL: &[u8] = b"EMPTY";
@@ -18,6 +50,7 @@ pub enum MerkleError {
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[serde(rename_all = "lowercase")]
 pub enum Direction {
     Left,
@@ -46,11 +79,20 @@ pub struct InclusionProof {
     pub path: Vec<ProofStep>,
     pub root: String,
     pub size: u64,
+    #[serde(default, skip_serializing_if = "HashAlgorithm::is_default")]
+    pub algorithm: HashAlgorithm,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct AppendRequest {
     pub payload: String,
+    /// Committed into the entry's v2 leaf, so the log must write them.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    /// The submitter's signature over `leaf::entry_message` of the payload
+    /// and labels, committed into the v2 leaf.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<DetachedSignature>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -59,6 +101,52 @@ pub struct AppendResponse {
     pub size: u64,
     pub leaf: String,
     pub root: String,
+    /// What the leaf commits to besides the payload, for a v2 leaf (see
+    /// [`leaf`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<leaf::LeafMeta>,
+}
+
+/// Body of `POST /append/batch`. The entries are appended in order, either
+/// all of them or none.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BatchAppendRequest {
+    pub entries: Vec<BatchEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BatchEntry {
+    pub payload: String,
+    /// Chosen by the client. An entry whose key the log already holds is
+    /// not appended again, so a batch can be resent safely.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+    /// As in [`AppendRequest`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    /// As in [`AppendRequest`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<DetachedSignature>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BatchAppendResponse {
+    /// One per request entry, in the same order.
+    pub entries: Vec<BatchAppended>,
+    /// The tree after the batch.
+    pub size: u64,
+    pub root: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BatchAppended {
+    pub index: u64,
+    pub leaf: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<leaf::LeafMeta>,
+    /// The key was seen before; `index` is where that entry was appended.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub duplicate: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -73,6 +161,20 @@ pub struct VerifyRequest {
     pub leaf: String,
     pub path: Vec<ProofStep>,
     pub root: String,
+    #[serde(default, skip_serializing_if = "HashAlgorithm::is_default")]
+    pub algorithm: HashAlgorithm,
+}
+
+impl From<&InclusionProof> for VerifyRequest {
+    fn from(proof: &InclusionProof) -> Self {
+        Self {
+            index: proof.index,
+            leaf: proof.leaf.clone(),
+            path: proof.path.clone(),
+            root: proof.root.clone(),
+            algorithm: proof.algorithm,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -82,116 +184,264 @@ pub struct VerifyResponse {
     pub expected_root: String,
 }
 
+/// Version written into new anchor records. Records without a `version`
+/// field predate it and deserialize as version 1.
+pub const ANCHOR_RECORD_VERSION: u32 = 2;
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct AnchorRecord {
+    #[serde(default = "legacy_anchor_version")]
+    pub version: u32,
     pub root: String,
     pub size: u64,
     pub timestamp_nanos: String,
     pub txid: String,
+    #[serde(flatten)]
+    pub metadata: AnchorMetadata,
 }
 
-pub fn leaf_hash(bytes: &[u8]) -> [u8; 32] {
-    let mut hasher = Sha256::new();
-    hasher.update(LEAF_PREFIX);
-    hasher.update(bytes);
-    hasher.finalize().into()
+/// Where and how a root was anchored (added in version 2). Every field is
+/// optional so version 1 records still deserialize.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AnchorMetadata {
+    /// Backend type, e.g. `simulated`, `bitcoin`, `ots`, `tsa`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
+    /// Chain or service instance, e.g. `mainnet`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_height: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmations: Option<u64>,
+    /// Where the receipt or proof for this anchor can be fetched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receipt_uri: Option<String>,
+    /// Identity of the key that signed the record, as `ed25519:<hex>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_identity: Option<String>,
 }
 
-pub fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
-    let mut hasher = Sha256::new();
-    hasher.update(NODE_PREFIX);
-    hasher.update(left);
-    hasher.update(right);
-    hasher.finalize().into()
+fn legacy_anchor_version() -> u32 {
+    1
 }
 
-pub fn empty_root() -> [u8; 32] {
-    let mut hasher = Sha256::new();
-    hasher.update(EMPTY_SENTINEL);
-    hasher.finalize().into()
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AnchorStatus {
+    Pending,
+    Confirmed,
+    Failed,
 }
 
-pub fn root(leaves: &[[u8; 32]]) -> [u8; 32] {
-    if leaves.is_empty() {
-        return empty_root();
+impl AnchorStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnchorStatus::Pending => "pending",
+            AnchorStatus::Confirmed => "confirmed",
+            AnchorStatus::Failed => "failed",
+        }
     }
 
-    let mut layer: Vec<[u8; 32]> = leaves.to_vec();
-    while layer.len() > 1 {
-        layer = parents(&layer);
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(AnchorStatus::Pending),
+            "confirmed" => Some(AnchorStatus::Confirmed),
+            "failed" => Some(AnchorStatus::Failed),
+            _ => None,
+        }
     }
-    layer[0]
 }
 
-fn parents(layer: &[[u8; 32]]) -> Vec<[u8; 32]> {
-    let mut parents = Vec::with_capacity((layer.len() + 1) / 2);
-    let mut iter = layer.chunks(2);
-    while let Some(chunk) = iter.next() {
-        let left = chunk[0];
-        let right = if chunk.len() == 2 { chunk[1] } else { chunk[0] };
-        parents.push(node_hash(&left, &right));
+impl AnchorRecord {
+    /// A current-version record with no metadata yet.
+    pub fn new(root: String, size: u64, timestamp_nanos: String, txid: String) -> Self {
+        Self {
+            version: ANCHOR_RECORD_VERSION,
+            root,
+            size,
+            timestamp_nanos,
+            txid,
+            metadata: AnchorMetadata::default(),
+        }
     }
-    parents
-}
 
-pub fn inclusion_path(leaves: &[[u8; 32]], index: usize) -> Result<Vec<ProofStep>, MerkleError> {
-    if index >= leaves.len() {
-        return Err(MerkleError::IndexOutOfRange);
+    /// The exact bytes covered by an anchor signature. Metadata is not
+    /// signed, so version 1 signatures stay valid.
+    pub fn signed_message(&self) -> Vec<u8> {
+        format!(
+            "reality-anchor/v1\n{}\n{}\n{}\n{}\n",
+            self.size,
+            self.root.to_ascii_lowercase(),
+            self.timestamp_nanos,
+            self.txid
+        )
+        .into_bytes()
     }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StoredAnchor {
+    #[serde(flatten)]
+    pub record: AnchorRecord,
+    pub status: AnchorStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<signer::DetachedSignature>,
+}
+
+/// Filters for listing anchors; time bounds are inclusive unix nanoseconds.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AnchorQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_nanos: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_nanos: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<AnchorStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
 
-    if leaves.len() <= 1 {
-        return Ok(Vec::new());
+pub fn leaf_hash(bytes: &[u8]) -> [u8; 32] {
+    digest::leaf_hash::<DefaultDigest>(bytes)
+}
+
+/// Incremental `leaf_hash` for payloads that arrive in chunks.
+#[derive(Debug, Clone)]
+pub struct LeafHasher(Hasher);
+
+impl LeafHasher {
+    pub fn new() -> Self {
+        Self::with_algorithm(DefaultDigest::ALGORITHM)
     }
 
-    let mut path = Vec::new();
-    let mut idx = index;
-    let mut layer: Vec<[u8; 32]> = leaves.to_vec();
-
-    while layer.len() > 1 {
-        let is_right = idx % 2 == 1;
-        let sibling_idx = if is_right {
-            idx - 1
-        } else if idx + 1 < layer.len() {
-            idx + 1
-        } else {
-            idx
-        };
+    pub fn with_algorithm(algorithm: HashAlgorithm) -> Self {
+        let mut hasher = Hasher::new(algorithm);
+        hasher.update(&LEAF_PREFIX);
+        Self(hasher)
+    }
 
-        let sibling_hash = layer[sibling_idx];
-        let direction = if is_right {
-            Direction::Left
-        } else {
-            Direction::Right
-        };
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.0.update(chunk);
+    }
 
-        path.push(ProofStep {
-            direction,
-            hash: hex::encode(sibling_hash),
-        });
+    pub fn finalize(self) -> [u8; 32] {
+        self.0.finalize()
+    }
+}
 
-        layer = parents(&layer);
-        idx /= 2;
+impl Default for LeafHasher {
+    fn default() -> Self {
+        Self::new()
     }
+}
+
+pub fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    digest::node_hash::<DefaultDigest>(left, right)
+}
+
+pub fn empty_root() -> [u8; 32] {
+    digest::empty_root::<DefaultDigest>()
+}
+
+pub fn root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    digest::root::<DefaultDigest>(leaves)
+}
+
+/// `root` for a log built with `algorithm`; the leaves must already be
+/// `algorithm.leaf_hash`es.
+pub fn root_with(algorithm: HashAlgorithm, leaves: &[[u8; 32]]) -> [u8; 32] {
+    with_digest!(algorithm, D => digest::root::<D>(leaves))
+}
 
-    Ok(path)
+pub fn inclusion_path(leaves: &[[u8; 32]], index: usize) -> Result<Vec<ProofStep>, MerkleError> {
+    digest::inclusion_path::<DefaultDigest>(leaves, index)
+}
+
+pub fn inclusion_path_with(
+    algorithm: HashAlgorithm,
+    leaves: &[[u8; 32]],
+    index: usize,
+) -> Result<Vec<ProofStep>, MerkleError> {
+    with_digest!(algorithm, D => digest::inclusion_path::<D>(leaves, index))
 }
 
 pub fn make_proof(leaves: &[[u8; 32]], index: usize) -> Result<InclusionProof, MerkleError> {
-    let size = leaves.len() as u64;
-    let leaf = leaves
-        .get(index)
-        .ok_or(MerkleError::IndexOutOfRange)?
-        .to_owned();
-    let path = inclusion_path(leaves, index)?;
-    let root = hex::encode(root(leaves));
-
-    Ok(InclusionProof {
-        index: index as u64,
-        leaf: hex::encode(leaf),
-        path,
-        root,
-        size,
-    })
+    digest::make_proof::<DefaultDigest>(leaves, index)
+}
+
+pub fn make_proof_with(
+    algorithm: HashAlgorithm,
+    leaves: &[[u8; 32]],
+    index: usize,
+) -> Result<InclusionProof, MerkleError> {
+    with_digest!(algorithm, D => digest::make_proof::<D>(leaves, index))
+}
+
+/// Every layer of a tree, kept so many proofs can be taken from it without
+/// rehashing the leaves for each one.
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+    algorithm: HashAlgorithm,
+    /// The leaves first, the root's layer last.
+    layers: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: &[[u8; 32]]) -> Self {
+        Self::with(DefaultDigest::ALGORITHM, leaves)
+    }
+
+    pub fn with(algorithm: HashAlgorithm, leaves: &[[u8; 32]]) -> Self {
+        let mut layers = vec![leaves.to_vec()];
+        while layers[layers.len() - 1].len() > 1 {
+            let parents = algorithm.parents(&layers[layers.len() - 1]);
+            layers.push(parents);
+        }
+        Self { algorithm, layers }
+    }
+
+    pub fn size(&self) -> u64 {
+        self.layers[0].len() as u64
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        match self.layers.last().and_then(|layer| layer.first()) {
+            Some(root) => *root,
+            None => self.algorithm.empty_root(),
+        }
+    }
+
+    /// The same proof as `make_proof_with` over the tree's leaves.
+    pub fn prove(&self, index: usize) -> Result<InclusionProof, MerkleError> {
+        let leaf = *self.layers[0]
+            .get(index)
+            .ok_or(MerkleError::IndexOutOfRange)?;
+        let mut path = Vec::with_capacity(self.layers.len() - 1);
+        let mut idx = index;
+        for layer in &self.layers[..self.layers.len() - 1] {
+            let (direction, sibling) = if idx % 2 == 1 {
+                (Direction::Left, idx - 1)
+            } else {
+                (Direction::Right, (idx + 1).min(layer.len() - 1))
+            };
+            path.push(ProofStep {
+                direction,
+                hash: hex::encode(layer[sibling]),
+            });
+            idx /= 2;
+        }
+        Ok(InclusionProof {
+            index: index as u64,
+            leaf: hex::encode(leaf),
+            path,
+            root: hex::encode(self.root()),
+            size: self.size(),
+            algorithm: self.algorithm,
+        })
+    }
 }
 
 pub fn verify(req: &VerifyRequest) -> VerifyResponse {
@@ -221,8 +471,8 @@ pub fn verify(req: &VerifyRequest) -> VerifyResponse {
         };
 
         computed = match step.direction {
-            Direction::Left => node_hash(&sibling, &computed),
-            Direction::Right => node_hash(&computed, &sibling),
+            Direction::Left => req.algorithm.node_hash(&sibling, &computed),
+            Direction::Right => req.algorithm.node_hash(&computed, &sibling),
         };
     }
 
@@ -261,7 +511,7 @@ mod tests {
     use super::*;
 
     fn h(data: &str) -> [u8; 32] {
-        leaf_hash(data.as_bytes())
+        digest::leaf_hash::<sha2::Sha256>(data.as_bytes())
     }
 
     #[test]
@@ -279,10 +529,10 @@ mod tests {
         let leaves3 = vec![h("a"), h("b"), h("c")];
         let leaves4 = vec![h("a"), h("b"), h("c"), h("d")];
 
-        let root1 = hex::encode(root(&leaves1));
-        let root2 = hex::encode(root(&leaves2));
-        let root3 = hex::encode(root(&leaves3));
-        let root4 = hex::encode(root(&leaves4));
+        let root1 = hex::encode(digest::root::<sha2::Sha256>(&leaves1));
+        let root2 = hex::encode(digest::root::<sha2::Sha256>(&leaves2));
+        let root3 = hex::encode(digest::root::<sha2::Sha256>(&leaves3));
+        let root4 = hex::encode(digest::root::<sha2::Sha256>(&leaves4));
 
         assert_eq!(
             root1,
@@ -302,19 +552,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn anchor_record_reads_v1_and_v2() {
+        let v1: AnchorRecord =
+            serde_json::from_str(r#"{"root":"aa","size":3,"timestamp_nanos":"7","txid":"t"}"#)
+                .expect("v1 record");
+        assert_eq!(v1.version, 1);
+        assert_eq!(v1.metadata, AnchorMetadata::default());
+
+        let mut v2 = AnchorRecord::new("aa".into(), 3, "7".into(), "t".into());
+        v2.metadata.backend = Some("bitcoin".into());
+        v2.metadata.block_height = Some(840_000);
+        let json = serde_json::to_string(&v2).unwrap();
+        assert!(json.contains(r#""version":2"#));
+        assert!(!json.contains("receipt_uri"));
+        assert_eq!(serde_json::from_str::<AnchorRecord>(&json).unwrap(), v2);
+        assert_eq!(v1.signed_message(), v2.signed_message());
+    }
+
     #[test]
     fn inclusion_proof_round_trip() {
         let leaves = vec![h("alpha"), h("beta"), h("gamma"), h("delta")];
         let proof = make_proof(&leaves, 2).expect("proof");
-        let verify_req = VerifyRequest {
-            index: proof.index,
-            leaf: proof.leaf.clone(),
-            path: proof.path.clone(),
-            root: proof.root.clone(),
-        };
-
-        let response = verify(&verify_req);
+        let response = verify(&VerifyRequest::from(&proof));
         assert!(response.valid);
         assert_eq!(response.expected_root, proof.root);
     }
+
+    #[test]
+    fn tree_proofs_match_make_proof() {
+        for size in [1usize, 2, 5, 8, 13] {
+            let leaves: Vec<[u8; 32]> = (0..size).map(|i| h(&i.to_string())).collect();
+            let tree = MerkleTree::new(&leaves);
+            assert_eq!(tree.root(), root(&leaves));
+            for index in 0..size {
+                assert_eq!(
+                    tree.prove(index).unwrap(),
+                    make_proof(&leaves, index).unwrap()
+                );
+            }
+            assert!(tree.prove(size).is_err());
+        }
+        assert_eq!(MerkleTree::new(&[]).root(), empty_root());
+    }
 }
@@ -0,0 +1,133 @@
+//! Links to other logs. A [`LogLink`] entry embeds another log's signed
+//! checkpoint, optionally with a consistency proof from the checkpoint this
+//! log linked before. Two logs that link each other's checkpoints are
+//! entangled: to show one history to some readers and another to the rest,
+//! an operator would have to fork both logs in step, and each fork would be
+//! recorded in the other log.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    checkpoint::{Checkpoint, SignedCheckpoint},
+    consistency::{verify_consistency, ConsistencyProof},
+};
+
+/// Another log's checkpoint, as appended to this one.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct LogLink {
+    /// The other log's checkpoint, signed with its key.
+    pub checkpoint: SignedCheckpoint,
+    /// Proof that the other log grew from the checkpoint linked before to
+    /// `checkpoint`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub consistency: Option<ConsistencyProof>,
+}
+
+/// The newest checkpoint linked from one log, at `index` in this one.
+/// logd's `GET /links` lists one per linked log.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LinkedCheckpoint {
+    pub index: u64,
+    #[serde(flatten)]
+    pub checkpoint: SignedCheckpoint,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum LinkError {
+    #[error("the checkpoint's signature does not verify")]
+    BadSignature,
+    #[error("the consistency proof does not end at the checkpoint")]
+    ConsistencyMismatch,
+    #[error("the consistency proof does not verify")]
+    InvalidConsistency,
+}
+
+impl LogLink {
+    /// The link as an entry payload.
+    pub fn to_payload(&self) -> String {
+        serde_json::to_string(self).expect("links serialize")
+    }
+
+    /// The link a payload holds, if it is one.
+    pub fn from_payload(payload: &str) -> Option<Self> {
+        serde_json::from_str(payload).ok()
+    }
+
+    /// Lowercase hex key of the linked log.
+    pub fn public_key(&self) -> String {
+        self.checkpoint.signature.public_key.to_ascii_lowercase()
+    }
+
+    /// Check the checkpoint's signature and, if there is one, that the
+    /// consistency proof verifies and ends at the checkpoint. Callers must
+    /// still decide whether the key is one they trust.
+    pub fn verify(&self) -> Result<(), LinkError> {
+        if !self.checkpoint.verify() {
+            return Err(LinkError::BadSignature);
+        }
+        let Some(proof) = &self.consistency else {
+            return Ok(());
+        };
+        let head = &self.checkpoint.checkpoint;
+        if proof.new_size != head.size || !proof.new_root.eq_ignore_ascii_case(&head.root) {
+            return Err(LinkError::ConsistencyMismatch);
+        }
+        if !verify_consistency(proof) {
+            return Err(LinkError::InvalidConsistency);
+        }
+        Ok(())
+    }
+
+    /// Whether the consistency proof starts at `earlier`, so this link
+    /// provably extends it. Only meaningful once [`LogLink::verify`] passed.
+    pub fn extends(&self, earlier: &Checkpoint) -> bool {
+        self.consistency.as_ref().is_some_and(|proof| {
+            proof.old_size == earlier.size && proof.old_root.eq_ignore_ascii_case(&earlier.root)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        consistency::consistency_proof,
+        leaf_hash, root,
+        signer::{KeyFileSigner, Signer},
+    };
+
+    #[test]
+    fn links_verify_and_extend_earlier_links() {
+        let signer = KeyFileSigner::from_seed([7u8; 32]);
+        let leaves: Vec<_> = (0..5u8).map(|i| leaf_hash(&[i])).collect();
+        let head = |size: usize| Checkpoint {
+            size: size as u64,
+            root: hex::encode(root(&leaves[..size])),
+            timestamp_nanos: "1".into(),
+        };
+        let link = LogLink {
+            checkpoint: head(5).sign(&signer).unwrap(),
+            consistency: Some(consistency_proof(&leaves, 2, leaves.len()).unwrap()),
+        };
+        assert_eq!(link.verify(), Ok(()));
+        assert!(link.extends(&head(2)) && !link.extends(&head(3)));
+        assert_eq!(
+            LogLink::from_payload(&link.to_payload()),
+            Some(link.clone())
+        );
+        assert_eq!(link.public_key(), hex::encode(signer.public_key()));
+
+        let mut short = link.clone();
+        short.consistency = Some(consistency_proof(&leaves, 2, 4).unwrap());
+        assert_eq!(short.verify(), Err(LinkError::ConsistencyMismatch));
+        let mut forged = link.clone();
+        forged.consistency.as_mut().unwrap().old_root = hex::encode(leaves[0]);
+        assert_eq!(forged.verify(), Err(LinkError::InvalidConsistency));
+        let mut resigned = link;
+        resigned.checkpoint.checkpoint.size = 4;
+        assert_eq!(resigned.verify(), Err(LinkError::BadSignature));
+        assert_eq!(LogLink::from_payload(r#"{"payload":"a"}"#), None);
+    }
+}
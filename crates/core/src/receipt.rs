@@ -0,0 +1,368 @@
+//! Anchor receipts: an anchor together with what a third party needs to check
+//! it without trusting the anchor daemon.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    consistency::{verify_consistency, ConsistencyProof},
+    digest, verify, AnchorRecord, InclusionProof, StoredAnchor, VerifyRequest,
+};
+use sha2::{Digest, Sha256};
+
+/// Transaction id the simulated backend derives for a checkpoint.
+pub fn simulated_txid(size: u64, root: &str, timestamp_nanos: &str) -> String {
+    hex::encode(Sha256::digest(
+        format!("{size}:{root}:{timestamp_nanos}").as_bytes(),
+    ))
+}
+
+/// Leaf for one checkpoint in an anchored batch. Batches are SHA-256
+/// whatever the log is built with.
+pub fn batch_leaf(size: u64, root: &str) -> [u8; 32] {
+    digest::leaf_hash::<Sha256>(format!("{size}:{}", root.to_ascii_lowercase()).as_bytes())
+}
+
+/// Where a batched anchor sits in the batch transaction.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BatchMembership {
+    pub batch_txid: String,
+    pub batch_root: String,
+    pub proof: InclusionProof,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AnchorReceipt {
+    #[serde(flatten)]
+    pub anchor: StoredAnchor,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch: Option<BatchMembership>,
+}
+
+/// An inclusion proof pinned to an anchored root, as logd serves it for
+/// `/prove/<index>?at_anchor=<txid>`. A receipt can then cite a root with
+/// an external timestamp rather than whatever root the log reports now.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AnchoredProof {
+    pub anchor: AnchorReceipt,
+    /// Proof of the entry against the anchored tree.
+    pub proof: InclusionProof,
+    /// Proof that the anchored tree is a prefix of the tree when served.
+    pub consistency: ConsistencyProof,
+}
+
+/// Optional evidence to check a receipt against.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReceiptContext {
+    /// An inclusion proof whose root should be the anchored root.
+    #[serde(default)]
+    pub proof: Option<InclusionProof>,
+    /// The anchor before this one. Anchors are not hash-chained, so the link
+    /// is that the log only grew between them.
+    #[serde(default)]
+    pub previous: Option<AnchorRecord>,
+    /// Proves the previous anchor's tree is a prefix of this one's.
+    #[serde(default)]
+    pub consistency: Option<ConsistencyProof>,
+    /// Hex key the anchor signature must come from.
+    #[serde(default)]
+    pub anchor_key: Option<String>,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ReceiptError {
+    #[error("txid {found} does not match the simulated txid {expected}")]
+    TxidMismatch { expected: String, found: String },
+    #[error("batch member txid must be {batch_txid}/<index>")]
+    NotInBatch { batch_txid: String },
+    #[error("batch member needs its batch membership proof")]
+    MissingBatch,
+    #[error("batch proof does not place this checkpoint under the batch root")]
+    InvalidBatchProof,
+    #[error("anchor signature is invalid")]
+    BadSignature,
+    #[error("anchor is signed by {0}, not the trusted anchor key")]
+    UntrustedKey(String),
+    #[error("anchor is unsigned but an anchor key was required")]
+    Unsigned,
+    #[error("proof root or size does not match the anchored checkpoint")]
+    ProofMismatch,
+    #[error("inclusion proof does not lead to its root")]
+    InvalidProof,
+    #[error("log shrank or was rewritten since the previous anchor")]
+    NotAfterPrevious,
+    #[error("consistency proof does not link the previous anchor to this one")]
+    InvalidConsistency,
+    #[error("consistency proof does not lead on from the anchored root")]
+    NotFromAnchor,
+}
+
+/// What `AnchorReceipt::verify` was able to check.
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+pub struct ReceiptReport {
+    /// False for backends whose transactions cannot be recomputed offline.
+    pub txid_checked: bool,
+    pub signature_checked: bool,
+}
+
+impl AnchorReceipt {
+    pub fn verify(&self, context: &ReceiptContext) -> Result<ReceiptReport, ReceiptError> {
+        let record = &self.anchor.record;
+        let txid_checked = self.check_txid()?;
+        let signature_checked = self.check_signature(context.anchor_key.as_deref())?;
+
+        if let Some(proof) = &context.proof {
+            if !proof.root.eq_ignore_ascii_case(&record.root) || proof.size != record.size {
+                return Err(ReceiptError::ProofMismatch);
+            }
+            if !verify(&VerifyRequest::from(proof)).valid {
+                return Err(ReceiptError::InvalidProof);
+            }
+        }
+
+        if let Some(previous) = &context.previous {
+            let same_tree =
+                previous.size == record.size && previous.root.eq_ignore_ascii_case(&record.root);
+            if previous.size > record.size || (previous.size == record.size && !same_tree) {
+                return Err(ReceiptError::NotAfterPrevious);
+            }
+            if let Some(consistency) = &context.consistency {
+                let links = consistency.old_size == previous.size
+                    && consistency.old_root.eq_ignore_ascii_case(&previous.root)
+                    && consistency.new_size == record.size
+                    && consistency.new_root.eq_ignore_ascii_case(&record.root);
+                if !links || !verify_consistency(consistency) {
+                    return Err(ReceiptError::InvalidConsistency);
+                }
+            }
+        }
+
+        Ok(ReceiptReport {
+            txid_checked,
+            signature_checked,
+        })
+    }
+
+    fn check_txid(&self) -> Result<bool, ReceiptError> {
+        let record = &self.anchor.record;
+        // Version 1 records predate backend metadata and were all simulated.
+        if !matches!(record.metadata.backend.as_deref(), None | Some("simulated")) {
+            return Ok(false);
+        }
+        let (size, root, txid) = match &self.batch {
+            None if record.txid.contains('/') => return Err(ReceiptError::MissingBatch),
+            None => (record.size, record.root.as_str(), record.txid.as_str()),
+            Some(batch) => {
+                let index = record
+                    .txid
+                    .strip_prefix(&batch.batch_txid)
+                    .and_then(|rest| rest.strip_prefix('/'))
+                    .and_then(|i| i.parse::<u64>().ok())
+                    .ok_or_else(|| ReceiptError::NotInBatch {
+                        batch_txid: batch.batch_txid.clone(),
+                    })?;
+                let proof = &batch.proof;
+                let leaf = hex::encode(batch_leaf(record.size, &record.root));
+                // The anchor daemon builds batches with SHA-256 only.
+                let valid = proof.algorithm.is_default()
+                    && proof.index == index
+                    && proof.leaf.eq_ignore_ascii_case(&leaf)
+                    && proof.root.eq_ignore_ascii_case(&batch.batch_root)
+                    && verify(&VerifyRequest::from(proof)).valid;
+                if !valid {
+                    return Err(ReceiptError::InvalidBatchProof);
+                }
+                (
+                    proof.size,
+                    batch.batch_root.as_str(),
+                    batch.batch_txid.as_str(),
+                )
+            }
+        };
+        let expected = simulated_txid(size, root, &record.timestamp_nanos);
+        if !expected.eq_ignore_ascii_case(txid) {
+            return Err(ReceiptError::TxidMismatch {
+                expected,
+                found: txid.to_string(),
+            });
+        }
+        Ok(true)
+    }
+
+    fn check_signature(&self, anchor_key: Option<&str>) -> Result<bool, ReceiptError> {
+        let Some(signature) = &self.anchor.signature else {
+            return match anchor_key {
+                Some(_) => Err(ReceiptError::Unsigned),
+                None => Ok(false),
+            };
+        };
+        if !signature.verify(&self.anchor.record.signed_message()) {
+            return Err(ReceiptError::BadSignature);
+        }
+        match anchor_key {
+            Some(key) if !signature.public_key.eq_ignore_ascii_case(key) => {
+                Err(ReceiptError::UntrustedKey(signature.public_key.clone()))
+            }
+            _ => Ok(true),
+        }
+    }
+}
+
+impl AnchoredProof {
+    /// Check the anchor with the proof as [`AnchorReceipt::verify`] does,
+    /// signed by `anchor_key` if given, and that the consistency proof
+    /// starts at the anchored tree. Compare `consistency.new_root` with a
+    /// current root you trust to tie the anchor to it.
+    pub fn verify(&self, anchor_key: Option<&str>) -> Result<ReceiptReport, ReceiptError> {
+        let report = self.anchor.verify(&ReceiptContext {
+            proof: Some(self.proof.clone()),
+            anchor_key: anchor_key.map(str::to_string),
+            ..ReceiptContext::default()
+        })?;
+        let record = &self.anchor.anchor.record;
+        let consistency = &self.consistency;
+        if consistency.old_size != record.size
+            || !consistency.old_root.eq_ignore_ascii_case(&record.root)
+            || !verify_consistency(consistency)
+        {
+            return Err(ReceiptError::NotFromAnchor);
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        consistency::consistency_proof,
+        leaf_hash, make_proof, root,
+        signer::{DetachedSignature, KeyFileSigner, Signer},
+        AnchorStatus,
+    };
+
+    fn anchored(leaves: &[[u8; 32]], ts: &str) -> AnchorRecord {
+        let root = hex::encode(root(leaves));
+        let txid = simulated_txid(leaves.len() as u64, &root, ts);
+        AnchorRecord::new(root, leaves.len() as u64, ts.into(), txid)
+    }
+
+    #[test]
+    fn receipts_check_txid_signature_proof_and_growth() {
+        let leaves: Vec<[u8; 32]> = (0..6u8).map(|i| leaf_hash(&[i])).collect();
+        let signer = KeyFileSigner::from_seed([3u8; 32]);
+        let record = anchored(&leaves, "20");
+        let receipt = AnchorReceipt {
+            anchor: StoredAnchor {
+                signature: Some(
+                    DetachedSignature::create(&signer, &record.signed_message()).unwrap(),
+                ),
+                record,
+                status: AnchorStatus::Confirmed,
+            },
+            batch: None,
+        };
+        let previous = anchored(&leaves[..4], "10");
+        let context = ReceiptContext {
+            proof: Some(make_proof(&leaves, 5).unwrap()),
+            previous: Some(previous.clone()),
+            consistency: Some(consistency_proof(&leaves, 4, leaves.len()).unwrap()),
+            anchor_key: Some(hex::encode(signer.public_key())),
+        };
+        assert_eq!(
+            receipt.verify(&context),
+            Ok(ReceiptReport {
+                txid_checked: true,
+                signature_checked: true,
+            })
+        );
+
+        let mut shrunk = context.clone();
+        shrunk.previous.as_mut().unwrap().size = 7;
+        assert_eq!(receipt.verify(&shrunk), Err(ReceiptError::NotAfterPrevious));
+
+        let mut wrong_proof = context.clone();
+        wrong_proof.proof = Some(make_proof(&leaves[..5], 1).unwrap());
+        assert_eq!(
+            receipt.verify(&wrong_proof),
+            Err(ReceiptError::ProofMismatch)
+        );
+
+        let mut forged = receipt.clone();
+        forged.anchor.record.txid = "00".repeat(32);
+        assert!(matches!(
+            forged.verify(&ReceiptContext::default()),
+            Err(ReceiptError::TxidMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn anchored_proofs_lead_on_from_the_anchor() {
+        let leaves: Vec<[u8; 32]> = (0..6u8).map(|i| leaf_hash(&[i])).collect();
+        let anchored = AnchoredProof {
+            anchor: AnchorReceipt {
+                anchor: StoredAnchor {
+                    record: anchored(&leaves[..4], "30"),
+                    status: AnchorStatus::Confirmed,
+                    signature: None,
+                },
+                batch: None,
+            },
+            proof: make_proof(&leaves[..4], 2).unwrap(),
+            consistency: consistency_proof(&leaves, 4, leaves.len()).unwrap(),
+        };
+        assert!(anchored.verify(None).is_ok());
+        assert_eq!(anchored.verify(Some("ab")), Err(ReceiptError::Unsigned));
+
+        let mut current = anchored.clone();
+        current.proof = make_proof(&leaves, 2).unwrap();
+        assert_eq!(current.verify(None), Err(ReceiptError::ProofMismatch));
+        let mut elsewhere = anchored;
+        elsewhere.consistency = consistency_proof(&leaves, 3, leaves.len()).unwrap();
+        assert_eq!(elsewhere.verify(None), Err(ReceiptError::NotFromAnchor));
+    }
+
+    #[test]
+    fn batch_members_are_checked_against_the_batch_txid() {
+        let checkpoints = [(3u64, "aa".repeat(32)), (5u64, "bb".repeat(32))];
+        let leaves: Vec<[u8; 32]> = checkpoints
+            .iter()
+            .map(|(size, root)| batch_leaf(*size, root))
+            .collect();
+        let batch_root = hex::encode(digest::root::<Sha256>(&leaves));
+        let batch_txid = simulated_txid(2, &batch_root, "7");
+        let (size, member_root) = &checkpoints[1];
+        let receipt = AnchorReceipt {
+            anchor: StoredAnchor {
+                record: AnchorRecord::new(
+                    member_root.clone(),
+                    *size,
+                    "7".into(),
+                    format!("{batch_txid}/1"),
+                ),
+                status: AnchorStatus::Confirmed,
+                signature: None,
+            },
+            batch: Some(BatchMembership {
+                batch_txid,
+                batch_root,
+                proof: digest::make_proof::<Sha256>(&leaves, 1).unwrap(),
+            }),
+        };
+        assert!(receipt.verify(&ReceiptContext::default()).is_ok());
+
+        let mut unbatched = receipt.clone();
+        unbatched.batch = None;
+        assert_eq!(
+            unbatched.verify(&ReceiptContext::default()),
+            Err(ReceiptError::MissingBatch)
+        );
+
+        let mut swapped = receipt;
+        swapped.batch.as_mut().unwrap().proof = make_proof(&leaves, 0).unwrap();
+        assert_eq!(
+            swapped.verify(&ReceiptContext::default()),
+            Err(ReceiptError::InvalidBatchProof)
+        );
+    }
+}
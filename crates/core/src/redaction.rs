@@ -0,0 +1,194 @@
+//! Tombstones for redacted entries. Redaction deletes an entry's payload but
+//! keeps its leaf, so the tree, every root and every inclusion proof stay as
+//! they were. In the payload's place the log keeps a tombstone, signed with
+//! its checkpoint key, saying which leaf lost its payload, when, why, on
+//! whose request and under what authority.
+//!
+//! logd also appends every tombstone to a redaction log of its own, so the
+//! record of what was erased is append-only and provable like the log it
+//! describes.
+
+use serde::{Deserialize, Serialize};
+
+use crate::signer::{DetachedSignature, Signer, SignerError};
+
+/// What the log records when it redacts entry `index`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Tombstone {
+    pub index: u64,
+    /// Hex leaf hash of the entry, which still proves its inclusion.
+    pub leaf: String,
+    pub reason: String,
+    /// Who asked for the redaction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requested_by: Option<String>,
+    /// What made it necessary, such as a legal basis or a case reference.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authority: Option<String>,
+    /// RFC 3339 time of the redaction.
+    pub redacted_at: String,
+}
+
+/// A tombstone signed by the log.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SignedTombstone {
+    #[serde(flatten)]
+    pub tombstone: Tombstone,
+    #[serde(flatten)]
+    pub signature: DetachedSignature,
+}
+
+/// Why an entry is redacted, and on whose say-so. The body of logd's
+/// `POST /admin/redact/<index>`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RedactRequest {
+    pub reason: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requested_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authority: Option<String>,
+}
+
+impl From<&str> for RedactRequest {
+    fn from(reason: &str) -> Self {
+        Self {
+            reason: reason.to_string(),
+            ..Self::default()
+        }
+    }
+}
+
+/// One tombstone in logd's redaction log, at `position`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RecordedRedaction {
+    pub position: u64,
+    #[serde(flatten)]
+    pub tombstone: SignedTombstone,
+}
+
+/// logd's `GET /redactions`: the redaction log's size and root, and the
+/// tombstones asked for, oldest first.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RedactionList {
+    pub size: u64,
+    pub root: String,
+    pub redactions: Vec<RecordedRedaction>,
+}
+
+impl Tombstone {
+    /// The exact bytes covered by the log's signature. A tombstone that
+    /// names neither requester nor authority keeps the first format, with
+    /// the reason last so its text cannot shift the other fields. Otherwise
+    /// the free-text fields are JSON strings, one per line.
+    pub fn signed_message(&self) -> Vec<u8> {
+        let leaf = self.leaf.to_ascii_lowercase();
+        if self.requested_by.is_none() && self.authority.is_none() {
+            return format!(
+                "reality-tombstone/v1\n{}\n{leaf}\n{}\n{}",
+                self.index, self.redacted_at, self.reason
+            )
+            .into_bytes();
+        }
+        let text = |value: &Option<String>| {
+            serde_json::to_string(value).expect("strings serialize as JSON")
+        };
+        format!(
+            "reality-tombstone/v2\n{}\n{leaf}\n{}\n{}\n{}\n{}\n",
+            self.index,
+            self.redacted_at,
+            text(&Some(self.reason.clone())),
+            text(&self.requested_by),
+            text(&self.authority)
+        )
+        .into_bytes()
+    }
+
+    pub fn sign(self, signer: &dyn Signer) -> Result<SignedTombstone, SignerError> {
+        let signature = DetachedSignature::create(signer, &self.signed_message())?;
+        Ok(SignedTombstone {
+            tombstone: self,
+            signature,
+        })
+    }
+}
+
+impl SignedTombstone {
+    /// Check the signature against the embedded public key. Callers must still
+    /// decide whether that key is one they trust.
+    pub fn verify(&self) -> bool {
+        self.signature.verify(&self.tombstone.signed_message())
+    }
+
+    /// Check the signature and that it was made by `public_key_hex`.
+    pub fn verify_with(&self, public_key_hex: &str) -> bool {
+        self.signature
+            .public_key
+            .eq_ignore_ascii_case(public_key_hex)
+            && self.verify()
+    }
+
+    /// Whether this is a valid tombstone for entry `index` with `leaf`.
+    pub fn covers(&self, index: u64, leaf: &[u8; 32]) -> bool {
+        self.tombstone.index == index
+            && self.tombstone.leaf.eq_ignore_ascii_case(&hex::encode(leaf))
+            && self.verify()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{leaf_hash, signer::KeyFileSigner};
+
+    #[test]
+    fn signed_tombstone_covers_one_entry() {
+        let signer = KeyFileSigner::from_seed([3u8; 32]);
+        let leaf = leaf_hash(b"alice@example.com");
+        let signed = Tombstone {
+            index: 7,
+            leaf: hex::encode(leaf).to_ascii_uppercase(),
+            reason: "erasure request\n#42".into(),
+            requested_by: None,
+            authority: None,
+            redacted_at: "2026-01-02T03:04:05Z".into(),
+        }
+        .sign(&signer)
+        .unwrap();
+        assert!(signed.covers(7, &leaf));
+        assert!(signed.verify_with(&hex::encode(signer.public_key())));
+        assert!(!signed.covers(8, &leaf) && !signed.covers(7, &leaf_hash(b"bob")));
+
+        let json = serde_json::to_string(&signed).unwrap();
+        let mut tampered: SignedTombstone = serde_json::from_str(&json).unwrap();
+        assert_eq!(tampered, signed);
+        tampered.tombstone.reason = "erasure request".into();
+        assert!(!tampered.verify());
+    }
+
+    #[test]
+    fn requester_and_authority_are_signed() {
+        let signer = KeyFileSigner::from_seed([4u8; 32]);
+        let tombstone = Tombstone {
+            index: 2,
+            leaf: "ab".repeat(32),
+            reason: "erasure".into(),
+            requested_by: Some("dpo@example.com".into()),
+            authority: Some("GDPR Art. 17\ncase 9".into()),
+            redacted_at: "2026-01-02T03:04:05Z".into(),
+        };
+        let message = String::from_utf8(tombstone.signed_message()).unwrap();
+        assert!(message.starts_with("reality-tombstone/v2\n"));
+        assert!(message.ends_with("\"GDPR Art. 17\\ncase 9\"\n"));
+        let signed = tombstone.sign(&signer).unwrap();
+        assert!(signed.covers(2, &[0xab; 32]));
+
+        // Moving text between fields breaks the signature.
+        let mut moved = signed.clone();
+        moved.tombstone.requested_by = None;
+        moved.tombstone.authority = Some("dpo@example.com".into());
+        assert!(!moved.verify());
+        let mut dropped = signed;
+        dropped.tombstone.authority = None;
+        assert!(!dropped.verify());
+    }
+}
@@ -0,0 +1,573 @@
+//! `.rlproof`: a single-file receipt for one entry that can be emailed or
+//! archived and checked offline.
+//!
+//! ```text
+//! magic    "RLPF"                   4 bytes
+//! version  0x01                     1 byte
+//! section  tag, length, body        1 + 4 (u32 big-endian) + length bytes, repeated
+//! ```
+//!
+//! Sections appear in tag order and only anchors and cosignatures repeat:
+//!
+//! ```text
+//! 0x01 payload       the UTF-8 payload          \ exactly one of these
+//! 0x02 digest        SHA-256 of the payload     /
+//! 0x03 proof         binary inclusion proof (`RLIP`, see `encoding`)
+//! 0x04 checkpoint    `SignedCheckpoint` as JSON
+//! 0x05 anchor        `AnchorLink` as JSON, any number
+//! 0x06 cosignature   `CosignatureLink` as JSON, any number
+//! 0x07 meta          `LeafMeta` as JSON, for entries with v2 leaves
+//! ```
+//!
+//! The signed structures are stored as the JSON logd and the anchor daemon
+//! serve, so their signatures are checked over exactly the fields they were
+//! made over.
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::{
+    blob::BlobRef,
+    bundle::{verify_checkpointed, BundleError, EntryBundle},
+    checkpoint::{Checkpoint, Cosignature, SignedCheckpoint},
+    consistency::{verify_consistency, ConsistencyProof},
+    encoding::DecodeError,
+    leaf::{entry_leaf_with, LeafMeta},
+    receipt::{AnchorReceipt, ReceiptContext, ReceiptError, ReceiptReport},
+    InclusionProof,
+};
+
+const MAGIC: &[u8; 4] = b"RLPF";
+const VERSION: u8 = 0x01;
+
+const PAYLOAD: u8 = 0x01;
+const DIGEST: u8 = 0x02;
+const PROOF: u8 = 0x03;
+const CHECKPOINT: u8 = 0x04;
+const ANCHOR: u8 = 0x05;
+const COSIGNATURE: u8 = 0x06;
+const META: u8 = 0x07;
+
+/// The entry itself, or only its SHA-256 digest when the payload must not
+/// travel with the receipt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Payload {
+    Inline(String),
+    Digest([u8; 32]),
+}
+
+/// An anchor of a tree that holds the entry. When it anchored a different
+/// size than the file's checkpoint, `consistency` links the two trees.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AnchorLink {
+    pub receipt: AnchorReceipt,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub consistency: Option<ConsistencyProof>,
+}
+
+/// A witness cosignature of a tree that holds the entry, linked to the
+/// file's checkpoint like an [`AnchorLink`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CosignatureLink {
+    pub cosignature: Cosignature,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub consistency: Option<ConsistencyProof>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofFile {
+    pub payload: Payload,
+    pub proof: InclusionProof,
+    pub checkpoint: SignedCheckpoint,
+    pub anchors: Vec<AnchorLink>,
+    pub cosignatures: Vec<CosignatureLink>,
+    /// What the entry's v2 leaf commits to besides the payload.
+    pub meta: Option<LeafMeta>,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum FormatError {
+    #[error("not a .rlproof file")]
+    BadMagic,
+    #[error("unsupported .rlproof version {0}")]
+    UnsupportedVersion(u8),
+    #[error("file is truncated")]
+    Truncated,
+    #[error("unknown section {0:#04x}")]
+    UnknownSection(u8),
+    #[error("section {0:#04x} is repeated or out of order")]
+    Misordered(u8),
+    #[error("file has no {0} section")]
+    Missing(&'static str),
+    #[error("payload is not UTF-8")]
+    NotUtf8,
+    #[error("payload digest must be 32 bytes")]
+    BadDigest,
+    #[error("inclusion proof: {0}")]
+    Proof(#[from] DecodeError),
+    #[error("section {tag:#04x} is malformed: {reason}")]
+    Malformed { tag: u8, reason: String },
+    #[error("section {0:#04x} is over 4 GiB")]
+    TooLarge(u8),
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ProofFileError {
+    #[error("payload does not match the file's payload digest")]
+    DigestMismatch,
+    #[error("the submitter's signature in the entry's leaf does not hold")]
+    BadSubmitterSignature,
+    #[error(transparent)]
+    Entry(#[from] BundleError),
+    #[error("anchor {index}: {source}")]
+    Anchor { index: usize, source: ReceiptError },
+    #[error("anchor {0} is not linked to a tree that holds the entry")]
+    AnchorNotLinked(usize),
+    #[error("cosignature {0} is invalid")]
+    BadCosignature(usize),
+    #[error("cosignature {index} is by {key}, not a trusted witness")]
+    UntrustedWitness { index: usize, key: String },
+    #[error("cosignature {0} is not linked to a tree that holds the entry")]
+    CosignatureNotLinked(usize),
+}
+
+/// Keys the file's signatures must come from. Anything left unset accepts
+/// any valid signature.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrustedKeys {
+    pub log_key: Option<String>,
+    pub anchor_key: Option<String>,
+    /// Hex keys of the witnesses whose cosignatures count; empty accepts
+    /// every witness.
+    pub witness_keys: Vec<String>,
+}
+
+/// What `ProofFile::verify` was able to check.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct ProofFileReport {
+    /// False when the file holds only a digest and no payload was given.
+    pub payload_checked: bool,
+    /// Hex key of the submitter whose signature over the payload held.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub submitter: Option<String>,
+    pub anchors: Vec<ReceiptReport>,
+}
+
+impl ProofFile {
+    /// A file for a `/bundle/N` receipt, with the payload or only its digest.
+    pub fn from_bundle(bundle: EntryBundle, detach: bool) -> Self {
+        let payload = if detach {
+            Payload::Digest(Sha256::digest(bundle.payload.as_bytes()).into())
+        } else {
+            Payload::Inline(bundle.payload)
+        };
+        Self {
+            payload,
+            proof: bundle.proof,
+            checkpoint: bundle.checkpoint,
+            anchors: Vec::new(),
+            cosignatures: Vec::new(),
+            meta: bundle.meta,
+        }
+    }
+
+    /// Check payload → leaf → root → checkpoint → signature, then every
+    /// anchor and cosignature. `payload` is checked against a detached
+    /// digest; an inline payload is always checked on its own.
+    pub fn verify(
+        &self,
+        keys: &TrustedKeys,
+        payload: Option<&[u8]>,
+    ) -> Result<ProofFileReport, ProofFileError> {
+        let payload = match (&self.payload, payload) {
+            (Payload::Inline(inline), _) => Some(inline.as_bytes()),
+            (Payload::Digest(digest), Some(given)) => {
+                if Sha256::digest(given).as_slice() != digest {
+                    return Err(ProofFileError::DigestMismatch);
+                }
+                Some(given)
+            }
+            (Payload::Digest(_), None) => None,
+        };
+        let signed = payload.is_some_and(|payload| {
+            self.meta
+                .as_ref()
+                .is_none_or(|meta| meta.signature_holds(payload))
+        });
+        if let Some(payload) = payload {
+            let leaf = entry_leaf_with(self.proof.algorithm, payload, self.meta.as_ref());
+            if !hex::encode(leaf).eq_ignore_ascii_case(&self.proof.leaf) {
+                return Err(BundleError::LeafMismatch.into());
+            }
+            // A payload logd moved to a blob store was signed as submitted,
+            // so its signature can only be checked against the blob.
+            let moved = std::str::from_utf8(payload)
+                .ok()
+                .and_then(BlobRef::from_payload)
+                .is_some();
+            if !signed && !moved {
+                return Err(ProofFileError::BadSubmitterSignature);
+            }
+        }
+        verify_checkpointed(&self.proof, &self.checkpoint, keys.log_key.as_deref())?;
+
+        let mut anchors = Vec::with_capacity(self.anchors.len());
+        for (index, anchor) in self.anchors.iter().enumerate() {
+            let context = ReceiptContext {
+                anchor_key: keys.anchor_key.clone(),
+                ..ReceiptContext::default()
+            };
+            let report = anchor
+                .receipt
+                .verify(&context)
+                .map_err(|source| ProofFileError::Anchor { index, source })?;
+            let record = &anchor.receipt.anchor.record;
+            if !self.holds_entry(record.size, &record.root, anchor.consistency.as_ref()) {
+                return Err(ProofFileError::AnchorNotLinked(index));
+            }
+            anchors.push(report);
+        }
+
+        for (index, link) in self.cosignatures.iter().enumerate() {
+            let cosignature = &link.cosignature;
+            if !cosignature.verify() {
+                return Err(ProofFileError::BadCosignature(index));
+            }
+            let key = &cosignature.witness.public_key;
+            if !keys.witness_keys.is_empty()
+                && !keys
+                    .witness_keys
+                    .iter()
+                    .any(|trusted| trusted.eq_ignore_ascii_case(key))
+            {
+                return Err(ProofFileError::UntrustedWitness {
+                    index,
+                    key: key.clone(),
+                });
+            }
+            let Checkpoint { size, root, .. } = &cosignature.checkpoint;
+            if !self.holds_entry(*size, root, link.consistency.as_ref()) {
+                return Err(ProofFileError::CosignatureNotLinked(index));
+            }
+        }
+
+        Ok(ProofFileReport {
+            payload_checked: payload.is_some(),
+            submitter: signed
+                .then_some(self.meta.as_ref())
+                .flatten()
+                .and_then(|meta| meta.signer())
+                .map(str::to_string),
+            anchors,
+        })
+    }
+
+    /// Whether the tree of `size` with `root` holds the entry: it is the
+    /// checkpoint's tree, or `consistency` shows one is a prefix of the other
+    /// and it is large enough to reach the entry.
+    fn holds_entry(&self, size: u64, root: &str, consistency: Option<&ConsistencyProof>) -> bool {
+        let checkpoint = &self.checkpoint.checkpoint;
+        if size <= self.proof.index {
+            return false;
+        }
+        if size == checkpoint.size {
+            return root.eq_ignore_ascii_case(&checkpoint.root);
+        }
+        let ((old_size, old_root), (new_size, new_root)) = if size < checkpoint.size {
+            ((size, root), (checkpoint.size, checkpoint.root.as_str()))
+        } else {
+            ((checkpoint.size, checkpoint.root.as_str()), (size, root))
+        };
+        consistency.is_some_and(|proof| {
+            proof.old_size == old_size
+                && proof.old_root.eq_ignore_ascii_case(old_root)
+                && proof.new_size == new_size
+                && proof.new_root.eq_ignore_ascii_case(new_root)
+                && verify_consistency(proof)
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, FormatError> {
+        let mut out = MAGIC.to_vec();
+        out.push(VERSION);
+        match &self.payload {
+            Payload::Inline(payload) => section(&mut out, PAYLOAD, payload.as_bytes())?,
+            Payload::Digest(digest) => section(&mut out, DIGEST, digest)?,
+        }
+        section(&mut out, PROOF, &self.proof.to_bytes()?)?;
+        section(&mut out, CHECKPOINT, &to_json(&self.checkpoint))?;
+        for anchor in &self.anchors {
+            section(&mut out, ANCHOR, &to_json(anchor))?;
+        }
+        for cosignature in &self.cosignatures {
+            section(&mut out, COSIGNATURE, &to_json(cosignature))?;
+        }
+        if let Some(meta) = &self.meta {
+            section(&mut out, META, &to_json(meta))?;
+        }
+        Ok(out)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FormatError> {
+        let rest = bytes.strip_prefix(MAGIC).ok_or(FormatError::BadMagic)?;
+        let (&version, mut rest) = rest.split_first().ok_or(FormatError::Truncated)?;
+        if version != VERSION {
+            return Err(FormatError::UnsupportedVersion(version));
+        }
+
+        let mut payload = None;
+        let mut proof = None;
+        let mut checkpoint = None;
+        let mut anchors = Vec::new();
+        let mut cosignatures = Vec::new();
+        let mut meta = None;
+        let mut last = 0;
+        while !rest.is_empty() {
+            if rest.len() < 5 {
+                return Err(FormatError::Truncated);
+            }
+            let tag = rest[0];
+            let len = u32::from_be_bytes(rest[1..5].try_into().expect("4 bytes")) as usize;
+            let body = rest.get(5..5 + len).ok_or(FormatError::Truncated)?;
+            rest = &rest[5 + len..];
+
+            if !(PAYLOAD..=META).contains(&tag) {
+                return Err(FormatError::UnknownSection(tag));
+            }
+            let repeats = matches!(tag, ANCHOR | COSIGNATURE) && tag == last;
+            let payload_first = !matches!(tag, PAYLOAD | DIGEST) || last == 0;
+            if tag <= last && !repeats || !payload_first {
+                return Err(FormatError::Misordered(tag));
+            }
+            last = tag;
+            match tag {
+                PAYLOAD => {
+                    let text = std::str::from_utf8(body).map_err(|_| FormatError::NotUtf8)?;
+                    payload = Some(Payload::Inline(text.to_string()));
+                }
+                DIGEST => {
+                    let digest = body.try_into().map_err(|_| FormatError::BadDigest)?;
+                    payload = Some(Payload::Digest(digest));
+                }
+                PROOF => proof = Some(InclusionProof::from_bytes(body)?),
+                CHECKPOINT => checkpoint = Some(from_json(tag, body)?),
+                ANCHOR => anchors.push(from_json(tag, body)?),
+                COSIGNATURE => cosignatures.push(from_json(tag, body)?),
+                _ => meta = Some(from_json(tag, body)?),
+            }
+        }
+
+        Ok(Self {
+            payload: payload.ok_or(FormatError::Missing("payload"))?,
+            proof: proof.ok_or(FormatError::Missing("proof"))?,
+            checkpoint: checkpoint.ok_or(FormatError::Missing("checkpoint"))?,
+            anchors,
+            cosignatures,
+            meta,
+        })
+    }
+}
+
+fn section(out: &mut Vec<u8>, tag: u8, body: &[u8]) -> Result<(), FormatError> {
+    let len = u32::try_from(body.len()).map_err(|_| FormatError::TooLarge(tag))?;
+    out.push(tag);
+    out.extend_from_slice(&len.to_be_bytes());
+    out.extend_from_slice(body);
+    Ok(())
+}
+
+fn to_json<T: Serialize>(value: &T) -> Vec<u8> {
+    serde_json::to_vec(value).expect("section serializes")
+}
+
+fn from_json<T: DeserializeOwned>(tag: u8, body: &[u8]) -> Result<T, FormatError> {
+    serde_json::from_slice(body).map_err(|err| FormatError::Malformed {
+        tag,
+        reason: err.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        consistency::consistency_proof,
+        leaf_hash, make_proof,
+        receipt::simulated_txid,
+        root,
+        signer::{DetachedSignature, KeyFileSigner, Signer},
+        AnchorRecord, AnchorStatus, StoredAnchor,
+    };
+
+    fn signed(leaves: &[[u8; 32]], signer: &KeyFileSigner) -> SignedCheckpoint {
+        Checkpoint {
+            size: leaves.len() as u64,
+            root: hex::encode(root(leaves)),
+            timestamp_nanos: "1".into(),
+        }
+        .sign(signer)
+        .expect("sign")
+    }
+
+    #[test]
+    fn files_round_trip_and_check_every_link() {
+        let payloads = ["a", "b", "c", "d", "e"];
+        let leaves: Vec<[u8; 32]> = payloads.iter().map(|p| leaf_hash(p.as_bytes())).collect();
+        let log = KeyFileSigner::from_seed([5u8; 32]);
+        let witness = KeyFileSigner::from_seed([6u8; 32]);
+        let bundle = EntryBundle {
+            payload: "c".into(),
+            meta: None,
+            proof: make_proof(&leaves[..4], 2).unwrap(),
+            checkpoint: signed(&leaves[..4], &log),
+        };
+
+        let anchored_root = hex::encode(root(&leaves));
+        let txid = simulated_txid(5, &anchored_root, "9");
+        let mut file = ProofFile::from_bundle(bundle.clone(), false);
+        file.anchors.push(AnchorLink {
+            receipt: AnchorReceipt {
+                anchor: StoredAnchor {
+                    record: AnchorRecord::new(anchored_root, 5, "9".into(), txid),
+                    status: AnchorStatus::Confirmed,
+                    signature: None,
+                },
+                batch: None,
+            },
+            consistency: Some(consistency_proof(&leaves, 4, leaves.len()).unwrap()),
+        });
+        file.cosignatures.push(CosignatureLink {
+            cosignature: file.checkpoint.checkpoint.clone().cosign(&witness).unwrap(),
+            consistency: None,
+        });
+
+        let bytes = file.to_bytes().unwrap();
+        assert!(bytes.starts_with(b"RLPF\x01\x01"));
+        assert_eq!(ProofFile::from_bytes(&bytes), Ok(file.clone()));
+        let keys = TrustedKeys {
+            log_key: Some(hex::encode(log.public_key())),
+            witness_keys: vec![hex::encode(witness.public_key())],
+            ..TrustedKeys::default()
+        };
+        let report = file.verify(&keys, None).unwrap();
+        assert!(report.payload_checked);
+        assert!(report.anchors[0].txid_checked);
+
+        let mut unlinked = file.clone();
+        unlinked.anchors[0].consistency = None;
+        assert_eq!(
+            unlinked.verify(&keys, None),
+            Err(ProofFileError::AnchorNotLinked(0))
+        );
+        let strangers = TrustedKeys {
+            witness_keys: vec!["00".repeat(32)],
+            ..TrustedKeys::default()
+        };
+        assert!(matches!(
+            file.verify(&strangers, None),
+            Err(ProofFileError::UntrustedWitness { index: 0, .. })
+        ));
+
+        let detached = ProofFile::from_bundle(bundle, true);
+        let bytes = detached.to_bytes().unwrap();
+        assert_eq!(bytes[5], DIGEST);
+        let detached = ProofFile::from_bytes(&bytes).unwrap();
+        let keys = TrustedKeys::default();
+        assert!(!detached.verify(&keys, None).unwrap().payload_checked);
+        assert!(detached.verify(&keys, Some(b"c")).unwrap().payload_checked);
+        assert_eq!(
+            detached.verify(&keys, Some(b"d")),
+            Err(ProofFileError::DigestMismatch)
+        );
+
+        assert_eq!(
+            ProofFile::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(FormatError::Truncated)
+        );
+        let mut unknown = bytes.clone();
+        unknown.extend_from_slice(&[0x08, 0, 0, 0, 0]);
+        assert_eq!(
+            ProofFile::from_bytes(&unknown),
+            Err(FormatError::UnknownSection(0x08))
+        );
+        let mut twice = bytes[..5].to_vec();
+        twice.extend_from_slice(&bytes[5..]);
+        twice.extend_from_slice(&bytes[5..42]);
+        assert_eq!(
+            ProofFile::from_bytes(&twice),
+            Err(FormatError::Misordered(DIGEST))
+        );
+        assert_eq!(
+            ProofFile::from_bytes(b"RLIP\x02"),
+            Err(FormatError::BadMagic)
+        );
+    }
+
+    #[test]
+    fn v2_entries_carry_their_leaf_metadata() {
+        let meta = LeafMeta {
+            sequence: 1,
+            ..LeafMeta::default()
+        };
+        let leaves = [leaf_hash(b"a"), meta.leaf(b"b")];
+        let log = KeyFileSigner::from_seed([5u8; 32]);
+        let file = ProofFile::from_bundle(
+            EntryBundle {
+                payload: "b".into(),
+                meta: Some(meta),
+                proof: make_proof(&leaves, 1).unwrap(),
+                checkpoint: signed(&leaves, &log),
+            },
+            false,
+        );
+        let bytes = file.to_bytes().unwrap();
+        let read = ProofFile::from_bytes(&bytes).unwrap();
+        assert_eq!(read, file);
+        assert!(read.verify(&TrustedKeys::default(), None).is_ok());
+
+        let mut stripped = read;
+        stripped.meta = None;
+        assert_eq!(
+            stripped.verify(&TrustedKeys::default(), None),
+            Err(ProofFileError::Entry(BundleError::LeafMismatch))
+        );
+
+        // A signed entry names its submitter, once its signature holds.
+        let submitter = KeyFileSigner::from_seed([6u8; 32]);
+        let signed_file = |stored: &str, signed_payload: &[u8]| {
+            let message = crate::leaf::entry_message(signed_payload, &Default::default());
+            let meta = LeafMeta {
+                sequence: 1,
+                signature: Some(DetachedSignature::create(&submitter, &message).unwrap()),
+                ..LeafMeta::default()
+            };
+            let leaves = [leaf_hash(b"a"), meta.leaf(stored.as_bytes())];
+            ProofFile::from_bundle(
+                EntryBundle {
+                    payload: stored.into(),
+                    meta: Some(meta),
+                    proof: make_proof(&leaves, 1).unwrap(),
+                    checkpoint: signed(&leaves, &log),
+                },
+                false,
+            )
+        };
+        let report = signed_file("b", b"b")
+            .verify(&TrustedKeys::default(), None)
+            .unwrap();
+        assert_eq!(report.submitter, Some(hex::encode(submitter.public_key())));
+        assert_eq!(
+            signed_file("b", b"c").verify(&TrustedKeys::default(), None),
+            Err(ProofFileError::BadSubmitterSignature)
+        );
+
+        // A payload moved to a blob store was signed as submitted, so the
+        // file verifies without naming a submitter it cannot check.
+        let reference = BlobRef::new(b"large").to_payload();
+        let report = signed_file(&reference, b"large")
+            .verify(&TrustedKeys::default(), None)
+            .unwrap();
+        assert_eq!(report.submitter, None);
+    }
+}
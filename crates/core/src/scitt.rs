@@ -0,0 +1,307 @@
+//! SCITT receipts: COSE_Sign1 inclusion receipts as drafted in "COSE
+//! Receipts" (draft-ietf-cose-merkle-tree-proofs), the form supply-chain
+//! transparency tooling expects proofs from a transparency service in.
+//!
+//! A receipt signs the tree's root without carrying it: the verifier
+//! recomputes the root from the entry and the inclusion proof in the
+//! unprotected header, `[tree_size, leaf_index, [sibling, ...]]` as for RFC
+//! 9162, and checks the signature over that. This log's tree pairs an odd
+//! last node with itself where RFC 9162 promotes it, so receipts name a
+//! verifiable data structure from COSE's private-use range, one per hash
+//! algorithm, rather than `RFC9162_SHA256`.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    algorithm::HashAlgorithm,
+    cbor::Value,
+    ct::depth,
+    decode_hash,
+    signer::{verify_signature, Signer},
+    InclusionProof,
+};
+
+/// What logd serves receipts as.
+pub const MEDIA_TYPE: &str = "application/cose";
+
+const COSE_SIGN1_TAG: u64 = 18;
+const ALG: i64 = 1;
+const KID: i64 = 4;
+const CWT_CLAIMS: i64 = 15;
+const IAT: i64 = 6;
+const VDS: i64 = 395;
+const VDP: i64 = 396;
+const INCLUSION_PROOFS: i64 = -1;
+const EDDSA: i64 = -8;
+
+/// The verifiable data structure value for this log's tree built with
+/// `algorithm`.
+pub fn vds(algorithm: HashAlgorithm) -> i64 {
+    match algorithm {
+        HashAlgorithm::Sha256 => -65537,
+        HashAlgorithm::Sha3_256 => -65538,
+        HashAlgorithm::Blake3 => -65539,
+        HashAlgorithm::Sha512_256 => -65540,
+    }
+}
+
+/// What a valid receipt establishes.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReceiptClaims {
+    pub index: u64,
+    pub size: u64,
+    /// The root the signature covers, recomputed from the entry.
+    pub root: String,
+    /// Hex ed25519 key the receipt is signed with, from its `kid`.
+    pub public_key: String,
+    /// Unix seconds the receipt was issued at.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issued_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "HashAlgorithm::is_default")]
+    pub algorithm: HashAlgorithm,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ScittError {
+    #[error("not a COSE_Sign1 receipt: {0}")]
+    Malformed(&'static str),
+    #[error("receipts are signed with EdDSA, not COSE algorithm {0}")]
+    UnsupportedAlgorithm(i64),
+    #[error("verifiable data structure {0} is not this log's")]
+    UnknownStructure(i64),
+    #[error("the inclusion proof does not fit a tree of its size")]
+    ProofShape,
+    #[error("the proof holds a hash that is not 32 bytes of hex")]
+    InvalidHash,
+    #[error("failed to sign the receipt: {0}")]
+    Signing(String),
+    #[error("the receipt's signature does not cover the root the entry leads to")]
+    BadSignature,
+    #[error("the receipt is signed by {0}, not the trusted log key")]
+    UntrustedKey(String),
+}
+
+/// Sign a receipt for the entry `proof` is for, at unix time `issued_at`.
+pub fn issue(
+    proof: &InclusionProof,
+    signer: &dyn Signer,
+    issued_at: i64,
+) -> Result<Vec<u8>, ScittError> {
+    let hash = |hex: &str| decode_hash(hex).ok_or(ScittError::InvalidHash);
+    let root = hash(&proof.root)?;
+    let path = proof
+        .path
+        .iter()
+        .map(|step| Ok(Value::Bytes(hash(&step.hash)?.to_vec())))
+        .collect::<Result<_, ScittError>>()?;
+    let inclusion = Value::Array(vec![
+        Value::Int(proof.size as i64),
+        Value::Int(proof.index as i64),
+        Value::Array(path),
+    ]);
+
+    let protected = Value::Map(vec![
+        (Value::Int(ALG), Value::Int(EDDSA)),
+        (Value::Int(KID), Value::Bytes(signer.public_key().to_vec())),
+        (
+            Value::Int(CWT_CLAIMS),
+            Value::Map(vec![(Value::Int(IAT), Value::Int(issued_at))]),
+        ),
+        (Value::Int(VDS), Value::Int(vds(proof.algorithm))),
+    ])
+    .encode();
+    let signature = signer
+        .sign(&to_be_signed(&protected, &root))
+        .map_err(|err| ScittError::Signing(err.to_string()))?;
+    let unprotected = Value::Map(vec![(
+        Value::Int(VDP),
+        Value::Map(vec![(
+            Value::Int(INCLUSION_PROOFS),
+            Value::Array(vec![Value::Bytes(inclusion.encode())]),
+        )]),
+    )]);
+    Ok(Value::Tag(
+        COSE_SIGN1_TAG,
+        Box::new(Value::Array(vec![
+            Value::Bytes(protected),
+            unprotected,
+            Value::Null,
+            Value::Bytes(signature.to_vec()),
+        ])),
+    )
+    .encode())
+}
+
+/// Check that `receipt` proves the entry with `payload` is in the log.
+/// Without `trusted_key` any signer is accepted, so callers should pin the
+/// log's key wherever they can.
+pub fn verify(
+    receipt: &[u8],
+    payload: &[u8],
+    trusted_key: Option<&str>,
+) -> Result<ReceiptClaims, ScittError> {
+    let malformed = ScittError::Malformed;
+    let value = Value::decode(receipt).ok_or(malformed("invalid CBOR"))?;
+    let value = match value {
+        Value::Tag(COSE_SIGN1_TAG, inner) => *inner,
+        untagged => untagged,
+    };
+    let [protected_bytes, unprotected, _payload, signature] =
+        value.as_array().ok_or(malformed("not an array"))?
+    else {
+        return Err(malformed("not four fields"));
+    };
+    let protected_bytes = protected_bytes
+        .as_bytes()
+        .ok_or(malformed("protected header is not a byte string"))?;
+    let protected = Value::decode(protected_bytes).ok_or(malformed("protected header"))?;
+
+    let alg = protected
+        .get(ALG)
+        .and_then(Value::as_int)
+        .ok_or(malformed("no algorithm"))?;
+    if alg != EDDSA {
+        return Err(ScittError::UnsupportedAlgorithm(alg));
+    }
+    let structure = protected
+        .get(VDS)
+        .and_then(Value::as_int)
+        .ok_or(malformed("no verifiable data structure"))?;
+    let algorithm = HashAlgorithm::ALL
+        .into_iter()
+        .find(|&algorithm| vds(algorithm) == structure)
+        .ok_or(ScittError::UnknownStructure(structure))?;
+    let public_key: [u8; 32] = protected
+        .get(KID)
+        .and_then(Value::as_bytes)
+        .and_then(|kid| kid.try_into().ok())
+        .ok_or(malformed("kid is not an ed25519 key"))?;
+    let issued_at = protected
+        .get(CWT_CLAIMS)
+        .and_then(|claims| claims.get(IAT))
+        .and_then(Value::as_int);
+
+    let inclusion = unprotected
+        .get(VDP)
+        .and_then(|proofs| proofs.get(INCLUSION_PROOFS))
+        .and_then(Value::as_array)
+        .and_then(<[Value]>::first)
+        .and_then(Value::as_bytes)
+        .and_then(Value::decode)
+        .ok_or(malformed("no inclusion proof"))?;
+    let [size, index, path] = inclusion.as_array().ok_or(ScittError::ProofShape)? else {
+        return Err(ScittError::ProofShape);
+    };
+    let (Some(size), Some(index), Some(path)) = (
+        size.as_int().and_then(|n| u64::try_from(n).ok()),
+        index.as_int().and_then(|n| u64::try_from(n).ok()),
+        path.as_array(),
+    ) else {
+        return Err(ScittError::ProofShape);
+    };
+    if index >= size || path.len() != depth(size) {
+        return Err(ScittError::ProofShape);
+    }
+
+    // Directions follow from the index, one bit per level.
+    let mut computed = algorithm.leaf_hash(payload);
+    let mut position = index;
+    for sibling in path {
+        let sibling: [u8; 32] = sibling
+            .as_bytes()
+            .and_then(|hash| hash.try_into().ok())
+            .ok_or(ScittError::ProofShape)?;
+        computed = if position % 2 == 1 {
+            algorithm.node_hash(&sibling, &computed)
+        } else {
+            algorithm.node_hash(&computed, &sibling)
+        };
+        position /= 2;
+    }
+
+    let signature: [u8; 64] = signature
+        .as_bytes()
+        .and_then(|sig| sig.try_into().ok())
+        .ok_or(malformed("signature is not 64 bytes"))?;
+    if !verify_signature(
+        &public_key,
+        &to_be_signed(protected_bytes, &computed),
+        &signature,
+    ) {
+        return Err(ScittError::BadSignature);
+    }
+    let public_key = hex::encode(public_key);
+    if let Some(trusted) = trusted_key {
+        if !trusted.eq_ignore_ascii_case(&public_key) {
+            return Err(ScittError::UntrustedKey(public_key));
+        }
+    }
+    Ok(ReceiptClaims {
+        index,
+        size,
+        root: hex::encode(computed),
+        public_key,
+        issued_at,
+        algorithm,
+    })
+}
+
+/// COSE's `Sig_structure` for a Sign1 with the root as detached payload.
+fn to_be_signed(protected: &[u8], root: &[u8; 32]) -> Vec<u8> {
+    Value::Array(vec![
+        Value::Text("Signature1".to_string()),
+        Value::Bytes(protected.to_vec()),
+        Value::Bytes(Vec::new()),
+        Value::Bytes(root.to_vec()),
+    ])
+    .encode()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{leaf_hash, make_proof, signer::KeyFileSigner};
+
+    #[test]
+    fn receipts_verify_for_every_entry_and_nothing_else() {
+        let signer = KeyFileSigner::from_seed([8u8; 32]);
+        let key = hex::encode(signer.public_key());
+        let payloads: Vec<String> = (0..7).map(|i| format!("entry {i}")).collect();
+        for size in 1..=payloads.len() {
+            let leaves: Vec<_> = payloads[..size]
+                .iter()
+                .map(|p| leaf_hash(p.as_bytes()))
+                .collect();
+            for (index, payload) in payloads[..size].iter().enumerate() {
+                let proof = make_proof(&leaves, index).unwrap();
+                let receipt = issue(&proof, &signer, 1_700_000_000).unwrap();
+                let claims = verify(&receipt, payload.as_bytes(), Some(&key)).unwrap();
+                assert_eq!((claims.index, claims.size), (index as u64, size as u64));
+                assert_eq!(claims.root, proof.root);
+                assert_eq!(claims.issued_at, Some(1_700_000_000));
+                assert_eq!(
+                    verify(&receipt, b"something else", None),
+                    Err(ScittError::BadSignature)
+                );
+            }
+        }
+
+        let leaves = [leaf_hash(b"a"), leaf_hash(b"b")];
+        let receipt = issue(&make_proof(&leaves, 1).unwrap(), &signer, 0).unwrap();
+        assert!(matches!(
+            verify(&receipt, b"b", Some(&"00".repeat(32))),
+            Err(ScittError::UntrustedKey(_))
+        ));
+        // A tagged COSE_Sign1 with an EdDSA protected header.
+        assert_eq!(receipt[0], 0xd2);
+        let mut tampered = receipt.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert_eq!(verify(&tampered, b"b", None), Err(ScittError::BadSignature));
+        assert!(matches!(
+            verify(b"\x80", b"b", None),
+            Err(ScittError::Malformed(_))
+        ));
+    }
+}
@@ -0,0 +1,667 @@
+use std::path::{Path, PathBuf};
+
+use ed25519_dalek::{Signature, Signer as _, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::keyfile;
+
+#[derive(Debug, Error)]
+pub enum SignerError {
+    #[error("invalid signer spec: {0}")]
+    InvalidSpec(String),
+    #[error("failed to read key file {path}: {source}")]
+    KeyFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("key file must hold a hex-encoded 32-byte ed25519 seed")]
+    InvalidKey,
+    #[error(
+        "key file {} is encrypted; set REALITY_KEY_PASSPHRASE or REALITY_KEY_PASSPHRASE_FILE",
+        .0.display()
+    )]
+    PassphraseRequired(PathBuf),
+    #[error("key file encryption failed: {0}")]
+    Encryption(String),
+    #[error("encrypted key files are not supported in this build (enable the `age` feature)")]
+    AgeDisabled,
+    #[error("pkcs11 support is not compiled in (enable the `pkcs11` feature)")]
+    Pkcs11Disabled,
+    #[error("hsm error: {0}")]
+    Hsm(String),
+    #[error("AWS KMS support is not compiled in (enable the `aws-kms` feature)")]
+    AwsKmsDisabled,
+    #[error("AWS KMS error: {0}")]
+    Kms(String),
+}
+
+/// An ed25519 signing key, wherever it lives. Implementations that talk to
+/// hardware never expose the private key to the process.
+///
+/// `sign` may block on an HSM or a KMS round trip, so async code calls it
+/// from a blocking context, such as `tokio::task::spawn_blocking`, rather
+/// than on a runtime worker.
+pub trait Signer: Send + Sync {
+    fn public_key(&self) -> [u8; 32];
+    fn sign(&self, message: &[u8]) -> Result<[u8; 64], SignerError>;
+}
+
+/// A detached signature together with the key that made it, both hex-encoded.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DetachedSignature {
+    pub public_key: String,
+    pub signature: String,
+}
+
+impl DetachedSignature {
+    pub fn create(signer: &dyn Signer, message: &[u8]) -> Result<Self, SignerError> {
+        Ok(Self {
+            public_key: hex::encode(signer.public_key()),
+            signature: hex::encode(signer.sign(message)?),
+        })
+    }
+
+    /// Check the signature over `message` with the embedded public key.
+    pub fn verify(&self, message: &[u8]) -> bool {
+        match (
+            decode::<32>(&self.public_key),
+            decode::<64>(&self.signature),
+        ) {
+            (Some(key), Some(sig)) => verify_signature(&key, message, &sig),
+            _ => false,
+        }
+    }
+}
+
+/// A statement that a service replaced its signing key. The old key signs
+/// it to vouch for the new one, and the new key signs it to show it is held.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KeyRotation {
+    /// What the key signs for, such as `log` or `witness`.
+    pub role: String,
+    pub rotated_at_nanos: String,
+    pub old: DetachedSignature,
+    pub new: DetachedSignature,
+}
+
+impl KeyRotation {
+    pub fn sign(
+        role: &str,
+        rotated_at_nanos: String,
+        old: &dyn Signer,
+        new: &dyn Signer,
+    ) -> Result<Self, SignerError> {
+        let message = Self::message(
+            role,
+            &hex::encode(old.public_key()),
+            &hex::encode(new.public_key()),
+            &rotated_at_nanos,
+        );
+        Ok(Self {
+            role: role.to_string(),
+            old: DetachedSignature::create(old, &message)?,
+            new: DetachedSignature::create(new, &message)?,
+            rotated_at_nanos,
+        })
+    }
+
+    /// The exact bytes both keys sign.
+    pub fn signed_message(&self) -> Vec<u8> {
+        Self::message(
+            &self.role,
+            &self.old.public_key,
+            &self.new.public_key,
+            &self.rotated_at_nanos,
+        )
+    }
+
+    fn message(role: &str, old: &str, new: &str, at: &str) -> Vec<u8> {
+        format!(
+            "reality-key-rotation/v1\n{role}\n{}\n{}\n{at}\n",
+            old.to_ascii_lowercase(),
+            new.to_ascii_lowercase()
+        )
+        .into_bytes()
+    }
+
+    pub fn verify(&self) -> bool {
+        let message = self.signed_message();
+        self.old.verify(&message) && self.new.verify(&message)
+    }
+}
+
+/// Strict ed25519 verification (rejects small-order keys and malleable
+/// signatures).
+pub fn verify_signature(public_key: &[u8; 32], message: &[u8], signature: &[u8; 64]) -> bool {
+    let Ok(key) = VerifyingKey::from_bytes(public_key) else {
+        return false;
+    };
+    key.verify_strict(message, &Signature::from_bytes(signature))
+        .is_ok()
+}
+
+/// Signer backed by a hex-encoded seed on local disk.
+pub struct KeyFileSigner {
+    key: SigningKey,
+}
+
+impl KeyFileSigner {
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        Self {
+            key: SigningKey::from_bytes(&seed),
+        }
+    }
+
+    /// Load a key file, decrypting it with the passphrase from the
+    /// environment if it is encrypted (see [`keyfile`](crate::keyfile)).
+    pub fn load(path: &Path) -> Result<Self, SignerError> {
+        let passphrase = keyfile::passphrase_from_env()?;
+        let seed = keyfile::read_seed(path, passphrase.as_deref())?;
+        Ok(Self::from_seed(seed))
+    }
+}
+
+impl Signer for KeyFileSigner {
+    fn public_key(&self) -> [u8; 32] {
+        self.key.verifying_key().to_bytes()
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<[u8; 64], SignerError> {
+        Ok(self.key.sign(message).to_bytes())
+    }
+}
+
+/// Open a signer from a spec string:
+///
+/// - `file:<path>` — hex seed on disk, possibly encrypted with a passphrase
+/// - `pkcs11:token=<label>;object=<label>?module-path=<lib>&pin-value=<pin>` —
+///   an RFC 7512 style URI naming an Ed25519 key pair in an HSM. Without
+///   `pin-value` the PIN is read from `REALITY_PKCS11_PIN`.
+/// - `aws-kms:<key id or ARN>` — an AWS KMS `ECC_NIST_EDWARDS25519` key,
+///   reached with the credentials of [`crate::sigv4::Credentials::from_env`]
+///   at `AWS_ENDPOINT_URL_KMS`, or the regional endpoint when that is unset.
+pub fn open_signer(spec: &str) -> Result<Box<dyn Signer>, SignerError> {
+    if let Some(path) = spec.strip_prefix("file:") {
+        return Ok(Box::new(KeyFileSigner::load(Path::new(path))?));
+    }
+    if spec.starts_with("pkcs11:") {
+        let uri = Pkcs11Uri::parse(spec)?;
+        return open_pkcs11(uri);
+    }
+    if let Some(key_id) = spec.strip_prefix("aws-kms:") {
+        return open_aws_kms(key_id);
+    }
+    Err(SignerError::InvalidSpec(format!(
+        "expected `file:`, `pkcs11:` or `aws-kms:` prefix in {spec:?}"
+    )))
+}
+
+/// The subset of RFC 7512 attributes needed to find a signing key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pkcs11Uri {
+    pub token: String,
+    pub object: String,
+    pub module_path: String,
+    pub pin: Option<String>,
+}
+
+impl Pkcs11Uri {
+    pub fn parse(uri: &str) -> Result<Self, SignerError> {
+        let body = uri
+            .strip_prefix("pkcs11:")
+            .ok_or_else(|| SignerError::InvalidSpec("missing pkcs11: scheme".into()))?;
+        let (path, query) = body.split_once('?').unwrap_or((body, ""));
+
+        let (mut token, mut object, mut module_path, mut pin) = (None, None, None, None);
+        for attr in path.split(';').filter(|a| !a.is_empty()) {
+            match attr.split_once('=') {
+                Some(("token", v)) => token = Some(percent_decode(v)?),
+                Some(("object", v)) => object = Some(percent_decode(v)?),
+                _ => {}
+            }
+        }
+        for attr in query.split('&').filter(|a| !a.is_empty()) {
+            match attr.split_once('=') {
+                Some(("module-path", v)) => module_path = Some(percent_decode(v)?),
+                Some(("pin-value", v)) => pin = Some(percent_decode(v)?),
+                _ => {}
+            }
+        }
+
+        let missing = |name: &str| SignerError::InvalidSpec(format!("pkcs11 uri needs {name}"));
+        Ok(Self {
+            token: token.ok_or_else(|| missing("token"))?,
+            object: object.ok_or_else(|| missing("object"))?,
+            module_path: module_path.ok_or_else(|| missing("module-path"))?,
+            pin,
+        })
+    }
+}
+
+/// Undo RFC 7512's percent-encoding of an attribute value, which must
+/// decode to UTF-8.
+fn percent_decode(value: &str) -> Result<String, SignerError> {
+    let invalid = || SignerError::InvalidSpec(format!("bad percent-encoding in {value:?}"));
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail.get(..2).ok_or_else(invalid)?;
+            let hex = std::str::from_utf8(hex).map_err(|_| invalid())?;
+            bytes.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| invalid())
+}
+
+#[cfg(not(feature = "pkcs11"))]
+fn open_pkcs11(_uri: Pkcs11Uri) -> Result<Box<dyn Signer>, SignerError> {
+    Err(SignerError::Pkcs11Disabled)
+}
+
+#[cfg(feature = "pkcs11")]
+fn open_pkcs11(uri: Pkcs11Uri) -> Result<Box<dyn Signer>, SignerError> {
+    Ok(Box::new(pkcs11::Pkcs11Signer::open(&uri)?))
+}
+
+#[cfg(feature = "pkcs11")]
+mod pkcs11 {
+    use std::sync::Mutex;
+
+    use cryptoki::{
+        context::{CInitializeArgs, Pkcs11},
+        mechanism::Mechanism,
+        object::{Attribute, AttributeType, ObjectClass, ObjectHandle},
+        session::{Session, UserType},
+        types::AuthPin,
+    };
+
+    use super::{Pkcs11Uri, Signer, SignerError};
+
+    /// Ed25519 key pair held by a PKCS#11 token; signing happens on the device.
+    pub struct Pkcs11Signer {
+        session: Mutex<Session>,
+        key: ObjectHandle,
+        public_key: [u8; 32],
+    }
+
+    fn hsm(err: impl std::fmt::Display) -> SignerError {
+        SignerError::Hsm(err.to_string())
+    }
+
+    impl Pkcs11Signer {
+        pub fn open(uri: &Pkcs11Uri) -> Result<Self, SignerError> {
+            let ctx = Pkcs11::new(&uri.module_path).map_err(hsm)?;
+            ctx.initialize(CInitializeArgs::OsThreads).map_err(hsm)?;
+
+            let slot = ctx
+                .get_slots_with_token()
+                .map_err(hsm)?
+                .into_iter()
+                .find(|slot| {
+                    ctx.get_token_info(*slot)
+                        .map(|info| info.label().trim() == uri.token)
+                        .unwrap_or(false)
+                })
+                .ok_or_else(|| hsm(format!("no token labelled {:?}", uri.token)))?;
+
+            let pin = match &uri.pin {
+                Some(pin) => pin.clone(),
+                None => std::env::var("REALITY_PKCS11_PIN")
+                    .map_err(|_| hsm("no pin-value and REALITY_PKCS11_PIN is unset"))?,
+            };
+            let session = ctx.open_ro_session(slot).map_err(hsm)?;
+            session
+                .login(UserType::User, Some(&AuthPin::new(pin)))
+                .map_err(hsm)?;
+
+            let find = |class| {
+                session
+                    .find_objects(&[
+                        Attribute::Class(class),
+                        Attribute::Label(uri.object.as_bytes().to_vec()),
+                    ])
+                    .map_err(hsm)?
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| hsm(format!("no {class} labelled {:?}", uri.object)))
+            };
+            let key = find(ObjectClass::PRIVATE_KEY)?;
+            let public = find(ObjectClass::PUBLIC_KEY)?;
+
+            let point = session
+                .get_attributes(public, &[AttributeType::EcPoint])
+                .map_err(hsm)?
+                .into_iter()
+                .find_map(|attr| match attr {
+                    Attribute::EcPoint(point) => Some(point),
+                    _ => None,
+                })
+                .ok_or_else(|| hsm("public key has no CKA_EC_POINT"))?;
+            // CKA_EC_POINT is a DER OCTET STRING wrapping the 32-byte key.
+            let raw = match point.as_slice() {
+                [0x04, 0x20, rest @ ..] => rest,
+                other => other,
+            };
+            let public_key: [u8; 32] = raw
+                .try_into()
+                .map_err(|_| hsm("public key is not an ed25519 point"))?;
+
+            Ok(Self {
+                session: Mutex::new(session),
+                key,
+                public_key,
+            })
+        }
+    }
+
+    impl Signer for Pkcs11Signer {
+        fn public_key(&self) -> [u8; 32] {
+            self.public_key
+        }
+
+        fn sign(&self, message: &[u8]) -> Result<[u8; 64], SignerError> {
+            let session = self.session.lock().expect("pkcs11 session poisoned");
+            let signature = session
+                .sign(&Mechanism::Eddsa, self.key, message)
+                .map_err(hsm)?;
+            signature
+                .try_into()
+                .map_err(|_| hsm("token returned a malformed ed25519 signature"))
+        }
+    }
+}
+
+#[cfg(not(feature = "aws-kms"))]
+fn open_aws_kms(_key_id: &str) -> Result<Box<dyn Signer>, SignerError> {
+    Err(SignerError::AwsKmsDisabled)
+}
+
+#[cfg(feature = "aws-kms")]
+fn open_aws_kms(key_id: &str) -> Result<Box<dyn Signer>, SignerError> {
+    let credentials =
+        crate::sigv4::Credentials::from_env().map_err(|err| SignerError::Kms(err.to_string()))?;
+    let endpoint = std::env::var("AWS_ENDPOINT_URL_KMS")
+        .unwrap_or_else(|_| format!("https://kms.{}.amazonaws.com/", credentials.region));
+    Ok(Box::new(aws_kms::AwsKmsSigner::open(
+        key_id,
+        &endpoint,
+        credentials,
+    )?))
+}
+
+#[cfg(feature = "aws-kms")]
+mod aws_kms {
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+    use reqwest::{blocking::Client, Url};
+    use serde_json::{json, Value};
+
+    use super::{Signer, SignerError};
+    use crate::sigv4::Credentials;
+
+    /// DER SubjectPublicKeyInfo of an Ed25519 key, up to the key itself.
+    const ED25519_SPKI_PREFIX: [u8; 12] = [
+        0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+    ];
+
+    /// Ed25519 key pair held by AWS KMS; signing happens in KMS.
+    pub struct AwsKmsSigner {
+        key_id: String,
+        endpoint: Url,
+        credentials: Credentials,
+        http: Client,
+        public_key: [u8; 32],
+    }
+
+    fn kms(err: impl std::fmt::Display) -> SignerError {
+        SignerError::Kms(err.to_string())
+    }
+
+    impl AwsKmsSigner {
+        pub fn open(
+            key_id: &str,
+            endpoint: &str,
+            credentials: Credentials,
+        ) -> Result<Self, SignerError> {
+            let endpoint =
+                Url::parse(endpoint).map_err(|err| kms(format!("{endpoint:?}: {err}")))?;
+            let mut signer = Self {
+                key_id: key_id.to_string(),
+                endpoint,
+                credentials,
+                http: off_runtime(Client::new),
+                public_key: [0; 32],
+            };
+            let reply = signer.call("GetPublicKey", json!({ "KeyId": key_id }))?;
+            if reply["KeySpec"] != "ECC_NIST_EDWARDS25519" {
+                return Err(kms(format!(
+                    "key {key_id} is {}, not an ECC_NIST_EDWARDS25519 key",
+                    reply["KeySpec"]
+                )));
+            }
+            signer.public_key = decode_field(&reply, "PublicKey")?
+                .strip_prefix(&ED25519_SPKI_PREFIX)
+                .and_then(|key| key.try_into().ok())
+                .ok_or_else(|| kms("public key is not an ed25519 SubjectPublicKeyInfo"))?;
+            Ok(signer)
+        }
+
+        /// Call KMS action `action` with a JSON body.
+        fn call(&self, action: &str, body: Value) -> Result<Value, SignerError> {
+            let target = format!("TrentService.{action}");
+            let body = serde_json::to_vec(&body).expect("JSON values serialize");
+            let headers = self.credentials.sign(
+                "kms",
+                "POST",
+                &self.endpoint,
+                &[
+                    ("content-type", "application/x-amz-json-1.1"),
+                    ("x-amz-target", &target),
+                ],
+                &body,
+            );
+            off_runtime(|| {
+                let mut request = self.http.post(self.endpoint.clone());
+                for (name, value) in headers {
+                    request = request.header(name, value);
+                }
+                let response = request
+                    .body(body)
+                    .send()
+                    .map_err(|err| kms(format!("{action}: {err}")))?;
+                let status = response.status();
+                let text = response.text().map_err(kms)?;
+                if !status.is_success() {
+                    return Err(kms(format!("{action} returned {status}: {text}")));
+                }
+                serde_json::from_str(&text).map_err(kms)
+            })
+        }
+    }
+
+    impl Signer for AwsKmsSigner {
+        fn public_key(&self) -> [u8; 32] {
+            self.public_key
+        }
+
+        fn sign(&self, message: &[u8]) -> Result<[u8; 64], SignerError> {
+            let reply = self.call(
+                "Sign",
+                json!({
+                    "KeyId": self.key_id,
+                    "Message": STANDARD.encode(message),
+                    "MessageType": "RAW",
+                    "SigningAlgorithm": "ED25519_SHA_512",
+                }),
+            )?;
+            decode_field(&reply, "Signature")?
+                .try_into()
+                .map_err(|_| kms("KMS returned a malformed ed25519 signature"))
+        }
+    }
+
+    fn decode_field(reply: &Value, field: &str) -> Result<Vec<u8>, SignerError> {
+        let value = reply[field]
+            .as_str()
+            .ok_or_else(|| kms(format!("KMS reply has no {field}")))?;
+        STANDARD.decode(value).map_err(kms)
+    }
+
+    /// Run `f` on a thread of its own. A blocking client panics when used
+    /// from an async runtime, and a signer is opened, and may be called, from
+    /// one. Callers still wait for `f`, so they sign off the runtime's
+    /// workers (see [`Signer`]).
+    fn off_runtime<T: Send>(f: impl FnOnce() -> T + Send) -> T {
+        std::thread::scope(|scope| scope.spawn(f).join().expect("KMS call panicked"))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::{
+            io::{BufRead, BufReader, Read, Write},
+            net::TcpListener,
+        };
+
+        use super::*;
+        use crate::signer::KeyFileSigner;
+
+        /// Answer GetPublicKey and Sign like KMS would, with `key`.
+        fn fake_kms(key: KeyFileSigner, requests: usize) -> String {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}/", listener.local_addr().unwrap());
+            std::thread::spawn(move || {
+                for stream in listener.incoming().take(requests) {
+                    let mut stream = stream.unwrap();
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let (mut target, mut length) = (String::new(), 0);
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        let line = line.trim_end().to_ascii_lowercase();
+                        if line.is_empty() {
+                            break;
+                        }
+                        if let Some(value) = line.strip_prefix("x-amz-target: ") {
+                            target = value.to_string();
+                        }
+                        if let Some(value) = line.strip_prefix("content-length: ") {
+                            length = value.parse().unwrap();
+                        }
+                        assert!(!line.starts_with("authorization: ") || line.contains("/kms/"));
+                    }
+                    let mut body = vec![0; length];
+                    reader.read_exact(&mut body).unwrap();
+                    let body: Value = serde_json::from_slice(&body).unwrap();
+                    let reply = match target.as_str() {
+                        "trentservice.getpublickey" => json!({
+                            "KeySpec": "ECC_NIST_EDWARDS25519",
+                            "PublicKey": STANDARD.encode(
+                                [&ED25519_SPKI_PREFIX[..], &key.public_key()].concat()
+                            ),
+                        }),
+                        "trentservice.sign" => {
+                            assert_eq!(body["SigningAlgorithm"], "ED25519_SHA_512");
+                            let message =
+                                STANDARD.decode(body["Message"].as_str().unwrap()).unwrap();
+                            json!({ "Signature": STANDARD.encode(key.sign(&message).unwrap()) })
+                        }
+                        other => panic!("unexpected KMS action {other}"),
+                    }
+                    .to_string();
+                    write!(
+                        stream,
+                        "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{reply}",
+                        reply.len()
+                    )
+                    .unwrap();
+                }
+            });
+            url
+        }
+
+        #[test]
+        fn signs_with_a_kms_key() {
+            let key = KeyFileSigner::from_seed([7; 32]);
+            let expected = key.public_key();
+            let endpoint = fake_kms(KeyFileSigner::from_seed([7; 32]), 2);
+            let credentials = Credentials {
+                region: "eu-west-1".into(),
+                access_key: "AKID".into(),
+                secret_key: "secret".into(),
+                session_token: None,
+            };
+            let signer = AwsKmsSigner::open("alias/sth", &endpoint, credentials).unwrap();
+            assert_eq!(signer.public_key(), expected);
+            let signature = signer.sign(b"checkpoint").unwrap();
+            assert_eq!(signature, key.sign(b"checkpoint").unwrap());
+        }
+    }
+}
+
+pub(crate) fn decode<const N: usize>(value: &str) -> Option<[u8; N]> {
+    hex::decode(value).ok()?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_file_signature_round_trip() {
+        let signer = KeyFileSigner::from_seed([3u8; 32]);
+        let sig = DetachedSignature::create(&signer, b"checkpoint").expect("sign");
+        assert!(sig.verify(b"checkpoint"));
+        assert!(!sig.verify(b"checkpoint!"));
+    }
+
+    #[test]
+    fn key_rotations_are_signed_by_both_keys() {
+        let old = KeyFileSigner::from_seed([1u8; 32]);
+        let new = KeyFileSigner::from_seed([2u8; 32]);
+        let rotation = KeyRotation::sign("log", "5".into(), &old, &new).expect("sign");
+        assert!(rotation.verify());
+
+        let mut forged = rotation.clone();
+        forged.new = KeyRotation::sign("log", "5".into(), &new, &new)
+            .unwrap()
+            .new;
+        assert!(!forged.verify());
+        let mut relabelled = rotation;
+        relabelled.role = "witness".into();
+        assert!(!relabelled.verify());
+    }
+
+    #[test]
+    fn parses_pkcs11_uri() {
+        let uri = Pkcs11Uri::parse(
+            "pkcs11:token=reality;object=sth?module-path=/usr/lib/softhsm/libsofthsm2.so&pin-value=1234",
+        )
+        .expect("uri");
+        assert_eq!(uri.token, "reality");
+        assert_eq!(uri.object, "sth");
+        assert_eq!(uri.module_path, "/usr/lib/softhsm/libsofthsm2.so");
+        assert_eq!(uri.pin.as_deref(), Some("1234"));
+        assert!(Pkcs11Uri::parse("pkcs11:token=reality").is_err());
+    }
+
+    #[test]
+    fn percent_decodes_pkcs11_attributes() {
+        let uri = Pkcs11Uri::parse(
+            "pkcs11:token=Reality%20HSM;object=sth%3Bkey?module-path=/opt/my%20hsm/lib.so&pin-value=p%26w",
+        )
+        .expect("uri");
+        assert_eq!(uri.token, "Reality HSM");
+        assert_eq!(uri.object, "sth;key");
+        assert_eq!(uri.module_path, "/opt/my hsm/lib.so");
+        assert_eq!(uri.pin.as_deref(), Some("p&w"));
+        for bad in ["%2", "%zz", "%ff"] {
+            let uri = format!("pkcs11:token={bad};object=sth?module-path=/lib.so");
+            assert!(Pkcs11Uri::parse(&uri).is_err(), "{bad}");
+        }
+    }
+}
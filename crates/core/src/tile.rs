@@ -0,0 +1,152 @@
+//! Fixed-width runs of leaf hashes that clients can fetch and cache to build
+//! proofs themselves instead of trusting the log's `/prove` answers.
+//!
+//! Tile `n` holds leaves `n * TILE_WIDTH ..` up to `TILE_WIDTH` of them. A full
+//! tile never changes, so it can be cached forever; only the last tile grows.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    algorithm::HashAlgorithm, decode_hash, leaf::LeafMeta, make_proof_with, InclusionProof,
+};
+
+pub const TILE_WIDTH: usize = 256;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LeafTile {
+    pub index: u64,
+    pub leaves: Vec<String>,
+}
+
+impl LeafTile {
+    pub fn is_full(&self) -> bool {
+        self.leaves.len() == TILE_WIDTH
+    }
+}
+
+/// Payloads of the entries in leaf tile `index`, in order.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EntryTile {
+    pub index: u64,
+    pub payloads: Vec<String>,
+    /// One per payload, when any entry in the tile has a v2 leaf.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub meta: Vec<Option<LeafMeta>>,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TileError {
+    #[error("expected tile {expected}, got tile {found}")]
+    OutOfOrder { expected: u64, found: u64 },
+    #[error("tile {0} is partial but is not the last tile")]
+    PartialTile(u64),
+    #[error("tiles hold {available} leaves, fewer than the tree size {size}")]
+    TooFewLeaves { available: u64, size: u64 },
+    #[error("leaf index {index} is outside a tree of size {size}")]
+    IndexOutOfRange { index: u64, size: u64 },
+    #[error("leaf hashes must be 32 bytes of hex")]
+    InvalidHash,
+}
+
+/// Split hex leaf hashes into tiles, as logd serves them.
+pub fn tile(leaves: &[String], index: u64) -> Option<LeafTile> {
+    let start = usize::try_from(index).ok()?.checked_mul(TILE_WIDTH)?;
+    if start >= leaves.len() {
+        return None;
+    }
+    let end = leaves.len().min(start + TILE_WIDTH);
+    Some(LeafTile {
+        index,
+        leaves: leaves[start..end].to_vec(),
+    })
+}
+
+/// Build the inclusion proof for `index` in the tree of the first `size`
+/// leaves of a log built with `algorithm`. `tiles` must run from tile 0
+/// without gaps. The resulting root is only as trustworthy as the tiles, so
+/// compare it with a verified checkpoint.
+pub fn prove_from_tiles(
+    algorithm: HashAlgorithm,
+    tiles: &[LeafTile],
+    size: u64,
+    index: u64,
+) -> Result<InclusionProof, TileError> {
+    let mut leaves = Vec::new();
+    for (expected, tile) in (0u64..).zip(tiles) {
+        if tile.index != expected {
+            return Err(TileError::OutOfOrder {
+                expected,
+                found: tile.index,
+            });
+        }
+        if !tile.is_full() && expected + 1 != tiles.len() as u64 {
+            return Err(TileError::PartialTile(tile.index));
+        }
+        leaves.extend_from_slice(&tile.leaves);
+    }
+    if (leaves.len() as u64) < size {
+        return Err(TileError::TooFewLeaves {
+            available: leaves.len() as u64,
+            size,
+        });
+    }
+    leaves.truncate(size as usize);
+    prove_from_leaves(algorithm, &leaves, index)
+}
+
+/// Build the inclusion proof for `index` from every leaf hash of the tree.
+pub fn prove_from_leaves(
+    algorithm: HashAlgorithm,
+    leaves: &[String],
+    index: u64,
+) -> Result<InclusionProof, TileError> {
+    let size = leaves.len() as u64;
+    if index >= size {
+        return Err(TileError::IndexOutOfRange { index, size });
+    }
+    let hashes = leaves
+        .iter()
+        .map(|leaf| decode_hash(leaf))
+        .collect::<Option<Vec<_>>>()
+        .ok_or(TileError::InvalidHash)?;
+    make_proof_with(algorithm, &hashes, index as usize)
+        .map_err(|_| TileError::IndexOutOfRange { index, size })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        digest::{DefaultDigest, LogDigest},
+        leaf_hash, make_proof,
+    };
+
+    #[test]
+    fn tiles_rebuild_the_logs_proofs() {
+        let hashes: Vec<[u8; 32]> = (0..600u32).map(|i| leaf_hash(&i.to_be_bytes())).collect();
+        let leaves: Vec<String> = hashes.iter().map(hex::encode).collect();
+        let tiles: Vec<LeafTile> = (0..).map_while(|n| tile(&leaves, n)).collect();
+        assert_eq!(tiles.len(), 3);
+        assert!(tiles[1].is_full() && !tiles[2].is_full());
+
+        for (size, index) in [(600, 599), (600, 0), (300, 257), (1, 0)] {
+            assert_eq!(
+                prove_from_tiles(DefaultDigest::ALGORITHM, &tiles, size, index),
+                Ok(make_proof(&hashes[..size as usize], index as usize).unwrap())
+            );
+        }
+        assert!(matches!(
+            prove_from_tiles(HashAlgorithm::Sha256, &tiles[..2], 600, 1),
+            Err(TileError::TooFewLeaves { .. })
+        ));
+        assert!(matches!(
+            prove_from_tiles(HashAlgorithm::Sha256, &tiles[1..], 300, 1),
+            Err(TileError::OutOfOrder { .. })
+        ));
+        assert!(matches!(
+            prove_from_tiles(HashAlgorithm::Sha256, &tiles, 600, 600),
+            Err(TileError::IndexOutOfRange { .. })
+        ));
+    }
+}
@@ -0,0 +1,407 @@
+//! Export in the C2SP transparency log formats read by Trillian Tessera,
+//! Sunlight and Go's `golang.org/x/mod/sumdb/tlog` tooling: `tlog-tiles`
+//! hash tiles and entry bundles, plus a `tlog-checkpoint` in a signed note.
+//!
+//! Those formats describe an RFC 6962 tree. Subtrees of `2^k` leaves hash the
+//! same in that tree and in this log's, so every tile matches this log's
+//! hashes. Only the root can differ: this log duplicates an odd last node
+//! where RFC 6962 promotes it. The checkpoint therefore carries the RFC 6962
+//! root of the same leaves, and is signed separately from logd's own.
+//! The formats are SHA-256 only, so a log built with another digest has
+//! no tlog export.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::{
+    digest,
+    signer::{verify_signature, Signer, SignerError},
+};
+
+/// Levels of the tree covered by one tile; tiles are `2^8` hashes wide.
+pub const TILE_HEIGHT: u32 = 8;
+const TILE_WIDTH: u64 = 1 << TILE_HEIGHT;
+
+/// Signature type byte of an Ed25519 note key.
+const ED25519: u8 = 0x01;
+
+#[derive(Debug, Error)]
+pub enum TlogError {
+    #[error("entry {index} is {len} bytes; entry bundles hold at most 65535")]
+    EntryTooLarge { index: u64, len: usize },
+    #[error("{0:?} cannot be used as a checkpoint origin or key name")]
+    InvalidName(String),
+    #[error("malformed checkpoint note")]
+    MalformedNote,
+    #[error("malformed verifier key")]
+    MalformedKey,
+    #[error("no valid signature from the verifier key")]
+    Unverified,
+    #[error(transparent)]
+    Signer(#[from] SignerError),
+}
+
+/// One file of a tile export, with its path relative to the export root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TileFile {
+    pub path: String,
+    pub data: Vec<u8>,
+}
+
+/// A checkpoint read back from a verified note.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlogCheckpoint {
+    pub origin: String,
+    pub size: u64,
+    pub root: [u8; 32],
+}
+
+/// Root of the RFC 6962 tree over `leaves`, which must be SHA-256 leaf
+/// hashes as this log computes them.
+pub fn rfc6962_root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    let mut range = CompactRange::default();
+    for leaf in leaves {
+        range.push(*leaf);
+    }
+    range.root()
+}
+
+/// The right edge of an RFC 6962 tree: the roots of its perfect subtrees,
+/// largest first, one per set bit of the size. It grows a leaf at a time
+/// and gives the root without the earlier leaves, so a mirror can check a
+/// log's tree heads while it copies the log.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompactRange {
+    size: u64,
+    nodes: Vec<[u8; 32]>,
+}
+
+impl CompactRange {
+    /// Rebuild a range saved from `size` and `nodes`; `None` when the node
+    /// count does not fit the size.
+    pub fn from_parts(size: u64, nodes: Vec<[u8; 32]>) -> Option<Self> {
+        (nodes.len() == size.count_ones() as usize).then_some(Self { size, nodes })
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn nodes(&self) -> &[[u8; 32]] {
+        &self.nodes
+    }
+
+    pub fn push(&mut self, leaf: [u8; 32]) {
+        let mut node = leaf;
+        let mut size = self.size;
+        while size & 1 == 1 {
+            let left = self.nodes.pop().expect("one node per set bit");
+            node = digest::node_hash::<Sha256>(&left, &node);
+            size >>= 1;
+        }
+        self.nodes.push(node);
+        self.size += 1;
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        let mut nodes = self.nodes.iter().rev();
+        match nodes.next() {
+            Some(last) => nodes.fold(*last, |right, left| {
+                digest::node_hash::<Sha256>(left, &right)
+            }),
+            None => digest::empty_root::<Sha256>(),
+        }
+    }
+}
+
+/// Every hash tile of the tree over `leaves`: at level `L`, tile `N` holds
+/// the hashes of the perfect subtrees of `2^(8L)` leaves numbered
+/// `N * 256 ..`. The last tile of a level is partial unless it is full.
+pub fn hash_tiles(leaves: &[[u8; 32]]) -> Vec<TileFile> {
+    let mut files = Vec::new();
+    let mut nodes = leaves.to_vec();
+    let mut level = 0;
+    while !nodes.is_empty() {
+        for (index, chunk) in (0..).zip(nodes.chunks(TILE_WIDTH as usize)) {
+            files.push(TileFile {
+                path: tile_path(&level.to_string(), index, chunk.len()),
+                data: chunk.concat(),
+            });
+        }
+        for _ in 0..TILE_HEIGHT {
+            nodes = nodes
+                .chunks_exact(2)
+                .map(|pair| digest::node_hash::<Sha256>(&pair[0], &pair[1]))
+                .collect();
+        }
+        level += 1;
+    }
+    files
+}
+
+/// The entry bundles holding `payloads`, 256 to a bundle, each entry
+/// prefixed with its big-endian 16-bit length.
+pub fn entry_bundles<P: AsRef<[u8]>>(payloads: &[P]) -> Result<Vec<TileFile>, TlogError> {
+    let mut files = Vec::new();
+    for (index, chunk) in (0..).zip(payloads.chunks(TILE_WIDTH as usize)) {
+        let mut data = Vec::new();
+        for (offset, payload) in (0..).zip(chunk) {
+            let payload = payload.as_ref();
+            let len = u16::try_from(payload.len()).map_err(|_| TlogError::EntryTooLarge {
+                index: index * TILE_WIDTH + offset,
+                len: payload.len(),
+            })?;
+            data.extend_from_slice(&len.to_be_bytes());
+            data.extend_from_slice(payload);
+        }
+        files.push(TileFile {
+            path: tile_path("entries", index, chunk.len()),
+            data,
+        });
+    }
+    Ok(files)
+}
+
+/// `tile/<level>/<N>[.p/<width>]`, with `N` split into three-digit path
+/// elements of which all but the last carry an `x` prefix.
+fn tile_path(level: &str, index: u64, width: usize) -> String {
+    let mut digits = format!("{:03}", index % 1000);
+    let mut rest = index / 1000;
+    while rest > 0 {
+        digits = format!("x{:03}/{digits}", rest % 1000);
+        rest /= 1000;
+    }
+    let partial = if width < TILE_WIDTH as usize {
+        format!(".p/{width}")
+    } else {
+        String::new()
+    };
+    format!("tile/{level}/{digits}{partial}")
+}
+
+/// The note body of a checkpoint: origin, size and base64 root, one per line.
+pub fn checkpoint_text(origin: &str, size: u64, root: &[u8; 32]) -> String {
+    format!("{origin}\n{size}\n{}\n", STANDARD.encode(root))
+}
+
+/// A checkpoint note signed by `signer` under the key name `origin`.
+pub fn sign_checkpoint(
+    origin: &str,
+    size: u64,
+    root: &[u8; 32],
+    signer: &dyn Signer,
+) -> Result<String, TlogError> {
+    check_name(origin)?;
+    let text = checkpoint_text(origin, size, root);
+    let signature = signer.sign(text.as_bytes())?;
+    let mut blob = key_id(origin, &signer.public_key()).to_vec();
+    blob.extend_from_slice(&signature);
+    Ok(format!(
+        "{text}\n\u{2014} {origin} {}\n",
+        STANDARD.encode(blob)
+    ))
+}
+
+/// The verifier key that checks notes signed by `public_key` under `name`,
+/// as `<name>+<key id>+<base64 key>`.
+pub fn verifier_key(name: &str, public_key: &[u8; 32]) -> Result<String, TlogError> {
+    check_name(name)?;
+    let mut key = vec![ED25519];
+    key.extend_from_slice(public_key);
+    Ok(format!(
+        "{name}+{}+{}",
+        hex::encode(key_id(name, public_key)),
+        STANDARD.encode(key)
+    ))
+}
+
+/// Check a checkpoint note against a verifier key and read it. Signature
+/// lines from other keys are ignored.
+pub fn verify_checkpoint(note: &str, verifier_key: &str) -> Result<TlogCheckpoint, TlogError> {
+    let (name, public_key) = parse_verifier_key(verifier_key)?;
+    let id = key_id(name, &public_key);
+    let (text, signatures) = note.split_once("\n\n").ok_or(TlogError::MalformedNote)?;
+    let text = format!("{text}\n");
+    let verified = signatures.lines().any(|line| {
+        let Some((signer, blob)) = line
+            .strip_prefix("\u{2014} ")
+            .and_then(|rest| rest.split_once(' '))
+        else {
+            return false;
+        };
+        let Ok(blob) = STANDARD.decode(blob) else {
+            return false;
+        };
+        signer == name
+            && blob.len() == 4 + 64
+            && blob[..4] == id
+            && verify_signature(
+                &public_key,
+                text.as_bytes(),
+                blob[4..].try_into().expect("64 bytes"),
+            )
+    });
+    if !verified {
+        return Err(TlogError::Unverified);
+    }
+
+    let mut lines = text.lines();
+    let (Some(origin), Some(size), Some(root)) = (lines.next(), lines.next(), lines.next()) else {
+        return Err(TlogError::MalformedNote);
+    };
+    let size = size.parse().map_err(|_| TlogError::MalformedNote)?;
+    let root = STANDARD
+        .decode(root)
+        .ok()
+        .and_then(|root| root.try_into().ok())
+        .ok_or(TlogError::MalformedNote)?;
+    Ok(TlogCheckpoint {
+        origin: origin.to_string(),
+        size,
+        root,
+    })
+}
+
+fn parse_verifier_key(key: &str) -> Result<(&str, [u8; 32]), TlogError> {
+    let mut parts = key.splitn(3, '+');
+    let (Some(name), Some(id), Some(key)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(TlogError::MalformedKey);
+    };
+    let key = STANDARD.decode(key).map_err(|_| TlogError::MalformedKey)?;
+    let (&ED25519, public_key) = key.split_first().ok_or(TlogError::MalformedKey)? else {
+        return Err(TlogError::MalformedKey);
+    };
+    let public_key: [u8; 32] = public_key.try_into().map_err(|_| TlogError::MalformedKey)?;
+    if hex::decode(id).ok().as_deref() != Some(&key_id(name, &public_key)[..]) {
+        return Err(TlogError::MalformedKey);
+    }
+    Ok((name, public_key))
+}
+
+/// First four bytes of SHA-256 over the name, a newline, the signature type
+/// and the public key.
+fn key_id(name: &str, public_key: &[u8; 32]) -> [u8; 4] {
+    let digest = Sha256::new()
+        .chain_update(name)
+        .chain_update([b'\n', ED25519])
+        .chain_update(public_key)
+        .finalize();
+    digest[..4].try_into().expect("4 bytes")
+}
+
+fn check_name(name: &str) -> Result<(), TlogError> {
+    if name.is_empty() || name.contains('+') || name.contains(char::is_whitespace) {
+        return Err(TlogError::InvalidName(name.to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        digest::{leaf_hash, root},
+        signer::KeyFileSigner,
+    };
+
+    #[test]
+    fn matches_rfc6962_and_signed_note_vectors() {
+        // Test vectors from the certificate-transparency reference code.
+        let inputs: [&[u8]; 8] = [
+            b"",
+            b"\x00",
+            b"\x10",
+            b"\x20\x21",
+            b"\x30\x31",
+            b"\x40\x41\x42\x43",
+            b"\x50\x51\x52\x53\x54\x55\x56\x57",
+            b"\x60\x61\x62\x63\x64\x65\x66\x67\x68\x69\x6a\x6b\x6c\x6d\x6e\x6f",
+        ];
+        let leaves: Vec<[u8; 32]> = inputs
+            .iter()
+            .map(|input| leaf_hash::<Sha256>(input))
+            .collect();
+        for (size, expected) in [
+            (
+                1,
+                "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+            ),
+            (
+                3,
+                "aeb6bcfe274b70a14fb067a5e5578264db0fa9b51af5e0ba159158f329e06e77",
+            ),
+            (
+                8,
+                "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328",
+            ),
+        ] {
+            assert_eq!(hex::encode(rfc6962_root(&leaves[..size])), expected);
+        }
+        assert_eq!(rfc6962_root(&leaves), root::<Sha256>(&leaves));
+        let mut range = CompactRange::default();
+        leaves[..5].iter().for_each(|leaf| range.push(*leaf));
+        let mut resumed = CompactRange::from_parts(5, range.nodes().to_vec()).unwrap();
+        leaves[5..].iter().for_each(|leaf| resumed.push(*leaf));
+        assert_eq!(resumed.root(), rfc6962_root(&leaves));
+        assert!(CompactRange::from_parts(7, range.nodes().to_vec()).is_none());
+        assert_ne!(rfc6962_root(&leaves[..3]), root::<Sha256>(&leaves[..3]));
+
+        // The signed note example from golang.org/x/mod/sumdb/note.
+        let seed = STANDARD
+            .decode("AYEKFALVFGyNhPJEMzD1QIDr+Y7hfZx09iUvxdXHKDFz")
+            .unwrap();
+        let signer = KeyFileSigner::from_seed(seed[1..].try_into().unwrap());
+        assert_eq!(
+            verifier_key("PeterNeumann", &signer.public_key()).unwrap(),
+            "PeterNeumann+c74f20a3+ARpc2QcUPDhMQegwxbzhKqiBfsVkmqq/LDE4izWy10TW"
+        );
+
+        let root = rfc6962_root(&leaves[..3]);
+        let note = sign_checkpoint("example.com/log", 3, &root, &signer).unwrap();
+        let key = verifier_key("example.com/log", &signer.public_key()).unwrap();
+        assert_eq!(
+            verify_checkpoint(&note, &key).unwrap(),
+            TlogCheckpoint {
+                origin: "example.com/log".into(),
+                size: 3,
+                root,
+            }
+        );
+        let forged = note.replacen("\n3\n", "\n4\n", 1);
+        assert!(matches!(
+            verify_checkpoint(&forged, &key),
+            Err(TlogError::Unverified)
+        ));
+    }
+
+    #[test]
+    fn tiles_follow_the_tlog_tiles_layout() {
+        let leaves: Vec<[u8; 32]> = (0..70_000u32)
+            .map(|i| leaf_hash::<Sha256>(&i.to_be_bytes()))
+            .collect();
+        let tiles = hash_tiles(&leaves);
+        let paths: Vec<&str> = tiles.iter().map(|tile| tile.path.as_str()).collect();
+        // 273 full level-0 tiles and one of 112 hashes; one level-1 tile of
+        // 273 subtree hashes is two tiles, the second partial.
+        assert_eq!(paths.len(), 274 + 2 + 1);
+        assert_eq!(paths[0], "tile/0/000");
+        assert_eq!(paths[273], "tile/0/273.p/112");
+        assert_eq!(
+            &paths[274..],
+            ["tile/1/000", "tile/1/001.p/17", "tile/2/000.p/1"]
+        );
+        assert_eq!(tiles[1].data[..32], leaves[256]);
+        assert_eq!(tiles[274].data[..32], root::<Sha256>(&leaves[..256]));
+        assert_eq!(tiles[276].data, root::<Sha256>(&leaves[..65_536]));
+
+        assert_eq!(tile_path("0", 1_234_067, 256), "tile/0/x001/x234/067");
+        let bundles = entry_bundles(&[&b"ab"[..], b""]).unwrap();
+        assert_eq!(bundles[0].path, "tile/entries/000.p/2");
+        assert_eq!(bundles[0].data, b"\x00\x02ab\x00\x00");
+        assert!(matches!(
+            entry_bundles(&[vec![0; 70_000]]),
+            Err(TlogError::EntryTooLarge { index: 0, .. })
+        ));
+    }
+}
@@ -0,0 +1,207 @@
+use std::{path::PathBuf, time::Duration};
+
+use clap::Parser;
+use reality_config::Layered;
+use serde::Deserialize;
+
+/// Largest batch logd accepts in one `/append/batch` request.
+const MAX_BATCH: usize = 1000;
+
+/// Command-line flags. Anything left unset falls back to the config file and
+/// then to defaults.
+#[derive(Debug, Parser)]
+#[command(
+    name = "reality-ct-import",
+    version,
+    about = "Mirrors a Certificate Transparency log into a RealityLog"
+)]
+pub struct Cli {
+    /// Path to a TOML config file.
+    #[arg(long, env = "REALITY_CT_IMPORT_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Base URL of the CT log, without `/ct/v1`.
+    #[arg(long)]
+    pub ct_url: Option<String>,
+    /// Name recorded in every entry and used for the state file; defaults to
+    /// the CT URL's host and path.
+    #[arg(long)]
+    pub name: Option<String>,
+    /// Base URL of the logd instance to append to.
+    #[arg(long, env = "REALITY_CT_IMPORT_LOG_URL")]
+    pub log_url: Option<String>,
+    /// Bearer token sent with every append.
+    #[arg(long, env = "REALITY_CT_IMPORT_TOKEN")]
+    pub token: Option<String>,
+    /// Directory holding the import state.
+    #[arg(long, env = "REALITY_CT_IMPORT_DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
+    /// CT index to start at on the first run. Anything but 0 means the
+    /// tree heads cannot be checked.
+    #[arg(long)]
+    pub start: Option<u64>,
+    /// Most entries requested from the CT log and appended at once.
+    #[arg(long)]
+    pub batch_size: Option<usize>,
+    /// Append only each entry's leaf hash instead of the entry itself.
+    #[arg(long)]
+    pub hashes_only: bool,
+    /// Keep each entry's `extra_data` (its certificate chain) as well.
+    #[arg(long, conflicts_with = "hashes_only")]
+    pub extra_data: bool,
+    /// Keep running and import new entries as the CT log grows.
+    #[arg(long)]
+    pub follow: bool,
+    /// Seconds between tree head checks with `--follow`.
+    #[arg(long)]
+    pub poll_secs: Option<u64>,
+}
+
+/// Fully resolved importer configuration.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub ct_url: String,
+    pub name: Option<String>,
+    pub log_url: String,
+    pub token: Option<String>,
+    pub data_dir: PathBuf,
+    pub start: u64,
+    pub batch_size: usize,
+    pub hashes_only: bool,
+    pub extra_data: bool,
+    pub follow: bool,
+    pub poll_secs: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            ct_url: String::new(),
+            name: None,
+            log_url: "http://127.0.0.1:8080".into(),
+            token: None,
+            data_dir: PathBuf::from("data"),
+            start: 0,
+            batch_size: 256,
+            hashes_only: false,
+            extra_data: false,
+            follow: false,
+            poll_secs: 60,
+        }
+    }
+}
+
+impl Layered<Cli> for Config {
+    fn file(cli: &Cli) -> Option<PathBuf> {
+        cli.config.clone()
+    }
+
+    fn apply(&mut self, cli: Cli) -> anyhow::Result<()> {
+        self.hashes_only |= cli.hashes_only;
+        self.extra_data |= cli.extra_data;
+        self.follow |= cli.follow;
+        if let Some(ct_url) = cli.ct_url {
+            self.ct_url = ct_url;
+        }
+        if let Some(name) = cli.name {
+            self.name = Some(name);
+        }
+        if let Some(log_url) = cli.log_url {
+            self.log_url = log_url;
+        }
+        if let Some(token) = cli.token {
+            self.token = Some(token);
+        }
+        if let Some(data_dir) = cli.data_dir {
+            self.data_dir = data_dir;
+        }
+        if let Some(start) = cli.start {
+            self.start = start;
+        }
+        if let Some(batch_size) = cli.batch_size {
+            self.batch_size = batch_size;
+        }
+        if let Some(poll_secs) = cli.poll_secs {
+            self.poll_secs = poll_secs;
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(!self.ct_url.is_empty(), "ct_url is required");
+        anyhow::ensure!(
+            (1..=MAX_BATCH).contains(&self.batch_size),
+            "batch_size must be between 1 and {MAX_BATCH}"
+        );
+        anyhow::ensure!(
+            !(self.hashes_only && self.extra_data),
+            "extra_data cannot be kept with hashes_only"
+        );
+        anyhow::ensure!(self.poll_secs > 0, "poll_secs must be positive");
+        let name = self.name();
+        anyhow::ensure!(
+            !name.is_empty() && name.chars().all(name_char),
+            "name {name:?} may only hold letters, digits, '.', '-' and '_'"
+        );
+        Ok(())
+    }
+}
+
+impl Config {
+    /// The configured name, or the CT URL's host and path with every `/`
+    /// or other unusable character turned into `_`.
+    pub fn name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            let url = self.ct_url.trim_end_matches('/');
+            let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+            url.chars()
+                .map(|c| if name_char(c) { c } else { '_' })
+                .collect()
+        })
+    }
+
+    /// The CT API endpoint `path`, such as `get-sth`.
+    pub fn ct_endpoint(&self, path: &str) -> String {
+        format!("{}/ct/v1/{path}", self.ct_url.trim_end_matches('/'))
+    }
+
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_secs)
+    }
+}
+
+fn name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_default_to_the_ct_url() {
+        let mut config: Config = toml::from_str(
+            r#"
+            ct_url = "https://ct.googleapis.com:443/logs/us1/argon2025h1/"
+            batch_size = 100
+            "#,
+        )
+        .expect("config");
+        config
+            .apply(Cli::parse_from(["reality-ct-import", "--hashes-only"]))
+            .unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.name(), "ct.googleapis.com_443_logs_us1_argon2025h1");
+        assert_eq!(
+            config.ct_endpoint("get-sth"),
+            "https://ct.googleapis.com:443/logs/us1/argon2025h1/ct/v1/get-sth"
+        );
+        assert!(config.hashes_only);
+
+        config.extra_data = true;
+        assert!(config.validate().is_err());
+        config.extra_data = false;
+        config.name = Some("argon/2025".into());
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,140 @@
+//! The two RFC 6962 endpoints the importer reads, and the entries it appends
+//! for what they return.
+
+use anyhow::Context;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reality_core::{leaf_hash, BatchEntry};
+use serde::{Deserialize, Serialize};
+
+/// `get-sth`. The signature is not checked; see the README.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SignedTreeHead {
+    pub tree_size: u64,
+    pub timestamp: u64,
+    pub sha256_root_hash: String,
+}
+
+impl SignedTreeHead {
+    pub fn root(&self) -> anyhow::Result<[u8; 32]> {
+        STANDARD
+            .decode(&self.sha256_root_hash)
+            .ok()
+            .and_then(|root| root.try_into().ok())
+            .context("tree head root is not 32 bytes of base64")
+    }
+}
+
+/// `get-entries`; logs may return fewer entries than asked for.
+#[derive(Debug, Deserialize)]
+pub struct Entries {
+    pub entries: Vec<CtEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CtEntry {
+    /// Base64 `MerkleTreeLeaf`.
+    pub leaf_input: String,
+    /// Base64 certificate chain.
+    #[serde(default)]
+    pub extra_data: String,
+}
+
+/// What is kept of each CT entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keep {
+    /// Only the leaf hash.
+    Hash,
+    /// The leaf, and the chain too with `extra_data`.
+    Entry { extra_data: bool },
+}
+
+/// The payload appended for one CT entry. `index` is its position in the CT
+/// log and `leaf_hash` its RFC 6962 leaf hash there.
+#[derive(Serialize)]
+struct Mirrored<'a> {
+    source: &'static str,
+    log: &'a str,
+    index: u64,
+    leaf_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    leaf_input: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extra_data: Option<&'a str>,
+}
+
+impl CtEntry {
+    /// The entry's RFC 6962 leaf hash, which uses the same `0x00` prefix as
+    /// this log's.
+    pub fn leaf_hash(&self) -> anyhow::Result<[u8; 32]> {
+        let leaf = STANDARD
+            .decode(&self.leaf_input)
+            .context("leaf_input is not base64")?;
+        Ok(leaf_hash(&leaf))
+    }
+
+    /// The batch entry for CT index `index` of the log `name`, keyed by that
+    /// index so a resent batch never appends it twice.
+    pub fn to_batch_entry(
+        &self,
+        name: &str,
+        index: u64,
+        leaf: &[u8; 32],
+        keep: Keep,
+    ) -> BatchEntry {
+        let (leaf_input, extra_data) = match keep {
+            Keep::Hash => (None, None),
+            Keep::Entry { extra_data } => (
+                Some(self.leaf_input.as_str()),
+                extra_data.then_some(self.extra_data.as_str()),
+            ),
+        };
+        let payload = serde_json::to_string(&Mirrored {
+            source: "ct",
+            log: name,
+            index,
+            leaf_hash: hex::encode(leaf),
+            leaf_input,
+            extra_data,
+        })
+        .expect("entry serializes");
+        BatchEntry {
+            payload,
+            idempotency_key: Some(format!("ct:{name}:{index}")),
+            labels: Default::default(),
+            signature: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_keep_the_ct_index_and_leaf_hash() {
+        let entries: Entries =
+            serde_json::from_str(r#"{"entries":[{"leaf_input":"AAABlwY=","extra_data":"AAAA"}]}"#)
+                .unwrap();
+        let entry = &entries.entries[0];
+        let leaf = entry.leaf_hash().unwrap();
+        assert_eq!(leaf, leaf_hash(&[0, 0, 1, 0x97, 6]));
+
+        let hash = entry.to_batch_entry("argon", 41, &leaf, Keep::Hash);
+        assert_eq!(hash.idempotency_key.as_deref(), Some("ct:argon:41"));
+        let payload: serde_json::Value = serde_json::from_str(&hash.payload).unwrap();
+        assert_eq!(payload["index"], 41);
+        assert_eq!(payload["leaf_hash"], hex::encode(leaf));
+        assert!(payload.get("leaf_input").is_none());
+
+        let full = entry.to_batch_entry("argon", 41, &leaf, Keep::Entry { extra_data: true });
+        let payload: serde_json::Value = serde_json::from_str(&full.payload).unwrap();
+        assert_eq!(payload["leaf_input"], "AAABlwY=");
+        assert_eq!(payload["extra_data"], "AAAA");
+
+        let sth: SignedTreeHead = serde_json::from_str(
+            r#"{"tree_size":7,"timestamp":1,"sha256_root_hash":"AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=","tree_head_signature":"BAMA"}"#,
+        )
+        .unwrap();
+        assert_eq!(sth.root().unwrap(), [0; 32]);
+    }
+}
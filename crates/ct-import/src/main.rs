@@ -0,0 +1,229 @@
+//! `reality-ct-import`: mirrors a Certificate Transparency log into logd.
+//!
+//! Entries are read with `get-entries` and appended in `/append/batch`
+//! requests keyed by their CT index, so a batch resent after a timeout or a
+//! crash is never appended twice. An import that starts at index 0 keeps the
+//! compact range of the CT tree, and every tree head it catches up with must
+//! have the root of the entries it copied.
+
+mod config;
+mod ct;
+
+use std::{fs::File, io::Write, path::PathBuf, time::Duration};
+
+use anyhow::Context;
+use clap::Parser;
+use reality_client::Client;
+use reality_config::Layered;
+use reality_core::{tlog::CompactRange, BatchEntry};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    config::{Cli, Config},
+    ct::{Entries, Keep, SignedTreeHead},
+};
+
+/// Longest pause between attempts while either log is unavailable.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Import progress, saved after every batch.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct State {
+    /// The next CT index to import.
+    next: u64,
+    /// Compact range of the CT tree over entries `0..next`, as hex; absent
+    /// when the import did not start at 0.
+    #[serde(default)]
+    range: Option<Vec<String>>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let config = Config::load(Cli::parse())?;
+    let name = config.name();
+    std::fs::create_dir_all(&config.data_dir).context("create data dir")?;
+    let state_path = config.data_dir.join(format!("ct-import-{name}.json"));
+    let mut state = load_state(&state_path)?.unwrap_or_else(|| State {
+        next: config.start,
+        range: (config.start == 0).then(Vec::new),
+    });
+    let mut range = match &state.range {
+        Some(nodes) => Some(restore_range(state.next, nodes)?),
+        None => {
+            warn!(
+                start = state.next,
+                "not starting at 0; tree heads cannot be checked"
+            );
+            None
+        }
+    };
+
+    let mut builder = Client::builder(&config.log_url);
+    if let Some(token) = &config.token {
+        builder = builder.token(token);
+    }
+    let client = builder.build()?;
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let keep = if config.hashes_only {
+        Keep::Hash
+    } else {
+        Keep::Entry {
+            extra_data: config.extra_data,
+        }
+    };
+    info!(log = %name, next = state.next, "importing");
+
+    loop {
+        let sth: SignedTreeHead = fetch(&http, &config.ct_endpoint("get-sth")).await;
+        if sth.tree_size < state.next {
+            warn!(
+                tree_size = sth.tree_size,
+                next = state.next,
+                "tree head is behind the import; skipping it"
+            );
+        }
+        while state.next < sth.tree_size {
+            let end = (state.next + config.batch_size as u64).min(sth.tree_size) - 1;
+            let url = format!(
+                "{}?start={}&end={end}",
+                config.ct_endpoint("get-entries"),
+                state.next
+            );
+            let Entries { mut entries } = fetch(&http, &url).await;
+            entries.truncate((end + 1 - state.next) as usize);
+            if entries.is_empty() {
+                warn!(start = state.next, "CT log returned no entries; retrying");
+                tokio::time::sleep(config.poll_interval()).await;
+                continue;
+            }
+
+            let mut next_range = range.clone();
+            let mut batch = Vec::with_capacity(entries.len());
+            for (index, entry) in (state.next..).zip(&entries) {
+                let leaf = entry
+                    .leaf_hash()
+                    .with_context(|| format!("CT entry {index}"))?;
+                if let Some(range) = &mut next_range {
+                    range.push(leaf);
+                }
+                batch.push(entry.to_batch_entry(&name, index, &leaf, keep));
+            }
+            let duplicates = append(&client, batch).await;
+            state.next += entries.len() as u64;
+            range = next_range;
+            state.range = range
+                .as_ref()
+                .map(|range| range.nodes().iter().map(hex::encode).collect());
+            save_state(&state_path, &state)?;
+            info!(
+                entries = entries.len(),
+                duplicates,
+                next = state.next,
+                "imported batch"
+            );
+        }
+
+        if let Some(range) = range.as_ref().filter(|range| range.size() == sth.tree_size) {
+            anyhow::ensure!(
+                range.root() == sth.root()?,
+                "CT entries 0..{} do not match the tree head root {}",
+                sth.tree_size,
+                sth.sha256_root_hash
+            );
+            info!(
+                tree_size = sth.tree_size,
+                timestamp = sth.timestamp,
+                "tree head matches the imported entries"
+            );
+        }
+        if !config.follow {
+            return Ok(());
+        }
+        tokio::time::sleep(config.poll_interval()).await;
+    }
+}
+
+/// GET `url` as JSON until it succeeds.
+async fn fetch<T: DeserializeOwned>(http: &reqwest::Client, url: &str) -> T {
+    let mut backoff = Duration::from_millis(250);
+    loop {
+        let result = async {
+            http.get(url)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<T>()
+                .await
+        }
+        .await;
+        match result {
+            Ok(value) => return value,
+            Err(err) => {
+                warn!(%url, %err, retry_in = ?backoff, "CT request failed");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+/// Send one batch until logd takes it; returns how many entries it already
+/// held. Every entry is keyed, so resending is always safe.
+async fn append(client: &Client, entries: Vec<BatchEntry>) -> usize {
+    let mut backoff = Duration::from_millis(250);
+    loop {
+        match client.append_batch(entries.clone()).await {
+            Ok(response) => {
+                return response
+                    .entries
+                    .iter()
+                    .filter(|entry| entry.duplicate)
+                    .count()
+            }
+            Err(err) => {
+                warn!(%err, retry_in = ?backoff, "append failed");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+fn restore_range(size: u64, nodes: &[String]) -> anyhow::Result<CompactRange> {
+    let nodes = nodes
+        .iter()
+        .map(|node| {
+            hex::decode(node)
+                .ok()
+                .and_then(|node| <[u8; 32]>::try_from(node).ok())
+        })
+        .collect::<Option<Vec<_>>>()
+        .context("saved range holds a malformed hash")?;
+    CompactRange::from_parts(size, nodes).context("saved range does not fit its size")
+}
+
+fn load_state(path: &PathBuf) -> anyhow::Result<Option<State>> {
+    match std::fs::read_to_string(path) {
+        Ok(raw) => serde_json::from_str(&raw)
+            .map(Some)
+            .with_context(|| format!("parse state {}", path.display())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("read state {}", path.display())),
+    }
+}
+
+/// Rewrite the state through a temporary file, so a crash leaves either the
+/// old or the new state.
+fn save_state(path: &PathBuf, state: &State) -> anyhow::Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    let mut file = File::create(&tmp).with_context(|| format!("create {}", tmp.display()))?;
+    file.write_all(&serde_json::to_vec_pretty(state)?)?;
+    file.sync_all()
+        .with_context(|| format!("write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("replace {}", path.display()))
+}
@@ -0,0 +1,70 @@
+/*
+ * Verify an inclusion proof from logd against its signed checkpoint.
+ *
+ *   cargo build -p reality-core-ffi
+ *   cc crates/ffi/examples/verify.c -Icrates/ffi/include \
+ *      -Ltarget/debug -lreality_core_ffi -o verify
+ *   curl -s localhost:8080/checkpoint > checkpoint.json
+ *   curl -s localhost:8080/prove/0/bin > proof.bin
+ *   LD_LIBRARY_PATH=target/debug ./verify <public-key-hex> checkpoint.json proof.bin
+ */
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "reality_core.h"
+
+static unsigned char *slurp(const char *path, size_t *len) {
+  FILE *file = fopen(path, "rb");
+  if (!file) return NULL;
+  fseek(file, 0, SEEK_END);
+  long size = ftell(file);
+  rewind(file);
+  unsigned char *data = malloc((size_t)size + 1);
+  *len = fread(data, 1, (size_t)size, file);
+  data[*len] = '\0';
+  fclose(file);
+  return data;
+}
+
+int main(int argc, char **argv) {
+  if (argc != 4) {
+    fprintf(stderr, "usage: %s <public-key-hex> <checkpoint.json> <proof.bin>\n", argv[0]);
+    return 2;
+  }
+  if (rl_abi_version() != RL_ABI_VERSION) {
+    fprintf(stderr, "library ABI %u does not match header %u\n", rl_abi_version(), RL_ABI_VERSION);
+    return 2;
+  }
+
+  uint8_t key[32];
+  for (int i = 0; i < 32; i++) {
+    if (sscanf(argv[1] + 2 * i, "%2hhx", &key[i]) != 1) {
+      fprintf(stderr, "public key must be 64 hex characters\n");
+      return 2;
+    }
+  }
+
+  size_t json_len, proof_len;
+  unsigned char *json = slurp(argv[2], &json_len);
+  unsigned char *proof = slurp(argv[3], &proof_len);
+  if (!json || !proof) {
+    perror("read");
+    return 2;
+  }
+
+  RlCheckpointInfo head;
+  RlStatus status = rl_verify_checkpoint((const char *)json, key, &head);
+  if (status != RL_STATUS_OK) {
+    fprintf(stderr, "checkpoint: %s\n", rl_status_message(status));
+    return 1;
+  }
+  status = rl_verify_inclusion(proof, proof_len, NULL, head.root, head.size);
+  if (status != RL_STATUS_OK) {
+    fprintf(stderr, "proof: %s\n", rl_status_message(status));
+    return 1;
+  }
+  printf("proof verified against checkpoint of size %llu\n", (unsigned long long)head.size);
+  free(json);
+  free(proof);
+  return 0;
+}
@@ -0,0 +1,164 @@
+/* RealityLog core verifier, C ABI. */
+
+#ifndef REALITY_CORE_H
+#define REALITY_CORE_H
+
+/* Generated by cbindgen from crates/ffi/src/lib.rs. Do not edit; run `REALITY_FFI_BLESS=1 cargo test -p reality-core-ffi` to regenerate. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Bumped whenever a signature or struct layout in this header changes.
+ */
+#define RL_ABI_VERSION 1
+
+/**
+ * Values accepted where a function takes a hash algorithm.
+ */
+typedef enum {
+  RL_HASH_ALGORITHM_SHA256 = 0,
+  RL_HASH_ALGORITHM_SHA3_256 = 1,
+  RL_HASH_ALGORITHM_BLAKE3 = 2,
+  RL_HASH_ALGORITHM_SHA512_256 = 3,
+} RlHashAlgorithm;
+
+/**
+ * Result of every `rl_*` call.
+ */
+typedef enum {
+  /**
+   * The input verified.
+   */
+  RL_STATUS_OK = 0,
+  /**
+   * The input was well formed but did not verify.
+   */
+  RL_STATUS_INVALID = 1,
+  /**
+   * The input could not be parsed.
+   */
+  RL_STATUS_MALFORMED = 2,
+  /**
+   * A required pointer was null.
+   */
+  RL_STATUS_NULL_ARGUMENT = 3,
+} RlStatus;
+
+/**
+ * What a binary proof claims, filled in once it has been decoded.
+ */
+typedef struct {
+  uint64_t index;
+  uint64_t size;
+  /**
+   * An `RlHashAlgorithm` value.
+   */
+  uint32_t algorithm;
+  uint8_t leaf[32];
+  uint8_t root[32];
+} RlProofInfo;
+
+/**
+ * The tree head a verified checkpoint commits to.
+ */
+typedef struct {
+  uint64_t size;
+  uint8_t root[32];
+} RlCheckpointInfo;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * The ABI version the library was built with; compare it with
+ * `RL_ABI_VERSION` from the header you compiled against.
+ */
+uint32_t rl_abi_version(void);
+
+/**
+ * A static, NUL-terminated description of `status`. Never free it.
+ */
+const char *rl_status_message(RlStatus status);
+
+/**
+ * Write the 32-byte leaf hash of `data`, as logd computes it on append,
+ * to `out_hash`. `algorithm` is an `RlHashAlgorithm` value.
+ *
+ * # Safety
+ *
+ * `data` must point to `len` readable bytes (it may be null when `len` is
+ * 0) and `out_hash` to 32 writable bytes.
+ */
+RlStatus rl_leaf_hash(uint32_t algorithm, const uint8_t *data, size_t len, uint8_t *out_hash);
+
+/**
+ * Verify a proof in the binary encoding served by logd's `/prove/N/bin`:
+ * its leaf and path must lead to its own root. This alone does not make the
+ * root trustworthy; prefer `rl_verify_inclusion`, or compare `out_info`'s
+ * root and size with a verified checkpoint.
+ *
+ * # Safety
+ *
+ * `proof` must point to `len` readable bytes. `out_info` must be null or
+ * point to a writable `RlProofInfo`.
+ */
+RlStatus rl_verify_proof_bytes(const uint8_t *proof, size_t len, RlProofInfo *out_info);
+
+/**
+ * Verify a binary proof against a tree head you already trust: the proof
+ * must verify, end at `root` (32 bytes) for a tree of `size` leaves and,
+ * when `leaf` is not null, prove that 32-byte leaf hash.
+ *
+ * # Safety
+ *
+ * `proof` must point to `len` readable bytes, `root` to 32 bytes, and
+ * `leaf` must be null or point to 32 bytes.
+ */
+RlStatus rl_verify_inclusion(const uint8_t *proof,
+                             size_t len,
+                             const uint8_t *leaf,
+                             const uint8_t *root,
+                             uint64_t size);
+
+/**
+ * Verify a signed checkpoint, the JSON body of logd's `/checkpoint`, against
+ * the log's 32-byte ed25519 `public_key`. On success `out_info` receives the
+ * size and root, which inclusion proofs can then be checked against.
+ *
+ * # Safety
+ *
+ * `checkpoint_json` must be a NUL-terminated string and `public_key` must
+ * point to 32 bytes. `out_info` must be null or point to a writable
+ * `RlCheckpointInfo`.
+ */
+RlStatus rl_verify_checkpoint(const char *checkpoint_json,
+                              const uint8_t *public_key,
+                              RlCheckpointInfo *out_info);
+
+/**
+ * Verify a checkpoint signature from its parts, for callers that do not
+ * handle JSON: the log's 64-byte ed25519 `signature` by the 32-byte
+ * `public_key` over the checkpoint for `size`, the 32-byte `root` and the
+ * decimal `timestamp_nanos` string.
+ *
+ * # Safety
+ *
+ * `root` and `public_key` must point to 32 bytes, `signature` to 64 bytes,
+ * and `timestamp_nanos` must be a NUL-terminated string.
+ */
+RlStatus rl_verify_checkpoint_signature(uint64_t size,
+                                        const uint8_t *root,
+                                        const char *timestamp_nanos,
+                                        const uint8_t *public_key,
+                                        const uint8_t *signature);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* REALITY_CORE_H */
@@ -0,0 +1,401 @@
+//! Stable C ABI for the RealityLog core verifier.
+//!
+//! C, C++ and Go (through cgo) services link `libreality_core_ffi` and
+//! include `include/reality_core.h`, which is generated from this file. Every
+//! function returns an [`RlStatus`]. Hashes and keys are raw bytes, not hex,
+//! and their pointers must cover the documented length. Output structs are
+//! optional: pass null to skip them. The functions keep no state and are
+//! safe to call from any thread.
+
+use std::{
+    ffi::{c_char, CStr},
+    slice,
+};
+
+use reality_core::{
+    algorithm::HashAlgorithm,
+    checkpoint::{Checkpoint, SignedCheckpoint},
+    signer::verify_signature,
+    verify, InclusionProof, VerifyRequest,
+};
+
+/// Bumped whenever a signature or struct layout in this header changes.
+pub const RL_ABI_VERSION: u32 = 1;
+
+/// Result of every `rl_*` call.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RlStatus {
+    /// The input verified.
+    Ok = 0,
+    /// The input was well formed but did not verify.
+    Invalid = 1,
+    /// The input could not be parsed.
+    Malformed = 2,
+    /// A required pointer was null.
+    NullArgument = 3,
+}
+
+/// Values accepted where a function takes a hash algorithm.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RlHashAlgorithm {
+    Sha256 = 0,
+    Sha3_256 = 1,
+    Blake3 = 2,
+    Sha512_256 = 3,
+}
+
+/// What a binary proof claims, filled in once it has been decoded.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RlProofInfo {
+    pub index: u64,
+    pub size: u64,
+    /// An `RlHashAlgorithm` value.
+    pub algorithm: u32,
+    pub leaf: [u8; 32],
+    pub root: [u8; 32],
+}
+
+/// The tree head a verified checkpoint commits to.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RlCheckpointInfo {
+    pub size: u64,
+    pub root: [u8; 32],
+}
+
+/// The ABI version the library was built with; compare it with
+/// `RL_ABI_VERSION` from the header you compiled against.
+#[no_mangle]
+pub extern "C" fn rl_abi_version() -> u32 {
+    RL_ABI_VERSION
+}
+
+/// A static, NUL-terminated description of `status`. Never free it.
+#[no_mangle]
+pub extern "C" fn rl_status_message(status: RlStatus) -> *const c_char {
+    let message: &'static CStr = match status {
+        RlStatus::Ok => c"ok",
+        RlStatus::Invalid => c"did not verify",
+        RlStatus::Malformed => c"malformed input",
+        RlStatus::NullArgument => c"required pointer was null",
+    };
+    message.as_ptr()
+}
+
+/// Write the 32-byte leaf hash of `data`, as logd computes it on append,
+/// to `out_hash`. `algorithm` is an `RlHashAlgorithm` value.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes (it may be null when `len` is
+/// 0) and `out_hash` to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rl_leaf_hash(
+    algorithm: u32,
+    data: *const u8,
+    len: usize,
+    out_hash: *mut u8,
+) -> RlStatus {
+    let Some(algorithm) = algorithm_from(algorithm) else {
+        return RlStatus::Malformed;
+    };
+    let Some(data) = bytes(data, len) else {
+        return RlStatus::NullArgument;
+    };
+    if out_hash.is_null() {
+        return RlStatus::NullArgument;
+    }
+    let hash = algorithm.leaf_hash(data);
+    out_hash.copy_from_nonoverlapping(hash.as_ptr(), hash.len());
+    RlStatus::Ok
+}
+
+/// Verify a proof in the binary encoding served by logd's `/prove/N/bin`:
+/// its leaf and path must lead to its own root. This alone does not make the
+/// root trustworthy; prefer `rl_verify_inclusion`, or compare `out_info`'s
+/// root and size with a verified checkpoint.
+///
+/// # Safety
+///
+/// `proof` must point to `len` readable bytes. `out_info` must be null or
+/// point to a writable `RlProofInfo`.
+#[no_mangle]
+pub unsafe extern "C" fn rl_verify_proof_bytes(
+    proof: *const u8,
+    len: usize,
+    out_info: *mut RlProofInfo,
+) -> RlStatus {
+    let Some(proof) = bytes(proof, len) else {
+        return RlStatus::NullArgument;
+    };
+    let Ok(proof) = InclusionProof::from_bytes(proof) else {
+        return RlStatus::Malformed;
+    };
+    if let Some(out_info) = out_info.as_mut() {
+        *out_info = proof_info(&proof);
+    }
+    status(verify(&VerifyRequest::from(&proof)).valid)
+}
+
+/// Verify a binary proof against a tree head you already trust: the proof
+/// must verify, end at `root` (32 bytes) for a tree of `size` leaves and,
+/// when `leaf` is not null, prove that 32-byte leaf hash.
+///
+/// # Safety
+///
+/// `proof` must point to `len` readable bytes, `root` to 32 bytes, and
+/// `leaf` must be null or point to 32 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rl_verify_inclusion(
+    proof: *const u8,
+    len: usize,
+    leaf: *const u8,
+    root: *const u8,
+    size: u64,
+) -> RlStatus {
+    let mut info = RlProofInfo {
+        index: 0,
+        size: 0,
+        algorithm: 0,
+        leaf: [0; 32],
+        root: [0; 32],
+    };
+    let Some(root) = hash(root) else {
+        return RlStatus::NullArgument;
+    };
+    let verified = rl_verify_proof_bytes(proof, len, &mut info);
+    if verified != RlStatus::Ok {
+        return verified;
+    }
+    let leaf_matches = leaf.is_null() || hash(leaf) == Some(info.leaf);
+    status(leaf_matches && info.root == root && info.size == size)
+}
+
+/// Verify a signed checkpoint, the JSON body of logd's `/checkpoint`, against
+/// the log's 32-byte ed25519 `public_key`. On success `out_info` receives the
+/// size and root, which inclusion proofs can then be checked against.
+///
+/// # Safety
+///
+/// `checkpoint_json` must be a NUL-terminated string and `public_key` must
+/// point to 32 bytes. `out_info` must be null or point to a writable
+/// `RlCheckpointInfo`.
+#[no_mangle]
+pub unsafe extern "C" fn rl_verify_checkpoint(
+    checkpoint_json: *const c_char,
+    public_key: *const u8,
+    out_info: *mut RlCheckpointInfo,
+) -> RlStatus {
+    if checkpoint_json.is_null() {
+        return RlStatus::NullArgument;
+    }
+    let Some(public_key) = hash(public_key) else {
+        return RlStatus::NullArgument;
+    };
+    let Ok(signed) =
+        serde_json::from_slice::<SignedCheckpoint>(CStr::from_ptr(checkpoint_json).to_bytes())
+    else {
+        return RlStatus::Malformed;
+    };
+    let Some(root) = decode::<32>(&signed.checkpoint.root) else {
+        return RlStatus::Malformed;
+    };
+    if !signed.verify_with(&hex::encode(public_key)) {
+        return RlStatus::Invalid;
+    }
+    if let Some(out_info) = out_info.as_mut() {
+        *out_info = RlCheckpointInfo {
+            size: signed.checkpoint.size,
+            root,
+        };
+    }
+    RlStatus::Ok
+}
+
+/// Verify a checkpoint signature from its parts, for callers that do not
+/// handle JSON: the log's 64-byte ed25519 `signature` by the 32-byte
+/// `public_key` over the checkpoint for `size`, the 32-byte `root` and the
+/// decimal `timestamp_nanos` string.
+///
+/// # Safety
+///
+/// `root` and `public_key` must point to 32 bytes, `signature` to 64 bytes,
+/// and `timestamp_nanos` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rl_verify_checkpoint_signature(
+    size: u64,
+    root: *const u8,
+    timestamp_nanos: *const c_char,
+    public_key: *const u8,
+    signature: *const u8,
+) -> RlStatus {
+    let (Some(root), Some(public_key)) = (hash(root), hash(public_key)) else {
+        return RlStatus::NullArgument;
+    };
+    if timestamp_nanos.is_null() || signature.is_null() {
+        return RlStatus::NullArgument;
+    }
+    let Ok(timestamp_nanos) = CStr::from_ptr(timestamp_nanos).to_str() else {
+        return RlStatus::Malformed;
+    };
+    let signature: [u8; 64] = slice::from_raw_parts(signature, 64)
+        .try_into()
+        .expect("64 bytes");
+    let message = Checkpoint {
+        size,
+        root: hex::encode(root),
+        timestamp_nanos: timestamp_nanos.to_string(),
+    }
+    .signed_message();
+    status(verify_signature(&public_key, &message, &signature))
+}
+
+fn status(valid: bool) -> RlStatus {
+    if valid {
+        RlStatus::Ok
+    } else {
+        RlStatus::Invalid
+    }
+}
+
+fn algorithm_from(value: u32) -> Option<HashAlgorithm> {
+    match value {
+        0 => Some(HashAlgorithm::Sha256),
+        1 => Some(HashAlgorithm::Sha3_256),
+        2 => Some(HashAlgorithm::Blake3),
+        3 => Some(HashAlgorithm::Sha512_256),
+        _ => None,
+    }
+}
+
+fn proof_info(proof: &InclusionProof) -> RlProofInfo {
+    RlProofInfo {
+        index: proof.index,
+        size: proof.size,
+        algorithm: match proof.algorithm {
+            HashAlgorithm::Sha256 => RlHashAlgorithm::Sha256,
+            HashAlgorithm::Sha3_256 => RlHashAlgorithm::Sha3_256,
+            HashAlgorithm::Blake3 => RlHashAlgorithm::Blake3,
+            HashAlgorithm::Sha512_256 => RlHashAlgorithm::Sha512_256,
+        } as u32,
+        leaf: decode(&proof.leaf).unwrap_or_default(),
+        root: decode(&proof.root).unwrap_or_default(),
+    }
+}
+
+fn decode<const N: usize>(value: &str) -> Option<[u8; N]> {
+    hex::decode(value).ok()?.try_into().ok()
+}
+
+/// `len` bytes at `ptr`; a null `ptr` is only accepted for an empty input.
+unsafe fn bytes<'a>(ptr: *const u8, len: usize) -> Option<&'a [u8]> {
+    match (ptr.is_null(), len) {
+        (true, 0) => Some(&[]),
+        (true, _) => None,
+        (false, _) => Some(slice::from_raw_parts(ptr, len)),
+    }
+}
+
+unsafe fn hash(ptr: *const u8) -> Option<[u8; 32]> {
+    (!ptr.is_null()).then(|| slice::from_raw_parts(ptr, 32).try_into().expect("32 bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::CString, path::Path, ptr};
+
+    use reality_core::{make_proof_with, signer::KeyFileSigner};
+
+    use super::*;
+
+    #[test]
+    fn verifies_a_proof_against_a_signed_checkpoint() {
+        let leaves: Vec<[u8; 32]> = [b"a", b"b", b"c"]
+            .iter()
+            .map(|p| HashAlgorithm::Sha256.leaf_hash(*p))
+            .collect();
+        let mut leaf = [0u8; 32];
+        let status = unsafe { rl_leaf_hash(0, b"c".as_ptr(), 1, leaf.as_mut_ptr()) };
+        assert_eq!((status, leaf), (RlStatus::Ok, leaves[2]));
+        assert_eq!(
+            unsafe { rl_leaf_hash(9, ptr::null(), 0, leaf.as_mut_ptr()) },
+            RlStatus::Malformed
+        );
+
+        let signer = KeyFileSigner::from_seed([5u8; 32]);
+        let proof = make_proof_with(HashAlgorithm::Sha256, &leaves, 2).unwrap();
+        let signed = Checkpoint {
+            size: 3,
+            root: proof.root.clone(),
+            timestamp_nanos: "7".into(),
+        }
+        .sign(&signer)
+        .unwrap();
+        let key: [u8; 32] = decode(&signed.signature.public_key).unwrap();
+        let json = CString::new(serde_json::to_string(&signed).unwrap()).unwrap();
+        let mut head = RlCheckpointInfo {
+            size: 0,
+            root: [0; 32],
+        };
+        assert_eq!(
+            unsafe { rl_verify_checkpoint(json.as_ptr(), key.as_ptr(), &mut head) },
+            RlStatus::Ok
+        );
+        assert_eq!(
+            unsafe { rl_verify_checkpoint(json.as_ptr(), leaf.as_ptr(), ptr::null_mut()) },
+            RlStatus::Invalid
+        );
+        let signature: [u8; 64] = decode(&signed.signature.signature).unwrap();
+        assert_eq!(
+            unsafe {
+                rl_verify_checkpoint_signature(
+                    3,
+                    head.root.as_ptr(),
+                    c"7".as_ptr(),
+                    key.as_ptr(),
+                    signature.as_ptr(),
+                )
+            },
+            RlStatus::Ok
+        );
+
+        let bytes = proof.to_bytes().unwrap();
+        let verify = |leaf: *const u8, size| unsafe {
+            rl_verify_inclusion(bytes.as_ptr(), bytes.len(), leaf, head.root.as_ptr(), size)
+        };
+        assert_eq!(verify(leaves[2].as_ptr(), head.size), RlStatus::Ok);
+        assert_eq!(verify(leaves[1].as_ptr(), head.size), RlStatus::Invalid);
+        assert_eq!(verify(ptr::null(), 4), RlStatus::Invalid);
+        assert_eq!(
+            unsafe { rl_verify_proof_bytes(bytes.as_ptr(), 3, ptr::null_mut()) },
+            RlStatus::Malformed
+        );
+    }
+
+    /// The committed header must match what cbindgen generates from this file.
+    #[test]
+    fn header_is_up_to_date() {
+        let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml")).unwrap();
+        let mut generated = Vec::new();
+        cbindgen::Builder::new()
+            .with_crate(crate_dir)
+            .with_config(config)
+            .generate()
+            .expect("generate header")
+            .write(&mut generated);
+
+        let path = crate_dir.join("include/reality_core.h");
+        if std::env::var_os("REALITY_FFI_BLESS").is_some() {
+            std::fs::write(&path, &generated).unwrap();
+        }
+        let committed = std::fs::read(&path).unwrap_or_default();
+        assert!(
+            committed == generated,
+            "include/reality_core.h is stale; rerun with REALITY_FFI_BLESS=1"
+        );
+    }
+}
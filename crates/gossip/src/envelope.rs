@@ -0,0 +1,116 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use reality_core::{
+    checkpoint::SignedCheckpoint,
+    signer::{DetachedSignature, Signer, SignerError},
+};
+use serde::{Deserialize, Serialize};
+
+/// What one peer tells another: the newest checkpoint it holds for each log,
+/// the peers it knows, and its own signature over both.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Envelope {
+    pub checkpoints: Vec<SignedCheckpoint>,
+    /// Gossip URLs of other peers the sender knows.
+    #[serde(default)]
+    pub peers: Vec<String>,
+    pub sent_at_nanos: String,
+    pub sender: DetachedSignature,
+}
+
+impl Envelope {
+    pub fn seal(
+        checkpoints: Vec<SignedCheckpoint>,
+        peers: Vec<String>,
+        signer: &dyn Signer,
+    ) -> Result<Self, SignerError> {
+        let sent_at_nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos()
+            .to_string();
+        let message = signed_message(&checkpoints, &peers, &sent_at_nanos);
+        Ok(Self {
+            sender: DetachedSignature::create(signer, &message)?,
+            checkpoints,
+            peers,
+            sent_at_nanos,
+        })
+    }
+
+    /// Check the sender's signature. The checkpoints inside carry the log's
+    /// own signatures, which are checked one by one as they are observed.
+    pub fn verify(&self) -> bool {
+        !self.peers.iter().any(|peer| peer.contains('\n'))
+            && self.sender.verify(&signed_message(
+                &self.checkpoints,
+                &self.peers,
+                &self.sent_at_nanos,
+            ))
+    }
+}
+
+/// The exact bytes covered by the sender's signature. Counts come first so
+/// no list can be extended or truncated without breaking it.
+fn signed_message(checkpoints: &[SignedCheckpoint], peers: &[String], sent_at: &str) -> Vec<u8> {
+    let mut message = format!(
+        "reality-gossip/v1\n{sent_at}\n{}\n{}\n",
+        checkpoints.len(),
+        peers.len()
+    )
+    .into_bytes();
+    for peer in peers {
+        message.extend(format!("{peer}\n").into_bytes());
+    }
+    for signed in checkpoints {
+        message.extend(
+            format!(
+                "{}\n{}\n",
+                signed.signature.public_key.to_ascii_lowercase(),
+                signed.signature.signature.to_ascii_lowercase()
+            )
+            .into_bytes(),
+        );
+        message.extend(signed.checkpoint.signed_message());
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use reality_core::{checkpoint::Checkpoint, signer::KeyFileSigner};
+
+    use super::*;
+
+    #[test]
+    fn sender_signature_covers_every_field() {
+        let log = KeyFileSigner::from_seed([1u8; 32]);
+        let peer = KeyFileSigner::from_seed([2u8; 32]);
+        let checkpoint = Checkpoint {
+            size: 4,
+            root: "ab".repeat(32),
+            timestamp_nanos: "1".into(),
+        }
+        .sign(&log)
+        .unwrap();
+        let envelope = Envelope::seal(
+            vec![checkpoint],
+            vec!["http://peer.example/gossip".into()],
+            &peer,
+        )
+        .unwrap();
+        assert!(envelope.verify());
+
+        let mut dropped = envelope.clone();
+        dropped.checkpoints.clear();
+        assert!(!dropped.verify());
+
+        let mut redirected = envelope.clone();
+        redirected.peers[0] = "http://evil.example/gossip".into();
+        assert!(!redirected.verify());
+
+        let mut resized = envelope;
+        resized.checkpoints[0].checkpoint.size = 5;
+        assert!(!resized.verify());
+    }
+}
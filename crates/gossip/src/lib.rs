@@ -0,0 +1,362 @@
+//! Checkpoint gossip between vantage points.
+//!
+//! A log that shows one history to some clients and another to the rest
+//! can only be caught by comparing what each of them saw. Every participant
+//! (logd, a monitor, a witness, or a plain client) feeds the checkpoints it
+//! verified into a [`Gossip`] node. Nodes exchange the newest checkpoint per
+//! log with their peers in signed [`Envelope`]s. Any two checkpoints signed
+//! by the same log for the same size with different roots come out as a
+//! [`SplitView`].
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # async fn run(signer: Arc<dyn reality_core::signer::Signer>,
+//! #     checkpoint: reality_core::checkpoint::SignedCheckpoint) -> Result<(), reality_gossip::Error> {
+//! let gossip = reality_gossip::Gossip::new(
+//!     reality_gossip::GossipConfig {
+//!         peers: vec!["https://monitor.example/gossip".into()],
+//!         ..Default::default()
+//!     },
+//!     signer,
+//! )?;
+//! gossip.observe(checkpoint);
+//! for split in gossip.round().await {
+//!     eprintln!("log {} forked at size {}", split.log_key, split.size);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod envelope;
+mod pool;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use reality_core::{
+    checkpoint::SignedCheckpoint,
+    signer::{Signer, SignerError},
+};
+use reqwest::StatusCode;
+use thiserror::Error;
+use tracing::{error, warn};
+
+pub use envelope::Envelope;
+pub use pool::{Observation, Pool, SplitView};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("request to peer failed: {0}")]
+    Transport(#[from] reqwest::Error),
+    #[error("peer returned {status}: {body}")]
+    Status { status: StatusCode, body: String },
+    #[error("failed to sign envelope: {0}")]
+    Signer(#[from] SignerError),
+    #[error("envelope signature is invalid")]
+    BadEnvelope,
+    #[error("envelope sender {0} is not a trusted peer")]
+    UntrustedPeer(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct GossipConfig {
+    /// Gossip URLs of the peers to exchange with, e.g. `https://host/gossip`.
+    pub peers: Vec<String>,
+    /// Hex keys allowed to send envelopes; empty accepts any sender.
+    pub trusted_peers: Vec<String>,
+    /// Hex keys of the logs to follow; empty follows every log.
+    pub logs: Vec<String>,
+    /// Peers contacted per round.
+    pub fanout: usize,
+    /// Adopt peer URLs that trusted senders advertise, up to `max_peers`.
+    pub learn_peers: bool,
+    pub max_peers: usize,
+    /// Sizes kept per log for comparison.
+    pub history: usize,
+    pub timeout: Duration,
+}
+
+impl Default for GossipConfig {
+    fn default() -> Self {
+        Self {
+            peers: Vec::new(),
+            trusted_peers: Vec::new(),
+            logs: Vec::new(),
+            fanout: 3,
+            learn_peers: false,
+            max_peers: 64,
+            history: 1024,
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// One gossip participant: its pool of checkpoints, its peers, and the key
+/// it signs envelopes with.
+pub struct Gossip {
+    config: GossipConfig,
+    signer: Arc<dyn Signer>,
+    http: reqwest::Client,
+    state: Mutex<State>,
+}
+
+struct State {
+    pool: Pool,
+    peers: Vec<String>,
+    /// Where the next round starts in `peers`, so every peer is reached in turn.
+    next: usize,
+}
+
+impl Gossip {
+    pub fn new(config: GossipConfig, signer: Arc<dyn Signer>) -> Result<Self, Error> {
+        let http = reqwest::Client::builder().timeout(config.timeout).build()?;
+        let mut pool = Pool::new(config.history);
+        if !config.logs.is_empty() {
+            pool = pool.follow_only(config.logs.iter().cloned());
+        }
+        let state = State {
+            pool,
+            peers: config.peers.clone(),
+            next: 0,
+        };
+        Ok(Self {
+            config,
+            signer,
+            http,
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Hex key this node signs its envelopes with.
+    pub fn public_key(&self) -> String {
+        hex::encode(self.signer.public_key())
+    }
+
+    /// Add a checkpoint this node saw itself.
+    pub fn observe(&self, signed: SignedCheckpoint) -> Observation {
+        let observation = self.state().pool.observe(signed);
+        if let Observation::SplitView(split) = &observation {
+            report(split);
+        }
+        observation
+    }
+
+    /// Every split view found so far.
+    pub fn splits(&self) -> Vec<SplitView> {
+        self.state().pool.splits().to_vec()
+    }
+
+    pub fn peers(&self) -> Vec<String> {
+        self.state().peers.clone()
+    }
+
+    /// This node's signed view, as sent to peers.
+    pub fn envelope(&self) -> Result<Envelope, Error> {
+        let (checkpoints, peers) = {
+            let state = self.state();
+            (state.pool.latest(), state.peers.clone())
+        };
+        Ok(Envelope::seal(checkpoints, peers, self.signer.as_ref())?)
+    }
+
+    /// Merge a peer's envelope; returns the split views it revealed.
+    pub fn receive(&self, envelope: Envelope) -> Result<Vec<SplitView>, Error> {
+        if !envelope.verify() {
+            return Err(Error::BadEnvelope);
+        }
+        let sender = envelope.sender.public_key.to_ascii_lowercase();
+        let trusted = self.config.trusted_peers.is_empty()
+            || self
+                .config
+                .trusted_peers
+                .iter()
+                .any(|key| key.eq_ignore_ascii_case(&sender));
+        if !trusted {
+            return Err(Error::UntrustedPeer(sender));
+        }
+
+        let mut state = self.state();
+        let mut splits = Vec::new();
+        for signed in envelope.checkpoints {
+            if let Observation::SplitView(split) = state.pool.observe(signed) {
+                report(&split);
+                splits.push(*split);
+            }
+        }
+        if self.config.learn_peers {
+            for peer in envelope.peers {
+                if state.peers.len() >= self.config.max_peers {
+                    break;
+                }
+                if !state.peers.contains(&peer) {
+                    state.peers.push(peer);
+                }
+            }
+        }
+        Ok(splits)
+    }
+
+    /// Push-pull with one peer: send our envelope and merge the one it
+    /// answers with.
+    pub async fn exchange(&self, peer: &str) -> Result<Vec<SplitView>, Error> {
+        let resp = self.http.post(peer).json(&self.envelope()?).send().await?;
+        self.receive(read_envelope(resp).await?)
+    }
+
+    /// Pull-only, for nodes that serve no `/gossip` endpoint of their own.
+    pub async fn pull(&self, peer: &str) -> Result<Vec<SplitView>, Error> {
+        let resp = self.http.get(peer).send().await?;
+        self.receive(read_envelope(resp).await?)
+    }
+
+    /// One anti-entropy round: exchange with the next `fanout` peers.
+    /// Unreachable peers are logged and skipped.
+    pub async fn round(&self) -> Vec<SplitView> {
+        let peers: Vec<String> = {
+            let mut state = self.state();
+            if state.peers.is_empty() {
+                return Vec::new();
+            }
+            let count = self.config.fanout.clamp(1, state.peers.len());
+            let start = state.next % state.peers.len();
+            state.next = start + count;
+            (start..start + count)
+                .map(|i| state.peers[i % state.peers.len()].clone())
+                .collect()
+        };
+        let mut splits = Vec::new();
+        for peer in peers {
+            match self.exchange(&peer).await {
+                Ok(found) => splits.extend(found),
+                Err(err) => warn!(%peer, %err, "gossip exchange failed"),
+            }
+        }
+        splits
+    }
+
+    /// `GET /gossip` returns this node's envelope; `POST /gossip` merges the
+    /// sender's and answers with this node's.
+    #[cfg(feature = "server")]
+    pub fn router(self: Arc<Self>) -> axum::Router {
+        use axum::{extract::State, http::StatusCode as ServerStatus, routing::get, Json};
+
+        type Reply = Result<Json<Envelope>, (ServerStatus, String)>;
+
+        fn reply(gossip: &Gossip) -> Reply {
+            gossip.envelope().map(Json).map_err(|err| {
+                error!(%err, "failed to seal gossip envelope");
+                (ServerStatus::INTERNAL_SERVER_ERROR, err.to_string())
+            })
+        }
+
+        async fn pull(State(gossip): State<Arc<Gossip>>) -> Reply {
+            reply(&gossip)
+        }
+
+        async fn push(State(gossip): State<Arc<Gossip>>, Json(envelope): Json<Envelope>) -> Reply {
+            match gossip.receive(envelope) {
+                Ok(_) => reply(&gossip),
+                Err(err @ Error::UntrustedPeer(_)) => {
+                    Err((ServerStatus::FORBIDDEN, err.to_string()))
+                }
+                Err(err) => Err((ServerStatus::BAD_REQUEST, err.to_string())),
+            }
+        }
+
+        axum::Router::new()
+            .route("/gossip", get(pull).post(push))
+            .with_state(self)
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("gossip state poisoned")
+    }
+}
+
+async fn read_envelope(resp: reqwest::Response) -> Result<Envelope, Error> {
+    let status = resp.status();
+    if !status.is_success() {
+        let body = resp.text().await.unwrap_or_default();
+        return Err(Error::Status { status, body });
+    }
+    Ok(resp.json().await?)
+}
+
+fn report(split: &SplitView) {
+    error!(
+        log_key = %split.log_key,
+        size = split.size,
+        first_root = %split.first.checkpoint.root,
+        second_root = %split.second.checkpoint.root,
+        "ALERT: log signed two roots for the same size"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::{extract::State, routing::post, Json, Router};
+    use reality_core::{checkpoint::Checkpoint, signer::KeyFileSigner};
+
+    fn node(seed: u8, trusted_peers: Vec<String>) -> Arc<Gossip> {
+        let config = GossipConfig {
+            trusted_peers,
+            ..Default::default()
+        };
+        let signer = Arc::new(KeyFileSigner::from_seed([seed; 32]));
+        Arc::new(Gossip::new(config, signer).unwrap())
+    }
+
+    /// Serves `gossip` the way `Gossip::router` does, without the feature.
+    async fn serve(gossip: Arc<Gossip>) -> String {
+        let app = Router::new()
+            .route(
+                "/gossip",
+                post(
+                    |State(gossip): State<Arc<Gossip>>, Json(envelope): Json<Envelope>| async move {
+                        gossip.receive(envelope).unwrap();
+                        Json(gossip.envelope().unwrap())
+                    },
+                ),
+            )
+            .with_state(gossip);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{addr}/gossip")
+    }
+
+    #[tokio::test]
+    async fn exchange_reveals_a_split_view_to_both_sides() {
+        let log = KeyFileSigner::from_seed([9u8; 32]);
+        let head = |root: &str| {
+            Checkpoint {
+                size: 7,
+                root: root.repeat(32),
+                timestamp_nanos: "1".into(),
+            }
+            .sign(&log)
+            .unwrap()
+        };
+
+        let remote = node(2, Vec::new());
+        remote.observe(head("bb"));
+        let url = serve(remote.clone()).await;
+
+        let local = node(1, vec![remote.public_key()]);
+        local.observe(head("aa"));
+        let splits = local.exchange(&url).await.unwrap();
+        assert_eq!(splits.len(), 1);
+        assert_eq!(splits[0].first.checkpoint.root, "aa".repeat(32));
+        assert_eq!(remote.splits().len(), 1);
+
+        let stranger = node(3, Vec::new());
+        assert!(matches!(
+            local.receive(stranger.envelope().unwrap()),
+            Err(Error::UntrustedPeer(_))
+        ));
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use reality_core::checkpoint::SignedCheckpoint;
+use serde::{Deserialize, Serialize};
+
+/// Two checkpoints signed by the same log key for the same size with
+/// different roots. Either signature alone is valid, so together they prove
+/// the log showed different histories to different parties.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SplitView {
+    pub log_key: String,
+    pub size: u64,
+    pub first: SignedCheckpoint,
+    pub second: SignedCheckpoint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Observation {
+    /// A size not seen before for this log.
+    New,
+    /// Matches what the pool already holds at that size.
+    Known,
+    /// Not signed by its embedded key, or from a log the pool does not follow.
+    Ignored,
+    /// Contradicts a checkpoint the pool holds at the same size.
+    SplitView(Box<SplitView>),
+}
+
+/// Checkpoints seen so far, per log key and size.
+///
+/// Only checkpoints of equal size can be compared without the log. Telling
+/// whether a larger one extends a smaller one needs a consistency proof, so
+/// callers that can reach the log should check that themselves.
+#[derive(Debug, Clone)]
+pub struct Pool {
+    logs: HashMap<String, BTreeMap<u64, SignedCheckpoint>>,
+    /// Lowercase hex keys of the logs followed; `None` follows any log.
+    follow: Option<HashSet<String>>,
+    /// Sizes kept per log; the oldest are dropped first.
+    history: usize,
+    splits: Vec<SplitView>,
+}
+
+impl Pool {
+    pub fn new(history: usize) -> Self {
+        Self {
+            logs: HashMap::new(),
+            follow: None,
+            history: history.max(1),
+            splits: Vec::new(),
+        }
+    }
+
+    /// Ignore checkpoints from any log key not in `keys`.
+    pub fn follow_only(mut self, keys: impl IntoIterator<Item = String>) -> Self {
+        self.follow = Some(keys.into_iter().map(|k| k.to_ascii_lowercase()).collect());
+        self
+    }
+
+    pub fn observe(&mut self, signed: SignedCheckpoint) -> Observation {
+        let key = signed.signature.public_key.to_ascii_lowercase();
+        let followed = self.follow.as_ref().is_none_or(|keys| keys.contains(&key));
+        if !followed || !signed.verify() {
+            return Observation::Ignored;
+        }
+        let sizes = self.logs.entry(key.clone()).or_default();
+        let size = signed.checkpoint.size;
+        if let Some(held) = sizes.get(&size) {
+            if held
+                .checkpoint
+                .root
+                .eq_ignore_ascii_case(&signed.checkpoint.root)
+            {
+                return Observation::Known;
+            }
+            let split = SplitView {
+                log_key: key,
+                size,
+                first: held.clone(),
+                second: signed,
+            };
+            let reported = self.splits.iter().any(|seen| {
+                seen.log_key == split.log_key
+                    && seen.size == size
+                    && seen
+                        .second
+                        .checkpoint
+                        .root
+                        .eq_ignore_ascii_case(&split.second.checkpoint.root)
+            });
+            if !reported {
+                self.splits.push(split.clone());
+            }
+            return Observation::SplitView(Box::new(split));
+        }
+        sizes.insert(size, signed);
+        while sizes.len() > self.history {
+            sizes.pop_first();
+        }
+        Observation::New
+    }
+
+    /// The newest checkpoint held for each log: what gets gossiped.
+    pub fn latest(&self) -> Vec<SignedCheckpoint> {
+        let mut latest: Vec<_> = self
+            .logs
+            .values()
+            .filter_map(|sizes| sizes.last_key_value().map(|(_, signed)| signed.clone()))
+            .collect();
+        latest.sort_by(|a, b| a.signature.public_key.cmp(&b.signature.public_key));
+        latest
+    }
+
+    /// Every split view found so far, each pair once.
+    pub fn splits(&self) -> &[SplitView] {
+        &self.splits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reality_core::{checkpoint::Checkpoint, signer::KeyFileSigner};
+
+    use super::*;
+
+    fn signed(signer: &KeyFileSigner, size: u64, root: &str) -> SignedCheckpoint {
+        Checkpoint {
+            size,
+            root: root.repeat(32),
+            timestamp_nanos: size.to_string(),
+        }
+        .sign(signer)
+        .unwrap()
+    }
+
+    #[test]
+    fn reports_conflicting_roots_once() {
+        let log = KeyFileSigner::from_seed([1u8; 32]);
+        let mut pool = Pool::new(2);
+        assert_eq!(pool.observe(signed(&log, 3, "aa")), Observation::New);
+        assert_eq!(pool.observe(signed(&log, 3, "AA")), Observation::Known);
+
+        let mut forged = signed(&log, 3, "bb");
+        forged.checkpoint.root = "cc".repeat(32);
+        assert_eq!(pool.observe(forged), Observation::Ignored);
+
+        assert!(matches!(
+            pool.observe(signed(&log, 3, "bb")),
+            Observation::SplitView(split) if split.size == 3
+        ));
+        assert!(matches!(
+            pool.observe(signed(&log, 3, "bb")),
+            Observation::SplitView(_)
+        ));
+        assert_eq!(pool.splits().len(), 1);
+
+        pool.observe(signed(&log, 4, "dd"));
+        pool.observe(signed(&log, 5, "ee"));
+        assert_eq!(pool.latest(), vec![signed(&log, 5, "ee")]);
+        assert_eq!(pool.observe(signed(&log, 3, "ff")), Observation::New);
+    }
+
+    #[test]
+    fn follows_only_the_listed_logs() {
+        let log = KeyFileSigner::from_seed([1u8; 32]);
+        let other = KeyFileSigner::from_seed([2u8; 32]);
+        let mut pool = Pool::new(8).follow_only([signed(&log, 1, "aa").signature.public_key]);
+        assert_eq!(pool.observe(signed(&other, 1, "aa")), Observation::Ignored);
+        assert_eq!(pool.observe(signed(&log, 1, "aa")), Observation::New);
+    }
+}
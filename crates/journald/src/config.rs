@@ -0,0 +1,160 @@
+use std::{path::PathBuf, time::Duration};
+
+use clap::Parser;
+use reality_config::Layered;
+use serde::Deserialize;
+
+/// Largest batch logd accepts in one `/append/batch` request.
+const MAX_BATCH: usize = 1000;
+
+/// Command-line flags. Anything left unset falls back to the config file and
+/// then to defaults.
+#[derive(Debug, Parser)]
+#[command(
+    name = "reality-journald",
+    version,
+    about = "Streams systemd journal entries into a RealityLog"
+)]
+pub struct Cli {
+    /// Path to a TOML config file.
+    #[arg(long, env = "REALITY_JOURNALD_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Systemd unit whose entries are shipped (repeatable); none ships all.
+    #[arg(long = "unit")]
+    pub units: Vec<String>,
+    /// Journal field to keep in each entry (repeatable); none keeps all.
+    #[arg(long = "field")]
+    pub fields: Vec<String>,
+    /// Base URL of the logd instance to append to.
+    #[arg(long, env = "REALITY_JOURNALD_LOG_URL")]
+    pub log_url: Option<String>,
+    /// Bearer token sent with every append.
+    #[arg(long, env = "REALITY_JOURNALD_TOKEN")]
+    pub token: Option<String>,
+    /// Directory holding the cursor file.
+    #[arg(long, env = "REALITY_JOURNALD_DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
+    /// Read journal files from this directory instead of the system journal.
+    #[arg(long)]
+    pub directory: Option<PathBuf>,
+    /// Without a saved cursor, start at the oldest entry rather than the
+    /// newest.
+    #[arg(long)]
+    pub from_start: bool,
+    /// Most entries sent in one request.
+    #[arg(long)]
+    pub batch_size: Option<usize>,
+}
+
+/// Fully resolved reader configuration.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub log_url: String,
+    pub token: Option<String>,
+    pub data_dir: PathBuf,
+    pub directory: Option<PathBuf>,
+    pub units: Vec<String>,
+    pub fields: Vec<String>,
+    pub from_start: bool,
+    pub batch_size: usize,
+    /// Longest wait for new entries before a partial batch is sent.
+    pub wait_ms: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            log_url: "http://127.0.0.1:8080".into(),
+            token: None,
+            data_dir: PathBuf::from("data"),
+            directory: None,
+            units: Vec::new(),
+            fields: Vec::new(),
+            from_start: false,
+            batch_size: 100,
+            wait_ms: 1000,
+        }
+    }
+}
+
+impl Layered<Cli> for Config {
+    fn file(cli: &Cli) -> Option<PathBuf> {
+        cli.config.clone()
+    }
+
+    fn apply(&mut self, cli: Cli) -> anyhow::Result<()> {
+        self.units.extend(cli.units);
+        self.fields.extend(cli.fields);
+        self.from_start |= cli.from_start;
+        if let Some(log_url) = cli.log_url {
+            self.log_url = log_url;
+        }
+        if let Some(token) = cli.token {
+            self.token = Some(token);
+        }
+        if let Some(data_dir) = cli.data_dir {
+            self.data_dir = data_dir;
+        }
+        if let Some(directory) = cli.directory {
+            self.directory = Some(directory);
+        }
+        if let Some(batch_size) = cli.batch_size {
+            self.batch_size = batch_size;
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            (1..=MAX_BATCH).contains(&self.batch_size),
+            "batch_size must be between 1 and {MAX_BATCH}"
+        );
+        for field in &self.fields {
+            anyhow::ensure!(
+                !field.is_empty()
+                    && field
+                        .bytes()
+                        .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'_'),
+                "{field:?} is not a journal field name"
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Config {
+    pub fn wait(&self) -> Duration {
+        Duration::from_millis(self.wait_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_names_must_be_journal_fields() {
+        let mut config: Config = toml::from_str(
+            r#"
+            units = ["sshd.service"]
+            fields = ["MESSAGE", "_PID"]
+            "#,
+        )
+        .expect("config");
+        config
+            .apply(Cli::parse_from([
+                "reality-journald",
+                "--unit",
+                "sudo.service",
+                "--from-start",
+            ]))
+            .unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.units, ["sshd.service", "sudo.service"]);
+        assert!(config.from_start);
+
+        config.fields.push("message".into());
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,226 @@
+//! The few sd-journal(3) calls the reader needs. libsystemd is loaded at
+//! run time, so the binary builds without its headers and fails with a
+//! clear error on hosts without systemd.
+
+use std::{
+    ffi::{c_char, c_int, c_void, CStr, CString},
+    io,
+    os::unix::ffi::OsStrExt,
+    path::Path,
+    ptr,
+    time::Duration,
+};
+
+use libloading::Library;
+
+const SD_JOURNAL_LOCAL_ONLY: c_int = 1 << 0;
+const SD_JOURNAL_SYSTEM: c_int = 1 << 2;
+
+type Handle = *mut c_void;
+
+/// Function pointers resolved from libsystemd; valid while `_lib` is loaded.
+struct Api {
+    _lib: Library,
+    open: unsafe extern "C" fn(*mut Handle, c_int) -> c_int,
+    open_directory: unsafe extern "C" fn(*mut Handle, *const c_char, c_int) -> c_int,
+    close: unsafe extern "C" fn(Handle),
+    add_match: unsafe extern "C" fn(Handle, *const c_void, usize) -> c_int,
+    set_data_threshold: unsafe extern "C" fn(Handle, usize) -> c_int,
+    seek_head: unsafe extern "C" fn(Handle) -> c_int,
+    seek_tail: unsafe extern "C" fn(Handle) -> c_int,
+    seek_cursor: unsafe extern "C" fn(Handle, *const c_char) -> c_int,
+    test_cursor: unsafe extern "C" fn(Handle, *const c_char) -> c_int,
+    next: unsafe extern "C" fn(Handle) -> c_int,
+    previous: unsafe extern "C" fn(Handle) -> c_int,
+    get_cursor: unsafe extern "C" fn(Handle, *mut *mut c_char) -> c_int,
+    get_realtime_usec: unsafe extern "C" fn(Handle, *mut u64) -> c_int,
+    restart_data: unsafe extern "C" fn(Handle),
+    enumerate_data: unsafe extern "C" fn(Handle, *mut *const c_void, *mut usize) -> c_int,
+    wait: unsafe extern "C" fn(Handle, u64) -> c_int,
+    free: unsafe extern "C" fn(*mut c_void),
+}
+
+impl Api {
+    fn load() -> io::Result<Self> {
+        // SAFETY: libsystemd runs no initialisers with preconditions, and
+        // every symbol is given the signature declared in sd-journal.h.
+        unsafe {
+            let lib = Library::new("libsystemd.so.0").map_err(unavailable)?;
+            let libc = libloading::os::unix::Library::this();
+            macro_rules! sym {
+                ($lib:expr, $name:literal) => {
+                    *$lib.get($name).map_err(unavailable)?
+                };
+            }
+            Ok(Self {
+                open: sym!(lib, b"sd_journal_open\0"),
+                open_directory: sym!(lib, b"sd_journal_open_directory\0"),
+                close: sym!(lib, b"sd_journal_close\0"),
+                add_match: sym!(lib, b"sd_journal_add_match\0"),
+                set_data_threshold: sym!(lib, b"sd_journal_set_data_threshold\0"),
+                seek_head: sym!(lib, b"sd_journal_seek_head\0"),
+                seek_tail: sym!(lib, b"sd_journal_seek_tail\0"),
+                seek_cursor: sym!(lib, b"sd_journal_seek_cursor\0"),
+                test_cursor: sym!(lib, b"sd_journal_test_cursor\0"),
+                next: sym!(lib, b"sd_journal_next\0"),
+                previous: sym!(lib, b"sd_journal_previous\0"),
+                get_cursor: sym!(lib, b"sd_journal_get_cursor\0"),
+                get_realtime_usec: sym!(lib, b"sd_journal_get_realtime_usec\0"),
+                restart_data: sym!(lib, b"sd_journal_restart_data\0"),
+                enumerate_data: sym!(lib, b"sd_journal_enumerate_data\0"),
+                wait: sym!(lib, b"sd_journal_wait\0"),
+                free: sym!(libc, b"free\0"),
+                _lib: lib,
+            })
+        }
+    }
+}
+
+fn unavailable(err: libloading::Error) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("libsystemd is not available: {err}"),
+    )
+}
+
+/// Turn a negative errno return into an error.
+fn check(ret: c_int) -> io::Result<c_int> {
+    if ret < 0 {
+        Err(io::Error::from_raw_os_error(-ret))
+    } else {
+        Ok(ret)
+    }
+}
+
+/// One journal entry with every field as raw bytes, in journal order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    pub cursor: String,
+    pub realtime_usec: u64,
+    pub fields: Vec<(String, Vec<u8>)>,
+}
+
+/// An open journal, read forward from wherever it was positioned.
+pub struct Journal {
+    api: Api,
+    handle: Handle,
+}
+
+impl Journal {
+    /// The local system journal, or the journal files in `directory`.
+    pub fn open(directory: Option<&Path>) -> io::Result<Self> {
+        let api = Api::load()?;
+        let mut handle = ptr::null_mut();
+        // SAFETY: `handle` is written by sd_journal_open* only on success.
+        let ret = unsafe {
+            match directory {
+                Some(dir) => {
+                    let dir = CString::new(dir.as_os_str().as_bytes())?;
+                    (api.open_directory)(&mut handle, dir.as_ptr(), 0)
+                }
+                None => (api.open)(&mut handle, SD_JOURNAL_LOCAL_ONLY | SD_JOURNAL_SYSTEM),
+            }
+        };
+        check(ret)?;
+        let journal = Self { api, handle };
+        // Fields are kept whole rather than cut at the default 64 KiB.
+        check(unsafe { (journal.api.set_data_threshold)(journal.handle, 0) })?;
+        Ok(journal)
+    }
+
+    /// Only return entries whose field matches, as in `FIELD=value`. Matches
+    /// on the same field are alternatives.
+    pub fn add_match(&mut self, field_value: &str) -> io::Result<()> {
+        check(unsafe {
+            (self.api.add_match)(self.handle, field_value.as_ptr().cast(), field_value.len())
+        })?;
+        Ok(())
+    }
+
+    /// Position before the oldest entry.
+    pub fn seek_head(&mut self) -> io::Result<()> {
+        check(unsafe { (self.api.seek_head)(self.handle) })?;
+        Ok(())
+    }
+
+    /// Position after the newest entry, so only entries written from now on
+    /// are read.
+    pub fn seek_tail(&mut self) -> io::Result<()> {
+        check(unsafe { (self.api.seek_tail)(self.handle) })?;
+        // Settle on the last entry so `next` continues after it.
+        check(unsafe { (self.api.previous)(self.handle) })?;
+        Ok(())
+    }
+
+    /// Position just after the entry at `cursor`. Returns false when that
+    /// entry is gone, for example vacuumed, and reading resumes at the
+    /// closest entry instead.
+    pub fn seek_after(&mut self, cursor: &str) -> io::Result<bool> {
+        let cursor = CString::new(cursor)?;
+        check(unsafe { (self.api.seek_cursor)(self.handle, cursor.as_ptr()) })?;
+        if !self.step()? {
+            return Ok(false);
+        }
+        let found = check(unsafe { (self.api.test_cursor)(self.handle, cursor.as_ptr()) })? > 0;
+        if !found {
+            // Leave the closest entry to be read next.
+            check(unsafe { (self.api.previous)(self.handle) })?;
+        }
+        Ok(found)
+    }
+
+    /// The next entry, or `None` once the journal is caught up.
+    pub fn next_entry(&mut self) -> io::Result<Option<JournalEntry>> {
+        if !self.step()? {
+            return Ok(None);
+        }
+        let mut raw = ptr::null_mut();
+        check(unsafe { (self.api.get_cursor)(self.handle, &mut raw) })?;
+        // SAFETY: on success `raw` is a NUL-terminated string we must free.
+        let cursor = unsafe {
+            let cursor = CStr::from_ptr(raw).to_string_lossy().into_owned();
+            (self.api.free)(raw.cast());
+            cursor
+        };
+        let mut realtime_usec = 0;
+        check(unsafe { (self.api.get_realtime_usec)(self.handle, &mut realtime_usec) })?;
+
+        let mut fields = Vec::new();
+        unsafe { (self.api.restart_data)(self.handle) };
+        loop {
+            let mut data = ptr::null();
+            let mut len = 0;
+            if check(unsafe { (self.api.enumerate_data)(self.handle, &mut data, &mut len) })? == 0 {
+                break;
+            }
+            // SAFETY: the journal keeps `data` valid until the next call.
+            let field = unsafe { std::slice::from_raw_parts(data.cast::<u8>(), len) };
+            if let Some(eq) = field.iter().position(|b| *b == b'=') {
+                let name = String::from_utf8_lossy(&field[..eq]).into_owned();
+                fields.push((name, field[eq + 1..].to_vec()));
+            }
+        }
+        Ok(Some(JournalEntry {
+            cursor,
+            realtime_usec,
+            fields,
+        }))
+    }
+
+    /// Block until the journal changes or `timeout` passes.
+    pub fn wait(&mut self, timeout: Duration) -> io::Result<()> {
+        let usec = u64::try_from(timeout.as_micros()).unwrap_or(u64::MAX);
+        check(unsafe { (self.api.wait)(self.handle, usec) })?;
+        Ok(())
+    }
+
+    fn step(&mut self) -> io::Result<bool> {
+        Ok(check(unsafe { (self.api.next)(self.handle) })? > 0)
+    }
+}
+
+impl Drop for Journal {
+    fn drop(&mut self) {
+        unsafe { (self.api.close)(self.handle) }
+    }
+}
@@ -0,0 +1,134 @@
+//! `reality-journald`: streams systemd journal entries into logd.
+//!
+//! Entries of the selected units go out in `/append/batch` requests, keyed
+//! by their leaf hash. The payload includes the journal cursor, so a batch
+//! resent after a timeout or a crash is never appended twice. The cursor of
+//! the last entry in the log is saved after every batch and reading resumes
+//! just after it.
+
+mod config;
+mod journal;
+mod payload;
+
+use std::{fs::File, io::Write, path::PathBuf, time::Duration};
+
+use anyhow::Context;
+use clap::Parser;
+use reality_client::blocking::Client;
+use reality_config::Layered;
+use reality_core::{leaf_hash, BatchEntry};
+use tracing::{info, warn};
+
+use crate::{
+    config::{Cli, Config},
+    journal::{Journal, JournalEntry},
+};
+
+/// Longest pause between attempts while logd is unavailable.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let config = Config::load(Cli::parse())?;
+    std::fs::create_dir_all(&config.data_dir).context("create data dir")?;
+    let cursor_path = config.data_dir.join("journald-cursor");
+    let mut builder = Client::builder(&config.log_url);
+    if let Some(token) = &config.token {
+        builder = builder.token(token);
+    }
+    let client = builder.build_blocking()?;
+
+    let mut journal = Journal::open(config.directory.as_deref()).context("open journal")?;
+    for unit in &config.units {
+        journal.add_match(&format!("_SYSTEMD_UNIT={unit}"))?;
+    }
+    match load_cursor(&cursor_path)? {
+        Some(cursor) => {
+            if !journal.seek_after(&cursor)? {
+                warn!(%cursor, "saved entry is no longer in the journal; resuming at the closest");
+            }
+        }
+        None if config.from_start => journal.seek_head()?,
+        None => journal.seek_tail()?,
+    }
+    info!(units = ?config.units, "streaming journal");
+
+    loop {
+        let batch = collect(&mut journal, config.batch_size)?;
+        let Some(last) = batch.last() else {
+            journal.wait(config.wait())?;
+            continue;
+        };
+        let cursor = last.cursor.clone();
+        let entries = batch
+            .iter()
+            .map(|entry| {
+                let payload = payload::payload(entry, &config.fields);
+                let key = format!("journald:{}", hex::encode(leaf_hash(payload.as_bytes())));
+                BatchEntry {
+                    payload,
+                    idempotency_key: Some(key),
+                    labels: Default::default(),
+                    signature: None,
+                }
+            })
+            .collect();
+        let duplicates = append(&client, entries);
+        save_cursor(&cursor_path, &cursor)?;
+        info!(entries = batch.len(), duplicates, "shipped batch");
+    }
+}
+
+/// Up to `limit` entries that are already in the journal.
+fn collect(journal: &mut Journal, limit: usize) -> anyhow::Result<Vec<JournalEntry>> {
+    let mut batch = Vec::new();
+    while batch.len() < limit {
+        match journal.next_entry().context("read journal")? {
+            Some(entry) => batch.push(entry),
+            None => break,
+        }
+    }
+    Ok(batch)
+}
+
+/// Send one batch until logd takes it; returns how many entries it already
+/// held. Every entry is keyed, so resending is always safe.
+fn append(client: &Client, entries: Vec<BatchEntry>) -> usize {
+    let mut backoff = Duration::from_millis(250);
+    loop {
+        match client.append_batch(entries.clone()) {
+            Ok(response) => {
+                return response
+                    .entries
+                    .iter()
+                    .filter(|entry| entry.duplicate)
+                    .count()
+            }
+            Err(err) => {
+                warn!(%err, retry_in = ?backoff, "append failed");
+                std::thread::sleep(backoff);
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+fn load_cursor(path: &PathBuf) -> anyhow::Result<Option<String>> {
+    match std::fs::read_to_string(path) {
+        Ok(raw) => Ok(Some(raw.trim().to_string()).filter(|cursor| !cursor.is_empty())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("read cursor {}", path.display())),
+    }
+}
+
+/// Rewrite the cursor file through a temporary file, so a crash leaves
+/// either the old or the new cursor.
+fn save_cursor(path: &PathBuf, cursor: &str) -> anyhow::Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut file = File::create(&tmp).with_context(|| format!("create {}", tmp.display()))?;
+    file.write_all(cursor.as_bytes())?;
+    file.sync_all()
+        .with_context(|| format!("write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("replace {}", path.display()))
+}
@@ -0,0 +1,81 @@
+use std::collections::BTreeMap;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::journal::JournalEntry;
+
+/// The payload appended for one journal entry. Values that are not UTF-8
+/// become `{"base64": ...}`, and a field the entry carries more than once
+/// becomes an array of its values in journal order.
+#[derive(Serialize)]
+struct Shipped<'a> {
+    source: &'static str,
+    cursor: &'a str,
+    realtime_usec: u64,
+    fields: BTreeMap<&'a str, Value>,
+}
+
+/// Serialise `entry`, keeping only the fields in `allow` unless it is empty.
+pub fn payload(entry: &JournalEntry, allow: &[String]) -> String {
+    let mut fields: BTreeMap<&str, Value> = BTreeMap::new();
+    for (name, bytes) in &entry.fields {
+        if !allow.is_empty() && !allow.iter().any(|field| field == name) {
+            continue;
+        }
+        let value = match std::str::from_utf8(bytes) {
+            Ok(text) => Value::from(text),
+            Err(_) => json!({ "base64": STANDARD.encode(bytes) }),
+        };
+        match fields.get_mut(name.as_str()) {
+            None => {
+                fields.insert(name, value);
+            }
+            Some(Value::Array(values)) => values.push(value),
+            Some(first) => *first = Value::Array(vec![first.take(), value]),
+        }
+    }
+    serde_json::to_string(&Shipped {
+        source: "journald",
+        cursor: &entry.cursor,
+        realtime_usec: entry.realtime_usec,
+        fields,
+    })
+    .expect("entry serializes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_allowed_fields_and_binary_values() {
+        let entry = JournalEntry {
+            cursor: "s=1;i=2".into(),
+            realtime_usec: 1_700_000_000_000_000,
+            fields: vec![
+                ("MESSAGE".into(), b"Accepted publickey for root".to_vec()),
+                ("_SYSTEMD_UNIT".into(), b"sshd.service".to_vec()),
+                ("TAG".into(), b"a".to_vec()),
+                ("TAG".into(), vec![0xff, 0x00]),
+                ("_PID".into(), b"812".to_vec()),
+            ],
+        };
+        let all: Value = serde_json::from_str(&payload(&entry, &[])).unwrap();
+        assert_eq!(all["source"], "journald");
+        assert_eq!(all["cursor"], "s=1;i=2");
+        assert_eq!(all["fields"]["TAG"], json!(["a", { "base64": "/wA=" }]));
+        assert_eq!(all["fields"]["_PID"], "812");
+
+        let allowed = ["MESSAGE".to_string(), "_SYSTEMD_UNIT".to_string()];
+        let some: Value = serde_json::from_str(&payload(&entry, &allowed)).unwrap();
+        assert_eq!(
+            some["fields"],
+            json!({
+                "MESSAGE": "Accepted publickey for root",
+                "_SYSTEMD_UNIT": "sshd.service",
+            })
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Day buckets over a [`Log`]. Entries are grouped by the UTC day of their
+//! `appended_at`. Once a day is over, the root of a tree of that day's
+//! entries alone is appended to a master log under `days/` in the log's
+//! directory (see [`reality_core::day`]). Receipts for entries of sealed
+//! days are then as small as a day is, however long the log runs, and a
+//! sealed day can be archived as one unit.
+//!
+//! The log itself is unchanged, so every other proof keeps working. An
+//! entry whose clock reads earlier than the entry before it stays in that
+//! entry's day, which keeps every day a contiguous run of indices.
+
+use std::{path::PathBuf, sync::Arc};
+
+use reality_core::{
+    day::{DayList, DayReceipt, DaySeal, RecordedDay},
+    make_proof, root, BatchEntry,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{Entry, Log, LogError, Snapshot};
+
+/// Directory of the master log, under the log's directory.
+pub const DAYS: &str = "days";
+
+/// A sealed day as one unit: its seal and its entries as stored.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DayArchive {
+    pub seal: DaySeal,
+    pub entries: Vec<Entry>,
+}
+
+/// The master log of a log's days, and the seals it holds.
+#[derive(Debug)]
+pub struct Days {
+    master: Log,
+    seals: Vec<DaySeal>,
+}
+
+impl Days {
+    /// Open the master log of the log in `dir`.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, LogError> {
+        let master = Log::open(dir.into().join(DAYS))?;
+        let seals = master
+            .iter()
+            .filter_map(|entry| DaySeal::from_payload(&entry.payload))
+            .collect();
+        Ok(Self { master, seals })
+    }
+
+    /// The master log as it is now.
+    pub fn master(&self) -> Arc<Snapshot> {
+        self.master.snapshot()
+    }
+
+    /// Every seal with its position in the master log, oldest first.
+    pub fn list(&self) -> DayList {
+        let head = self.master.root();
+        DayList {
+            size: head.size,
+            root: head.root,
+            days: self
+                .seals
+                .iter()
+                .enumerate()
+                .map(|(position, seal)| RecordedDay {
+                    position: position as u64,
+                    seal: seal.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Index of the first entry no seal covers.
+    fn next(&self) -> u64 {
+        self.seals.last().map_or(0, |seal| seal.first + seal.size)
+    }
+
+    /// Seals for the days of `snapshot` that are over by `today`
+    /// (`YYYY-MM-DD`) and not sealed yet, oldest first. Nothing is appended
+    /// until each is passed to [`Days::seal`].
+    pub fn closed(&self, snapshot: &Snapshot, today: &str) -> Vec<DaySeal> {
+        let mut seals = Vec::new();
+        let mut first = self.next();
+        while let Some(entry) = snapshot.get(first) {
+            let day = day_of(entry);
+            if day >= today {
+                break;
+            }
+            let mut end = first + 1;
+            while snapshot.get(end).is_some_and(|entry| day_of(entry) <= day) {
+                end += 1;
+            }
+            let leaves: Vec<_> = (first..end)
+                .map(|index| snapshot.leaf(index).expect("entries have leaves"))
+                .collect();
+            seals.push(DaySeal {
+                day: day.to_string(),
+                first,
+                size: end - first,
+                root: hex::encode(root(&leaves)),
+                archive: None,
+            });
+            first = end;
+        }
+        seals
+    }
+
+    /// The day `seal` covers as one unit, as JSON.
+    pub fn archive(snapshot: &Snapshot, seal: &DaySeal) -> Vec<u8> {
+        let archive = DayArchive {
+            seal: seal.clone(),
+            entries: (seal.first..seal.first + seal.size)
+                .filter_map(|index| snapshot.get(index).cloned())
+                .collect(),
+        };
+        serde_json::to_vec(&archive).expect("day archives serialize")
+    }
+
+    /// Append `seal`, which must be the next of [`Days::closed`], to the
+    /// master log. Sealing a day again with the same seal changes nothing.
+    pub fn seal(&mut self, seal: DaySeal) -> Result<(), LogError> {
+        let appended = self.master.append_batch(vec![BatchEntry {
+            payload: seal.to_payload(),
+            idempotency_key: Some(format!("day:{}", seal.first)),
+            labels: Default::default(),
+            signature: None,
+        }])?;
+        if !appended.entries[0].duplicate {
+            self.seals.push(seal);
+        }
+        Ok(())
+    }
+
+    /// Receipt for entry `index` of `snapshot`, or `None` while its day is
+    /// not sealed.
+    pub fn receipt(&self, snapshot: &Snapshot, index: u64) -> Result<Option<DayReceipt>, LogError> {
+        if index >= snapshot.len() {
+            return Err(LogError::OutOfRange {
+                index,
+                size: snapshot.len(),
+            });
+        }
+        let position = self
+            .seals
+            .partition_point(|seal| seal.first + seal.size <= index);
+        let Some(seal) = self.seals.get(position) else {
+            return Ok(None);
+        };
+        let leaves: Vec<_> = (seal.first..seal.first + seal.size)
+            .map(|at| snapshot.leaf(at).expect("sealed entries are in the log"))
+            .collect();
+        let entry =
+            make_proof(&leaves, (index - seal.first) as usize).expect("the entry is in its day");
+        let master = self.master.snapshot();
+        Ok(Some(DayReceipt {
+            index,
+            seal: master
+                .get(position as u64)
+                .expect("seals are in the master log")
+                .payload
+                .clone(),
+            entry,
+            master: master.proof(position as u64)?,
+        }))
+    }
+}
+
+/// `YYYY-MM-DD` of an entry's RFC 3339 UTC time.
+fn day_of(entry: &Entry) -> &str {
+    entry.appended_at.get(..10).unwrap_or(&entry.appended_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn seals_closed_days_and_proves_their_entries() {
+        let dir = std::env::temp_dir().join(format!("reality-log-days-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut log = Log::open(&dir).unwrap();
+        for payload in ["a", "b", "c"] {
+            log.append(payload).unwrap();
+        }
+        let today = log.get(0).unwrap().appended_at[..10].to_string();
+        let mut days = Days::open(&dir).unwrap();
+        let snapshot = log.snapshot();
+        assert!(days.closed(&snapshot, &today).is_empty());
+        assert_eq!(days.receipt(&snapshot, 1).unwrap(), None);
+
+        // Seen from tomorrow, today is over.
+        let closed = days.closed(&snapshot, "9999-12-31");
+        assert_eq!(closed.len(), 1);
+        assert_eq!((closed[0].first, closed[0].size), (0, 3));
+        let archive: DayArchive =
+            serde_json::from_slice(&Days::archive(&snapshot, &closed[0])).unwrap();
+        assert_eq!(archive.entries[2].payload, "c");
+        days.seal(closed[0].clone()).unwrap();
+        days.seal(closed[0].clone()).unwrap();
+        assert!(days.closed(&snapshot, "9999-12-31").is_empty());
+
+        let receipt = days.receipt(&snapshot, 1).unwrap().unwrap();
+        assert_eq!(receipt.verify().unwrap(), closed[0]);
+        assert_eq!(receipt.master.root, days.list().root);
+        assert!(matches!(
+            days.receipt(&snapshot, 3),
+            Err(LogError::OutOfRange { index: 3, size: 3 })
+        ));
+
+        // Later entries start the next day, and seals survive a reopen.
+        log.append("d").unwrap();
+        let days = Days::open(&dir).unwrap();
+        assert_eq!(days.list().days[0].seal, closed[0]);
+        let next = days.closed(&log.snapshot(), "9999-12-31");
+        assert_eq!((next[0].first, next[0].size), (3, 1));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,1471 @@
+//! The engine behind logd, for applications that host a tamper-evident log
+//! in-process instead of running the HTTP server.
+//!
+//! A [`Log`] lives in a directory, in the same files logd uses, so a log
+//! written by either can be served by logd, backed up with `rl backup` and
+//! opened by the other. Every append is written before it returns, along
+//! with the internal nodes it completes, so reopening a large log does not
+//! rehash it.
+//!
+//! ```no_run
+//! # fn run() -> Result<(), reality_log::LogError> {
+//! let mut log = reality_log::Log::open("audit")?;
+//! let appended = log.append("user alice deleted record 7")?;
+//! let proof = log.proof(appended.index)?;
+//! assert!(reality_core::verify(&(&proof).into()).valid);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod days;
+mod nodes;
+pub mod replay;
+mod replica;
+pub mod seal;
+pub mod stream;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use arc_swap::ArcSwap;
+use reality_core::{
+    blob::BlobRef,
+    bundle::EntryBundle,
+    checkpoint::{Checkpoint, SignedCheckpoint},
+    consistency::ConsistencyProof,
+    leaf::{check_labels, entry_leaf, entry_message, Hlc, LabelError, LeafMeta, Provenance},
+    redaction::{RedactRequest, SignedTombstone, Tombstone},
+    signer::Signer,
+    AppendRequest, AppendResponse, BatchAppendResponse, BatchAppended, BatchEntry, InclusionProof,
+    RootResponse,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::{
+    nodes::NodeStore,
+    seal::{is_sealed, PayloadKeys},
+};
+
+/// Hex leaf hashes, in index order.
+pub const LEAVES: &str = "leaves.json";
+/// The [`Entry`] for each leaf, in index order.
+pub const ENTRIES: &str = "entries.json";
+/// Directory caching the tree's internal nodes, rebuilt from the leaves
+/// when missing.
+pub const NODES: &str = "nodes";
+
+/// One appended entry as it is stored.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct Entry {
+    /// The payload as stored, which is sealed in a log that encrypts
+    /// payloads and empty once redacted; [`Snapshot::payload`] has the
+    /// plaintext.
+    pub payload: String,
+    /// Leaf hash of the payload, stored as hex.
+    #[serde(with = "hex_leaf")]
+    pub leaf: [u8; 32],
+    /// What a v2 leaf commits to besides the payload; `None` for a v1 leaf.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<LeafMeta>,
+    /// RFC 3339 time the entry was appended.
+    pub appended_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+    /// Set when the payload was deleted by [`Log::redact`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redaction: Option<SignedTombstone>,
+    /// Provenance kept with the entry but not committed to by its leaf.
+    #[serde(default, skip_serializing_if = "Provenance::is_empty")]
+    pub provenance: Provenance,
+}
+
+/// The provenance of appended entries, split by whether their leaves
+/// commit to it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Origin {
+    /// Committed into each v2 leaf.
+    pub committed: Provenance,
+    /// Only stored with each entry.
+    pub stored: Provenance,
+}
+
+#[derive(Debug, Error)]
+pub enum LogError {
+    #[error("failed to access {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+    #[error("{path} is not valid JSON: {source}")]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("stored leaf {0} is not a 32-byte hex hash")]
+    CorruptLeaf(u64),
+    #[error("{index} is outside a log of {size} entries")]
+    OutOfRange { index: u64, size: u64 },
+    #[error("idempotency key {0:?} was used for a different payload")]
+    KeyConflict(String),
+    #[error("failed to sign: {0}")]
+    Signing(String),
+    #[error("entry {0} is encrypted, and none of the log's payload keys opens it")]
+    Sealed(u64),
+    #[error("entry {0} was redacted")]
+    Redacted(u64),
+    #[error(transparent)]
+    Label(#[from] LabelError),
+    #[error("labels are committed in v2 leaves, which this log does not write")]
+    LabelsNeedV2,
+    #[error("signatures are committed in v2 leaves, which this log does not write")]
+    SignedNeedsV2,
+    #[error("the submitter's signature does not hold for the entry's payload and labels")]
+    BadSignature,
+    #[error("provenance is committed in v2 leaves, which this log does not write")]
+    ProvenanceNeedsV2,
+    #[error("replicated entry {index} {reason}")]
+    BadReplica { index: u64, reason: &'static str },
+    #[error("replicated entries do not rebuild the primary's tree of size {0}")]
+    ReplicaDiverges(u64),
+}
+
+/// SHA-256 of a plaintext payload, as [`Snapshot::holding`] looks it up.
+pub fn payload_digest(payload: &str) -> [u8; 32] {
+    ring::digest::digest(&ring::digest::SHA256, payload.as_bytes())
+        .as_ref()
+        .try_into()
+        .expect("SHA-256 digests are 32 bytes")
+}
+
+/// Entries and the tree (leaves first), shared by a [`Log`] and its
+/// snapshots. Only the `Log` appends to them, so a snapshot reads what it
+/// covers without locking. A redaction replaces the entries with a new store
+/// that shares the tree.
+#[derive(Debug)]
+struct Store {
+    entries: boxcar::Vec<Entry>,
+    nodes: Arc<NodeStore>,
+    keys: Option<Arc<PayloadKeys>>,
+    /// Indices of the entries carrying each label, signed by each
+    /// submitter or holding each payload. Redaction keeps an entry's
+    /// metadata, so the new store shares this one, and payload lookups skip
+    /// redacted entries.
+    index: Arc<RwLock<MetaIndex>>,
+}
+
+/// Entry indices by their payload and what their v2 leaves commit to,
+/// oldest first.
+#[derive(Debug, Default)]
+struct MetaIndex {
+    labels: HashMap<(String, String), Vec<u64>>,
+    /// Keyed by lowercase hex public key.
+    signers: HashMap<String, Vec<u64>>,
+    /// Keyed by SHA-256 of the plaintext payload, for any leaf version.
+    payloads: HashMap<[u8; 32], Vec<u64>>,
+}
+
+impl MetaIndex {
+    /// Index `entry`, opening its payload with `keys` if it is sealed.
+    fn add(&mut self, index: u64, entry: &Entry, keys: Option<&PayloadKeys>) {
+        if entry.redaction.is_none() {
+            let opened = keys
+                .filter(|_| is_sealed(&entry.payload))
+                .and_then(|keys| keys.open(&entry.payload, &entry.leaf));
+            let payload = opened.as_deref().unwrap_or(&entry.payload);
+            self.payloads
+                .entry(payload_digest(payload))
+                .or_default()
+                .push(index);
+        }
+        let Some(meta) = &entry.meta else {
+            return;
+        };
+        for (key, value) in &meta.labels {
+            self.labels
+                .entry((key.clone(), value.clone()))
+                .or_default()
+                .push(index);
+        }
+        if let Some(signer) = meta.signer() {
+            self.signers
+                .entry(signer.to_ascii_lowercase())
+                .or_default()
+                .push(index);
+        }
+    }
+}
+
+/// The log as it was at one size. A snapshot never changes while the log
+/// grows, and taking or cloning one copies nothing, so a reader can hold one
+/// across several calls and get answers that agree with each other.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    store: Arc<Store>,
+    size: u64,
+    root: [u8; 32],
+}
+
+impl Snapshot {
+    /// Number of entries.
+    pub fn len(&self) -> u64 {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    /// Whether new payloads are sealed, as in a log opened with
+    /// [`Log::open_sealed`].
+    pub fn seals(&self) -> bool {
+        self.store.keys.is_some()
+    }
+
+    pub fn get(&self, index: u64) -> Option<&Entry> {
+        if index >= self.size {
+            return None;
+        }
+        self.store.entries.get(usize::try_from(index).ok()?)
+    }
+
+    /// Plaintext payload of entry `index`, opening it if it is sealed.
+    pub fn payload(&self, index: u64) -> Result<String, LogError> {
+        let entry = self.get(index).ok_or(LogError::OutOfRange {
+            index,
+            size: self.size,
+        })?;
+        if entry.redaction.is_some() {
+            return Err(LogError::Redacted(index));
+        }
+        if entry_leaf(entry.payload.as_bytes(), entry.meta.as_ref()) == entry.leaf {
+            return Ok(entry.payload.clone());
+        }
+        self.store
+            .keys
+            .as_ref()
+            .and_then(|keys| keys.open(&entry.payload, &entry.leaf))
+            .ok_or(LogError::Sealed(index))
+    }
+
+    /// Leaf hash of entry `index`.
+    pub fn leaf(&self, index: u64) -> Option<[u8; 32]> {
+        if index >= self.size {
+            return None;
+        }
+        self.store.nodes.leaf(index)
+    }
+
+    /// Entries in index order.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            snapshot: self,
+            next: 0,
+        }
+    }
+
+    /// Indices of the entries labelled `key` = `value`, oldest first.
+    pub fn labelled(&self, key: &str, value: &str) -> Vec<u64> {
+        let index = self.store.index.read().expect("meta index lock poisoned");
+        self.within(index.labels.get(&(key.to_string(), value.to_string())))
+    }
+
+    /// Indices of the entries signed by the submitter with hex key
+    /// `public_key`, oldest first.
+    pub fn signed_by(&self, public_key: &str) -> Vec<u64> {
+        let index = self.store.index.read().expect("meta index lock poisoned");
+        self.within(index.signers.get(&public_key.to_ascii_lowercase()))
+    }
+
+    /// Indices of the entries whose payload has SHA-256 `digest`, oldest
+    /// first. Redacted entries are left out.
+    pub fn holding(&self, digest: &[u8; 32]) -> Vec<u64> {
+        let index = self.store.index.read().expect("meta index lock poisoned");
+        let mut found = self.within(index.payloads.get(digest));
+        found.retain(|at| self.get(*at).is_some_and(|entry| entry.redaction.is_none()));
+        found
+    }
+
+    fn within(&self, indices: Option<&Vec<u64>>) -> Vec<u64> {
+        indices.map_or(Vec::new(), |indices| {
+            indices
+                .iter()
+                .copied()
+                .take_while(|index| *index < self.size)
+                .collect()
+        })
+    }
+
+    /// Root of the whole snapshot.
+    pub fn root(&self) -> RootResponse {
+        RootResponse {
+            root: hex::encode(self.root),
+            size: self.size,
+        }
+    }
+
+    /// Root of the tree formed by the first `size` entries.
+    pub fn root_at(&self, size: u64) -> Result<RootResponse, LogError> {
+        self.check_size(size)?;
+        Ok(RootResponse {
+            root: hex::encode(self.store.nodes.root(size)),
+            size,
+        })
+    }
+
+    /// Inclusion proof for entry `index` against the whole snapshot. Reads
+    /// one stored node per level, however large the log is.
+    pub fn proof(&self, index: u64) -> Result<InclusionProof, LogError> {
+        self.proof_at(index, self.size)
+    }
+
+    /// Inclusion proof for entry `index` against the tree of the first
+    /// `size` entries, such as a root the caller already holds.
+    pub fn proof_at(&self, index: u64, size: u64) -> Result<InclusionProof, LogError> {
+        self.check_size(size)?;
+        if index >= size {
+            return Err(LogError::OutOfRange { index, size });
+        }
+        Ok(self.store.nodes.inclusion(index, size))
+    }
+
+    /// Proof that the tree of the first `old` entries is a prefix of the
+    /// tree of the first `new`.
+    pub fn consistency(&self, old: u64, new: u64) -> Result<ConsistencyProof, LogError> {
+        if old > new {
+            return Err(LogError::OutOfRange {
+                index: old,
+                size: new,
+            });
+        }
+        self.check_size(new)?;
+        Ok(self.store.nodes.consistency(old, new))
+    }
+
+    /// The snapshot's tree head, signed by `signer`.
+    pub fn checkpoint(&self, signer: &dyn Signer) -> Result<SignedCheckpoint, LogError> {
+        Checkpoint {
+            size: self.size,
+            root: hex::encode(self.root),
+            timestamp_nanos: OffsetDateTime::now_utc().unix_timestamp_nanos().to_string(),
+        }
+        .sign(signer)
+        .map_err(|err| LogError::Signing(err.to_string()))
+    }
+
+    /// Receipt for entry `index` against `checkpoint`, a signed head of
+    /// this snapshot. It carries the plaintext payload, since a sealed one
+    /// does not hash to the leaf; a redacted entry's is empty.
+    pub fn bundle(
+        &self,
+        index: u64,
+        checkpoint: SignedCheckpoint,
+    ) -> Result<EntryBundle, LogError> {
+        let proof = self.proof(index)?;
+        let payload = match self.payload(index) {
+            Err(LogError::Redacted(_)) => String::new(),
+            payload => payload?,
+        };
+        let entry = self.get(index).expect("proven entries exist");
+        Ok(EntryBundle {
+            payload,
+            meta: entry.meta.clone(),
+            proof,
+            checkpoint,
+        })
+    }
+
+    fn check_size(&self, size: u64) -> Result<(), LogError> {
+        if size > self.size {
+            return Err(LogError::OutOfRange {
+                index: size,
+                size: self.size,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Entries of a [`Snapshot`] in index order.
+#[derive(Debug, Clone)]
+pub struct Iter<'a> {
+    snapshot: &'a Snapshot,
+    next: u64,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a Entry;
+
+    fn next(&mut self) -> Option<&'a Entry> {
+        let entry = self.snapshot.get(self.next)?;
+        self.next += 1;
+        Some(entry)
+    }
+}
+
+impl<'a> IntoIterator for &'a Snapshot {
+    type Item = &'a Entry;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// The latest [`Snapshot`] of a [`Log`], for readers on other threads.
+/// Loading it never waits for an append, and an append never waits for
+/// readers.
+#[derive(Debug, Clone)]
+pub struct LogReader(Arc<ArcSwap<Snapshot>>);
+
+impl LogReader {
+    pub fn snapshot(&self) -> Arc<Snapshot> {
+        self.0.load_full()
+    }
+}
+
+/// A tamper-evident log stored in one directory.
+///
+/// Appending takes `&mut self`, so writers need a lock; readers can skip it
+/// by taking [`Log::reader`] first. Let only one `Log` at a time open a
+/// directory.
+#[derive(Debug)]
+pub struct Log {
+    dir: PathBuf,
+    store: Arc<Store>,
+    head: Arc<Snapshot>,
+    published: Arc<ArcSwap<Snapshot>>,
+    /// Index of the entry appended under each idempotency key.
+    keys: HashMap<String, u64>,
+    /// How many nodes of each level the files under `nodes/` hold.
+    written: Vec<usize>,
+    /// The clock of the last v2 leaf, while new entries get v2 leaves.
+    clock: Option<Hlc>,
+}
+
+impl Log {
+    /// Open the log in `dir`, creating the directory if needed.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, LogError> {
+        Self::open_with(dir.into(), None)
+    }
+
+    /// Open the log in `dir` so that new payloads are sealed with `keys`
+    /// (see [`seal`]) and sealed payloads can be read back.
+    pub fn open_sealed(dir: impl Into<PathBuf>, keys: PayloadKeys) -> Result<Self, LogError> {
+        Self::open_with(dir.into(), Some(keys))
+    }
+
+    fn open_with(dir: PathBuf, payload_keys: Option<PayloadKeys>) -> Result<Self, LogError> {
+        fs::create_dir_all(&dir).map_err(|source| LogError::Io {
+            path: dir.clone(),
+            source,
+        })?;
+        let leaves: Vec<String> = read_json(&dir.join(LEAVES))?.unwrap_or_default();
+        let entries: Vec<Entry> = read_json(&dir.join(ENTRIES))?.unwrap_or_default();
+        let (nodes, mut written) = NodeStore::load(
+            &dir,
+            leaves
+                .iter()
+                .enumerate()
+                .map(|(index, leaf)| decode_leaf(index as u64, leaf))
+                .collect::<Result<Vec<_>, _>>()?,
+        );
+        // Save what had to be rebuilt for the next open.
+        let _ = nodes.sync(&dir, &mut written);
+        let keys = entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| Some((entry.idempotency_key.clone()?, index as u64)))
+            .collect();
+        let mut index = MetaIndex::default();
+        for (position, entry) in entries.iter().enumerate() {
+            index.add(position as u64, entry, payload_keys.as_ref());
+        }
+        let size = nodes.len();
+        let store = Arc::new(Store {
+            entries: entries.into_iter().collect(),
+            nodes: Arc::new(nodes),
+            keys: payload_keys.map(Arc::new),
+            index: Arc::new(RwLock::new(index)),
+        });
+        let head = Arc::new(Snapshot {
+            root: store.nodes.root(size),
+            store: store.clone(),
+            size,
+        });
+        Ok(Self {
+            dir,
+            store,
+            published: Arc::new(ArcSwap::new(head.clone())),
+            head,
+            keys,
+            written,
+            clock: None,
+        })
+    }
+
+    /// Give entries appended from now on v2 leaves, which commit to their
+    /// index and a hybrid logical clock reading (see
+    /// [`reality_core::leaf`]). The clock carries on from the last v2 entry,
+    /// so it keeps moving forward across restarts.
+    pub fn use_leaf_v2(&mut self) {
+        let last = (0..self.len())
+            .rev()
+            .find_map(|index| self.get(index)?.meta.as_ref().map(|meta| meta.hlc));
+        self.clock = Some(last.unwrap_or_default());
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Flush the log's files and directory to disk. Appends only write
+    /// them, so until this returns an append can be lost to a crash, though
+    /// not to a restart of the process. One call covers every append before
+    /// it, which is what lets logd share it between concurrent appends.
+    pub fn sync(&self) -> Result<(), LogError> {
+        for path in [
+            self.dir.join(LEAVES),
+            self.dir.join(ENTRIES),
+            self.dir.clone(),
+        ] {
+            let synced = match fs::File::open(&path) {
+                Ok(file) => file.sync_all(),
+                // Nothing appended yet.
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+                Err(err) => Err(err),
+            };
+            synced.map_err(|source| LogError::Io { path, source })?;
+        }
+        Ok(())
+    }
+
+    /// The log as it is now.
+    pub fn snapshot(&self) -> Arc<Snapshot> {
+        self.head.clone()
+    }
+
+    /// A handle that sees each append as soon as it is written.
+    pub fn reader(&self) -> LogReader {
+        LogReader(self.published.clone())
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> u64 {
+        self.head.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.is_empty()
+    }
+
+    pub fn get(&self, index: u64) -> Option<&Entry> {
+        self.head.get(index)
+    }
+
+    /// Entries in index order.
+    pub fn iter(&self) -> Iter<'_> {
+        self.head.iter()
+    }
+
+    /// Indices of the entries labelled `key` = `value`, oldest first.
+    pub fn labelled(&self, key: &str, value: &str) -> Vec<u64> {
+        self.head.labelled(key, value)
+    }
+
+    /// Indices of the entries signed by the submitter with hex key
+    /// `public_key`, oldest first.
+    pub fn signed_by(&self, public_key: &str) -> Vec<u64> {
+        self.head.signed_by(public_key)
+    }
+
+    /// Indices of the unredacted entries whose payload has SHA-256
+    /// `digest`, oldest first.
+    pub fn holding(&self, digest: &[u8; 32]) -> Vec<u64> {
+        self.head.holding(digest)
+    }
+
+    /// Append one entry.
+    pub fn append(&mut self, payload: impl Into<String>) -> Result<AppendResponse, LogError> {
+        self.append_labelled(payload, BTreeMap::new())
+    }
+
+    /// Append one entry with `labels`, which its v2 leaf commits to.
+    pub fn append_labelled(
+        &mut self,
+        payload: impl Into<String>,
+        labels: BTreeMap<String, String>,
+    ) -> Result<AppendResponse, LogError> {
+        let request = AppendRequest {
+            payload: payload.into(),
+            labels,
+            signature: None,
+        };
+        self.append_request(request, &Origin::default())
+    }
+
+    /// Append one entry as logd's `/append` receives it: with labels and,
+    /// if its submitter signed it, the signature, both committed to its v2
+    /// leaf, and the provenance of the request.
+    pub fn append_request(
+        &mut self,
+        request: AppendRequest,
+        origin: &Origin,
+    ) -> Result<AppendResponse, LogError> {
+        self.append_request_offloaded(request, None, origin)
+    }
+
+    /// [`Log::append_request`] for a request whose payload the caller moved
+    /// to a blob store, `submitted` being the payload as it arrived.
+    pub fn append_request_offloaded(
+        &mut self,
+        request: AppendRequest,
+        submitted: Option<String>,
+        origin: &Origin,
+    ) -> Result<AppendResponse, LogError> {
+        let entry = BatchEntry {
+            payload: request.payload,
+            idempotency_key: None,
+            labels: request.labels,
+            signature: request.signature,
+        };
+        let batch = self.append_batch_offloaded(vec![entry], &[submitted], origin)?;
+        let appended = &batch.entries[0];
+        Ok(AppendResponse {
+            index: appended.index,
+            size: batch.size,
+            leaf: appended.leaf.clone(),
+            root: batch.root,
+            meta: appended.meta.clone(),
+        })
+    }
+
+    /// Append entries in order, all of them or none, with one write. An
+    /// entry whose idempotency key the log already holds is reported as a
+    /// duplicate instead of appended, which makes a resent batch harmless.
+    /// A key reused with a different payload, labels or signature rejects
+    /// the whole batch, as do labels or signatures the log cannot commit to
+    /// and signatures that do not hold.
+    pub fn append_batch(
+        &mut self,
+        entries: Vec<BatchEntry>,
+    ) -> Result<BatchAppendResponse, LogError> {
+        self.append_batch_from(entries, &Origin::default())
+    }
+
+    /// [`Log::append_batch`], recording `origin` with every entry it
+    /// appends. A duplicate keeps the provenance it was first appended with.
+    pub fn append_batch_from(
+        &mut self,
+        entries: Vec<BatchEntry>,
+        origin: &Origin,
+    ) -> Result<BatchAppendResponse, LogError> {
+        self.append_batch_offloaded(entries, &[], origin)
+    }
+
+    /// [`Log::append_batch_from`] for entries whose payloads the caller moved
+    /// to a blob store, leaving a [`BlobRef`] in their place. `submitted[i]`
+    /// is entry `i`'s payload as it arrived, if it was moved. A submitter
+    /// signs what it submitted, so a signature is checked against that, and
+    /// the reference must be to it.
+    pub fn append_batch_offloaded(
+        &mut self,
+        entries: Vec<BatchEntry>,
+        submitted: &[Option<String>],
+        origin: &Origin,
+    ) -> Result<BatchAppendResponse, LogError> {
+        let now = OffsetDateTime::now_utc();
+        let appended_at = now
+            .format(&Rfc3339)
+            .expect("the current time formats as RFC 3339");
+        let mut clock = self.clock;
+        if clock.is_none() && !origin.committed.is_empty() {
+            return Err(LogError::ProvenanceNeedsV2);
+        }
+        let base = self.len();
+        // Resolve every entry before touching the log, so a conflict
+        // leaves it unchanged.
+        let mut pending: Vec<Entry> = Vec::new();
+        let mut batch_keys: HashMap<String, u64> = HashMap::new();
+        let mut results = Vec::with_capacity(entries.len());
+        for (i, entry) in entries.into_iter().enumerate() {
+            if !entry.labels.is_empty() {
+                check_labels(&entry.labels)?;
+                if clock.is_none() {
+                    return Err(LogError::LabelsNeedV2);
+                }
+            }
+            if let Some(signature) = &entry.signature {
+                if clock.is_none() {
+                    return Err(LogError::SignedNeedsV2);
+                }
+                let signed = match submitted.get(i).and_then(Option::as_ref) {
+                    Some(submitted) => {
+                        let moved = BlobRef::from_payload(&entry.payload)
+                            .is_some_and(|blob| blob.matches(submitted.as_bytes()));
+                        if !moved {
+                            return Err(LogError::BadSignature);
+                        }
+                        submitted
+                    }
+                    None => &entry.payload,
+                };
+                if !signature.verify(&entry_message(signed.as_bytes(), &entry.labels)) {
+                    return Err(LogError::BadSignature);
+                }
+            }
+            let earlier = entry.idempotency_key.as_ref().and_then(|key| {
+                let index = self.keys.get(key).or_else(|| batch_keys.get(key))?;
+                Some((key, *index))
+            });
+            if let Some((key, index)) = earlier {
+                let held = match index.checked_sub(base) {
+                    Some(offset) => &pending[offset as usize],
+                    None => self
+                        .store
+                        .entries
+                        .get(index as usize)
+                        .expect("keys index the log"),
+                };
+                // The same payload has the same leaf under the held entry's
+                // metadata.
+                let held_labels = held.meta.as_ref().map(|meta| &meta.labels);
+                let held_signature = held.meta.as_ref().and_then(|meta| meta.signature.as_ref());
+                if entry_leaf(entry.payload.as_bytes(), held.meta.as_ref()) != held.leaf
+                    || held_labels.map_or(!entry.labels.is_empty(), |held| *held != entry.labels)
+                    || held_signature != entry.signature.as_ref()
+                {
+                    return Err(LogError::KeyConflict(key.clone()));
+                }
+                results.push(BatchAppended {
+                    index,
+                    leaf: hex::encode(held.leaf),
+                    meta: held.meta.clone(),
+                    duplicate: true,
+                });
+                continue;
+            }
+            let index = base + pending.len() as u64;
+            let meta = clock.as_mut().map(|clock| {
+                *clock = clock.tick(now.unix_timestamp_nanos() as u64);
+                LeafMeta {
+                    sequence: index,
+                    hlc: *clock,
+                    labels: entry.labels,
+                    signature: entry.signature,
+                    provenance: origin.committed.clone(),
+                }
+            });
+            let leaf = entry_leaf(entry.payload.as_bytes(), meta.as_ref());
+            if let Some(key) = &entry.idempotency_key {
+                batch_keys.insert(key.clone(), index);
+            }
+            let payload = match &self.store.keys {
+                Some(keys) => keys.seal(&entry.payload, &leaf),
+                None => entry.payload,
+            };
+            results.push(BatchAppended {
+                index,
+                leaf: hex::encode(leaf),
+                meta: meta.clone(),
+                duplicate: false,
+            });
+            pending.push(Entry {
+                payload,
+                leaf,
+                meta,
+                appended_at: appended_at.clone(),
+                idempotency_key: entry.idempotency_key,
+                redaction: None,
+                provenance: origin.stored.clone(),
+            });
+        }
+
+        if !pending.is_empty() {
+            self.commit(pending, batch_keys)?;
+            self.clock = clock;
+        }
+        let head = self.root();
+        Ok(BatchAppendResponse {
+            entries: results,
+            size: head.size,
+            root: head.root,
+        })
+    }
+
+    /// Delete the payload of entry `index` and keep a tombstone signed by
+    /// `signer` in its place, recording `request`: a reason, or a
+    /// [`RedactRequest`] that also names the requester and authority. The
+    /// leaf stays, so roots and proofs do not change. Redacting an entry
+    /// again returns its first tombstone.
+    ///
+    /// The payload leaves `entries.json` before this returns, and leaves
+    /// memory once snapshots taken before the redaction are dropped.
+    pub fn redact(
+        &mut self,
+        index: u64,
+        request: impl Into<RedactRequest>,
+        signer: &dyn Signer,
+    ) -> Result<SignedTombstone, LogError> {
+        let size = self.len();
+        let entry = self
+            .get(index)
+            .ok_or(LogError::OutOfRange { index, size })?;
+        if let Some(tombstone) = &entry.redaction {
+            return Ok(tombstone.clone());
+        }
+        let request = request.into();
+        let tombstone = Tombstone {
+            index,
+            leaf: hex::encode(entry.leaf),
+            reason: request.reason,
+            requested_by: request.requested_by,
+            authority: request.authority,
+            redacted_at: OffsetDateTime::now_utc()
+                .format(&Rfc3339)
+                .expect("the current time formats as RFC 3339"),
+        }
+        .sign(signer)
+        .map_err(|err| LogError::Signing(err.to_string()))?;
+        let redacted = Entry {
+            payload: String::new(),
+            redaction: Some(tombstone.clone()),
+            ..entry.clone()
+        };
+        let entries = || {
+            self.store
+                .entries
+                .iter()
+                .take(size as usize)
+                .map(|(at, entry)| if at as u64 == index { &redacted } else { entry })
+        };
+        write_json(&self.dir.join(ENTRIES), &Seq(entries))?;
+        // Entries are never changed in place, since snapshots read them
+        // without locking; the redacted copy goes into a new store.
+        let store = Arc::new(Store {
+            entries: entries().cloned().collect(),
+            nodes: self.store.nodes.clone(),
+            keys: self.store.keys.clone(),
+            index: self.store.index.clone(),
+        });
+        self.store = store;
+        self.head = Arc::new(Snapshot {
+            store: self.store.clone(),
+            size,
+            root: self.head.root,
+        });
+        self.published.store(self.head.clone());
+        Ok(tombstone)
+    }
+
+    /// Root of the whole log.
+    pub fn root(&self) -> RootResponse {
+        self.head.root()
+    }
+
+    /// Root of the tree formed by the first `size` entries.
+    pub fn root_at(&self, size: u64) -> Result<RootResponse, LogError> {
+        self.head.root_at(size)
+    }
+
+    /// Inclusion proof for entry `index` against the whole log.
+    pub fn proof(&self, index: u64) -> Result<InclusionProof, LogError> {
+        self.head.proof(index)
+    }
+
+    /// Inclusion proof for entry `index` against the tree of the first
+    /// `size` entries.
+    pub fn proof_at(&self, index: u64, size: u64) -> Result<InclusionProof, LogError> {
+        self.head.proof_at(index, size)
+    }
+
+    /// Proof that the tree of the first `old` entries is a prefix of the
+    /// tree of the first `new`.
+    pub fn consistency(&self, old: u64, new: u64) -> Result<ConsistencyProof, LogError> {
+        self.head.consistency(old, new)
+    }
+
+    /// The current tree head, signed by `signer`.
+    pub fn checkpoint(&self, signer: &dyn Signer) -> Result<SignedCheckpoint, LogError> {
+        self.head.checkpoint(signer)
+    }
+
+    /// Write `pending` and add it to the log, with the idempotency `keys`
+    /// it introduces.
+    fn commit(&mut self, pending: Vec<Entry>, keys: HashMap<String, u64>) -> Result<(), LogError> {
+        // Write first: snapshots share the store, so nothing may be added
+        // to it that is not on disk.
+        self.persist(&pending)?;
+        self.keys.extend(keys);
+        let mut index = self.store.index.write().expect("meta index lock poisoned");
+        for entry in pending {
+            let keys = self.store.keys.as_deref();
+            index.add(self.store.entries.count() as u64, &entry, keys);
+            self.store.nodes.push(entry.leaf);
+            self.store.entries.push(entry);
+        }
+        drop(index);
+        // The node files are only a cache: a failed write is retried by the
+        // next append, and anything missing is rebuilt at open.
+        let _ = self.store.nodes.sync(&self.dir, &mut self.written);
+        let size = self.store.nodes.len();
+        self.head = Arc::new(Snapshot {
+            store: self.store.clone(),
+            size,
+            root: self.store.nodes.root(size),
+        });
+        self.published.store(self.head.clone());
+        Ok(())
+    }
+
+    /// Write the leaves and then the entries, the order `rl backup` expects,
+    /// with `pending` after what the log holds. Leaves are written as hex,
+    /// which every tool that reads the directory understands.
+    fn persist(&self, pending: &[Entry]) -> Result<(), LogError> {
+        let size = self.len();
+        write_json(
+            &self.dir.join(LEAVES),
+            &Seq(|| {
+                self.store
+                    .nodes
+                    .leaves(size)
+                    .chain(pending.iter().map(|entry| entry.leaf))
+                    .map(hex::encode)
+            }),
+        )?;
+        write_json(
+            &self.dir.join(ENTRIES),
+            &Seq(|| {
+                self.store
+                    .entries
+                    .iter()
+                    .take(size as usize)
+                    .map(|(_, entry)| entry)
+                    .chain(pending)
+            }),
+        )
+    }
+}
+
+impl<'a> IntoIterator for &'a Log {
+    type Item = &'a Entry;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Serializes the items `.0` yields as a sequence, so a file can be written
+/// from several sources without collecting them first.
+struct Seq<F>(F);
+
+impl<F, I> Serialize for Seq<F>
+where
+    F: Fn() -> I,
+    I: Iterator,
+    I::Item: Serialize,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq((self.0)())
+    }
+}
+
+fn decode_leaf(index: u64, leaf: &str) -> Result<[u8; 32], LogError> {
+    hex::decode(leaf)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(LogError::CorruptLeaf(index))
+}
+
+/// Serde for a leaf hash as hex, the way logd's files and API carry it.
+mod hex_leaf {
+    use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(leaf: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(leaf))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 32], D::Error> {
+        let hex = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        let mut leaf = [0u8; 32];
+        hex::decode_to_slice(hex.as_ref(), &mut leaf)
+            .map_err(|_| D::Error::custom("a leaf hash must be 32 bytes of hex"))?;
+        Ok(leaf)
+    }
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<Option<T>, LogError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(source) => {
+            return Err(LogError::Io {
+                path: path.to_path_buf(),
+                source,
+            })
+        }
+    };
+    if content.trim().is_empty() {
+        return Ok(None);
+    }
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|source| LogError::Json {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+fn write_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<(), LogError> {
+    let json = serde_json::to_string_pretty(value).map_err(|source| LogError::Json {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    fs::write(path, json).map_err(|source| LogError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reality_core::{consistency::verify_consistency, leaf_hash, signer::KeyFileSigner, verify};
+
+    fn keyed(payload: &str, key: &str) -> BatchEntry {
+        BatchEntry {
+            payload: payload.into(),
+            idempotency_key: Some(key.into()),
+            labels: Default::default(),
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn appends_proves_and_reopens() {
+        let dir = std::env::temp_dir().join(format!("reality-log-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut log = Log::open(&dir).unwrap();
+        assert!(log.is_empty());
+        log.sync().unwrap();
+        for payload in ["a", "b", "c"] {
+            log.append(payload).unwrap();
+        }
+        log.sync().unwrap();
+        let old = log.root();
+        let before = log.snapshot();
+        let reader = log.reader();
+
+        let batch = log
+            .append_batch(vec![keyed("d", "k1"), keyed("e", "k2"), keyed("d", "k1")])
+            .unwrap();
+        assert_eq!(batch.size, 5);
+        assert!(batch.entries[2].duplicate);
+        assert_eq!(batch.entries[2].index, 3);
+        let resent = log.append_batch(vec![keyed("e", "k2")]).unwrap();
+        assert!(resent.entries[0].duplicate);
+        assert_eq!(resent.root, batch.root);
+        assert!(matches!(
+            log.append_batch(vec![keyed("f", "k3"), keyed("other", "k1")]),
+            Err(LogError::KeyConflict(key)) if key == "k1"
+        ));
+        assert_eq!(log.len(), 5);
+        // Snapshots keep answering for the size they were taken at.
+        assert_eq!(before.len(), 3);
+        assert_eq!(before.root(), old);
+        assert_eq!(before.proof(2).unwrap().root, old.root);
+        assert_eq!(log.proof_at(2, 3).unwrap(), before.proof(2).unwrap());
+        assert!(matches!(
+            before.proof_at(0, 4),
+            Err(LogError::OutOfRange { index: 4, size: 3 })
+        ));
+        assert!(matches!(
+            before.proof_at(3, 3),
+            Err(LogError::OutOfRange { index: 3, size: 3 })
+        ));
+        assert!(before.get(3).is_none() && before.leaf(3).is_none());
+        assert_eq!(before.iter().count(), 3);
+        assert_eq!(reader.snapshot().root().root, batch.root);
+
+        for index in 0..log.len() {
+            let proof = log.proof(index).unwrap();
+            assert_eq!(proof.root, batch.root);
+            assert!(verify(&(&proof).into()).valid);
+        }
+        assert!(matches!(
+            log.proof(5),
+            Err(LogError::OutOfRange { index: 5, size: 5 })
+        ));
+        let consistency = log.consistency(3, 5).unwrap();
+        assert_eq!(consistency.old_root, old.root);
+        assert!(verify_consistency(&consistency));
+
+        let signer = KeyFileSigner::from_seed([6u8; 32]);
+        let checkpoint = log.checkpoint(&signer).unwrap();
+        assert!(checkpoint.verify());
+        assert_eq!(checkpoint.checkpoint.root, batch.root);
+
+        drop(log);
+        let log = Log::open(&dir).unwrap();
+        assert_eq!(log.root().root, batch.root);
+        let payloads: Vec<_> = log.iter().map(|entry| entry.payload.as_str()).collect();
+        assert_eq!(payloads, ["a", "b", "c", "d", "e"]);
+        assert_eq!(log.get(4).unwrap().idempotency_key.as_deref(), Some("k2"));
+        // Leaves live in memory as bytes but stay hex on disk.
+        let leaf = log.snapshot().leaf(4).unwrap();
+        assert_eq!(leaf, leaf_hash(b"e"));
+        let stored = fs::read_to_string(dir.join(ENTRIES)).unwrap();
+        assert!(stored.contains(&format!("\"leaf\": \"{}\"", hex::encode(leaf))));
+        let leaves: Vec<String> = read_json(&dir.join(LEAVES)).unwrap().unwrap();
+        assert_eq!(leaves[4], hex::encode(leaf));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn redacts_payloads_but_keeps_proofs() {
+        let dir = std::env::temp_dir().join(format!("reality-log-redact-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let signer = KeyFileSigner::from_seed([8u8; 32]);
+        let mut log = Log::open(&dir).unwrap();
+        log.append("a").unwrap();
+        log.append_batch(vec![keyed("alice@example.com", "k")])
+            .unwrap();
+        let root = log.root();
+        let before = log.snapshot();
+        let reader = log.reader();
+
+        let tombstone = log.redact(1, "erasure request", &signer).unwrap();
+        assert!(tombstone.covers(1, &leaf_hash(b"alice@example.com")));
+        assert_eq!(tombstone.tombstone.reason, "erasure request");
+        assert_eq!(log.redact(1, "again", &signer).unwrap(), tombstone);
+        assert!(matches!(
+            log.redact(2, "", &signer),
+            Err(LogError::OutOfRange { index: 2, size: 2 })
+        ));
+        let entry = log.get(1).unwrap();
+        assert_eq!(entry.payload, "");
+        assert_eq!(entry.redaction.as_ref(), Some(&tombstone));
+        assert!(matches!(
+            reader.snapshot().payload(1),
+            Err(LogError::Redacted(1))
+        ));
+        // The tree is untouched, and earlier snapshots keep their view.
+        assert_eq!(log.root(), root);
+        assert!(verify(&(&log.proof(1).unwrap()).into()).valid);
+        assert_eq!(before.get(1).unwrap().payload, "alice@example.com");
+        // Resends are still matched by leaf.
+        let resent = log
+            .append_batch(vec![keyed("alice@example.com", "k")])
+            .unwrap();
+        assert!(resent.entries[0].duplicate);
+        log.append("c").unwrap();
+        assert_eq!(reader.snapshot().get(2).unwrap().payload, "c");
+
+        drop(log);
+        let stored = fs::read_to_string(dir.join(ENTRIES)).unwrap();
+        assert!(!stored.contains("alice"));
+        let log = Log::open(&dir).unwrap();
+        assert_eq!(log.get(1).unwrap().redaction.as_ref(), Some(&tombstone));
+        assert_eq!(log.snapshot().payload(2).unwrap(), "c");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn finds_entries_by_payload() {
+        let dir = std::env::temp_dir().join(format!("reality-log-holding-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let signer = KeyFileSigner::from_seed([6; 32]);
+        let mut log = Log::open(&dir).unwrap();
+        log.append("contract").unwrap();
+        log.use_leaf_v2();
+        log.append("invoice").unwrap();
+        log.append("contract").unwrap();
+        let snapshot = log.snapshot();
+        log.append("contract").unwrap();
+        assert_eq!(log.holding(&payload_digest("contract")), [0, 2, 3]);
+        // A snapshot only finds what it covers.
+        assert_eq!(snapshot.holding(&payload_digest("contract")), [0, 2]);
+        assert!(log.holding(&payload_digest("receipt")).is_empty());
+
+        log.redact(0, "erasure", &signer).unwrap();
+        assert_eq!(log.holding(&payload_digest("contract")), [2, 3]);
+        drop(log);
+        let log = Log::open(&dir).unwrap();
+        assert_eq!(log.holding(&payload_digest("contract")), [2, 3]);
+        assert_eq!(log.holding(&payload_digest("invoice")), [1]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn seals_new_payloads() {
+        let dir = std::env::temp_dir().join(format!("reality-log-sealed-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        Log::open(&dir).unwrap().append("plain").unwrap();
+        let keys = || PayloadKeys::new(1, [9; 32]);
+        let mut log = Log::open_sealed(&dir, keys()).unwrap();
+        log.append("secret").unwrap();
+        log.append_batch(vec![keyed("keyed", "k")]).unwrap();
+        // Resends are matched by leaf, which sealing leaves alone.
+        let resent = log.append_batch(vec![keyed("keyed", "k")]).unwrap();
+        assert!(resent.entries[0].duplicate);
+        assert_eq!(resent.size, 3);
+        let stored = fs::read_to_string(dir.join(ENTRIES)).unwrap();
+        assert!(stored.contains("\"plain\"") && !stored.contains("secret"));
+        assert!(seal::is_sealed(&log.get(1).unwrap().payload));
+        // Leaves hash the plaintext, so proofs do not change.
+        assert_eq!(log.snapshot().leaf(1), Some(leaf_hash(b"secret")));
+        assert!(verify(&(&log.proof(1).unwrap()).into()).valid);
+
+        drop(log);
+        let log = Log::open_sealed(&dir, keys()).unwrap();
+        let snapshot = log.snapshot();
+        assert!(snapshot.seals());
+        let payloads: Vec<_> = (0..3).map(|i| snapshot.payload(i).unwrap()).collect();
+        assert_eq!(payloads, ["plain", "secret", "keyed"]);
+        // Sealed payloads are found by their plaintext.
+        assert_eq!(log.holding(&payload_digest("secret")), [1]);
+        assert!(matches!(
+            snapshot.payload(3),
+            Err(LogError::OutOfRange { .. })
+        ));
+        // Bundles carry the plaintext, so they verify.
+        let signer = KeyFileSigner::from_seed([5; 32]);
+        let checkpoint = snapshot.checkpoint(&signer).unwrap();
+        let bundle = snapshot.bundle(1, checkpoint).unwrap();
+        assert_eq!(bundle.payload, "secret");
+        bundle
+            .verify(Some(&hex::encode(signer.public_key())))
+            .unwrap();
+        let unkeyed = Log::open(&dir).unwrap().snapshot();
+        assert!(!unkeyed.seals());
+        assert_eq!(unkeyed.payload(0).unwrap(), "plain");
+        assert!(matches!(unkeyed.payload(1), Err(LogError::Sealed(1))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn commits_sequence_and_clock_into_v2_leaves() {
+        let dir = std::env::temp_dir().join(format!("reality-log-v2-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut log = Log::open(&dir).unwrap();
+        log.append("v1").unwrap();
+        log.use_leaf_v2();
+        let first = log.append("a").unwrap();
+        let batch = log
+            .append_batch(vec![keyed("b", "k"), keyed("c", "k2")])
+            .unwrap();
+        let meta = first.meta.clone().unwrap();
+        assert_eq!(meta.sequence, 1);
+        assert_eq!(hex::encode(meta.leaf(b"a")), first.leaf);
+        let metas: Vec<_> = batch
+            .entries
+            .iter()
+            .map(|e| e.meta.clone().unwrap())
+            .collect();
+        assert!(metas[0].follows(&meta) && metas[1].follows(&metas[0]));
+        assert!(log.get(0).unwrap().meta.is_none());
+        assert!(verify(&(&log.proof(2).unwrap()).into()).valid);
+        // Resends are matched under the held entry's metadata.
+        let resent = log.append_batch(vec![keyed("b", "k")]).unwrap();
+        assert!(resent.entries[0].duplicate);
+        assert_eq!(resent.entries[0].meta, Some(metas[0].clone()));
+        assert!(matches!(
+            log.append_batch(vec![keyed("other", "k")]),
+            Err(LogError::KeyConflict(_))
+        ));
+
+        drop(log);
+        let mut log = Log::open(&dir).unwrap();
+        assert_eq!(log.get(3).unwrap().meta, Some(metas[1].clone()));
+        assert_eq!(log.snapshot().payload(2).unwrap(), "b");
+        log.use_leaf_v2();
+        let next = log.append("d").unwrap().meta.unwrap();
+        assert!(next.follows(&metas[1]));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn indexes_labels_committed_in_leaves() {
+        let dir = std::env::temp_dir().join(format!("reality-log-labels-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let labels = |pairs: &[(&str, &str)]| -> BTreeMap<String, String> {
+            pairs
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect()
+        };
+        let checkout = labels(&[("service", "checkout"), ("env", "prod")]);
+        let mut log = Log::open(&dir).unwrap();
+        assert!(matches!(
+            log.append_labelled("a", checkout.clone()),
+            Err(LogError::LabelsNeedV2)
+        ));
+        log.use_leaf_v2();
+        assert!(matches!(
+            log.append_labelled("a", labels(&[("bad key", "")])),
+            Err(LogError::Label(LabelError::BadKey(_)))
+        ));
+        let first = log.append_labelled("a", checkout.clone()).unwrap();
+        assert_eq!(first.meta.as_ref().unwrap().labels, checkout);
+        log.append("b").unwrap();
+        let mut entry = keyed("c", "k");
+        entry.labels = labels(&[("service", "checkout")]);
+        log.append_batch(vec![entry.clone()]).unwrap();
+        assert_eq!(log.labelled("service", "checkout"), [0, 2]);
+        assert_eq!(log.labelled("env", "prod"), [0]);
+        assert!(log.labelled("env", "dev").is_empty());
+
+        // A resend must carry the labels it was appended with.
+        assert!(log.append_batch(vec![entry.clone()]).unwrap().entries[0].duplicate);
+        entry.labels.clear();
+        assert!(matches!(
+            log.append_batch(vec![entry]),
+            Err(LogError::KeyConflict(_))
+        ));
+
+        // Rewriting a label on disk breaks the entry's leaf.
+        drop(log);
+        let log = Log::open(&dir).unwrap();
+        assert_eq!(log.labelled("service", "checkout"), [0, 2]);
+        let stored = log.get(0).unwrap();
+        let mut rewritten = stored.meta.clone().unwrap();
+        rewritten.labels.insert("env".into(), "dev".into());
+        assert_ne!(rewritten.leaf(b"a"), stored.leaf);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn commits_and_indexes_submitter_signatures() {
+        use reality_core::{
+            leaf::entry_message,
+            signer::{DetachedSignature, Signer},
+        };
+
+        let dir = std::env::temp_dir().join(format!("reality-log-signed-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let submitter = KeyFileSigner::from_seed([9; 32]);
+        let key = hex::encode(submitter.public_key());
+        let signed = |payload: &str| AppendRequest {
+            payload: payload.into(),
+            labels: BTreeMap::new(),
+            signature: Some(
+                DetachedSignature::create(
+                    &submitter,
+                    &entry_message(payload.as_bytes(), &BTreeMap::new()),
+                )
+                .unwrap(),
+            ),
+        };
+        let mut log = Log::open(&dir).unwrap();
+        assert!(matches!(
+            log.append_request(signed("a"), &Origin::default()),
+            Err(LogError::SignedNeedsV2)
+        ));
+        log.use_leaf_v2();
+        let mut forged = signed("a");
+        forged.payload = "b".into();
+        assert!(matches!(
+            log.append_request(forged, &Origin::default()),
+            Err(LogError::BadSignature)
+        ));
+        let first = log.append_request(signed("a"), &Origin::default()).unwrap();
+        assert_eq!(first.meta.as_ref().unwrap().signer(), Some(key.as_str()));
+        log.append("b").unwrap();
+        log.append_request(signed("c"), &Origin::default()).unwrap();
+        assert_eq!(log.signed_by(&key.to_ascii_uppercase()), [0, 2]);
+
+        // Another submitter cannot claim a key's entry on resend.
+        let mut entry = keyed("d", "k");
+        entry.signature = signed("d").signature;
+        log.append_batch(vec![entry.clone()]).unwrap();
+        entry.signature = None;
+        assert!(matches!(
+            log.append_batch(vec![entry]),
+            Err(LogError::KeyConflict(_))
+        ));
+
+        // A large payload moved to a blob store is checked as submitted.
+        let large = "e".repeat(64);
+        let mut offloaded = signed(&large);
+        offloaded.payload = BlobRef::new(large.as_bytes()).to_payload();
+        let moved = Some(large.clone());
+        assert!(matches!(
+            log.append_request(offloaded.clone(), &Origin::default()),
+            Err(LogError::BadSignature)
+        ));
+        assert!(matches!(
+            log.append_request_offloaded(
+                offloaded.clone(),
+                Some("f".repeat(64)),
+                &Origin::default()
+            ),
+            Err(LogError::BadSignature)
+        ));
+        let blob = log
+            .append_request_offloaded(offloaded, moved, &Origin::default())
+            .unwrap();
+        assert_eq!(blob.index, 4);
+
+        drop(log);
+        let log = Log::open(&dir).unwrap();
+        assert_eq!(log.signed_by(&key), [0, 2, 3, 4]);
+        let stored = log.get(0).unwrap();
+        let mut unsigned = stored.meta.clone().unwrap();
+        unsigned.signature = None;
+        assert_ne!(unsigned.leaf(b"a"), stored.leaf);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn commits_or_stores_provenance() {
+        let dir = std::env::temp_dir().join(format!("reality-log-origin-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let origin = Origin {
+            committed: Provenance {
+                submitter: Some("billing".into()),
+                ..Provenance::default()
+            },
+            stored: Provenance {
+                source_ip: Some("5e".into()),
+                ..Provenance::default()
+            },
+        };
+        let mut log = Log::open(&dir).unwrap();
+        assert!(matches!(
+            log.append_batch_from(vec![keyed("a", "k")], &origin),
+            Err(LogError::ProvenanceNeedsV2)
+        ));
+        // Stored provenance alone needs no v2 leaves.
+        let stored_only = Origin {
+            committed: Provenance::default(),
+            ..origin.clone()
+        };
+        log.append_batch_from(vec![keyed("a", "k")], &stored_only)
+            .unwrap();
+        log.use_leaf_v2();
+        log.append_batch_from(vec![keyed("b", "j")], &origin)
+            .unwrap();
+        // A resend from elsewhere is still a duplicate, with its first
+        // provenance.
+        let resent = log
+            .append_batch_from(vec![keyed("b", "j")], &Origin::default())
+            .unwrap();
+        assert!(resent.entries[0].duplicate);
+
+        drop(log);
+        let log = Log::open(&dir).unwrap();
+        let (first, second) = (log.get(0).unwrap(), log.get(1).unwrap());
+        assert_eq!(first.provenance, origin.stored);
+        assert_eq!(second.provenance, origin.stored);
+        let meta = second.meta.clone().unwrap();
+        assert_eq!(meta.provenance, origin.committed);
+        let mut untraced = meta.clone();
+        untraced.provenance = Provenance::default();
+        assert_ne!(untraced.leaf(b"b"), second.leaf);
+        assert_eq!(meta.leaf(b"b"), second.leaf);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,363 @@
+//! Internal nodes of the tree, kept as the log grows so roots and proofs read
+//! one node per level instead of rehashing every leaf.
+//!
+//! Only complete subtrees are stored: level `k` holds the hashes of the
+//! `size >> k` subtrees of `2^k` leaves that are all present. Those never
+//! change once written, so the store works for every earlier size too. The
+//! tree duplicates an odd last node, so the right edge of a tree (at most one
+//! incomplete node per level) is rebuilt from stored nodes on each request,
+//! which costs one hash per level.
+//!
+//! Levels above the leaves are also kept on disk, one file of raw 32-byte
+//! hashes per level under `nodes/`, so opening a log reads them back instead
+//! of rehashing every leaf. The files are a cache of what the leaves
+//! determine: whatever is missing or does not match is rebuilt at open.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use reality_core::{
+    consistency::ConsistencyProof,
+    digest::{DefaultDigest, LogDigest},
+    empty_root, node_hash, Direction, InclusionProof, ProofStep,
+};
+
+use crate::stream::RootFold;
+
+/// Enough levels for any `u64` number of leaves.
+const LEVELS: usize = 64;
+
+/// Levels only grow, and only the [`crate::Log`] that owns the store appends
+/// to them, so readers of earlier sizes never wait for or see a write.
+#[derive(Debug)]
+pub(crate) struct NodeStore {
+    /// The leaves first; each later level halves the one below.
+    levels: Vec<boxcar::Vec<[u8; 32]>>,
+}
+
+impl Default for NodeStore {
+    fn default() -> Self {
+        Self {
+            levels: (0..LEVELS).map(|_| boxcar::Vec::new()).collect(),
+        }
+    }
+}
+
+impl NodeStore {
+    /// The store for `leaves`, taking each level from its file in `dir`
+    /// where the file agrees with the level below. Also returns how many
+    /// nodes of each level the files already hold.
+    pub fn load(dir: &Path, leaves: Vec<[u8; 32]>) -> (Self, Vec<usize>) {
+        let size = leaves.len();
+        let mut levels = Vec::with_capacity(LEVELS);
+        levels.push(leaves);
+        let mut written = vec![0];
+        let mut level = 1;
+        while size >> level > 0 {
+            let expected = size >> level;
+            let below = &levels[level - 1];
+            let parent = |index: usize| node_hash(&below[2 * index], &below[2 * index + 1]);
+            let mut nodes = read_level(&level_path(dir, level), expected).unwrap_or_default();
+            if nodes
+                .last()
+                .is_some_and(|last| *last != parent(nodes.len() - 1))
+            {
+                nodes.clear();
+            }
+            written.push(nodes.len());
+            nodes.extend((nodes.len()..expected).map(parent));
+            levels.push(nodes);
+            level += 1;
+        }
+        let store = Self::default();
+        for (level, nodes) in levels.into_iter().enumerate() {
+            for node in nodes {
+                store.levels[level].push(node);
+            }
+        }
+        (store, written)
+    }
+
+    /// Write the nodes the files in `dir` are missing, given how many of
+    /// each level they hold. A failed write is retried by the next sync.
+    pub fn sync(&self, dir: &Path, written: &mut Vec<usize>) -> io::Result<()> {
+        for level in 1..LEVELS {
+            let count = self.levels[level].count();
+            if count == 0 {
+                break;
+            }
+            if written.len() == level {
+                written.push(0);
+            }
+            if written[level] == count {
+                continue;
+            }
+            let path = level_path(dir, level);
+            if level == 1 {
+                fs::create_dir_all(path.parent().expect("level files are in a directory"))?;
+            }
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&path)?;
+            let from = written[level];
+            let bytes: Vec<u8> = (from..count)
+                .flat_map(|index| self.levels[level][index])
+                .collect();
+            file.set_len((from * 32) as u64)?;
+            file.seek(SeekFrom::Start((from * 32) as u64))?;
+            file.write_all(&bytes)?;
+            written[level] = count;
+        }
+        Ok(())
+    }
+
+    /// Number of leaves.
+    pub fn len(&self) -> u64 {
+        self.levels[0].count() as u64
+    }
+
+    pub fn leaf(&self, index: u64) -> Option<[u8; 32]> {
+        self.levels[0].get(usize::try_from(index).ok()?).copied()
+    }
+
+    /// The first `size` leaves.
+    pub fn leaves(&self, size: u64) -> impl Iterator<Item = [u8; 32]> + '_ {
+        (0..size).map(|index| self.levels[0][index as usize])
+    }
+
+    /// Add a leaf and every subtree it completes.
+    pub fn push(&self, leaf: [u8; 32]) {
+        self.levels[0].push(leaf);
+        let size = self.levels[0].count();
+        let mut level = 1;
+        while size >> level > self.levels[level].count() {
+            let below = &self.levels[level - 1];
+            let last = below.count() - 1;
+            self.levels[level].push(node_hash(&below[last - 1], &below[last]));
+            level += 1;
+        }
+    }
+
+    /// Root of the tree of the first `size` leaves; `size` must not exceed
+    /// the number stored.
+    pub fn root(&self, size: u64) -> [u8; 32] {
+        match self.edge(size).last() {
+            Some(root) => *root,
+            None => empty_root(),
+        }
+    }
+
+    /// A fold of the first `size` leaves, to compute roots of the tree
+    /// with more leaves added without adding them.
+    pub fn fold(&self, size: u64) -> RootFold {
+        RootFold::resume(size, |level| {
+            self.levels[level][((size >> level) - 1) as usize]
+        })
+    }
+
+    /// Proof for leaf `index` in the tree of the first `size` leaves;
+    /// `index < size`, and `size` must not exceed the number stored.
+    pub fn inclusion(&self, index: u64, size: u64) -> InclusionProof {
+        let edge = self.edge(size);
+        let mut path = Vec::with_capacity(edge.len().saturating_sub(1));
+        let mut idx = index;
+        for level in 0..edge.len() - 1 {
+            let (direction, sibling) = if idx % 2 == 1 {
+                (Direction::Left, idx - 1)
+            } else {
+                (Direction::Right, (idx + 1).min(width(size, level) - 1))
+            };
+            path.push(ProofStep {
+                direction,
+                hash: hex::encode(self.node(&edge, size, level, sibling)),
+            });
+            idx /= 2;
+        }
+        InclusionProof {
+            index,
+            leaf: hex::encode(self.levels[0][index as usize]),
+            path,
+            root: hex::encode(edge[edge.len() - 1]),
+            size,
+            algorithm: DefaultDigest::ALGORITHM,
+        }
+    }
+
+    /// The same proof `reality_core::consistency::consistency_proof` builds
+    /// from all the leaves; `old <= new`, and `new` is at most the number stored.
+    pub fn consistency(&self, old: u64, new: u64) -> ConsistencyProof {
+        let edge = self.edge(new);
+        let mut proof = ConsistencyProof {
+            old_size: old,
+            old_root: hex::encode(self.root(old)),
+            new_size: new,
+            new_root: hex::encode(self.root(new)),
+            path: Vec::new(),
+            algorithm: DefaultDigest::ALGORITHM,
+        };
+        if old == 0 {
+            return proof;
+        }
+        let start = old.trailing_zeros() as usize;
+        // The old tree's frontier: complete subtrees, so all stored.
+        for level in (start..edge.len()).filter(|level| old >> level & 1 == 1) {
+            let index = ((old >> level) - 1) as usize;
+            proof.path.push(hex::encode(self.levels[level][index]));
+        }
+        for level in start..edge.len() - 1 {
+            let idx = (old - 1) >> level;
+            if idx & 1 == 0 && idx + 1 < width(new, level) {
+                proof
+                    .path
+                    .push(hex::encode(self.node(&edge, new, level, idx + 1)));
+            }
+        }
+        proof
+    }
+
+    /// The last node of each level of the tree of the first `size` leaves,
+    /// from the last leaf up to the root; empty for an empty tree.
+    fn edge(&self, size: u64) -> Vec<[u8; 32]> {
+        let mut edge: Vec<[u8; 32]> = Vec::new();
+        if size == 0 {
+            return edge;
+        }
+        edge.push(self.levels[0][size as usize - 1]);
+        let mut level = 1;
+        while width(size, level - 1) > 1 {
+            let last = width(size, level) - 1;
+            let left = self.node(&edge, size, level - 1, 2 * last);
+            let right = if 2 * last + 1 < width(size, level - 1) {
+                self.node(&edge, size, level - 1, 2 * last + 1)
+            } else {
+                left
+            };
+            edge.push(node_hash(&left, &right));
+            level += 1;
+        }
+        edge
+    }
+
+    /// Node `index` of `level` in the tree of the first `size` leaves, given
+    /// that tree's right edge up to at least `level`.
+    fn node(&self, edge: &[[u8; 32]], size: u64, level: usize, index: u64) -> [u8; 32] {
+        if (index + 1) << level <= size {
+            self.levels[level][index as usize]
+        } else {
+            edge[level]
+        }
+    }
+}
+
+/// Number of nodes at `level` of a non-empty tree of `size` leaves.
+fn width(size: u64, level: usize) -> u64 {
+    ((size - 1) >> level) + 1
+}
+
+fn level_path(dir: &Path, level: usize) -> PathBuf {
+    dir.join(crate::NODES).join(level.to_string())
+}
+
+/// Up to `limit` whole nodes from a level file.
+fn read_level(path: &Path, limit: usize) -> io::Result<Vec<[u8; 32]>> {
+    let mut bytes = Vec::new();
+    File::open(path)?
+        .take((limit * 32) as u64)
+        .read_to_end(&mut bytes)?;
+    Ok(bytes
+        .chunks_exact(32)
+        .map(|node| node.try_into().expect("chunks are 32 bytes"))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reality_core::{consistency::consistency_proof, leaf_hash, make_proof, root};
+
+    fn levels(store: &NodeStore) -> Vec<Vec<[u8; 32]>> {
+        store
+            .levels
+            .iter()
+            .map(|nodes| nodes.iter().map(|(_, node)| *node).collect::<Vec<_>>())
+            .filter(|nodes| !nodes.is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn matches_core_for_every_size() {
+        let leaves: Vec<_> = (0..70u32).map(|i| leaf_hash(&i.to_be_bytes())).collect();
+        let store = NodeStore::default();
+        assert_eq!(store.root(0), empty_root());
+        for leaf in &leaves {
+            store.push(*leaf);
+        }
+        for size in 0..=leaves.len() {
+            let prefix = &leaves[..size];
+            assert_eq!(store.root(size as u64), root(prefix), "size {size}");
+            let mut fold = store.fold(size as u64);
+            for leaf in &leaves[size..(size + 5).min(leaves.len())] {
+                fold.push(*leaf);
+            }
+            assert_eq!(fold.root(), root(&leaves[..fold.size() as usize]));
+            for index in 0..size {
+                assert_eq!(
+                    store.inclusion(index as u64, size as u64),
+                    make_proof(prefix, index).unwrap(),
+                    "size {size} index {index}"
+                );
+            }
+            for old in 0..=size {
+                assert_eq!(
+                    store.consistency(old as u64, size as u64),
+                    consistency_proof(prefix, old, size).unwrap(),
+                    "old {old} new {size}"
+                );
+            }
+        }
+        assert_eq!(store.levels[6].count(), 1);
+    }
+
+    #[test]
+    fn persists_levels_and_repairs_them() {
+        let dir = std::env::temp_dir().join(format!("reality-log-nodes-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let leaves: Vec<_> = (0..45u32).map(|i| leaf_hash(&i.to_be_bytes())).collect();
+        let (store, mut written) = NodeStore::load(&dir, leaves[..30].to_vec());
+        store.sync(&dir, &mut written).unwrap();
+        for leaf in &leaves[30..] {
+            store.push(*leaf);
+        }
+        store.sync(&dir, &mut written).unwrap();
+        assert_eq!(fs::metadata(level_path(&dir, 1)).unwrap().len(), 22 * 32);
+        assert_eq!(fs::metadata(level_path(&dir, 5)).unwrap().len(), 32);
+
+        let (reopened, reread) = NodeStore::load(&dir, leaves.clone());
+        assert_eq!(levels(&reopened), levels(&store));
+        assert_eq!(reread, written);
+        // A shorter log reads a prefix of each level.
+        let (shorter, _) = NodeStore::load(&dir, leaves[..20].to_vec());
+        assert_eq!(shorter.root(20), root(&leaves[..20]));
+
+        // Torn and wrong files are rebuilt from the leaves.
+        fs::OpenOptions::new()
+            .append(true)
+            .open(level_path(&dir, 1))
+            .unwrap()
+            .write_all(&[7; 5])
+            .unwrap();
+        fs::write(level_path(&dir, 2), [0u8; 64]).unwrap();
+        fs::remove_file(level_path(&dir, 3)).unwrap();
+        let (repaired, mut rewritten) = NodeStore::load(&dir, leaves.clone());
+        assert_eq!(levels(&repaired), levels(&store));
+        assert_eq!(rewritten[1..4], [22, 0, 0]);
+        repaired.sync(&dir, &mut rewritten).unwrap();
+        assert_eq!(NodeStore::load(&dir, leaves).1, written);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
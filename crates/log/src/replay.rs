@@ -0,0 +1,341 @@
+//! Replaying a log from its files. [`Stored`] reads `entries.json` and
+//! `leaves.json` as they are on disk, without [`Log::open`] rebuilding
+//! anything, and [`Stored::replay`] checks them entry by entry: each payload
+//! against its leaf, each leaf against `leaves.json`, and the tree rebuilt
+//! from those leaves against roots recorded elsewhere, such as witness
+//! cosignatures and anchors. The replay stops at the first disagreement and
+//! names the first index that may differ.
+
+use std::{fmt, path::Path, sync::Arc};
+
+use reality_core::leaf::entry_leaf;
+
+use crate::{
+    decode_leaf, read_json,
+    seal::{is_sealed, PayloadKeys},
+    stream::RootFold,
+    Entry, Log, LogError, ENTRIES, LEAVES,
+};
+
+/// A root of this log recorded outside its files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedRoot {
+    /// Where it was recorded, such as `witness <key>` or `anchor <txid>`.
+    pub source: String,
+    pub size: u64,
+    /// Hex root of the first `size` leaves.
+    pub root: String,
+}
+
+/// A log's files as read from disk.
+#[derive(Debug)]
+pub struct Stored {
+    entries: Vec<Entry>,
+    leaves: Vec<String>,
+    keys: Option<Arc<PayloadKeys>>,
+}
+
+/// What a replay checked, and where it stopped if the files disagree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replay {
+    /// Entries checked before the replay stopped.
+    pub size: u64,
+    /// Hex root of those entries' leaves.
+    pub root: String,
+    /// Recorded roots that matched.
+    pub matched: usize,
+    /// Sealed payloads left unopened for want of keys; their leaves are
+    /// still checked against `leaves.json`.
+    pub unopened: u64,
+    pub divergence: Option<Divergence>,
+}
+
+/// The first disagreement a replay found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Divergence {
+    /// One file has an entry the other lacks.
+    Missing { index: u64, file: &'static str },
+    /// `leaves.json` holds something other than a leaf hash.
+    CorruptLeaf { index: u64 },
+    /// The entry's leaf is not the one in `leaves.json`.
+    LeafMismatch { index: u64 },
+    /// The payload neither hashes nor opens to its leaf.
+    PayloadMismatch { index: u64 },
+    /// The entry is redacted without a valid tombstone for its leaf.
+    BadTombstone { index: u64 },
+    /// The rebuilt root of `size` leaves is not the recorded one. Roots
+    /// matched up to `from` leaves, so the first change is at or after it.
+    Root {
+        source: String,
+        size: u64,
+        from: u64,
+    },
+    /// A root was recorded for more leaves than the files hold.
+    Truncated { source: String, size: u64, len: u64 },
+}
+
+impl Divergence {
+    /// The first index that may differ from what was appended.
+    pub fn index(&self) -> u64 {
+        match self {
+            Self::Missing { index, .. }
+            | Self::CorruptLeaf { index }
+            | Self::LeafMismatch { index }
+            | Self::PayloadMismatch { index }
+            | Self::BadTombstone { index } => *index,
+            Self::Root { from, .. } => *from,
+            Self::Truncated { len, .. } => *len,
+        }
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing { index, file } => write!(f, "entry {index} is missing from {file}"),
+            Self::CorruptLeaf { index } => write!(f, "leaf {index} is not a 32-byte hex hash"),
+            Self::LeafMismatch { index } => {
+                write!(f, "entry {index}'s leaf differs from {LEAVES}")
+            }
+            Self::PayloadMismatch { index } => {
+                write!(f, "entry {index}'s payload does not match its leaf")
+            }
+            Self::BadTombstone { index } => {
+                write!(f, "entry {index} is redacted without a valid tombstone")
+            }
+            Self::Root { source, size, from } => write!(
+                f,
+                "the root of {size} leaves differs from {source}; roots agree up to {from}"
+            ),
+            Self::Truncated { source, size, len } => write!(
+                f,
+                "{source} records {size} leaves but only {len} are stored"
+            ),
+        }
+    }
+}
+
+impl Stored {
+    /// Read the files of the log in `dir`. Sealed payloads are opened with
+    /// `keys` when given.
+    pub fn read(dir: impl AsRef<Path>, keys: Option<PayloadKeys>) -> Result<Self, LogError> {
+        Self::read_with(dir.as_ref(), keys.map(Arc::new))
+    }
+
+    fn read_with(dir: &Path, keys: Option<Arc<PayloadKeys>>) -> Result<Self, LogError> {
+        Ok(Self {
+            entries: read_json(&dir.join(ENTRIES))?.unwrap_or_default(),
+            leaves: read_json(&dir.join(LEAVES))?.unwrap_or_default(),
+            keys,
+        })
+    }
+
+    /// Replay every entry, comparing the rebuilt tree with `recorded` on
+    /// the way.
+    pub fn replay(&self, recorded: &[RecordedRoot]) -> Replay {
+        let mut recorded: Vec<_> = recorded.iter().collect();
+        recorded.sort_by_key(|root| root.size);
+        let mut pending = recorded.into_iter().peekable();
+        let mut fold = RootFold::new();
+        let mut replay = Replay {
+            size: 0,
+            root: String::new(),
+            matched: 0,
+            unopened: 0,
+            divergence: None,
+        };
+        let mut agreed = 0;
+        let len = self.entries.len().max(self.leaves.len()) as u64;
+        loop {
+            while let Some(root) = pending.next_if(|root| root.size == fold.size()) {
+                if !root.root.eq_ignore_ascii_case(&hex::encode(fold.root())) {
+                    replay.divergence = Some(Divergence::Root {
+                        source: root.source.clone(),
+                        size: root.size,
+                        from: agreed,
+                    });
+                    return replay.at(&fold);
+                }
+                agreed = root.size;
+                replay.matched += 1;
+            }
+            let index = fold.size();
+            if index == len {
+                break;
+            }
+            match self.check(index, &mut replay.unopened) {
+                Ok(leaf) => fold.push(leaf),
+                Err(divergence) => {
+                    replay.divergence = Some(divergence);
+                    return replay.at(&fold);
+                }
+            }
+        }
+        if let Some(root) = pending.next() {
+            replay.divergence = Some(Divergence::Truncated {
+                source: root.source.clone(),
+                size: root.size,
+                len,
+            });
+        }
+        replay.at(&fold)
+    }
+
+    /// The leaf of entry `index` once its payload and leaf check out.
+    fn check(&self, index: u64, unopened: &mut u64) -> Result<[u8; 32], Divergence> {
+        let (Some(entry), Some(leaf)) = (
+            self.entries.get(index as usize),
+            self.leaves.get(index as usize),
+        ) else {
+            let file = if self.entries.len() as u64 <= index {
+                ENTRIES
+            } else {
+                LEAVES
+            };
+            return Err(Divergence::Missing { index, file });
+        };
+        let leaf = decode_leaf(index, leaf).map_err(|_| Divergence::CorruptLeaf { index })?;
+        if entry.leaf != leaf {
+            return Err(Divergence::LeafMismatch { index });
+        }
+        if let Some(tombstone) = &entry.redaction {
+            if !entry.payload.is_empty() || !tombstone.covers(index, &leaf) {
+                return Err(Divergence::BadTombstone { index });
+            }
+            return Ok(leaf);
+        }
+        if entry_leaf(entry.payload.as_bytes(), entry.meta.as_ref()) == leaf {
+            return Ok(leaf);
+        }
+        if !is_sealed(&entry.payload) {
+            return Err(Divergence::PayloadMismatch { index });
+        }
+        match &self.keys {
+            Some(keys) if keys.open(&entry.payload, &leaf).is_none() => {
+                Err(Divergence::PayloadMismatch { index })
+            }
+            Some(_) => Ok(leaf),
+            None => {
+                *unopened += 1;
+                Ok(leaf)
+            }
+        }
+    }
+}
+
+impl Replay {
+    fn at(mut self, fold: &RootFold) -> Self {
+        self.size = fold.size();
+        self.root = hex::encode(fold.root());
+        self
+    }
+}
+
+impl Log {
+    /// This log's files as they are on disk, read with its payload keys.
+    /// Appends need `&mut self`, so both files are read at the same size.
+    pub fn stored(&self) -> Result<Stored, LogError> {
+        Stored::read_with(&self.dir, self.store.keys.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use reality_core::{leaf_hash, signer::KeyFileSigner};
+
+    use super::*;
+
+    #[test]
+    fn replays_files_and_finds_the_first_divergence() {
+        let dir = std::env::temp_dir().join(format!("reality-log-replay-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut log = Log::open_sealed(&dir, PayloadKeys::new(1, [3; 32])).unwrap();
+        for payload in ["a", "b", "c", "d"] {
+            log.append(payload).unwrap();
+        }
+        log.redact(2, "erasure", &KeyFileSigner::from_seed([4; 32]))
+            .unwrap();
+        let recorded = |source: &str, size: u64| RecordedRoot {
+            source: source.into(),
+            size,
+            root: log.root_at(size).unwrap().root,
+        };
+        let roots = vec![recorded("witness", 4), recorded("anchor", 2)];
+
+        let replay = log.stored().unwrap().replay(&roots);
+        assert_eq!(replay.divergence, None);
+        assert_eq!((replay.size, replay.matched), (4, 2));
+        assert_eq!(replay.root, log.root().root);
+        let unkeyed = Stored::read(&dir, None).unwrap().replay(&roots);
+        assert_eq!((unkeyed.unopened, unkeyed.divergence), (3, None));
+
+        let mut beyond = recorded("witness", 4);
+        beyond.size = 6;
+        assert_eq!(
+            unkeyed_replay(&dir, &[beyond]).divergence,
+            Some(Divergence::Truncated {
+                source: "witness".into(),
+                size: 6,
+                len: 4
+            })
+        );
+
+        // Rewrite entry 1 consistently in both files: only the recorded
+        // roots can tell.
+        let rewritten = leaf_hash(b"rewritten");
+        let stored = fs::read_to_string(dir.join(ENTRIES)).unwrap();
+        let mut entries: Vec<Entry> = serde_json::from_str(&stored).unwrap();
+        entries[1].payload = "rewritten".into();
+        entries[1].leaf = rewritten;
+        fs::write(dir.join(ENTRIES), serde_json::to_string(&entries).unwrap()).unwrap();
+        let leaves = fs::read_to_string(dir.join(LEAVES)).unwrap();
+        let original = hex::encode(log.snapshot().leaf(1).unwrap());
+        fs::write(
+            dir.join(LEAVES),
+            leaves.replace(&original, &hex::encode(rewritten)),
+        )
+        .unwrap();
+        let replay = unkeyed_replay(&dir, &roots);
+        assert_eq!(
+            replay.divergence,
+            Some(Divergence::Root {
+                source: "anchor".into(),
+                size: 2,
+                from: 0
+            })
+        );
+        assert_eq!(replay.divergence.unwrap().index(), 0);
+
+        // A payload changed without its leaf is caught at its index.
+        fs::write(dir.join(LEAVES), &leaves).unwrap();
+        entries[1].leaf = log.snapshot().leaf(1).unwrap();
+        fs::write(dir.join(ENTRIES), serde_json::to_string(&entries).unwrap()).unwrap();
+        assert_eq!(
+            unkeyed_replay(&dir, &roots).divergence,
+            Some(Divergence::PayloadMismatch { index: 1 })
+        );
+
+        // So is an append that reached one file but not the other.
+        let mut entries: Vec<Entry> = serde_json::from_str(&stored).unwrap();
+        entries.truncate(3);
+        fs::write(dir.join(ENTRIES), serde_json::to_string(&entries).unwrap()).unwrap();
+        let replay = Stored::read(&dir, Some(PayloadKeys::new(1, [3; 32])))
+            .unwrap()
+            .replay(&[]);
+        assert_eq!(
+            replay.divergence,
+            Some(Divergence::Missing {
+                index: 3,
+                file: ENTRIES
+            })
+        );
+        assert_eq!((replay.size, replay.unopened), (3, 0));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn unkeyed_replay(dir: &Path, roots: &[RecordedRoot]) -> Replay {
+        Stored::read(dir, None).unwrap().replay(roots)
+    }
+}
@@ -0,0 +1,144 @@
+//! Replication. A standby copies a primary's entries as they are stored,
+//! sealed payloads and tombstones included, and [`Log::replicate`] adds them
+//! only once they rebuild the tree of a checkpoint the primary signed. The
+//! standby's log is then always a prefix of the primary's, which is what a
+//! promotion hands over (see `reality_core::handoff`).
+
+use std::collections::HashMap;
+
+use reality_core::{checkpoint::Checkpoint, leaf::entry_leaf};
+
+use crate::{seal::is_sealed, Entry, Log, LogError};
+
+impl Log {
+    /// Append `entries`, copied from a primary, if together with the
+    /// entries already here they rebuild the tree of `checkpoint`. Each entry
+    /// must match its leaf, unless its payload is sealed under keys this log
+    /// does not hold; the root still covers it.
+    pub fn replicate(
+        &mut self,
+        entries: Vec<Entry>,
+        checkpoint: &Checkpoint,
+    ) -> Result<(), LogError> {
+        let base = self.len();
+        if base + entries.len() as u64 != checkpoint.size {
+            return Err(LogError::ReplicaDiverges(checkpoint.size));
+        }
+        let mut fold = self.store.nodes.fold(base);
+        let mut keys = HashMap::new();
+        for (offset, entry) in entries.iter().enumerate() {
+            let index = base + offset as u64;
+            self.check_replica(index, entry)
+                .map_err(|reason| LogError::BadReplica { index, reason })?;
+            if let Some(key) = &entry.idempotency_key {
+                keys.insert(key.clone(), index);
+            }
+            fold.push(entry.leaf);
+        }
+        if !hex::encode(fold.root()).eq_ignore_ascii_case(&checkpoint.root) {
+            return Err(LogError::ReplicaDiverges(checkpoint.size));
+        }
+        // Once the primary writes v2 leaves, so does this log.
+        let v2 = self.clock.is_some() || entries.iter().any(|entry| entry.meta.is_some());
+        if !entries.is_empty() {
+            self.commit(entries, keys)?;
+        }
+        if v2 {
+            self.use_leaf_v2();
+        }
+        Ok(())
+    }
+
+    fn check_replica(&self, index: u64, entry: &Entry) -> Result<(), &'static str> {
+        if entry
+            .meta
+            .as_ref()
+            .is_some_and(|meta| meta.sequence != index)
+        {
+            return Err("has another sequence number");
+        }
+        if let Some(tombstone) = &entry.redaction {
+            if !entry.payload.is_empty() || !tombstone.covers(index, &entry.leaf) {
+                return Err("is redacted without a valid tombstone");
+            }
+            return Ok(());
+        }
+        if entry_leaf(entry.payload.as_bytes(), entry.meta.as_ref()) == entry.leaf {
+            return Ok(());
+        }
+        if !is_sealed(&entry.payload) {
+            return Err("does not match its leaf");
+        }
+        match &self.store.keys {
+            Some(keys) if keys.open(&entry.payload, &entry.leaf).is_none() => {
+                Err("does not open to its leaf")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use reality_core::signer::KeyFileSigner;
+
+    use super::*;
+
+    #[test]
+    fn replicas_only_take_what_rebuilds_the_checkpoint() {
+        let dir = std::env::temp_dir().join(format!("reality-log-replica-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let signer = KeyFileSigner::from_seed([4; 32]);
+        let mut primary = Log::open(dir.join("primary")).unwrap();
+        primary.use_leaf_v2();
+        for payload in ["a", "b", "c", "d"] {
+            primary.append(payload).unwrap();
+        }
+        primary.redact(1, "erasure", &signer).unwrap();
+        let copy = |log: &Log, from: u64, to: u64| -> Vec<Entry> {
+            (from..to).map(|i| log.get(i).unwrap().clone()).collect()
+        };
+        let checkpoint = |log: &Log, size: u64| Checkpoint {
+            size,
+            root: log.root_at(size).unwrap().root,
+            timestamp_nanos: "1".into(),
+        };
+
+        let mut standby = Log::open(dir.join("standby")).unwrap();
+        standby
+            .replicate(copy(&primary, 0, 2), &checkpoint(&primary, 2))
+            .unwrap();
+        let mut forged = copy(&primary, 2, 4);
+        forged[1].payload = "forged".into();
+        assert!(matches!(
+            standby.replicate(forged.clone(), &checkpoint(&primary, 4)),
+            Err(LogError::BadReplica { index: 3, .. })
+        ));
+        forged[1].leaf = entry_leaf(b"forged", forged[1].meta.as_ref());
+        assert!(matches!(
+            standby.replicate(forged, &checkpoint(&primary, 4)),
+            Err(LogError::ReplicaDiverges(4))
+        ));
+        assert!(matches!(
+            standby.replicate(copy(&primary, 2, 3), &checkpoint(&primary, 4)),
+            Err(LogError::ReplicaDiverges(4))
+        ));
+        assert_eq!(standby.len(), 2);
+        standby
+            .replicate(copy(&primary, 2, 4), &checkpoint(&primary, 4))
+            .unwrap();
+        assert_eq!(standby.root(), primary.root());
+        assert_eq!(
+            standby.get(1).unwrap().redaction,
+            primary.get(1).unwrap().redaction
+        );
+
+        // Appends after a promotion carry on the primary's clock.
+        let last = primary.get(3).unwrap().meta.clone().unwrap();
+        let next = standby.append("e").unwrap().meta.unwrap();
+        assert!(next.follows(&last));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,158 @@
+//! Payloads encrypted at rest. A log opened with [`PayloadKeys`] stores each
+//! new payload sealed with AES-256-GCM, both in memory and in `entries.json`.
+//! The leaf is still the hash of the plaintext, so proofs, roots and every
+//! verifier are unaffected. The leaf is also the associated data, so a
+//! sealed payload cannot be moved to another entry.
+//!
+//! A sealed payload is `sealed:<key id>:<base64 of nonce, ciphertext and
+//! tag>`. Entries appended before encryption was turned on stay in plaintext.
+//! Whether a stored payload is sealed is decided by its leaf: a payload that
+//! hashes to its leaf is plaintext, even if it happens to look sealed.
+
+use std::collections::BTreeMap;
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    rand::{SecureRandom, SystemRandom},
+};
+
+const PREFIX: &str = "sealed:";
+const TAG_LEN: usize = 16;
+
+/// Data keys by id. New payloads are sealed with the current key; the
+/// others open payloads sealed before a rotation.
+pub struct PayloadKeys {
+    current: u32,
+    keys: BTreeMap<u32, LessSafeKey>,
+    rng: SystemRandom,
+}
+
+impl std::fmt::Debug for PayloadKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PayloadKeys")
+            .field("current", &self.current)
+            .field("ids", &self.keys.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl PayloadKeys {
+    /// Keys that seal with AES-256 key `key`, known as `id`.
+    pub fn new(id: u32, key: [u8; 32]) -> Self {
+        Self {
+            current: id,
+            keys: BTreeMap::new(),
+            rng: SystemRandom::new(),
+        }
+        .with_key(id, key)
+    }
+
+    /// Also open payloads sealed with `key`, known as `id`.
+    pub fn with_key(mut self, id: u32, key: [u8; 32]) -> Self {
+        let key = UnboundKey::new(&AES_256_GCM, &key).expect("AES-256 keys are 32 bytes");
+        self.keys.insert(id, LessSafeKey::new(key));
+        self
+    }
+
+    /// Id of the key new payloads are sealed with.
+    pub fn current(&self) -> u32 {
+        self.current
+    }
+
+    pub(crate) fn seal(&self, payload: &str, leaf: &[u8; 32]) -> String {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .expect("the system random source works");
+        let mut sealed = payload.as_bytes().to_vec();
+        self.keys[&self.current]
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(leaf),
+                &mut sealed,
+            )
+            .expect("payloads fit in one AES-GCM message");
+        let mut body = nonce.to_vec();
+        body.extend(sealed);
+        format!("{PREFIX}{}:{}", self.current, STANDARD.encode(body))
+    }
+
+    /// The plaintext of `sealed`, or `None` if it is not sealed with one of
+    /// these keys for `leaf`.
+    pub(crate) fn open(&self, sealed: &str, leaf: &[u8; 32]) -> Option<String> {
+        let (id, body) = parse(sealed)?;
+        let key = self.keys.get(&id)?;
+        let mut body = STANDARD.decode(body).ok()?;
+        if body.len() < NONCE_LEN + TAG_LEN {
+            return None;
+        }
+        let mut ciphertext = body.split_off(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(&body).ok()?;
+        let plaintext = key
+            .open_in_place(nonce, Aad::from(leaf), &mut ciphertext)
+            .ok()?;
+        String::from_utf8(plaintext.to_vec()).ok()
+    }
+}
+
+/// Whether `payload` has the form of a sealed payload. Check first whether
+/// it hashes to its leaf, which makes it plaintext whatever its form.
+pub fn is_sealed(payload: &str) -> bool {
+    parse(payload).is_some()
+}
+
+/// Length in bytes of the plaintext of a sealed payload.
+pub fn plaintext_len(payload: &str) -> Option<u64> {
+    let (_, body) = parse(payload)?;
+    let padding = body.bytes().rev().take_while(|b| *b == b'=').count();
+    let decoded = (body.len() / 4 * 3).saturating_sub(padding);
+    decoded
+        .checked_sub(NONCE_LEN + TAG_LEN)
+        .map(|len| len as u64)
+}
+
+/// The key id and base64 body of a sealed payload.
+fn parse(payload: &str) -> Option<(u32, &str)> {
+    let (id, body) = payload.strip_prefix(PREFIX)?.split_once(':')?;
+    let valid = body.len() % 4 == 0
+        && body
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/' | b'='));
+    valid.then_some((id.parse().ok()?, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reality_core::leaf_hash;
+
+    #[test]
+    fn seals_to_the_leaf_and_key() {
+        let keys = PayloadKeys::new(2, [7; 32]).with_key(1, [1; 32]);
+        let leaf = leaf_hash(b"secret");
+        let sealed = keys.seal("secret", &leaf);
+        assert!(sealed.starts_with("sealed:2:") && is_sealed(&sealed));
+        assert_eq!(plaintext_len(&sealed), Some(6));
+        assert_eq!(keys.open(&sealed, &leaf).as_deref(), Some("secret"));
+        assert_ne!(keys.seal("secret", &leaf), sealed, "nonces are fresh");
+
+        // Another entry's leaf, a missing key or a changed byte fail.
+        assert_eq!(keys.open(&sealed, &leaf_hash(b"other")), None);
+        assert_eq!(PayloadKeys::new(1, [1; 32]).open(&sealed, &leaf), None);
+        let mut tampered = sealed.clone().into_bytes();
+        let middle = tampered.len() / 2;
+        tampered[middle] = if tampered[middle] == b'A' { b'B' } else { b'A' };
+        assert_eq!(
+            keys.open(&String::from_utf8(tampered).unwrap(), &leaf),
+            None
+        );
+
+        // Older keys still open what they sealed.
+        let old = PayloadKeys::new(1, [1; 32]).seal("", &leaf);
+        assert_eq!(plaintext_len(&old), Some(0));
+        assert_eq!(keys.open(&old, &leaf).as_deref(), Some(""));
+        assert!(!is_sealed("sealed:x:AAAA") && !is_sealed("hello"));
+        assert!(!format!("{keys:?}").contains("[7"));
+    }
+}
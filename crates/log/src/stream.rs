@@ -0,0 +1,250 @@
+//! Roots computed from a log directory without loading it. The leaves are
+//! read from disk a buffer at a time and folded into one pending subtree per
+//! level, so checking a log of any size needs memory for a few dozen hashes.
+
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+
+use reality_core::{empty_root, node_hash, RootResponse};
+use serde::de::{self, DeserializeSeed, IgnoredAny, SeqAccess, Visitor};
+
+use crate::{decode_leaf, LogError, LEAVES};
+
+/// A root built one leaf at a time.
+#[derive(Debug, Clone, Default)]
+pub struct RootFold {
+    size: u64,
+    /// The complete subtree waiting for a right sibling at each level, which
+    /// are the subtrees named by the set bits of `size`.
+    pending: Vec<Option<[u8; 32]>>,
+}
+
+impl RootFold {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The fold of `size` leaves, from `subtree(level)`: the complete
+    /// subtree at each level named by a set bit of `size`.
+    pub(crate) fn resume(size: u64, mut subtree: impl FnMut(usize) -> [u8; 32]) -> Self {
+        let levels = (u64::BITS - size.leading_zeros()) as usize;
+        Self {
+            size,
+            pending: (0..levels)
+                .map(|level| (size >> level & 1 == 1).then(|| subtree(level)))
+                .collect(),
+        }
+    }
+
+    /// Leaves folded so far.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn push(&mut self, leaf: [u8; 32]) {
+        let mut carry = leaf;
+        for slot in self.pending.iter_mut() {
+            match slot.take() {
+                Some(left) => carry = node_hash(&left, &carry),
+                None => {
+                    *slot = Some(carry);
+                    self.size += 1;
+                    return;
+                }
+            }
+        }
+        self.pending.push(Some(carry));
+        self.size += 1;
+    }
+
+    /// The root of the leaves folded so far, the same as
+    /// `reality_core::root` over them.
+    pub fn root(&self) -> [u8; 32] {
+        let mut subtrees = self
+            .pending
+            .iter()
+            .enumerate()
+            .filter_map(|(level, node)| Some((level, (*node)?)));
+        let Some((mut level, mut node)) = subtrees.next() else {
+            return empty_root();
+        };
+        // Walk up the right edge: it pairs with the next larger subtree on
+        // its left where there is one, and is duplicated where there is not.
+        while (self.size - 1) >> level > 0 {
+            node = if ((self.size - 1) >> level) & 1 == 1 {
+                let (_, left) = subtrees
+                    .find(|(at, _)| *at == level)
+                    .expect("a left sibling is a pending subtree");
+                node_hash(&left, &node)
+            } else {
+                node_hash(&node, &node)
+            };
+            level += 1;
+        }
+        node
+    }
+}
+
+/// Root of the log stored in `dir`, read from disk without loading it.
+pub fn root(dir: impl AsRef<Path>) -> Result<RootResponse, LogError> {
+    fold(dir.as_ref(), u64::MAX)
+}
+
+/// Root of the first `size` entries of the log stored in `dir`, read from
+/// disk without loading it.
+pub fn root_at(dir: impl AsRef<Path>, size: u64) -> Result<RootResponse, LogError> {
+    let head = fold(dir.as_ref(), size)?;
+    if head.size < size {
+        return Err(LogError::OutOfRange {
+            index: size,
+            size: head.size,
+        });
+    }
+    Ok(head)
+}
+
+/// Fold the first `limit` leaves of `dir`; fewer if the log is smaller.
+fn fold(dir: &Path, limit: u64) -> Result<RootResponse, LogError> {
+    let path = dir.join(LEAVES);
+    let io_error = |source| LogError::Io {
+        path: path.clone(),
+        source,
+    };
+    let mut reader = match File::open(&path) {
+        Ok(file) => BufReader::with_capacity(1 << 20, file),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(head(&RootFold::new())),
+        Err(source) => return Err(io_error(source)),
+    };
+    // logd treats a blank file as an empty log.
+    loop {
+        let buf = reader.fill_buf().map_err(io_error)?;
+        let Some(text) = buf.iter().position(|b| !b.is_ascii_whitespace()) else {
+            if buf.is_empty() {
+                return Ok(head(&RootFold::new()));
+            }
+            let skip = buf.len();
+            reader.consume(skip);
+            continue;
+        };
+        reader.consume(text);
+        break;
+    }
+
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let mut leaves = Leaves {
+        fold: RootFold::new(),
+        limit,
+        corrupt: None,
+    };
+    let parsed = (&mut leaves)
+        .deserialize(&mut deserializer)
+        .and_then(|()| deserializer.end());
+    if let Some(index) = leaves.corrupt {
+        return Err(LogError::CorruptLeaf(index));
+    }
+    parsed.map_err(|source| LogError::Json { path, source })?;
+    Ok(head(&leaves.fold))
+}
+
+fn head(fold: &RootFold) -> RootResponse {
+    RootResponse {
+        root: hex::encode(fold.root()),
+        size: fold.size(),
+    }
+}
+
+/// Visits the leaf array one element at a time, folding up to `limit` and
+/// skipping the rest.
+struct Leaves {
+    fold: RootFold,
+    limit: u64,
+    corrupt: Option<u64>,
+}
+
+impl<'de> DeserializeSeed<'de> for &mut Leaves {
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for &mut Leaves {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of hex leaf hashes")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while self.fold.size() < self.limit {
+            let Some(leaf) = seq.next_element::<String>()? else {
+                return Ok(());
+            };
+            let index = self.fold.size();
+            let leaf = decode_leaf(index, &leaf).map_err(|err| {
+                self.corrupt = Some(index);
+                de::Error::custom(err)
+            })?;
+            self.fold.push(leaf);
+        }
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reality_core::leaf_hash;
+
+    #[test]
+    fn folds_leaves_from_disk() {
+        let leaves: Vec<_> = (0..100u32).map(|i| leaf_hash(&i.to_be_bytes())).collect();
+        let mut fold = RootFold::new();
+        for (size, leaf) in leaves.iter().enumerate() {
+            assert_eq!(
+                fold.root(),
+                reality_core::root(&leaves[..size]),
+                "size {size}"
+            );
+            fold.push(*leaf);
+        }
+        assert_eq!(fold.root(), reality_core::root(&leaves));
+
+        let dir = std::env::temp_dir().join(format!("reality-log-stream-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(root(&dir).unwrap().size, 0);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(LEAVES), "  \n").unwrap();
+        assert_eq!(root(&dir).unwrap().root, hex::encode(empty_root()));
+
+        let hex_leaves: Vec<_> = leaves.iter().map(hex::encode).collect();
+        let json = serde_json::to_string_pretty(&hex_leaves).unwrap();
+        std::fs::write(dir.join(LEAVES), &json).unwrap();
+        let head = root(&dir).unwrap();
+        assert_eq!(head.size, 100);
+        assert_eq!(head.root, hex::encode(reality_core::root(&leaves)));
+        assert_eq!(
+            root_at(&dir, 37).unwrap().root,
+            hex::encode(reality_core::root(&leaves[..37]))
+        );
+        assert!(matches!(
+            root_at(&dir, 101),
+            Err(LogError::OutOfRange {
+                index: 101,
+                size: 100
+            })
+        ));
+
+        std::fs::write(dir.join(LEAVES), json.replacen(&hex_leaves[3], "zz", 1)).unwrap();
+        assert!(matches!(root(&dir), Err(LogError::CorruptLeaf(3))));
+        std::fs::write(dir.join(LEAVES), &json[..json.len() - 2]).unwrap();
+        assert!(matches!(root(&dir), Err(LogError::Json { .. })));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
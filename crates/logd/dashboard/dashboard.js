@@ -0,0 +1,200 @@
+// logd's built-in dashboard. Everything comes from logd's public API, and
+// proofs are checked in the browser by the RealityLog wasm verifier when it
+// was bundled into `dashboard/pkg/`.
+
+const TILE_WIDTH = 256;
+const RECENT = 20;
+const REFRESH_MS = 5000;
+const DAY_NANOS = 86_400n * 1_000_000_000n;
+
+const $ = (id) => document.getElementById(id);
+
+let wasm = null;
+
+async function api(path, init) {
+  const response = await fetch(path, init);
+  if (!response.ok) {
+    throw new Error(`${path}: ${response.status} ${(await response.text()).trim()}`);
+  }
+  return response.json();
+}
+
+function cell(row, text, className) {
+  const td = row.insertCell();
+  const inner = document.createElement(className === "pre" ? "pre" : "code");
+  inner.textContent = text;
+  td.append(className ? inner : text);
+  return td;
+}
+
+function nanosToDate(nanos) {
+  try {
+    return new Date(Number(BigInt(nanos) / 1_000_000n)).toISOString();
+  } catch {
+    return nanos;
+  }
+}
+
+async function refreshHead() {
+  const head = await api("root");
+  $("size").textContent = head.size;
+  $("root").textContent = head.root;
+  try {
+    const checkpoint = await api("checkpoint");
+    $("checkpoint").textContent = `signed by ${checkpoint.public_key} at size ${checkpoint.size}`;
+  } catch {
+    $("checkpoint").textContent = "not signed (logd has no REALITY_LOG_SIGNER)";
+  }
+  return head;
+}
+
+// The last `RECENT` entries, newest first, from the last one or two tiles.
+async function refreshEntries(size) {
+  const body = $("entries");
+  const rows = [];
+  let tile = Math.floor((size - 1) / TILE_WIDTH);
+  while (size > 0 && tile >= 0 && rows.length < RECENT) {
+    const [leaves, entries] = await Promise.all([api(`tile/${tile}`), api(`tile/${tile}/entries`)]);
+    for (let i = entries.payloads.length - 1; i >= 0 && rows.length < RECENT; i--) {
+      rows.push([tile * TILE_WIDTH + i, entries.payloads[i], leaves.leaves[i]]);
+    }
+    tile--;
+  }
+  body.replaceChildren();
+  for (const [index, payload, leaf] of rows) {
+    const row = body.insertRow();
+    cell(row, String(index));
+    cell(row, payload, "pre");
+    cell(row, leaf, "code");
+  }
+  if (rows.length === 0) {
+    body.insertRow().insertCell().textContent = "The log is empty.";
+  }
+}
+
+async function refreshAnchors() {
+  const body = $("anchors");
+  const since = BigInt(Date.now()) * 1_000_000n - DAY_NANOS;
+  let anchors;
+  try {
+    anchors = await api(`anchors?from_nanos=${since}`);
+  } catch (err) {
+    body.replaceChildren();
+    body.insertRow().insertCell().textContent = `Anchors unavailable: ${err.message}`;
+    return;
+  }
+  anchors.sort((a, b) => (BigInt(b.timestamp_nanos) > BigInt(a.timestamp_nanos) ? 1 : -1));
+  body.replaceChildren();
+  for (const anchor of anchors.slice(0, 10)) {
+    const row = body.insertRow();
+    cell(row, String(anchor.size));
+    cell(row, anchor.root, "code");
+    cell(row, anchor.txid, "code");
+    cell(row, anchor.status);
+    cell(row, nanosToDate(anchor.timestamp_nanos));
+  }
+  if (anchors.length === 0) {
+    body.insertRow().insertCell().textContent = "No anchors in the last 24 hours.";
+  }
+}
+
+async function refresh() {
+  try {
+    const head = await refreshHead();
+    await Promise.all([refreshEntries(head.size), refreshAnchors()]);
+    $("updated").textContent = `Updated ${new Date().toLocaleTimeString()}`;
+  } catch (err) {
+    $("updated").textContent = `Refresh failed: ${err.message}`;
+  }
+}
+
+function report(checks) {
+  const list = $("result");
+  list.replaceChildren();
+  for (const [ok, text] of checks) {
+    const item = document.createElement("li");
+    item.className = ok ? "ok" : "bad";
+    item.textContent = text;
+    list.append(item);
+  }
+}
+
+async function verifyProof(event) {
+  event.preventDefault();
+  let proof;
+  try {
+    proof = JSON.parse($("proof").value);
+  } catch (err) {
+    report([[false, `not JSON: ${err.message}`]]);
+    return;
+  }
+  try {
+    report(await check(proof, $("payload").value));
+  } catch (err) {
+    report([[false, err.message ?? String(err)]]);
+  }
+}
+
+async function check(proof, payload) {
+  const checks = [];
+  if (payload !== "" && wasm) {
+    const leaf = wasm.leaf_hash_of_utf8(payload, proof.algorithm);
+    checks.push([leaf === String(proof.leaf).toLowerCase(), `payload hashes to ${leaf}`]);
+  }
+  const result = wasm
+    ? wasm.verify_inclusion(proof)
+    : await api("verify", {
+        method: "POST",
+        headers: { "content-type": "application/json" },
+        body: JSON.stringify(proof),
+      });
+  checks.push([
+    result.valid,
+    result.valid
+      ? `path leads to root ${result.expected_root}`
+      : result.error ?? `path leads to ${result.computed_root}, not ${result.expected_root}`,
+  ]);
+  if (typeof proof.size === "number") {
+    try {
+      const atSize = await api(`root/${proof.size}`);
+      const same = atSize.root.toLowerCase() === String(proof.root).toLowerCase();
+      checks.push([same, same ? `the log's root at size ${proof.size} matches` : `the log's root at size ${proof.size} is ${atSize.root}`]);
+    } catch (err) {
+      checks.push([false, `the log has no tree of size ${proof.size}: ${err.message}`]);
+    }
+  }
+  return checks;
+}
+
+async function fetchProof() {
+  const index = $("index").value;
+  if (index === "") {
+    return;
+  }
+  try {
+    $("proof").value = JSON.stringify(await api(`prove/${index}`), null, 2);
+    const tile = Math.floor(index / TILE_WIDTH);
+    const entries = await api(`tile/${tile}/entries`);
+    $("payload").value = entries.payloads[index % TILE_WIDTH] ?? "";
+  } catch (err) {
+    report([[false, err.message]]);
+  }
+}
+
+async function main() {
+  try {
+    wasm = await import(new URL("pkg/reality_wasm_core.js", import.meta.url).href);
+    await wasm.default();
+    $("verifier").textContent = "Proofs are checked in this browser by the RealityLog wasm verifier.";
+  } catch {
+    wasm = null;
+    $("verifier").textContent =
+      "This logd was built without the wasm verifier, so proofs are checked by logd's /verify and payloads are not hashed.";
+  }
+  $("verify").addEventListener("submit", verifyProof);
+  $("fetch-proof").addEventListener("click", fetchProof);
+  await refresh();
+  setInterval(refresh, REFRESH_MS);
+}
+
+main();
@@ -0,0 +1,171 @@
+//! DSSE attestations, such as in-toto statements and SLSA provenance, as an
+//! entry type of their own. `/attestations` checks an envelope before it is
+//! appended, and every in-toto envelope in the log, whichever route it came
+//! in by, is indexed by its subjects' digests so it can be looked up by the
+//! artifact it is about.
+
+use std::collections::HashMap;
+
+use reality_core::{
+    dsse::{DsseError, Envelope, Statement},
+    BatchEntry,
+};
+use sha2::{Digest, Sha256};
+
+/// Which envelopes are accepted and indexed.
+#[derive(Debug, Default)]
+pub struct Attestations {
+    /// Hex ed25519 keys, one of which must have signed an envelope; empty
+    /// accepts any well-formed envelope.
+    keys: Vec<String>,
+}
+
+/// Why an envelope was refused.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Rejection {
+    Malformed(DsseError),
+    Untrusted,
+}
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed(err) => err.fmt(f),
+            Self::Untrusted => f.write_str("no signature is by a trusted attestation key"),
+        }
+    }
+}
+
+impl Attestations {
+    pub fn new(keys: Vec<String>) -> Self {
+        Self { keys }
+    }
+
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Check `envelope` and return the entry to append: the envelope
+    /// re-serialized without unknown fields, keyed by its digest so that a
+    /// resubmission is a duplicate.
+    pub fn admit(&self, envelope: &Envelope) -> Result<BatchEntry, Rejection> {
+        envelope.validate().map_err(Rejection::Malformed)?;
+        if !self.trusts(envelope) {
+            return Err(Rejection::Untrusted);
+        }
+        let payload = serde_json::to_string(envelope).expect("envelope serializes");
+        Ok(BatchEntry {
+            idempotency_key: Some(format!(
+                "dsse:{}",
+                hex::encode(Sha256::digest(payload.as_bytes()))
+            )),
+            payload,
+            labels: Default::default(),
+            signature: None,
+        })
+    }
+
+    /// The statement in `payload` if it is an in-toto envelope this log
+    /// would accept.
+    pub fn statement(&self, payload: &str) -> Option<Statement> {
+        let envelope: Envelope = serde_json::from_str(payload).ok()?;
+        let statement = envelope.validate().ok()??;
+        self.trusts(&envelope).then_some(statement)
+    }
+
+    fn trusts(&self, envelope: &Envelope) -> bool {
+        self.keys.is_empty() || self.keys.iter().any(|key| envelope.verify_ed25519(key))
+    }
+}
+
+/// Entry indices by subject digest, as `<algorithm>:<value>` in lowercase.
+#[derive(Debug, Clone, Default)]
+pub struct SubjectIndex {
+    entries: HashMap<String, Vec<u64>>,
+}
+
+impl SubjectIndex {
+    pub fn insert(&mut self, index: u64, statement: &Statement) {
+        for digest in statement
+            .subject
+            .iter()
+            .flat_map(|subject| subject.digests())
+        {
+            let indices = self.entries.entry(digest).or_default();
+            // A statement may list one digest under several subjects.
+            if indices.last() != Some(&index) {
+                indices.push(index);
+            }
+        }
+    }
+
+    /// Forget entry `index`, whose payload was redacted.
+    pub fn remove(&mut self, index: u64) {
+        self.entries.retain(|_, indices| {
+            indices.retain(|at| *at != index);
+            !indices.is_empty()
+        });
+    }
+
+    /// Indices of the attestations about `digest`, oldest first.
+    pub fn get(&self, digest: &str) -> &[u64] {
+        self.entries
+            .get(&digest.to_ascii_lowercase())
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reality_core::{
+        dsse::IN_TOTO_PAYLOAD_TYPE,
+        signer::{KeyFileSigner, Signer},
+    };
+
+    use super::*;
+
+    fn envelope(signer: &KeyFileSigner) -> Envelope {
+        let statement = serde_json::json!({
+            "_type": "https://in-toto.io/Statement/v1",
+            "subject": [
+                {"name": "a", "digest": {"sha256": "AA"}},
+                {"name": "a.sig", "digest": {"sha256": "aa", "sha512": "bb"}},
+            ],
+            "predicateType": "https://slsa.dev/provenance/v1",
+        });
+        let payload = serde_json::to_vec(&statement).unwrap();
+        Envelope::sign(IN_TOTO_PAYLOAD_TYPE, &payload, signer, "").unwrap()
+    }
+
+    #[test]
+    fn admits_trusted_envelopes_and_indexes_subjects() {
+        let signer = KeyFileSigner::from_seed([4u8; 32]);
+        let other = KeyFileSigner::from_seed([5u8; 32]);
+        let pinned = Attestations::new(vec![hex::encode(signer.public_key())]);
+
+        let entry = pinned.admit(&envelope(&signer)).unwrap();
+        assert!(entry.idempotency_key.unwrap().starts_with("dsse:"));
+        assert_eq!(
+            pinned.admit(&envelope(&other)).unwrap_err(),
+            Rejection::Untrusted
+        );
+        assert!(Attestations::default().admit(&envelope(&other)).is_ok());
+        let mut unsigned = envelope(&signer);
+        unsigned.signatures.clear();
+        assert_eq!(
+            pinned.admit(&unsigned).unwrap_err(),
+            Rejection::Malformed(DsseError::Unsigned)
+        );
+
+        let mut index = SubjectIndex::default();
+        let statement = pinned.statement(&entry.payload).unwrap();
+        index.insert(7, &statement);
+        assert!(pinned
+            .statement(&serde_json::to_string(&envelope(&other)).unwrap())
+            .is_none());
+        assert!(pinned.statement("not an envelope").is_none());
+        assert_eq!(index.get("SHA256:aa"), [7]);
+        assert_eq!(index.get("sha512:bb"), [7]);
+        assert!(index.get("sha256:cc").is_empty());
+    }
+}
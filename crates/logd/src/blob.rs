@@ -0,0 +1,174 @@
+//! Large payloads kept outside the log. With `REALITY_LOG_BLOB_THRESHOLD`
+//! set, a payload longer than that many bytes is written to a blob store
+//! keyed by its SHA-256, and a [`BlobRef`] is appended in its place. The
+//! tree, tiles and entry listings then stay small whatever the artifacts
+//! weigh, and the leaf still commits to the blob's digest and length.
+//!
+//! A blob is stored before its reference is appended, so every reference in
+//! the log resolves. A blob whose append then fails is left behind; being
+//! keyed by content, it is reused if the payload is sent again.
+
+use std::path::PathBuf;
+
+use anyhow::Context;
+use reality_core::blob::BlobRef;
+use reqwest::{Method, StatusCode, Url};
+
+use crate::sigv4::{self, Credentials};
+
+/// Where blobs live.
+#[derive(Debug)]
+pub enum BlobStore {
+    /// One file per blob, named by its hex SHA-256.
+    Dir(PathBuf),
+    S3(S3),
+}
+
+/// Payloads over `threshold` bytes go to `store`.
+#[derive(Debug)]
+pub struct Blobs {
+    threshold: usize,
+    store: BlobStore,
+    http: reqwest::Client,
+}
+
+impl Blobs {
+    pub fn new(threshold: usize, store: BlobStore) -> Self {
+        Self {
+            threshold,
+            store,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// The reference to append in place of `payload` once it is stored, if
+    /// it is over the threshold. A payload that is already a reference is
+    /// appended as it is.
+    pub async fn offload(&self, payload: &str) -> anyhow::Result<Option<String>> {
+        if payload.len() <= self.threshold || BlobRef::from_payload(payload).is_some() {
+            return Ok(None);
+        }
+        Ok(Some(self.put(payload.as_bytes()).await?.to_payload()))
+    }
+
+    /// Store `content` unless it is already there.
+    pub async fn put(&self, content: &[u8]) -> anyhow::Result<BlobRef> {
+        let reference = BlobRef::new(content);
+        let key = reference.sha256().expect("new references are sha256");
+        match &self.store {
+            BlobStore::Dir(dir) => {
+                let path = dir.join(key);
+                if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+                    return Ok(reference);
+                }
+                tokio::fs::create_dir_all(dir)
+                    .await
+                    .with_context(|| format!("create {}", dir.display()))?;
+                // Renamed into place, so a crash never leaves a torn blob
+                // under its digest.
+                let partial = dir.join(format!("{key}.partial"));
+                tokio::fs::write(&partial, content)
+                    .await
+                    .with_context(|| format!("write {}", partial.display()))?;
+                tokio::fs::rename(&partial, &path)
+                    .await
+                    .with_context(|| format!("rename {}", partial.display()))?;
+            }
+            BlobStore::S3(s3) => {
+                let response = s3
+                    .request(&self.http, Method::PUT, key, content)
+                    .send()
+                    .await?;
+                anyhow::ensure!(
+                    response.status().is_success(),
+                    "S3 PUT {key} returned {}",
+                    response.status()
+                );
+            }
+        }
+        Ok(reference)
+    }
+
+    /// The blob with hex SHA-256 `key`, if it is stored.
+    pub async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        match &self.store {
+            BlobStore::Dir(dir) => match tokio::fs::read(dir.join(key)).await {
+                Ok(content) => Ok(Some(content)),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err).with_context(|| format!("read blob {key}")),
+            },
+            BlobStore::S3(s3) => {
+                let response = s3.request(&self.http, Method::GET, key, b"").send().await?;
+                match response.status() {
+                    StatusCode::NOT_FOUND => Ok(None),
+                    status if status.is_success() => Ok(Some(response.bytes().await?.to_vec())),
+                    status => anyhow::bail!("S3 GET {key} returned {status}"),
+                }
+            }
+        }
+    }
+}
+
+/// An S3 bucket, or any store with S3's API, addressed path-style as
+/// `<endpoint>/<bucket>`.
+#[derive(Debug)]
+pub struct S3 {
+    /// The bucket's URL, ending in `/`.
+    bucket: Url,
+    credentials: Credentials,
+}
+
+impl S3 {
+    pub fn new(bucket: &str, credentials: Credentials) -> anyhow::Result<Self> {
+        let bucket = Url::parse(&format!("{}/", bucket.trim_end_matches('/')))
+            .with_context(|| format!("{bucket:?} is not a bucket URL"))?;
+        anyhow::ensure!(bucket.has_host(), "{bucket} has no host");
+        Ok(Self {
+            bucket,
+            credentials,
+        })
+    }
+
+    fn request(
+        &self,
+        http: &reqwest::Client,
+        method: Method,
+        key: &str,
+        body: &[u8],
+    ) -> reqwest::RequestBuilder {
+        let url = self.bucket.join(key).expect("hex keys are valid paths");
+        sigv4::request(
+            &self.credentials,
+            http,
+            "s3",
+            method,
+            url,
+            &[],
+            body.to_vec(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn offloads_large_payloads_by_digest() {
+        let dir = std::env::temp_dir().join(format!("logd-blobs-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let blobs = Blobs::new(8, BlobStore::Dir(dir.clone()));
+        assert_eq!(blobs.offload("small").await.unwrap(), None);
+        let large = "a payload over eight bytes";
+        let offloaded = blobs.offload(large).await.unwrap().unwrap();
+        let reference = BlobRef::from_payload(&offloaded).unwrap();
+        assert!(reference.matches(large.as_bytes()));
+        let key = reference.sha256().unwrap();
+        assert_eq!(blobs.get(key).await.unwrap().unwrap(), large.as_bytes());
+        assert_eq!(blobs.put(large.as_bytes()).await.unwrap(), reference);
+        assert_eq!(blobs.offload(&offloaded).await.unwrap(), None);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        assert!(blobs.get(&"0".repeat(64)).await.unwrap().is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
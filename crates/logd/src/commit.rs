@@ -0,0 +1,166 @@
+//! Group commit. An append returns once its entry is written to the log's
+//! files, which survives logd restarting but not the machine losing power.
+//! With `?durability=synced`, or `REALITY_LOG_DURABILITY=synced` for every
+//! append, it returns only once the files are also flushed to disk.
+//!
+//! A flush costs far more than a write, so appends share one: the first
+//! append waiting for a flush opens a window of `REALITY_LOG_SYNC_WINDOW_MS`
+//! milliseconds, every append that waits within it joins, and a single
+//! `Log::sync` at its end covers them all. The window is the most a synced
+//! append waits for others to join.
+
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use axum::http::StatusCode;
+use reality_log::Log;
+use serde::Deserialize;
+use tokio::sync::{oneshot, Mutex, Notify};
+use tracing::{debug, error};
+
+/// The window when `REALITY_LOG_SYNC_WINDOW_MS` is unset.
+pub const DEFAULT_WINDOW: Duration = Duration::from_millis(2);
+
+/// When an append is acknowledged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Durability {
+    /// Once written to the log's files.
+    Written,
+    /// Once those files are flushed to disk.
+    Synced,
+}
+
+impl FromStr for Durability {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "written" => Ok(Self::Written),
+            "synced" => Ok(Self::Synced),
+            _ => anyhow::bail!("durability must be written or synced, not {s:?}"),
+        }
+    }
+}
+
+/// Appends waiting for the next flush.
+#[derive(Debug)]
+pub struct GroupCommit {
+    /// For requests that do not ask for a durability.
+    default: Durability,
+    window: Duration,
+    /// Told whether the flush covering them succeeded.
+    waiting: std::sync::Mutex<Vec<oneshot::Sender<bool>>>,
+    wake: Notify,
+}
+
+impl Default for GroupCommit {
+    fn default() -> Self {
+        Self::new(Durability::Written, DEFAULT_WINDOW)
+    }
+}
+
+impl GroupCommit {
+    pub fn new(default: Durability, window: Duration) -> Self {
+        Self {
+            default,
+            window,
+            waiting: std::sync::Mutex::new(Vec::new()),
+            wake: Notify::new(),
+        }
+    }
+
+    /// Wait until what was appended before the call is as durable as
+    /// `requested` asks, or the default when it does not. Call it after
+    /// releasing the log, which the flush takes.
+    pub async fn durable(&self, requested: Option<Durability>) -> Result<(), (StatusCode, String)> {
+        if requested.unwrap_or(self.default) == Durability::Written {
+            return Ok(());
+        }
+        let (flushed, outcome) = oneshot::channel();
+        self.waiting
+            .lock()
+            .expect("group commit lock poisoned")
+            .push(flushed);
+        self.wake.notify_one();
+        match outcome.await {
+            Ok(true) => Ok(()),
+            _ => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "appended, but failed to flush the log to disk".into(),
+            )),
+        }
+    }
+}
+
+/// Flush `log` for each window in which appends wait.
+pub fn spawn_syncer(group: Arc<GroupCommit>, log: Arc<Mutex<Log>>) {
+    tokio::spawn(async move {
+        loop {
+            group.wake.notified().await;
+            tokio::time::sleep(group.window).await;
+            let waiting =
+                std::mem::take(&mut *group.waiting.lock().expect("group commit lock poisoned"));
+            if waiting.is_empty() {
+                continue;
+            }
+            // Held while flushing: appends rewrite the files, and a flush in
+            // the middle of that could put less on disk than was written.
+            let flushed = match log.lock().await.sync() {
+                Ok(()) => true,
+                Err(err) => {
+                    error!(?err, "failed to flush the log");
+                    false
+                }
+            };
+            debug!(appends = waiting.len(), "flushed the log");
+            for waiter in waiting {
+                let _ = waiter.send(flushed);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn concurrent_appends_share_a_flush() {
+        let dir = std::env::temp_dir().join(format!("logd-commit-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let log = Arc::new(Mutex::new(Log::open(&dir).unwrap()));
+        let group = Arc::new(GroupCommit::new(
+            Durability::Written,
+            Duration::from_millis(50),
+        ));
+        spawn_syncer(group.clone(), log.clone());
+
+        // Written appends never wait for the syncer.
+        log.lock().await.append("a").unwrap();
+        group.durable(None).await.unwrap();
+
+        let acknowledged = Arc::new(AtomicUsize::new(0));
+        let appends: Vec<_> = (0..20)
+            .map(|i| {
+                let (log, group, acknowledged) = (log.clone(), group.clone(), acknowledged.clone());
+                tokio::spawn(async move {
+                    log.lock().await.append(format!("entry {i}")).unwrap();
+                    group.durable(Some(Durability::Synced)).await.unwrap();
+                    acknowledged.fetch_add(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        // All of them wait out the window opened by the first.
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(acknowledged.load(Ordering::SeqCst), 0);
+        for append in appends {
+            append.await.unwrap();
+        }
+        assert_eq!(acknowledged.load(Ordering::SeqCst), 20);
+        assert_eq!(Log::open(&dir).unwrap().len(), 21);
+        assert!("fsync".parse::<Durability>().is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
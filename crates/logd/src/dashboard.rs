@@ -0,0 +1,78 @@
+//! The built-in dashboard (the `dashboard` feature): the files in
+//! `crates/logd/dashboard/`, embedded into the binary and served at `/` and
+//! `/dashboard/`. The page only calls logd's public API. It checks proofs
+//! with the wasm verifier when `dashboard/pkg/` held a wasm-pack build at
+//! compile time, and with `/verify` otherwise.
+
+use axum::{
+    extract::Path,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use rust_embed::RustEmbed;
+
+#[derive(RustEmbed)]
+#[folder = "dashboard/"]
+struct Assets;
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/", get(|| async { serve("index.html") }))
+        .route(
+            "/dashboard/*path",
+            get(|Path(path): Path<String>| async move { serve(&path) }),
+        )
+}
+
+fn serve(path: &str) -> Response {
+    match Assets::get(path) {
+        Some(file) => (
+            [
+                (header::CONTENT_TYPE, content_type(path)),
+                // Assets change with the binary, so browsers must revalidate.
+                (header::CACHE_CONTROL, "no-cache"),
+            ],
+            file.data,
+        )
+            .into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+fn content_type(path: &str) -> &'static str {
+    match path.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("json") => "application/json",
+        Some("wasm") => "application/wasm",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serves_embedded_assets() {
+        let index = serve("index.html");
+        assert_eq!(index.status(), StatusCode::OK);
+        assert_eq!(
+            index.headers()[header::CONTENT_TYPE],
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(
+            serve("dashboard.js").headers()[header::CONTENT_TYPE],
+            "text/javascript; charset=utf-8"
+        );
+        assert_eq!(serve("missing.js").status(), StatusCode::NOT_FOUND);
+        assert_eq!(serve("../Cargo.toml").status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            content_type("pkg/reality_wasm_core_bg.wasm"),
+            "application/wasm"
+        );
+    }
+}
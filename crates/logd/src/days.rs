@@ -0,0 +1,122 @@
+//! Day buckets. With `REALITY_LOG_DAYS=1`, logd seals each UTC day once it
+//! is over: the root of the day's own tree goes into a master log under
+//! `days/` in the data directory (see `reality_log::days`). With a blob
+//! store configured, the day's entries are also archived there as one unit
+//! first, and the seal names the archive.
+//!
+//! Sealing runs at startup and then once a minute, so a day is sealed
+//! within a minute of midnight UTC, or at the next start after downtime.
+
+use std::{sync::Arc, time::Duration};
+
+use reality_log::{days::Days, LogReader};
+use time::OffsetDateTime;
+use tokio::sync::Mutex;
+use tracing::{error, info};
+
+use crate::blob::Blobs;
+
+/// How often logd looks for days to seal.
+const EVERY: Duration = Duration::from_secs(60);
+
+/// Seal in the background every day of `reader`'s log that is over.
+pub fn spawn_sealer(days: Arc<Mutex<Days>>, reader: LogReader, blobs: Option<Arc<Blobs>>) {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(EVERY);
+        loop {
+            ticks.tick().await;
+            if let Err(err) = seal_closed(&days, &reader, blobs.as_deref(), &today()).await {
+                error!(?err, "failed to seal a day");
+            }
+        }
+    });
+}
+
+/// Seal every day over by `today`, archiving each to `blobs` first when
+/// given. Returns how many were sealed.
+pub async fn seal_closed(
+    days: &Mutex<Days>,
+    reader: &LogReader,
+    blobs: Option<&Blobs>,
+    today: &str,
+) -> anyhow::Result<usize> {
+    let snapshot = reader.snapshot();
+    // Only this task seals, so the lock is held for appends alone and the
+    // seals worked out now are still next when they are appended.
+    let closed = days.lock().await.closed(&snapshot, today);
+    for mut seal in closed.iter().cloned() {
+        if let Some(blobs) = blobs {
+            let archive = blobs.put(&Days::archive(&snapshot, &seal)).await?;
+            seal.archive = Some(archive.blob);
+        }
+        info!(day = %seal.day, first = seal.first, size = seal.size, "sealed day");
+        days.lock().await.seal(seal)?;
+    }
+    Ok(closed.len())
+}
+
+/// Today's UTC date as `YYYY-MM-DD`.
+fn today() -> String {
+    let date = OffsetDateTime::now_utc().date();
+    format!(
+        "{:04}-{:02}-{:02}",
+        date.year(),
+        u8::from(date.month()),
+        date.day()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use reality_log::Log;
+
+    use super::*;
+    use crate::blob::BlobStore;
+
+    #[tokio::test]
+    async fn seals_and_archives_closed_days() {
+        let dir = std::env::temp_dir().join(format!("logd-days-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut log = Log::open(&dir).unwrap();
+        for payload in ["a", "b"] {
+            log.append(payload).unwrap();
+        }
+        let days = Mutex::new(Days::open(&dir).unwrap());
+        let blobs = Blobs::new(0, BlobStore::Dir(dir.join("blobs")));
+        let reader = log.reader();
+
+        assert_eq!(
+            seal_closed(&days, &reader, Some(&blobs), &today())
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            seal_closed(&days, &reader, Some(&blobs), "9999-12-31")
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            seal_closed(&days, &reader, Some(&blobs), "9999-12-31")
+                .await
+                .unwrap(),
+            0
+        );
+
+        let days = days.into_inner();
+        let seal = &days.list().days[0].seal;
+        let archive = seal.archive.as_deref().unwrap();
+        let stored = blobs
+            .get(&archive["sha256:".len()..])
+            .await
+            .unwrap()
+            .unwrap();
+        let archived: reality_log::days::DayArchive = serde_json::from_slice(&stored).unwrap();
+        assert_eq!(&archived.seal.root, &seal.root);
+        assert_eq!(archived.entries.len(), 2);
+        let receipt = days.receipt(&reader.snapshot(), 1).unwrap().unwrap();
+        assert_eq!(&receipt.verify().unwrap(), seal);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,188 @@
+//! Just enough DER to pick fields out of certificates and timestamp
+//! requests, and to write timestamp tokens.
+
+use time::OffsetDateTime;
+
+pub const BOOLEAN: u8 = 0x01;
+pub const INTEGER: u8 = 0x02;
+pub const BIT_STRING: u8 = 0x03;
+pub const OCTET_STRING: u8 = 0x04;
+pub const OID: u8 = 0x06;
+pub const UTF8_STRING: u8 = 0x0c;
+pub const GENERALIZED_TIME: u8 = 0x18;
+pub const SEQUENCE: u8 = 0x30;
+pub const SET: u8 = 0x31;
+
+/// Context-specific tag `[n]`, constructed.
+pub const fn context(n: u8) -> u8 {
+    0xa0 | n
+}
+
+#[derive(Clone, Copy)]
+pub struct Element<'a> {
+    pub tag: u8,
+    pub contents: &'a [u8],
+    /// The whole element, header included.
+    pub raw: &'a [u8],
+}
+
+/// The element at the start of `input`.
+pub fn read(input: &[u8]) -> Option<Element<'_>> {
+    split(input).map(|(element, _)| element)
+}
+
+/// The consecutive elements in `input`.
+pub fn elements(mut input: &[u8]) -> impl Iterator<Item = Element<'_>> {
+    std::iter::from_fn(move || {
+        let (element, rest) = split(input)?;
+        input = rest;
+        Some(element)
+    })
+}
+
+fn split(input: &[u8]) -> Option<(Element<'_>, &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (usize::from(first), rest)
+    } else {
+        let width = usize::from(first & 0x7f);
+        if width == 0 || width > 4 || rest.len() < width {
+            return None;
+        }
+        let (len, rest) = rest.split_at(width);
+        let len = len
+            .iter()
+            .fold(0usize, |len, &byte| len << 8 | usize::from(byte));
+        (len, rest)
+    };
+    if rest.len() < len {
+        return None;
+    }
+    let header = input.len() - rest.len();
+    let (contents, rest) = rest.split_at(len);
+    Some((
+        Element {
+            tag,
+            contents,
+            raw: &input[..header + len],
+        },
+        rest,
+    ))
+}
+
+pub fn tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    match contents.len() {
+        len @ 0..=0x7f => out.push(len as u8),
+        len => {
+            let bytes = len.to_be_bytes();
+            let skip = bytes.iter().take_while(|&&byte| byte == 0).count();
+            out.push(0x80 | (bytes.len() - skip) as u8);
+            out.extend_from_slice(&bytes[skip..]);
+        }
+    }
+    out.extend_from_slice(contents);
+    out
+}
+
+pub fn sequence(parts: &[&[u8]]) -> Vec<u8> {
+    tlv(SEQUENCE, &parts.concat())
+}
+
+/// A SET OF, with its members in DER's sorted order, under `tag`.
+pub fn set_of(tag: u8, mut members: Vec<Vec<u8>>) -> Vec<u8> {
+    members.sort();
+    tlv(tag, &members.concat())
+}
+
+/// A non-negative INTEGER from big-endian bytes.
+pub fn unsigned(bytes: &[u8]) -> Vec<u8> {
+    let skip = bytes.iter().take_while(|&&byte| byte == 0).count();
+    let mut contents = bytes[skip..].to_vec();
+    if contents.first().is_none_or(|&byte| byte & 0x80 != 0) {
+        contents.insert(0, 0);
+    }
+    tlv(INTEGER, &contents)
+}
+
+pub fn small(n: u64) -> Vec<u8> {
+    unsigned(&n.to_be_bytes())
+}
+
+/// The contents of the OBJECT IDENTIFIER written `dotted`, such as
+/// `1.2.840.113549`, or `None` if it is not one.
+pub fn oid_contents(dotted: &str) -> Option<Vec<u8>> {
+    let arcs = dotted
+        .split('.')
+        .map(|arc| arc.parse::<u128>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let [first @ 0..=2, second, rest @ ..] = arcs.as_slice() else {
+        return None;
+    };
+    if *first < 2 && *second >= 40 {
+        return None;
+    }
+    let mut out = Vec::new();
+    for arc in std::iter::once(first * 40 + second).chain(rest.iter().copied()) {
+        let mut chunk = vec![(arc & 0x7f) as u8];
+        let mut arc = arc >> 7;
+        while arc > 0 {
+            chunk.push(0x80 | (arc & 0x7f) as u8);
+            arc >>= 7;
+        }
+        out.extend(chunk.iter().rev());
+    }
+    Some(out)
+}
+
+/// An OBJECT IDENTIFIER known to be well formed.
+pub fn oid(dotted: &str) -> Vec<u8> {
+    tlv(OID, &oid_contents(dotted).expect("well-formed OID"))
+}
+
+/// `YYYYMMDDHHMMSSZ`, to the second.
+pub fn generalized_time(time: OffsetDateTime) -> Vec<u8> {
+    let time = time.to_offset(time::UtcOffset::UTC);
+    let text = format!(
+        "{:04}{:02}{:02}{:02}{:02}{:02}Z",
+        time.year(),
+        u8::from(time.month()),
+        time.day(),
+        time.hour(),
+        time.minute(),
+        time.second()
+    );
+    tlv(GENERALIZED_TIME, text.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_elements_and_encodes_oids() {
+        let long = vec![7u8; 300];
+        let encoded = sequence(&[&small(0), &small(128), &tlv(OCTET_STRING, &long)]);
+        assert_eq!(&encoded[..4], [SEQUENCE, 0x82, 0x01, 0x37]);
+        let outer = read(&encoded).unwrap();
+        assert_eq!(outer.raw, encoded);
+        let inner: Vec<_> = elements(outer.contents).collect();
+        assert_eq!(inner[0].raw, [INTEGER, 1, 0]);
+        assert_eq!(inner[1].raw, [INTEGER, 2, 0, 0x80]);
+        assert_eq!(inner[2].contents, long);
+        assert!(read(&encoded[..100]).is_none());
+
+        assert_eq!(
+            oid("1.2.840.113549.1.9.16.1.4"),
+            [OID, 11, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 1, 9, 16, 1, 4]
+        );
+        assert_eq!(oid_contents("2.5.29.17").unwrap(), [0x55, 0x1d, 0x11]);
+        assert!(oid_contents("1.40").is_none());
+        assert!(oid_contents("3.1").is_none());
+        assert!(oid_contents("1.2.x").is_none());
+
+        let time = OffsetDateTime::from_unix_timestamp(1_735_689_660).unwrap();
+        assert_eq!(generalized_time(time)[2..], *b"20250101000100Z");
+    }
+}
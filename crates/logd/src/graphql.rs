@@ -0,0 +1,372 @@
+//! GraphQL over entries, proofs, checkpoints and anchors (the `graphql`
+//! feature), so a page can fetch an entry, its proof and the anchor that
+//! covers it in one round trip. `POST /graphql` executes a query and
+//! `GET /graphql` returns the schema in SDL.
+//!
+//! Each request sees the tree as it was when the request arrived: proofs,
+//! the checkpoint and covering anchors in one response all describe the
+//! same size and root, even while entries are appended.
+
+use std::sync::Arc;
+
+use async_graphql::{
+    ComplexObject, Context, EmptyMutation, EmptySubscription, Enum, Error, Object, Schema,
+    SimpleObject,
+};
+use axum::{extract::State, routing::get, Json, Router};
+use reality_core::{
+    checkpoint::SignedCheckpoint, AnchorQuery, AnchorStatus, Direction, InclusionProof,
+    StoredAnchor,
+};
+use reality_log::Snapshot;
+use tokio::sync::OnceCell;
+
+use crate::AppState;
+
+/// Most entries one `entries` field returns.
+const MAX_ENTRIES: u64 = 1000;
+
+pub type LogSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+#[derive(Clone)]
+struct Graphql {
+    schema: LogSchema,
+    state: AppState,
+}
+
+pub fn router(state: AppState) -> Router {
+    let schema = Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .limit_depth(8)
+        .finish();
+    Router::new()
+        .route("/graphql", get(sdl).post(execute))
+        .with_state(Graphql { schema, state })
+}
+
+async fn execute(
+    State(graphql): State<Graphql>,
+    Json(request): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    let view = Arc::new(View::new(&graphql.state));
+    Json(graphql.schema.execute(request.data(view)).await)
+}
+
+async fn sdl(State(graphql): State<Graphql>) -> String {
+    graphql.schema.sdl()
+}
+
+/// The tree one request sees. Anchors are only fetched if a field asks
+/// for them.
+struct View {
+    state: AppState,
+    snapshot: Arc<Snapshot>,
+    anchors: OnceCell<Vec<StoredAnchor>>,
+}
+
+impl View {
+    fn new(state: &AppState) -> Self {
+        Self {
+            state: state.clone(),
+            snapshot: state.reader.snapshot(),
+            anchors: OnceCell::new(),
+        }
+    }
+
+    fn size(&self) -> u64 {
+        self.snapshot.len()
+    }
+
+    async fn anchors(&self) -> async_graphql::Result<&[StoredAnchor]> {
+        self.anchors
+            .get_or_try_init(|| async {
+                // Without an anchor daemon no entry is anchored.
+                if self.state.anchor_api.is_none() {
+                    return Ok(Vec::new());
+                }
+                self.state
+                    .read_anchors(&AnchorQuery::default())
+                    .await
+                    .map_err(|err| {
+                        tracing::error!(?err, "failed to read anchors");
+                        Error::new("failed to read anchors")
+                    })
+            })
+            .await
+            .map(Vec::as_slice)
+    }
+
+    fn entry(self: &Arc<Self>, index: u64) -> Option<Entry> {
+        let entry = self.snapshot.get(index)?;
+        Some(Entry {
+            index,
+            payload: entry.payload.clone(),
+            leaf: hex::encode(entry.leaf),
+            appended_at: entry.appended_at.clone(),
+            view: self.clone(),
+        })
+    }
+}
+
+fn view<'a>(ctx: &Context<'a>) -> &'a Arc<View> {
+    ctx.data_unchecked::<Arc<View>>()
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Number of entries in the tree this request sees.
+    async fn size(&self, ctx: &Context<'_>) -> u64 {
+        view(ctx).size()
+    }
+
+    /// Hex root of the tree this request sees.
+    async fn root(&self, ctx: &Context<'_>) -> String {
+        view(ctx).snapshot.root().root
+    }
+
+    /// The entry at `index`, or null past the end of the log.
+    async fn entry(&self, ctx: &Context<'_>, index: u64) -> Option<Entry> {
+        view(ctx).entry(index)
+    }
+
+    /// Up to `limit` entries (at most 1000) from index `from`.
+    async fn entries(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 0)] from: u64,
+        #[graphql(default = 100)] limit: u64,
+    ) -> Vec<Entry> {
+        let view = view(ctx);
+        let end = from.saturating_add(limit.min(MAX_ENTRIES)).min(view.size());
+        let mut entries = Vec::new();
+        for index in from..end {
+            entries.extend(view.entry(index));
+        }
+        entries
+    }
+
+    /// The tree head signed with the checkpoint key, or null when logd has
+    /// no signer.
+    async fn checkpoint(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<SignedHead>> {
+        let view = view(ctx);
+        if view.state.signer.is_none() {
+            return Ok(None);
+        }
+        let signed = view
+            .state
+            .signed_checkpoint(view.snapshot.clone())
+            .await
+            .map_err(|(_, message)| Error::new(message))?;
+        Ok(Some(signed.into()))
+    }
+
+    /// Anchors of this log's roots, oldest first.
+    async fn anchors(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Anchor>> {
+        let anchors = view(ctx).anchors().await?;
+        Ok(anchors.iter().map(Anchor::from).collect())
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct Entry {
+    index: u64,
+    payload: String,
+    /// Hex leaf hash of the payload.
+    leaf: String,
+    /// RFC 3339 time the entry was appended.
+    appended_at: String,
+    #[graphql(skip)]
+    view: Arc<View>,
+}
+
+#[ComplexObject]
+impl Entry {
+    /// Inclusion proof against the tree this request sees.
+    async fn proof(&self) -> async_graphql::Result<Proof> {
+        let proof = self.view.snapshot.proof(self.index)?;
+        Ok(proof.into())
+    }
+
+    /// The first confirmed anchor of a tree that holds this entry, if any.
+    async fn anchor(&self) -> async_graphql::Result<Option<Anchor>> {
+        let anchors = self.view.anchors().await?;
+        Ok(anchors
+            .iter()
+            .find(|anchor| {
+                anchor.status == AnchorStatus::Confirmed && anchor.record.size > self.index
+            })
+            .map(Anchor::from))
+    }
+}
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+#[derive(SimpleObject)]
+pub struct Step {
+    /// Which side of the path the sibling is on.
+    side: Side,
+    hash: String,
+}
+
+#[derive(SimpleObject)]
+pub struct Proof {
+    index: u64,
+    leaf: String,
+    path: Vec<Step>,
+    root: String,
+    size: u64,
+    algorithm: String,
+}
+
+impl From<InclusionProof> for Proof {
+    fn from(proof: InclusionProof) -> Self {
+        Self {
+            index: proof.index,
+            leaf: proof.leaf,
+            path: proof
+                .path
+                .into_iter()
+                .map(|step| Step {
+                    side: match step.direction {
+                        Direction::Left => Side::Left,
+                        Direction::Right => Side::Right,
+                    },
+                    hash: step.hash,
+                })
+                .collect(),
+            root: proof.root,
+            size: proof.size,
+            algorithm: proof.algorithm.to_string(),
+        }
+    }
+}
+
+/// A checkpoint and the log key's signature over it.
+#[derive(SimpleObject)]
+pub struct SignedHead {
+    size: u64,
+    root: String,
+    timestamp_nanos: String,
+    public_key: String,
+    signature: String,
+}
+
+impl From<SignedCheckpoint> for SignedHead {
+    fn from(signed: SignedCheckpoint) -> Self {
+        Self {
+            size: signed.checkpoint.size,
+            root: signed.checkpoint.root,
+            timestamp_nanos: signed.checkpoint.timestamp_nanos,
+            public_key: signed.signature.public_key,
+            signature: signed.signature.signature,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct Anchor {
+    txid: String,
+    root: String,
+    size: u64,
+    timestamp_nanos: String,
+    status: String,
+    backend: Option<String>,
+    network: Option<String>,
+    block_height: Option<u64>,
+    confirmations: Option<u64>,
+    receipt_uri: Option<String>,
+}
+
+impl From<&StoredAnchor> for Anchor {
+    fn from(anchor: &StoredAnchor) -> Self {
+        let record = &anchor.record;
+        Self {
+            txid: record.txid.clone(),
+            root: record.root.clone(),
+            size: record.size,
+            timestamp_nanos: record.timestamp_nanos.clone(),
+            status: anchor.status.as_str().to_string(),
+            backend: record.metadata.backend.clone(),
+            network: record.metadata.network.clone(),
+            block_height: record.metadata.block_height,
+            confirmations: record.metadata.confirmations,
+            receipt_uri: record.metadata.receipt_uri.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        append, attestation::Attestations, link::Links, sigstore::Sigstore, webhook::Webhooks,
+        DurabilityQuery, Intake, Tokens,
+    };
+    use axum::extract::Query;
+    use reality_core::{signer::KeyFileSigner, AppendRequest};
+
+    #[tokio::test]
+    async fn answers_entries_with_proofs_and_a_matching_checkpoint() {
+        let dir = std::env::temp_dir().join(format!("logd-graphql-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let state = AppState::new(
+            dir.clone(),
+            None,
+            Some(Arc::new(KeyFileSigner::from_seed([5u8; 32]))),
+            Vec::new(),
+            Intake {
+                webhooks: Webhooks::default(),
+                attestations: Attestations::new(Vec::new()),
+                links: Links::default(),
+                sigstore: Sigstore::default(),
+                tsa: None,
+                blobs: None,
+            },
+            None,
+            Tokens::default(),
+        )
+        .await
+        .unwrap();
+        for payload in ["a", "b", "c"] {
+            let request = AppendRequest {
+                payload: payload.into(),
+                labels: Default::default(),
+                signature: None,
+            };
+            let _ = append(
+                Query(DurabilityQuery { durability: None }),
+                State(state.clone()),
+                Default::default(),
+                None,
+                Json(request),
+            )
+            .await
+            .unwrap();
+        }
+
+        let schema = Schema::build(QueryRoot, EmptyMutation, EmptySubscription).finish();
+        let query = "{ size checkpoint { root size } \
+                     entries(from: 1) { index payload anchor { txid } \
+                     proof { root size path { side hash } } } }";
+        let view = Arc::new(View::new(&state));
+        let response = schema
+            .execute(async_graphql::Request::new(query).data(view))
+            .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        assert_eq!(data["size"], 3);
+        let entries = data["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["payload"], "b");
+        assert_eq!(entries[0]["proof"]["root"], data["checkpoint"]["root"]);
+        assert_eq!(entries[0]["proof"]["path"][0]["side"], "LEFT");
+        assert!(entries[1]["anchor"].is_null());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
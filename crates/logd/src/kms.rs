@@ -0,0 +1,253 @@
+//! Keys for payloads encrypted at rest. With `REALITY_LOG_KMS` set, logd
+//! seals each new payload with a data key (see `reality_log::seal`). Data
+//! keys are only ever stored wrapped by a key-encryption key held in a KMS,
+//! in `payload-keys.json` under the data directory, and are unwrapped at
+//! startup. A leaked data directory holds no usable key.
+
+use std::path::Path;
+
+use anyhow::Context;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use reality_log::seal::PayloadKeys;
+use reqwest::{Method, Url};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    rand::{SecureRandom, SystemRandom},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::sigv4::{self, Credentials};
+
+/// Wrapped data keys, in this file under the data directory.
+pub const KEYS_FILE: &str = "payload-keys.json";
+
+/// Binds wrapped keys to their purpose, as AAD for a local key and as the
+/// encryption context for AWS KMS.
+const PURPOSE: &str = "reality-log payload key";
+
+/// Where the key-encryption key lives.
+pub enum Kms {
+    /// An AES-256 key in a local file of 64 hex digits.
+    File(Box<LessSafeKey>),
+    Aws(Box<AwsKms>),
+}
+
+/// A symmetric AWS KMS key, which never leaves KMS.
+pub struct AwsKms {
+    key_id: String,
+    endpoint: Url,
+    credentials: Credentials,
+    http: reqwest::Client,
+}
+
+impl std::fmt::Debug for Kms {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::File(_) => f.write_str("File"),
+            Self::Aws(aws) => f.debug_struct("Aws").field("key_id", &aws.key_id).finish(),
+        }
+    }
+}
+
+/// One data key as stored.
+#[derive(Debug, Serialize, Deserialize)]
+struct WrappedKey {
+    id: u32,
+    /// Base64 of the wrapped key.
+    wrapped: String,
+}
+
+impl Kms {
+    /// `file:<path>` or `aws-kms:<key id or ARN>`. AWS KMS is reached at
+    /// `AWS_ENDPOINT_URL_KMS`, or the regional endpoint when that is unset.
+    pub fn open(spec: &str) -> anyhow::Result<Self> {
+        if let Some(path) = spec.strip_prefix("file:") {
+            let hex = std::fs::read_to_string(path)
+                .with_context(|| format!("read key-encryption key {path}"))?;
+            let mut key = [0u8; 32];
+            hex::decode_to_slice(hex.trim(), &mut key)
+                .with_context(|| format!("{path} does not hold 32 bytes of hex"))?;
+            let key = UnboundKey::new(&AES_256_GCM, &key).expect("AES-256 keys are 32 bytes");
+            return Ok(Self::File(Box::new(LessSafeKey::new(key))));
+        }
+        if let Some(key_id) = spec.strip_prefix("aws-kms:") {
+            let credentials = Credentials::from_env().context("AWS KMS needs AWS credentials")?;
+            let endpoint = match std::env::var("AWS_ENDPOINT_URL_KMS") {
+                Ok(url) => url,
+                Err(_) => format!("https://kms.{}.amazonaws.com/", credentials.region),
+            };
+            return Ok(Self::Aws(Box::new(AwsKms {
+                key_id: key_id.to_string(),
+                endpoint: Url::parse(&endpoint)
+                    .with_context(|| format!("{endpoint:?} is not a URL"))?,
+                credentials,
+                http: reqwest::Client::new(),
+            })));
+        }
+        anyhow::bail!("unknown KMS {spec:?}; expected file:<path> or aws-kms:<key id>")
+    }
+
+    async fn wrap(&self, key: &[u8; 32]) -> anyhow::Result<Vec<u8>> {
+        match self {
+            Self::File(kek) => {
+                let mut nonce = [0u8; NONCE_LEN];
+                SystemRandom::new()
+                    .fill(&mut nonce)
+                    .map_err(|_| anyhow::anyhow!("the system random source failed"))?;
+                let mut wrapped = key.to_vec();
+                kek.seal_in_place_append_tag(
+                    Nonce::assume_unique_for_key(nonce),
+                    Aad::from(PURPOSE),
+                    &mut wrapped,
+                )
+                .map_err(|_| anyhow::anyhow!("failed to wrap a payload key"))?;
+                Ok([nonce.to_vec(), wrapped].concat())
+            }
+            Self::Aws(aws) => {
+                let body = json!({
+                    "KeyId": aws.key_id,
+                    "Plaintext": STANDARD.encode(key),
+                    "EncryptionContext": { "purpose": PURPOSE },
+                });
+                let reply = aws.call("Encrypt", body).await?;
+                decode_field(&reply, "CiphertextBlob")
+            }
+        }
+    }
+
+    async fn unwrap(&self, wrapped: &[u8]) -> anyhow::Result<[u8; 32]> {
+        let key = match self {
+            Self::File(kek) => {
+                anyhow::ensure!(wrapped.len() > NONCE_LEN, "wrapped key is truncated");
+                let (nonce, sealed) = wrapped.split_at(NONCE_LEN);
+                let nonce = Nonce::try_assume_unique_for_key(nonce).expect("nonce is 12 bytes");
+                let mut sealed = sealed.to_vec();
+                kek.open_in_place(nonce, Aad::from(PURPOSE), &mut sealed)
+                    .map_err(|_| anyhow::anyhow!("the key-encryption key does not open it"))?
+                    .to_vec()
+            }
+            Self::Aws(aws) => {
+                let body = json!({
+                    "KeyId": aws.key_id,
+                    "CiphertextBlob": STANDARD.encode(wrapped),
+                    "EncryptionContext": { "purpose": PURPOSE },
+                });
+                let reply = aws.call("Decrypt", body).await?;
+                decode_field(&reply, "Plaintext")?
+            }
+        };
+        key.try_into()
+            .map_err(|_| anyhow::anyhow!("unwrapped key is not 32 bytes"))
+    }
+}
+
+impl AwsKms {
+    /// Call AWS KMS action `action` with a JSON body.
+    async fn call(
+        &self,
+        action: &str,
+        body: serde_json::Value,
+    ) -> anyhow::Result<serde_json::Value> {
+        let target = format!("TrentService.{action}");
+        let response = sigv4::request(
+            &self.credentials,
+            &self.http,
+            "kms",
+            Method::POST,
+            self.endpoint.clone(),
+            &[
+                ("content-type", "application/x-amz-json-1.1"),
+                ("x-amz-target", &target),
+            ],
+            serde_json::to_vec(&body)?,
+        )
+        .send()
+        .await
+        .with_context(|| format!("call KMS {action}"))?;
+        let status = response.status();
+        let text = response.text().await?;
+        anyhow::ensure!(
+            status.is_success(),
+            "KMS {action} returned {status}: {text}"
+        );
+        Ok(serde_json::from_str(&text)?)
+    }
+}
+
+fn decode_field(reply: &serde_json::Value, field: &str) -> anyhow::Result<Vec<u8>> {
+    let value = reply[field]
+        .as_str()
+        .with_context(|| format!("KMS reply has no {field}"))?;
+    Ok(STANDARD.decode(value)?)
+}
+
+/// The data keys for the log in `data_dir`, unwrapped with `kms`. A new key
+/// is created, wrapped and stored when there is none or `rotate` is set;
+/// it seals payloads from then on, and the older keys still open theirs.
+pub async fn payload_keys(kms: &Kms, data_dir: &Path, rotate: bool) -> anyhow::Result<PayloadKeys> {
+    let path = data_dir.join(KEYS_FILE);
+    let mut stored: Vec<WrappedKey> = crate::read_json(path.clone()).await?.unwrap_or_default();
+    if stored.is_empty() || rotate {
+        let mut key = [0u8; 32];
+        SystemRandom::new()
+            .fill(&mut key)
+            .map_err(|_| anyhow::anyhow!("the system random source failed"))?;
+        let id = stored.iter().map(|key| key.id + 1).max().unwrap_or(1);
+        stored.push(WrappedKey {
+            id,
+            wrapped: STANDARD.encode(kms.wrap(&key).await?),
+        });
+        tokio::fs::create_dir_all(data_dir).await?;
+        crate::write_json(path, &stored).await?;
+    }
+    let mut keys: Option<PayloadKeys> = None;
+    // The newest key is current.
+    for stored in stored.iter().rev() {
+        let wrapped = STANDARD
+            .decode(&stored.wrapped)
+            .with_context(|| format!("payload key {} is not base64", stored.id))?;
+        let key = kms
+            .unwrap(&wrapped)
+            .await
+            .with_context(|| format!("unwrap payload key {}", stored.id))?;
+        keys = Some(match keys {
+            Some(keys) => keys.with_key(stored.id, key),
+            None => PayloadKeys::new(stored.id, key),
+        });
+    }
+    Ok(keys.expect("at least one key is stored"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn wraps_keys_and_rotates() {
+        let dir = std::env::temp_dir().join(format!("logd-kms-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let kek = dir.join("kek");
+        std::fs::write(&kek, hex::encode([5u8; 32])).unwrap();
+        let kms = Kms::open(&format!("file:{}", kek.display())).unwrap();
+        assert!(Kms::open("vault:x").is_err());
+
+        let first = payload_keys(&kms, &dir, false).await.unwrap();
+        assert_eq!(first.current(), 1);
+        let stored = std::fs::read_to_string(dir.join(KEYS_FILE)).unwrap();
+        assert_eq!(payload_keys(&kms, &dir, false).await.unwrap().current(), 1);
+        assert_eq!(
+            std::fs::read_to_string(dir.join(KEYS_FILE)).unwrap(),
+            stored
+        );
+        let rotated = payload_keys(&kms, &dir, true).await.unwrap();
+        assert_eq!(rotated.current(), 2);
+
+        // Another key-encryption key opens nothing.
+        std::fs::write(&kek, hex::encode([6u8; 32])).unwrap();
+        let other = Kms::open(&format!("file:{}", kek.display())).unwrap();
+        assert!(payload_keys(&other, &dir, false).await.is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
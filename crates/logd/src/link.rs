@@ -0,0 +1,313 @@
+//! Links to other logs as an entry type of their own (see
+//! `reality_core::link`). `/links` checks a link before it is appended: the
+//! other log's signature, whether its key is a trusted peer, and that the
+//! checkpoint neither forks from nor predates the one linked from that log
+//! before. Every link in the log, whichever route it came in by, counts as
+//! linked.
+
+use std::{collections::HashMap, sync::RwLock};
+
+use reality_core::{
+    checkpoint::Checkpoint,
+    link::{LinkError, LinkedCheckpoint, LogLink},
+    BatchEntry,
+};
+use sha2::{Digest, Sha256};
+use tokio::sync::{Mutex, MutexGuard};
+
+/// Which links are accepted, and the newest one from each log.
+#[derive(Debug, Default)]
+pub struct Links {
+    /// Hex keys of the logs that may be linked; empty accepts any.
+    peers: Vec<String>,
+    /// The newest linked checkpoint of each log, by its key.
+    latest: RwLock<HashMap<String, LinkedCheckpoint>>,
+    /// Held from checking a link until it is appended, so two links cannot
+    /// both pass against the same earlier one.
+    admitting: Mutex<()>,
+}
+
+/// Why a link was refused.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Rejection {
+    Malformed(LinkError),
+    Untrusted,
+    /// The log already links a larger checkpoint of that log.
+    Stale {
+        linked: u64,
+    },
+    /// The checkpoint, or the one its consistency proof starts from,
+    /// disagrees with the checkpoint linked from that log.
+    Fork {
+        linked: u64,
+    },
+    /// The consistency proof does not start at the checkpoint linked last.
+    Unlinked {
+        linked: u64,
+    },
+}
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed(err) => err.fmt(f),
+            Self::Untrusted => f.write_str("the checkpoint is not signed by a peer log"),
+            Self::Stale { linked } => {
+                write!(f, "a checkpoint of size {linked} is already linked")
+            }
+            Self::Fork { linked } => write!(
+                f,
+                "the checkpoint forks from the linked checkpoint of size {linked}"
+            ),
+            Self::Unlinked { linked } => write!(
+                f,
+                "the consistency proof must start at the linked checkpoint of size {linked}"
+            ),
+        }
+    }
+}
+
+impl Links {
+    pub fn new(peers: Vec<String>) -> Self {
+        Self {
+            peers,
+            ..Self::default()
+        }
+    }
+
+    pub fn peers(&self) -> &[String] {
+        &self.peers
+    }
+
+    /// Taken before [`Links::admit`] and held until the link is appended.
+    pub async fn admitting(&self) -> MutexGuard<'_, ()> {
+        self.admitting.lock().await
+    }
+
+    /// Check `link` and return the entry to append, keyed by its digest so
+    /// that a resubmission is a duplicate.
+    pub fn admit(&self, link: &LogLink) -> Result<BatchEntry, Rejection> {
+        link.verify().map_err(Rejection::Malformed)?;
+        if !self.trusts(link) {
+            return Err(Rejection::Untrusted);
+        }
+        if let Some(earlier) = self.read().get(&link.public_key()) {
+            check_against(link, &earlier.checkpoint.checkpoint)?;
+        }
+        let payload = link.to_payload();
+        Ok(BatchEntry {
+            idempotency_key: Some(format!(
+                "link:{}",
+                hex::encode(Sha256::digest(payload.as_bytes()))
+            )),
+            payload,
+            labels: Default::default(),
+            signature: None,
+        })
+    }
+
+    /// Count entry `index` as linked if its payload is a link this log
+    /// would accept and newer than the last from its log.
+    pub fn note(&self, index: u64, payload: &str) {
+        let Some(link) = LogLink::from_payload(payload) else {
+            return;
+        };
+        if link.verify().is_err() || !self.trusts(&link) {
+            return;
+        }
+        let mut latest = self.latest.write().expect("link index lock poisoned");
+        let newer = latest.get(&link.public_key()).is_none_or(|earlier| {
+            earlier.checkpoint.checkpoint.size < link.checkpoint.checkpoint.size
+        });
+        if newer {
+            latest.insert(
+                link.public_key(),
+                LinkedCheckpoint {
+                    index,
+                    checkpoint: link.checkpoint,
+                },
+            );
+        }
+    }
+
+    /// The newest linked checkpoint of each log, by key.
+    pub fn latest(&self) -> Vec<LinkedCheckpoint> {
+        let mut latest: Vec<_> = self.read().values().cloned().collect();
+        latest.sort_by(|a, b| {
+            a.checkpoint
+                .signature
+                .public_key
+                .cmp(&b.checkpoint.signature.public_key)
+        });
+        latest
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, HashMap<String, LinkedCheckpoint>> {
+        self.latest.read().expect("link index lock poisoned")
+    }
+
+    fn trusts(&self, link: &LogLink) -> bool {
+        self.peers.is_empty() || self.peers.contains(&link.public_key())
+    }
+}
+
+/// Whether `link` may follow `earlier`, the checkpoint linked last from the
+/// same log.
+fn check_against(link: &LogLink, earlier: &Checkpoint) -> Result<(), Rejection> {
+    let head = &link.checkpoint.checkpoint;
+    let linked = earlier.size;
+    if head.size < linked {
+        return Err(Rejection::Stale { linked });
+    }
+    if head.size == linked && !head.root.eq_ignore_ascii_case(&earlier.root) {
+        return Err(Rejection::Fork { linked });
+    }
+    match &link.consistency {
+        Some(_) if link.extends(earlier) => Ok(()),
+        Some(proof) if proof.old_size == linked => Err(Rejection::Fork { linked }),
+        Some(_) if head.size > linked => Err(Rejection::Unlinked { linked }),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reality_core::{
+        consistency::consistency_proof,
+        leaf_hash, root,
+        signer::{KeyFileSigner, Signer},
+    };
+
+    use super::*;
+
+    #[test]
+    fn refuses_forks_and_stale_links() {
+        let peer = KeyFileSigner::from_seed([8u8; 32]);
+        let stranger = KeyFileSigner::from_seed([9u8; 32]);
+        let leaves: Vec<_> = (0..6u8).map(|i| leaf_hash(&[i])).collect();
+        let link =
+            |signer: &KeyFileSigner, leaves: &[[u8; 32]], size: usize, from: Option<usize>| {
+                LogLink {
+                    checkpoint: Checkpoint {
+                        size: size as u64,
+                        root: hex::encode(root(&leaves[..size])),
+                        timestamp_nanos: "1".into(),
+                    }
+                    .sign(signer)
+                    .unwrap(),
+                    consistency: from.map(|old| consistency_proof(leaves, old, size).unwrap()),
+                }
+            };
+        let links = Links::new(vec![hex::encode(peer.public_key())]);
+        assert_eq!(
+            links.admit(&link(&stranger, &leaves, 3, None)).unwrap_err(),
+            Rejection::Untrusted
+        );
+        let first = links.admit(&link(&peer, &leaves, 3, None)).unwrap();
+        assert!(first.idempotency_key.unwrap().starts_with("link:"));
+        links.note(0, &first.payload);
+        links.note(1, "not a link");
+
+        // A forked history of the same log.
+        let mut forked = leaves.clone();
+        forked[1] = leaf_hash(b"rewritten");
+        assert_eq!(
+            links.admit(&link(&peer, &forked, 3, None)).unwrap_err(),
+            Rejection::Fork { linked: 3 }
+        );
+        assert_eq!(
+            links.admit(&link(&peer, &forked, 5, Some(3))).unwrap_err(),
+            Rejection::Fork { linked: 3 }
+        );
+        assert_eq!(
+            links.admit(&link(&peer, &leaves, 2, None)).unwrap_err(),
+            Rejection::Stale { linked: 3 }
+        );
+        assert_eq!(
+            links.admit(&link(&peer, &leaves, 5, Some(2))).unwrap_err(),
+            Rejection::Unlinked { linked: 3 }
+        );
+
+        let next = links.admit(&link(&peer, &leaves, 5, Some(3))).unwrap();
+        links.note(2, &next.payload);
+        assert!(links.admit(&link(&peer, &leaves, 6, None)).is_ok());
+        let latest = links.latest();
+        assert_eq!(latest.len(), 1);
+        assert_eq!(
+            (latest[0].index, latest[0].checkpoint.checkpoint.size),
+            (2, 5)
+        );
+    }
+
+    #[tokio::test]
+    async fn counts_links_appended_through_append() {
+        use std::sync::Arc;
+
+        use axum::{
+            extract::{Query, State},
+            Json,
+        };
+        use reality_core::AppendRequest;
+
+        use crate::{
+            append, attestation::Attestations, sigstore::Sigstore, webhook::Webhooks, AppState,
+            DurabilityQuery, Intake, Tokens,
+        };
+
+        let dir = std::env::temp_dir().join(format!("logd-link-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let peer = KeyFileSigner::from_seed([8u8; 32]);
+        let leaves: Vec<_> = (0..3u8).map(|i| leaf_hash(&[i])).collect();
+        let link = LogLink {
+            checkpoint: Checkpoint {
+                size: 3,
+                root: hex::encode(root(&leaves)),
+                timestamp_nanos: "1".into(),
+            }
+            .sign(&peer)
+            .unwrap(),
+            consistency: None,
+        };
+        let links = Links::new(vec![hex::encode(peer.public_key())]);
+        let payload = links.admit(&link).unwrap().payload;
+        let state = AppState::new(
+            dir.clone(),
+            None,
+            Some(Arc::new(KeyFileSigner::from_seed([5u8; 32]))),
+            Vec::new(),
+            Intake {
+                webhooks: Webhooks::default(),
+                attestations: Attestations::new(Vec::new()),
+                links,
+                sigstore: Sigstore::default(),
+                tsa: None,
+                blobs: None,
+            },
+            None,
+            Tokens::default(),
+        )
+        .await
+        .unwrap();
+
+        let appended = append(
+            Query(DurabilityQuery { durability: None }),
+            State(state.clone()),
+            Default::default(),
+            None,
+            Json(AppendRequest {
+                payload,
+                labels: Default::default(),
+                signature: None,
+            }),
+        )
+        .await
+        .unwrap();
+        let latest = state.links.latest();
+        assert_eq!(latest.len(), 1);
+        assert_eq!(
+            (latest[0].index, latest[0].checkpoint.checkpoint.size),
+            (appended.index, 3)
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,42 +1,205 @@
+mod attestation;
+mod blob;
+mod commit;
+#[cfg(feature = "dashboard")]
+mod dashboard;
+mod days;
+mod der;
+#[cfg(feature = "graphql")]
+mod graphql;
+mod kms;
+mod link;
+mod provenance;
+mod redaction;
+mod rollback;
+mod sigstore;
+mod sigv4;
+mod standby;
+mod storage;
+mod systemd;
+mod tsa;
+mod webhook;
+
 use std::{env, net::SocketAddr, path::PathBuf, sync::Arc};
 
 use anyhow::Context;
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    body::Bytes,
+    extract::{ConnectInfo, DefaultBodyLimit, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
 use reality_core::{
-    leaf_hash, make_proof, root as merkle_root, AnchorRecord, AppendRequest, AppendResponse,
-    InclusionProof, MerkleError, RootResponse, VerifyRequest, VerifyResponse,
+    bundle::{EntryBundle, PayloadEvidence, PayloadLookup},
+    checkpoint::{Cosignature, SignedCheckpoint},
+    consistency::ConsistencyProof,
+    day::{DayList, DayReceipt},
+    dsse::{AttestationHit, Envelope},
+    freshness::{valid_nonce, FreshCheckpoint, FreshnessRequest},
+    handoff::Handoff,
+    leaf::LabelHit,
+    link::{LinkedCheckpoint, LogLink},
+    receipt::{AnchorReceipt, AnchoredProof},
+    redaction::{RedactRequest, RedactionList, SignedTombstone},
+    scitt,
+    signer::{open_signer, DetachedSignature, Signer},
+    tile::{EntryTile, LeafTile, TILE_WIDTH},
+    AnchorQuery, AnchorStatus, AppendRequest, AppendResponse, BatchAppendRequest,
+    BatchAppendResponse, BatchEntry, InclusionProof, RootResponse, StoredAnchor, VerifyRequest,
+    VerifyResponse,
+};
+use reality_log::{
+    days::Days, payload_digest, seal::PayloadKeys, Entry, Log, LogError, LogReader, Origin,
+    Snapshot,
 };
+use sha2::{Digest, Sha256};
 use time::OffsetDateTime;
-use tokio::{net::TcpListener, sync::RwLock};
-use tracing::{error, info};
-
-#[derive(Clone, serde::Serialize, serde::Deserialize, Default)]
-struct LogEntry {
-    payload: String,
-    leaf: String,
-    appended_at: String,
-}
+use tokio::{net::TcpListener, sync::Mutex};
+use tracing::{error, info, warn};
 
-#[derive(Clone, serde::Serialize, serde::Deserialize, Default)]
-struct StateSnapshot {
-    leaves: Vec<String>,
-    entries: Vec<LogEntry>,
-}
+use crate::{
+    attestation::{Attestations, Rejection, SubjectIndex},
+    blob::{BlobStore, Blobs, S3},
+    commit::{Durability, GroupCommit},
+    kms::Kms,
+    link::Links,
+    provenance::Recorder,
+    redaction::Redactions,
+    rollback::Watermark,
+    sigstore::Sigstore,
+    sigv4::Credentials,
+    standby::Standby,
+    tsa::Tsa,
+    webhook::Webhooks,
+};
 
 #[derive(Clone)]
 struct AppState {
-    inner: Arc<RwLock<StateSnapshot>>,
+    /// Taken by appends only. Reads go through `reader`, which never waits
+    /// for an append.
+    log: Arc<Mutex<Log>>,
+    reader: LogReader,
+    /// Every tombstone, in a log of its own.
+    redactions: Arc<Redactions>,
+    /// Seals of closed days, with `REALITY_LOG_DAYS=1`.
+    days: Option<Arc<Mutex<Days>>>,
+    /// Where signed checkpoints are recorded, for rollback detection.
+    watermark: Option<Arc<Watermark>>,
+    /// Appends waiting for a flush, which startup's syncer performs.
+    commits: Arc<GroupCommit>,
+    /// The primary this log follows, with `REALITY_LOG_FOLLOW`.
+    standby: Option<Arc<Standby>>,
+    /// Indices of in-toto attestations by subject digest.
+    subjects: Arc<std::sync::RwLock<SubjectIndex>>,
     data_dir: PathBuf,
+    anchor_api: Option<String>,
+    http: reqwest::Client,
+    signer: Option<Arc<dyn Signer>>,
+    /// Hex public keys allowed to post cosignatures; empty accepts any.
+    witnesses: Arc<Vec<String>>,
+    /// Hex public keys allowed to sign entries; empty accepts any.
+    submitters: Arc<Vec<String>>,
+    /// What appends record about where their entries came from.
+    provenance: Arc<Recorder>,
+    witness_lock: Arc<Mutex<()>>,
+    webhooks: Arc<Webhooks>,
+    attestations: Arc<Attestations>,
+    links: Arc<Links>,
+    sigstore: Arc<Sigstore>,
+    tsa: Option<Arc<Tsa>>,
+    blobs: Option<Arc<Blobs>>,
+    /// Bearer token the routes serving plaintext payloads require, when set.
+    read_token: Option<Arc<str>>,
+    /// Bearer token of the `/admin` routes, which are off without one.
+    admin_token: Option<Arc<str>>,
+}
+
+/// Bearer tokens guarding routes, as configured at startup.
+#[derive(Default)]
+struct Tokens {
+    read: Option<String>,
+    admin: Option<String>,
+}
+
+/// The routes that check a submission of their own kind before appending
+/// it, as configured at startup.
+struct Intake {
+    webhooks: Webhooks,
+    attestations: Attestations,
+    links: Links,
+    sigstore: Sigstore,
+    tsa: Option<Tsa>,
+    /// Where payloads over the blob threshold are moved before appending.
+    blobs: Option<Blobs>,
+}
+
+/// Most entries accepted in one `/append/batch` request.
+const MAX_BATCH: usize = 1000;
+
+/// Largest request body accepted when blobs are enabled, unless
+/// `REALITY_LOG_BLOB_MAX_BYTES` says otherwise.
+const DEFAULT_BLOB_MAX: usize = 64 << 20;
+
+/// Why anchors cannot be read: they live in the anchor daemon's store.
+const NO_ANCHOR_API: &str = "anchors are not configured; set REALITY_ANCHOR_API";
+
+#[derive(serde::Deserialize)]
+struct AttestationQuery {
+    /// `<algorithm>:<value>`, such as `sha256:<hex>`.
+    digest: String,
+}
+
+#[derive(serde::Deserialize)]
+struct EntryQuery {
+    /// `<key>:<value>`, such as `service:checkout`.
+    label: Option<String>,
+    /// Hex public key of a submitter.
+    signer: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct ConsistencyQuery {
+    old: u64,
+    new: Option<u64>,
+}
+
+#[derive(serde::Deserialize)]
+struct DurabilityQuery {
+    durability: Option<Durability>,
+}
+
+#[derive(serde::Deserialize)]
+struct ReplicationQuery {
+    from: u64,
+    to: u64,
+}
+
+#[derive(serde::Deserialize)]
+struct RedactionQuery {
+    /// Only the tombstone for this entry of the main log.
+    entry: Option<u64>,
+}
+
+#[derive(Default, serde::Deserialize)]
+struct ProveQuery {
+    /// Prove against the tree of this many leaves instead of the current one.
+    size: Option<u64>,
+}
+
+#[derive(serde::Deserialize)]
+struct AnchorPin {
+    /// Prove against the root anchored in this transaction instead.
+    at_anchor: Option<String>,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
+    if env::args().nth(1).as_deref() == Some("verify-storage") {
+        return verify_storage().await;
+    }
 
     let port = env::var("PORT")
         .ok()
@@ -46,55 +209,582 @@ async fn main() -> anyhow::Result<()> {
 
     let data_dir =
         PathBuf::from(env::var("REALITY_LOG_DIR").unwrap_or_else(|_| "data".to_string()));
-    let state = AppState::new(data_dir).await?;
+    let anchor_api = env::var("REALITY_ANCHOR_API").ok();
+    let signer = match env::var("REALITY_LOG_SIGNER") {
+        Ok(spec) => {
+            let signer: Arc<dyn Signer> =
+                Arc::from(open_signer(&spec).context("open checkpoint signer")?);
+            info!(public_key = %hex::encode(signer.public_key()), "signing checkpoints");
+            Some(signer)
+        }
+        Err(_) => None,
+    };
+    let witnesses = hex_keys("REALITY_LOG_WITNESSES");
+    let attestations = Attestations::new(hex_keys("REALITY_LOG_DSSE_KEYS"));
+    if !attestations.keys().is_empty() {
+        info!(
+            keys = attestations.keys().len(),
+            "accepting attestations signed by trusted keys"
+        );
+    }
+    let links = Links::new(hex_keys("REALITY_LOG_PEERS"));
+    if !links.peers().is_empty() {
+        info!(
+            peers = links.peers().len(),
+            "linking checkpoints of peer logs"
+        );
+    }
+    let webhooks = match env::var("REALITY_LOG_WEBHOOKS") {
+        Ok(path) => {
+            let webhooks = Webhooks::load(path.as_ref())?;
+            info!(sources = ?webhooks.names().collect::<Vec<_>>(), "accepting webhooks");
+            webhooks
+        }
+        Err(_) => Webhooks::default(),
+    };
+    let sigstore = match env::var("REALITY_LOG_SIGSTORE_ROOTS") {
+        Ok(path) => {
+            let sigstore = Sigstore::load(path.as_ref())?;
+            info!(roots = sigstore.roots(), "verifying Sigstore bundles");
+            sigstore
+        }
+        Err(_) => Sigstore::default(),
+    };
+    let tsa = match (
+        env::var("REALITY_LOG_TSA_KEY"),
+        env::var("REALITY_LOG_TSA_CERT"),
+    ) {
+        (Ok(key), Ok(cert)) => {
+            anyhow::ensure!(
+                signer.is_some(),
+                "timestamping needs REALITY_LOG_SIGNER to sign the checkpoints in its tokens"
+            );
+            let policy = env::var("REALITY_LOG_TSA_POLICY").ok();
+            let tsa = Tsa::load(key.as_ref(), cert.as_ref(), policy.as_deref())?;
+            info!("issuing RFC 3161 timestamps");
+            Some(tsa)
+        }
+        (Err(_), Err(_)) => None,
+        _ => anyhow::bail!("set both REALITY_LOG_TSA_KEY and REALITY_LOG_TSA_CERT, or neither"),
+    };
+    let blobs = match env::var("REALITY_LOG_BLOB_THRESHOLD") {
+        Ok(threshold) => {
+            let threshold = threshold
+                .parse()
+                .context("REALITY_LOG_BLOB_THRESHOLD is not a number of bytes")?;
+            let store = match env::var("REALITY_LOG_BLOB_S3") {
+                Ok(bucket) => BlobStore::S3(S3::new(
+                    &bucket,
+                    Credentials::from_env().context("S3 blobs need AWS credentials")?,
+                )?),
+                Err(_) => BlobStore::Dir(data_dir.join("blobs")),
+            };
+            info!(threshold, ?store, "storing large payloads as blobs");
+            Some(Blobs::new(threshold, store))
+        }
+        Err(_) => None,
+    };
+    let tokens = Tokens {
+        read: env::var("REALITY_LOG_READ_TOKEN").ok(),
+        admin: env::var("REALITY_LOG_ADMIN_TOKEN").ok(),
+    };
+    let payload_keys = match env::var("REALITY_LOG_KMS") {
+        Ok(spec) => {
+            anyhow::ensure!(
+                tokens.read.is_some(),
+                "encrypting payloads needs REALITY_LOG_READ_TOKEN to guard /entry, /payload and /bundle"
+            );
+            let kms = Kms::open(&spec)?;
+            let rotate = env::var("REALITY_LOG_ROTATE_PAYLOAD_KEY").is_ok_and(|v| v == "1");
+            let keys = kms::payload_keys(&kms, &data_dir, rotate)
+                .await
+                .context("load payload keys")?;
+            info!(key = keys.current(), ?kms, "encrypting payloads at rest");
+            Some(keys)
+        }
+        Err(_) => None,
+    };
+    let body_limit = match env::var("REALITY_LOG_BLOB_MAX_BYTES") {
+        Ok(max) => max
+            .parse()
+            .context("REALITY_LOG_BLOB_MAX_BYTES is not a number of bytes")?,
+        Err(_) => DEFAULT_BLOB_MAX,
+    };
+    let state = AppState::new(
+        data_dir,
+        anchor_api,
+        signer,
+        witnesses,
+        Intake {
+            webhooks,
+            attestations,
+            links,
+            sigstore,
+            tsa,
+            blobs,
+        },
+        payload_keys,
+        tokens,
+    )
+    .await?;
+    let submitters = hex_keys("REALITY_LOG_SUBMITTER_KEYS");
+    if !submitters.is_empty() {
+        info!(
+            count = submitters.len(),
+            "accepting signed entries from registered submitters"
+        );
+    }
+    let state = AppState {
+        submitters: Arc::new(submitters),
+        ..state
+    };
+    let state = if env::var("REALITY_LOG_DAYS").is_ok_and(|v| v == "1") {
+        let days = Arc::new(Mutex::new(
+            Days::open(&state.data_dir).context("open the day log")?,
+        ));
+        info!("sealing each day into the day log");
+        days::spawn_sealer(days.clone(), state.reader.clone(), state.blobs.clone());
+        AppState {
+            days: Some(days),
+            ..state
+        }
+    } else {
+        state
+    };
+    let file = env::var("REALITY_LOG_CHECKPOINT_FILE")
+        .ok()
+        .map(PathBuf::from);
+    let witness = env::var("REALITY_LOG_CHECKPOINT_WITNESS").ok();
+    let state = if file.is_some() || witness.is_some() {
+        let signer = state.signer.clone().context(
+            "rollback detection records signed checkpoints, so it needs REALITY_LOG_SIGNER",
+        )?;
+        let watermark = Watermark::new(file, witness);
+        watermark
+            .check(&state.reader.snapshot(), &hex::encode(signer.public_key()))
+            .await?;
+        info!("recording signed checkpoints to detect rollbacks");
+        AppState {
+            watermark: Some(Arc::new(watermark)),
+            ..state
+        }
+    } else {
+        state
+    };
+    let durability = match env::var("REALITY_LOG_DURABILITY") {
+        Ok(durability) => durability.parse()?,
+        Err(_) => Durability::Written,
+    };
+    let window = match env::var("REALITY_LOG_SYNC_WINDOW_MS") {
+        Ok(window) => std::time::Duration::from_millis(
+            window
+                .parse()
+                .context("REALITY_LOG_SYNC_WINDOW_MS is not a number of milliseconds")?,
+        ),
+        Err(_) => commit::DEFAULT_WINDOW,
+    };
+    info!(?durability, ?window, "acknowledging appends");
+    let commits = Arc::new(GroupCommit::new(durability, window));
+    commit::spawn_syncer(commits.clone(), state.log.clone());
+    let state = AppState { commits, ..state };
+    if env::var("REALITY_LOG_LEAF_V2").is_ok_and(|v| v == "1") {
+        info!("committing sequence numbers and clocks into v2 leaves");
+        state.log.lock().await.use_leaf_v2();
+    }
+    let recorder = Recorder::new(
+        &env::var("REALITY_LOG_PROVENANCE").unwrap_or_default(),
+        &env::var("REALITY_LOG_PROVENANCE_STORED").unwrap_or_default(),
+        env::var("REALITY_LOG_PROVENANCE_SALT").ok(),
+    )?;
+    if recorder.commits() && !env::var("REALITY_LOG_LEAF_V2").is_ok_and(|v| v == "1") {
+        anyhow::bail!("committing provenance into leaves needs REALITY_LOG_LEAF_V2=1");
+    }
+    if recorder.records() {
+        info!(?recorder, "recording the provenance of appends");
+    }
+    let state = AppState {
+        provenance: Arc::new(recorder),
+        ..state
+    };
+    let follow = env::var("REALITY_LOG_FOLLOW").ok();
+    let promoted = state.data_path(standby::HANDOFF).exists();
+    if follow.is_some() && promoted {
+        warn!("this log was promoted from a standby; ignoring REALITY_LOG_FOLLOW");
+    }
+    let state = if let Some(primary) = follow.filter(|_| !promoted) {
+        let key = env::var("REALITY_LOG_FOLLOW_KEY").context(
+            "a standby checks the primary's checkpoints, so it needs REALITY_LOG_FOLLOW_KEY",
+        )?;
+        let every = match env::var("REALITY_LOG_FOLLOW_EVERY") {
+            Ok(every) => every
+                .parse()
+                .context("REALITY_LOG_FOLLOW_EVERY is not a number of seconds")?,
+            Err(_) => 5,
+        };
+        let token = env::var("REALITY_LOG_FOLLOW_TOKEN").ok();
+        let standby = Arc::new(Standby::new(primary, key, token));
+        info!(
+            primary = standby.primary(),
+            every, "following the primary as a standby"
+        );
+        let state = AppState {
+            standby: Some(standby.clone()),
+            ..state
+        };
+        standby::spawn_follower(
+            state.clone(),
+            standby,
+            std::time::Duration::from_secs(every),
+        );
+        state
+    } else {
+        state
+    };
+    if let Ok(every) = env::var("REALITY_LOG_VERIFY_EVERY") {
+        let every = every
+            .parse()
+            .context("REALITY_LOG_VERIFY_EVERY is not a number of seconds")?;
+        info!(every, "verifying storage in the background");
+        storage::spawn_verifier(state.clone(), std::time::Duration::from_secs(every));
+    }
 
     let app = Router::new()
         .route("/health", get(health))
         .route("/append", post(append))
+        .route("/append/batch", post(append_batch))
+        .route("/ingest/webhook/:source", post(ingest_webhook))
+        .route(
+            "/attestations",
+            get(find_attestations).post(append_attestation),
+        )
+        .route("/sigstore", post(append_sigstore_bundle))
+        .route("/links", get(link_list).post(append_link))
+        .route("/timestamp", post(timestamp))
         .route("/root", get(root))
+        .route("/root/:size", get(root_at))
+        .route("/checkpoint", get(checkpoint))
+        .route("/freshness", post(freshness))
+        .route("/consistency", get(consistency))
+        .route("/witness", get(witness_list).post(witness_submit))
         .route("/prove/:index", get(prove))
+        .route("/prove/:index/bin", get(prove_bin))
+        .route("/scitt/receipt/:index", get(scitt_receipt))
+        .route("/bundle/:index", get(bundle))
+        .route("/tile/:index", get(leaf_tile))
+        .route("/tile/:index/entries", get(entry_tile))
+        .route("/entries", get(labelled_entries))
+        .route("/entry/:index", get(entry))
+        .route("/payload/:index", get(payload))
+        .route("/admin/redact/:index", post(redact))
+        .route("/admin/promote", post(promote))
+        .route("/handoff", get(handoff))
+        .route("/replication/entries", get(replication_entries))
+        .route("/redactions", get(redactions))
+        .route("/redactions/prove/:position", get(prove_redaction))
+        .route("/redactions/consistency", get(redaction_consistency))
+        .route("/days", get(days))
+        .route("/days/receipt/:index", get(day_receipt))
+        .route("/days/consistency", get(day_consistency))
         .route("/verify", post(verify))
+        .route("/verify-payload", post(verify_payload))
         .route("/anchors", get(anchors))
+        .route("/blobs", post(append_blob))
+        .route("/blobs/:sha256", get(blob))
         .with_state(state.clone());
+    // Axum's default 2 MB limit is kept unless large payloads have somewhere
+    // to go.
+    let app = if state.blobs.is_some() {
+        app.layer(DefaultBodyLimit::max(body_limit))
+    } else {
+        app
+    };
+    #[cfg(feature = "dashboard")]
+    let app = app.merge(dashboard::router());
+    #[cfg(feature = "graphql")]
+    let app = app.merge(graphql::router(state.clone()));
 
-    let listener = TcpListener::bind(addr).await?;
-    info!(%addr, "listening");
-    axum::serve(listener, app.into_make_service()).await?;
+    let listener = match systemd::listener()? {
+        Some(listener) => TcpListener::from_std(listener)?,
+        None => TcpListener::bind(addr).await?,
+    };
+    info!(addr = %listener.local_addr()?, "listening");
+    systemd::notify("READY=1");
+    systemd::spawn_watchdog();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown())
+    .await?;
 
     Ok(())
 }
 
-impl AppState {
-    async fn new(data_dir: PathBuf) -> anyhow::Result<Self> {
-        tokio::fs::create_dir_all(&data_dir)
-            .await
-            .context("create data dir")?;
+/// `reality-logd verify-storage`: replay the data directory against the
+/// roots recorded outside it (see `storage`) without serving it.
+async fn verify_storage() -> anyhow::Result<()> {
+    let data_dir =
+        PathBuf::from(env::var("REALITY_LOG_DIR").unwrap_or_else(|_| "data".to_string()));
+    let anchor_api = env::var("REALITY_ANCHOR_API").ok();
+    let keys = match env::var("REALITY_LOG_KMS") {
+        Ok(spec) => Some(
+            kms::payload_keys(&Kms::open(&spec)?, &data_dir, false)
+                .await
+                .context("load payload keys")?,
+        ),
+        Err(_) => None,
+    };
+    let roots =
+        storage::recorded_roots(&reqwest::Client::new(), anchor_api.as_deref(), &data_dir).await?;
+    let stored = reality_log::replay::Stored::read(&data_dir, keys).context("read the log")?;
+    let replay = tokio::task::spawn_blocking(move || stored.replay(&roots)).await?;
+    println!("{}", storage::report(&replay)?);
+    if anchor_api.is_none() {
+        println!("anchored roots were not checked; set REALITY_ANCHOR_API to check them");
+    }
+    Ok(())
+}
 
-        let leaves: Vec<String> = read_json(data_dir.join("leaves.json"))
-            .await?
-            .unwrap_or_default();
-        let entries: Vec<LogEntry> = read_json(data_dir.join("entries.json"))
-            .await?
-            .unwrap_or_default();
+/// Resolves on SIGTERM or Ctrl-C, so in-flight requests finish before exit.
+async fn shutdown() {
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(err) => {
+                warn!(?err, "failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate => {}
+    }
+    info!("shutting down");
+    systemd::notify("STOPPING=1");
+}
+
+impl AppState {
+    async fn new(
+        data_dir: PathBuf,
+        anchor_api: Option<String>,
+        signer: Option<Arc<dyn Signer>>,
+        witnesses: Vec<String>,
+        intake: Intake,
+        payload_keys: Option<PayloadKeys>,
+        tokens: Tokens,
+    ) -> anyhow::Result<Self> {
+        let Intake {
+            webhooks,
+            attestations,
+            links,
+            sigstore,
+            tsa,
+            blobs,
+        } = intake;
+        let log = match payload_keys {
+            Some(keys) => Log::open_sealed(&data_dir, keys),
+            None => Log::open(&data_dir),
+        }
+        .context("open the log")?;
+        let redactions = Redactions::open(&data_dir, &log)?;
 
-        ensure_file(data_dir.join("anchors.json")).await?;
+        let mut subjects = SubjectIndex::default();
+        for (index, entry) in log.iter().enumerate() {
+            if let Some(statement) = attestations.statement(&entry.payload) {
+                subjects.insert(index as u64, &statement);
+            }
+            links.note(index as u64, &entry.payload);
+        }
 
         Ok(Self {
-            inner: Arc::new(RwLock::new(StateSnapshot { leaves, entries })),
+            reader: log.reader(),
+            log: Arc::new(Mutex::new(log)),
+            redactions: Arc::new(redactions),
+            days: None,
+            watermark: None,
+            commits: Arc::new(GroupCommit::default()),
+            standby: None,
+            subjects: Arc::new(std::sync::RwLock::new(subjects)),
             data_dir,
+            anchor_api,
+            http: reqwest::Client::new(),
+            signer,
+            witnesses: Arc::new(witnesses),
+            submitters: Arc::default(),
+            provenance: Arc::default(),
+            witness_lock: Arc::new(Mutex::new(())),
+            webhooks: Arc::new(webhooks),
+            attestations: Arc::new(attestations),
+            links: Arc::new(links),
+            sigstore: Arc::new(sigstore),
+            tsa: tsa.map(Arc::new),
+            blobs: blobs.map(Arc::new),
+            read_token: tokens.read.map(Arc::from),
+            admin_token: tokens.admin.map(Arc::from),
         })
     }
 
-    async fn persist(&self, snapshot: &StateSnapshot) -> anyhow::Result<()> {
-        write_json(self.data_path("leaves.json"), &snapshot.leaves).await?;
-        write_json(self.data_path("entries.json"), &snapshot.entries).await?;
+    /// Query the anchor daemon's store through `REALITY_ANCHOR_API`.
+    async fn read_anchors(&self, query: &AnchorQuery) -> anyhow::Result<Vec<StoredAnchor>> {
+        read_anchors(&self.http, self.anchor_api.as_deref(), query).await
+    }
+
+    /// The anchor daemon's receipt for `txid`.
+    async fn anchor_receipt(&self, txid: &str) -> anyhow::Result<Option<AnchorReceipt>> {
+        let api = self.anchor_api.as_ref().context(NO_ANCHOR_API)?;
+        let url = format!("{}/receipt/{txid}", api.trim_end_matches('/'));
+        let resp = self.http.get(url).send().await?;
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Ok(Some(resp.error_for_status()?.json().await?))
+    }
+
+    /// Refuse an anchor read when no anchor daemon is configured.
+    fn anchors_configured(&self) -> Result<(), (StatusCode, String)> {
+        match self.anchor_api {
+            Some(_) => Ok(()),
+            None => Err((StatusCode::NOT_FOUND, NO_ANCHOR_API.into())),
+        }
+    }
+
+    fn subjects_mut(&self) -> std::sync::RwLockWriteGuard<'_, SubjectIndex> {
+        self.subjects.write().expect("subject index lock poisoned")
+    }
+
+    /// Refuse a request without the read token, when one is configured.
+    fn authorize_read(&self, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+        match &self.read_token {
+            Some(token) if !bearer_is(headers, token) => Err((
+                StatusCode::UNAUTHORIZED,
+                "missing or wrong read token".into(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Refuse a request without the admin token, and every request when
+    /// none is configured.
+    fn authorize_admin(&self, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+        let token = self.admin_token.as_ref().ok_or((
+            StatusCode::NOT_FOUND,
+            "admin routes are not configured".to_string(),
+        ))?;
+        if !bearer_is(headers, token) {
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                "missing or wrong admin token".into(),
+            ));
+        }
         Ok(())
     }
 
-    async fn read_anchors(&self) -> anyhow::Result<Vec<AnchorRecord>> {
-        Ok(read_json(self.data_path("anchors.json"))
-            .await?
-            .unwrap_or_default())
+    /// Refuse an entry signed by a key that is not registered, when keys
+    /// are. Whether the signature holds is the log's to check.
+    fn admit_submitter(
+        &self,
+        signature: Option<&DetachedSignature>,
+    ) -> Result<(), (StatusCode, String)> {
+        match signature {
+            Some(signature)
+                if !self.submitters.is_empty()
+                    && !self
+                        .submitters
+                        .contains(&signature.public_key.to_ascii_lowercase()) =>
+            {
+                Err((
+                    StatusCode::FORBIDDEN,
+                    format!("submitter key {} is not registered", signature.public_key),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Refuse to change a log that is still a standby.
+    fn writable(&self) -> Result<(), (StatusCode, String)> {
+        match &self.standby {
+            Some(standby) if !standby.promoted() => Err((
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!(
+                    "this log is a standby of {}; promote it to append",
+                    standby.primary()
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn days(&self) -> Result<&Mutex<Days>, (StatusCode, String)> {
+        self.days.as_deref().ok_or((
+            StatusCode::NOT_FOUND,
+            "day buckets are not enabled".to_string(),
+        ))
+    }
+
+    /// Keep `cosigned` as its witness's latest cosignature, unless the one
+    /// kept covers a larger tree.
+    async fn keep_cosignature(&self, cosigned: Cosignature) -> anyhow::Result<()> {
+        let witness = cosigned.witness.public_key.clone();
+        let _guard = self.witness_lock.lock().await;
+        let path = self.data_path("witnesses.json");
+        let mut all: Vec<Cosignature> = read_json(path.clone()).await?.unwrap_or_default();
+        match all
+            .iter_mut()
+            .find(|c| c.witness.public_key.eq_ignore_ascii_case(&witness))
+        {
+            Some(existing) if existing.checkpoint.size > cosigned.checkpoint.size => {}
+            Some(existing) => *existing = cosigned,
+            None => all.push(cosigned),
+        }
+        write_json(path, &all).await
+    }
+
+    /// Sign a checkpoint of `snapshot`. With rollback detection on, it is
+    /// recorded before it is handed out and submitted to the witness after.
+    async fn signed_checkpoint(
+        &self,
+        snapshot: Arc<Snapshot>,
+    ) -> Result<SignedCheckpoint, (StatusCode, String)> {
+        let signer = self.signer.clone().ok_or((
+            StatusCode::NOT_FOUND,
+            "checkpoint signing is not configured".to_string(),
+        ))?;
+        let signing = snapshot.clone();
+        let signed = off_worker(move || signing.checkpoint(signer.as_ref()))
+            .await
+            .map_err(|err| log_error(err, "size exceeds log size"))?;
+        let Some(watermark) = self.watermark.clone() else {
+            return Ok(signed);
+        };
+        match watermark.record(&signed).await {
+            Ok(false) => {}
+            Ok(true) => {
+                let (state, submitted) = (self.clone(), signed.clone());
+                tokio::spawn(async move {
+                    match watermark.submit(&submitted, snapshot).await {
+                        Ok(Some(cosigned)) => {
+                            if let Err(err) = state.keep_cosignature(cosigned).await {
+                                error!(?err, "failed to store cosignature");
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(err) => warn!(?err, "failed to submit checkpoint to the witness"),
+                    }
+                });
+            }
+            Err(err) => {
+                error!(?err, "failed to record checkpoint");
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "failed to record checkpoint".into(),
+                ));
+            }
+        }
+        Ok(signed)
     }
 
     fn data_path(&self, name: &str) -> PathBuf {
@@ -102,104 +792,918 @@ impl AppState {
     }
 }
 
+/// Run `sign` on the blocking pool. A signer may wait on an HSM or a KMS
+/// round trip, which must not hold up a runtime worker.
+async fn off_worker<T: Send + 'static>(sign: impl FnOnce() -> T + Send + 'static) -> T {
+    tokio::task::spawn_blocking(sign)
+        .await
+        .expect("signing panicked")
+}
+
 async fn health() -> &'static str {
     "ok"
 }
 
 async fn append(
+    Query(query): Query<DurabilityQuery>,
     State(state): State<AppState>,
-    Json(req): Json<AppendRequest>,
+    headers: HeaderMap,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    Json(mut req): Json<AppendRequest>,
 ) -> Result<Json<AppendResponse>, (StatusCode, String)> {
-    let leaf_bytes = leaf_hash(req.payload.as_bytes());
-    let leaf_hex = hex::encode(leaf_bytes);
-    let entry = LogEntry {
-        payload: req.payload.clone(),
-        leaf: leaf_hex.clone(),
-        appended_at: OffsetDateTime::now_utc()
-            .format(&time::format_description::well_known::Rfc3339)
-            .unwrap(),
-    };
+    state.writable()?;
+    let origin = state.provenance.origin(&headers, peer.map(|peer| peer.0))?;
+    // The submitter signed the payload it sent, not the blob reference.
+    let mut submitted = None;
+    if let Some(blobs) = &state.blobs {
+        if let Some(reference) = blobs.offload(&req.payload).await.map_err(blob_error)? {
+            submitted = Some(std::mem::replace(&mut req.payload, reference));
+        }
+    }
+    state.admit_submitter(req.signature.as_ref())?;
+    let statement = state.attestations.statement(&req.payload);
+    let mut log = state.log.lock().await;
+    let appended = log
+        .append_request_offloaded(req, submitted, &origin)
+        .map_err(|err| log_error(err, "leaf index out of range"))?;
+    if let Some(statement) = statement {
+        state.subjects_mut().insert(appended.index, &statement);
+    }
+    let entry = log
+        .get(appended.index)
+        .expect("appended entries are in the log");
+    state.links.note(appended.index, &entry.payload);
+    drop(log);
+    state.commits.durable(query.durability).await?;
+    Ok(Json(appended))
+}
 
-    let (response, snapshot) = {
-        let mut guard = state.inner.write().await;
-        guard.leaves.push(leaf_hex.clone());
-        guard.entries.push(entry);
-        let snapshot = guard.clone();
-        let index = snapshot.leaves.len() as u64 - 1;
-        let leaves = match decode_leaves(&snapshot.leaves) {
-            Ok(l) => l,
-            Err(e) => {
-                error!(?e, "failed to decode leaves");
-                return Err((
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "corrupt leaf storage".into(),
-                ));
-            }
+/// Append several entries under one lock and one write. Entries whose
+/// idempotency key is already in the log are reported as duplicates instead
+/// of appended, which makes a resent batch harmless. A key reused with a
+/// different payload rejects the whole batch.
+async fn append_batch(
+    Query(query): Query<DurabilityQuery>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    peer: Option<ConnectInfo<SocketAddr>>,
+    Json(req): Json<BatchAppendRequest>,
+) -> Result<Json<BatchAppendResponse>, (StatusCode, String)> {
+    let origin = state.provenance.origin(&headers, peer.map(|peer| peer.0))?;
+    append_entries(state, req, query.durability, origin).await
+}
+
+/// What `/append/batch` does, for the routes that append entries of their
+/// own, acknowledged as `durability` asks or the default and recording
+/// `origin`.
+async fn append_entries(
+    state: AppState,
+    mut req: BatchAppendRequest,
+    durability: Option<Durability>,
+    origin: Origin,
+) -> Result<Json<BatchAppendResponse>, (StatusCode, String)> {
+    state.writable()?;
+    if req.entries.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "batch is empty".into()));
+    }
+    if req.entries.len() > MAX_BATCH {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("batch holds more than {MAX_BATCH} entries"),
+        ));
+    }
+    for entry in &req.entries {
+        state.admit_submitter(entry.signature.as_ref())?;
+    }
+    let mut submitted = Vec::new();
+    if let Some(blobs) = &state.blobs {
+        for entry in &mut req.entries {
+            let reference = blobs.offload(&entry.payload).await.map_err(blob_error)?;
+            submitted.push(reference.map(|r| std::mem::replace(&mut entry.payload, r)));
+        }
+    }
+    let mut log = state.log.lock().await;
+    let appended = log
+        .append_batch_offloaded(req.entries, &submitted, &origin)
+        .map_err(|err| log_error(err, "leaf index out of range"))?;
+    for result in appended.entries.iter().filter(|result| !result.duplicate) {
+        let entry = log
+            .get(result.index)
+            .expect("appended entries are in the log");
+        if let Some(statement) = state.attestations.statement(&entry.payload) {
+            state.subjects_mut().insert(result.index, &statement);
+        }
+        state.links.note(result.index, &entry.payload);
+    }
+    drop(log);
+    state.commits.durable(durability).await?;
+    Ok(Json(appended))
+}
+
+/// Append a third-party webhook delivery once its signature checks out.
+/// Deliveries are keyed by the provider's delivery id, so a redelivery is
+/// reported as a duplicate of the first.
+async fn ingest_webhook(
+    Path(name): Path<String>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<BatchAppendResponse>, (StatusCode, String)> {
+    let source = state
+        .webhooks
+        .get(&name)
+        .ok_or((StatusCode::NOT_FOUND, "unknown webhook source".to_string()))?;
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    if let Err(rejection) = source.verify(&headers, &body, now) {
+        warn!(source = %name, %rejection, "rejected webhook");
+        return Err((StatusCode::UNAUTHORIZED, rejection.to_string()));
+    }
+    let entries = vec![source.entry(&headers, &body)];
+    append_entries(
+        state,
+        BatchAppendRequest { entries },
+        None,
+        Origin::default(),
+    )
+    .await
+}
+
+/// Append a DSSE envelope once it is well formed and, when
+/// `REALITY_LOG_DSSE_KEYS` is set, signed by one of those keys. A resubmitted
+/// envelope is reported as a duplicate.
+async fn append_attestation(
+    State(state): State<AppState>,
+    Json(envelope): Json<Envelope>,
+) -> Result<Json<BatchAppendResponse>, (StatusCode, String)> {
+    let entry = state.attestations.admit(&envelope).map_err(|rejection| {
+        let status = match rejection {
+            Rejection::Malformed(_) => StatusCode::BAD_REQUEST,
+            Rejection::Untrusted => StatusCode::FORBIDDEN,
+        };
+        (status, rejection.to_string())
+    })?;
+    let entries = vec![entry];
+    append_entries(
+        state,
+        BatchAppendRequest { entries },
+        None,
+        Origin::default(),
+    )
+    .await
+}
+
+/// Append a Sigstore bundle along with what logd could verify of it. When
+/// `REALITY_LOG_SIGSTORE_ROOTS` is set, a bundle that does not verify
+/// against those roots is refused. A resubmitted bundle is reported as a
+/// duplicate.
+async fn append_sigstore_bundle(
+    State(state): State<AppState>,
+    Json(bundle): Json<serde_json::Value>,
+) -> Result<Json<BatchAppendResponse>, (StatusCode, String)> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let entry = state.sigstore.admit(&bundle, now).map_err(|rejection| {
+        let status = match rejection {
+            sigstore::Rejection::Malformed(_) => StatusCode::BAD_REQUEST,
+            sigstore::Rejection::Unverified(_) => StatusCode::FORBIDDEN,
+        };
+        (status, rejection.to_string())
+    })?;
+    let entries = vec![entry];
+    append_entries(
+        state,
+        BatchAppendRequest { entries },
+        None,
+        Origin::default(),
+    )
+    .await
+}
+
+/// Append another log's signed checkpoint once it verifies, is signed by
+/// a key in `REALITY_LOG_PEERS` when that is set, and agrees with the
+/// checkpoint linked from that log before. A resubmitted link is reported
+/// as a duplicate.
+async fn append_link(
+    State(state): State<AppState>,
+    Json(link): Json<LogLink>,
+) -> Result<Json<BatchAppendResponse>, (StatusCode, String)> {
+    let _admitting = state.links.admitting().await;
+    let entry = state.links.admit(&link).map_err(|rejection| {
+        let status = match rejection {
+            link::Rejection::Malformed(_) => StatusCode::BAD_REQUEST,
+            link::Rejection::Untrusted => StatusCode::FORBIDDEN,
+            link::Rejection::Stale { .. }
+            | link::Rejection::Fork { .. }
+            | link::Rejection::Unlinked { .. } => StatusCode::CONFLICT,
         };
-        let root_hex = hex::encode(merkle_root(&leaves));
+        warn!(public_key = %link.public_key(), %rejection, "rejected link");
+        (status, rejection.to_string())
+    })?;
+    let entries = vec![entry];
+    append_entries(
+        state.clone(),
+        BatchAppendRequest { entries },
+        None,
+        Origin::default(),
+    )
+    .await
+}
+
+/// The newest linked checkpoint of each linked log.
+async fn link_list(State(state): State<AppState>) -> Json<Vec<LinkedCheckpoint>> {
+    Json(state.links.latest())
+}
+
+/// RFC 3161 over HTTP: a DER TimeStampReq in, a DER TimeStampResp out. The
+/// hash is appended, and the token carries the entry's bundle. A request
+/// logd cannot honor gets a rejection response, as the RFC asks, not an
+/// HTTP error.
+async fn timestamp(
+    State(state): State<AppState>,
+    body: Bytes,
+) -> Result<([(header::HeaderName, &'static str); 1], Vec<u8>), (StatusCode, String)> {
+    let tsa = state.tsa.clone().ok_or((
+        StatusCode::NOT_FOUND,
+        "timestamping is not configured".to_string(),
+    ))?;
+    let reply = |der| ([(header::CONTENT_TYPE, "application/timestamp-reply")], der);
+    let request = match tsa.parse(&body) {
+        Ok(request) => request,
+        Err(failure) => {
+            warn!(%failure, "rejected timestamp request");
+            return Ok(reply(tsa::reject(failure)));
+        }
+    };
+    let entries = vec![request.entry()];
+    let Json(appended) = append_entries(
+        state.clone(),
+        BatchAppendRequest { entries },
+        None,
+        Origin::default(),
+    )
+    .await?;
+    let index = appended.entries[0].index;
+    let bundle = entry_bundle(&state, index).await?;
+    let response = tsa.grant(&request, &bundle).map_err(|err| {
+        error!(?err, "failed to issue timestamp");
         (
-            AppendResponse {
-                index,
-                size: snapshot.leaves.len() as u64,
-                leaf: leaf_hex,
-                root: root_hex,
-            },
-            snapshot,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "failed to issue timestamp".to_string(),
         )
+    })?;
+    Ok(reply(response))
+}
+
+/// In-toto attestations whose subjects include `digest`, oldest first.
+async fn find_attestations(
+    Query(query): Query<AttestationQuery>,
+    State(state): State<AppState>,
+) -> Json<Vec<AttestationHit>> {
+    let snapshot = state.reader.snapshot();
+    let indices = state
+        .subjects
+        .read()
+        .expect("subject index lock poisoned")
+        .get(&query.digest)
+        .to_vec();
+    let hits = indices
+        .into_iter()
+        .filter_map(|index| {
+            let entry = snapshot.get(index)?;
+            let statement = state.attestations.statement(&entry.payload)?;
+            Some(AttestationHit {
+                index,
+                leaf: hex::encode(entry.leaf),
+                predicate_type: statement.predicate_type,
+                subjects: statement.subject,
+            })
+        })
+        .collect();
+    Json(hits)
+}
+
+/// Entries carrying a label, or signed by a submitter, oldest first.
+async fn labelled_entries(
+    Query(query): Query<EntryQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<LabelHit>>, (StatusCode, String)> {
+    let snapshot = state.reader.snapshot();
+    let indices = match (query.label, query.signer) {
+        (Some(label), None) => {
+            let (key, value) = label.split_once(':').ok_or((
+                StatusCode::BAD_REQUEST,
+                "label must be <key>:<value>".to_string(),
+            ))?;
+            snapshot.labelled(key, value)
+        }
+        (None, Some(signer)) => snapshot.signed_by(&signer),
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "give either label=<key>:<value> or signer=<hex key>".into(),
+            ))
+        }
     };
+    let hits = indices
+        .into_iter()
+        .filter_map(|index| {
+            let entry = snapshot.get(index)?;
+            Some(LabelHit {
+                index,
+                leaf: hex::encode(entry.leaf),
+                meta: entry.meta.clone()?,
+                appended_at: entry.appended_at.clone(),
+            })
+        })
+        .collect();
+    Ok(Json(hits))
+}
+
+async fn root(State(state): State<AppState>) -> Json<RootResponse> {
+    Json(state.reader.snapshot().root())
+}
 
-    if let Err(err) = state.persist(&snapshot).await {
-        error!(?err, "persist failure");
-        return Err((StatusCode::INTERNAL_SERVER_ERROR, "persist failure".into()));
+/// Current tree head signed with the configured checkpoint key.
+async fn checkpoint(
+    State(state): State<AppState>,
+) -> Result<Json<SignedCheckpoint>, (StatusCode, String)> {
+    state
+        .signed_checkpoint(state.reader.snapshot())
+        .await
+        .map(Json)
+}
+
+/// The current checkpoint bound to the client's nonce by a second signature,
+/// so the client knows the head was current when it asked.
+async fn freshness(
+    State(state): State<AppState>,
+    Json(req): Json<FreshnessRequest>,
+) -> Result<Json<FreshCheckpoint>, (StatusCode, String)> {
+    let signer = state.signer.clone().ok_or((
+        StatusCode::NOT_FOUND,
+        "checkpoint signing is not configured".to_string(),
+    ))?;
+    if !valid_nonce(&req.nonce) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "nonce must be hex of 16 to 64 bytes".into(),
+        ));
     }
+    let checkpoint = state.signed_checkpoint(state.reader.snapshot()).await?;
+    off_worker(move || FreshCheckpoint::sign(req.nonce, checkpoint, signer.as_ref()))
+        .await
+        .map(Json)
+        .map_err(|err| log_error(LogError::Signing(err.to_string()), "size exceeds log size"))
+}
 
-    Ok(Json(response))
+/// Root of the tree formed by the first `size` leaves.
+async fn root_at(
+    Path(size): Path<u64>,
+    State(state): State<AppState>,
+) -> Result<Json<RootResponse>, (StatusCode, String)> {
+    let head = state
+        .reader
+        .snapshot()
+        .root_at(size)
+        .map_err(|err| log_error(err, "size exceeds log size"))?;
+    Ok(Json(head))
 }
 
-async fn root(State(state): State<AppState>) -> Result<Json<RootResponse>, (StatusCode, String)> {
-    let snapshot = state.inner.read().await.clone();
-    let leaves = match decode_leaves(&snapshot.leaves) {
-        Ok(l) => l,
-        Err(e) => {
-            error!(?e, "failed to decode leaves");
-            return Err((
+/// Proof that the tree at `old` leaves is a prefix of the tree at `new`
+/// (the current size when omitted).
+async fn consistency(
+    Query(query): Query<ConsistencyQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ConsistencyProof>, (StatusCode, String)> {
+    consistency_in(&state.reader.snapshot(), query).map(Json)
+}
+
+fn consistency_in(
+    snapshot: &Snapshot,
+    query: ConsistencyQuery,
+) -> Result<ConsistencyProof, (StatusCode, String)> {
+    let new = query.new.unwrap_or(snapshot.len());
+    snapshot
+        .consistency(query.old, new)
+        .map_err(|err| log_error(err, "sizes out of range"))
+}
+
+/// Latest cosignature from each witness.
+async fn witness_list(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<Cosignature>>, (StatusCode, String)> {
+    read_json(state.data_path("witnesses.json"))
+        .await
+        .map(|c| Json(c.unwrap_or_default()))
+        .map_err(|err| {
+            error!(?err, "failed to read witnesses");
+            (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                "corrupt leaf storage".into(),
-            ));
-        }
-    };
-    let root_hex = hex::encode(merkle_root(&leaves));
-    Ok(Json(RootResponse {
-        root: root_hex,
-        size: snapshot.leaves.len() as u64,
-    }))
+                "failed to read witnesses".into(),
+            )
+        })
 }
 
+/// Accept a witness cosignature over a checkpoint of this log. It replaces
+/// the witness's previous cosignature unless that one covers a larger tree.
+async fn witness_submit(
+    State(state): State<AppState>,
+    Json(cosigned): Json<Cosignature>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if !cosigned.verify() {
+        return Err((StatusCode::BAD_REQUEST, "invalid cosignature".into()));
+    }
+    let witness = cosigned.witness.public_key.to_ascii_lowercase();
+    if !state.witnesses.is_empty() && !state.witnesses.contains(&witness) {
+        return Err((StatusCode::FORBIDDEN, "unknown witness key".into()));
+    }
+    let size = cosigned.checkpoint.size;
+    let Json(head) = root_at(Path(size), State(state.clone())).await?;
+    if !head.root.eq_ignore_ascii_case(&cosigned.checkpoint.root) {
+        return Err((
+            StatusCode::CONFLICT,
+            "checkpoint root is not in this log's history".into(),
+        ));
+    }
+
+    if let Err(err) = state.keep_cosignature(cosigned).await {
+        error!(?err, "failed to store cosignature");
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "failed to store cosignature".into(),
+        ));
+    }
+    info!(%witness, size, "accepted witness cosignature");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Inclusion proof for entry `index` against the current tree, or against
+/// the tree of `?size=N` leaves so it matches a root the client already has.
+/// With `?at_anchor=<txid>` it is an [`AnchoredProof`] instead.
 async fn prove(
-    Path(index): Path<usize>,
+    Path(index): Path<u64>,
+    Query(query): Query<ProveQuery>,
+    Query(pin): Query<AnchorPin>,
     State(state): State<AppState>,
-) -> Result<Json<InclusionProof>, (StatusCode, String)> {
-    let snapshot = state.inner.read().await.clone();
-    let leaves = match decode_leaves(&snapshot.leaves) {
-        Ok(l) => l,
-        Err(e) => {
-            error!(?e, "failed to decode leaves");
-            return Err((
+) -> Result<Response, (StatusCode, String)> {
+    match pin.at_anchor {
+        Some(_) if query.size.is_some() => Err((
+            StatusCode::BAD_REQUEST,
+            "give size or at_anchor, not both".into(),
+        )),
+        Some(txid) => Ok(Json(prove_at_anchor(&state, index, &txid).await?).into_response()),
+        None => Ok(Json(prove_in(&state.reader.snapshot(), index, query)?).into_response()),
+    }
+}
+
+/// Proof of entry `index` against the root anchored in `txid`, and of the
+/// current tree growing from it. The anchor must be confirmed and its root
+/// must be this log's root at the anchored size.
+async fn prove_at_anchor(
+    state: &AppState,
+    index: u64,
+    txid: &str,
+) -> Result<AnchoredProof, (StatusCode, String)> {
+    state.anchors_configured()?;
+    let anchor = state
+        .anchor_receipt(txid)
+        .await
+        .map_err(|err| {
+            error!(?err, "failed to read anchors");
+            (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                "corrupt leaf storage".into(),
-            ));
-        }
+                "failed to read anchors".to_string(),
+            )
+        })?
+        .ok_or((StatusCode::NOT_FOUND, "unknown anchor".to_string()))?;
+    let record = &anchor.anchor.record;
+    if anchor.anchor.status != AnchorStatus::Confirmed {
+        return Err((
+            StatusCode::CONFLICT,
+            format!("anchor is {}", anchor.anchor.status.as_str()),
+        ));
+    }
+    let snapshot = state.reader.snapshot();
+    let head = snapshot
+        .root_at(record.size)
+        .map_err(|err| log_error(err, "anchored size exceeds log size"))?;
+    if !head.root.eq_ignore_ascii_case(&record.root) {
+        return Err((
+            StatusCode::CONFLICT,
+            "anchored root is not in this log's history".into(),
+        ));
+    }
+    if index >= record.size {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "entry was appended after the anchor".into(),
+        ));
+    }
+    let missing = |err| log_error(err, "leaf index out of range");
+    Ok(AnchoredProof {
+        proof: snapshot.proof_at(index, record.size).map_err(missing)?,
+        consistency: snapshot
+            .consistency(record.size, snapshot.len())
+            .map_err(missing)?,
+        anchor,
+    })
+}
+
+fn prove_in(
+    snapshot: &Snapshot,
+    index: u64,
+    query: ProveQuery,
+) -> Result<InclusionProof, (StatusCode, String)> {
+    let size = query.size.unwrap_or(snapshot.len());
+    let missing = if size > snapshot.len() {
+        "size exceeds log size"
+    } else {
+        "leaf index out of range"
     };
+    snapshot
+        .proof_at(index, size)
+        .map_err(|err| log_error(err, missing))
+}
 
-    let proof = make_proof(&leaves, index).map_err(|err| match err {
-        MerkleError::IndexOutOfRange => (StatusCode::NOT_FOUND, "leaf index out of range".into()),
-        _ => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "unable to build proof".into(),
-        ),
-    })?;
+/// Same proof as `/prove/:index` in the compact binary encoding.
+async fn prove_bin(
+    path: Path<u64>,
+    query: Query<ProveQuery>,
+    state: State<AppState>,
+) -> Result<([(header::HeaderName, &'static str); 1], Vec<u8>), (StatusCode, String)> {
+    let proof = prove_in(&state.reader.snapshot(), path.0, query.0)?;
+    let bytes = proof
+        .to_bytes()
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+    Ok(([(header::CONTENT_TYPE, "application/octet-stream")], bytes))
+}
 
-    Ok(Json(proof))
+/// A SCITT receipt for entry `index` against the current tree, signed with
+/// the checkpoint key.
+async fn scitt_receipt(
+    Path(index): Path<u64>,
+    State(state): State<AppState>,
+) -> Result<([(header::HeaderName, &'static str); 1], Vec<u8>), (StatusCode, String)> {
+    let signer = state.signer.clone().ok_or((
+        StatusCode::NOT_FOUND,
+        "checkpoint signing is not configured".to_string(),
+    ))?;
+    let proof = prove_in(&state.reader.snapshot(), index, ProveQuery::default())?;
+    let issued_at = OffsetDateTime::now_utc().unix_timestamp();
+    let receipt = off_worker(move || scitt::issue(&proof, signer.as_ref(), issued_at))
+        .await
+        .map_err(|err| {
+            error!(?err, "failed to issue receipt");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to issue receipt".to_string(),
+            )
+        })?;
+    Ok(([(header::CONTENT_TYPE, scitt::MEDIA_TYPE)], receipt))
+}
+
+/// Leaf hashes `index * TILE_WIDTH ..` for clients that build their own
+/// proofs. Full tiles never change, so they are marked immutable.
+async fn leaf_tile(
+    Path(index): Path<u64>,
+    State(state): State<AppState>,
+) -> Result<([(header::HeaderName, &'static str); 1], Json<LeafTile>), (StatusCode, String)> {
+    let snapshot = state.reader.snapshot();
+    let (start, end) = tile_range(snapshot.len(), index)?;
+    let tile = LeafTile {
+        index,
+        leaves: (start..end)
+            .map(|index| hex::encode(snapshot.leaf(index).expect("tiles are within the log")))
+            .collect(),
+    };
+    let cache = if tile.is_full() {
+        "public, max-age=31536000, immutable"
+    } else {
+        "no-cache"
+    };
+    Ok(([(header::CACHE_CONTROL, cache)], Json(tile)))
+}
+
+/// Payloads of the entries in leaf tile `index`, for mirrors and exports.
+/// Cached like `/tile/:index`.
+async fn entry_tile(
+    Path(index): Path<u64>,
+    State(state): State<AppState>,
+) -> Result<([(header::HeaderName, &'static str); 1], Json<EntryTile>), (StatusCode, String)> {
+    let snapshot = state.reader.snapshot();
+    let (start, end) = tile_range(snapshot.len(), index)?;
+    let cache = if end - start == TILE_WIDTH as u64 {
+        "public, max-age=31536000, immutable"
+    } else {
+        "no-cache"
+    };
+    let entries: Vec<_> = (start..end)
+        .map(|index| snapshot.get(index).expect("tiles are within the log"))
+        .collect();
+    let meta = if entries.iter().any(|entry| entry.meta.is_some()) {
+        entries.iter().map(|entry| entry.meta.clone()).collect()
+    } else {
+        Vec::new()
+    };
+    Ok((
+        [(header::CACHE_CONTROL, cache)],
+        Json(EntryTile {
+            index,
+            payloads: entries.iter().map(|entry| entry.payload.clone()).collect(),
+            meta,
+        }),
+    ))
+}
+
+/// Entry `index` as stored, with its payload decrypted if the log encrypts
+/// payloads. A redacted entry comes with its tombstone. Needs the read token
+/// when one is set.
+async fn entry(
+    Path(index): Path<u64>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Entry>, (StatusCode, String)> {
+    state.authorize_read(&headers)?;
+    let snapshot = state.reader.snapshot();
+    let payload = match snapshot.payload(index) {
+        Ok(payload) => Some(payload),
+        Err(LogError::Redacted(_)) => None,
+        Err(err) => return Err(log_error(err, "leaf index out of range")),
+    };
+    let entry = snapshot.get(index).expect("read entries exist").clone();
+    Ok(Json(Entry {
+        payload: payload.unwrap_or_default(),
+        ..entry
+    }))
+}
+
+/// Delete entry `index`'s payload and return the signed tombstone kept in
+/// its place. Its leaf stays, so it can still be proven. Needs the admin
+/// token and a checkpoint signer, which signs the tombstone.
+async fn redact(
+    Path(index): Path<u64>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<RedactRequest>,
+) -> Result<Json<SignedTombstone>, (StatusCode, String)> {
+    state.authorize_admin(&headers)?;
+    state.writable()?;
+    let signer = state.signer.clone().ok_or((
+        StatusCode::NOT_FOUND,
+        "checkpoint signing is not configured".to_string(),
+    ))?;
+    if req.reason.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "a redaction needs a reason".into()));
+    }
+    let mut log = state.log.clone().lock_owned().await;
+    let tombstone = off_worker(move || log.redact(index, req, signer.as_ref()))
+        .await
+        .map_err(|err| log_error(err, "leaf index out of range"))?;
+    state.subjects_mut().remove(index);
+    // Startup records it if this fails, so the redaction itself stands.
+    state
+        .redactions
+        .record(&tombstone)
+        .await
+        .map_err(|err| log_error(err, "leaf index out of range"))?;
+    let recorded = &tombstone.tombstone;
+    info!(
+        index,
+        reason = %recorded.reason,
+        requested_by = recorded.requested_by.as_deref(),
+        authority = recorded.authority.as_deref(),
+        "redacted entry"
+    );
+    Ok(Json(tombstone))
+}
+
+/// Entries `from..to` as stored, sealed payloads left sealed, for a standby
+/// to copy. At most [`MAX_BATCH`] at a time. Needs the read token when one
+/// is set.
+async fn replication_entries(
+    Query(query): Query<ReplicationQuery>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<Entry>>, (StatusCode, String)> {
+    state.authorize_read(&headers)?;
+    let snapshot = state.reader.snapshot();
+    if query.from > query.to || query.to > snapshot.len() {
+        return Err((StatusCode::NOT_FOUND, "range exceeds log size".into()));
+    }
+    if query.to - query.from > MAX_BATCH as u64 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("at most {MAX_BATCH} entries at a time"),
+        ));
+    }
+    let entries = (query.from..query.to)
+        .map(|index| {
+            snapshot
+                .get(index)
+                .expect("entries up to the size exist")
+                .clone()
+        })
+        .collect();
+    Ok(Json(entries))
+}
+
+/// Promote this standby to primary and return the signed handoff from the
+/// old primary's checkpoint to its own (see `standby`). Needs the admin
+/// token.
+async fn promote(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Handoff>, (StatusCode, String)> {
+    state.authorize_admin(&headers)?;
+    let standby = state.standby.clone().ok_or((
+        StatusCode::NOT_FOUND,
+        "this log is not a standby".to_string(),
+    ))?;
+    let handoff = standby
+        .promote(&state)
+        .await
+        .map_err(|err| (StatusCode::CONFLICT, format!("{err:#}")))?;
+    Ok(Json(handoff))
+}
+
+/// The handoff this log signed when it was promoted.
+async fn handoff(State(state): State<AppState>) -> Result<Json<Handoff>, (StatusCode, String)> {
+    read_json(state.data_path(standby::HANDOFF))
+        .await
+        .map_err(|err| {
+            error!(?err, "failed to read the handoff");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "failed to read the handoff".to_string(),
+            )
+        })?
+        .map(Json)
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            "this log was not promoted from a standby".to_string(),
+        ))
+}
+
+/// The redaction log's size and root with its tombstones, or only the one
+/// for `?entry=<index>`.
+async fn redactions(
+    Query(query): Query<RedactionQuery>,
+    State(state): State<AppState>,
+) -> Json<RedactionList> {
+    Json(state.redactions.list(query.entry))
+}
+
+/// Inclusion proof for the tombstone at `position` in the redaction log.
+async fn prove_redaction(
+    Path(position): Path<u64>,
+    Query(query): Query<ProveQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<InclusionProof>, (StatusCode, String)> {
+    prove_in(&state.redactions.snapshot(), position, query).map(Json)
+}
+
+/// Proof that the redaction log only grew between two sizes.
+async fn redaction_consistency(
+    Query(query): Query<ConsistencyQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ConsistencyProof>, (StatusCode, String)> {
+    consistency_in(&state.redactions.snapshot(), query).map(Json)
+}
+
+/// The day log's size and root with every seal.
+async fn days(State(state): State<AppState>) -> Result<Json<DayList>, (StatusCode, String)> {
+    Ok(Json(state.days()?.lock().await.list()))
+}
+
+/// Receipt for entry `index` through its day's seal. 404 until the day is
+/// sealed.
+async fn day_receipt(
+    Path(index): Path<u64>,
+    State(state): State<AppState>,
+) -> Result<Json<DayReceipt>, (StatusCode, String)> {
+    let snapshot = state.reader.snapshot();
+    state
+        .days()?
+        .lock()
+        .await
+        .receipt(&snapshot, index)
+        .map_err(|err| log_error(err, "leaf index out of range"))?
+        .map(Json)
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            "the entry's day is not sealed yet".into(),
+        ))
+}
+
+/// Proof that the day log only grew between two sizes.
+async fn day_consistency(
+    Query(query): Query<ConsistencyQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<ConsistencyProof>, (StatusCode, String)> {
+    let master = state.days()?.lock().await.master();
+    consistency_in(&master, query).map(Json)
+}
+
+/// The plaintext payload of entry `index`. Needs the read token when one is
+/// set.
+async fn payload(
+    Path(index): Path<u64>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<([(header::HeaderName, &'static str); 1], String), (StatusCode, String)> {
+    state.authorize_read(&headers)?;
+    let payload = state
+        .reader
+        .snapshot()
+        .payload(index)
+        .map_err(|err| log_error(err, "leaf index out of range"))?;
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        payload,
+    ))
+}
+
+/// Receipt for one entry: plaintext payload, inclusion proof, and a freshly
+/// signed checkpoint for the current tree. Needs the read token when one is
+/// set, as the payload comes back.
+async fn bundle(
+    Path(index): Path<u64>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<EntryBundle>, (StatusCode, String)> {
+    state.authorize_read(&headers)?;
+    Ok(Json(entry_bundle(&state, index).await?))
+}
+
+async fn entry_bundle(state: &AppState, index: u64) -> Result<EntryBundle, (StatusCode, String)> {
+    if state.signer.is_none() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "checkpoint signing is not configured".into(),
+        ));
+    }
+    let snapshot = state.reader.snapshot();
+    let missing = "leaf index out of range";
+    if index >= snapshot.len() {
+        return Err((StatusCode::NOT_FOUND, missing.into()));
+    }
+    let checkpoint = state.signed_checkpoint(snapshot.clone()).await?;
+    snapshot
+        .bundle(index, checkpoint)
+        .map_err(|err| log_error(err, missing))
+}
+
+/// Store the request body as a blob, whatever its size, and append its
+/// reference. A blob already in the log is reported as a duplicate.
+async fn append_blob(
+    Query(query): Query<DurabilityQuery>,
+    State(state): State<AppState>,
+    body: Bytes,
+) -> Result<Json<BatchAppendResponse>, (StatusCode, String)> {
+    let blobs = state.blobs.clone().ok_or((
+        StatusCode::NOT_FOUND,
+        "blob storage is not configured".to_string(),
+    ))?;
+    state.writable()?;
+    let reference = blobs.put(&body).await.map_err(blob_error)?;
+    let entries = vec![BatchEntry {
+        idempotency_key: Some(format!("blob:{}", reference.blob)),
+        payload: reference.to_payload(),
+        labels: Default::default(),
+        signature: None,
+    }];
+    append_entries(
+        state,
+        BatchAppendRequest { entries },
+        query.durability,
+        Origin::default(),
+    )
+    .await
+}
+
+/// The blob with hex SHA-256 `sha256`. Its content never changes, so it is
+/// marked immutable.
+async fn blob(
+    Path(sha256): Path<String>,
+    State(state): State<AppState>,
+) -> Result<([(header::HeaderName, &'static str); 2], Vec<u8>), (StatusCode, String)> {
+    let missing = || (StatusCode::NOT_FOUND, "no such blob".to_string());
+    let blobs = state.blobs.clone().ok_or_else(missing)?;
+    let key = sha256.to_ascii_lowercase();
+    if key.len() != 64 || !key.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err((StatusCode::BAD_REQUEST, "expected a hex SHA-256".into()));
+    }
+    let content = blobs
+        .get(&key)
+        .await
+        .map_err(blob_error)?
+        .ok_or_else(missing)?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/octet-stream"),
+            (header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
+        ],
+        content,
+    ))
 }
 
 async fn verify(
@@ -208,10 +1712,67 @@ async fn verify(
     Ok(Json(reality_core::verify(&req)))
 }
 
+/// Find the earliest entry holding a payload, or a payload with a given
+/// SHA-256, and prove it against a freshly signed checkpoint, checking the
+/// bundle here before returning it. A lookup by hash, or any lookup in a log
+/// that encrypts payloads, needs the read token when one is set.
+async fn verify_payload(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(lookup): Json<PayloadLookup>,
+) -> Result<Json<PayloadEvidence>, (StatusCode, String)> {
+    let snapshot = state.reader.snapshot();
+    if lookup.needs_read_token(snapshot.seals()) {
+        state.authorize_read(&headers)?;
+    }
+    let digest = match (&lookup.payload, &lookup.sha256) {
+        (Some(payload), None) => payload_digest(payload),
+        (None, Some(sha256)) => hex::decode(sha256)
+            .ok()
+            .and_then(|digest| digest.try_into().ok())
+            .ok_or((
+                StatusCode::BAD_REQUEST,
+                "sha256 must be 64 hex digits".to_string(),
+            ))?,
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "give either payload or sha256".into(),
+            ))
+        }
+    };
+    let found = snapshot.holding(&digest);
+    let (&index, later) = found.split_first().ok_or((
+        StatusCode::NOT_FOUND,
+        "no entry holds this payload".to_string(),
+    ))?;
+    let checkpoint = state.signed_checkpoint(snapshot.clone()).await?;
+    let bundle = snapshot
+        .bundle(index, checkpoint)
+        .map_err(|err| log_error(err, "leaf index out of range"))?;
+    let entry = snapshot.get(index).expect("proven entries exist");
+    if let Err(err) = bundle.verify(None) {
+        error!(?err, index, "a freshly built bundle does not verify");
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "failed to verify the entry's proof".into(),
+        ));
+    }
+    Ok(Json(PayloadEvidence {
+        sha256: hex::encode(digest),
+        index,
+        appended_at: entry.appended_at.clone(),
+        later: later.to_vec(),
+        bundle,
+    }))
+}
+
 async fn anchors(
     State(state): State<AppState>,
-) -> Result<Json<Vec<AnchorRecord>>, (StatusCode, String)> {
-    match state.read_anchors().await {
+    Query(query): Query<AnchorQuery>,
+) -> Result<Json<Vec<StoredAnchor>>, (StatusCode, String)> {
+    state.anchors_configured()?;
+    match state.read_anchors(&query).await {
         Ok(records) => Ok(Json(records)),
         Err(err) => {
             error!(?err, "failed to read anchors");
@@ -223,18 +1784,75 @@ async fn anchors(
     }
 }
 
-fn decode_leaves(hashes: &[String]) -> Result<Vec<[u8; 32]>, hex::FromHexError> {
-    hashes.iter().map(|h| decode_hash(h)).collect()
+/// [`AppState::read_anchors`] before the state exists, for `verify-storage`.
+/// Anchors live in the anchor daemon's store, so this fails without its API.
+async fn read_anchors(
+    http: &reqwest::Client,
+    anchor_api: Option<&str>,
+    query: &AnchorQuery,
+) -> anyhow::Result<Vec<StoredAnchor>> {
+    let api = anchor_api.context(NO_ANCHOR_API)?;
+    let url = format!("{}/anchors", api.trim_end_matches('/'));
+    let resp = http
+        .get(url)
+        .query(query)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(resp.json().await?)
 }
 
-fn decode_hash(hex_str: &str) -> Result<[u8; 32], hex::FromHexError> {
-    let bytes = hex::decode(hex_str)?;
-    if bytes.len() != 32 {
-        return Err(hex::FromHexError::InvalidStringLength);
-    }
-    let mut array = [0u8; 32];
-    array.copy_from_slice(&bytes);
-    Ok(array)
+/// A comma-separated list of hex keys from `var`, lowercased.
+fn hex_keys(var: &str) -> Vec<String> {
+    env::var(var)
+        .map(|keys| {
+            keys.split(',')
+                .map(|k| k.trim().to_ascii_lowercase())
+                .filter(|k| !k.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Entries `start..end` of tile `index` in a log of `size` entries.
+fn tile_range(size: u64, index: u64) -> Result<(u64, u64), (StatusCode, String)> {
+    let start = index
+        .checked_mul(TILE_WIDTH as u64)
+        .filter(|start| *start < size)
+        .ok_or((StatusCode::NOT_FOUND, "tile out of range".to_string()))?;
+    Ok((start, size.min(start + TILE_WIDTH as u64)))
+}
+
+fn blob_error(err: anyhow::Error) -> (StatusCode, String) {
+    error!(?err, "blob store failure");
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "blob store failure".to_string(),
+    )
+}
+
+/// The response for a failed log operation, with `missing` as the message
+/// when the request is out of range.
+fn log_error(err: LogError, missing: &str) -> (StatusCode, String) {
+    let message = match err {
+        LogError::OutOfRange { .. } => return (StatusCode::NOT_FOUND, missing.to_string()),
+        LogError::KeyConflict(_) => return (StatusCode::CONFLICT, err.to_string()),
+        LogError::Redacted(_) => return (StatusCode::GONE, err.to_string()),
+        LogError::Label(_)
+        | LogError::LabelsNeedV2
+        | LogError::SignedNeedsV2
+        | LogError::BadSignature => return (StatusCode::BAD_REQUEST, err.to_string()),
+        LogError::BadReplica { .. } | LogError::ReplicaDiverges(_) => {
+            return (StatusCode::CONFLICT, err.to_string())
+        }
+        LogError::CorruptLeaf(_) => "corrupt leaf storage",
+        LogError::Io { .. } | LogError::Json { .. } => "persist failure",
+        LogError::Signing(_) => "failed to sign checkpoint",
+        LogError::Sealed(_) => "failed to decrypt payload",
+        LogError::ProvenanceNeedsV2 => "provenance is configured without v2 leaves",
+    };
+    error!(?err, "{message}");
+    (StatusCode::INTERNAL_SERVER_ERROR, message.to_string())
 }
 
 async fn read_json<T>(path: PathBuf) -> anyhow::Result<Option<T>>
@@ -263,9 +1881,13 @@ where
     Ok(())
 }
 
-async fn ensure_file(path: PathBuf) -> anyhow::Result<()> {
-    if tokio::fs::metadata(&path).await.is_err() {
-        tokio::fs::write(path, b"[]").await?;
-    }
-    Ok(())
+/// Whether the request's bearer token is `token`. Digests are compared, so
+/// the time taken says nothing about the token.
+fn bearer_is(headers: &HeaderMap, token: &str) -> bool {
+    let given = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    Sha256::digest(given) == Sha256::digest(token.as_bytes())
 }
@@ -0,0 +1,185 @@
+//! Entry provenance. `/append` and `/append/batch` can record where each
+//! entry came from: the name its client gives in `X-Reality-Submitter`, a
+//! salted hash of the client's IP address, and a fingerprint of the bearer
+//! token it sent. `REALITY_LOG_PROVENANCE` lists the fields committed into
+//! each v2 leaf, `REALITY_LOG_PROVENANCE_STORED` those only kept with the
+//! entry; both take `submitter`, `source_ip` and `api_key`. Hashing an
+//! address needs `REALITY_LOG_PROVENANCE_SALT`, since an unsalted hash of an
+//! IPv4 address is undone by trying them all.
+
+use std::{net::SocketAddr, str::FromStr};
+
+use anyhow::Context;
+use axum::http::{header, HeaderMap, StatusCode};
+use hmac::{Hmac, Mac};
+use reality_core::leaf::Provenance;
+use reality_log::Origin;
+use sha2::{Digest, Sha256};
+
+/// Header naming the submitting client.
+pub const SUBMITTER_HEADER: &str = "x-reality-submitter";
+/// Longest submitter name, in bytes.
+pub const MAX_SUBMITTER: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Submitter,
+    SourceIp,
+    ApiKey,
+}
+
+impl FromStr for Field {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "submitter" => Ok(Self::Submitter),
+            "source_ip" => Ok(Self::SourceIp),
+            "api_key" => Ok(Self::ApiKey),
+            _ => anyhow::bail!("provenance fields are submitter, source_ip and api_key, not {s:?}"),
+        }
+    }
+}
+
+/// Which fields are committed and which stored, as configured at startup.
+#[derive(Debug, Default)]
+pub struct Recorder {
+    committed: Vec<Field>,
+    stored: Vec<Field>,
+    salt: Option<Vec<u8>>,
+}
+
+impl Recorder {
+    /// From comma-separated field lists. A field may be committed or
+    /// stored, not both.
+    pub fn new(committed: &str, stored: &str, salt: Option<String>) -> anyhow::Result<Self> {
+        let fields = |list: &str| -> anyhow::Result<Vec<Field>> {
+            list.split(',')
+                .map(str::trim)
+                .filter(|field| !field.is_empty())
+                .map(str::parse)
+                .collect()
+        };
+        let committed = fields(committed).context("REALITY_LOG_PROVENANCE")?;
+        let stored = fields(stored).context("REALITY_LOG_PROVENANCE_STORED")?;
+        if let Some(field) = committed.iter().find(|field| stored.contains(field)) {
+            anyhow::bail!("provenance field {field:?} is both committed and stored");
+        }
+        let hashes_ip = committed.contains(&Field::SourceIp) || stored.contains(&Field::SourceIp);
+        anyhow::ensure!(
+            !hashes_ip || salt.is_some(),
+            "recording source_ip needs REALITY_LOG_PROVENANCE_SALT"
+        );
+        Ok(Self {
+            committed,
+            stored,
+            salt: salt.map(String::into_bytes),
+        })
+    }
+
+    /// Whether leaves commit to any field, which needs v2 leaves.
+    pub fn commits(&self) -> bool {
+        !self.committed.is_empty()
+    }
+
+    pub fn records(&self) -> bool {
+        self.commits() || !self.stored.is_empty()
+    }
+
+    /// The provenance of a request from `peer` with `headers`.
+    pub fn origin(
+        &self,
+        headers: &HeaderMap,
+        peer: Option<SocketAddr>,
+    ) -> Result<Origin, (StatusCode, String)> {
+        if !self.records() {
+            return Ok(Origin::default());
+        }
+        let submitter = match headers.get(SUBMITTER_HEADER) {
+            Some(value) => {
+                let name = value
+                    .to_str()
+                    .ok()
+                    .filter(|name| !name.is_empty() && name.len() <= MAX_SUBMITTER);
+                Some(name.map(str::to_string).ok_or((
+                    StatusCode::BAD_REQUEST,
+                    format!("{SUBMITTER_HEADER} must be 1 to {MAX_SUBMITTER} visible ASCII bytes"),
+                ))?)
+            }
+            None => None,
+        };
+        let source_ip = peer.zip(self.salt.as_ref()).map(|(peer, salt)| {
+            let mut mac = Hmac::<Sha256>::new_from_slice(salt).expect("HMAC takes any key length");
+            mac.update(peer.ip().to_string().as_bytes());
+            hex::encode(mac.finalize().into_bytes())
+        });
+        let api_key = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|token| hex::encode(Sha256::digest(token)));
+        let pick = |fields: &[Field]| Provenance {
+            submitter: submitter
+                .clone()
+                .filter(|_| fields.contains(&Field::Submitter)),
+            source_ip: source_ip
+                .clone()
+                .filter(|_| fields.contains(&Field::SourceIp)),
+            api_key: api_key.clone().filter(|_| fields.contains(&Field::ApiKey)),
+        };
+        Ok(Origin {
+            committed: pick(&self.committed),
+            stored: pick(&self.stored),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn splits_provenance_between_leaf_and_entry() {
+        assert!(Recorder::new("submitter", "submitter", None).is_err());
+        assert!(Recorder::new("", "source_ip", None).is_err());
+        assert!(Recorder::new("address", "", None).is_err());
+        let recorder =
+            Recorder::new("submitter, api_key", "source_ip", Some("pepper".into())).unwrap();
+        assert!(recorder.commits());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(SUBMITTER_HEADER, HeaderValue::from_static("billing"));
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer t0k"),
+        );
+        let peer = Some(SocketAddr::from(([10, 0, 0, 7], 40000)));
+        let origin = recorder.origin(&headers, peer).unwrap();
+        assert_eq!(origin.committed.submitter.as_deref(), Some("billing"));
+        assert_eq!(
+            origin.committed.api_key,
+            Some(hex::encode(Sha256::digest("t0k")))
+        );
+        assert_eq!(origin.committed.source_ip, None);
+        assert_eq!(origin.stored.submitter, None);
+        let hashed = origin.stored.source_ip.unwrap();
+        assert_ne!(hashed, hex::encode(Sha256::digest("10.0.0.7")));
+        // The port is not part of the hash.
+        let other_port = Some(SocketAddr::from(([10, 0, 0, 7], 40001)));
+        let again = recorder.origin(&headers, other_port).unwrap();
+        assert_eq!(again.stored.source_ip, Some(hashed));
+
+        headers.insert(SUBMITTER_HEADER, HeaderValue::from_static(""));
+        assert_eq!(
+            recorder.origin(&headers, peer).unwrap_err().0,
+            StatusCode::BAD_REQUEST
+        );
+        // Nothing is read when nothing is recorded.
+        assert_eq!(
+            Recorder::default().origin(&headers, peer).unwrap(),
+            Origin::default()
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! The redaction log: every tombstone logd signs, appended to a log of its
+//! own under `redactions/` in the data directory. Auditors can then check
+//! that an erasure happened without taking the operator's word for it: the
+//! tombstone is signed with the log key and names a leaf they can prove in
+//! the main log, and the redaction log only grows, so a recorded erasure
+//! cannot quietly disappear.
+//!
+//! A tombstone is written into the main log first and recorded here second,
+//! keyed by its entry's index. Startup records any tombstone a crash left
+//! out, so the two always end up agreeing.
+
+use std::path::Path;
+
+use anyhow::Context;
+use reality_core::{
+    redaction::{RecordedRedaction, RedactionList, SignedTombstone},
+    BatchEntry,
+};
+use reality_log::{Log, LogError, LogReader, Snapshot};
+use tokio::sync::Mutex;
+
+/// Directory of the redaction log, under the data directory.
+pub const DIR: &str = "redactions";
+
+pub struct Redactions {
+    log: Mutex<Log>,
+    reader: LogReader,
+}
+
+impl Redactions {
+    /// Open the redaction log for `main`, recording any of its tombstones
+    /// that are missing.
+    pub fn open(data_dir: &Path, main: &Log) -> anyhow::Result<Self> {
+        let mut log = Log::open(data_dir.join(DIR)).context("open the redaction log")?;
+        let missing: Vec<_> = main
+            .iter()
+            .filter_map(|entry| entry.redaction.as_ref())
+            .map(record)
+            .collect();
+        if !missing.is_empty() {
+            log.append_batch(missing)
+                .context("record tombstones in the redaction log")?;
+        }
+        Ok(Self {
+            reader: log.reader(),
+            log: Mutex::new(log),
+        })
+    }
+
+    /// Append `tombstone`, unless it is already recorded.
+    pub async fn record(&self, tombstone: &SignedTombstone) -> Result<(), LogError> {
+        self.log
+            .lock()
+            .await
+            .append_batch(vec![record(tombstone)])
+            .map(drop)
+    }
+
+    pub fn snapshot(&self) -> std::sync::Arc<Snapshot> {
+        self.reader.snapshot()
+    }
+
+    /// The recorded tombstones, only those for entry `index` if given.
+    pub fn list(&self, index: Option<u64>) -> RedactionList {
+        let snapshot = self.snapshot();
+        let head = snapshot.root();
+        let redactions = snapshot
+            .iter()
+            .enumerate()
+            .filter_map(|(position, entry)| {
+                let tombstone: SignedTombstone = serde_json::from_str(&entry.payload).ok()?;
+                Some(RecordedRedaction {
+                    position: position as u64,
+                    tombstone,
+                })
+            })
+            .filter(|recorded| {
+                index.is_none_or(|index| recorded.tombstone.tombstone.index == index)
+            })
+            .collect();
+        RedactionList {
+            size: head.size,
+            root: head.root,
+            redactions,
+        }
+    }
+}
+
+/// The redaction log entry for `tombstone`. One entry can only be redacted
+/// once, so its index is the key.
+fn record(tombstone: &SignedTombstone) -> BatchEntry {
+    BatchEntry {
+        payload: serde_json::to_string(tombstone).expect("tombstones serialize"),
+        idempotency_key: Some(format!("redaction:{}", tombstone.tombstone.index)),
+        labels: Default::default(),
+        signature: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reality_core::{redaction::RedactRequest, signer::KeyFileSigner};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn records_each_tombstone_once() {
+        let dir = std::env::temp_dir().join(format!("logd-redactions-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let signer = KeyFileSigner::from_seed([9u8; 32]);
+        let mut main = Log::open(&dir).unwrap();
+        for payload in ["a", "b", "c"] {
+            main.append(payload).unwrap();
+        }
+        let request = RedactRequest {
+            reason: "erasure".into(),
+            requested_by: Some("dpo".into()),
+            authority: Some("GDPR Art. 17".into()),
+        };
+        let first = main.redact(2, request, &signer).unwrap();
+
+        // Startup records what the main log holds but the redaction log
+        // lacks, and nothing twice.
+        let redactions = Redactions::open(&dir, &main).unwrap();
+        let second = main.redact(0, "mistake", &signer).unwrap();
+        redactions.record(&second).await.unwrap();
+        redactions.record(&second).await.unwrap();
+        drop(redactions);
+        let redactions = Redactions::open(&dir, &main).unwrap();
+        let list = redactions.list(None);
+        assert_eq!(list.size, 2);
+        assert_eq!(list.redactions[0].tombstone, first);
+        assert_eq!(list.redactions[1].position, 1);
+        assert_eq!(redactions.list(Some(0)).redactions[0].tombstone, second);
+        assert!(redactions.list(Some(1)).redactions.is_empty());
+        assert_eq!(list.root, redactions.snapshot().root().root);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,277 @@
+//! Rollback and fork detection. With `REALITY_LOG_CHECKPOINT_FILE`, logd
+//! keeps the largest checkpoint it has signed in that file, which belongs
+//! outside the data directory so that restoring a backup does not restore
+//! it too. With `REALITY_LOG_CHECKPOINT_WITNESS`, each such checkpoint is
+//! also submitted to a `reality-witness`, and the cosignature kept with the
+//! others.
+//!
+//! At startup logd refuses to serve if the log it opened is smaller than
+//! either recorded checkpoint, or has another root at that size. A log
+//! restored from an old backup, or rewritten, cannot then pass for the one
+//! whose checkpoints were handed out.
+
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::Context;
+use reality_core::checkpoint::{Checkpoint, Cosignature, SignedCheckpoint};
+use reality_log::Snapshot;
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// Where checkpoints are recorded, and the largest recorded so far.
+#[derive(Debug)]
+pub struct Watermark {
+    file: Option<PathBuf>,
+    witness: Option<String>,
+    http: reqwest::Client,
+    /// Size of the checkpoint in `file`, held while it is written.
+    recorded: Mutex<u64>,
+    /// Size the witness last cosigned, held while submitting.
+    witnessed: Mutex<u64>,
+}
+
+/// How the opened log disagrees with a recorded checkpoint.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Divergence {
+    RolledBack { recorded: u64, size: u64 },
+    Forked { size: u64 },
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RolledBack { recorded, size } => write!(
+                f,
+                "the log has {size} entries but a checkpoint of size {recorded} was signed"
+            ),
+            Self::Forked { size } => {
+                write!(f, "the log's root at size {size} is not the signed one")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Divergence {}
+
+/// The witness's `GET /status`, as far as logd reads it.
+#[derive(Deserialize)]
+struct WitnessStatus {
+    logs: Vec<WitnessedLog>,
+}
+
+#[derive(Deserialize)]
+struct WitnessedLog {
+    public_key: String,
+    cosigned: Option<Cosignature>,
+}
+
+impl Watermark {
+    pub fn new(file: Option<PathBuf>, witness: Option<String>) -> Self {
+        Self {
+            file,
+            witness,
+            http: reqwest::Client::new(),
+            recorded: Mutex::new(0),
+            witnessed: Mutex::new(0),
+        }
+    }
+
+    /// Check `snapshot`, the log as opened, against the recorded
+    /// checkpoints of the log with `public_key`. An unreachable witness is
+    /// only warned about.
+    pub async fn check(&self, snapshot: &Snapshot, public_key: &str) -> anyhow::Result<()> {
+        if let Some(path) = &self.file {
+            if let Some(signed) = crate::read_json::<SignedCheckpoint>(path.clone())
+                .await
+                .with_context(|| format!("read {}", path.display()))?
+            {
+                anyhow::ensure!(
+                    signed.verify(),
+                    "the checkpoint in {} has an invalid signature",
+                    path.display()
+                );
+                compare(snapshot, &signed.checkpoint).with_context(|| {
+                    format!(
+                        "refusing to serve: the log disagrees with {}",
+                        path.display()
+                    )
+                })?;
+                *self.recorded.lock().await = signed.checkpoint.size;
+            }
+        }
+        if let Some(witness) = &self.witness {
+            match self.witnessed_checkpoint(witness, public_key).await {
+                Ok(Some(cosigned)) => {
+                    compare(snapshot, &cosigned.checkpoint).with_context(|| {
+                        format!("refusing to serve: the log disagrees with witness {witness}")
+                    })?;
+                    *self.witnessed.lock().await = cosigned.checkpoint.size;
+                }
+                Ok(None) => {}
+                Err(err) => warn!(?err, %witness, "could not check the witnessed checkpoint"),
+            }
+        }
+        Ok(())
+    }
+
+    async fn witnessed_checkpoint(
+        &self,
+        witness: &str,
+        public_key: &str,
+    ) -> anyhow::Result<Option<Cosignature>> {
+        let status: WitnessStatus = self
+            .http
+            .get(format!("{}/status", witness.trim_end_matches('/')))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let Some(cosigned) = status
+            .logs
+            .into_iter()
+            .find(|log| log.public_key.eq_ignore_ascii_case(public_key))
+            .and_then(|log| log.cosigned)
+        else {
+            return Ok(None);
+        };
+        anyhow::ensure!(cosigned.verify(), "the witness's cosignature is invalid");
+        Ok(Some(cosigned))
+    }
+
+    /// Write `signed` to the checkpoint file if it is larger than the one
+    /// there. Returns whether it was.
+    pub async fn record(&self, signed: &SignedCheckpoint) -> anyhow::Result<bool> {
+        let mut recorded = self.recorded.lock().await;
+        if signed.checkpoint.size <= *recorded {
+            return Ok(false);
+        }
+        if let Some(path) = &self.file {
+            crate::write_json(path.clone(), signed).await?;
+        }
+        *recorded = signed.checkpoint.size;
+        Ok(true)
+    }
+
+    /// Submit `signed`, a checkpoint of `snapshot`, to the witness, and
+    /// return its cosignature. `None` without a witness, or when it already
+    /// cosigned a checkpoint this large.
+    pub async fn submit(
+        &self,
+        signed: &SignedCheckpoint,
+        snapshot: Arc<Snapshot>,
+    ) -> anyhow::Result<Option<Cosignature>> {
+        let Some(witness) = &self.witness else {
+            return Ok(None);
+        };
+        let url = format!("{}/add-checkpoint", witness.trim_end_matches('/'));
+        let mut witnessed = self.witnessed.lock().await;
+        // A witness that holds another size says which; retry once from it.
+        for _ in 0..2 {
+            let size = signed.checkpoint.size;
+            if size <= *witnessed {
+                return Ok(None);
+            }
+            let consistency = match *witnessed {
+                0 => None,
+                old => Some(snapshot.consistency(old, size)?),
+            };
+            let resp = self
+                .http
+                .post(&url)
+                .json(&serde_json::json!({
+                    "old_size": *witnessed,
+                    "consistency": consistency,
+                    "checkpoint": signed,
+                }))
+                .send()
+                .await?;
+            if resp.status() == reqwest::StatusCode::CONFLICT {
+                *witnessed = resp
+                    .text()
+                    .await?
+                    .trim()
+                    .parse()
+                    .context("the witness's size is not a number")?;
+                continue;
+            }
+            let cosigned: Cosignature = resp.error_for_status()?.json().await?;
+            *witnessed = size;
+            info!(%witness, size, "witness cosigned checkpoint");
+            return Ok(Some(cosigned));
+        }
+        anyhow::bail!("the witness's size keeps changing")
+    }
+}
+
+/// Whether `snapshot` still holds the tree `recorded` was signed over.
+pub fn compare(snapshot: &Snapshot, recorded: &Checkpoint) -> Result<(), Divergence> {
+    let size = recorded.size;
+    if snapshot.len() < size {
+        return Err(Divergence::RolledBack {
+            recorded: size,
+            size: snapshot.len(),
+        });
+    }
+    let head = snapshot.root_at(size).expect("sizes up to the log's exist");
+    if !head.root.eq_ignore_ascii_case(&recorded.root) {
+        return Err(Divergence::Forked { size });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use reality_core::signer::{KeyFileSigner, Signer};
+    use reality_log::Log;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn refuses_logs_behind_the_recorded_checkpoint() {
+        let dir = std::env::temp_dir().join(format!("logd-rollback-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let signer = KeyFileSigner::from_seed([6; 32]);
+        let key = hex::encode(signer.public_key());
+        let mut log = Log::open(dir.join("log")).unwrap();
+        log.append("a").unwrap();
+        let old = log.snapshot();
+        log.append("b").unwrap();
+
+        let file = dir.join("checkpoint.json");
+        let watermark = Watermark::new(Some(file.clone()), None);
+        watermark.check(&old, &key).await.unwrap();
+        let signed = log.checkpoint(&signer).unwrap();
+        assert!(watermark.record(&signed).await.unwrap());
+        assert!(!watermark
+            .record(&old.checkpoint(&signer).unwrap())
+            .await
+            .unwrap());
+
+        // Restarted on the log as it was before the second append.
+        let restarted = Watermark::new(Some(file.clone()), None);
+        let err = restarted.check(&old, &key).await.unwrap_err();
+        assert_eq!(
+            err.root_cause().to_string(),
+            Divergence::RolledBack {
+                recorded: 2,
+                size: 1
+            }
+            .to_string()
+        );
+        restarted.check(&log.snapshot(), &key).await.unwrap();
+
+        let mut forked = Log::open(dir.join("forked")).unwrap();
+        for payload in ["a", "c", "d"] {
+            forked.append(payload).unwrap();
+        }
+        assert_eq!(
+            compare(&forked.snapshot(), &signed.checkpoint),
+            Err(Divergence::Forked { size: 2 })
+        );
+        let first = old.checkpoint(&signer).unwrap().checkpoint;
+        assert_eq!(compare(&forked.snapshot(), &first), Ok(()));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,539 @@
+//! Sigstore bundles as submissions, so logd can stand in for Rekor inside an
+//! organization. `/sigstore` checks a bundle's shape and appends it whole,
+//! with a `verification` object beside it recording what logd established.
+//!
+//! With `REALITY_LOG_SIGSTORE_ROOTS` set (and the `sigstore` feature built
+//! in), a bundle is only accepted once its certificate chains to one of
+//! those CAs for code signing, at the time its first transparency log entry
+//! was integrated, and a DSSE envelope's signature verifies against it.
+//! Without roots, bundles are accepted as `unverified`, and the identities in
+//! their certificates are recorded as claims.
+
+use std::path::Path;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reality_core::{dsse::Envelope, BatchEntry};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::der;
+
+/// The type of the entries `/sigstore` appends.
+pub const ENTRY_TYPE: &str = "sigstore-bundle";
+
+/// Every version of the bundle media type starts with this.
+const MEDIA_TYPE_PREFIX: &str = "application/vnd.dev.sigstore.bundle";
+
+/// OID 2.5.29.17, subjectAltName, as DER contents.
+const SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+
+/// OID 1.3.6.1.4.1.57264.1.8, Fulcio's OIDC issuer as a UTF8String.
+const FULCIO_ISSUER: &[u8] = &[0x2b, 6, 1, 4, 1, 0x83, 0xbf, 0x30, 1, 8];
+
+/// OID 1.3.6.1.4.1.57264.1.1, the older issuer extension holding raw text.
+const FULCIO_ISSUER_V1: &[u8] = &[0x2b, 6, 1, 4, 1, 0x83, 0xbf, 0x30, 1, 1];
+
+/// The parts of a bundle logd reads; the rest is appended as submitted.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Bundle {
+    media_type: String,
+    verification_material: Material,
+    message_signature: Option<MessageSignature>,
+    dsse_envelope: Option<Envelope>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Material {
+    certificate: Option<RawBytes>,
+    x509_certificate_chain: Option<CertificateChain>,
+    public_key: Option<serde_json::Value>,
+    #[serde(default)]
+    tlog_entries: Vec<TlogEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawBytes {
+    raw_bytes: String,
+}
+
+#[derive(Deserialize)]
+struct CertificateChain {
+    certificates: Vec<RawBytes>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TlogEntry {
+    /// Unix seconds; a string in protobuf's JSON form, but numbers are taken too.
+    integrated_time: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MessageSignature {
+    message_digest: Option<MessageDigest>,
+    signature: String,
+}
+
+#[derive(Deserialize)]
+struct MessageDigest {
+    algorithm: String,
+    digest: String,
+}
+
+/// What logd established about a bundle, recorded in its entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Verification {
+    pub status: Status,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// When the first transparency log entry says the bundle was logged;
+    /// the certificate is checked at this time rather than now.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integrated_time: Option<i64>,
+    /// The leaf certificate's email and URI subject alternative names.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identities: Vec<String>,
+    /// The OIDC issuer Fulcio recorded in the leaf certificate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    pub signature: SignatureCheck,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// The certificate chains to a configured root.
+    Verified,
+    /// No roots are configured, so nothing was checked.
+    Unverified,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureCheck {
+    Verified,
+    /// A message signature covers an artifact logd never sees, and without
+    /// roots no signature is checked at all.
+    NotChecked,
+}
+
+/// The appended payload.
+#[derive(Serialize)]
+struct Submission<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    /// The signed artifact's digest as `<algorithm>:<hex>`, for message
+    /// signatures that carry one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    artifact: Option<String>,
+    verification: Verification,
+    bundle: &'a serde_json::Value,
+}
+
+/// Why a bundle was refused.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Rejection {
+    Malformed(String),
+    /// Roots are configured and the bundle does not verify against them.
+    #[cfg_attr(not(feature = "sigstore"), allow(dead_code))]
+    Unverified(String),
+}
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed(reason) => write!(f, "malformed bundle: {reason}"),
+            Self::Unverified(reason) => write!(f, "bundle does not verify: {reason}"),
+        }
+    }
+}
+
+/// The CAs bundles must chain to; none accepts every well-formed bundle.
+#[derive(Default)]
+pub struct Sigstore {
+    #[cfg(feature = "sigstore")]
+    roots: Vec<rustls_pki_types::TrustAnchor<'static>>,
+}
+
+impl Sigstore {
+    /// Trust every certificate in the PEM file at `path`, such as Fulcio's
+    /// root and intermediate or a private CA's.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        use anyhow::Context;
+
+        let pem = std::fs::read(path)
+            .with_context(|| format!("read Sigstore roots {}", path.display()))?;
+        Self::from_pem(&pem).with_context(|| format!("load Sigstore roots {}", path.display()))
+    }
+
+    #[cfg(feature = "sigstore")]
+    fn from_pem(pem: &[u8]) -> anyhow::Result<Self> {
+        let mut roots = Vec::new();
+        for cert in rustls_pemfile::certs(&mut &pem[..]) {
+            let cert = cert?;
+            let anchor = webpki::anchor_from_trusted_cert(&cert)
+                .map_err(|err| anyhow::anyhow!("unusable CA certificate: {err}"))?;
+            roots.push(anchor.to_owned());
+        }
+        anyhow::ensure!(!roots.is_empty(), "no certificates");
+        Ok(Self { roots })
+    }
+
+    #[cfg(not(feature = "sigstore"))]
+    fn from_pem(_pem: &[u8]) -> anyhow::Result<Self> {
+        anyhow::bail!(
+            "logd was built without the sigstore feature; rebuild it with --features sigstore"
+        )
+    }
+
+    /// How many CAs are trusted.
+    pub fn roots(&self) -> usize {
+        #[cfg(feature = "sigstore")]
+        return self.roots.len();
+        #[cfg(not(feature = "sigstore"))]
+        0
+    }
+
+    /// Check `bundle` and return the entry to append, keyed by the bundle's
+    /// digest so that a resubmission is a duplicate. `now` is the time a
+    /// bundle without transparency log entries is checked at.
+    pub fn admit(&self, bundle: &serde_json::Value, now: i64) -> Result<BatchEntry, Rejection> {
+        let malformed = |reason: &str| Rejection::Malformed(reason.to_string());
+        let parsed =
+            Bundle::deserialize(bundle).map_err(|err| Rejection::Malformed(err.to_string()))?;
+        if !parsed.media_type.starts_with(MEDIA_TYPE_PREFIX) {
+            return Err(Rejection::Malformed(format!(
+                "media type {:?} is not a Sigstore bundle",
+                parsed.media_type
+            )));
+        }
+        let material = &parsed.verification_material;
+        let certs = match (&material.certificate, &material.x509_certificate_chain) {
+            (Some(cert), _) => vec![decode(&cert.raw_bytes, "certificate")?],
+            (None, Some(chain)) if !chain.certificates.is_empty() => chain
+                .certificates
+                .iter()
+                .map(|cert| decode(&cert.raw_bytes, "certificate"))
+                .collect::<Result<_, _>>()?,
+            _ if material.public_key.is_some() => Vec::new(),
+            _ => return Err(malformed("no certificate or public key")),
+        };
+        let integrated_time = match material.tlog_entries.first() {
+            Some(entry) => Some(integrated_time(entry).ok_or_else(|| {
+                malformed("the transparency log entry has no valid integratedTime")
+            })?),
+            None => None,
+        };
+
+        let artifact = match (&parsed.message_signature, &parsed.dsse_envelope) {
+            (Some(signature), None) => {
+                decode(&signature.signature, "signature")?;
+                match &signature.message_digest {
+                    Some(digest) => Some(format!(
+                        "{}:{}",
+                        algorithm(&digest.algorithm),
+                        hex::encode(decode(&digest.digest, "message digest")?)
+                    )),
+                    None => None,
+                }
+            }
+            (None, Some(envelope)) => {
+                envelope
+                    .validate()
+                    .map_err(|err| Rejection::Malformed(err.to_string()))?;
+                None
+            }
+            _ => {
+                return Err(malformed(
+                    "exactly one of messageSignature and dsseEnvelope is required",
+                ))
+            }
+        };
+
+        let leaf = certs.first().map(Vec::as_slice);
+        let mut verification = Verification {
+            status: Status::Unverified,
+            reason: Some("no Sigstore roots are configured".to_string()),
+            integrated_time,
+            identities: leaf.map(identities).unwrap_or_default(),
+            issuer: leaf.and_then(issuer),
+            signature: SignatureCheck::NotChecked,
+        };
+        let at = integrated_time.unwrap_or(now);
+        if let Some(signature) = self.check(&certs, parsed.dsse_envelope.as_ref(), at)? {
+            verification.status = Status::Verified;
+            verification.reason = None;
+            verification.signature = signature;
+        }
+
+        let digest = Sha256::digest(bundle.to_string().as_bytes());
+        let payload = serde_json::to_string(&Submission {
+            kind: ENTRY_TYPE,
+            artifact,
+            verification,
+            bundle,
+        })
+        .expect("submission serializes");
+        Ok(BatchEntry {
+            idempotency_key: Some(format!("sigstore:{}", hex::encode(digest))),
+            payload,
+            labels: Default::default(),
+            signature: None,
+        })
+    }
+
+    /// Check the certificate chain, and an envelope's signature, at unix
+    /// time `at`; `None` when there are no roots to check against.
+    #[cfg(feature = "sigstore")]
+    fn check(
+        &self,
+        certs: &[Vec<u8>],
+        envelope: Option<&Envelope>,
+        at: i64,
+    ) -> Result<Option<SignatureCheck>, Rejection> {
+        use rustls_pki_types::{CertificateDer, UnixTime};
+        use webpki::{EndEntityCert, KeyUsage, ALL_VERIFICATION_ALGS};
+
+        /// id-kp-codeSigning, 1.3.6.1.5.5.7.3.3.
+        const CODE_SIGNING: &[u8] = &[0x2b, 6, 1, 5, 5, 7, 3, 3];
+
+        if self.roots.is_empty() {
+            return Ok(None);
+        }
+        let unverified = |reason: String| Rejection::Unverified(reason);
+        let Some((leaf, intermediates)) = certs.split_first() else {
+            return Err(unverified(
+                "it is signed with a public key, not a certificate".to_string(),
+            ));
+        };
+        let leaf = CertificateDer::from(leaf.as_slice());
+        let intermediates: Vec<_> = intermediates
+            .iter()
+            .map(|cert| CertificateDer::from(cert.as_slice()))
+            .collect();
+        let cert = EndEntityCert::try_from(&leaf)
+            .map_err(|err| Rejection::Malformed(format!("certificate: {err}")))?;
+        let time = UnixTime::since_unix_epoch(std::time::Duration::from_secs(
+            u64::try_from(at).unwrap_or_default(),
+        ));
+        cert.verify_for_usage(
+            ALL_VERIFICATION_ALGS,
+            &self.roots,
+            &intermediates,
+            time,
+            KeyUsage::required(CODE_SIGNING),
+            None,
+            None,
+        )
+        .map_err(|err| unverified(format!("certificate: {err}")))?;
+
+        let Some(envelope) = envelope else {
+            return Ok(Some(SignatureCheck::NotChecked));
+        };
+        let payload = envelope
+            .payload_bytes()
+            .map_err(|err| Rejection::Malformed(err.to_string()))?;
+        let message = reality_core::dsse::pae(&envelope.payload_type, &payload);
+        let signed = envelope.signatures.iter().any(|signature| {
+            STANDARD.decode(&signature.sig).is_ok_and(|sig| {
+                ALL_VERIFICATION_ALGS
+                    .iter()
+                    .any(|alg| cert.verify_signature(*alg, &message, &sig).is_ok())
+            })
+        });
+        if !signed {
+            return Err(unverified(
+                "no envelope signature is by the certificate's key".to_string(),
+            ));
+        }
+        Ok(Some(SignatureCheck::Verified))
+    }
+
+    #[cfg(not(feature = "sigstore"))]
+    fn check(
+        &self,
+        _certs: &[Vec<u8>],
+        _envelope: Option<&Envelope>,
+        _at: i64,
+    ) -> Result<Option<SignatureCheck>, Rejection> {
+        Ok(None)
+    }
+}
+
+fn decode(value: &str, field: &str) -> Result<Vec<u8>, Rejection> {
+    STANDARD
+        .decode(value)
+        .map_err(|_| Rejection::Malformed(format!("the {field} is not valid base64")))
+}
+
+fn integrated_time(entry: &TlogEntry) -> Option<i64> {
+    match entry.integrated_time.as_ref()? {
+        serde_json::Value::String(time) => time.parse().ok(),
+        serde_json::Value::Number(time) => time.as_i64(),
+        _ => None,
+    }
+}
+
+/// A bundle's `HashAlgorithm` name as the usual lowercase digest prefix.
+fn algorithm(name: &str) -> String {
+    match name {
+        "SHA2_256" => "sha256".to_string(),
+        "SHA2_384" => "sha384".to_string(),
+        "SHA2_512" => "sha512".to_string(),
+        other => other.to_ascii_lowercase(),
+    }
+}
+
+/// The email and URI subject alternative names of a DER certificate.
+fn identities(cert: &[u8]) -> Vec<String> {
+    let Some(names) = extension(cert, SUBJECT_ALT_NAME).and_then(der::read) else {
+        return Vec::new();
+    };
+    der::elements(names.contents)
+        // [1] rfc822Name and [6] uniformResourceIdentifier, both IA5String.
+        .filter(|name| name.tag == 0x81 || name.tag == 0x86)
+        .filter_map(|name| String::from_utf8(name.contents.to_vec()).ok())
+        .collect()
+}
+
+fn issuer(cert: &[u8]) -> Option<String> {
+    let text = match extension(cert, FULCIO_ISSUER) {
+        Some(value) => der::read(value).filter(|s| s.tag == 0x0c)?.contents,
+        None => extension(cert, FULCIO_ISSUER_V1)?,
+    };
+    String::from_utf8(text.to_vec()).ok()
+}
+
+/// The value of the extension `oid` in a DER certificate.
+fn extension<'a>(cert: &'a [u8], oid: &[u8]) -> Option<&'a [u8]> {
+    let cert = der::read(cert)?;
+    let tbs = der::read(cert.contents)?;
+    // Extensions are the [3] field of TBSCertificate.
+    let explicit = der::elements(tbs.contents).find(|field| field.tag == 0xa3)?;
+    let extensions = der::read(explicit.contents)?;
+    der::elements(extensions.contents).find_map(|extension| {
+        let mut fields = der::elements(extension.contents);
+        let id = fields.next().filter(|id| id.tag == 0x06)?;
+        // The critical flag is optional.
+        let value = fields.find(|field| field.tag == 0x04)?;
+        (id.contents == oid).then_some(value.contents)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUNDLE: &str = include_str!("sigstore/bundle.json");
+
+    /// After the fixture leaf certificate's ten minutes of validity.
+    const LATER: i64 = 1_767_225_600;
+
+    fn bundle() -> serde_json::Value {
+        serde_json::from_str(BUNDLE).unwrap()
+    }
+
+    fn verification(entry: &BatchEntry) -> Verification {
+        let payload: serde_json::Value = serde_json::from_str(&entry.payload).unwrap();
+        serde_json::from_value(payload["verification"].clone()).unwrap()
+    }
+
+    #[test]
+    fn records_bundles_unverified_without_roots() {
+        let entry = Sigstore::default().admit(&bundle(), LATER).unwrap();
+        assert!(entry
+            .idempotency_key
+            .as_ref()
+            .unwrap()
+            .starts_with("sigstore:"));
+        let verification = verification(&entry);
+        assert_eq!(verification.status, Status::Unverified);
+        assert_eq!(verification.signature, SignatureCheck::NotChecked);
+        assert_eq!(verification.integrated_time, Some(1_735_689_660));
+        assert_eq!(
+            verification.identities,
+            ["https://github.com/org/repo/.github/workflows/release.yml@refs/heads/main"]
+        );
+        assert_eq!(
+            verification.issuer.as_deref(),
+            Some("https://token.actions.githubusercontent.com")
+        );
+
+        let mut message = bundle();
+        let envelope = message.as_object_mut().unwrap().remove("dsseEnvelope");
+        message["messageSignature"] = serde_json::json!({
+            "messageDigest": {"algorithm": "SHA2_256", "digest": STANDARD.encode([0xab; 32])},
+            "signature": envelope.unwrap()["signatures"][0]["sig"],
+        });
+        let entry = Sigstore::default().admit(&message, LATER).unwrap();
+        let payload: serde_json::Value = serde_json::from_str(&entry.payload).unwrap();
+        assert_eq!(payload["artifact"], format!("sha256:{}", "ab".repeat(32)));
+
+        let mut both = message.clone();
+        both["dsseEnvelope"] = bundle()["dsseEnvelope"].clone();
+        let mut retyped = bundle();
+        retyped["mediaType"] = "application/json".into();
+        let mut bare = bundle();
+        bare["verificationMaterial"] = serde_json::json!({});
+        for malformed in [both, retyped, bare, serde_json::json!([])] {
+            assert!(matches!(
+                Sigstore::default().admit(&malformed, LATER),
+                Err(Rejection::Malformed(_))
+            ));
+        }
+    }
+
+    #[cfg(feature = "sigstore")]
+    #[test]
+    fn verifies_chains_and_envelope_signatures() {
+        let root = include_str!("sigstore/root.pem");
+        let intermediate = include_str!("sigstore/intermediate.pem");
+        let trusted = Sigstore::from_pem(format!("{root}{intermediate}").as_bytes()).unwrap();
+        let root_only = Sigstore::from_pem(root.as_bytes()).unwrap();
+
+        let entry = trusted.admit(&bundle(), LATER).unwrap();
+        let verification = verification(&entry);
+        assert_eq!(verification.status, Status::Verified);
+        assert_eq!(verification.signature, SignatureCheck::Verified);
+        assert!(matches!(
+            root_only.admit(&bundle(), LATER),
+            Err(Rejection::Unverified(_))
+        ));
+
+        // The intermediate can come with the bundle instead.
+        let intermediate_der = rustls_pemfile::certs(&mut intermediate.as_bytes())
+            .next()
+            .unwrap()
+            .unwrap();
+        let mut chained = bundle();
+        let leaf = chained["verificationMaterial"]
+            .as_object_mut()
+            .unwrap()
+            .remove("certificate")
+            .unwrap();
+        chained["verificationMaterial"]["x509CertificateChain"] = serde_json::json!({
+            "certificates": [leaf, {"rawBytes": STANDARD.encode(intermediate_der)}],
+        });
+        assert!(root_only.admit(&chained, LATER).is_ok());
+
+        // Without a log entry's time the leaf is checked now, long after it
+        // expired.
+        let mut untimed = bundle();
+        untimed["verificationMaterial"]["tlogEntries"] = serde_json::json!([]);
+        assert!(matches!(
+            trusted.admit(&untimed, LATER),
+            Err(Rejection::Unverified(_))
+        ));
+
+        let mut forged = bundle();
+        forged["dsseEnvelope"]["payloadType"] = "application/json".into();
+        assert!(matches!(
+            trusted.admit(&forged, LATER),
+            Err(Rejection::Unverified(_))
+        ));
+    }
+}
@@ -0,0 +1,27 @@
+//! Signed requests to the AWS APIs logd calls: S3 for blobs and KMS for
+//! payload keys. The signing itself is `reality_core::sigv4`, which the
+//! AWS KMS checkpoint signer shares.
+
+pub use reality_core::sigv4::Credentials;
+use reqwest::{Method, Url};
+
+/// A request to `service` carrying `headers` and `body`, signed now.
+pub fn request(
+    credentials: &Credentials,
+    http: &reqwest::Client,
+    service: &str,
+    method: Method,
+    url: Url,
+    headers: &[(&str, &str)],
+    body: Vec<u8>,
+) -> reqwest::RequestBuilder {
+    let signed = credentials.sign(service, method.as_str(), &url, headers, &body);
+    let mut request = http.request(method, url);
+    for (name, value) in signed {
+        request = request.header(name, value);
+    }
+    if !body.is_empty() {
+        request = request.body(body);
+    }
+    request
+}
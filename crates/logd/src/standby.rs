@@ -0,0 +1,298 @@
+//! Active/passive failover. With `REALITY_LOG_FOLLOW=<primary url>` logd
+//! runs as a standby: it refuses appends, and every
+//! `REALITY_LOG_FOLLOW_EVERY` seconds fetches the primary's latest signed
+//! checkpoint and copies the entries it lacks from the primary's
+//! `GET /replication/entries`. They are only kept once they rebuild that
+//! checkpoint's tree (see `reality_log::replica`), so the standby always
+//! holds a prefix of the primary's log.
+//!
+//! `POST /admin/promote` makes the standby the primary. It catches up
+//! first if the old primary still answers, checks that it holds the tree of
+//! the last primary checkpoint it verified, and signs a handoff from that
+//! checkpoint to its own first one (`reality_core::handoff`). The handoff is
+//! kept in `handoff.json` and served at `GET /handoff`.
+
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use anyhow::Context;
+use reality_core::{checkpoint::SignedCheckpoint, handoff::Handoff};
+use reality_log::Entry;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::{rollback, AppState, MAX_BATCH};
+
+/// Where `handoff.json` lives under the data directory.
+pub const HANDOFF: &str = "handoff.json";
+
+#[derive(Debug)]
+pub struct Standby {
+    primary: String,
+    /// Hex key the primary's checkpoints must be signed with.
+    primary_key: String,
+    /// The primary's read token, for its replication route.
+    token: Option<String>,
+    http: reqwest::Client,
+    /// The primary's latest checkpoint whose tree this log holds. Held
+    /// while syncing or promoting, so the two never overlap.
+    synced: Mutex<Option<SignedCheckpoint>>,
+    promoted: AtomicBool,
+}
+
+impl Standby {
+    pub fn new(primary: String, primary_key: String, token: Option<String>) -> Self {
+        Self {
+            primary: primary.trim_end_matches('/').to_string(),
+            primary_key,
+            token,
+            http: reqwest::Client::new(),
+            synced: Mutex::new(None),
+            promoted: AtomicBool::new(false),
+        }
+    }
+
+    pub fn primary(&self) -> &str {
+        &self.primary
+    }
+
+    pub fn promoted(&self) -> bool {
+        self.promoted.load(Ordering::SeqCst)
+    }
+
+    /// Copy what the primary has signed and this log lacks. Returns the
+    /// size now held.
+    pub async fn sync(&self, state: &AppState) -> anyhow::Result<u64> {
+        let mut synced = self.synced.lock().await;
+        anyhow::ensure!(!self.promoted(), "this log was promoted");
+        let signed: SignedCheckpoint = self
+            .http
+            .get(format!("{}/checkpoint", self.primary))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        anyhow::ensure!(
+            signed.verify()
+                && signed
+                    .signature
+                    .public_key
+                    .eq_ignore_ascii_case(&self.primary_key),
+            "the primary's checkpoint is not validly signed by REALITY_LOG_FOLLOW_KEY"
+        );
+        let size = signed.checkpoint.size;
+        let held = state.reader.snapshot().len();
+        anyhow::ensure!(
+            size >= held,
+            "the primary signed a tree of {size} entries, but this standby holds {held}"
+        );
+        let mut entries = Vec::with_capacity((size - held) as usize);
+        while held + (entries.len() as u64) < size {
+            let from = held + entries.len() as u64;
+            let to = size.min(from + MAX_BATCH as u64);
+            let mut request = self
+                .http
+                .get(format!("{}/replication/entries", self.primary))
+                .query(&[("from", from), ("to", to)]);
+            if let Some(token) = &self.token {
+                request = request.bearer_auth(token);
+            }
+            let page: Vec<Entry> = request.send().await?.error_for_status()?.json().await?;
+            anyhow::ensure!(
+                page.len() as u64 == to - from,
+                "the primary sent {} entries from {from} to {to}",
+                page.len()
+            );
+            entries.extend(page);
+        }
+        state
+            .log
+            .lock()
+            .await
+            .replicate(entries, &signed.checkpoint)
+            .context("replicate the primary's entries")?;
+        *synced = Some(signed);
+        Ok(size)
+    }
+
+    /// Stop following and sign the handoff from the primary's last
+    /// verified checkpoint to this log's first checkpoint as primary.
+    pub async fn promote(&self, state: &AppState) -> anyhow::Result<Handoff> {
+        let signer = state
+            .signer
+            .clone()
+            .context("promotion signs a handoff checkpoint, so it needs REALITY_LOG_SIGNER")?;
+        if let Err(err) = self.sync(state).await {
+            warn!(?err, primary = %self.primary, "could not catch up before promotion");
+        }
+        let synced = self.synced.lock().await;
+        anyhow::ensure!(!self.promoted(), "this log was already promoted");
+        let from = synced
+            .clone()
+            .context("no checkpoint of the primary has been verified yet")?;
+        let snapshot = state.reader.snapshot();
+        rollback::compare(&snapshot, &from.checkpoint)
+            .context("this log does not hold the primary's last verified tree")?;
+        let to = state
+            .signed_checkpoint(snapshot.clone())
+            .await
+            .map_err(|(_, message)| anyhow::anyhow!(message))?;
+        let (old, new) = (from.checkpoint.size, to.checkpoint.size);
+        let consistency = if old < new {
+            Some(snapshot.consistency(old, new)?)
+        } else {
+            None
+        };
+        let handoff =
+            crate::off_worker(move || Handoff::sign(from, to, consistency, signer.as_ref()))
+                .await?;
+        crate::write_json(state.data_dir.join(HANDOFF), &handoff).await?;
+        self.promoted.store(true, Ordering::SeqCst);
+        info!(
+            size = handoff.to.checkpoint.size,
+            primary = %self.primary,
+            "promoted to primary"
+        );
+        Ok(handoff)
+    }
+}
+
+/// Follow the primary every `every` until promoted.
+pub fn spawn_follower(state: AppState, standby: std::sync::Arc<Standby>, every: Duration) {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(every);
+        let mut held = state.reader.snapshot().len();
+        while !standby.promoted() {
+            ticks.tick().await;
+            match standby.sync(&state).await {
+                Ok(size) if size > held => {
+                    info!(size, "replicated the primary's checkpoint");
+                    held = size;
+                }
+                Ok(_) => {}
+                Err(_) if standby.promoted() => {}
+                Err(err) => warn!(?err, primary = %standby.primary, "failed to follow the primary"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{
+        extract::{Query, State},
+        routing::{get, post},
+        Json, Router,
+    };
+    use reality_core::{
+        signer::{KeyFileSigner, Signer},
+        AppendRequest,
+    };
+
+    use super::*;
+    use crate::{
+        append, attestation::Attestations, checkpoint, link::Links, replication_entries,
+        sigstore::Sigstore, webhook::Webhooks, DurabilityQuery, Intake, Tokens,
+    };
+
+    async fn state(dir: std::path::PathBuf, seed: u8) -> AppState {
+        AppState::new(
+            dir,
+            None,
+            Some(Arc::new(KeyFileSigner::from_seed([seed; 32]))),
+            Vec::new(),
+            Intake {
+                webhooks: Webhooks::default(),
+                attestations: Attestations::new(Vec::new()),
+                links: Links::default(),
+                sigstore: Sigstore::default(),
+                tsa: None,
+                blobs: None,
+            },
+            None,
+            Tokens::default(),
+        )
+        .await
+        .unwrap()
+    }
+
+    async fn append_to(state: &AppState, payload: &str) -> Result<(), axum::http::StatusCode> {
+        let request = AppendRequest {
+            payload: payload.into(),
+            labels: Default::default(),
+            signature: None,
+        };
+        append(
+            Query(DurabilityQuery { durability: None }),
+            State(state.clone()),
+            Default::default(),
+            None,
+            Json(request),
+        )
+        .await
+        .map(|_| ())
+        .map_err(|(status, _)| status)
+    }
+
+    #[tokio::test]
+    async fn follows_the_primary_and_hands_off_on_promotion() {
+        let dir = std::env::temp_dir().join(format!("logd-standby-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let primary = state(dir.join("primary"), 1).await;
+        for payload in ["a", "b", "c"] {
+            append_to(&primary, payload).await.unwrap();
+        }
+        let app = Router::new()
+            .route("/checkpoint", get(checkpoint))
+            .route("/replication/entries", get(replication_entries))
+            .route("/append", post(append))
+            .with_state(primary.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let old_key = hex::encode(KeyFileSigner::from_seed([1; 32]).public_key());
+        let standby = Arc::new(Standby::new(url, old_key.clone(), None));
+        let follower = AppState {
+            standby: Some(standby.clone()),
+            ..state(dir.join("standby"), 2).await
+        };
+        assert_eq!(standby.sync(&follower).await.unwrap(), 3);
+        assert_eq!(
+            append_to(&follower, "d").await,
+            Err(axum::http::StatusCode::SERVICE_UNAVAILABLE)
+        );
+        append_to(&primary, "d").await.unwrap();
+        assert_eq!(standby.sync(&follower).await.unwrap(), 4);
+        assert_eq!(
+            follower.reader.snapshot().root(),
+            primary.reader.snapshot().root()
+        );
+
+        // A checkpoint by another key is not followed.
+        let stranger = Standby::new(
+            standby.primary().to_string(),
+            hex::encode(KeyFileSigner::from_seed([3; 32]).public_key()),
+            None,
+        );
+        assert!(stranger.sync(&follower).await.is_err());
+
+        let handoff = standby.promote(&follower).await.unwrap();
+        let new_key = hex::encode(KeyFileSigner::from_seed([2; 32]).public_key());
+        handoff.verify(Some(&old_key), Some(&new_key)).unwrap();
+        assert_eq!(handoff.from.checkpoint.root, handoff.to.checkpoint.root);
+        assert!(standby.promote(&follower).await.is_err());
+        append_to(&follower, "e").await.unwrap();
+        let kept: Handoff = crate::read_json(dir.join("standby").join(HANDOFF))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(kept, handoff);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
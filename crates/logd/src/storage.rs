@@ -0,0 +1,180 @@
+//! Storage verification. `reality-logd verify-storage` replays the data
+//! directory (see `reality_log::replay`) against every root recorded outside
+//! it: the checkpoints witnesses cosigned and the anchored roots. It prints
+//! what agreed, or the first index where the files diverge and exits with an
+//! error.
+//!
+//! With `REALITY_LOG_VERIFY_EVERY=<seconds>` the server runs the same replay
+//! in the background, also checking the root it serves, and logs an error
+//! naming the first divergent index.
+
+use std::{path::Path, time::Duration};
+
+use anyhow::Context;
+use reality_core::{checkpoint::Cosignature, AnchorQuery};
+use reality_log::replay::{RecordedRoot, Replay};
+use tracing::{error, info};
+
+use crate::AppState;
+
+/// Every root of this log recorded outside its files. Anchored roots come
+/// from the anchor daemon, and are left out when `anchor_api` is unset.
+pub async fn recorded_roots(
+    http: &reqwest::Client,
+    anchor_api: Option<&str>,
+    data_dir: &Path,
+) -> anyhow::Result<Vec<RecordedRoot>> {
+    let cosignatures: Vec<Cosignature> = crate::read_json(data_dir.join("witnesses.json"))
+        .await?
+        .unwrap_or_default();
+    let anchors = match anchor_api {
+        Some(_) => crate::read_anchors(http, anchor_api, &AnchorQuery::default())
+            .await
+            .context("read anchors")?,
+        None => Vec::new(),
+    };
+    Ok(cosignatures
+        .into_iter()
+        .map(|cosigned| RecordedRoot {
+            source: format!("witness {}", cosigned.witness.public_key),
+            size: cosigned.checkpoint.size,
+            root: cosigned.checkpoint.root,
+        })
+        .chain(anchors.into_iter().map(|anchor| RecordedRoot {
+            source: format!("anchor {}", anchor.record.txid),
+            size: anchor.record.size,
+            root: anchor.record.root,
+        }))
+        .collect())
+}
+
+/// Replay the running log's files against the recorded roots and the root
+/// it serves.
+pub async fn verify(state: &AppState) -> anyhow::Result<Replay> {
+    // Recorded before the files are read, so none is for a larger tree.
+    let mut roots =
+        recorded_roots(&state.http, state.anchor_api.as_deref(), &state.data_dir).await?;
+    let (stored, served) = {
+        let log = state.log.lock().await;
+        (log.stored().context("read the log's files")?, log.root())
+    };
+    roots.push(RecordedRoot {
+        source: "the served tree".into(),
+        size: served.size,
+        root: served.root,
+    });
+    Ok(tokio::task::spawn_blocking(move || stored.replay(&roots)).await?)
+}
+
+/// Replay the log every `every` in the background.
+pub fn spawn_verifier(state: AppState, every: Duration) {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(every);
+        loop {
+            ticks.tick().await;
+            match verify(&state).await {
+                Ok(Replay {
+                    divergence: Some(divergence),
+                    ..
+                }) => error!(index = divergence.index(), %divergence, "storage diverges"),
+                Ok(replay) => info!(
+                    size = replay.size,
+                    matched = replay.matched,
+                    "verified storage"
+                ),
+                Err(err) => error!(?err, "failed to verify storage"),
+            }
+        }
+    });
+}
+
+/// What `verify-storage` prints for a replay, or the error it exits with.
+pub fn report(replay: &Replay) -> anyhow::Result<String> {
+    if let Some(divergence) = &replay.divergence {
+        anyhow::bail!(
+            "storage diverges at index {}: {divergence}",
+            divergence.index()
+        );
+    }
+    let mut report = format!(
+        "verified {} entries with root {}; {} recorded roots agree",
+        replay.size, replay.root, replay.matched
+    );
+    if replay.unopened > 0 {
+        report.push_str(&format!(
+            "\n{} sealed payloads were not opened; set REALITY_LOG_KMS to check them",
+            replay.unopened
+        ));
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use reality_core::{
+        checkpoint::Checkpoint, signer::KeyFileSigner, AnchorRecord, AnchorStatus, StoredAnchor,
+    };
+    use reality_log::{replay::Stored, Log};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn replays_against_witnesses_and_anchors() {
+        let dir = std::env::temp_dir().join(format!("logd-storage-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut log = Log::open(&dir).unwrap();
+        for payload in ["a", "b", "c"] {
+            log.append(payload).unwrap();
+        }
+        let head = log.root_at(2).unwrap();
+        let cosigned = Checkpoint {
+            size: head.size,
+            root: head.root.clone(),
+            timestamp_nanos: "1".into(),
+        }
+        .cosign(&KeyFileSigner::from_seed([5; 32]))
+        .unwrap();
+        crate::write_json(dir.join("witnesses.json"), &vec![cosigned])
+            .await
+            .unwrap();
+        let anchor = AnchorRecord {
+            version: 2,
+            root: log.root().root,
+            size: 3,
+            timestamp_nanos: "1".into(),
+            txid: "tx1".into(),
+            metadata: Default::default(),
+        };
+        // The anchor daemon's `/anchors`.
+        let anchors = vec![StoredAnchor {
+            record: anchor,
+            status: AnchorStatus::Confirmed,
+            signature: None,
+        }];
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let anchor_api = format!("http://{}", listener.local_addr().unwrap());
+        let app = axum::Router::new().route(
+            "/anchors",
+            axum::routing::get(move || async move { axum::Json(anchors) }),
+        );
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let http = reqwest::Client::new();
+        assert_eq!(recorded_roots(&http, None, &dir).await.unwrap().len(), 1);
+        let roots = recorded_roots(&http, Some(&anchor_api), &dir)
+            .await
+            .unwrap();
+        assert_eq!(roots.len(), 2);
+        assert_eq!(roots[1].source, "anchor tx1");
+        let replay = Stored::read(&dir, None).unwrap().replay(&roots);
+        assert!(report(&replay)
+            .unwrap()
+            .starts_with("verified 3 entries with root"));
+
+        let mut forged = roots.clone();
+        forged[0].root = forged[1].root.clone();
+        let err = report(&Stored::read(&dir, None).unwrap().replay(&forged)).unwrap_err();
+        assert!(err.to_string().starts_with("storage diverges at index 0:"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
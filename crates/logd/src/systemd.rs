@@ -0,0 +1,155 @@
+//! systemd integration without libsystemd. With socket activation systemd
+//! binds the port and hands logd the listening socket, so restarts never
+//! refuse connections; `sd_notify` messages tell systemd when logd is ready,
+//! when it is stopping, and that it is still alive. Outside systemd the
+//! variables these read are unset and nothing here does anything.
+
+use std::{
+    env, io,
+    net::TcpListener,
+    os::{fd::FromRawFd, unix::net::UnixDatagram},
+    time::Duration,
+};
+
+use anyhow::Context;
+use tracing::warn;
+
+/// The first descriptor systemd passes (`SD_LISTEN_FDS_START`).
+const LISTEN_FDS_START: i32 = 3;
+
+/// The listening socket systemd passed in, if logd was socket-activated.
+pub fn listener() -> anyhow::Result<Option<TcpListener>> {
+    let passed = passed_fds(
+        env::var("LISTEN_PID").ok().as_deref(),
+        env::var("LISTEN_FDS").ok().as_deref(),
+        std::process::id(),
+    );
+    if passed == 0 {
+        return Ok(None);
+    }
+    if passed > 1 {
+        warn!(
+            passed,
+            "systemd passed several sockets; serving on the first"
+        );
+    }
+    // SAFETY: systemd passes `LISTEN_FDS` open descriptors starting at fd 3
+    // to the process named by `LISTEN_PID`, which was checked to be this
+    // one, and nothing else in logd takes ownership of fd 3.
+    let listener = unsafe { TcpListener::from_raw_fd(LISTEN_FDS_START) };
+    listener
+        .local_addr()
+        .context("the socket systemd passed is not a bound TCP socket")?;
+    listener.set_nonblocking(true)?;
+    Ok(Some(listener))
+}
+
+/// How many sockets systemd passed this process, from `LISTEN_PID` and
+/// `LISTEN_FDS`. Variables meant for another process count as none.
+fn passed_fds(pid: Option<&str>, fds: Option<&str>, own: u32) -> usize {
+    match (pid.and_then(|pid| pid.parse::<u32>().ok()), fds) {
+        (Some(pid), Some(fds)) if pid == own => fds.parse().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// Send `state` (such as `READY=1`) to systemd, if it is listening.
+pub fn notify(state: &str) {
+    let Ok(socket) = env::var("NOTIFY_SOCKET") else {
+        return;
+    };
+    if let Err(err) = send(&socket, state) {
+        warn!(?err, state, "failed to notify systemd");
+    }
+}
+
+fn send(socket: &str, state: &str) -> io::Result<()> {
+    let datagram = UnixDatagram::unbound()?;
+    match socket.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::{linux::net::SocketAddrExt, unix::net::SocketAddr};
+            let addr = SocketAddr::from_abstract_name(name)?;
+            datagram.send_to_addr(state.as_bytes(), &addr)?;
+        }
+        #[cfg(not(target_os = "linux"))]
+        Some(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "abstract sockets need Linux",
+            ))
+        }
+        None => {
+            datagram.send_to(state.as_bytes(), socket)?;
+        }
+    }
+    Ok(())
+}
+
+/// Keep systemd's watchdog fed while the runtime is responsive: a stalled
+/// runtime stops the pings and systemd restarts logd.
+pub fn spawn_watchdog() {
+    let Some(every) = watchdog_interval(
+        env::var("WATCHDOG_USEC").ok().as_deref(),
+        env::var("WATCHDOG_PID").ok().as_deref(),
+        std::process::id(),
+    ) else {
+        return;
+    };
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(every);
+        loop {
+            ticks.tick().await;
+            notify("WATCHDOG=1");
+        }
+    });
+}
+
+/// How often to ping: half of `WATCHDOG_USEC`, as sd_watchdog_enabled(3)
+/// recommends, if the watchdog is on for this process.
+fn watchdog_interval(usec: Option<&str>, pid: Option<&str>, own: u32) -> Option<Duration> {
+    if let Some(pid) = pid {
+        if pid.parse::<u32>().ok()? != own {
+            return None;
+        }
+    }
+    let usec: u64 = usec?.parse().ok().filter(|&usec| usec > 0)?;
+    Some(Duration::from_micros(usec / 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_environment_and_notifies() {
+        assert_eq!(passed_fds(Some("42"), Some("1"), 42), 1);
+        assert_eq!(passed_fds(Some("41"), Some("1"), 42), 0);
+        assert_eq!(passed_fds(None, Some("2"), 42), 0);
+        assert_eq!(passed_fds(Some("42"), Some("x"), 42), 0);
+
+        assert_eq!(
+            watchdog_interval(Some("30000000"), None, 42),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(
+            watchdog_interval(Some("30000000"), Some("42"), 42),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(watchdog_interval(Some("30000000"), Some("7"), 42), None);
+        assert_eq!(watchdog_interval(Some("0"), None, 42), None);
+        assert_eq!(watchdog_interval(None, None, 42), None);
+
+        let dir = std::env::temp_dir().join(format!("logd-notify-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notify");
+        let systemd = UnixDatagram::bind(&path).unwrap();
+        send(path.to_str().unwrap(), "READY=1").unwrap();
+        let mut buf = [0u8; 64];
+        let n = systemd.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"READY=1");
+        assert!(send(dir.join("missing").to_str().unwrap(), "READY=1").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
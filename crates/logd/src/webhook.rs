@@ -0,0 +1,358 @@
+//! Signed third-party webhooks. Each configured source names a provider
+//! whose HMAC signature scheme is checked before the body is appended, so
+//! the log only ever holds deliveries that came from the secret's owner.
+
+use std::{collections::HashMap, path::Path};
+
+use anyhow::Context;
+use axum::http::HeaderMap;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hmac::{Hmac, Mac};
+use reality_core::{leaf_hash, BatchEntry};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+/// Stripe's default tolerance for the signed timestamp.
+const STRIPE_TOLERANCE_SECS: u64 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Provider {
+    /// `X-Hub-Signature-256: sha256=<hex>` over the body.
+    Github,
+    /// `Stripe-Signature: t=<unix>,v1=<hex>` over `<t>.<body>`.
+    Stripe,
+    /// A hex HMAC-SHA256 of the body in a configurable header, optionally
+    /// prefixed with `sha256=`.
+    HmacSha256,
+}
+
+/// One entry of the `REALITY_LOG_WEBHOOKS` file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SourceConfig {
+    name: String,
+    provider: Provider,
+    secret: Option<String>,
+    /// Environment variable holding the secret, instead of `secret`.
+    secret_env: Option<String>,
+    /// Signature header for `hmac-sha256`.
+    header: Option<String>,
+    /// Header carrying a delivery id for `hmac-sha256`.
+    delivery_header: Option<String>,
+    /// Largest accepted age of a Stripe signature, in seconds.
+    tolerance_secs: Option<u64>,
+}
+
+/// A configured webhook source with its secret resolved.
+pub struct Source {
+    name: String,
+    provider: Provider,
+    secret: Vec<u8>,
+    header: String,
+    delivery_header: Option<String>,
+    tolerance_secs: u64,
+}
+
+/// All configured sources, by name.
+#[derive(Default)]
+pub struct Webhooks {
+    sources: HashMap<String, Source>,
+}
+
+impl Webhooks {
+    /// Read the JSON array of sources at `path`.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("read webhooks {}", path.display()))?;
+        let configs: Vec<SourceConfig> = serde_json::from_str(&raw)
+            .with_context(|| format!("parse webhooks {}", path.display()))?;
+        let mut sources = HashMap::new();
+        for config in configs {
+            let source = Source::new(config)?;
+            anyhow::ensure!(
+                !sources.contains_key(&source.name),
+                "webhook source {} is configured twice",
+                source.name
+            );
+            sources.insert(source.name.clone(), source);
+        }
+        Ok(Self { sources })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Source> {
+        self.sources.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.sources.keys().map(String::as_str)
+    }
+}
+
+/// Why a delivery was refused.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Rejection {
+    MissingSignature(String),
+    MalformedSignature,
+    BadSignature,
+    Expired,
+}
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingSignature(header) => write!(f, "missing {header} header"),
+            Self::MalformedSignature => f.write_str("malformed signature header"),
+            Self::BadSignature => f.write_str("signature does not match"),
+            Self::Expired => f.write_str("signature timestamp is outside the tolerance"),
+        }
+    }
+}
+
+/// The appended payload. `body` holds the raw body when it is UTF-8, and
+/// `body_base64` otherwise. Nothing in it varies between redeliveries of
+/// one event, so a redelivery is reported as a duplicate.
+#[derive(Serialize)]
+struct Delivery<'a> {
+    source: &'static str,
+    webhook: &'a str,
+    provider: Provider,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    event: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    delivery: Option<String>,
+    verification: Verification<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_type: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body_base64: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Verification<'a> {
+    algorithm: &'static str,
+    header: &'a str,
+}
+
+impl Source {
+    fn new(config: SourceConfig) -> anyhow::Result<Self> {
+        let secret = match (config.secret, config.secret_env) {
+            (Some(secret), None) => secret,
+            (None, Some(var)) => std::env::var(&var)
+                .with_context(|| format!("webhook {}: read secret from {var}", config.name))?,
+            _ => anyhow::bail!(
+                "webhook {}: set exactly one of secret and secret_env",
+                config.name
+            ),
+        };
+        anyhow::ensure!(
+            !secret.is_empty(),
+            "webhook {}: secret is empty",
+            config.name
+        );
+        anyhow::ensure!(
+            config.provider == Provider::HmacSha256
+                || (config.header.is_none() && config.delivery_header.is_none()),
+            "webhook {}: header and delivery_header only apply to hmac-sha256",
+            config.name
+        );
+        let header = match config.provider {
+            Provider::Github => "X-Hub-Signature-256".to_string(),
+            Provider::Stripe => "Stripe-Signature".to_string(),
+            Provider::HmacSha256 => config.header.unwrap_or_else(|| "X-Signature".into()),
+        };
+        Ok(Self {
+            name: config.name,
+            provider: config.provider,
+            secret: secret.into_bytes(),
+            header,
+            delivery_header: config.delivery_header,
+            tolerance_secs: config.tolerance_secs.unwrap_or(STRIPE_TOLERANCE_SECS),
+        })
+    }
+
+    /// Check the delivery's signature; `now` is the current Unix time.
+    pub fn verify(&self, headers: &HeaderMap, body: &[u8], now: i64) -> Result<(), Rejection> {
+        let value = headers
+            .get(&self.header)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| Rejection::MissingSignature(self.header.clone()))?;
+        match self.provider {
+            Provider::Github => {
+                let hex = value
+                    .strip_prefix("sha256=")
+                    .ok_or(Rejection::MalformedSignature)?;
+                self.check(&[body], hex)
+            }
+            Provider::HmacSha256 => self.check(&[body], value.trim_start_matches("sha256=")),
+            Provider::Stripe => {
+                let mut timestamp = None;
+                let mut signatures = Vec::new();
+                for part in value.split(',') {
+                    match part.trim().split_once('=') {
+                        Some(("t", t)) => timestamp = t.parse::<i64>().ok(),
+                        Some(("v1", sig)) => signatures.push(sig),
+                        _ => {}
+                    }
+                }
+                let timestamp = timestamp.ok_or(Rejection::MalformedSignature)?;
+                if signatures.is_empty() {
+                    return Err(Rejection::MalformedSignature);
+                }
+                let t = timestamp.to_string();
+                // Stripe lists one signature per active secret during a roll.
+                if !signatures
+                    .iter()
+                    .any(|sig| self.check(&[t.as_bytes(), b".", body], sig).is_ok())
+                {
+                    return Err(Rejection::BadSignature);
+                }
+                if now.abs_diff(timestamp) > self.tolerance_secs {
+                    return Err(Rejection::Expired);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Compare in constant time against the HMAC of `parts` concatenated.
+    fn check(&self, parts: &[&[u8]], hex_signature: &str) -> Result<(), Rejection> {
+        let expected = hex::decode(hex_signature).map_err(|_| Rejection::MalformedSignature)?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret).expect("any key length");
+        for part in parts {
+            mac.update(part);
+        }
+        mac.verify_slice(&expected)
+            .map_err(|_| Rejection::BadSignature)
+    }
+
+    /// The entry to append for a verified delivery. Its idempotency key is
+    /// the provider's delivery id when there is one and the leaf hash
+    /// otherwise.
+    pub fn entry(&self, headers: &HeaderMap, body: &[u8]) -> BatchEntry {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let (event, delivery) = match self.provider {
+            Provider::Github => (header("X-GitHub-Event"), header("X-GitHub-Delivery")),
+            Provider::Stripe => {
+                let field = |value: &serde_json::Value, name: &str| {
+                    value.get(name)?.as_str().map(str::to_string)
+                };
+                match serde_json::from_slice::<serde_json::Value>(body) {
+                    Ok(value) => (field(&value, "type"), field(&value, "id")),
+                    Err(_) => (None, None),
+                }
+            }
+            Provider::HmacSha256 => (None, self.delivery_header.as_deref().and_then(header)),
+        };
+        let text = std::str::from_utf8(body).ok();
+        let payload = serde_json::to_string(&Delivery {
+            source: "webhook",
+            webhook: &self.name,
+            provider: self.provider,
+            event,
+            delivery: delivery.clone(),
+            verification: Verification {
+                algorithm: "hmac-sha256",
+                header: &self.header,
+            },
+            content_type: headers
+                .get("content-type")
+                .and_then(|value| value.to_str().ok()),
+            body: text,
+            body_base64: text.is_none().then(|| STANDARD.encode(body)),
+        })
+        .expect("delivery serializes");
+        let id = delivery.unwrap_or_else(|| hex::encode(leaf_hash(payload.as_bytes())));
+        BatchEntry {
+            idempotency_key: Some(format!("webhook:{}:{id}", self.name)),
+            payload,
+            labels: Default::default(),
+            signature: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn source(provider: Provider) -> Source {
+        Source::new(SourceConfig {
+            name: "test".into(),
+            provider,
+            secret: Some("It's a Secret to Everybody".into()),
+            secret_env: None,
+            header: None,
+            delivery_header: None,
+            tolerance_secs: None,
+        })
+        .unwrap()
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn checks_provider_signatures() {
+        // The example from GitHub's webhook documentation.
+        let github = source(Provider::Github);
+        let body = b"Hello, World!";
+        let signed = headers(&[
+            (
+                "x-hub-signature-256",
+                "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17",
+            ),
+            ("x-github-event", "push"),
+            ("x-github-delivery", "72d3162e-cc78-11e3-81ab-4c9367dc0958"),
+        ]);
+        assert_eq!(github.verify(&signed, body, 0), Ok(()));
+        assert_eq!(
+            github.verify(&signed, b"Hello, World?", 0),
+            Err(Rejection::BadSignature)
+        );
+        assert_eq!(
+            github.verify(&HeaderMap::new(), body, 0),
+            Err(Rejection::MissingSignature("X-Hub-Signature-256".into()))
+        );
+        let entry = github.entry(&signed, body);
+        assert_eq!(
+            entry.idempotency_key.as_deref(),
+            Some("webhook:test:72d3162e-cc78-11e3-81ab-4c9367dc0958")
+        );
+        let payload: serde_json::Value = serde_json::from_str(&entry.payload).unwrap();
+        assert_eq!(payload["event"], "push");
+        assert_eq!(payload["body"], "Hello, World!");
+
+        let stripe = source(Provider::Stripe);
+        let body = br#"{"id":"evt_1","type":"charge.succeeded"}"#;
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"It's a Secret to Everybody").unwrap();
+        mac.update(b"1700000000.");
+        mac.update(body);
+        let sig = hex::encode(mac.finalize().into_bytes());
+        let signed = headers(&[(
+            "stripe-signature",
+            &format!("t=1700000000,v1={},v1={sig},v0=00", "ab".repeat(32)),
+        )]);
+        assert_eq!(stripe.verify(&signed, body, 1_700_000_100), Ok(()));
+        assert_eq!(
+            stripe.verify(&signed, body, 1_700_000_301),
+            Err(Rejection::Expired)
+        );
+        let entry = stripe.entry(&signed, body);
+        assert_eq!(entry.idempotency_key.as_deref(), Some("webhook:test:evt_1"));
+    }
+}
@@ -0,0 +1,260 @@
+//! UniFFI bindings for verifying RealityLog receipts on Android and iOS.
+//!
+//! The generated Kotlin package is `io.realitylog`, and the Swift module is
+//! `RealityLog`. Proofs, checkpoints, bundles and receipts are passed as the
+//! JSON text of the logd or anchor response. Binary proofs are passed as
+//! bytes. Each `verify_*` function returns normally when the check passes.
+//! Otherwise it throws `VerificationError.Invalid` with the reason, or
+//! `VerificationError.Malformed` for input it could not evaluate. Kotlin
+//! names the error `VerificationException`.
+
+use reality_core::{
+    algorithm::HashAlgorithm,
+    bundle::{BundleError, EntryBundle},
+    checkpoint::SignedCheckpoint,
+    consistency::{self, ConsistencyProof},
+    receipt::{AnchorReceipt, ReceiptContext, ReceiptError},
+    verify, InclusionProof, VerifyRequest,
+};
+use serde::de::DeserializeOwned;
+
+uniffi::setup_scaffolding!();
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error, uniffi::Error)]
+pub enum VerificationError {
+    /// The input could not be evaluated, e.g. missing fields or bad hex.
+    #[error("malformed input: {reason}")]
+    Malformed { reason: String },
+    /// The input was evaluated and is not valid.
+    #[error("verification failed: {reason}")]
+    Invalid { reason: String },
+}
+
+impl VerificationError {
+    fn malformed(reason: impl Into<String>) -> Self {
+        Self::Malformed {
+            reason: reason.into(),
+        }
+    }
+
+    fn invalid(reason: impl Into<String>) -> Self {
+        Self::Invalid {
+            reason: reason.into(),
+        }
+    }
+}
+
+impl From<BundleError> for VerificationError {
+    fn from(err: BundleError) -> Self {
+        Self::invalid(err.to_string())
+    }
+}
+
+impl From<ReceiptError> for VerificationError {
+    fn from(err: ReceiptError) -> Self {
+        Self::invalid(err.to_string())
+    }
+}
+
+type Checked<T = ()> = Result<T, VerificationError>;
+
+/// The tree head a verified checkpoint commits to.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct TreeHead {
+    pub size: u64,
+    pub root: String,
+    pub timestamp_nanos: String,
+}
+
+/// What an anchor receipt check could establish offline.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct AnchorReport {
+    /// False for backends whose transactions cannot be recomputed offline.
+    pub txid_checked: bool,
+    pub signature_checked: bool,
+}
+
+/// Hex leaf hash of a payload, as logd computes it on append. `algorithm`
+/// is `"sha256"` (the default), `"sha3-256"`, `"blake3"` or `"sha512-256"`.
+#[uniffi::export(default(algorithm = None))]
+pub fn leaf_hash(payload: Vec<u8>, algorithm: Option<String>) -> Checked<String> {
+    Ok(hex::encode(parse_algorithm(algorithm)?.leaf_hash(&payload)))
+}
+
+/// Verify an inclusion proof from `/prove/N`: the leaf and path must lead to
+/// the proof's root. Compare that root with a verified checkpoint.
+#[uniffi::export]
+pub fn verify_inclusion(proof_json: String) -> Checked {
+    let request: VerifyRequest = parse(&proof_json, "proof")?;
+    let response = verify(&request);
+    if response.valid {
+        Ok(())
+    } else if response.computed_root.is_empty() {
+        Err(VerificationError::malformed(
+            "proof contains a malformed hash",
+        ))
+    } else {
+        Err(VerificationError::invalid(format!(
+            "proof leads to {}, not {}",
+            response.computed_root, response.expected_root
+        )))
+    }
+}
+
+/// Verify a proof in the binary encoding served by `/prove/N/bin`.
+#[uniffi::export]
+pub fn verify_inclusion_bytes(proof: Vec<u8>) -> Checked {
+    let proof = InclusionProof::from_bytes(&proof)
+        .map_err(|err| VerificationError::malformed(format!("invalid binary proof: {err}")))?;
+    verify_inclusion(serde_json::to_string(&proof).expect("proof serializes"))
+}
+
+/// Verify a consistency proof from `/consistency`. Pass the roots you trust
+/// for the old and new sizes to require that the proof links exactly those.
+#[uniffi::export(default(old_root = None, new_root = None))]
+pub fn verify_consistency(
+    proof_json: String,
+    old_root: Option<String>,
+    new_root: Option<String>,
+) -> Checked {
+    let proof: ConsistencyProof = parse(&proof_json, "consistency proof")?;
+    for (expected, actual, which) in [
+        (old_root, &proof.old_root, "old"),
+        (new_root, &proof.new_root, "new"),
+    ] {
+        if let Some(expected) = expected {
+            if !expected.eq_ignore_ascii_case(actual) {
+                return Err(VerificationError::invalid(format!(
+                    "proof's {which} root {actual} is not the expected {expected}"
+                )));
+            }
+        }
+    }
+    if !consistency::verify_consistency(&proof) {
+        return Err(VerificationError::invalid(format!(
+            "proof does not link size {} to size {}",
+            proof.old_size, proof.new_size
+        )));
+    }
+    Ok(())
+}
+
+/// Verify a signed checkpoint from `/checkpoint` against the log's hex
+/// ed25519 key, and return the tree head it commits to.
+#[uniffi::export]
+pub fn verify_checkpoint(checkpoint_json: String, public_key: String) -> Checked<TreeHead> {
+    let signed: SignedCheckpoint = parse(&checkpoint_json, "checkpoint")?;
+    if !signed
+        .signature
+        .public_key
+        .eq_ignore_ascii_case(&public_key)
+    {
+        return Err(VerificationError::invalid(format!(
+            "checkpoint is signed by {}, not {public_key}",
+            signed.signature.public_key
+        )));
+    }
+    if !signed.verify() {
+        return Err(VerificationError::invalid(
+            "checkpoint signature is invalid",
+        ));
+    }
+    let checkpoint = signed.checkpoint;
+    Ok(TreeHead {
+        size: checkpoint.size,
+        root: checkpoint.root,
+        timestamp_nanos: checkpoint.timestamp_nanos,
+    })
+}
+
+/// Verify an `EntryBundle` from `/bundle/N` end to end: payload → leaf →
+/// root → checkpoint → signature. Pass the log's key as `trusted_key` to
+/// also require that it signed the checkpoint.
+#[uniffi::export(default(trusted_key = None))]
+pub fn verify_bundle(bundle_json: String, trusted_key: Option<String>) -> Checked {
+    let bundle: EntryBundle = parse(&bundle_json, "bundle")?;
+    Ok(bundle.verify(trusted_key.as_deref())?)
+}
+
+/// Verify an anchor receipt from the anchor API's `/receipt/<txid>`.
+/// `context_json` may add a proof ending at the anchored root, the previous
+/// anchor with a consistency proof, and the required `anchor_key`.
+#[uniffi::export(default(context_json = None))]
+pub fn verify_anchor(receipt_json: String, context_json: Option<String>) -> Checked<AnchorReport> {
+    let receipt: AnchorReceipt = parse(&receipt_json, "receipt")?;
+    let context = match context_json {
+        Some(context) => parse(&context, "context")?,
+        None => ReceiptContext::default(),
+    };
+    let report = receipt.verify(&context)?;
+    Ok(AnchorReport {
+        txid_checked: report.txid_checked,
+        signature_checked: report.signature_checked,
+    })
+}
+
+fn parse<T: DeserializeOwned>(json: &str, what: &str) -> Checked<T> {
+    serde_json::from_str(json)
+        .map_err(|err| VerificationError::malformed(format!("invalid {what}: {err}")))
+}
+
+fn parse_algorithm(name: Option<String>) -> Checked<HashAlgorithm> {
+    name.map_or(Ok(HashAlgorithm::default()), |name| {
+        name.parse().map_err(VerificationError::malformed)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use reality_core::{checkpoint::Checkpoint, make_proof_with, signer::KeyFileSigner};
+
+    use super::*;
+
+    #[test]
+    fn verifies_a_bundle_the_way_an_app_would() {
+        let leaves: Vec<[u8; 32]> = ["a", "b", "c"]
+            .iter()
+            .map(|p| HashAlgorithm::Sha256.leaf_hash(p.as_bytes()))
+            .collect();
+        assert_eq!(
+            leaf_hash(b"c".to_vec(), None).unwrap(),
+            hex::encode(leaves[2])
+        );
+        assert!(matches!(
+            leaf_hash(Vec::new(), Some("md5".into())),
+            Err(VerificationError::Malformed { .. })
+        ));
+
+        let proof = make_proof_with(HashAlgorithm::Sha256, &leaves, 2).unwrap();
+        verify_inclusion_bytes(proof.to_bytes().unwrap()).unwrap();
+
+        let signer = KeyFileSigner::from_seed([4u8; 32]);
+        let signed = Checkpoint {
+            size: 3,
+            root: proof.root.clone(),
+            timestamp_nanos: "1".into(),
+        }
+        .sign(&signer)
+        .unwrap();
+        let key = signed.signature.public_key.clone();
+        let head = verify_checkpoint(serde_json::to_string(&signed).unwrap(), key.clone()).unwrap();
+        assert_eq!((head.size, head.root.as_str()), (3, proof.root.as_str()));
+
+        let bundle = serde_json::to_string(&EntryBundle {
+            payload: "c".into(),
+            meta: None,
+            proof,
+            checkpoint: signed,
+        })
+        .unwrap();
+        verify_bundle(bundle.clone(), Some(key)).unwrap();
+        assert!(matches!(
+            verify_bundle(bundle.replace("\"c\"", "\"d\""), None),
+            Err(VerificationError::Invalid { .. })
+        ));
+        assert!(matches!(
+            verify_inclusion(r#"{"index": 0}"#.into()),
+            Err(VerificationError::Malformed { .. })
+        ));
+    }
+}
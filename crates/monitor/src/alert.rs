@@ -0,0 +1,61 @@
+use reality_client::TrustedHead;
+use reqwest::Client;
+use serde::Serialize;
+use tracing::{error, warn};
+
+use crate::config::AlertConfig;
+
+/// Payload POSTed to every alert webhook.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Alert {
+    /// Consecutive failed polls of a log reached the configured threshold.
+    Unreachable {
+        log: String,
+        consecutive_failures: u32,
+        last_error: String,
+    },
+    /// A poll succeeded again after `Unreachable` fired.
+    Recovered { log: String, failed_rounds: u32 },
+    /// The log served a head that does not extend its verified history, or a
+    /// checkpoint not validly signed by its key; fired immediately.
+    Inconsistent {
+        log: String,
+        trusted: Option<Box<TrustedHead>>,
+        observed: Option<Box<TrustedHead>>,
+        reason: String,
+    },
+}
+
+pub struct Alerter {
+    config: AlertConfig,
+    client: Client,
+}
+
+impl Alerter {
+    pub fn new(config: AlertConfig, client: Client) -> Self {
+        Self { config, client }
+    }
+
+    pub fn threshold(&self) -> u32 {
+        self.config.failure_threshold
+    }
+
+    /// Deliver `alert` to every configured webhook. Delivery failures are
+    /// logged; they never stop the monitoring loop.
+    pub async fn fire(&self, alert: &Alert) {
+        error!(?alert, "ALERT");
+        for url in &self.config.webhooks {
+            let sent = self
+                .client
+                .post(url)
+                .json(alert)
+                .send()
+                .await
+                .and_then(|resp| resp.error_for_status());
+            if let Err(err) = sent {
+                warn!(?err, %url, "failed to deliver alert");
+            }
+        }
+    }
+}
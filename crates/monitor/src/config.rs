@@ -0,0 +1,271 @@
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
+
+use anyhow::Context;
+use clap::Parser;
+use reality_config::Layered;
+use serde::Deserialize;
+
+/// Command-line flags. Anything left unset falls back to the config file and
+/// then to defaults.
+#[derive(Debug, Parser)]
+#[command(
+    name = "reality-monitor",
+    version,
+    about = "Checks that RealityLog logs only ever grow"
+)]
+pub struct Cli {
+    /// Path to a TOML config file.
+    #[arg(long, env = "REALITY_MONITOR_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Log to monitor as `name=url` (repeatable).
+    #[arg(long = "log", value_parser = parse_log)]
+    pub logs: Vec<LogConfig>,
+    /// Directory holding the monitor store.
+    #[arg(long, env = "REALITY_MONITOR_DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
+    /// Seconds between polls of each log.
+    #[arg(long, env = "REALITY_MONITOR_INTERVAL_SECS")]
+    pub interval_secs: Option<u64>,
+    /// Address for the status and metrics API.
+    #[arg(long, env = "REALITY_MONITOR_LISTEN")]
+    pub listen: Option<SocketAddr>,
+    /// Consecutive failed polls of a log before alerts fire.
+    #[arg(long)]
+    pub alert_threshold: Option<u32>,
+    /// Webhook URLs that receive alerts (repeatable).
+    #[arg(long = "alert-webhook")]
+    pub alert_webhooks: Vec<String>,
+    /// Key that signs audit reports: `file:<path>` with a hex ed25519 seed,
+    /// or a PKCS#11 URI. Setting it turns reports on.
+    #[arg(long, env = "REALITY_MONITOR_REPORT_SIGNER", hide_env_values = true)]
+    pub report_signer: Option<String>,
+    /// Seconds each audit report covers.
+    #[arg(long)]
+    pub report_interval_secs: Option<u64>,
+}
+
+/// Fully resolved monitor configuration.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub data_dir: PathBuf,
+    pub interval_secs: u64,
+    pub listen: SocketAddr,
+    pub alerts: AlertConfig,
+    pub logs: Vec<LogConfig>,
+    /// Signed periodic audit reports; off unless configured.
+    pub report: Option<ReportConfig>,
+}
+
+/// One log to follow.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct LogConfig {
+    /// Stable name; the stored history is keyed by it.
+    pub name: String,
+    pub url: String,
+    /// Hex ed25519 key the log signs checkpoints with. Without it the monitor
+    /// follows the unsigned `/root`.
+    #[serde(default)]
+    pub log_public_key: Option<String>,
+    /// File containing a bearer token for this log's API.
+    #[serde(default)]
+    pub api_token_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertConfig {
+    /// Consecutive failed polls of a log before an alert fires.
+    /// Inconsistencies alert immediately.
+    pub failure_threshold: u32,
+    /// URLs that receive alert events as JSON POSTs.
+    pub webhooks: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReportConfig {
+    /// Key that signs each report: `file:<path>` or a PKCS#11 URI.
+    pub signer: String,
+    /// Seconds each report covers.
+    pub interval_secs: u64,
+    /// Where reports are written; defaults to `reports` in the data dir.
+    pub dir: Option<PathBuf>,
+    /// Hex key every anchor must be signed by.
+    pub anchor_key: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            data_dir: PathBuf::from("data"),
+            interval_secs: 30,
+            listen: SocketAddr::from(([127, 0, 0, 1], 8082)),
+            alerts: AlertConfig::default(),
+            logs: Vec::new(),
+            report: None,
+        }
+    }
+}
+
+impl Default for ReportConfig {
+    fn default() -> Self {
+        Self {
+            signer: String::new(),
+            interval_secs: 86_400,
+            dir: None,
+            anchor_key: None,
+        }
+    }
+}
+
+impl ReportConfig {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            webhooks: Vec::new(),
+        }
+    }
+}
+
+impl LogConfig {
+    pub fn api_token(&self) -> anyhow::Result<Option<String>> {
+        let Some(path) = &self.api_token_file else {
+            return Ok(None);
+        };
+        let token = std::fs::read_to_string(path)
+            .with_context(|| format!("read token file {}", path.display()))?;
+        Ok(Some(token.trim().to_string()))
+    }
+}
+
+impl Layered<Cli> for Config {
+    fn file(cli: &Cli) -> Option<PathBuf> {
+        cli.config.clone()
+    }
+
+    fn apply(&mut self, cli: Cli) -> anyhow::Result<()> {
+        self.logs.extend(cli.logs);
+        if let Some(data_dir) = cli.data_dir {
+            self.data_dir = data_dir;
+        }
+        if let Some(interval) = cli.interval_secs {
+            self.interval_secs = interval;
+        }
+        if let Some(listen) = cli.listen {
+            self.listen = listen;
+        }
+        if let Some(threshold) = cli.alert_threshold {
+            self.alerts.failure_threshold = threshold;
+        }
+        self.alerts.webhooks.extend(cli.alert_webhooks);
+        if let Some(signer) = cli.report_signer {
+            self.report.get_or_insert_with(ReportConfig::default).signer = signer;
+        }
+        if let Some(interval) = cli.report_interval_secs {
+            self.report
+                .get_or_insert_with(ReportConfig::default)
+                .interval_secs = interval;
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(self.interval_secs > 0, "interval_secs must be positive");
+        anyhow::ensure!(
+            self.alerts.failure_threshold > 0,
+            "alerts.failure_threshold must be positive"
+        );
+        anyhow::ensure!(!self.logs.is_empty(), "at least one log is required");
+        for (i, log) in self.logs.iter().enumerate() {
+            anyhow::ensure!(
+                !self.logs[..i].iter().any(|other| other.name == log.name),
+                "log name {} is used twice",
+                log.name
+            );
+        }
+        if let Some(report) = &self.report {
+            anyhow::ensure!(!report.signer.is_empty(), "report.signer is required");
+            anyhow::ensure!(
+                report.interval_secs > 0,
+                "report.interval_secs must be positive"
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Config {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+}
+
+fn parse_log(value: &str) -> Result<LogConfig, String> {
+    let (name, url) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected name=url, got {value}"))?;
+    Ok(LogConfig {
+        name: name.to_string(),
+        url: url.to_string(),
+        log_public_key: None,
+        api_token_file: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_add_to_the_config_file() {
+        let mut config: Config = toml::from_str(
+            r#"
+            interval_secs = 10
+
+            [[logs]]
+            name = "prod"
+            url = "https://log.example"
+            log_public_key = "ab"
+            "#,
+        )
+        .expect("config");
+        let cli = Cli::parse_from([
+            "reality-monitor",
+            "--log",
+            "staging=http://127.0.0.1:8080",
+            "--alert-webhook",
+            "https://hooks.example",
+        ]);
+        config.apply(cli).unwrap();
+
+        assert_eq!(config.interval_secs, 10);
+        assert_eq!(config.logs.len(), 2);
+        assert_eq!(config.logs[1].name, "staging");
+        assert_eq!(config.alerts.webhooks, ["https://hooks.example"]);
+        assert!(config.validate().is_ok());
+
+        config.logs[1].name = "prod".into();
+        assert!(config.validate().is_err());
+        config.logs[1].name = "staging".into();
+
+        config
+            .apply(Cli::parse_from([
+                "reality-monitor",
+                "--report-interval-secs",
+                "3600",
+            ]))
+            .unwrap();
+        assert!(config.validate().is_err());
+        config.report.as_mut().unwrap().signer = "file:/etc/monitor.key".into();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.report.unwrap().interval(), Duration::from_secs(3600));
+    }
+}
@@ -0,0 +1,280 @@
+//! `reality-monitor`: follows one or more logs, checks that every new tree
+//! head extends the last one it verified, and keeps the verified history.
+//! With `[report]` configured it also writes a signed audit report for every
+//! period.
+
+mod alert;
+mod config;
+mod report;
+mod server;
+mod status;
+mod store;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Context;
+use clap::Parser;
+use reality_client::{Client, TrustedHead, VerifyingClient};
+use reality_config::Layered;
+use tokio::time::sleep;
+use tracing::{error, info, warn};
+
+use crate::{
+    alert::{Alert, Alerter},
+    config::{Cli, Config, LogConfig},
+    report::Reporter,
+    server::{ApiState, SharedStore},
+    status::{AlertTransition, MonitorStatus, SharedStatus},
+    store::{now_nanos, MonitorStore},
+};
+
+/// Result of one poll of one log.
+enum Outcome {
+    Verified(TrustedHead),
+    /// Evidence against the log: `observed` is the refused head, when it got
+    /// as far as being fetched and signature-checked.
+    Refused {
+        observed: Option<TrustedHead>,
+        reason: String,
+    },
+    Failed(String),
+}
+
+struct Watched {
+    config: LogConfig,
+    client: VerifyingClient,
+    /// Last head recorded as an incident, so a log stuck on a bad head is
+    /// reported once.
+    last_refused: Option<Option<TrustedHead>>,
+}
+
+struct Monitor {
+    logs: Vec<Watched>,
+    store: SharedStore,
+    status: SharedStatus,
+    alerter: Alerter,
+    reporter: Option<Reporter>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let config = Config::load(Cli::parse())?;
+    tokio::fs::create_dir_all(&config.data_dir)
+        .await
+        .context("create data dir")?;
+    let store = MonitorStore::open(&config.data_dir.join("monitor.sqlite3"))?;
+
+    let mut logs = Vec::new();
+    for log in &config.logs {
+        let mut builder = Client::builder(&log.url);
+        if let Some(token) = log.api_token()? {
+            builder = builder.token(token);
+        }
+        let mut client = builder.build()?.verifying(log.log_public_key.clone());
+        if let Some(head) = store.latest(&log.name)? {
+            info!(log = %log.name, size = head.size, "resuming from stored head");
+            client = client
+                .with_trusted(head)
+                .with_context(|| format!("stored head of {} no longer verifies", log.name))?;
+        }
+        logs.push(Watched {
+            config: log.clone(),
+            client,
+            last_refused: None,
+        });
+    }
+
+    let reporter = config
+        .report
+        .as_ref()
+        .map(|report| Reporter::open(report, &config.data_dir, &store, now_nanos()))
+        .transpose()?;
+
+    let status = MonitorStatus::new(
+        config
+            .logs
+            .iter()
+            .map(|log| (log.name.clone(), log.url.clone())),
+    )
+    .shared();
+    let store = Arc::new(Mutex::new(store));
+    let api_state = ApiState {
+        store: store.clone(),
+        status: status.clone(),
+        // Each poll may spend up to a few client timeouts on retries.
+        stale_after: config.interval() * 3 + Duration::from_secs(120) * logs.len() as u32,
+    };
+    let listen = config.listen;
+    tokio::spawn(async move {
+        if let Err(err) = server::serve(listen, api_state).await {
+            error!(?err, "monitor API stopped");
+        }
+    });
+
+    let mut monitor = Monitor {
+        logs,
+        store,
+        status,
+        alerter: Alerter::new(config.alerts.clone(), reqwest::Client::new()),
+        reporter,
+    };
+    info!(
+        logs = monitor.logs.len(),
+        interval_secs = config.interval_secs,
+        "monitoring"
+    );
+    loop {
+        monitor.round().await;
+        monitor.report().await;
+        sleep(config.interval()).await;
+    }
+}
+
+impl Monitor {
+    async fn round(&mut self) {
+        for i in 0..self.logs.len() {
+            let outcome = poll(&self.logs[i].client).await;
+            self.handle(i, outcome).await;
+        }
+        self.status
+            .lock()
+            .expect("status poisoned")
+            .round_finished();
+    }
+
+    /// Write the audit report once its period is over.
+    async fn report(&mut self) {
+        let Some(reporter) = &mut self.reporter else {
+            return;
+        };
+        let end = now_nanos();
+        if !reporter.due(end) {
+            return;
+        }
+        let mut audits = Vec::with_capacity(self.logs.len());
+        for log in &self.logs {
+            match reporter
+                .audit(&log.config, &log.client, &self.store, end)
+                .await
+            {
+                Ok(audit) => audits.push(audit),
+                Err(err) => {
+                    error!(?err, log = %log.config.name, "failed to audit log; retrying next round");
+                    return;
+                }
+            }
+        }
+        match reporter.write(audits, end, &self.store) {
+            Ok(path) => info!(path = %path.display(), "wrote audit report"),
+            Err(err) => error!(?err, "failed to write audit report"),
+        }
+    }
+
+    async fn handle(&mut self, i: usize, outcome: Outcome) {
+        let name = self.logs[i].config.name.clone();
+        match outcome {
+            Outcome::Verified(head) => {
+                let recorded = self
+                    .store
+                    .lock()
+                    .expect("monitor store poisoned")
+                    .record(&name, &head);
+                match recorded {
+                    Ok(true) => {
+                        info!(log = %name, size = head.size, root = %head.root, "verified new head")
+                    }
+                    Ok(false) => {}
+                    Err(err) => error!(?err, log = %name, "failed to record head"),
+                }
+                self.logs[i].last_refused = None;
+                let transition = self
+                    .status
+                    .lock()
+                    .expect("status poisoned")
+                    .verified(&name, head);
+                if let AlertTransition::Cleared { failed_rounds } = transition {
+                    self.alerter
+                        .fire(&Alert::Recovered {
+                            log: name,
+                            failed_rounds,
+                        })
+                        .await;
+                }
+            }
+            Outcome::Refused { observed, reason } => {
+                self.status
+                    .lock()
+                    .expect("status poisoned")
+                    .inconsistent(&name, reason.clone());
+                if self.logs[i].last_refused.as_ref() == Some(&observed) {
+                    return;
+                }
+                self.logs[i].last_refused = Some(observed.clone());
+                let trusted = self.logs[i].client.trusted().await;
+                if let (Some(trusted), Some(observed)) = (&trusted, &observed) {
+                    let recorded = self
+                        .store
+                        .lock()
+                        .expect("monitor store poisoned")
+                        .record_incident(&name, trusted, observed, &reason);
+                    if let Err(err) = recorded {
+                        error!(?err, log = %name, "failed to record incident");
+                    }
+                }
+                self.alerter
+                    .fire(&Alert::Inconsistent {
+                        log: name,
+                        trusted: trusted.map(Box::new),
+                        observed: observed.map(Box::new),
+                        reason,
+                    })
+                    .await;
+            }
+            Outcome::Failed(error) => {
+                warn!(log = %name, %error, "poll failed");
+                let threshold = self.alerter.threshold();
+                let transition = self.status.lock().expect("status poisoned").failed(
+                    &name,
+                    error.clone(),
+                    threshold,
+                );
+                if transition == AlertTransition::Raised {
+                    self.alerter
+                        .fire(&Alert::Unreachable {
+                            log: name,
+                            consecutive_failures: threshold,
+                            last_error: error,
+                        })
+                        .await;
+                }
+            }
+        }
+    }
+}
+
+/// Fetch the log's head and accept it only if it extends the verified one.
+async fn poll(client: &VerifyingClient) -> Outcome {
+    let served = match client.fetch_head().await {
+        Ok(served) => served,
+        Err(err) => return classify(err, None),
+    };
+    match client.advance(served.clone()).await {
+        Ok(head) => Outcome::Verified(head),
+        Err(err) => classify(err, Some(served)),
+    }
+}
+
+fn classify(err: reality_client::Error, observed: Option<TrustedHead>) -> Outcome {
+    match err {
+        reality_client::Error::Verification(err) => Outcome::Refused {
+            observed,
+            reason: err.to_string(),
+        },
+        err => Outcome::Failed(err.to_string()),
+    }
+}
@@ -0,0 +1,424 @@
+//! Signed periodic audit reports. Every `[report]` interval the monitor
+//! writes what it verified over the period as `report-<end>.json`, signed,
+//! and the same report as `report-<end>.txt` for people.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use reality_client::{Client, TrustedHead, VerifyingClient};
+use reality_core::{
+    audit::{
+        AnchorAudit, AnchorFinding, AuditReport, Discrepancy, LogAudit, SignedAuditReport,
+        AUDIT_REPORT_VERSION,
+    },
+    consistency::{verify_consistency, ConsistencyProof},
+    receipt::{AnchorReceipt, ReceiptContext},
+    signer::{open_signer, Signer},
+    AnchorQuery, AnchorRecord, AnchorStatus, RootResponse, StoredAnchor,
+};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::{
+    config::{LogConfig, ReportConfig},
+    server::SharedStore,
+    store::MonitorStore,
+};
+
+pub struct Reporter {
+    signer: Box<dyn Signer>,
+    interval_nanos: i64,
+    dir: PathBuf,
+    anchor_key: Option<String>,
+    /// End of the last report, where the next one starts.
+    last_end: i64,
+    previous: Option<String>,
+}
+
+/// Why an anchor was not counted as verified.
+enum Finding {
+    Unchecked(String),
+    Failed(String),
+}
+
+impl Reporter {
+    /// Continue the chain of reports in `store`, or start one at `now`.
+    pub fn open(
+        config: &ReportConfig,
+        data_dir: &Path,
+        store: &MonitorStore,
+        now: i64,
+    ) -> anyhow::Result<Self> {
+        let signer = open_signer(&config.signer).context("open report signer")?;
+        let (last_end, previous) = match store.last_report()? {
+            Some((end, digest)) => (end, Some(digest)),
+            None => (now, None),
+        };
+        Ok(Self {
+            signer,
+            interval_nanos: config.interval().as_nanos() as i64,
+            dir: config
+                .dir
+                .clone()
+                .unwrap_or_else(|| data_dir.join("reports")),
+            anchor_key: config.anchor_key.clone(),
+            last_end,
+            previous,
+        })
+    }
+
+    pub fn due(&self, now: i64) -> bool {
+        now - self.last_end >= self.interval_nanos
+    }
+
+    /// What the monitor verified about one log in `[last_end, end)`.
+    pub async fn audit(
+        &self,
+        log: &LogConfig,
+        client: &VerifyingClient,
+        store: &SharedStore,
+        end: i64,
+    ) -> anyhow::Result<LogAudit> {
+        let (checkpoints_observed, latest, incidents) = {
+            let store = store.lock().expect("monitor store poisoned");
+            (
+                store.verified_between(&log.name, self.last_end, end)?,
+                store.latest_before(&log.name, end)?,
+                store.incidents_between(self.last_end, end)?,
+            )
+        };
+        let discrepancies = incidents
+            .into_iter()
+            .filter(|incident| incident.log == log.name)
+            .map(|incident| Discrepancy {
+                detected_at_nanos: incident.detected_at_nanos,
+                trusted: root_of(&incident.trusted),
+                observed: root_of(&incident.observed),
+                reason: incident.reason,
+            })
+            .collect();
+        let anchors = self
+            .audit_anchors(client.client(), latest.as_ref(), end)
+            .await;
+        Ok(LogAudit {
+            log: log.name.clone(),
+            url: log.url.clone(),
+            checkpoints_observed,
+            latest: latest.as_ref().map(root_of),
+            discrepancies,
+            anchors,
+        })
+    }
+
+    /// Check the log's anchors from the period against `latest`.
+    async fn audit_anchors(
+        &self,
+        client: &Client,
+        latest: Option<&TrustedHead>,
+        end: i64,
+    ) -> AnchorAudit {
+        let query = AnchorQuery {
+            from_nanos: Some(self.last_end),
+            to_nanos: Some(end - 1),
+            ..AnchorQuery::default()
+        };
+        let anchors = match client.anchors(&query).await {
+            Ok(anchors) => anchors,
+            Err(err) => {
+                return AnchorAudit {
+                    error: Some(err.to_string()),
+                    ..AnchorAudit::default()
+                }
+            }
+        };
+        let mut audit = AnchorAudit::default();
+        for anchor in anchors {
+            let record = &anchor.record;
+            let finding = |reason| AnchorFinding {
+                txid: record.txid.clone(),
+                size: record.size,
+                root: record.root.clone(),
+                reason,
+            };
+            match self.check_anchor(client, &anchor, latest).await {
+                Ok(()) => audit.verified += 1,
+                Err(Finding::Unchecked(reason)) => audit.unchecked.push(finding(reason)),
+                Err(Finding::Failed(reason)) => audit.failed.push(finding(reason)),
+            }
+        }
+        audit
+    }
+
+    /// Check the anchor's txid and signature, then that the anchored root is
+    /// a prefix of the latest verified head.
+    async fn check_anchor(
+        &self,
+        client: &Client,
+        anchor: &StoredAnchor,
+        latest: Option<&TrustedHead>,
+    ) -> Result<(), Finding> {
+        let record = &anchor.record;
+        if anchor.status != AnchorStatus::Confirmed {
+            return Err(Finding::Unchecked(format!(
+                "anchor is {}",
+                anchor.status.as_str()
+            )));
+        }
+        if record.txid.contains('/') {
+            return Err(Finding::Unchecked(
+                "batch member; its batch proof is only in the anchor daemon's receipt".into(),
+            ));
+        }
+        let receipt = AnchorReceipt {
+            anchor: anchor.clone(),
+            batch: None,
+        };
+        let context = ReceiptContext {
+            anchor_key: self.anchor_key.clone(),
+            ..ReceiptContext::default()
+        };
+        receipt
+            .verify(&context)
+            .map_err(|err| Finding::Failed(err.to_string()))?;
+
+        let Some(latest) = latest.filter(|head| head.size >= record.size) else {
+            return Err(Finding::Unchecked(
+                "anchors a tree larger than any verified head".into(),
+            ));
+        };
+        let consistency = if record.size == latest.size {
+            None
+        } else {
+            let proof = client
+                .consistency(record.size, Some(latest.size))
+                .await
+                .map_err(|err| Finding::Unchecked(format!("fetch consistency proof: {err}")))?;
+            Some(proof)
+        };
+        if !extends(record, latest, consistency.as_ref()) {
+            return Err(Finding::Failed(
+                "anchored root is not a prefix of the verified head".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Sign the period's report, write it as JSON and text, and start the
+    /// next period at `end`.
+    pub fn write(
+        &mut self,
+        logs: Vec<LogAudit>,
+        end: i64,
+        store: &SharedStore,
+    ) -> anyhow::Result<PathBuf> {
+        let signed = AuditReport {
+            version: AUDIT_REPORT_VERSION,
+            period_start_nanos: self.last_end.to_string(),
+            period_end_nanos: end.to_string(),
+            previous: self.previous.clone(),
+            logs,
+        }
+        .sign(self.signer.as_ref())
+        .context("sign audit report")?;
+        let digest = signed.digest();
+
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("create {}", self.dir.display()))?;
+        let stem = format!("report-{}", file_time(end));
+        let path = self.dir.join(format!("{stem}.json"));
+        std::fs::write(&path, serde_json::to_vec_pretty(&signed)?)
+            .with_context(|| format!("write {}", path.display()))?;
+        let text = self.dir.join(format!("{stem}.txt"));
+        std::fs::write(&text, render(&signed))
+            .with_context(|| format!("write {}", text.display()))?;
+        store
+            .lock()
+            .expect("monitor store poisoned")
+            .record_report(end, &digest, &path)?;
+
+        self.last_end = end;
+        self.previous = Some(digest);
+        Ok(path)
+    }
+}
+
+/// Whether `consistency` shows the anchored tree is a prefix of `latest`,
+/// or the two are the same tree.
+fn extends(
+    record: &AnchorRecord,
+    latest: &TrustedHead,
+    consistency: Option<&ConsistencyProof>,
+) -> bool {
+    if record.size == latest.size {
+        return record.root.eq_ignore_ascii_case(&latest.root);
+    }
+    consistency.is_some_and(|proof| {
+        proof.old_size == record.size
+            && proof.old_root.eq_ignore_ascii_case(&record.root)
+            && proof.new_size == latest.size
+            && proof.new_root.eq_ignore_ascii_case(&latest.root)
+            && verify_consistency(proof)
+    })
+}
+
+fn root_of(head: &TrustedHead) -> RootResponse {
+    RootResponse {
+        size: head.size,
+        root: head.root.clone(),
+    }
+}
+
+/// The report as text, with the signature and digest to check it by.
+pub fn render(signed: &SignedAuditReport) -> String {
+    let report = &signed.report;
+    let mut text = format!(
+        "RealityLog audit report\n\
+         period     {} to {}\n\
+         previous   {}\n\
+         result     {}\n",
+        time_of(&report.period_start_nanos),
+        time_of(&report.period_end_nanos),
+        report.previous.as_deref().unwrap_or("none (first report)"),
+        if report.clean() {
+            "clean"
+        } else {
+            "PROBLEMS FOUND"
+        }
+    );
+    for log in &report.logs {
+        text += &format!("\nlog {}  {}\n", log.log, log.url);
+        text += &format!(
+            "  checkpoints observed  {} (each checked for consistency)\n",
+            log.checkpoints_observed
+        );
+        match &log.latest {
+            Some(head) => text += &format!("  latest head           {} {}\n", head.size, head.root),
+            None => text += "  latest head           none verified yet\n",
+        }
+        text += &format!("  discrepancies         {}\n", log.discrepancies.len());
+        for found in &log.discrepancies {
+            text += &format!(
+                "    {}  trusted {} {}, served {} {}: {}\n",
+                time_of(&found.detected_at_nanos),
+                found.trusted.size,
+                found.trusted.root,
+                found.observed.size,
+                found.observed.root,
+                found.reason
+            );
+        }
+        let anchors = &log.anchors;
+        match &anchors.error {
+            Some(err) => text += &format!("  anchors               not checked: {err}\n"),
+            None => {
+                text += &format!(
+                    "  anchors               {} verified, {} unchecked, {} failed\n",
+                    anchors.verified,
+                    anchors.unchecked.len(),
+                    anchors.failed.len()
+                )
+            }
+        }
+        for (label, findings) in [
+            ("FAILED", &anchors.failed),
+            ("unchecked", &anchors.unchecked),
+        ] {
+            for finding in findings {
+                text += &format!(
+                    "    {label}  {} (size {}): {}\n",
+                    finding.txid, finding.size, finding.reason
+                );
+            }
+        }
+    }
+    text += &format!(
+        "\nsigned by  {}\nsignature  {}\ndigest     {}\n",
+        signed.signature.public_key,
+        signed.signature.signature,
+        signed.digest()
+    );
+    text
+}
+
+fn time_of(nanos: &str) -> String {
+    nanos
+        .parse::<i128>()
+        .ok()
+        .and_then(|nanos| OffsetDateTime::from_unix_timestamp_nanos(nanos).ok())
+        .and_then(|at| at.format(&Rfc3339).ok())
+        .unwrap_or_else(|| nanos.to_string())
+}
+
+/// `20261015T120000Z`, for file names.
+fn file_time(nanos: i64) -> String {
+    let at = OffsetDateTime::from_unix_timestamp_nanos(nanos as i128)
+        .unwrap_or(OffsetDateTime::UNIX_EPOCH);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        at.year(),
+        at.month() as u8,
+        at.day(),
+        at.hour(),
+        at.minute(),
+        at.second()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reality_core::{
+        audit::AnchorAudit, consistency::consistency_proof, leaf_hash, root, signer::KeyFileSigner,
+    };
+
+    #[test]
+    fn anchors_must_be_a_prefix_of_the_verified_head() {
+        let leaves: Vec<[u8; 32]> = (0..6u8).map(|i| leaf_hash(&[i])).collect();
+        let record = AnchorRecord::new(hex::encode(root(&leaves[..4])), 4, "1".into(), "t".into());
+        let latest = TrustedHead {
+            size: 6,
+            root: hex::encode(root(&leaves)),
+            checkpoint: None,
+        };
+        let proof = consistency_proof(&leaves, 4, leaves.len()).unwrap();
+        assert!(extends(&record, &latest, Some(&proof)));
+        assert!(!extends(&record, &latest, None));
+
+        let mut forked = record.clone();
+        forked.root = "00".repeat(32);
+        assert!(!extends(&forked, &latest, Some(&proof)));
+        let same = AnchorRecord::new(latest.root.clone(), 6, "1".into(), "t".into());
+        assert!(extends(&same, &latest, None));
+
+        let signed = AuditReport {
+            version: AUDIT_REPORT_VERSION,
+            period_start_nanos: "1760486400000000000".into(),
+            period_end_nanos: "1760572800000000000".into(),
+            previous: None,
+            logs: vec![LogAudit {
+                log: "prod".into(),
+                url: "http://log".into(),
+                checkpoints_observed: 3,
+                latest: Some(root_of(&latest)),
+                discrepancies: Vec::new(),
+                anchors: AnchorAudit {
+                    verified: 1,
+                    failed: vec![AnchorFinding {
+                        txid: "t".into(),
+                        size: 4,
+                        root: forked.root,
+                        reason: "anchored root is not a prefix of the verified head".into(),
+                    }],
+                    ..AnchorAudit::default()
+                },
+            }],
+        }
+        .sign(&KeyFileSigner::from_seed([4; 32]))
+        .unwrap();
+        let text = render(&signed);
+        assert!(text.contains("period     2025-10-15T00:00:00Z to 2025-10-16T00:00:00Z\n"));
+        assert!(text.contains("result     PROBLEMS FOUND\n"));
+        assert!(text.contains("1 verified, 0 unchecked, 1 failed\n    FAILED  t (size 4)"));
+        assert!(text.ends_with(&format!("digest     {}\n", signed.digest())));
+        assert_eq!(file_time(1_760_572_800_000_000_000), "20251016T000000Z");
+    }
+}
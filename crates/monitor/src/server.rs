@@ -0,0 +1,109 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use serde::Deserialize;
+use tokio::net::TcpListener;
+use tracing::{error, info};
+
+use crate::{
+    status::{MonitorStatus, SharedStatus},
+    store::{Incident, MonitorStore, VerifiedHead},
+};
+
+pub type SharedStore = Arc<Mutex<MonitorStore>>;
+
+#[derive(Clone)]
+pub struct ApiState {
+    pub store: SharedStore,
+    pub status: SharedStatus,
+    /// How long the loop may go without finishing a round before /healthz fails.
+    pub stale_after: Duration,
+}
+
+#[derive(Debug, Deserialize)]
+struct HeadsQuery {
+    log: String,
+    #[serde(default = "default_limit")]
+    limit: usize,
+}
+
+fn default_limit() -> usize {
+    100
+}
+
+/// Serve the monitor's status API until the process exits.
+pub async fn serve(addr: SocketAddr, state: ApiState) -> anyhow::Result<()> {
+    let app = Router::new()
+        .route("/healthz", get(healthz))
+        .route("/status", get(status))
+        .route("/metrics", get(metrics))
+        .route("/heads", get(heads))
+        .route("/incidents", get(incidents))
+        .with_state(state);
+
+    let listener = TcpListener::bind(addr).await?;
+    info!(%addr, "monitor API listening");
+    axum::serve(listener, app.into_make_service()).await?;
+    Ok(())
+}
+
+async fn healthz(State(state): State<ApiState>) -> (StatusCode, &'static str) {
+    let live = state
+        .status
+        .lock()
+        .expect("status poisoned")
+        .is_live(state.stale_after);
+    if live {
+        (StatusCode::OK, "ok")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "monitor loop stalled")
+    }
+}
+
+async fn status(State(state): State<ApiState>) -> Json<MonitorStatus> {
+    Json(state.status.lock().expect("status poisoned").clone())
+}
+
+async fn metrics(State(state): State<ApiState>) -> String {
+    state.status.lock().expect("status poisoned").metrics()
+}
+
+async fn heads(
+    State(state): State<ApiState>,
+    Query(query): Query<HeadsQuery>,
+) -> Result<Json<Vec<VerifiedHead>>, (StatusCode, String)> {
+    let result = state
+        .store
+        .lock()
+        .expect("monitor store poisoned")
+        .history(&query.log, query.limit);
+    result.map(Json).map_err(internal)
+}
+
+async fn incidents(
+    State(state): State<ApiState>,
+) -> Result<Json<Vec<Incident>>, (StatusCode, String)> {
+    let result = state
+        .store
+        .lock()
+        .expect("monitor store poisoned")
+        .incidents();
+    result.map(Json).map_err(internal)
+}
+
+fn internal(err: anyhow::Error) -> (StatusCode, String) {
+    error!(?err, "monitor store query failed");
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "monitor store query failed".to_string(),
+    )
+}
@@ -0,0 +1,207 @@
+use std::{
+    fmt::Write,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use reality_client::TrustedHead;
+use serde::Serialize;
+use time::OffsetDateTime;
+
+pub type SharedStatus = Arc<Mutex<MonitorStatus>>;
+
+/// Renders one metric's value for a log.
+type Series = fn(&LogStatus) -> String;
+
+/// Live view of every monitored log, reported at `/status`.
+#[derive(Debug, Clone, Serialize)]
+pub struct MonitorStatus {
+    pub started_at: String,
+    pub last_round_at: Option<String>,
+    pub logs: Vec<LogStatus>,
+    #[serde(skip)]
+    last_round: Instant,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogStatus {
+    pub name: String,
+    pub url: String,
+    /// Latest verified head.
+    pub head: Option<TrustedHead>,
+    pub last_verified_at: Option<String>,
+    pub last_verified_nanos: Option<i128>,
+    pub consecutive_failures: u32,
+    /// Set once `consecutive_failures` reaches the alert threshold.
+    pub alerting: bool,
+    pub last_error: Option<String>,
+    /// Why the log was last caught contradicting its verified history.
+    /// Stays set until the monitor restarts.
+    pub inconsistent: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertTransition {
+    None,
+    Raised,
+    Cleared { failed_rounds: u32 },
+}
+
+impl MonitorStatus {
+    pub fn new(logs: impl IntoIterator<Item = (String, String)>) -> Self {
+        Self {
+            started_at: now_rfc3339(),
+            last_round_at: None,
+            logs: logs
+                .into_iter()
+                .map(|(name, url)| LogStatus {
+                    name,
+                    url,
+                    head: None,
+                    last_verified_at: None,
+                    last_verified_nanos: None,
+                    consecutive_failures: 0,
+                    alerting: false,
+                    last_error: None,
+                    inconsistent: None,
+                })
+                .collect(),
+            last_round: Instant::now(),
+        }
+    }
+
+    pub fn shared(self) -> SharedStatus {
+        Arc::new(Mutex::new(self))
+    }
+
+    pub fn round_finished(&mut self) {
+        self.last_round = Instant::now();
+        self.last_round_at = Some(now_rfc3339());
+    }
+
+    /// A poll of `log` verified `head`; reports whether an alert cleared.
+    pub fn verified(&mut self, log: &str, head: TrustedHead) -> AlertTransition {
+        let now = OffsetDateTime::now_utc();
+        let status = self.log_mut(log);
+        status.head = Some(head);
+        status.last_verified_at = Some(rfc3339(now));
+        status.last_verified_nanos = Some(now.unix_timestamp_nanos());
+        status.last_error = None;
+        let failed_rounds = std::mem::take(&mut status.consecutive_failures);
+        if std::mem::take(&mut status.alerting) {
+            AlertTransition::Cleared { failed_rounds }
+        } else {
+            AlertTransition::None
+        }
+    }
+
+    /// A poll of `log` failed without evidence against the log, e.g. it was
+    /// unreachable.
+    pub fn failed(&mut self, log: &str, error: String, threshold: u32) -> AlertTransition {
+        let status = self.log_mut(log);
+        status.consecutive_failures += 1;
+        status.last_error = Some(error);
+        if !status.alerting && status.consecutive_failures >= threshold {
+            status.alerting = true;
+            AlertTransition::Raised
+        } else {
+            AlertTransition::None
+        }
+    }
+
+    /// `log` served a head that contradicts its verified history.
+    pub fn inconsistent(&mut self, log: &str, reason: String) {
+        let status = self.log_mut(log);
+        status.last_error = Some(reason.clone());
+        status.inconsistent = Some(reason);
+    }
+
+    /// The loop is considered wedged once no round has finished for `stale_after`.
+    pub fn is_live(&self, stale_after: Duration) -> bool {
+        self.last_round.elapsed() <= stale_after
+    }
+
+    /// Prometheus text exposition, one series per log.
+    pub fn metrics(&self) -> String {
+        let mut out = String::new();
+        let series: [(&str, Series); 5] = [
+            ("reality_monitor_tree_size", |log| {
+                log.head.as_ref().map_or(0, |head| head.size).to_string()
+            }),
+            ("reality_monitor_consistent", |log| {
+                u8::from(log.inconsistent.is_none()).to_string()
+            }),
+            ("reality_monitor_consecutive_failures", |log| {
+                log.consecutive_failures.to_string()
+            }),
+            ("reality_monitor_alert_active", |log| {
+                u8::from(log.alerting || log.inconsistent.is_some()).to_string()
+            }),
+            ("reality_monitor_last_verified_timestamp_seconds", |log| {
+                let nanos = log.last_verified_nanos.unwrap_or(0);
+                (nanos as f64 / 1e9).to_string()
+            }),
+        ];
+        for (name, value) in series {
+            let _ = writeln!(out, "# TYPE {name} gauge");
+            for log in &self.logs {
+                let _ = writeln!(out, "{name}{{log=\"{}\"}} {}", log.name, value(log));
+            }
+        }
+        out
+    }
+
+    fn log_mut(&mut self, log: &str) -> &mut LogStatus {
+        self.logs
+            .iter_mut()
+            .find(|status| status.name == log)
+            .expect("status for every configured log")
+    }
+}
+
+fn now_rfc3339() -> String {
+    rfc3339(OffsetDateTime::now_utc())
+}
+
+fn rfc3339(at: OffsetDateTime) -> String {
+    at.format(&time::format_description::well_known::Rfc3339)
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_each_log_separately() {
+        let mut status = MonitorStatus::new([
+            ("prod".to_string(), "https://a".to_string()),
+            ("staging".to_string(), "https://b".to_string()),
+        ]);
+        assert_eq!(
+            status.failed("prod", "down".into(), 2),
+            AlertTransition::None
+        );
+        assert_eq!(
+            status.failed("prod", "down".into(), 2),
+            AlertTransition::Raised
+        );
+        status.inconsistent("staging", "fork".into());
+        let metrics = status.metrics();
+        assert!(metrics.contains("reality_monitor_alert_active{log=\"prod\"} 1"));
+        assert!(metrics.contains("reality_monitor_consistent{log=\"staging\"} 0"));
+
+        let head = TrustedHead {
+            size: 4,
+            root: "ab".repeat(32),
+            checkpoint: None,
+        };
+        assert_eq!(
+            status.verified("prod", head),
+            AlertTransition::Cleared { failed_rounds: 2 }
+        );
+        assert!(status
+            .metrics()
+            .contains("reality_monitor_tree_size{log=\"prod\"} 4"));
+    }
+}
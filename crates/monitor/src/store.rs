@@ -0,0 +1,314 @@
+use std::path::Path;
+
+use anyhow::Context;
+use reality_client::TrustedHead;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use time::OffsetDateTime;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS heads (
+    log TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    root TEXT NOT NULL,
+    checkpoint TEXT,
+    verified_at_nanos INTEGER NOT NULL,
+    PRIMARY KEY (log, size)
+);
+CREATE TABLE IF NOT EXISTS incidents (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    log TEXT NOT NULL,
+    trusted_size INTEGER NOT NULL,
+    trusted_root TEXT NOT NULL,
+    observed TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    detected_at_nanos INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS reports (
+    period_end_nanos INTEGER PRIMARY KEY,
+    digest TEXT NOT NULL,
+    path TEXT NOT NULL
+);
+";
+
+/// A tree head the monitor verified, as listed at `/heads`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VerifiedHead {
+    pub log: String,
+    #[serde(flatten)]
+    pub head: TrustedHead,
+    pub verified_at_nanos: String,
+}
+
+/// A head a log served that does not extend the one the monitor trusts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Incident {
+    pub log: String,
+    pub trusted: TrustedHead,
+    pub observed: TrustedHead,
+    pub reason: String,
+    pub detected_at_nanos: String,
+}
+
+/// SQLite history of every verified head, per log, plus the evidence of
+/// every inconsistency seen.
+pub struct MonitorStore {
+    conn: Connection,
+}
+
+impl MonitorStore {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("open monitor store {}", path.display()))?;
+        Self::init(conn)
+    }
+
+    #[cfg(test)]
+    pub fn in_memory() -> anyhow::Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> anyhow::Result<Self> {
+        conn.execute_batch(SCHEMA)
+            .context("create monitor schema")?;
+        Ok(Self { conn })
+    }
+
+    /// The largest verified head of `log`: where monitoring resumes.
+    pub fn latest(&self, log: &str) -> anyhow::Result<Option<TrustedHead>> {
+        self.conn
+            .query_row(
+                "SELECT size, root, checkpoint FROM heads WHERE log = ?1
+                 ORDER BY size DESC LIMIT 1",
+                params![log],
+                read_head,
+            )
+            .optional()?
+            .transpose()
+    }
+
+    /// The largest head of `log` verified before `before_nanos`.
+    pub fn latest_before(
+        &self,
+        log: &str,
+        before_nanos: i64,
+    ) -> anyhow::Result<Option<TrustedHead>> {
+        self.conn
+            .query_row(
+                "SELECT size, root, checkpoint FROM heads
+                 WHERE log = ?1 AND verified_at_nanos < ?2
+                 ORDER BY size DESC LIMIT 1",
+                params![log, before_nanos],
+                read_head,
+            )
+            .optional()?
+            .transpose()
+    }
+
+    /// How many heads of `log` were verified in `[from_nanos, to_nanos)`.
+    pub fn verified_between(
+        &self,
+        log: &str,
+        from_nanos: i64,
+        to_nanos: i64,
+    ) -> anyhow::Result<u64> {
+        let count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM heads
+             WHERE log = ?1 AND verified_at_nanos >= ?2 AND verified_at_nanos < ?3",
+            params![log, from_nanos, to_nanos],
+            |row| row.get(0),
+        )?;
+        Ok(count as u64)
+    }
+
+    /// Record a verified head. A size already recorded keeps its first entry.
+    pub fn record(&self, log: &str, head: &TrustedHead) -> anyhow::Result<bool> {
+        let checkpoint = head
+            .checkpoint
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
+        let inserted = self.conn.execute(
+            "INSERT OR IGNORE INTO heads (log, size, root, checkpoint, verified_at_nanos)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![log, head.size as i64, head.root, checkpoint, now_nanos()],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// Verified heads of `log`, newest first.
+    pub fn history(&self, log: &str, limit: usize) -> anyhow::Result<Vec<VerifiedHead>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT size, root, checkpoint, verified_at_nanos FROM heads WHERE log = ?1
+             ORDER BY size DESC LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![log, limit as i64], |row| {
+            let verified_at: i64 = row.get(3)?;
+            Ok(read_head(row)?.map(|head| VerifiedHead {
+                log: log.to_string(),
+                head,
+                verified_at_nanos: verified_at.to_string(),
+            }))
+        })?;
+        rows.map(|row| row?).collect()
+    }
+
+    pub fn record_incident(
+        &self,
+        log: &str,
+        trusted: &TrustedHead,
+        observed: &TrustedHead,
+        reason: &str,
+    ) -> anyhow::Result<()> {
+        self.conn.execute(
+            "INSERT INTO incidents
+             (log, trusted_size, trusted_root, observed, reason, detected_at_nanos)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                log,
+                trusted.size as i64,
+                trusted.root,
+                serde_json::to_string(observed)?,
+                reason,
+                now_nanos()
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn incidents(&self) -> anyhow::Result<Vec<Incident>> {
+        self.incidents_between(i64::MIN, i64::MAX)
+    }
+
+    /// Incidents detected in `[from_nanos, to_nanos)`, oldest first.
+    pub fn incidents_between(
+        &self,
+        from_nanos: i64,
+        to_nanos: i64,
+    ) -> anyhow::Result<Vec<Incident>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT log, trusted_size, trusted_root, observed, reason, detected_at_nanos
+             FROM incidents WHERE detected_at_nanos >= ?1 AND detected_at_nanos < ?2
+             ORDER BY id ASC",
+        )?;
+        let rows = stmt.query_map(params![from_nanos, to_nanos], |row| {
+            let trusted_size: i64 = row.get(1)?;
+            let observed: String = row.get(3)?;
+            let detected_at: i64 = row.get(5)?;
+            Ok((
+                row.get::<_, String>(0)?,
+                TrustedHead {
+                    size: trusted_size as u64,
+                    root: row.get(2)?,
+                    checkpoint: None,
+                },
+                observed,
+                row.get::<_, String>(4)?,
+                detected_at.to_string(),
+            ))
+        })?;
+        rows.map(|row| {
+            let (log, trusted, observed, reason, detected_at_nanos) = row?;
+            Ok(Incident {
+                log,
+                trusted,
+                observed: serde_json::from_str(&observed).context("decode incident")?,
+                reason,
+                detected_at_nanos,
+            })
+        })
+        .collect()
+    }
+
+    /// End and digest of the last audit report written.
+    pub fn last_report(&self) -> anyhow::Result<Option<(i64, String)>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT period_end_nanos, digest FROM reports
+                 ORDER BY period_end_nanos DESC LIMIT 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?)
+    }
+
+    pub fn record_report(
+        &self,
+        period_end_nanos: i64,
+        digest: &str,
+        path: &Path,
+    ) -> anyhow::Result<()> {
+        self.conn.execute(
+            "INSERT INTO reports (period_end_nanos, digest, path) VALUES (?1, ?2, ?3)",
+            params![period_end_nanos, digest, path.display().to_string()],
+        )?;
+        Ok(())
+    }
+}
+
+/// Columns `size, root, checkpoint` starting at 0.
+fn read_head(row: &rusqlite::Row<'_>) -> rusqlite::Result<anyhow::Result<TrustedHead>> {
+    let size: i64 = row.get(0)?;
+    let root: String = row.get(1)?;
+    let checkpoint: Option<String> = row.get(2)?;
+    Ok(checkpoint
+        .map(|json| serde_json::from_str(&json).context("decode stored checkpoint"))
+        .transpose()
+        .map(|checkpoint| TrustedHead {
+            size: size as u64,
+            root,
+            checkpoint,
+        }))
+}
+
+pub fn now_nanos() -> i64 {
+    OffsetDateTime::now_utc().unix_timestamp_nanos() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn head(size: u64, root: &str) -> TrustedHead {
+        TrustedHead {
+            size,
+            root: root.repeat(32),
+            checkpoint: None,
+        }
+    }
+
+    #[test]
+    fn keeps_history_and_evidence_per_log() {
+        let store = MonitorStore::in_memory().unwrap();
+        assert_eq!(store.latest("prod").unwrap(), None);
+
+        assert!(store.record("prod", &head(3, "aa")).unwrap());
+        assert!(store.record("prod", &head(5, "bb")).unwrap());
+        assert!(!store.record("prod", &head(5, "cc")).unwrap());
+        assert!(store.record("staging", &head(9, "dd")).unwrap());
+
+        assert_eq!(store.latest("prod").unwrap(), Some(head(5, "bb")));
+        let history = store.history("prod", 10).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].head, head(5, "bb"));
+
+        store
+            .record_incident("prod", &head(5, "bb"), &head(5, "ee"), "fork")
+            .unwrap();
+        let incidents = store.incidents().unwrap();
+        assert_eq!(incidents.len(), 1);
+        assert_eq!(incidents[0].observed, head(5, "ee"));
+
+        let now = now_nanos() + 1;
+        assert_eq!(store.verified_between("prod", 0, now).unwrap(), 2);
+        assert_eq!(store.verified_between("prod", now, i64::MAX).unwrap(), 0);
+        assert_eq!(store.latest_before("prod", 0).unwrap(), None);
+        assert_eq!(store.incidents_between(now, i64::MAX).unwrap(), []);
+
+        assert_eq!(store.last_report().unwrap(), None);
+        store.record_report(7, "ab", Path::new("r.json")).unwrap();
+        store.record_report(9, "cd", Path::new("r.json")).unwrap();
+        assert_eq!(store.last_report().unwrap(), Some((9, "cd".into())));
+    }
+}
@@ -0,0 +1,25 @@
+from typing import Any, Mapping, Optional, Union
+
+__version__: str
+
+# A logd response as decoded by `json.loads`, or its JSON text.
+Json = Union[Mapping[str, Any], str, bytes]
+
+class VerificationError(ValueError):
+    """A proof, checkpoint or receipt failed verification."""
+
+def leaf_hash(data: Union[str, bytes], algorithm: Optional[str] = None) -> str: ...
+def node_hash(left: str, right: str, algorithm: Optional[str] = None) -> str: ...
+def empty_root(algorithm: Optional[str] = None) -> str: ...
+def verify(proof: Json, *, strict: bool = False) -> bool: ...
+def verify_bytes(data: bytes, *, strict: bool = False) -> bool: ...
+def verify_consistency(
+    proof: Json,
+    old_root: Optional[str] = None,
+    new_root: Optional[str] = None,
+    *,
+    strict: bool = False,
+) -> bool: ...
+def verify_checkpoint(checkpoint: Json, public_key: str, *, strict: bool = False) -> bool: ...
+def verify_bundle(bundle: Json, trusted_key: Optional[str] = None, *, strict: bool = False) -> bool: ...
+def verify_anchor(receipt: Json, context: Optional[Json] = None, *, strict: bool = False) -> bool: ...
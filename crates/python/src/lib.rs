@@ -0,0 +1,366 @@
+//! Python bindings for RealityLog verification, published as `realitylog`.
+//!
+//! Structured inputs (proofs, checkpoints, bundles, receipts) may be passed as
+//! the `dict` that `json.loads` gives for a logd response, or as the JSON text
+//! itself. Every `verify*` function returns `True` or `False`. With
+//! `strict=True` a failed check raises `realitylog.VerificationError` carrying
+//! the reason instead. Input that cannot be evaluated at all, such as missing
+//! fields or an unknown hash algorithm, always raises `ValueError`.
+
+use pyo3::{create_exception, exceptions::PyValueError, prelude::*, types::PyString};
+use reality_core::{
+    algorithm::HashAlgorithm,
+    bundle::{BundleError, EntryBundle},
+    checkpoint::SignedCheckpoint,
+    consistency::{self, ConsistencyProof},
+    receipt::{AnchorReceipt, ReceiptContext, ReceiptError},
+    verify as verify_request, InclusionProof, VerifyRequest,
+};
+use serde::de::DeserializeOwned;
+
+create_exception!(
+    realitylog,
+    VerificationError,
+    PyValueError,
+    "A proof, checkpoint or receipt failed verification."
+);
+
+/// Why a check did not pass.
+#[derive(Debug, PartialEq, Eq)]
+enum Failure {
+    /// The input could not be evaluated; raised as `ValueError`.
+    Malformed(String),
+    /// The input was evaluated and is not valid.
+    Invalid(String),
+}
+
+type Checked = Result<(), Failure>;
+
+impl From<BundleError> for Failure {
+    fn from(err: BundleError) -> Self {
+        Failure::Invalid(err.to_string())
+    }
+}
+
+impl From<ReceiptError> for Failure {
+    fn from(err: ReceiptError) -> Self {
+        Failure::Invalid(err.to_string())
+    }
+}
+
+/// Hex leaf hash of a payload, as logd computes it on append. `str`
+/// payloads are hashed as UTF-8.
+#[pyfunction]
+#[pyo3(signature = (data, algorithm = None))]
+fn leaf_hash(data: &Bound<'_, PyAny>, algorithm: Option<&str>) -> PyResult<String> {
+    let algorithm = parse_algorithm(algorithm).map_err(raise)?;
+    let hash = match data.downcast::<PyString>() {
+        Ok(text) => algorithm.leaf_hash(text.to_cow()?.as_bytes()),
+        Err(_) => algorithm.leaf_hash(data.extract::<&[u8]>()?),
+    };
+    Ok(hex::encode(hash))
+}
+
+/// Hex interior node hash of two hex child hashes.
+#[pyfunction]
+#[pyo3(signature = (left, right, algorithm = None))]
+fn node_hash(left: &str, right: &str, algorithm: Option<&str>) -> PyResult<String> {
+    let algorithm = parse_algorithm(algorithm).map_err(raise)?;
+    let (left, right) = (hash(left).map_err(raise)?, hash(right).map_err(raise)?);
+    Ok(hex::encode(algorithm.node_hash(&left, &right)))
+}
+
+/// Hex root of an empty log.
+#[pyfunction]
+#[pyo3(signature = (algorithm = None))]
+fn empty_root(algorithm: Option<&str>) -> PyResult<String> {
+    let algorithm = parse_algorithm(algorithm).map_err(raise)?;
+    Ok(hex::encode(algorithm.empty_root()))
+}
+
+/// Verify an inclusion proof from `/prove/N` (or a `VerifyRequest`): the
+/// leaf and path must lead to the proof's root.
+#[pyfunction]
+#[pyo3(signature = (proof, *, strict = false))]
+fn verify(py: Python<'_>, proof: &Bound<'_, PyAny>, strict: bool) -> PyResult<bool> {
+    finish(check_inclusion(&json_text(py, proof)?), strict)
+}
+
+/// Verify a proof in the binary encoding served by `/prove/N/bin`.
+#[pyfunction]
+#[pyo3(signature = (data, *, strict = false))]
+fn verify_bytes(data: &[u8], strict: bool) -> PyResult<bool> {
+    finish(check_inclusion_bytes(data), strict)
+}
+
+/// Verify a consistency proof from `/consistency`. Pass the roots you trust
+/// for the old and new sizes to require that the proof links exactly those.
+#[pyfunction]
+#[pyo3(signature = (proof, old_root = None, new_root = None, *, strict = false))]
+fn verify_consistency(
+    py: Python<'_>,
+    proof: &Bound<'_, PyAny>,
+    old_root: Option<&str>,
+    new_root: Option<&str>,
+    strict: bool,
+) -> PyResult<bool> {
+    finish(
+        check_consistency(&json_text(py, proof)?, old_root, new_root),
+        strict,
+    )
+}
+
+/// Verify a signed checkpoint from `/checkpoint` against the log's hex
+/// ed25519 key.
+#[pyfunction]
+#[pyo3(signature = (checkpoint, public_key, *, strict = false))]
+fn verify_checkpoint(
+    py: Python<'_>,
+    checkpoint: &Bound<'_, PyAny>,
+    public_key: &str,
+    strict: bool,
+) -> PyResult<bool> {
+    finish(
+        check_checkpoint(&json_text(py, checkpoint)?, public_key),
+        strict,
+    )
+}
+
+/// Verify an `EntryBundle` from `/bundle/N` end to end: payload → leaf →
+/// root → checkpoint → signature. Pass the log's key as `trusted_key` to
+/// also require that it signed the checkpoint.
+#[pyfunction]
+#[pyo3(signature = (bundle, trusted_key = None, *, strict = false))]
+fn verify_bundle(
+    py: Python<'_>,
+    bundle: &Bound<'_, PyAny>,
+    trusted_key: Option<&str>,
+    strict: bool,
+) -> PyResult<bool> {
+    finish(check_bundle(&json_text(py, bundle)?, trusted_key), strict)
+}
+
+/// Verify an anchor receipt from the anchor API's `/receipt/<txid>`.
+/// `context` may add a proof ending at the anchored root, the previous
+/// anchor with a consistency proof, and the required `anchor_key`.
+#[pyfunction]
+#[pyo3(signature = (receipt, context = None, *, strict = false))]
+fn verify_anchor(
+    py: Python<'_>,
+    receipt: &Bound<'_, PyAny>,
+    context: Option<&Bound<'_, PyAny>>,
+    strict: bool,
+) -> PyResult<bool> {
+    let context = context.map(|context| json_text(py, context)).transpose()?;
+    finish(
+        check_anchor(&json_text(py, receipt)?, context.as_deref()),
+        strict,
+    )
+}
+
+#[pymodule]
+fn realitylog(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add("VerificationError", m.py().get_type::<VerificationError>())?;
+    m.add_function(wrap_pyfunction!(leaf_hash, m)?)?;
+    m.add_function(wrap_pyfunction!(node_hash, m)?)?;
+    m.add_function(wrap_pyfunction!(empty_root, m)?)?;
+    m.add_function(wrap_pyfunction!(verify, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_bytes, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_consistency, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_checkpoint, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_bundle, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_anchor, m)?)?;
+    Ok(())
+}
+
+fn check_inclusion(json: &str) -> Checked {
+    let request: VerifyRequest = parse(json, "proof")?;
+    let response = verify_request(&request);
+    if response.valid {
+        Ok(())
+    } else if response.computed_root.is_empty() {
+        Err(Failure::Malformed("proof contains a malformed hash".into()))
+    } else {
+        Err(Failure::Invalid(format!(
+            "proof leads to {}, not {}",
+            response.computed_root, response.expected_root
+        )))
+    }
+}
+
+fn check_inclusion_bytes(bytes: &[u8]) -> Checked {
+    let proof = InclusionProof::from_bytes(bytes)
+        .map_err(|err| Failure::Malformed(format!("invalid binary proof: {err}")))?;
+    check_inclusion(&serde_json::to_string(&proof).expect("proof serializes"))
+}
+
+fn check_consistency(json: &str, old_root: Option<&str>, new_root: Option<&str>) -> Checked {
+    let proof: ConsistencyProof = parse(json, "consistency proof")?;
+    for (expected, actual, which) in [
+        (old_root, &proof.old_root, "old"),
+        (new_root, &proof.new_root, "new"),
+    ] {
+        if let Some(expected) = expected {
+            if !expected.eq_ignore_ascii_case(actual) {
+                return Err(Failure::Invalid(format!(
+                    "proof's {which} root {actual} is not the expected {expected}"
+                )));
+            }
+        }
+    }
+    if !consistency::verify_consistency(&proof) {
+        return Err(Failure::Invalid(format!(
+            "proof does not link size {} to size {}",
+            proof.old_size, proof.new_size
+        )));
+    }
+    Ok(())
+}
+
+fn check_checkpoint(json: &str, public_key: &str) -> Checked {
+    let signed: SignedCheckpoint = parse(json, "checkpoint")?;
+    if !signed.signature.public_key.eq_ignore_ascii_case(public_key) {
+        return Err(Failure::Invalid(format!(
+            "checkpoint is signed by {}, not {public_key}",
+            signed.signature.public_key
+        )));
+    }
+    if !signed.verify() {
+        return Err(Failure::Invalid("checkpoint signature is invalid".into()));
+    }
+    Ok(())
+}
+
+fn check_bundle(json: &str, trusted_key: Option<&str>) -> Checked {
+    let bundle: EntryBundle = parse(json, "bundle")?;
+    Ok(bundle.verify(trusted_key)?)
+}
+
+fn check_anchor(json: &str, context: Option<&str>) -> Checked {
+    let receipt: AnchorReceipt = parse(json, "receipt")?;
+    let context = match context {
+        Some(context) => parse(context, "context")?,
+        None => ReceiptContext::default(),
+    };
+    receipt.verify(&context)?;
+    Ok(())
+}
+
+fn parse<T: DeserializeOwned>(json: &str, what: &str) -> Result<T, Failure> {
+    serde_json::from_str(json).map_err(|err| Failure::Malformed(format!("invalid {what}: {err}")))
+}
+
+fn parse_algorithm(name: Option<&str>) -> Result<HashAlgorithm, Failure> {
+    name.map_or(Ok(HashAlgorithm::default()), |name| {
+        name.parse().map_err(Failure::Malformed)
+    })
+}
+
+fn hash(value: &str) -> Result<[u8; 32], Failure> {
+    hex::decode(value)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| Failure::Malformed(format!("{value:?} is not a 32-byte hex hash")))
+}
+
+/// JSON text for `value`: `str` and `bytes` are taken as JSON already,
+/// anything else goes through `json.dumps`.
+fn json_text(py: Python<'_>, value: &Bound<'_, PyAny>) -> PyResult<String> {
+    if let Ok(text) = value.downcast::<PyString>() {
+        return Ok(text.to_cow()?.into_owned());
+    }
+    if let Ok(bytes) = value.extract::<&[u8]>() {
+        return String::from_utf8(bytes.to_vec())
+            .map_err(|_| PyValueError::new_err("JSON input must be UTF-8"));
+    }
+    py.import("json")?
+        .call_method1("dumps", (value,))?
+        .extract()
+}
+
+fn finish(checked: Checked, strict: bool) -> PyResult<bool> {
+    match checked {
+        Ok(()) => Ok(true),
+        Err(Failure::Invalid(reason)) if strict => Err(VerificationError::new_err(reason)),
+        Err(Failure::Invalid(_)) => Ok(false),
+        Err(malformed) => Err(raise(malformed)),
+    }
+}
+
+fn raise(failure: Failure) -> PyErr {
+    match failure {
+        Failure::Malformed(reason) => PyValueError::new_err(reason),
+        Failure::Invalid(reason) => VerificationError::new_err(reason),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reality_core::{
+        checkpoint::Checkpoint, consistency::consistency_proof, leaf_hash, make_proof,
+        signer::KeyFileSigner,
+    };
+
+    use super::*;
+
+    #[test]
+    fn separates_invalid_from_malformed_input() {
+        let leaves: Vec<[u8; 32]> = [b"a", b"b", b"c"].iter().map(|p| leaf_hash(*p)).collect();
+        let proof = make_proof(&leaves, 2).unwrap();
+        let json = serde_json::to_string(&proof).unwrap();
+        assert_eq!(check_inclusion(&json), Ok(()));
+        assert_eq!(check_inclusion_bytes(&proof.to_bytes().unwrap()), Ok(()));
+
+        let mut wrong = proof.clone();
+        wrong.root = "00".repeat(32);
+        assert!(matches!(
+            check_inclusion(&serde_json::to_string(&wrong).unwrap()),
+            Err(Failure::Invalid(_))
+        ));
+        assert!(matches!(
+            check_inclusion(r#"{"index": 0}"#),
+            Err(Failure::Malformed(_))
+        ));
+
+        let consistency =
+            serde_json::to_string(&consistency_proof(&leaves, 2, leaves.len()).unwrap()).unwrap();
+        assert_eq!(
+            check_consistency(&consistency, None, Some(&proof.root)),
+            Ok(())
+        );
+        assert!(matches!(
+            check_consistency(&consistency, Some(&proof.root), None),
+            Err(Failure::Invalid(_))
+        ));
+
+        let signer = KeyFileSigner::from_seed([3u8; 32]);
+        let signed = Checkpoint {
+            size: 3,
+            root: proof.root.clone(),
+            timestamp_nanos: "1".into(),
+        }
+        .sign(&signer)
+        .unwrap();
+        let key = signed.signature.public_key.clone();
+        let checkpoint = serde_json::to_string(&signed).unwrap();
+        assert_eq!(check_checkpoint(&checkpoint, &key), Ok(()));
+        assert!(matches!(
+            check_checkpoint(&checkpoint, &"00".repeat(32)),
+            Err(Failure::Invalid(_))
+        ));
+
+        let bundle = serde_json::to_string(&EntryBundle {
+            payload: "c".into(),
+            meta: None,
+            proof,
+            checkpoint: signed,
+        })
+        .unwrap();
+        assert_eq!(check_bundle(&bundle, Some(&key)), Ok(()));
+        assert!(matches!(
+            check_bundle(&bundle.replace("\"c\"", "\"d\""), None),
+            Err(Failure::Invalid(_))
+        ));
+    }
+}
@@ -0,0 +1,201 @@
+use std::{path::PathBuf, time::Duration};
+
+use clap::Parser;
+use reality_config::Layered;
+use serde::Deserialize;
+
+/// Largest batch logd accepts in one `/append/batch` request.
+const MAX_BATCH: usize = 1000;
+
+/// Command-line flags. Anything left unset falls back to the config file and
+/// then to defaults.
+#[derive(Debug, Parser)]
+#[command(
+    name = "reality-ship",
+    version,
+    about = "Tails log files and appends new lines to a RealityLog"
+)]
+pub struct Cli {
+    /// Path to a TOML config file.
+    #[arg(long, env = "REALITY_SHIP_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// File to ship, as `path` or `name=path` (repeatable).
+    #[arg(long = "file", value_parser = parse_file)]
+    pub files: Vec<FileConfig>,
+    /// Base URL of the logd instance to append to.
+    #[arg(long, env = "REALITY_SHIP_LOG_URL")]
+    pub log_url: Option<String>,
+    /// Bearer token sent with every append.
+    #[arg(long, env = "REALITY_SHIP_TOKEN")]
+    pub token: Option<String>,
+    /// Directory holding the cursor file.
+    #[arg(long, env = "REALITY_SHIP_DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
+    /// Host name recorded in every entry; defaults to the system's.
+    #[arg(long)]
+    pub host: Option<String>,
+    /// Most lines sent in one request.
+    #[arg(long)]
+    pub batch_size: Option<usize>,
+    /// Seconds between checks of the files when no change is reported.
+    #[arg(long)]
+    pub poll_secs: Option<u64>,
+}
+
+/// Fully resolved shipper configuration.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub log_url: String,
+    pub token: Option<String>,
+    pub data_dir: PathBuf,
+    pub host: Option<String>,
+    pub batch_size: usize,
+    pub poll_secs: u64,
+    /// Longer lines are shipped in pieces of this many bytes.
+    pub max_line: usize,
+    pub files: Vec<FileConfig>,
+}
+
+/// One file to tail.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    /// Stable name; entries and the stored cursor are keyed by it.
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            log_url: "http://127.0.0.1:8080".into(),
+            token: None,
+            data_dir: PathBuf::from("data"),
+            host: None,
+            batch_size: 100,
+            poll_secs: 5,
+            max_line: 64 * 1024,
+            files: Vec::new(),
+        }
+    }
+}
+
+impl Layered<Cli> for Config {
+    fn file(cli: &Cli) -> Option<PathBuf> {
+        cli.config.clone()
+    }
+
+    fn apply(&mut self, cli: Cli) -> anyhow::Result<()> {
+        self.files.extend(cli.files);
+        if let Some(log_url) = cli.log_url {
+            self.log_url = log_url;
+        }
+        if let Some(token) = cli.token {
+            self.token = Some(token);
+        }
+        if let Some(data_dir) = cli.data_dir {
+            self.data_dir = data_dir;
+        }
+        if let Some(host) = cli.host {
+            self.host = Some(host);
+        }
+        if let Some(batch_size) = cli.batch_size {
+            self.batch_size = batch_size;
+        }
+        if let Some(poll_secs) = cli.poll_secs {
+            self.poll_secs = poll_secs;
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(!self.files.is_empty(), "at least one file is required");
+        anyhow::ensure!(
+            (1..=MAX_BATCH).contains(&self.batch_size),
+            "batch_size must be between 1 and {MAX_BATCH}"
+        );
+        anyhow::ensure!(self.poll_secs > 0, "poll_secs must be positive");
+        anyhow::ensure!(self.max_line > 0, "max_line must be positive");
+        for (i, file) in self.files.iter().enumerate() {
+            anyhow::ensure!(
+                !self.files[..i].iter().any(|other| other.name == file.name),
+                "file name {} is used twice",
+                file.name
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Config {
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_secs)
+    }
+
+    /// The configured host name, or the system's.
+    pub fn host(&self) -> String {
+        self.host.clone().unwrap_or_else(|| {
+            std::fs::read_to_string("/proc/sys/kernel/hostname")
+                .ok()
+                .or_else(|| std::env::var("HOSTNAME").ok())
+                .map(|host| host.trim().to_string())
+                .filter(|host| !host.is_empty())
+                .unwrap_or_else(|| "localhost".into())
+        })
+    }
+}
+
+/// `name=path`, or a bare path named after its file name.
+fn parse_file(value: &str) -> Result<FileConfig, String> {
+    if let Some((name, path)) = value.split_once('=') {
+        return Ok(FileConfig {
+            name: name.to_string(),
+            path: PathBuf::from(path),
+        });
+    }
+    let path = PathBuf::from(value);
+    let name = path
+        .file_name()
+        .ok_or_else(|| format!("{value} does not name a file"))?
+        .to_string_lossy()
+        .into_owned();
+    Ok(FileConfig { name, path })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_need_unique_names() {
+        let mut config: Config = toml::from_str(
+            r#"
+            batch_size = 500
+
+            [[files]]
+            name = "auth"
+            path = "/var/log/auth.log"
+            "#,
+        )
+        .expect("config");
+        config
+            .apply(Cli::parse_from([
+                "reality-ship",
+                "--file",
+                "/var/log/nginx/access.log",
+                "--file",
+                "app=/srv/app/current.log",
+            ]))
+            .unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.files[1].name, "access.log");
+        assert_eq!(config.files[2].path, PathBuf::from("/srv/app/current.log"));
+
+        config.files[2].name = "auth".into();
+        assert!(config.validate().is_err());
+        config.files.truncate(1);
+        config.batch_size = MAX_BATCH + 1;
+        assert!(config.validate().is_err());
+    }
+}
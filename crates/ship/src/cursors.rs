@@ -0,0 +1,45 @@
+use std::{collections::BTreeMap, fs::File, io::Write, path::PathBuf};
+
+use anyhow::Context;
+
+use crate::tail::Cursor;
+
+/// Shipped position of every file, by name, kept in one JSON file. It is
+/// rewritten through a temporary file, so a crash leaves either the old or
+/// the new version.
+pub struct CursorStore {
+    path: PathBuf,
+    cursors: BTreeMap<String, Cursor>,
+}
+
+impl CursorStore {
+    pub fn load(path: PathBuf) -> anyhow::Result<Self> {
+        let cursors = match std::fs::read_to_string(&path) {
+            Ok(raw) => serde_json::from_str(&raw)
+                .with_context(|| format!("parse cursors {}", path.display()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => {
+                return Err(err).with_context(|| format!("read cursors {}", path.display()))
+            }
+        };
+        Ok(Self { path, cursors })
+    }
+
+    pub fn get(&self, name: &str) -> Cursor {
+        self.cursors.get(name).copied().unwrap_or_default()
+    }
+
+    pub fn set(&mut self, name: &str, cursor: Cursor) {
+        self.cursors.insert(name.to_string(), cursor);
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let tmp = self.path.with_extension("json.tmp");
+        let mut file = File::create(&tmp).with_context(|| format!("create {}", tmp.display()))?;
+        file.write_all(&serde_json::to_vec_pretty(&self.cursors)?)?;
+        file.sync_all()
+            .with_context(|| format!("write {}", tmp.display()))?;
+        std::fs::rename(&tmp, &self.path)
+            .with_context(|| format!("replace {}", self.path.display()))
+    }
+}
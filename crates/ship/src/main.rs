@@ -0,0 +1,171 @@
+//! `reality-ship`: tails log files and appends every new line to logd.
+//!
+//! Lines go out in `/append/batch` requests. Each entry's idempotency key is
+//! its leaf hash, and the payload names the host, file, inode and offset.
+//! A batch resent after a timeout or a crash is therefore never appended
+//! twice. The cursor of each file is saved once its lines are in the log, so
+//! a restart neither repeats nor skips lines.
+
+mod config;
+mod cursors;
+mod tail;
+
+use std::{collections::BTreeSet, sync::Arc, time::Duration};
+
+use anyhow::Context;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::Parser;
+use notify::{RecursiveMode, Watcher};
+use reality_client::Client;
+use reality_config::Layered;
+use reality_core::{leaf_hash, BatchEntry};
+use serde::Serialize;
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+use crate::{
+    config::{Cli, Config, FileConfig},
+    cursors::CursorStore,
+    tail::{Line, Tail},
+};
+
+/// Longest pause between attempts while logd is unavailable.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// The payload appended for one line. Lines that are not UTF-8 are carried
+/// in `line_base64` instead of `line`.
+#[derive(Serialize)]
+struct Shipped<'a> {
+    source: &'static str,
+    host: &'a str,
+    file: &'a str,
+    path: String,
+    inode: u64,
+    offset: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line_base64: Option<String>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let config = Config::load(Cli::parse())?;
+    std::fs::create_dir_all(&config.data_dir).context("create data dir")?;
+    let mut cursors = CursorStore::load(config.data_dir.join("ship-cursors.json"))?;
+    let mut builder = Client::builder(&config.log_url);
+    if let Some(token) = &config.token {
+        builder = builder.token(token);
+    }
+    let client = builder.build()?;
+    let host = config.host();
+
+    let mut tails: Vec<(FileConfig, Tail)> = config
+        .files
+        .iter()
+        .map(|file| {
+            let cursor = cursors.get(&file.name);
+            info!(file = %file.name, path = %file.path.display(), offset = cursor.offset, "shipping");
+            let tail = Tail::new(file.path.clone(), cursor, config.max_line);
+            (file.clone(), tail)
+        })
+        .collect();
+
+    // Directories rather than files are watched, so creation of a rotated
+    // file wakes the loop too. Polling covers anything the watcher misses.
+    let wake = Arc::new(Notify::new());
+    let notify_wake = wake.clone();
+    let mut watcher = notify::recommended_watcher(move |_| notify_wake.notify_one())?;
+    let dirs: BTreeSet<_> = tails
+        .iter()
+        .filter_map(|(_, tail)| tail.path().parent().map(|dir| dir.to_path_buf()))
+        .collect();
+    for dir in dirs {
+        if let Err(err) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+            warn!(dir = %dir.display(), %err, "cannot watch directory; polling only");
+        }
+    }
+
+    loop {
+        let batch = collect(&mut tails, config.batch_size);
+        if batch.is_empty() {
+            tokio::select! {
+                () = wake.notified() => {}
+                () = tokio::time::sleep(config.poll_interval()) => {}
+            }
+            continue;
+        }
+
+        let entries = batch
+            .iter()
+            .map(|(i, line)| entry(&host, &tails[*i].0, line))
+            .collect();
+        let duplicates = append(&client, entries).await;
+        for (i, line) in &batch {
+            cursors.set(&tails[*i].0.name, line.next);
+        }
+        cursors.save()?;
+        info!(lines = batch.len(), duplicates, "shipped batch");
+    }
+}
+
+/// Up to `limit` new lines across all files, with the index of their file.
+fn collect(tails: &mut [(FileConfig, Tail)], limit: usize) -> Vec<(usize, Line)> {
+    let mut batch = Vec::new();
+    for (i, (file, tail)) in tails.iter_mut().enumerate() {
+        match tail.read_lines(limit - batch.len()) {
+            Ok(lines) => batch.extend(lines.into_iter().map(|line| (i, line))),
+            Err(err) => warn!(file = %file.name, %err, "cannot read file"),
+        }
+        if batch.len() == limit {
+            break;
+        }
+    }
+    batch
+}
+
+fn entry(host: &str, file: &FileConfig, line: &Line) -> BatchEntry {
+    let text = std::str::from_utf8(&line.bytes).ok();
+    let payload = serde_json::to_string(&Shipped {
+        source: "file",
+        host,
+        file: &file.name,
+        path: file.path.display().to_string(),
+        inode: line.inode,
+        offset: line.offset,
+        line: text,
+        line_base64: text.is_none().then(|| STANDARD.encode(&line.bytes)),
+    })
+    .expect("line serializes");
+    let key = format!("ship:{}", hex::encode(leaf_hash(payload.as_bytes())));
+    BatchEntry {
+        payload,
+        idempotency_key: Some(key),
+        labels: Default::default(),
+        signature: None,
+    }
+}
+
+/// Send one batch until logd takes it; returns how many entries it already
+/// held. Every entry is keyed, so resending is always safe.
+async fn append(client: &Client, entries: Vec<BatchEntry>) -> usize {
+    let mut backoff = Duration::from_millis(250);
+    loop {
+        match client.append_batch(entries.clone()).await {
+            Ok(response) => {
+                return response
+                    .entries
+                    .iter()
+                    .filter(|entry| entry.duplicate)
+                    .count()
+            }
+            Err(err) => {
+                warn!(%err, retry_in = ?backoff, "append failed");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
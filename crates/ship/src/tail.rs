@@ -0,0 +1,212 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// How far a file has been shipped: `offset` is the first byte not yet
+/// appended, in the file with inode `inode`. The inode tells a rotated or
+/// replaced file from the one the offset belongs to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cursor {
+    pub inode: u64,
+    pub offset: u64,
+}
+
+/// One complete line, without its line break.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub inode: u64,
+    pub offset: u64,
+    pub bytes: Vec<u8>,
+    /// The cursor once this line is appended.
+    pub next: Cursor,
+}
+
+/// Follows one path across rotation. The open handle is read to its end
+/// before a new file at the path is picked up, so lines written just before
+/// a rename are not lost. A file that shrinks below the read position was
+/// truncated in place and is read again from the start.
+pub struct Tail {
+    path: PathBuf,
+    max_line: usize,
+    reader: Option<BufReader<File>>,
+    /// Read position; runs ahead of what has been appended.
+    position: Cursor,
+}
+
+impl Tail {
+    /// Resume at `cursor` if the file at `path` is still the one it refers
+    /// to; otherwise start at the beginning of whatever is there.
+    pub fn new(path: PathBuf, cursor: Cursor, max_line: usize) -> Self {
+        Self {
+            path,
+            max_line,
+            reader: None,
+            position: cursor,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Up to `limit` new complete lines. A trailing line without a line
+    /// break is held back until it is finished, or until the file is
+    /// rotated away.
+    pub fn read_lines(&mut self, limit: usize) -> io::Result<Vec<Line>> {
+        let mut lines = Vec::new();
+        while lines.len() < limit {
+            if self.reader.is_none() && !self.open()? {
+                break;
+            }
+            match self.next_line()? {
+                Some(line) => lines.push(line),
+                None if self.rotated(&mut lines)? => continue,
+                None => break,
+            }
+        }
+        Ok(lines)
+    }
+
+    fn open(&mut self) -> io::Result<bool> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(err) => return Err(err),
+        };
+        let meta = file.metadata()?;
+        if meta.ino() != self.position.inode || meta.len() < self.position.offset {
+            self.position = Cursor {
+                inode: meta.ino(),
+                offset: 0,
+            };
+        }
+        let mut reader = BufReader::new(file);
+        reader.seek(SeekFrom::Start(self.position.offset))?;
+        self.reader = Some(reader);
+        Ok(true)
+    }
+
+    /// The next complete line, or `None` at the end of what has been written.
+    fn next_line(&mut self) -> io::Result<Option<Line>> {
+        let reader = self.reader.as_mut().expect("file is open");
+        let mut bytes = Vec::new();
+        let read = reader
+            .by_ref()
+            .take(self.max_line as u64 + 1)
+            .read_until(b'\n', &mut bytes)?;
+        let complete = bytes.ends_with(b"\n");
+        if !complete && read <= self.max_line {
+            // Unfinished; read it again once more has been written.
+            reader.seek(SeekFrom::Start(self.position.offset))?;
+            return Ok(None);
+        }
+        let used = if complete { read } else { self.max_line };
+        if !complete {
+            reader.seek(SeekFrom::Start(self.position.offset + used as u64))?;
+            bytes.truncate(used);
+        }
+        Ok(Some(self.take(bytes, used)))
+    }
+
+    /// At the end of the open file: if the path now names another file, or
+    /// this one was truncated, switch to reading that from the start. An
+    /// unfinished last line of the old file is final by then and is added to
+    /// `lines`.
+    fn rotated(&mut self, lines: &mut Vec<Line>) -> io::Result<bool> {
+        let current = match std::fs::metadata(&self.path) {
+            Ok(meta) => meta,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(err) => return Err(err),
+        };
+        if current.ino() == self.position.inode {
+            if current.len() >= self.position.offset {
+                return Ok(false);
+            }
+        } else {
+            let reader = self.reader.as_mut().expect("file is open");
+            let mut rest = Vec::new();
+            reader.read_to_end(&mut rest)?;
+            if !rest.is_empty() {
+                let used = rest.len();
+                lines.push(self.take(rest, used));
+            }
+        }
+        self.reader = None;
+        self.position = Cursor {
+            inode: current.ino(),
+            offset: 0,
+        };
+        Ok(true)
+    }
+
+    fn take(&mut self, mut bytes: Vec<u8>, used: usize) -> Line {
+        let offset = self.position.offset;
+        self.position.offset += used as u64;
+        if bytes.ends_with(b"\n") {
+            bytes.pop();
+            if bytes.ends_with(b"\r") {
+                bytes.pop();
+            }
+        }
+        Line {
+            inode: self.position.inode,
+            offset,
+            bytes,
+            next: self.position,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn texts(lines: &[Line]) -> Vec<&str> {
+        lines
+            .iter()
+            .map(|line| std::str::from_utf8(&line.bytes).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn follows_appends_rotation_and_truncation() {
+        let dir = std::env::temp_dir().join(format!("reality-ship-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("app.log");
+        std::fs::write(&path, "one\r\ntwo\nthr").unwrap();
+
+        let mut tail = Tail::new(path.clone(), Cursor::default(), 8);
+        assert_eq!(texts(&tail.read_lines(10).unwrap()), ["one", "two"]);
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b"ee\nfour").unwrap();
+        let lines = tail.read_lines(10).unwrap();
+        assert_eq!(texts(&lines), ["three"]);
+
+        // A restart resumes at the last appended line.
+        let mut resumed = Tail::new(path.clone(), lines[0].next, 8);
+        file.write_all(b"\n").unwrap();
+        assert_eq!(texts(&resumed.read_lines(10).unwrap()), ["four"]);
+
+        file.write_all(b"five").unwrap();
+        std::fs::rename(&path, dir.join("app.log.1")).unwrap();
+        std::fs::write(&path, "six\n0123456789\n").unwrap();
+        assert_eq!(
+            texts(&resumed.read_lines(10).unwrap()),
+            ["five", "six", "01234567", "89"]
+        );
+
+        std::fs::write(&path, "new\n").unwrap();
+        assert_eq!(texts(&resumed.read_lines(10).unwrap()), ["new"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
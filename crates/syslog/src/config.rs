@@ -0,0 +1,168 @@
+use std::{net::SocketAddr, path::PathBuf};
+
+use anyhow::Context;
+use clap::Parser;
+use reality_config::Layered;
+use serde::Deserialize;
+
+/// Command-line flags. Anything left unset falls back to the config file and
+/// then to defaults.
+#[derive(Debug, Parser)]
+#[command(
+    name = "reality-syslog",
+    version,
+    about = "Receives RFC 5424 syslog and appends each message to a RealityLog"
+)]
+pub struct Cli {
+    /// Path to a TOML config file.
+    #[arg(long, env = "REALITY_SYSLOG_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Base URL of the logd instance to append to.
+    #[arg(long, env = "REALITY_SYSLOG_LOG_URL")]
+    pub log_url: Option<String>,
+    /// Bearer token sent with every append.
+    #[arg(long, env = "REALITY_SYSLOG_TOKEN")]
+    pub token: Option<String>,
+    /// Address to receive syslog datagrams on (RFC 5426).
+    #[arg(long)]
+    pub udp: Option<SocketAddr>,
+    /// Address to receive plain TCP syslog on (RFC 6587).
+    #[arg(long)]
+    pub tcp: Option<SocketAddr>,
+    /// Address to receive syslog over TLS on (RFC 5425).
+    #[arg(long)]
+    pub tls: Option<SocketAddr>,
+    /// PEM certificate chain for the TLS listener.
+    #[arg(long)]
+    pub tls_cert: Option<PathBuf>,
+    /// PEM private key for the TLS listener.
+    #[arg(long)]
+    pub tls_key: Option<PathBuf>,
+    /// Largest message accepted, in bytes. Longer TCP frames close the
+    /// connection.
+    #[arg(long)]
+    pub max_message: Option<usize>,
+}
+
+/// Fully resolved ingestion configuration.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub log_url: String,
+    pub token: Option<String>,
+    pub udp: Option<SocketAddr>,
+    pub tcp: Option<SocketAddr>,
+    pub tls: Option<TlsConfig>,
+    pub max_message: usize,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub listen: SocketAddr,
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            log_url: "http://127.0.0.1:8080".into(),
+            token: None,
+            udp: None,
+            tcp: None,
+            tls: None,
+            max_message: 64 * 1024,
+        }
+    }
+}
+
+impl Layered<Cli> for Config {
+    fn file(cli: &Cli) -> Option<PathBuf> {
+        cli.config.clone()
+    }
+
+    fn apply(&mut self, cli: Cli) -> anyhow::Result<()> {
+        if let Some(log_url) = cli.log_url {
+            self.log_url = log_url;
+        }
+        if let Some(token) = cli.token {
+            self.token = Some(token);
+        }
+        if let Some(udp) = cli.udp {
+            self.udp = Some(udp);
+        }
+        if let Some(tcp) = cli.tcp {
+            self.tcp = Some(tcp);
+        }
+        if let Some(max_message) = cli.max_message {
+            self.max_message = max_message;
+        }
+        match (cli.tls, cli.tls_cert, cli.tls_key) {
+            (None, None, None) => {}
+            (Some(listen), Some(cert), Some(key)) => {
+                self.tls = Some(TlsConfig { listen, cert, key })
+            }
+            (listen, cert, key) => {
+                let tls = self.tls.as_mut().context(
+                    "--tls, --tls-cert and --tls-key must be given together \
+                     unless the config file has a [tls] section",
+                )?;
+                tls.listen = listen.unwrap_or(tls.listen);
+                tls.cert = cert.unwrap_or_else(|| tls.cert.clone());
+                tls.key = key.unwrap_or_else(|| tls.key.clone());
+            }
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.udp.is_some() || self.tcp.is_some() || self.tls.is_some(),
+            "at least one of udp, tcp or tls must be configured"
+        );
+        anyhow::ensure!(self.max_message > 0, "max_message must be positive");
+        Ok(())
+    }
+}
+
+impl Config {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_complete_the_tls_section_from_the_file() {
+        let mut config: Config = toml::from_str(
+            r#"
+            udp = "0.0.0.0:514"
+
+            [tls]
+            listen = "0.0.0.0:6514"
+            cert = "/etc/reality/syslog.crt"
+            key = "/etc/reality/syslog.key"
+            "#,
+        )
+        .expect("config");
+        let cli = Cli::parse_from([
+            "reality-syslog",
+            "--tls-cert",
+            "/run/cert.pem",
+            "--max-message",
+            "2048",
+        ]);
+        config.apply(cli).unwrap();
+        config.validate().unwrap();
+        let tls = config.tls.as_ref().unwrap();
+        assert_eq!(tls.cert, PathBuf::from("/run/cert.pem"));
+        assert_eq!(tls.listen.port(), 6514);
+        assert_eq!(config.max_message, 2048);
+
+        let mut bare = Config::default();
+        assert!(bare
+            .apply(Cli::parse_from(["reality-syslog", "--tls", "0.0.0.0:6514"]))
+            .is_err());
+        assert!(bare.validate().is_err());
+    }
+}
@@ -0,0 +1,75 @@
+use std::io;
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+
+/// Read the next message from a syslog stream (RFC 6587). A frame starting
+/// with a digit uses octet counting, `LEN SP MSG`, which RFC 5425 requires
+/// over TLS. Anything else is taken as newline-terminated. Returns `None`
+/// at a clean end of stream.
+pub async fn read_frame<R>(reader: &mut R, max: usize) -> io::Result<Option<Vec<u8>>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let first = match reader.fill_buf().await?.first() {
+        Some(byte) => *byte,
+        None => return Ok(None),
+    };
+    if first.is_ascii_digit() {
+        let mut len = Vec::new();
+        (&mut *reader).take(11).read_until(b' ', &mut len).await?;
+        let len = std::str::from_utf8(len.strip_suffix(b" ").unwrap_or_default())
+            .ok()
+            .and_then(|len| len.parse::<usize>().ok())
+            .ok_or_else(|| invalid("malformed octet count"))?;
+        if len > max {
+            return Err(invalid(format!("frame of {len} bytes exceeds {max}")));
+        }
+        let mut frame = vec![0; len];
+        reader.read_exact(&mut frame).await?;
+        return Ok(Some(frame));
+    }
+
+    let mut frame = Vec::new();
+    (&mut *reader)
+        .take(max as u64 + 1)
+        .read_until(b'\n', &mut frame)
+        .await?;
+    match frame.strip_suffix(b"\n") {
+        Some(line) => Ok(Some(line.strip_suffix(b"\r").unwrap_or(line).to_vec())),
+        None if frame.len() > max => Err(invalid(format!("line exceeds {max} bytes"))),
+        // The peer closed mid-line; keep what it sent.
+        None => Ok(Some(frame)),
+    }
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reads_octet_counted_and_newline_frames() {
+        let mut stream: &[u8] = b"12 <13>1 - - -\n<14>1 two\r\n5 fiveX";
+        assert_eq!(
+            read_frame(&mut stream, 64).await.unwrap().unwrap(),
+            b"<13>1 - - -\n"
+        );
+        assert_eq!(
+            read_frame(&mut stream, 64).await.unwrap().unwrap(),
+            b"<14>1 two"
+        );
+        assert_eq!(
+            read_frame(&mut stream, 64).await.unwrap().unwrap(),
+            b"fiveX"
+        );
+        assert_eq!(read_frame(&mut stream, 64).await.unwrap(), None);
+
+        let mut oversized: &[u8] = b"100 <13>1";
+        assert!(read_frame(&mut oversized, 64).await.is_err());
+        let mut long_line: &[u8] = b"<13>1 aaaaaaaaaa\n";
+        assert!(read_frame(&mut long_line, 8).await.is_err());
+    }
+}
@@ -0,0 +1,123 @@
+use std::{fs::File, io::BufReader as StdBufReader, net::SocketAddr, path::Path, sync::Arc};
+
+use anyhow::Context;
+use time::OffsetDateTime;
+use tokio::{
+    io::{AsyncRead, BufReader},
+    net::{TcpListener, UdpSocket},
+    sync::mpsc,
+};
+use tokio_rustls::{rustls, TlsAcceptor};
+use tracing::{info, warn};
+
+use crate::{
+    config::TlsConfig,
+    framing::read_frame,
+    message::{Entry, Transport},
+};
+
+/// Receive datagrams, one message each (RFC 5426).
+pub async fn udp(addr: SocketAddr, max: usize, inbox: mpsc::Sender<Entry>) -> anyhow::Result<()> {
+    let socket = UdpSocket::bind(addr)
+        .await
+        .with_context(|| format!("bind udp {addr}"))?;
+    info!(%addr, "receiving syslog over udp");
+    let mut buf = vec![0; 65_535];
+    loop {
+        let (len, peer) = socket.recv_from(&mut buf).await?;
+        if len > max {
+            warn!(%peer, len, "dropping oversized syslog datagram");
+            continue;
+        }
+        let entry = Entry::new(Transport::Udp, peer, OffsetDateTime::now_utc(), &buf[..len]);
+        if inbox.send(entry).await.is_err() {
+            return Ok(());
+        }
+    }
+}
+
+pub async fn tcp(addr: SocketAddr, max: usize, inbox: mpsc::Sender<Entry>) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("bind tcp {addr}"))?;
+    info!(%addr, "receiving syslog over tcp");
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        tokio::spawn(stream_messages(
+            stream,
+            Transport::Tcp,
+            peer,
+            max,
+            inbox.clone(),
+        ));
+    }
+}
+
+pub async fn tls(config: TlsConfig, max: usize, inbox: mpsc::Sender<Entry>) -> anyhow::Result<()> {
+    let acceptor = acceptor(&config.cert, &config.key)?;
+    let listener = TcpListener::bind(config.listen)
+        .await
+        .with_context(|| format!("bind tls {}", config.listen))?;
+    info!(addr = %config.listen, "receiving syslog over tls");
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let acceptor = acceptor.clone();
+        let inbox = inbox.clone();
+        tokio::spawn(async move {
+            match acceptor.accept(stream).await {
+                Ok(stream) => stream_messages(stream, Transport::Tls, peer, max, inbox).await,
+                Err(err) => warn!(%peer, %err, "tls handshake failed"),
+            }
+        });
+    }
+}
+
+/// Forward every frame on one connection until the peer closes it or sends
+/// something that cannot be framed.
+async fn stream_messages<S>(
+    stream: S,
+    transport: Transport,
+    peer: SocketAddr,
+    max: usize,
+    inbox: mpsc::Sender<Entry>,
+) where
+    S: AsyncRead + Unpin,
+{
+    let mut reader = BufReader::new(stream);
+    loop {
+        match read_frame(&mut reader, max).await {
+            Ok(Some(frame)) => {
+                let entry = Entry::new(transport, peer, OffsetDateTime::now_utc(), &frame);
+                if inbox.send(entry).await.is_err() {
+                    return;
+                }
+            }
+            Ok(None) => return,
+            Err(err) => {
+                warn!(%peer, %err, "closing syslog connection");
+                return;
+            }
+        }
+    }
+}
+
+fn acceptor(cert: &Path, key: &Path) -> anyhow::Result<TlsAcceptor> {
+    let certs = rustls_pemfile::certs(&mut StdBufReader::new(
+        File::open(cert).with_context(|| format!("open {}", cert.display()))?,
+    ))
+    .collect::<Result<Vec<_>, _>>()
+    .with_context(|| format!("read certificates from {}", cert.display()))?;
+    let key = rustls_pemfile::private_key(&mut StdBufReader::new(
+        File::open(key).with_context(|| format!("open {}", key.display()))?,
+    ))
+    .with_context(|| format!("read private key from {}", key.display()))?
+    .with_context(|| format!("no private key in {}", key.display()))?;
+    let config = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()?
+    .with_no_client_auth()
+    .with_single_cert(certs, key)
+    .context("load tls certificate")?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
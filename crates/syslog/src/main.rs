@@ -0,0 +1,92 @@
+//! `reality-syslog`: receives RFC 5424 syslog over UDP, TCP or TLS and
+//! appends every message to logd. Each entry keeps the message's original
+//! bytes next to its parsed fields, so what a host sent can later be proven
+//! byte for byte.
+
+mod config;
+mod framing;
+mod listener;
+mod message;
+
+use std::time::Duration;
+
+use clap::Parser;
+use reality_client::{Client, Error};
+use reality_config::Layered;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+use crate::{
+    config::{Cli, Config},
+    message::Entry,
+};
+
+/// Longest pause between attempts while logd is unavailable.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let config = Config::load(Cli::parse())?;
+    let mut builder = Client::builder(&config.log_url);
+    if let Some(token) = &config.token {
+        builder = builder.token(token);
+    }
+    let client = builder.build()?;
+
+    // Listeners wait here while logd is slow or down, which pushes back on
+    // TCP senders instead of buffering without bound.
+    let (inbox, entries) = mpsc::channel(1024);
+    let mut listeners = tokio::task::JoinSet::new();
+    if let Some(addr) = config.udp {
+        listeners.spawn(listener::udp(addr, config.max_message, inbox.clone()));
+    }
+    if let Some(addr) = config.tcp {
+        listeners.spawn(listener::tcp(addr, config.max_message, inbox.clone()));
+    }
+    if let Some(tls) = config.tls.clone() {
+        listeners.spawn(listener::tls(tls, config.max_message, inbox.clone()));
+    }
+    drop(inbox);
+
+    tokio::select! {
+        () = append_all(&client, entries) => Ok(()),
+        Some(result) = listeners.join_next() => result?,
+    }
+}
+
+/// Append entries in the order they arrived. Failures that may be temporary
+/// are retried until they succeed, so a message can be appended twice when
+/// logd stored it but the response was lost. Rejected entries are dropped.
+async fn append_all(client: &Client, mut entries: mpsc::Receiver<Entry>) {
+    while let Some(entry) = entries.recv().await {
+        let payload = entry.to_payload();
+        let mut backoff = Duration::from_millis(250);
+        loop {
+            match client.append(payload.clone()).await {
+                Ok(appended) => {
+                    info!(index = appended.index, peer = %entry.peer, "appended syslog message");
+                    break;
+                }
+                Err(err) if is_temporary(&err) => {
+                    warn!(%err, retry_in = ?backoff, "append failed");
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+                Err(err) => {
+                    error!(%err, peer = %entry.peer, "logd rejected syslog message");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+fn is_temporary(err: &Error) -> bool {
+    match err {
+        Error::Transport(_) => true,
+        Error::Status { status, .. } => status.is_server_error() || status.as_u16() == 429,
+        _ => false,
+    }
+}
@@ -0,0 +1,307 @@
+use std::net::SocketAddr;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Serialize;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime, UtcOffset};
+
+/// An RFC 5424 message split into its fields. NILVALUE (`-`) fields are
+/// `None`, so two messages that differ only in spelling compare equal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SyslogMessage {
+    pub facility: u8,
+    pub severity: u8,
+    pub version: u8,
+    /// Normalized to UTC.
+    pub timestamp: Option<String>,
+    pub hostname: Option<String>,
+    pub app_name: Option<String>,
+    pub procid: Option<String>,
+    pub msgid: Option<String>,
+    pub structured_data: Vec<SdElement>,
+    /// The free-form part without a leading BOM or trailing line break.
+    /// Bytes that are not UTF-8 are replaced here but kept in the raw copy.
+    pub msg: Option<String>,
+}
+
+/// One `[id name="value" ...]` element, with escapes resolved and the
+/// parameters in the order they were sent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SdElement {
+    pub id: String,
+    pub params: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    Udp,
+    Tcp,
+    Tls,
+}
+
+/// The payload appended for one received message. `raw_base64` holds the
+/// bytes exactly as they arrived. The parsed `message` is there to be
+/// searched, and is `null` with a `parse_error` when it does not follow
+/// RFC 5424, so malformed input is still recorded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Entry {
+    pub source: &'static str,
+    pub transport: Transport,
+    pub peer: SocketAddr,
+    pub received_at: String,
+    pub message: Option<SyslogMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parse_error: Option<String>,
+    pub raw_base64: String,
+}
+
+impl Entry {
+    pub fn new(
+        transport: Transport,
+        peer: SocketAddr,
+        received_at: OffsetDateTime,
+        raw: &[u8],
+    ) -> Self {
+        let (message, parse_error) = match parse(raw) {
+            Ok(message) => (Some(message), None),
+            Err(err) => (None, Some(err)),
+        };
+        Self {
+            source: "syslog",
+            transport,
+            peer,
+            received_at: utc(received_at),
+            message,
+            parse_error,
+            raw_base64: STANDARD.encode(raw),
+        }
+    }
+
+    /// The canonical JSON text appended to the log.
+    pub fn to_payload(&self) -> String {
+        serde_json::to_string(self).expect("syslog entry serializes")
+    }
+}
+
+/// Parse one RFC 5424 message.
+pub fn parse(raw: &[u8]) -> Result<SyslogMessage, String> {
+    let mut input = Cursor { raw, pos: 0 };
+    input.expect(b'<')?;
+    let pri = input.number(3, "PRI")?;
+    input.expect(b'>')?;
+    if pri > 191 {
+        return Err(format!("PRI {pri} is out of range"));
+    }
+    let version = input.number(2, "VERSION")?;
+    if version == 0 {
+        return Err("VERSION must not be 0".into());
+    }
+    input.expect(b' ')?;
+    let timestamp = input
+        .field(128, "TIMESTAMP")?
+        .map(|stamp| {
+            OffsetDateTime::parse(&stamp, &Rfc3339)
+                .map(utc)
+                .map_err(|err| format!("invalid TIMESTAMP {stamp:?}: {err}"))
+        })
+        .transpose()?;
+    let hostname = input.field(255, "HOSTNAME")?;
+    let app_name = input.field(48, "APP-NAME")?;
+    let procid = input.field(128, "PROCID")?;
+    let msgid = input.field(32, "MSGID")?;
+    let structured_data = input.structured_data()?;
+    let msg = match input.rest() {
+        [] => None,
+        [b' ', msg @ ..] => {
+            let msg = msg.strip_prefix(b"\xef\xbb\xbf").unwrap_or(msg);
+            let text = String::from_utf8_lossy(msg);
+            let text = text.trim_end_matches(['\r', '\n']);
+            (!text.is_empty()).then(|| text.to_string())
+        }
+        [b'\r' | b'\n', ..] if input.rest().iter().all(|b| b"\r\n".contains(b)) => None,
+        _ => return Err("expected a space before MSG".into()),
+    };
+    Ok(SyslogMessage {
+        facility: (pri / 8) as u8,
+        severity: (pri % 8) as u8,
+        version: version as u8,
+        timestamp,
+        hostname,
+        app_name,
+        procid,
+        msgid,
+        structured_data,
+        msg,
+    })
+}
+
+fn utc(at: OffsetDateTime) -> String {
+    at.to_offset(UtcOffset::UTC)
+        .format(&Rfc3339)
+        .expect("timestamp formats")
+}
+
+struct Cursor<'a> {
+    raw: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.raw.get(self.pos).copied()
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.raw[self.pos..]
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        if self.peek() != Some(byte) {
+            return Err(format!("expected {:?} at byte {}", byte as char, self.pos));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn number(&mut self, max_digits: usize, what: &str) -> Result<u16, String> {
+        let digits = self
+            .rest()
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if digits == 0 || digits > max_digits {
+            return Err(format!("invalid {what}"));
+        }
+        let value = std::str::from_utf8(&self.rest()[..digits])
+            .expect("ascii digits")
+            .parse()
+            .expect("at most 3 digits");
+        self.pos += digits;
+        Ok(value)
+    }
+
+    /// A header field and the space after it; `-` is NILVALUE.
+    fn field(&mut self, max_len: usize, what: &str) -> Result<Option<String>, String> {
+        let len = self.rest().iter().take_while(|b| **b != b' ').count();
+        let value = &self.rest()[..len];
+        if value.is_empty()
+            || value.len() > max_len
+            || !value.iter().all(|b| (33..=126).contains(b))
+        {
+            return Err(format!("invalid {what}"));
+        }
+        let value = std::str::from_utf8(value).expect("printable ascii");
+        self.pos += len;
+        self.expect(b' ')?;
+        Ok((value != "-").then(|| value.to_string()))
+    }
+
+    fn structured_data(&mut self) -> Result<Vec<SdElement>, String> {
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+            return Ok(Vec::new());
+        }
+        let mut elements = Vec::new();
+        while self.peek() == Some(b'[') {
+            self.pos += 1;
+            let id = self.sd_name("SD-ID")?;
+            let mut params = Vec::new();
+            while self.peek() == Some(b' ') {
+                self.pos += 1;
+                let name = self.sd_name("PARAM-NAME")?;
+                self.expect(b'=')?;
+                self.expect(b'"')?;
+                params.push((name, self.param_value()?));
+            }
+            self.expect(b']')?;
+            elements.push(SdElement { id, params });
+        }
+        if elements.is_empty() {
+            return Err("invalid STRUCTURED-DATA".into());
+        }
+        Ok(elements)
+    }
+
+    fn sd_name(&mut self, what: &str) -> Result<String, String> {
+        let len = self
+            .rest()
+            .iter()
+            .take_while(|b| (33..=126).contains(*b) && !b"= ]\"".contains(b))
+            .count();
+        if len == 0 || len > 32 {
+            return Err(format!("invalid {what}"));
+        }
+        let name = std::str::from_utf8(&self.rest()[..len]).expect("printable ascii");
+        self.pos += len;
+        Ok(name.to_string())
+    }
+
+    /// A quoted PARAM-VALUE after its opening quote. Only `\"`, `\\` and
+    /// `\]` are escapes; any other backslash is kept as sent.
+    fn param_value(&mut self) -> Result<String, String> {
+        let mut value = Vec::new();
+        loop {
+            match self.rest() {
+                [b'\\', escaped @ (b'"' | b'\\' | b']'), ..] => {
+                    value.push(*escaped);
+                    self.pos += 2;
+                }
+                [b'"', ..] => {
+                    self.pos += 1;
+                    return String::from_utf8(value)
+                        .map_err(|_| "PARAM-VALUE is not UTF-8".to_string());
+                }
+                [byte, ..] => {
+                    value.push(*byte);
+                    self.pos += 1;
+                }
+                [] => return Err("unterminated PARAM-VALUE".into()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonicalizes_rfc5424_and_keeps_the_raw_bytes() {
+        let raw = b"<165>1 2003-10-11T22:14:15.003-07:00 host.example evntslog - ID47 \
+[exampleSDID@32473 iut=\"3\" eventSource=\"App\\\"lication\"][meta x=\"a\\b\"] \xef\xbb\xbfAn event\n";
+        let message = parse(raw).unwrap();
+        assert_eq!((message.facility, message.severity), (20, 5));
+        assert_eq!(
+            message.timestamp.as_deref(),
+            Some("2003-10-12T05:14:15.003Z")
+        );
+        assert_eq!(message.procid, None);
+        assert_eq!(
+            message.structured_data[0].params[1],
+            ("eventSource".into(), "App\"lication".into())
+        );
+        assert_eq!(message.structured_data[1].params[0].1, "a\\b");
+        assert_eq!(message.msg.as_deref(), Some("An event"));
+
+        let bare = parse(b"<13>1 - - - - - -").unwrap();
+        assert_eq!((bare.hostname, bare.msg), (None, None));
+
+        let peer = "10.0.0.5:40000".parse().unwrap();
+        let entry = Entry::new(
+            Transport::Udp,
+            peer,
+            OffsetDateTime::UNIX_EPOCH,
+            b"<34>Oct 11 22:14:15 mymachine su: 'su root' failed",
+        );
+        assert!(entry.message.is_none());
+        assert!(entry.parse_error.is_some());
+        let payload: serde_json::Value = serde_json::from_str(&entry.to_payload()).unwrap();
+        assert_eq!(payload["received_at"], "1970-01-01T00:00:00Z");
+        assert_eq!(
+            STANDARD
+                .decode(payload["raw_base64"].as_str().unwrap())
+                .unwrap(),
+            b"<34>Oct 11 22:14:15 mymachine su: 'su root' failed"
+        );
+    }
+}
@@ -0,0 +1,103 @@
+//! The JSON each selected event is appended as.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Number, Value};
+use tracing::field::{Field, Visit};
+
+/// An appended event. Entries written by [`crate::AuditLayer`] deserialize
+/// back into this, so auditors can read them with the same type.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    /// Unix nanoseconds when the event was recorded, not when it was appended.
+    pub timestamp_nanos: String,
+    pub level: String,
+    pub target: String,
+    /// [`crate::LayerConfig::source`], naming the service that emitted it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub fields: Map<String, Value>,
+    /// The spans the event happened in, outermost first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spans: Vec<SpanContext>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpanContext {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub fields: Map<String, Value>,
+}
+
+/// A span's fields as recorded so far, kept in its extensions.
+pub(crate) struct SpanFields(pub(crate) Map<String, Value>);
+
+/// Records fields as JSON values: numbers and booleans as themselves, errors
+/// and `Debug` values as their text.
+pub(crate) struct JsonVisitor<'a>(pub(crate) &'a mut Map<String, Value>);
+
+impl JsonVisitor<'_> {
+    fn insert(&mut self, field: &Field, value: Value) {
+        self.0.insert(field.name().to_string(), value);
+    }
+}
+
+impl Visit for JsonVisitor<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        let value = Number::from_f64(value).map_or_else(|| value.to_string().into(), Value::Number);
+        self.insert(field, value);
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.into());
+    }
+
+    fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+        self.insert(field, value.to_string().into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.insert(field, format!("{value:?}").into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_out_empty_parts() {
+        let event = AuditEvent {
+            timestamp_nanos: "1".into(),
+            level: "INFO".into(),
+            target: "audit".into(),
+            source: None,
+            message: Some("login".into()),
+            fields: Map::new(),
+            spans: vec![SpanContext {
+                name: "request".into(),
+                fields: Map::new(),
+            }],
+        };
+        let json = serde_json::to_string(&event).unwrap();
+        assert_eq!(
+            json,
+            r#"{"timestamp_nanos":"1","level":"INFO","target":"audit","message":"login","spans":[{"name":"request"}]}"#
+        );
+        assert_eq!(serde_json::from_str::<AuditEvent>(&json).unwrap(), event);
+    }
+}
@@ -0,0 +1,357 @@
+//! A `tracing-subscriber` layer that makes application audit events
+//! tamper-evident by appending them to a RealityLog.
+//!
+//! Selected events (by default those with the `audit` target) become
+//! [`AuditEvent`] JSON payloads, together with the fields of the spans they
+//! happened in. A background task appends them to logd in `/append/batch`
+//! requests. Every event carries an idempotency key, so a batch that is sent
+//! again after a timeout is never appended twice.
+//!
+//! ```no_run
+//! # use tracing_subscriber::prelude::*;
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = reality_client::Client::new("http://127.0.0.1:8080")?;
+//! let (audit, handle) = reality_tracing::AuditLayer::spawn(client, Default::default())?;
+//! tracing_subscriber::registry().with(audit).init();
+//!
+//! tracing::info!(target: "audit", user = "alice", action = "delete", "record removed");
+//! handle.shutdown().await;
+//! # Ok(())
+//! # }
+//! ```
+
+mod event;
+mod worker;
+
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tracing::{
+    instrument::WithSubscriber,
+    span::{Attributes, Id, Record},
+    subscriber::NoSubscriber,
+    Event, Subscriber,
+};
+use tracing_subscriber::{
+    filter::{LevelFilter, Targets},
+    layer::{Context, Layer},
+    registry::LookupSpan,
+};
+
+pub use event::{AuditEvent, SpanContext};
+
+use crate::{
+    event::{JsonVisitor, SpanFields},
+    worker::Shared,
+};
+
+/// Most entries logd accepts in one `/append/batch` request.
+pub const MAX_BATCH: usize = 1000;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("failed to read random bytes for idempotency keys: {0}")]
+    Random(#[from] getrandom::Error),
+}
+
+/// What to do with an event when the queue is full, because logd is slow
+/// or unreachable.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Drop it and count it in [`Stats::dropped`]; the application never
+    /// waits on logd.
+    #[default]
+    Drop,
+    /// Block the thread that emitted it until there is room. Nothing is
+    /// lost, but every thread emitting audit events stalls with logd.
+    Block,
+}
+
+#[derive(Debug, Clone)]
+pub struct LayerConfig {
+    /// Events to append; the `audit` target at every level by default.
+    pub targets: Targets,
+    /// Recorded in every event, to tell services sharing a log apart.
+    pub source: Option<String>,
+    /// Events held while waiting for logd.
+    pub capacity: usize,
+    /// Events per request, at most [`MAX_BATCH`].
+    pub batch_size: usize,
+    /// Longest an event waits for its batch to fill before it is sent.
+    pub flush_interval: Duration,
+    pub overflow: Overflow,
+}
+
+impl Default for LayerConfig {
+    fn default() -> Self {
+        Self {
+            targets: Targets::new().with_target("audit", LevelFilter::TRACE),
+            source: None,
+            capacity: 10_000,
+            batch_size: 100,
+            flush_interval: Duration::from_secs(1),
+            overflow: Overflow::Drop,
+        }
+    }
+}
+
+/// Counters since the layer was spawned.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Stats {
+    /// Events logd acknowledged.
+    pub appended: u64,
+    /// Events lost to a full queue or emitted after shutdown.
+    pub dropped: u64,
+    /// Events in batches logd refused with a 4xx, which are not resent.
+    pub rejected: u64,
+    /// Batch attempts that failed and will be retried.
+    pub failures: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// The layer to add to a subscriber. Cloning it shares the same queue.
+#[derive(Clone)]
+pub struct AuditLayer {
+    shared: Arc<Shared>,
+}
+
+/// Controls the background task that appends queued events.
+pub struct AuditHandle {
+    shared: Arc<Shared>,
+    worker: JoinHandle<()>,
+}
+
+impl AuditLayer {
+    /// Create a layer appending to `client`'s log and spawn its worker on
+    /// the current tokio runtime. The worker's own requests are not traced,
+    /// so a subscriber selecting every target cannot feed back into itself.
+    pub fn spawn(
+        client: reality_client::Client,
+        mut config: LayerConfig,
+    ) -> Result<(Self, AuditHandle), Error> {
+        let mut prefix = [0u8; 8];
+        getrandom::getrandom(&mut prefix)?;
+        config.batch_size = config.batch_size.clamp(1, MAX_BATCH);
+        config.capacity = config.capacity.max(1);
+        let shared = Arc::new(Shared::new(config, hex::encode(prefix)));
+
+        let worker_shared = shared.clone();
+        let worker = tokio::spawn(
+            async move { worker::run(&worker_shared, client).await }
+                .with_subscriber(NoSubscriber::default()),
+        );
+        Ok((
+            Self {
+                shared: shared.clone(),
+            },
+            AuditHandle { shared, worker },
+        ))
+    }
+}
+
+impl AuditHandle {
+    pub fn stats(&self) -> Stats {
+        self.shared.stats()
+    }
+
+    /// Wait until every event queued so far has been appended or rejected.
+    pub async fn flush(&self) {
+        self.shared.flush().await;
+    }
+
+    /// Stop accepting events and wait until the queue is empty. While logd
+    /// is unreachable this keeps retrying; bound it with
+    /// `tokio::time::timeout` if the process has to exit regardless.
+    pub async fn shutdown(self) {
+        self.shared.close();
+        let _ = self.worker.await;
+    }
+}
+
+impl<S> Layer<S> for AuditLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = Map::new();
+        attrs.record(&mut JsonVisitor(&mut fields));
+        span.extensions_mut().insert(SpanFields(fields));
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(SpanFields(fields)) = extensions.get_mut::<SpanFields>() {
+            values.record(&mut JsonVisitor(fields));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let config = self.shared.config();
+        if !config
+            .targets
+            .would_enable(metadata.target(), metadata.level())
+        {
+            return;
+        }
+        let mut fields = Map::new();
+        event.record(&mut JsonVisitor(&mut fields));
+        let message = fields.remove("message").map(|message| match message {
+            Value::String(message) => message,
+            other => other.to_string(),
+        });
+        let spans = ctx
+            .event_scope(event)
+            .into_iter()
+            .flat_map(|scope| scope.from_root())
+            .map(|span| SpanContext {
+                name: span.name().to_string(),
+                fields: span
+                    .extensions()
+                    .get::<SpanFields>()
+                    .map(|fields| fields.0.clone())
+                    .unwrap_or_default(),
+            })
+            .collect();
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let event = AuditEvent {
+            timestamp_nanos: timestamp.as_nanos().to_string(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            source: config.source.clone(),
+            message,
+            fields,
+            spans,
+        };
+        if let Ok(payload) = serde_json::to_string(&event) {
+            self.shared.push(payload);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use axum::{extract::State, routing::post, Json, Router};
+    use reality_core::{BatchAppendRequest, BatchAppendResponse, BatchAppended, BatchEntry};
+    use tracing_subscriber::prelude::*;
+
+    use super::*;
+
+    type Received = Arc<Mutex<Vec<BatchEntry>>>;
+
+    async fn serve() -> (String, Received) {
+        let received = Received::default();
+        let app = Router::new()
+            .route(
+                "/append/batch",
+                post(
+                    |State(received): State<Received>, Json(req): Json<BatchAppendRequest>| async move {
+                        let mut received = received.lock().unwrap();
+                        let start = received.len() as u64;
+                        let entries = (start..).zip(&req.entries).map(|(index, _)| BatchAppended {
+                            index,
+                            leaf: String::new(),
+                            meta: None,
+                            duplicate: false,
+                        });
+                        let response = BatchAppendResponse {
+                            entries: entries.collect(),
+                            size: start + req.entries.len() as u64,
+                            root: String::new(),
+                        };
+                        received.extend(req.entries);
+                        Json(response)
+                    },
+                ),
+            )
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (format!("http://{addr}/"), received)
+    }
+
+    #[tokio::test]
+    async fn appends_selected_events_with_their_spans() {
+        let (url, received) = serve().await;
+        let config = LayerConfig {
+            source: Some("billing".into()),
+            batch_size: 2,
+            ..Default::default()
+        };
+        let (layer, handle) =
+            AuditLayer::spawn(reality_client::Client::new(url).unwrap(), config).unwrap();
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("request", id = 7, user = tracing::field::Empty);
+            let _entered = span.enter();
+            span.record("user", "alice");
+            tracing::info!(target: "audit", amount = 1.5, refund = true, "refund issued");
+            tracing::info!("not an audit event");
+            tracing::warn!(target: "audit", "second");
+            tracing::debug!(target: "audit::export", rows = 3u64);
+        });
+        handle.flush().await;
+
+        let entries = received.lock().unwrap().clone();
+        assert_eq!(entries.len(), 3);
+        let keys: std::collections::HashSet<_> = entries
+            .iter()
+            .map(|entry| entry.idempotency_key.clone().unwrap())
+            .collect();
+        assert_eq!(keys.len(), 3);
+
+        let first: AuditEvent = serde_json::from_str(&entries[0].payload).unwrap();
+        assert_eq!(first.level, "INFO");
+        assert_eq!(first.source.as_deref(), Some("billing"));
+        assert_eq!(first.message.as_deref(), Some("refund issued"));
+        assert_eq!(first.fields["amount"], 1.5);
+        assert_eq!(first.fields["refund"], true);
+        assert_eq!(first.spans[0].name, "request");
+        assert_eq!(first.spans[0].fields["id"], 7);
+        assert_eq!(first.spans[0].fields["user"], "alice");
+        let last: AuditEvent = serde_json::from_str(&entries[2].payload).unwrap();
+        assert_eq!(last.target, "audit::export");
+        assert_eq!(last.message, None);
+
+        assert_eq!(handle.stats().appended, 3);
+        handle.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn drops_events_when_the_queue_is_full() {
+        // Nothing listens on port 9, so every batch fails and stays queued.
+        let client = reality_client::Client::new("http://127.0.0.1:9/").unwrap();
+        let config = LayerConfig {
+            capacity: 2,
+            batch_size: 1,
+            ..Default::default()
+        };
+        let (layer, handle) = AuditLayer::spawn(client, config).unwrap();
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+            for i in 0..10 {
+                tracing::info!(target: "audit", i);
+            }
+        });
+        let stats = handle.stats();
+        assert_eq!(stats.appended, 0);
+        assert!(stats.dropped >= 7, "{stats:?}");
+    }
+}
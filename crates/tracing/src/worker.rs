@@ -0,0 +1,185 @@
+//! The queue between the layer and logd, and the task that drains it in
+//! batches.
+
+use std::{
+    collections::VecDeque,
+    sync::{Condvar, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use reality_client::{Client, Error};
+use reality_core::BatchEntry;
+use reqwest::StatusCode;
+use tokio::sync::{oneshot, Notify};
+
+use crate::{LayerConfig, Overflow, Stats};
+
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+pub(crate) struct Shared {
+    config: LayerConfig,
+    /// Random per layer, so keys from different processes never collide.
+    key_prefix: String,
+    state: Mutex<State>,
+    /// Signalled when the worker takes events off a full queue.
+    space: Condvar,
+    /// Wakes the worker early: a batch filled, a flush, or shutdown.
+    wake: Notify,
+}
+
+#[derive(Default)]
+struct State {
+    queue: VecDeque<BatchEntry>,
+    next: u64,
+    closed: bool,
+    flushes: Vec<oneshot::Sender<()>>,
+    stats: Stats,
+}
+
+impl Shared {
+    pub(crate) fn new(config: LayerConfig, key_prefix: String) -> Self {
+        Self {
+            config,
+            key_prefix,
+            state: Mutex::default(),
+            space: Condvar::new(),
+            wake: Notify::new(),
+        }
+    }
+
+    pub(crate) fn config(&self) -> &LayerConfig {
+        &self.config
+    }
+
+    /// Queue `payload`, or drop it if the queue is full and the layer does
+    /// not block.
+    pub(crate) fn push(&self, payload: String) {
+        let mut state = self.state();
+        loop {
+            if state.closed {
+                state.stats.dropped += 1;
+                return;
+            }
+            if state.queue.len() < self.config.capacity {
+                break;
+            }
+            match self.config.overflow {
+                Overflow::Drop => {
+                    state.stats.dropped += 1;
+                    return;
+                }
+                Overflow::Block => {
+                    state = self.space.wait(state).expect("audit queue poisoned");
+                }
+            }
+        }
+        let key = format!("{}-{}", self.key_prefix, state.next);
+        state.next += 1;
+        state.queue.push_back(BatchEntry {
+            payload,
+            idempotency_key: Some(key),
+            labels: Default::default(),
+            signature: None,
+        });
+        if state.queue.len() >= self.config.batch_size {
+            self.wake.notify_one();
+        }
+    }
+
+    pub(crate) fn stats(&self) -> Stats {
+        self.state().stats.clone()
+    }
+
+    pub(crate) async fn flush(&self) {
+        let (done, flushed) = oneshot::channel();
+        self.state().flushes.push(done);
+        self.wake.notify_one();
+        // An error means the worker is gone, so nothing is left to wait for.
+        let _ = flushed.await;
+    }
+
+    pub(crate) fn close(&self) {
+        self.state().closed = true;
+        self.space.notify_all();
+        self.wake.notify_one();
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("audit queue poisoned")
+    }
+}
+
+/// Append queued events until the queue is closed and empty. A failed batch
+/// is resent with the same keys, so logd never holds an event twice, and
+/// the queue fills behind it in the meantime.
+pub(crate) async fn run(shared: &Shared, client: Client) {
+    let mut batch: Vec<BatchEntry> = Vec::new();
+    let mut backoff = shared.config.flush_interval;
+    loop {
+        let (closed, flushes) = {
+            let mut state = shared.state();
+            if batch.is_empty() {
+                let take = state.queue.len().min(shared.config.batch_size);
+                batch.extend(state.queue.drain(..take));
+                shared.space.notify_all();
+            }
+            let flushes = if batch.is_empty() {
+                std::mem::take(&mut state.flushes)
+            } else {
+                Vec::new()
+            };
+            (state.closed, flushes)
+        };
+        for done in flushes {
+            let _ = done.send(());
+        }
+        if batch.is_empty() {
+            if closed {
+                return;
+            }
+            let _ =
+                tokio::time::timeout(shared.config.flush_interval, shared.wake.notified()).await;
+            continue;
+        }
+
+        let result = client.append_batch(batch.clone()).await;
+        let retry = {
+            let mut state = shared.state();
+            match result {
+                Ok(response) => {
+                    state.stats.appended += response.entries.len() as u64;
+                    batch.clear();
+                    false
+                }
+                Err(err) if rejected(&err) => {
+                    state.stats.rejected += batch.len() as u64;
+                    state.stats.last_error = Some(err.to_string());
+                    batch.clear();
+                    false
+                }
+                Err(err) => {
+                    state.stats.failures += 1;
+                    state.stats.last_error = Some(err.to_string());
+                    true
+                }
+            }
+        };
+        if retry {
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        } else {
+            backoff = shared.config.flush_interval;
+        }
+    }
+}
+
+/// Whether logd refused the batch itself, so sending it again cannot help.
+fn rejected(err: &Error) -> bool {
+    matches!(
+        err,
+        Error::Status { status, .. }
+            if status.is_client_error()
+                && *status != StatusCode::REQUEST_TIMEOUT
+                && *status != StatusCode::TOO_MANY_REQUESTS
+    )
+}
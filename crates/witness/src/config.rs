@@ -0,0 +1,159 @@
+use std::{net::SocketAddr, path::PathBuf};
+
+use clap::Parser;
+use reality_config::Layered;
+use serde::Deserialize;
+
+/// Command-line flags. Anything left unset falls back to the config file and
+/// then to defaults.
+#[derive(Debug, Parser)]
+#[command(
+    name = "reality-witness",
+    version,
+    about = "Cosigns RealityLog checkpoints that extend everything it cosigned before"
+)]
+pub struct Cli {
+    /// Path to a TOML config file.
+    #[arg(long, env = "REALITY_WITNESS_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Log to witness as `name=<hex ed25519 key>` (repeatable).
+    #[arg(long = "log", value_parser = parse_log)]
+    pub logs: Vec<LogConfig>,
+    /// Directory holding the witness store.
+    #[arg(long, env = "REALITY_WITNESS_DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
+    /// Address for the witness API.
+    #[arg(long, env = "REALITY_WITNESS_LISTEN")]
+    pub listen: Option<SocketAddr>,
+    /// Signer spec for cosignatures (`file:<path>` or `pkcs11:...`).
+    #[arg(long, env = "REALITY_WITNESS_SIGNER")]
+    pub signer: Option<String>,
+}
+
+/// Fully resolved witness configuration.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub data_dir: PathBuf,
+    pub listen: SocketAddr,
+    pub signer: Option<String>,
+    pub logs: Vec<LogConfig>,
+}
+
+/// A log this witness vouches for.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct LogConfig {
+    /// Stable name; the cosigned history is keyed by it.
+    pub name: String,
+    /// Hex ed25519 key the log signs checkpoints with.
+    pub public_key: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            data_dir: PathBuf::from("data"),
+            listen: SocketAddr::from(([127, 0, 0, 1], 8083)),
+            signer: None,
+            logs: Vec::new(),
+        }
+    }
+}
+
+impl Layered<Cli> for Config {
+    fn file(cli: &Cli) -> Option<PathBuf> {
+        cli.config.clone()
+    }
+
+    fn apply(&mut self, cli: Cli) -> anyhow::Result<()> {
+        self.logs.extend(cli.logs);
+        if let Some(data_dir) = cli.data_dir {
+            self.data_dir = data_dir;
+        }
+        if let Some(listen) = cli.listen {
+            self.listen = listen;
+        }
+        if let Some(signer) = cli.signer {
+            self.signer = Some(signer);
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.signer.is_some(),
+            "a signer is required for cosignatures"
+        );
+        anyhow::ensure!(!self.logs.is_empty(), "at least one log is required");
+        for (i, log) in self.logs.iter().enumerate() {
+            anyhow::ensure!(
+                hex::decode(&log.public_key).is_ok_and(|key| key.len() == 32),
+                "public_key of log {} must be 32 hex-encoded bytes",
+                log.name
+            );
+            anyhow::ensure!(
+                !self.logs[..i].iter().any(|other| other.name == log.name),
+                "log name {} is used twice",
+                log.name
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Config {
+    /// The configured log signing with `public_key`, if any.
+    pub fn log_for_key(&self, public_key: &str) -> Option<&LogConfig> {
+        self.logs
+            .iter()
+            .find(|log| log.public_key.eq_ignore_ascii_case(public_key))
+    }
+}
+
+fn parse_log(value: &str) -> Result<LogConfig, String> {
+    let (name, public_key) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected name=<hex key>, got {value}"))?;
+    Ok(LogConfig {
+        name: name.to_string(),
+        public_key: public_key.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logs_need_a_valid_key_and_unique_name() {
+        let mut config: Config = toml::from_str(&format!(
+            r#"
+            signer = "file:witness.key"
+
+            [[logs]]
+            name = "prod"
+            public_key = "{}"
+            "#,
+            "AB".repeat(32)
+        ))
+        .expect("config");
+        let cli = Cli::parse_from([
+            "reality-witness",
+            "--log",
+            &format!("staging={}", "cd".repeat(32)),
+        ]);
+        config.apply(cli).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.log_for_key(&"ab".repeat(32)).map(|log| &*log.name),
+            Some("prod")
+        );
+
+        config.logs[1].public_key = "cd".into();
+        assert!(config.validate().is_err());
+        config.logs[1].public_key = "cd".repeat(32);
+        config.logs[1].name = "prod".into();
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,52 @@
+//! `reality-witness`: a standalone witness that third parties can run to
+//! vouch that a log only ever grows. It holds its own key and cosigns a
+//! submitted checkpoint only when it is proven to extend the last one it
+//! cosigned for that log.
+
+mod config;
+mod server;
+mod store;
+mod view;
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use clap::Parser;
+use reality_config::Layered;
+use reality_core::signer::open_signer;
+use tracing::info;
+
+use crate::{
+    config::{Cli, Config},
+    server::ApiState,
+    store::WitnessStore,
+};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let config = Config::load(Cli::parse())?;
+    tokio::fs::create_dir_all(&config.data_dir)
+        .await
+        .context("create data dir")?;
+    let store = WitnessStore::open(&config.data_dir.join("witness.sqlite3"))?;
+
+    let spec = config.signer.as_deref().context("witness signer")?;
+    let signer = open_signer(spec).context("open witness signer")?;
+    info!(public_key = %hex::encode(signer.public_key()), "signing key loaded");
+    for log in &config.logs {
+        let size = store
+            .latest(&log.name)?
+            .map_or(0, |(checkpoint, _)| checkpoint.checkpoint.size);
+        info!(log = %log.name, size, "witnessing");
+    }
+
+    let listen = config.listen;
+    let state = ApiState {
+        config: Arc::new(config),
+        store: Arc::new(Mutex::new(store)),
+        signer: Arc::from(signer),
+    };
+    server::serve(listen, state).await
+}
@@ -0,0 +1,185 @@
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use reality_core::{
+    checkpoint::{Cosignature, SignedCheckpoint},
+    signer::Signer,
+};
+use serde::Serialize;
+use tokio::net::TcpListener;
+use tracing::{error, info, warn};
+
+use crate::{
+    config::Config,
+    store::{Fork, WitnessStore},
+    view::{check, AddCheckpoint, Refusal},
+};
+
+#[derive(Clone)]
+pub struct ApiState {
+    pub config: Arc<Config>,
+    /// Held across check, cosign and record so two submissions for the same
+    /// log cannot both extend the same view.
+    pub store: Arc<Mutex<WitnessStore>>,
+    pub signer: Arc<dyn Signer>,
+}
+
+#[derive(Debug, Serialize)]
+struct WitnessStatus {
+    public_key: String,
+    logs: Vec<LogView>,
+}
+
+#[derive(Debug, Serialize)]
+struct LogView {
+    name: String,
+    public_key: String,
+    /// The latest checkpoint this witness cosigned for the log.
+    cosigned: Option<Cosignature>,
+}
+
+type ApiError = (StatusCode, String);
+
+/// Serve the witness API until the process exits.
+pub async fn serve(addr: SocketAddr, state: ApiState) -> anyhow::Result<()> {
+    let app = Router::new()
+        .route("/healthz", get(|| async { "ok" }))
+        .route("/status", get(status))
+        .route("/add-checkpoint", post(add_checkpoint))
+        .route("/forks", get(forks))
+        .with_state(state);
+
+    let listener = TcpListener::bind(addr).await?;
+    info!(%addr, "witness API listening");
+    axum::serve(listener, app.into_make_service()).await?;
+    Ok(())
+}
+
+async fn status(State(state): State<ApiState>) -> Result<Json<WitnessStatus>, ApiError> {
+    let store = state.store.lock().expect("witness store poisoned");
+    let mut logs = Vec::new();
+    for log in &state.config.logs {
+        let latest = store.latest(&log.name).map_err(internal)?;
+        logs.push(LogView {
+            name: log.name.clone(),
+            public_key: log.public_key.clone(),
+            cosigned: latest.map(|(_, cosignature)| cosignature),
+        });
+    }
+    Ok(Json(WitnessStatus {
+        public_key: hex::encode(state.signer.public_key()),
+        logs,
+    }))
+}
+
+/// Cosign a checkpoint that extends this witness's view of its log. It runs
+/// on the blocking pool, since the signer may wait on an HSM or a KMS round
+/// trip.
+async fn add_checkpoint(
+    State(state): State<ApiState>,
+    Json(request): Json<AddCheckpoint>,
+) -> Result<Json<Cosignature>, ApiError> {
+    tokio::task::spawn_blocking(move || cosign(&state, request))
+        .await
+        .map_err(|err| internal(err.into()))?
+        .map(Json)
+}
+
+fn cosign(state: &ApiState, request: AddCheckpoint) -> Result<Cosignature, ApiError> {
+    let log = state
+        .config
+        .log_for_key(&request.checkpoint.signature.public_key)
+        .ok_or((StatusCode::NOT_FOUND, "unknown log key".to_string()))?;
+    let store = state.store.lock().expect("witness store poisoned");
+    let latest = store.latest(&log.name).map_err(internal)?;
+    let witnessed = latest.as_ref().map(|(checkpoint, _)| checkpoint);
+
+    if let Err(refusal) = check(
+        &log.public_key,
+        witnessed.map(|signed| &signed.checkpoint),
+        &request,
+    ) {
+        if refusal == Refusal::Fork {
+            report_fork(&store, &log.name, witnessed, &request.checkpoint);
+        }
+        return Err(refused(refusal));
+    }
+
+    let cosignature = request
+        .checkpoint
+        .checkpoint
+        .clone()
+        .cosign(state.signer.as_ref())
+        .map_err(|err| internal(err.into()))?;
+    store
+        .record(&log.name, &request.checkpoint, &cosignature)
+        .map_err(internal)?;
+    info!(
+        log = %log.name,
+        size = cosignature.checkpoint.size,
+        root = %cosignature.checkpoint.root,
+        "cosigned checkpoint"
+    );
+    Ok(cosignature)
+}
+
+fn report_fork(
+    store: &WitnessStore,
+    log: &str,
+    witnessed: Option<&SignedCheckpoint>,
+    observed: &SignedCheckpoint,
+) {
+    let Some(witnessed) = witnessed else {
+        return;
+    };
+    error!(
+        %log,
+        size = observed.checkpoint.size,
+        witnessed_root = %witnessed.checkpoint.root,
+        observed_root = %observed.checkpoint.root,
+        "ALERT: log signed two roots for the same size; refusing to cosign"
+    );
+    if let Err(err) = store.record_fork(log, witnessed, observed) {
+        error!(?err, %log, "failed to record fork");
+    }
+}
+
+async fn forks(State(state): State<ApiState>) -> Result<Json<Vec<Fork>>, ApiError> {
+    let result = state.store.lock().expect("witness store poisoned").forks();
+    result.map(Json).map_err(internal)
+}
+
+/// Status codes follow the C2SP tlog-witness protocol where it has one.
+fn refused(refusal: Refusal) -> ApiError {
+    warn!(?refusal, "refused checkpoint");
+    match refusal {
+        Refusal::BadSignature => (
+            StatusCode::FORBIDDEN,
+            "checkpoint is not signed by the log key".into(),
+        ),
+        // The body is the size the submitter must prove consistency from.
+        Refusal::Stale { size } => (StatusCode::CONFLICT, size.to_string()),
+        Refusal::Invalid(reason) => (StatusCode::BAD_REQUEST, reason),
+        Refusal::BadProof(reason) => (StatusCode::UNPROCESSABLE_ENTITY, reason),
+        Refusal::Fork => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "log signed a different root for the witnessed size".into(),
+        ),
+    }
+}
+
+fn internal(err: anyhow::Error) -> ApiError {
+    error!(?err, "witness request failed");
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "witness request failed".to_string(),
+    )
+}
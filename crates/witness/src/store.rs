@@ -0,0 +1,193 @@
+use std::path::Path;
+
+use anyhow::Context;
+use reality_core::checkpoint::{Cosignature, SignedCheckpoint};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use time::OffsetDateTime;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS cosigned (
+    log TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    checkpoint TEXT NOT NULL,
+    cosignature TEXT NOT NULL,
+    cosigned_at_nanos INTEGER NOT NULL,
+    PRIMARY KEY (log, size)
+);
+CREATE TABLE IF NOT EXISTS forks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    log TEXT NOT NULL,
+    witnessed TEXT NOT NULL,
+    observed TEXT NOT NULL,
+    detected_at_nanos INTEGER NOT NULL
+);
+";
+
+/// Two checkpoints the log signed for the same size with different roots.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Fork {
+    pub log: String,
+    pub witnessed: SignedCheckpoint,
+    pub observed: SignedCheckpoint,
+    pub detected_at_nanos: String,
+}
+
+/// SQLite record of every checkpoint this witness cosigned, per log, plus
+/// any signed evidence of a fork.
+pub struct WitnessStore {
+    conn: Connection,
+}
+
+impl WitnessStore {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("open witness store {}", path.display()))?;
+        Self::init(conn)
+    }
+
+    #[cfg(test)]
+    pub fn in_memory() -> anyhow::Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> anyhow::Result<Self> {
+        conn.execute_batch(SCHEMA)
+            .context("create witness schema")?;
+        Ok(Self { conn })
+    }
+
+    /// The largest checkpoint cosigned for `log`: the witness's view of it.
+    pub fn latest(&self, log: &str) -> anyhow::Result<Option<(SignedCheckpoint, Cosignature)>> {
+        let row: Option<(String, String)> = self
+            .conn
+            .query_row(
+                "SELECT checkpoint, cosignature FROM cosigned WHERE log = ?1
+                 ORDER BY size DESC LIMIT 1",
+                params![log],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        row.map(|(checkpoint, cosignature)| {
+            Ok((
+                serde_json::from_str(&checkpoint).context("decode stored checkpoint")?,
+                serde_json::from_str(&cosignature).context("decode stored cosignature")?,
+            ))
+        })
+        .transpose()
+    }
+
+    /// Record a cosigned checkpoint. Cosigning the same size again replaces
+    /// the row, since the log re-signs its head with fresh timestamps.
+    pub fn record(
+        &self,
+        log: &str,
+        checkpoint: &SignedCheckpoint,
+        cosignature: &Cosignature,
+    ) -> anyhow::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO cosigned (log, size, checkpoint, cosignature, cosigned_at_nanos)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                log,
+                checkpoint.checkpoint.size as i64,
+                serde_json::to_string(checkpoint)?,
+                serde_json::to_string(cosignature)?,
+                now_nanos()
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn record_fork(
+        &self,
+        log: &str,
+        witnessed: &SignedCheckpoint,
+        observed: &SignedCheckpoint,
+    ) -> anyhow::Result<()> {
+        self.conn.execute(
+            "INSERT INTO forks (log, witnessed, observed, detected_at_nanos)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                log,
+                serde_json::to_string(witnessed)?,
+                serde_json::to_string(observed)?,
+                now_nanos()
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn forks(&self) -> anyhow::Result<Vec<Fork>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT log, witnessed, observed, detected_at_nanos FROM forks ORDER BY id ASC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let detected_at: i64 = row.get(3)?;
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                detected_at.to_string(),
+            ))
+        })?;
+        rows.map(|row| {
+            let (log, witnessed, observed, detected_at_nanos) = row?;
+            Ok(Fork {
+                log,
+                witnessed: serde_json::from_str(&witnessed).context("decode fork")?,
+                observed: serde_json::from_str(&observed).context("decode fork")?,
+                detected_at_nanos,
+            })
+        })
+        .collect()
+    }
+}
+
+fn now_nanos() -> i64 {
+    OffsetDateTime::now_utc().unix_timestamp_nanos() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use reality_core::{checkpoint::Checkpoint, signer::KeyFileSigner};
+
+    use super::*;
+
+    fn cosigned(size: u64, root: &str) -> (SignedCheckpoint, Cosignature) {
+        let checkpoint = Checkpoint {
+            size,
+            root: root.repeat(32),
+            timestamp_nanos: "1".into(),
+        };
+        (
+            checkpoint
+                .clone()
+                .sign(&KeyFileSigner::from_seed([1u8; 32]))
+                .unwrap(),
+            checkpoint
+                .cosign(&KeyFileSigner::from_seed([2u8; 32]))
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn keeps_the_largest_cosigned_checkpoint_per_log() {
+        let store = WitnessStore::in_memory().unwrap();
+        assert_eq!(store.latest("prod").unwrap(), None);
+
+        for (log, size, root) in [("prod", 3, "aa"), ("prod", 5, "bb"), ("staging", 9, "cc")] {
+            let (checkpoint, cosignature) = cosigned(size, root);
+            store.record(log, &checkpoint, &cosignature).unwrap();
+        }
+        let (latest, cosignature) = store.latest("prod").unwrap().unwrap();
+        assert_eq!(latest.checkpoint.size, 5);
+        assert!(cosignature.verify());
+
+        let (forked, _) = cosigned(5, "dd");
+        store.record_fork("prod", &latest, &forked).unwrap();
+        let forks = store.forks().unwrap();
+        assert_eq!(forks.len(), 1);
+        assert_eq!(forks[0].observed, forked);
+    }
+}
@@ -0,0 +1,160 @@
+//! The witness's rule: cosign a checkpoint only if it extends the last one
+//! cosigned for the same log.
+
+use reality_core::{
+    checkpoint::{Checkpoint, SignedCheckpoint},
+    consistency::{verify_consistency, ConsistencyProof},
+};
+use serde::{Deserialize, Serialize};
+
+/// Body of `POST /add-checkpoint`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddCheckpoint {
+    /// Size of the checkpoint the submitter believes this witness last
+    /// cosigned for the log; 0 for the first submission.
+    pub old_size: u64,
+    /// Proof from `old_size` to the new checkpoint. Not needed when
+    /// `old_size` is 0 or equals the new size.
+    #[serde(default)]
+    pub consistency: Option<ConsistencyProof>,
+    pub checkpoint: SignedCheckpoint,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Refusal {
+    /// The checkpoint is not validly signed by the log's key.
+    BadSignature,
+    /// `old_size` is not the size this witness holds; the submitter should
+    /// prove consistency from `size` instead.
+    Stale { size: u64 },
+    /// The request cannot be evaluated as sent.
+    Invalid(String),
+    /// The proof does not link the witnessed checkpoint to the new one.
+    BadProof(String),
+    /// The log signed two different roots for the same size: proof that it
+    /// shows different views to different parties.
+    Fork,
+}
+
+/// Check `request` against `latest`, the last checkpoint cosigned for the
+/// log whose key is `log_key`.
+pub fn check(
+    log_key: &str,
+    latest: Option<&Checkpoint>,
+    request: &AddCheckpoint,
+) -> Result<(), Refusal> {
+    if !request.checkpoint.verify_with(log_key) {
+        return Err(Refusal::BadSignature);
+    }
+    let held = latest.map_or(0, |latest| latest.size);
+    if request.old_size != held {
+        return Err(Refusal::Stale { size: held });
+    }
+    let Some(latest) = latest else {
+        return Ok(());
+    };
+
+    let new = &request.checkpoint.checkpoint;
+    if new.size < latest.size {
+        return Err(Refusal::Invalid(format!(
+            "checkpoint size {} is below the witnessed size {}",
+            new.size, latest.size
+        )));
+    }
+    if new.size == latest.size {
+        return if new.root.eq_ignore_ascii_case(&latest.root) {
+            Ok(())
+        } else {
+            Err(Refusal::Fork)
+        };
+    }
+
+    let proof = request
+        .consistency
+        .as_ref()
+        .ok_or_else(|| Refusal::Invalid("a consistency proof is required".into()))?;
+    let links = proof.old_size == latest.size
+        && proof.new_size == new.size
+        && proof.old_root.eq_ignore_ascii_case(&latest.root)
+        && proof.new_root.eq_ignore_ascii_case(&new.root);
+    if !links || !verify_consistency(proof) {
+        return Err(Refusal::BadProof(format!(
+            "no valid consistency proof from size {} to {}",
+            latest.size, new.size
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use reality_core::{
+        consistency::consistency_proof,
+        leaf_hash, root,
+        signer::{KeyFileSigner, Signer},
+    };
+
+    use super::*;
+
+    fn signed(leaves: &[[u8; 32]], signer: &KeyFileSigner) -> SignedCheckpoint {
+        Checkpoint {
+            size: leaves.len() as u64,
+            root: hex::encode(root(leaves)),
+            timestamp_nanos: "1".into(),
+        }
+        .sign(signer)
+        .unwrap()
+    }
+
+    #[test]
+    fn cosigns_only_proven_growth() {
+        let log = KeyFileSigner::from_seed([7u8; 32]);
+        let stranger = hex::encode(KeyFileSigner::from_seed([8u8; 32]).public_key());
+        let log_key = hex::encode(log.public_key());
+        let leaves: Vec<[u8; 32]> = (0..5u8).map(|i| leaf_hash(&[i])).collect();
+        let first = signed(&leaves[..3], &log);
+        let request = |old_size, consistency, checkpoint: &SignedCheckpoint| AddCheckpoint {
+            old_size,
+            consistency,
+            checkpoint: checkpoint.clone(),
+        };
+
+        assert_eq!(
+            check(&stranger, None, &request(0, None, &first)),
+            Err(Refusal::BadSignature)
+        );
+        assert_eq!(check(&log_key, None, &request(0, None, &first)), Ok(()));
+
+        let latest = Some(&first.checkpoint);
+        let grown = signed(&leaves, &log);
+        let proof = consistency_proof(&leaves, 3, leaves.len()).unwrap();
+        assert_eq!(
+            check(&log_key, latest, &request(0, None, &grown)),
+            Err(Refusal::Stale { size: 3 })
+        );
+        assert!(matches!(
+            check(&log_key, latest, &request(3, None, &grown)),
+            Err(Refusal::Invalid(_))
+        ));
+        assert_eq!(
+            check(&log_key, latest, &request(3, Some(proof.clone()), &grown)),
+            Ok(())
+        );
+
+        let mut other = leaves.clone();
+        other[0] = leaf_hash(b"rewritten");
+        let forked = signed(&other, &log);
+        assert!(matches!(
+            check(&log_key, latest, &request(3, Some(proof), &forked)),
+            Err(Refusal::BadProof(_))
+        ));
+        assert_eq!(
+            check(
+                &log_key,
+                latest,
+                &request(3, None, &signed(&other[..3], &log))
+            ),
+            Err(Refusal::Fork)
+        );
+    }
+}
@@ -40,12 +40,16 @@ async function bootstrap() {
     }
 
     try {
-      const isValid = verify_inclusion(proof);
-      if (isValid) {
-        output.textContent = 'Proof valid ✅';
+      const result = verify_inclusion(proof);
+      if (result.error) {
+        output.textContent = `Verification error: ${result.error}`;
+        output.dataset.state = 'err';
+      } else if (result.valid) {
+        output.textContent = `Proof valid ✅\nroot: ${result.computed_root}`;
         output.dataset.state = 'ok';
       } else {
-        output.textContent = 'Proof invalid ❌';
+        output.textContent =
+          `Proof invalid ❌\ncomputed root: ${result.computed_root}\nexpected root: ${result.expected_root}`;
         output.dataset.state = 'err';
       }
     } catch (err) {
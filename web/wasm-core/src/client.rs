@@ -0,0 +1,266 @@
+//! `LogClient`: reads from a logd URL and verifies every answer in wasm
+//! before the page sees it. Built with the `client` feature.
+
+use reality_core::{
+    checkpoint::SignedCheckpoint,
+    consistency::{verify_consistency, ConsistencyProof},
+    verify, InclusionProof, RootResponse, VerifyRequest,
+};
+use serde::de::DeserializeOwned;
+use wasm_bindgen::{prelude::*, JsCast};
+use wasm_bindgen_futures::{future_to_promise, JsFuture};
+use web_sys::{Response, Window, WorkerGlobalScope};
+
+use crate::{
+    check_checkpoint, from_js, to_js, too_many, whole, ErrorCode, Failure, VerifiedRead,
+    MAX_PATH_LEN,
+};
+
+/// Tree head that proofs are checked against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Head {
+    root: String,
+    size: u64,
+    signed: bool,
+}
+
+/// Verified reads from one logd instance. With the log's public key, heads
+/// come from `/checkpoint` and must carry its signature. Without it they come
+/// from `/root`, which only shows that the log's answers agree with each other.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct LogClient {
+    base_url: String,
+    log_key: Option<String>,
+}
+
+#[wasm_bindgen]
+impl LogClient {
+    #[wasm_bindgen(constructor)]
+    pub fn new(base_url: &str, log_public_key: Option<String>) -> LogClient {
+        LogClient {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            log_key: log_public_key,
+        }
+    }
+
+    /// The log's current tree head.
+    #[wasm_bindgen(unchecked_return_type = "Promise<VerifiedRead>")]
+    pub fn root(&self) -> js_sys::Promise {
+        let client = self.clone();
+        future_to_promise(async move {
+            to_js(&settle(
+                client.head().await.map(|head| verified(head, None)),
+            ))
+        })
+    }
+
+    /// Inclusion proof for entry `index`, checked to lead to the current
+    /// tree head. When the log grew between the two requests, a consistency
+    /// proof from the proof's tree to the head is fetched and checked too.
+    #[wasm_bindgen(unchecked_return_type = "Promise<VerifiedRead>")]
+    pub fn prove(&self, index: f64) -> js_sys::Promise {
+        let client = self.clone();
+        future_to_promise(async move { to_js(&settle(client.prove_entry(index).await)) })
+    }
+}
+
+impl LogClient {
+    async fn prove_entry(&self, index: f64) -> Result<VerifiedRead, Failure> {
+        let index = whole(index)?;
+        let proof: InclusionProof = self.get(&format!("/prove/{index}")).await?;
+        let head = self.head().await?;
+        let consistency = if head.size > proof.size {
+            let path = format!("/consistency?old={}&new={}", proof.size, head.size);
+            Some(self.get(&path).await?)
+        } else {
+            None
+        };
+        check_read(index, proof, head, consistency)
+    }
+
+    async fn head(&self) -> Result<Head, Failure> {
+        let Some(key) = &self.log_key else {
+            let head: RootResponse = self.get("/root").await?;
+            return Ok(Head {
+                root: head.root,
+                size: head.size,
+                signed: false,
+            });
+        };
+        let checked = check_checkpoint(self.get::<SignedCheckpoint>("/checkpoint").await, key);
+        match (checked.code, checked.error) {
+            (Some(code), Some(err)) => Err(Failure::new(code, err)),
+            _ => Ok(Head {
+                root: checked.root,
+                size: checked.size,
+                signed: true,
+            }),
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, Failure> {
+        let url = format!("{}{path}", self.base_url);
+        let response: Response = JsFuture::from(fetch(&url)?)
+            .await
+            .map_err(js_error)?
+            .unchecked_into();
+        if !response.ok() {
+            return Err(Failure::new(
+                ErrorCode::Network,
+                format!("GET {path} returned {}", response.status()),
+            ));
+        }
+        let body = JsFuture::from(response.text().map_err(js_error)?)
+            .await
+            .map_err(js_error)?;
+        from_js(body).map_err(|err| err.context(&format!("GET {path}")))
+    }
+}
+
+/// Check that `proof` answers for entry `index` and leads to `head`, directly
+/// or through `consistency` when the head is for a larger tree.
+fn check_read(
+    index: u64,
+    proof: InclusionProof,
+    head: Head,
+    consistency: Option<ConsistencyProof>,
+) -> Result<VerifiedRead, Failure> {
+    if proof.index != index {
+        return Err(Failure::malformed(format!(
+            "log answered for entry {}, not {index}",
+            proof.index
+        )));
+    }
+    too_many("path steps", proof.path.len(), MAX_PATH_LEN)?;
+    if !verify(&VerifyRequest::from(&proof)).valid {
+        return Err(Failure::new(
+            ErrorCode::RootMismatch,
+            "inclusion proof does not lead to its root",
+        ));
+    }
+    if proof.size > head.size {
+        return Err(Failure::new(
+            ErrorCode::Consistency,
+            "proof is for a larger tree than the log head",
+        ));
+    }
+    if proof.size == head.size {
+        if !proof.root.eq_ignore_ascii_case(&head.root) {
+            return Err(Failure::new(
+                ErrorCode::RootMismatch,
+                "proof root differs from the log head of the same size",
+            ));
+        }
+    } else {
+        let consistency = consistency.ok_or_else(|| {
+            Failure::new(
+                ErrorCode::Consistency,
+                "no consistency proof links the proof's tree to the log head",
+            )
+        })?;
+        too_many(
+            "consistency hashes",
+            consistency.path.len(),
+            2 * MAX_PATH_LEN,
+        )?;
+        let links = consistency.old_size == proof.size
+            && consistency.old_root.eq_ignore_ascii_case(&proof.root)
+            && consistency.new_size == head.size
+            && consistency.new_root.eq_ignore_ascii_case(&head.root);
+        if !links || !verify_consistency(&consistency) {
+            return Err(Failure::new(
+                ErrorCode::Consistency,
+                "consistency proof does not link the proof to the log head",
+            ));
+        }
+    }
+    Ok(verified(head, Some(proof)))
+}
+
+fn verified(head: Head, proof: Option<InclusionProof>) -> VerifiedRead {
+    VerifiedRead {
+        valid: true,
+        root: head.root,
+        size: head.size,
+        signed: head.signed,
+        proof,
+        code: None,
+        error: None,
+    }
+}
+
+fn settle(read: Result<VerifiedRead, Failure>) -> VerifiedRead {
+    read.unwrap_or_else(|err| VerifiedRead {
+        valid: false,
+        root: String::new(),
+        size: 0,
+        signed: false,
+        proof: None,
+        code: Some(err.code),
+        error: Some(err.message),
+    })
+}
+
+/// `fetch` from whichever global scope the module runs in.
+fn fetch(url: &str) -> Result<js_sys::Promise, Failure> {
+    let global = js_sys::global();
+    if let Some(window) = global.dyn_ref::<Window>() {
+        Ok(window.fetch_with_str(url))
+    } else if let Some(worker) = global.dyn_ref::<WorkerGlobalScope>() {
+        Ok(worker.fetch_with_str(url))
+    } else {
+        Err(Failure::new(
+            ErrorCode::Network,
+            "LogClient needs a window or worker to fetch from",
+        ))
+    }
+}
+
+fn js_error(err: JsValue) -> Failure {
+    let message = err
+        .dyn_ref::<js_sys::Error>()
+        .map(|err| String::from(err.message()))
+        .or_else(|| err.as_string())
+        .unwrap_or_else(|| "request failed".into());
+    Failure::new(ErrorCode::Network, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reality_core::{consistency::consistency_proof, leaf_hash, make_proof};
+
+    #[test]
+    fn reads_are_tied_to_the_head() {
+        let leaves: Vec<[u8; 32]> = (0..7u8).map(|i| leaf_hash(&[i])).collect();
+        let head = |size: usize| Head {
+            root: make_proof(&leaves[..size], 0).unwrap().root,
+            size: size as u64,
+            signed: true,
+        };
+        let proof = make_proof(&leaves[..5], 3).unwrap();
+
+        let read = check_read(3, proof.clone(), head(5), None).unwrap();
+        assert!(read.valid && read.signed);
+        assert_eq!(read.proof.as_ref(), Some(&proof));
+
+        let grown = consistency_proof(&leaves, 5, leaves.len()).unwrap();
+        let read = check_read(3, proof.clone(), head(7), Some(grown.clone())).unwrap();
+        assert_eq!(read.size, 7);
+
+        assert_eq!(
+            check_read(3, proof.clone(), head(7), None).map_err(|err| err.code),
+            Err(ErrorCode::Consistency)
+        );
+        assert!(check_read(2, proof.clone(), head(5), None).is_err());
+        assert!(check_read(3, proof.clone(), head(4), None).is_err());
+
+        let mut forked = head(5);
+        forked.root = grown.new_root.clone();
+        assert!(check_read(3, proof.clone(), forked, None).is_err());
+
+        let unrelated = consistency_proof(&leaves, 6, leaves.len()).unwrap();
+        assert!(check_read(3, proof, head(7), Some(unrelated)).is_err());
+    }
+}
@@ -0,0 +1,259 @@
+//! Numbered failure reasons. Failed results carry one as `code`, and
+//! `assert_valid` throws it as a JS `Error` whose `name` is the code's class
+//! name (`RootMismatchError`, `SignatureError`, ...) so pages can tell users
+//! exactly why a receipt was refused.
+
+use std::fmt;
+
+use reality_core::{
+    bundle::BundleError, ct::CtError, encoding::DecodeError, receipt::ReceiptError, tile::TileError,
+};
+use serde::{Serialize, Serializer};
+use wasm_bindgen::prelude::*;
+
+/// Why a verification failed. The numbers are stable; new codes are only
+/// ever appended.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// Input did not parse or has the wrong shape.
+    MalformedInput = 1,
+    /// Input is over one of the module's size limits.
+    LimitExceeded = 2,
+    /// A hash is not 32 bytes of hex.
+    BadHex = 3,
+    /// The payload does not hash to the proof's leaf.
+    LeafMismatch = 4,
+    /// A proof does not lead to its root, or that root is not the one it
+    /// was checked against.
+    RootMismatch = 5,
+    /// A signature does not verify, or is missing where one is required.
+    Signature = 6,
+    /// Validly signed, but not by the pinned key.
+    UntrustedKey = 7,
+    /// Nothing shows that one tree head extends another.
+    Consistency = 8,
+    /// An anchor's txid or batch membership does not check out.
+    Anchor = 9,
+    /// A correctly signed head contradicts history already trusted.
+    Fork = 10,
+    /// A request to the log failed.
+    Network = 11,
+    /// A leaf index is outside the tree.
+    OutOfRange = 12,
+}
+
+impl ErrorCode {
+    /// `name` of the JS errors thrown for this code.
+    pub fn class_name(self) -> &'static str {
+        match self {
+            Self::MalformedInput => "MalformedInputError",
+            Self::LimitExceeded => "LimitExceededError",
+            Self::BadHex => "BadHexError",
+            Self::LeafMismatch => "LeafMismatchError",
+            Self::RootMismatch => "RootMismatchError",
+            Self::Signature => "SignatureError",
+            Self::UntrustedKey => "UntrustedKeyError",
+            Self::Consistency => "ConsistencyError",
+            Self::Anchor => "AnchorError",
+            Self::Fork => "ForkError",
+            Self::Network => "NetworkError",
+            Self::OutOfRange => "OutOfRangeError",
+        }
+    }
+
+    fn from_number(code: f64) -> Option<Self> {
+        const ALL: [ErrorCode; 12] = [
+            ErrorCode::MalformedInput,
+            ErrorCode::LimitExceeded,
+            ErrorCode::BadHex,
+            ErrorCode::LeafMismatch,
+            ErrorCode::RootMismatch,
+            ErrorCode::Signature,
+            ErrorCode::UntrustedKey,
+            ErrorCode::Consistency,
+            ErrorCode::Anchor,
+            ErrorCode::Fork,
+            ErrorCode::Network,
+            ErrorCode::OutOfRange,
+        ];
+        ALL.into_iter().find(|known| *known as u32 as f64 == code)
+    }
+}
+
+impl Serialize for ErrorCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(*self as u32)
+    }
+}
+
+/// A failure and the code it is reported under.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Failure {
+    pub(crate) code: ErrorCode,
+    pub(crate) message: String,
+}
+
+impl Failure {
+    pub(crate) fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn malformed(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::MalformedInput, message)
+    }
+
+    /// Prefix the message, keeping the code.
+    pub(crate) fn context(self, what: &str) -> Self {
+        Self {
+            message: format!("{what}: {}", self.message),
+            ..self
+        }
+    }
+
+    /// The `Error` thrown to JS: `name` is the class name, `code` the number.
+    pub(crate) fn into_js(self) -> JsValue {
+        let error = js_sys::Error::new(&self.message);
+        error.set_name(self.code.class_name());
+        // Setting a property on a fresh Error object cannot fail.
+        let _ = js_sys::Reflect::set(&error, &"code".into(), &(self.code as u32).into());
+        error.into()
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<BundleError> for Failure {
+    fn from(err: BundleError) -> Self {
+        let code = match err {
+            BundleError::LeafMismatch => ErrorCode::LeafMismatch,
+            BundleError::InvalidProof { .. } | BundleError::CheckpointMismatch => {
+                ErrorCode::RootMismatch
+            }
+            BundleError::BadSignature => ErrorCode::Signature,
+            BundleError::UntrustedKey(_) => ErrorCode::UntrustedKey,
+        };
+        Self::new(code, err.to_string())
+    }
+}
+
+impl From<ReceiptError> for Failure {
+    fn from(err: ReceiptError) -> Self {
+        let code = match err {
+            ReceiptError::TxidMismatch { .. }
+            | ReceiptError::NotInBatch { .. }
+            | ReceiptError::MissingBatch
+            | ReceiptError::InvalidBatchProof => ErrorCode::Anchor,
+            ReceiptError::BadSignature | ReceiptError::Unsigned => ErrorCode::Signature,
+            ReceiptError::UntrustedKey(_) => ErrorCode::UntrustedKey,
+            ReceiptError::ProofMismatch | ReceiptError::InvalidProof => ErrorCode::RootMismatch,
+            ReceiptError::NotAfterPrevious
+            | ReceiptError::InvalidConsistency
+            | ReceiptError::NotFromAnchor => ErrorCode::Consistency,
+        };
+        Self::new(code, err.to_string())
+    }
+}
+
+impl From<TileError> for Failure {
+    fn from(err: TileError) -> Self {
+        let code = match err {
+            TileError::OutOfOrder { .. }
+            | TileError::PartialTile(_)
+            | TileError::TooFewLeaves { .. } => ErrorCode::MalformedInput,
+            TileError::IndexOutOfRange { .. } => ErrorCode::OutOfRange,
+            TileError::InvalidHash => ErrorCode::BadHex,
+        };
+        Self::new(code, err.to_string())
+    }
+}
+
+impl From<CtError> for Failure {
+    fn from(err: CtError) -> Self {
+        let code = match err {
+            CtError::InvalidHash => ErrorCode::BadHex,
+            CtError::IndexOutOfRange { .. } => ErrorCode::OutOfRange,
+            CtError::PathLength { .. } => ErrorCode::MalformedInput,
+        };
+        Self::new(code, err.to_string())
+    }
+}
+
+impl From<DecodeError> for Failure {
+    fn from(err: DecodeError) -> Self {
+        let code = match err {
+            DecodeError::InvalidHash => ErrorCode::BadHex,
+            DecodeError::TooManySteps => ErrorCode::LimitExceeded,
+            _ => ErrorCode::MalformedInput,
+        };
+        Self::new(code, err.to_string())
+    }
+}
+
+/// Return `result` unchanged if it reports success; otherwise throw its
+/// failure as an `Error` named after its `code`, with the code attached.
+#[wasm_bindgen(skip_typescript)]
+pub fn assert_valid(result: JsValue) -> Result<JsValue, JsValue> {
+    let field = |name: &str| js_sys::Reflect::get(&result, &name.into()).unwrap_or_default();
+    match failure_of(
+        field("valid").as_bool(),
+        field("code").as_f64(),
+        field("error").as_string(),
+    ) {
+        Some(failure) => Err(failure.into_js()),
+        None if result.is_object() => Ok(result),
+        None => Err(Failure::malformed("expected a verification result").into_js()),
+    }
+}
+
+/// The failure a result object reports, if any. Results without a `valid`
+/// flag (constructed proofs) fail exactly when they carry an `error`.
+fn failure_of(valid: Option<bool>, code: Option<f64>, error: Option<String>) -> Option<Failure> {
+    if valid != Some(false) && error.is_none() {
+        return None;
+    }
+    let code = code
+        .and_then(ErrorCode::from_number)
+        .unwrap_or(ErrorCode::MalformedInput);
+    let message = error.unwrap_or_else(|| match code {
+        ErrorCode::RootMismatch => "proof does not lead to the expected root".into(),
+        _ => "verification failed".into(),
+    });
+    Some(Failure::new(code, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_map_to_their_codes() {
+        assert_eq!(failure_of(Some(true), None, None), None);
+        assert_eq!(failure_of(None, None, None), None);
+        assert_eq!(
+            failure_of(Some(false), Some(6.0), Some("bad".into())),
+            Some(Failure::new(ErrorCode::Signature, "bad"))
+        );
+        assert_eq!(
+            failure_of(Some(false), Some(5.0), None).map(|f| f.code),
+            Some(ErrorCode::RootMismatch)
+        );
+        assert_eq!(
+            failure_of(None, Some(99.0), Some("x".into())).map(|f| f.code),
+            Some(ErrorCode::MalformedInput)
+        );
+        assert_eq!(
+            Failure::from(ReceiptError::InvalidConsistency).code,
+            ErrorCode::Consistency
+        );
+        assert_eq!(ErrorCode::Fork.class_name(), "ForkError");
+        assert_eq!(serde_json::to_string(&ErrorCode::BadHex).unwrap(), "3");
+    }
+}
@@ -1,10 +1,1264 @@
-use reality_core::{verify, VerifyRequest};
-use wasm_bindgen::prelude::*;
+//! Browser bindings for RealityLog verification.
+//!
+//! Every `verify_*` function takes a plain JS object (or, for compatibility,
+//! a JSON string) and returns a plain object. The `*_json` variants take and
+//! return JSON strings for callers that cannot pass objects.
+//!
+//! Inputs are bounded so a hostile page or payload cannot make the module
+//! allocate without limit: string and byte inputs are measured before they
+//! are copied into wasm memory, and arrays and proof paths are counted before
+//! any hashing starts. Exceeding a limit yields an ordinary `error` result.
+//!
+//! With the `client` feature the module also exports `LogClient`, which
+//! fetches from a logd URL and runs the same checks before returning.
+
+use reality_core::{
+    algorithm::HashAlgorithm,
+    bundle::EntryBundle,
+    checkpoint::SignedCheckpoint,
+    ct::CtInclusionProof,
+    receipt::{AnchorReceipt, ReceiptContext},
+    tile::{self, LeafTile},
+    verify, InclusionProof, VerifyRequest,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use wasm_bindgen::{prelude::*, JsCast};
+
+#[cfg(feature = "client")]
+mod client;
+mod errors;
+mod trust;
+
+#[cfg(feature = "client")]
+pub use client::LogClient;
+pub use errors::{assert_valid, ErrorCode};
+pub use trust::{AdvanceResult, TrustedCheckpoint};
+
+use errors::Failure;
+
+/// Largest JSON string accepted by any function.
+pub const MAX_INPUT_BYTES: usize = 32 << 20;
+/// Longest inclusion path accepted; no tree with a `u64` size is deeper.
+pub const MAX_PATH_LEN: usize = 64;
+/// Most requests accepted by one `verify_batch` call.
+pub const MAX_BATCH: usize = 10_000;
+/// Most leaves `prove_from_tiles` and `prove_from_leaves` will hash.
+pub const MAX_PROOF_LEAVES: usize = 1 << 18;
+/// Longest binary proof: 87 header bytes in the v2 encoding, which adds
+/// the algorithm byte, plus 33 bytes per step.
+const MAX_BINARY_PROOF: usize = 87 + 33 * MAX_PATH_LEN;
+
+// Hand-written so the package ships precise shapes for what the functions
+// accept and return. `typescript_matches_serde_shapes` fails if these drift
+// from the Rust types.
+#[allow(dead_code)] // only read through the custom section below and in tests
+const TS_TYPES: &str = r#"
+export type HashAlgorithm = "sha256" | "sha3-256" | "blake3" | "sha512-256";
+export interface ProofStep {
+  direction: "left" | "right";
+  hash: string;
+}
+export interface VerifyRequest {
+  index: number;
+  leaf: string;
+  path: ProofStep[];
+  root: string;
+  algorithm?: HashAlgorithm;
+}
+export interface InclusionProof {
+  index: number;
+  leaf: string;
+  path: ProofStep[];
+  root: string;
+  size: number;
+  algorithm?: HashAlgorithm;
+}
+export interface SignedCheckpoint {
+  size: number;
+  root: string;
+  timestamp_nanos: string;
+  public_key: string;
+  signature: string;
+}
+export interface EntryBundle {
+  payload: string;
+  proof: InclusionProof;
+  checkpoint: SignedCheckpoint;
+}
+export interface CtInclusionProof {
+  leaf_index: number;
+  audit_path: string[];
+}
+export interface LeafTile {
+  index: number;
+  leaves: string[];
+}
+export interface DetachedSignature {
+  public_key: string;
+  signature: string;
+}
+export interface AnchorRecord {
+  version: number;
+  root: string;
+  size: number;
+  timestamp_nanos: string;
+  txid: string;
+  backend?: string;
+  network?: string;
+  block_height?: number;
+  confirmations?: number;
+  receipt_uri?: string;
+  signing_identity?: string;
+}
+export interface BatchMembership {
+  batch_txid: string;
+  batch_root: string;
+  proof: InclusionProof;
+}
+export interface AnchorReceipt {
+  version: number;
+  root: string;
+  size: number;
+  timestamp_nanos: string;
+  txid: string;
+  backend?: string;
+  network?: string;
+  block_height?: number;
+  confirmations?: number;
+  receipt_uri?: string;
+  signing_identity?: string;
+  status: "pending" | "confirmed" | "failed";
+  signature?: DetachedSignature;
+  batch?: BatchMembership;
+}
+export interface ConsistencyProof {
+  old_size: number;
+  old_root: string;
+  new_size: number;
+  new_root: string;
+  path: string[];
+  algorithm?: HashAlgorithm;
+}
+export interface ReceiptContext {
+  proof?: InclusionProof;
+  previous?: AnchorRecord;
+  consistency?: ConsistencyProof;
+  anchor_key?: string;
+}
+export interface AnchorVerification {
+  valid: boolean;
+  txid_checked: boolean;
+  signature_checked: boolean;
+  code?: ErrorCode;
+  error?: string;
+}
+export interface VerificationResult {
+  valid: boolean;
+  computed_root: string;
+  expected_root: string;
+  code?: ErrorCode;
+  error?: string;
+}
+export interface BundleVerification {
+  valid: boolean;
+  log_public_key?: string;
+  code?: ErrorCode;
+  error?: string;
+}
+export interface ConstructedProof {
+  proof?: InclusionProof;
+  code?: ErrorCode;
+  error?: string;
+}
+export interface VerifiedRead {
+  valid: boolean;
+  root: string;
+  size: number;
+  signed: boolean;
+  proof?: InclusionProof;
+  code?: ErrorCode;
+  error?: string;
+}
+export interface AdvanceResult {
+  valid: boolean;
+  fork: boolean;
+  root: string;
+  size: number;
+  code?: ErrorCode;
+  error?: string;
+}
+export interface CheckpointVerification {
+  valid: boolean;
+  root: string;
+  size: number;
+  code?: ErrorCode;
+  error?: string;
+}
+export interface RealityLogError extends Error {
+  name: "MalformedInputError" | "LimitExceededError" | "BadHexError" | "LeafMismatchError" | "RootMismatchError" | "SignatureError" | "UntrustedKeyError" | "ConsistencyError" | "AnchorError" | "ForkError" | "NetworkError" | "OutOfRangeError";
+  code: ErrorCode;
+}
+"#;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES_SECTION: &str = TS_TYPES;
+
+/// Outcome of a verification call. `error` is set when the input could not be
+/// evaluated at all, as opposed to a well-formed proof that does not match;
+/// `code` is set in both cases.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VerificationResult {
+    pub valid: bool,
+    pub computed_root: String,
+    pub expected_root: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Outcome of `verify_bundle`. `error` names the first check that failed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BundleVerification {
+    pub valid: bool,
+    /// Key that signed the bundle's checkpoint, when the bundle parsed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_public_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Outcome of `verify_checkpoint`. `root` and `size` are only trustworthy
+/// when `valid` is true.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CheckpointVerification {
+    pub valid: bool,
+    pub root: String,
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Outcome of `prove_from_tiles` / `prove_from_leaves`: a proof built in the
+/// browser, or why one could not be built.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConstructedProof {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof: Option<InclusionProof>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Outcome of `verify_anchor`. `txid_checked` is false for backends whose
+/// transactions cannot be recomputed in the browser; `signature_checked` is
+/// false for unsigned anchors when no `anchor_key` was pinned.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AnchorVerification {
+    pub valid: bool,
+    pub txid_checked: bool,
+    pub signature_checked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Outcome of a `LogClient` read. `root` and `size` are the tree head the
+/// read was checked against; `signed` is true when that head came from a
+/// checkpoint signed by the pinned log key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VerifiedRead {
+    pub valid: bool,
+    pub root: String,
+    pub size: u64,
+    pub signed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof: Option<InclusionProof>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Verify an inclusion proof (`VerifyRequest`).
+#[wasm_bindgen(skip_typescript)]
+pub fn verify_inclusion(request: JsValue) -> Result<JsValue, JsValue> {
+    to_js(&check_inclusion(from_js(request)))
+}
+
+#[wasm_bindgen]
+pub fn verify_inclusion_json(request_json: &str) -> String {
+    to_json(&check_inclusion(from_json(request_json)))
+}
+
+/// Verify many `VerifyRequest`s in one call; results come back in input
+/// order, and a malformed item only fails its own entry. Input that is not
+/// an array yields a single entry carrying the error.
+#[wasm_bindgen(skip_typescript)]
+pub fn verify_batch(requests: JsValue) -> Result<JsValue, JsValue> {
+    to_js(&check_batch(
+        max_items(&requests, MAX_BATCH).and_then(|()| from_js(requests)),
+    ))
+}
 
 #[wasm_bindgen]
-pub fn verify_inclusion(req_json: &str) -> bool {
-    match serde_json::from_str::<VerifyRequest>(req_json) {
-        Ok(request) => verify(&request).valid,
-        Err(_) => false,
+pub fn verify_batch_json(requests_json: &str) -> String {
+    to_json(&check_batch(from_json(requests_json)))
+}
+
+/// Verify a proof in the compact binary encoding served by logd's
+/// `/prove/:index/bin`.
+#[wasm_bindgen(skip_typescript)]
+pub fn verify_inclusion_bytes(bytes: js_sys::Uint8Array) -> Result<JsValue, JsValue> {
+    if let Err(failure) = binary_proof_fits(bytes.length() as usize) {
+        return to_js(&check_inclusion(Err(failure)));
+    }
+    to_js(&check_inclusion_bytes(&bytes.to_vec()))
+}
+
+/// Verify a proof in CT wire format (`{ leaf_index, audit_path }`, base64
+/// hashes) that `leaf` is in the tree of `tree_size` leaves with `root`.
+/// `leaf` and `root` may be base64, as in a CT signed tree head, or hex.
+#[wasm_bindgen(skip_typescript)]
+pub fn verify_ct_proof(
+    proof: JsValue,
+    leaf: &str,
+    root: &str,
+    tree_size: f64,
+    algorithm: Option<String>,
+) -> Result<JsValue, JsValue> {
+    to_js(&check_ct_proof(
+        from_js(proof),
+        leaf,
+        root,
+        tree_size,
+        algorithm,
+    ))
+}
+
+/// Verify an `EntryBundle` receipt end to end: payload → leaf → root →
+/// checkpoint → ed25519 signature. Pass the log's hex public key to also
+/// require that the checkpoint was signed by it.
+#[wasm_bindgen(skip_typescript)]
+pub fn verify_bundle(bundle: JsValue, trusted_key: Option<String>) -> Result<JsValue, JsValue> {
+    to_js(&check_bundle(from_js(bundle), trusted_key.as_deref()))
+}
+
+#[wasm_bindgen]
+pub fn verify_bundle_json(bundle_json: &str, trusted_key: Option<String>) -> String {
+    to_json(&check_bundle(
+        from_json(bundle_json),
+        trusted_key.as_deref(),
+    ))
+}
+
+/// Check a signed checkpoint from logd's `/checkpoint` against the log's hex
+/// ed25519 public key before trusting proofs against its root.
+#[wasm_bindgen(skip_typescript)]
+pub fn verify_checkpoint(checkpoint: JsValue, pubkey_hex: &str) -> Result<JsValue, JsValue> {
+    to_js(&check_checkpoint(from_js(checkpoint), pubkey_hex))
+}
+
+#[wasm_bindgen]
+pub fn verify_checkpoint_json(checkpoint_json: &str, pubkey_hex: &str) -> String {
+    to_json(&check_checkpoint(from_json(checkpoint_json), pubkey_hex))
+}
+
+/// Build the proof for leaf `index` in the tree of the first `size` leaves
+/// from logd `/tile/N` responses, starting at tile 0. Check the proof's root
+/// against a verified checkpoint before trusting it.
+#[wasm_bindgen(skip_typescript)]
+pub fn prove_from_tiles(
+    tiles: JsValue,
+    size: f64,
+    index: f64,
+    algorithm: Option<String>,
+) -> Result<JsValue, JsValue> {
+    let tiles =
+        max_items(&tiles, MAX_PROOF_LEAVES / tile::TILE_WIDTH + 1).and_then(|()| from_js(tiles));
+    to_js(&construct(tiles.and_then(|tiles: Vec<LeafTile>| {
+        let leaves: usize = tiles.iter().map(|tile| tile.leaves.len()).sum();
+        too_many("leaves", leaves, MAX_PROOF_LEAVES)?;
+        tile::prove_from_tiles(
+            parse_algorithm(algorithm)?,
+            &tiles,
+            whole(size)?,
+            whole(index)?,
+        )
+        .map_err(Failure::from)
+    })))
+}
+
+/// Build the proof for leaf `index` from every hex leaf hash of the tree.
+#[wasm_bindgen(skip_typescript)]
+pub fn prove_from_leaves(
+    leaves: JsValue,
+    index: f64,
+    algorithm: Option<String>,
+) -> Result<JsValue, JsValue> {
+    let leaves = max_items(&leaves, MAX_PROOF_LEAVES).and_then(|()| from_js(leaves));
+    to_js(&construct(leaves.and_then(|leaves: Vec<String>| {
+        tile::prove_from_leaves(parse_algorithm(algorithm)?, &leaves, whole(index)?)
+            .map_err(Failure::from)
+    })))
+}
+
+/// Check an anchor receipt from the anchor API's `/receipt/<txid>`: the
+/// simulated txid (through the batch proof for batched anchors), the anchor
+/// signature, and whatever `context` supplies — a proof that must end at the
+/// anchored root, the previous anchor, and a consistency proof linking them.
+#[wasm_bindgen(skip_typescript)]
+pub fn verify_anchor(receipt: JsValue, context: JsValue) -> Result<JsValue, JsValue> {
+    let context = if context.is_undefined() || context.is_null() {
+        Ok(ReceiptContext::default())
+    } else {
+        from_js(context)
+    };
+    to_js(&check_anchor(from_js(receipt), context))
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TS_FUNCTIONS: &str = r#"
+export function verify_inclusion(request: VerifyRequest | string): VerificationResult;
+export function verify_batch(requests: VerifyRequest[] | string): VerificationResult[];
+export function verify_inclusion_bytes(bytes: Uint8Array): VerificationResult;
+export function verify_ct_proof(proof: CtInclusionProof | string, leaf: string, root: string, tree_size: number, algorithm?: HashAlgorithm): VerificationResult;
+export function verify_bundle(bundle: EntryBundle | string, trusted_key?: string): BundleVerification;
+export function verify_checkpoint(checkpoint: SignedCheckpoint | string, pubkey_hex: string): CheckpointVerification;
+export function verify_anchor(receipt: AnchorReceipt | string, context?: ReceiptContext | string): AnchorVerification;
+export function prove_from_tiles(tiles: LeafTile[] | string, size: number, index: number, algorithm?: HashAlgorithm): ConstructedProof;
+export function prove_from_leaves(leaves: string[] | string, index: number, algorithm?: HashAlgorithm): ConstructedProof;
+/** Returns `result` if it succeeded, otherwise throws it as a `RealityLogError`. */
+export function assert_valid<T extends { valid?: boolean; code?: ErrorCode; error?: string }>(result: T): T;
+"#;
+
+/// Hex leaf hash of raw payload bytes (a `Uint8Array` in JS), as logd
+/// computes it on append. `algorithm` defaults to `"sha256"`.
+#[wasm_bindgen]
+pub fn leaf_hash_hex(bytes: &[u8], algorithm: Option<String>) -> Result<String, JsValue> {
+    let algorithm = parse_algorithm(algorithm).map_err(Failure::into_js)?;
+    Ok(hex::encode(algorithm.leaf_hash(bytes)))
+}
+
+/// Hex leaf hash of a string payload, hashed as its UTF-8 bytes.
+#[wasm_bindgen]
+pub fn leaf_hash_of_utf8(payload: &str, algorithm: Option<String>) -> Result<String, JsValue> {
+    leaf_hash_hex(payload.as_bytes(), algorithm)
+}
+
+/// Hex interior node hash of two hex child hashes, with the node prefix.
+#[wasm_bindgen]
+pub fn node_hash_hex(
+    left: &str,
+    right: &str,
+    algorithm: Option<String>,
+) -> Result<String, JsValue> {
+    let algorithm = parse_algorithm(algorithm).map_err(Failure::into_js)?;
+    let (left, right) = (child_hash(left)?, child_hash(right)?);
+    Ok(hex::encode(algorithm.node_hash(&left, &right)))
+}
+
+/// Hex root of an empty log.
+#[wasm_bindgen]
+pub fn empty_root_hex(algorithm: Option<String>) -> Result<String, JsValue> {
+    let algorithm = parse_algorithm(algorithm).map_err(Failure::into_js)?;
+    Ok(hex::encode(algorithm.empty_root()))
+}
+
+fn child_hash(value: &str) -> Result<[u8; 32], JsValue> {
+    hex::decode(value)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| {
+            Failure::new(ErrorCode::BadHex, "node hashes must be 32 bytes of hex").into_js()
+        })
+}
+
+/// Streaming leaf hash for payloads too large to hold in one buffer: call
+/// `update` with each chunk (e.g. from `Blob.stream()`), then `finalize`.
+/// The result equals `leaf_hash_hex` of the concatenated bytes.
+#[wasm_bindgen]
+pub struct LeafHasher(reality_core::LeafHasher);
+
+#[wasm_bindgen]
+impl LeafHasher {
+    #[wasm_bindgen(constructor)]
+    pub fn new(algorithm: Option<String>) -> Result<LeafHasher, JsValue> {
+        let algorithm = parse_algorithm(algorithm).map_err(Failure::into_js)?;
+        Ok(Self(reality_core::LeafHasher::with_algorithm(algorithm)))
+    }
+
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.0.update(chunk);
+    }
+
+    /// Hex leaf hash. Consumes the hasher; the JS object is unusable after.
+    pub fn finalize(self) -> String {
+        hex::encode(self.0.finalize())
+    }
+}
+
+fn check_inclusion(request: Result<VerifyRequest, Failure>) -> VerificationResult {
+    let request = match request {
+        Ok(request) => request,
+        Err(err) => {
+            let err = err.context("invalid request");
+            return VerificationResult {
+                valid: false,
+                computed_root: String::new(),
+                expected_root: String::new(),
+                code: Some(err.code),
+                error: Some(err.message),
+            };
+        }
+    };
+    if let Err(err) = too_many("path steps", request.path.len(), MAX_PATH_LEN) {
+        return check_inclusion(Err(err));
+    }
+    let response = verify(&request);
+    // Core reports undecodable hashes by leaving computed_root empty.
+    let (code, error) = if response.computed_root.is_empty() {
+        (
+            Some(ErrorCode::BadHex),
+            Some("leaf and path hashes must be 32 bytes of hex".to_string()),
+        )
+    } else {
+        ((!response.valid).then_some(ErrorCode::RootMismatch), None)
+    };
+    VerificationResult {
+        valid: response.valid,
+        computed_root: response.computed_root,
+        expected_root: response.expected_root,
+        code,
+        error,
+    }
+}
+
+fn check_batch(requests: Result<Vec<serde_json::Value>, Failure>) -> Vec<VerificationResult> {
+    let items = requests.and_then(|items| {
+        too_many("batch items", items.len(), MAX_BATCH)?;
+        Ok(items)
+    });
+    match items {
+        Ok(items) => items
+            .into_iter()
+            .map(|item| {
+                check_inclusion(
+                    serde_json::from_value(item).map_err(|e| Failure::malformed(e.to_string())),
+                )
+            })
+            .collect(),
+        Err(err) => vec![check_inclusion(Err(err.context("invalid batch")))],
+    }
+}
+
+/// Checked before the bytes are copied out of JS memory.
+fn binary_proof_fits(len: usize) -> Result<(), Failure> {
+    if len > MAX_BINARY_PROOF {
+        return Err(Failure::new(
+            ErrorCode::LimitExceeded,
+            format!("binary proof is {len} bytes; the limit is {MAX_BINARY_PROOF}"),
+        ));
+    }
+    Ok(())
+}
+
+fn check_inclusion_bytes(bytes: &[u8]) -> VerificationResult {
+    check_inclusion(
+        InclusionProof::from_bytes(bytes)
+            .map(|proof| VerifyRequest::from(&proof))
+            .map_err(Failure::from),
+    )
+}
+
+fn check_ct_proof(
+    proof: Result<CtInclusionProof, Failure>,
+    leaf: &str,
+    root: &str,
+    tree_size: f64,
+    algorithm: Option<String>,
+) -> VerificationResult {
+    check_inclusion(proof.and_then(|proof| {
+        let proof =
+            proof.to_inclusion_proof(parse_algorithm(algorithm)?, leaf, root, whole(tree_size)?)?;
+        Ok(VerifyRequest::from(&proof))
+    }))
+}
+
+fn check_anchor(
+    receipt: Result<AnchorReceipt, Failure>,
+    context: Result<ReceiptContext, Failure>,
+) -> AnchorVerification {
+    let outcome = receipt
+        .map_err(|err| err.context("invalid receipt"))
+        .and_then(|receipt| {
+            let context = context.map_err(|err| err.context("invalid context"))?;
+            let paths = [
+                receipt.batch.as_ref().map(|batch| batch.proof.path.len()),
+                context.proof.as_ref().map(|proof| proof.path.len()),
+            ];
+            for len in paths.into_iter().flatten() {
+                too_many("path steps", len, MAX_PATH_LEN)?;
+            }
+            if let Some(consistency) = &context.consistency {
+                too_many(
+                    "consistency hashes",
+                    consistency.path.len(),
+                    2 * MAX_PATH_LEN,
+                )?;
+            }
+            Ok(receipt.verify(&context)?)
+        });
+    match outcome {
+        Ok(report) => AnchorVerification {
+            valid: true,
+            txid_checked: report.txid_checked,
+            signature_checked: report.signature_checked,
+            code: None,
+            error: None,
+        },
+        Err(err) => AnchorVerification {
+            valid: false,
+            txid_checked: false,
+            signature_checked: false,
+            code: Some(err.code),
+            error: Some(err.message),
+        },
+    }
+}
+
+fn check_bundle(
+    bundle: Result<EntryBundle, Failure>,
+    trusted_key: Option<&str>,
+) -> BundleVerification {
+    let bundle = match bundle {
+        Ok(bundle) => bundle,
+        Err(err) => {
+            let err = err.context("invalid bundle");
+            return BundleVerification {
+                valid: false,
+                log_public_key: None,
+                code: Some(err.code),
+                error: Some(err.message),
+            };
+        }
+    };
+    if let Err(err) = too_many("path steps", bundle.proof.path.len(), MAX_PATH_LEN) {
+        return check_bundle(Err(err), trusted_key);
+    }
+    let failure = bundle.verify(trusted_key).err().map(Failure::from);
+    BundleVerification {
+        valid: failure.is_none(),
+        log_public_key: Some(bundle.checkpoint.signature.public_key),
+        code: failure.as_ref().map(|err| err.code),
+        error: failure.map(|err| err.message),
+    }
+}
+
+fn check_checkpoint(
+    signed: Result<SignedCheckpoint, Failure>,
+    pubkey_hex: &str,
+) -> CheckpointVerification {
+    let signed = match signed {
+        Ok(signed) => signed,
+        Err(err) => {
+            let err = err.context("invalid checkpoint");
+            return CheckpointVerification {
+                valid: false,
+                root: String::new(),
+                size: 0,
+                code: Some(err.code),
+                error: Some(err.message),
+            };
+        }
+    };
+    let failure = if !signed.signature.public_key.eq_ignore_ascii_case(pubkey_hex) {
+        Some(Failure::new(
+            ErrorCode::UntrustedKey,
+            format!(
+                "checkpoint is signed by {}, not {pubkey_hex}",
+                signed.signature.public_key
+            ),
+        ))
+    } else if !signed.verify() {
+        Some(Failure::new(
+            ErrorCode::Signature,
+            "checkpoint signature is invalid",
+        ))
+    } else {
+        None
+    };
+    CheckpointVerification {
+        valid: failure.is_none(),
+        root: signed.checkpoint.root,
+        size: signed.checkpoint.size,
+        code: failure.as_ref().map(|err| err.code),
+        error: failure.map(|err| err.message),
+    }
+}
+
+fn construct(proof: Result<InclusionProof, Failure>) -> ConstructedProof {
+    match proof {
+        Ok(proof) => ConstructedProof {
+            proof: Some(proof),
+            code: None,
+            error: None,
+        },
+        Err(err) => ConstructedProof {
+            proof: None,
+            code: Some(err.code),
+            error: Some(err.message),
+        },
+    }
+}
+
+/// JS numbers are doubles; only accept exact non-negative integers.
+fn whole(value: f64) -> Result<u64, Failure> {
+    if value.fract() == 0.0 && (0.0..=9_007_199_254_740_991.0).contains(&value) {
+        Ok(value as u64)
+    } else {
+        Err(Failure::malformed(format!(
+            "{value} is not a valid leaf count or index"
+        )))
+    }
+}
+
+fn parse_algorithm(name: Option<String>) -> Result<HashAlgorithm, Failure> {
+    name.map_or(Ok(HashAlgorithm::default()), |name| {
+        name.parse().map_err(Failure::malformed)
+    })
+}
+
+fn too_many(what: &str, count: usize, limit: usize) -> Result<(), Failure> {
+    if count > limit {
+        Err(Failure::new(
+            ErrorCode::LimitExceeded,
+            format!("{count} {what} exceeds the limit of {limit}"),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Count a JS array before it is copied into wasm memory. Strings are left
+/// to `from_js`.
+fn max_items(value: &JsValue, limit: usize) -> Result<(), Failure> {
+    match value.dyn_ref::<js_sys::Array>() {
+        Some(array) => too_many("items", array.length() as usize, limit),
+        None => Ok(()),
+    }
+}
+
+/// Accept either a JS object or a JSON string.
+fn from_js<T: DeserializeOwned>(value: JsValue) -> Result<T, Failure> {
+    match value.dyn_ref::<js_sys::JsString>() {
+        // UTF-16 length; each unit is at least one UTF-8 byte.
+        Some(json) => {
+            too_many("input bytes", json.length() as usize, MAX_INPUT_BYTES)?;
+            from_json(&String::from(json))
+        }
+        None => {
+            serde_wasm_bindgen::from_value(value).map_err(|err| Failure::malformed(err.to_string()))
+        }
+    }
+}
+
+fn from_json<T: DeserializeOwned>(json: &str) -> Result<T, Failure> {
+    too_many("input bytes", json.len(), MAX_INPUT_BYTES)?;
+    serde_json::from_str(json).map_err(|err| Failure::malformed(err.to_string()))
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(value)?)
+}
+
+fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).expect("verification results serialize")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reality_core::leaf_hash;
+
+    #[test]
+    fn checkpoint_needs_the_pinned_key() {
+        use reality_core::{
+            checkpoint::Checkpoint,
+            signer::{KeyFileSigner, Signer},
+        };
+
+        let signer = KeyFileSigner::from_seed([9u8; 32]);
+        let signed = Checkpoint {
+            size: 2,
+            root: "ef".repeat(32),
+            timestamp_nanos: "1".into(),
+        }
+        .sign(&signer)
+        .unwrap();
+        let json = serde_json::to_string(&signed).unwrap();
+        let key = hex::encode(signer.public_key());
+
+        let ok = check_checkpoint(from_json(&json), &key);
+        assert!(ok.valid);
+        assert_eq!(ok.size, 2);
+        assert!(!check_checkpoint(from_json(&json), &"00".repeat(32)).valid);
+        let tampered = json.replace(r#""size":2"#, r#""size":3"#);
+        assert_eq!(
+            check_checkpoint(from_json(&tampered), &key)
+                .error
+                .as_deref(),
+            Some("checkpoint signature is invalid")
+        );
+    }
+
+    #[test]
+    fn leaf_helpers_match_core() {
+        assert_eq!(
+            leaf_hash_of_utf8("a", None).unwrap(),
+            "022a6979e6dab7aa5ae4c3e5e45f7e977112a7e63593820dbec1ec738a24f93c"
+        );
+        assert_eq!(
+            leaf_hash_hex("a".as_bytes(), None).unwrap(),
+            leaf_hash_of_utf8("a", None).unwrap()
+        );
+
+        let payload = vec![7u8; 100_000];
+        for algorithm in [None, Some("blake3".to_string())] {
+            let mut hasher = LeafHasher::new(algorithm.clone()).unwrap();
+            for chunk in payload.chunks(4096) {
+                hasher.update(chunk);
+            }
+            assert_eq!(
+                hasher.finalize(),
+                leaf_hash_hex(&payload, algorithm).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn tree_primitives_match_core() {
+        let (a, b) = (leaf_hash(b"a"), leaf_hash(b"b"));
+        assert_eq!(
+            node_hash_hex(&hex::encode(a), &hex::encode(b), None).unwrap(),
+            hex::encode(reality_core::root_with(HashAlgorithm::Sha256, &[a, b]))
+        );
+        assert_eq!(
+            empty_root_hex(None).unwrap(),
+            hex::encode(HashAlgorithm::Sha256.empty_root())
+        );
+        assert_ne!(
+            empty_root_hex(Some("blake3".into())).unwrap(),
+            empty_root_hex(None).unwrap()
+        );
+    }
+
+    #[test]
+    fn distinguishes_bad_input_from_bad_proof() {
+        let leaf = "ab".repeat(32);
+        let ok = check_inclusion(from_json(&format!(
+            r#"{{"index":0,"leaf":"{leaf}","path":[],"root":"{leaf}"}}"#
+        )));
+        assert!(ok.valid && ok.error.is_none() && ok.code.is_none());
+
+        let mismatch = check_inclusion(from_json(&format!(
+            r#"{{"index":0,"leaf":"{leaf}","path":[],"root":"{}"}}"#,
+            "cd".repeat(32)
+        )));
+        assert!(!mismatch.valid && mismatch.error.is_none());
+        assert_eq!(mismatch.computed_root, leaf);
+        assert_eq!(mismatch.code, Some(ErrorCode::RootMismatch));
+
+        let garbage = check_inclusion(from_json("{not json"));
+        assert!(!garbage.valid);
+        assert_eq!(garbage.code, Some(ErrorCode::MalformedInput));
+        assert!(garbage.error.unwrap().starts_with("invalid request"));
+
+        let bad_hex = check_inclusion(from_json(
+            r#"{"index":0,"leaf":"zz","path":[],"root":"00"}"#,
+        ));
+        assert!(bad_hex.error.is_some());
+        assert_eq!(bad_hex.code, Some(ErrorCode::BadHex));
+
+        let huge = check_inclusion(from_json(&" ".repeat(MAX_INPUT_BYTES + 1)));
+        assert_eq!(huge.code, Some(ErrorCode::LimitExceeded));
+    }
+
+    #[test]
+    fn verifies_ct_wire_proofs() {
+        let leaves: Vec<[u8; 32]> = (0..6u8).map(|i| leaf_hash(&[i])).collect();
+        let proof = reality_core::make_proof_with(HashAlgorithm::Sha256, &leaves, 5).unwrap();
+        let ct = CtInclusionProof::from_proof(&proof).unwrap();
+        let check = |ct: &CtInclusionProof, size| {
+            check_ct_proof(Ok(ct.clone()), &proof.leaf, &proof.root, size, None)
+        };
+        let ok = check(&ct, 6.0);
+        assert!(ok.valid && ok.error.is_none());
+
+        let mut reordered = ct.clone();
+        reordered.audit_path.swap(0, 1);
+        assert_eq!(check(&reordered, 6.0).code, Some(ErrorCode::RootMismatch));
+        assert_eq!(check(&ct, 3.0).code, Some(ErrorCode::OutOfRange));
+        assert_eq!(check(&ct, 6.5).code, Some(ErrorCode::MalformedInput));
+    }
+
+    #[test]
+    fn verifies_binary_proofs() {
+        let leaves: Vec<[u8; 32]> = (0..6u8).map(|i| leaf_hash(&[i])).collect();
+        let proof = reality_core::make_proof(&leaves, 4).unwrap();
+        let mut bytes = proof.to_bytes().unwrap();
+        let ok = check_inclusion_bytes(&bytes);
+        assert!(ok.valid && ok.error.is_none());
+        assert_eq!(ok.expected_root, proof.root);
+
+        bytes[30] ^= 1;
+        let tampered = check_inclusion_bytes(&bytes);
+        assert!(!tampered.valid && tampered.error.is_none());
+
+        let truncated = check_inclusion_bytes(&bytes[..40]);
+        assert!(truncated.error.unwrap().starts_with("invalid request"));
+    }
+
+    #[test]
+    fn builds_proofs_from_tiles() {
+        let hashes: Vec<[u8; 32]> = (0..300u32).map(|i| leaf_hash(&i.to_be_bytes())).collect();
+        let leaves: Vec<String> = hashes.iter().map(hex::encode).collect();
+        let tiles: Vec<LeafTile> = (0..).map_while(|n| tile::tile(&leaves, n)).collect();
+
+        let built = construct(
+            tile::prove_from_tiles(HashAlgorithm::Sha256, &tiles, 290, 270).map_err(Failure::from),
+        );
+        let proof = built.proof.expect("proof");
+        assert_eq!(
+            proof,
+            reality_core::make_proof_with(HashAlgorithm::Sha256, &hashes[..290], 270).unwrap()
+        );
+
+        assert!(whole(1.5).is_err() && whole(-1.0).is_err() && whole(f64::NAN).is_err());
+        assert_eq!(whole(290.0), Ok(290));
+    }
+
+    /// Field names of `export interface <name>` in `TS_TYPES`.
+    fn ts_fields(name: &str) -> Vec<String> {
+        let header = format!("export interface {name} {{");
+        let body = TS_TYPES
+            .split_once(&header)
+            .unwrap_or_else(|| panic!("no TypeScript interface {name}"))
+            .1;
+        let body = &body[..body.find('}').expect("closing brace")];
+        body.lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(field, _)| field.trim().trim_end_matches('?').to_string())
+            .collect()
+    }
+
+    fn json_fields<T: Serialize>(value: &T) -> Vec<String> {
+        match serde_json::to_value(value).unwrap() {
+            serde_json::Value::Object(map) => map.keys().cloned().collect(),
+            other => panic!("expected an object, got {other}"),
+        }
+    }
+
+    fn assert_shape<T: Serialize>(name: &str, sample: &T) {
+        let mut ts = ts_fields(name);
+        let mut rust = json_fields(sample);
+        ts.sort();
+        rust.sort();
+        assert_eq!(ts, rust, "TypeScript {name} does not match the Rust type");
+    }
+
+    #[test]
+    fn typescript_matches_serde_shapes() {
+        use reality_core::{
+            checkpoint::Checkpoint, make_proof_with, signer::KeyFileSigner, Direction, ProofStep,
+        };
+
+        // A non-default algorithm so the optional `algorithm` fields serialize.
+        let blake3 = HashAlgorithm::Blake3;
+        let leaves = [blake3.leaf_hash(b"a"), blake3.leaf_hash(b"b")];
+        let proof = make_proof_with(blake3, &leaves, 0).unwrap();
+        let checkpoint = Checkpoint {
+            size: 2,
+            root: proof.root.clone(),
+            timestamp_nanos: "1".into(),
+        }
+        .sign(&KeyFileSigner::from_seed([1u8; 32]))
+        .unwrap();
+        let error = Some(String::new());
+
+        assert_shape(
+            "ProofStep",
+            &ProofStep {
+                direction: Direction::Left,
+                hash: String::new(),
+            },
+        );
+        assert_shape("VerifyRequest", &VerifyRequest::from(&proof));
+        assert_shape("InclusionProof", &proof);
+        assert_shape("SignedCheckpoint", &checkpoint);
+        assert_shape(
+            "EntryBundle",
+            &EntryBundle {
+                payload: "a".into(),
+                meta: None,
+                proof: proof.clone(),
+                checkpoint: checkpoint.clone(),
+            },
+        );
+        assert_shape(
+            "CtInclusionProof",
+            &CtInclusionProof {
+                leaf_index: 0,
+                audit_path: vec![],
+            },
+        );
+        assert_shape(
+            "LeafTile",
+            &LeafTile {
+                index: 0,
+                leaves: vec![],
+            },
+        );
+        let anchor = reality_core::StoredAnchor {
+            record: reality_core::AnchorRecord {
+                metadata: reality_core::AnchorMetadata {
+                    backend: Some("simulated".into()),
+                    network: Some(String::new()),
+                    block_height: Some(1),
+                    confirmations: Some(1),
+                    receipt_uri: Some(String::new()),
+                    signing_identity: Some(String::new()),
+                },
+                ..reality_core::AnchorRecord::new(String::new(), 1, "1".into(), String::new())
+            },
+            status: reality_core::AnchorStatus::Confirmed,
+            signature: Some(checkpoint.signature.clone()),
+        };
+        assert_shape("AnchorRecord", &anchor.record);
+        assert_shape("DetachedSignature", &checkpoint.signature);
+        assert_shape(
+            "BatchMembership",
+            &reality_core::receipt::BatchMembership {
+                batch_txid: String::new(),
+                batch_root: String::new(),
+                proof: proof.clone(),
+            },
+        );
+        let receipt = AnchorReceipt {
+            anchor: anchor.clone(),
+            batch: Some(reality_core::receipt::BatchMembership {
+                batch_txid: String::new(),
+                batch_root: String::new(),
+                proof: proof.clone(),
+            }),
+        };
+        assert_shape("AnchorReceipt", &receipt);
+        let consistency =
+            reality_core::consistency::consistency_proof_with(blake3, &leaves, 1, leaves.len())
+                .unwrap();
+        assert_shape("ConsistencyProof", &consistency);
+        assert_shape(
+            "ReceiptContext",
+            &ReceiptContext {
+                proof: Some(proof.clone()),
+                previous: Some(anchor.record.clone()),
+                consistency: Some(consistency),
+                anchor_key: error.clone(),
+            },
+        );
+        assert_shape(
+            "AnchorVerification",
+            &AnchorVerification {
+                valid: true,
+                txid_checked: true,
+                signature_checked: true,
+                code: Some(ErrorCode::Fork),
+                error: error.clone(),
+            },
+        );
+        assert_shape(
+            "VerificationResult",
+            &VerificationResult {
+                valid: true,
+                computed_root: String::new(),
+                expected_root: String::new(),
+                code: Some(ErrorCode::Fork),
+                error: error.clone(),
+            },
+        );
+        assert_shape(
+            "BundleVerification",
+            &BundleVerification {
+                valid: true,
+                log_public_key: error.clone(),
+                code: Some(ErrorCode::Fork),
+                error: error.clone(),
+            },
+        );
+        assert_shape(
+            "VerifiedRead",
+            &VerifiedRead {
+                valid: true,
+                root: String::new(),
+                size: 0,
+                signed: true,
+                proof: Some(proof.clone()),
+                code: Some(ErrorCode::Fork),
+                error: error.clone(),
+            },
+        );
+        assert_shape(
+            "AdvanceResult",
+            &AdvanceResult {
+                valid: true,
+                fork: false,
+                root: String::new(),
+                size: 0,
+                code: Some(ErrorCode::Fork),
+                error: error.clone(),
+            },
+        );
+        assert_shape(
+            "ConstructedProof",
+            &ConstructedProof {
+                proof: Some(proof),
+                code: Some(ErrorCode::Fork),
+                error: error.clone(),
+            },
+        );
+        assert_shape(
+            "CheckpointVerification",
+            &CheckpointVerification {
+                valid: true,
+                root: String::new(),
+                size: 0,
+                code: Some(ErrorCode::Fork),
+                error,
+            },
+        );
+    }
+
+    #[test]
+    fn anchors_are_checked_against_their_proof() {
+        use reality_core::{make_proof, receipt::simulated_txid, AnchorRecord, StoredAnchor};
+
+        let leaves: Vec<[u8; 32]> = (0..3u8).map(|i| leaf_hash(&[i])).collect();
+        let proof = make_proof(&leaves, 2).unwrap();
+        let txid = simulated_txid(3, &proof.root, "5");
+        let receipt = AnchorReceipt {
+            anchor: StoredAnchor {
+                record: AnchorRecord::new(proof.root.clone(), 3, "5".into(), txid),
+                status: reality_core::AnchorStatus::Confirmed,
+                signature: None,
+            },
+            batch: None,
+        };
+        let context = ReceiptContext {
+            proof: Some(proof.clone()),
+            ..ReceiptContext::default()
+        };
+        let ok = check_anchor(Ok(receipt.clone()), Ok(context));
+        assert!(ok.valid && ok.txid_checked && !ok.signature_checked);
+
+        let other = ReceiptContext {
+            proof: Some(make_proof(&leaves[..2], 1).unwrap()),
+            ..ReceiptContext::default()
+        };
+        let mismatch = check_anchor(Ok(receipt), Ok(other));
+        assert!(!mismatch.valid);
+        assert!(mismatch.error.unwrap().contains("does not match"));
+    }
+
+    #[test]
+    fn batch_reports_each_item() {
+        let leaf = "ab".repeat(32);
+        let good = format!(r#"{{"index":0,"leaf":"{leaf}","path":[],"root":"{leaf}"}}"#);
+        let results: Vec<serde_json::Value> = serde_json::from_str(&verify_batch_json(&format!(
+            r#"[{good}, {{"index":"x"}}, {good}]"#
+        )))
+        .unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0]["valid"], true);
+        assert!(results[1]["error"].is_string());
+        assert_eq!(results[2]["valid"], true);
+    }
+
+    #[test]
+    fn hostile_inputs_hit_limits() {
+        let leaf = "ab".repeat(32);
+        let step = format!(r#"{{"direction":"left","hash":"{leaf}"}}"#);
+        let deep = format!(
+            r#"{{"index":0,"leaf":"{leaf}","path":[{}],"root":"{leaf}"}}"#,
+            vec![step; MAX_PATH_LEN + 1].join(",")
+        );
+        let result = check_inclusion(from_json(&deep));
+        assert!(result
+            .error
+            .unwrap()
+            .contains("path steps exceeds the limit"));
+
+        let huge = " ".repeat(MAX_INPUT_BYTES + 1);
+        assert!(check_inclusion(from_json(&huge))
+            .error
+            .unwrap()
+            .contains("input bytes"));
+
+        let batch = vec![serde_json::Value::Null; MAX_BATCH + 1];
+        let results = check_batch(Ok(batch));
+        assert_eq!(results.len(), 1);
+        assert!(results[0].error.as_ref().unwrap().contains("batch items"));
+    }
+
+    #[test]
+    fn proofs_carry_their_algorithm() {
+        let algorithm = HashAlgorithm::Blake3;
+        let leaves: Vec<[u8; 32]> = (0..5u8).map(|i| algorithm.leaf_hash(&[i])).collect();
+        let proof = reality_core::make_proof_with(algorithm, &leaves, 3).unwrap();
+        let json = serde_json::to_string(&proof).unwrap();
+        assert!(json.contains(r#""algorithm":"blake3""#));
+        assert!(check_inclusion(from_json(&json)).valid);
+        assert!(check_inclusion_bytes(&proof.to_bytes().unwrap()).valid);
+
+        let relabelled = json.replace(r#","algorithm":"blake3""#, "");
+        assert!(!check_inclusion(from_json(&relabelled)).valid);
+        assert!(parse_algorithm(Some("md5".into())).is_err());
+    }
+
+    #[test]
+    fn deepest_binary_proof_fits_the_limit() {
+        let algorithm = HashAlgorithm::Blake3;
+        let leaf = algorithm.leaf_hash(b"deep");
+        let mut proof = InclusionProof {
+            index: 0,
+            leaf: hex::encode(leaf),
+            path: (0..MAX_PATH_LEN as u8)
+                .map(|i| reality_core::ProofStep {
+                    direction: reality_core::Direction::Right,
+                    hash: hex::encode(algorithm.leaf_hash(&[i])),
+                })
+                .collect(),
+            root: String::new(),
+            size: u64::MAX,
+            algorithm,
+        };
+        proof.root = verify(&(&proof).into()).computed_root;
+        let bytes = proof.to_bytes().unwrap();
+        assert_eq!(bytes.len(), MAX_BINARY_PROOF);
+        assert!(binary_proof_fits(bytes.len()).is_ok());
+        assert!(check_inclusion_bytes(&bytes).valid);
+        assert!(binary_proof_fits(bytes.len() + 1).is_err());
+    }
+
+    #[test]
+    fn json_shim_returns_result_objects() {
+        let result: serde_json::Value = serde_json::from_str(&verify_inclusion_json("[]")).unwrap();
+        assert_eq!(result["valid"], false);
+        assert!(result["error"].is_string());
     }
 }
@@ -0,0 +1,285 @@
+//! Trust-on-first-use state for one log: the newest checkpoint this browser
+//! has verified. The host persists `serialize()` (e.g. in `localStorage`) and
+//! restores it on the next visit, so a log that forks its history is caught
+//! even when each view of it is validly signed.
+
+use reality_core::{
+    checkpoint::SignedCheckpoint,
+    consistency::{verify_consistency, ConsistencyProof},
+};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::{from_js, from_json, to_js, too_many, ErrorCode, Failure, MAX_PATH_LEN};
+
+const STATE_VERSION: u8 = 1;
+
+/// Outcome of `TrustedCheckpoint.advance`. `root` and `size` are the trusted
+/// head afterwards, which is unchanged when `valid` is false. `fork` is set
+/// when a correctly signed checkpoint cannot extend the trusted one: evidence
+/// that the log showed someone a different history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AdvanceResult {
+    pub valid: bool,
+    pub fork: bool,
+    pub root: String,
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct TrustState {
+    version: u8,
+    /// Pinned by the host, or taken from the first checkpoint accepted.
+    log_public_key: Option<String>,
+    checkpoint: Option<SignedCheckpoint>,
+}
+
+/// The latest checkpoint verified for one log. Create it with the log's key,
+/// or without one to trust the key of the first checkpoint it accepts.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct TrustedCheckpoint(TrustState);
+
+#[wasm_bindgen]
+impl TrustedCheckpoint {
+    #[wasm_bindgen(constructor)]
+    pub fn new(log_public_key: Option<String>) -> TrustedCheckpoint {
+        Self(TrustState {
+            version: STATE_VERSION,
+            log_public_key,
+            checkpoint: None,
+        })
+    }
+
+    /// Rebuild the state from `serialize()` output. The stored checkpoint is
+    /// checked again, so a corrupted blob is refused rather than trusted.
+    pub fn restore(state: &str) -> Result<TrustedCheckpoint, JsValue> {
+        restore_state(state).map(Self).map_err(Failure::into_js)
+    }
+
+    /// Accept `checkpoint` if it is signed by the log's key and extends the
+    /// trusted one: same root at the same size, or a larger tree linked by
+    /// `consistency`. Nothing changes when it is refused.
+    #[wasm_bindgen(unchecked_return_type = "AdvanceResult")]
+    pub fn advance(
+        &mut self,
+        #[wasm_bindgen(unchecked_param_type = "SignedCheckpoint | string")] checkpoint: JsValue,
+        #[wasm_bindgen(unchecked_param_type = "ConsistencyProof | string")] consistency: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let consistency =
+            (!consistency.is_undefined() && !consistency.is_null()).then(|| from_js(consistency));
+        to_js(&self.0.advance(from_js(checkpoint), consistency))
+    }
+
+    /// The trusted checkpoint, if one has been accepted.
+    #[wasm_bindgen(getter, unchecked_return_type = "SignedCheckpoint | undefined")]
+    pub fn checkpoint(&self) -> Result<JsValue, JsValue> {
+        to_js(&self.0.checkpoint)
+    }
+
+    /// JSON for the host to store and pass to `restore`.
+    pub fn serialize(&self) -> String {
+        serde_json::to_string(&self.0).expect("trust state serializes")
+    }
+}
+
+fn restore_state(state: &str) -> Result<TrustState, Failure> {
+    let state: TrustState = from_json(state).map_err(|err| err.context("invalid state"))?;
+    if state.version != STATE_VERSION {
+        return Err(Failure::malformed(format!(
+            "unsupported state version {}",
+            state.version
+        )));
+    }
+    if let Some(signed) = &state.checkpoint {
+        let key = state.log_public_key.as_deref().unwrap_or_default();
+        if !signed.verify_with(key) {
+            return Err(Failure::new(
+                ErrorCode::Signature,
+                "stored checkpoint is not signed by the stored log key",
+            ));
+        }
+    }
+    Ok(state)
+}
+
+impl TrustState {
+    fn advance(
+        &mut self,
+        checkpoint: Result<SignedCheckpoint, Failure>,
+        consistency: Option<Result<ConsistencyProof, Failure>>,
+    ) -> AdvanceResult {
+        let outcome = checkpoint
+            .map_err(|err| err.context("invalid checkpoint"))
+            .and_then(|signed| {
+                let consistency = consistency
+                    .transpose()
+                    .map_err(|err| err.context("invalid consistency proof"))?;
+                self.check(&signed, consistency.as_ref())?;
+                Ok(signed)
+            });
+        let failure = match outcome {
+            Ok(signed) => {
+                self.log_public_key = Some(signed.signature.public_key.clone());
+                self.checkpoint = Some(signed);
+                None
+            }
+            Err(err) => Some(err),
+        };
+        let trusted = self.checkpoint.as_ref().map(|signed| &signed.checkpoint);
+        AdvanceResult {
+            valid: failure.is_none(),
+            fork: failure
+                .as_ref()
+                .is_some_and(|err| err.code == ErrorCode::Fork),
+            root: trusted.map(|c| c.root.clone()).unwrap_or_default(),
+            size: trusted.map_or(0, |c| c.size),
+            code: failure.as_ref().map(|err| err.code),
+            error: failure.map(|err| err.message),
+        }
+    }
+
+    fn check(
+        &self,
+        signed: &SignedCheckpoint,
+        consistency: Option<&ConsistencyProof>,
+    ) -> Result<(), Failure> {
+        let key = self
+            .log_public_key
+            .as_deref()
+            .unwrap_or(&signed.signature.public_key);
+        if !signed.signature.public_key.eq_ignore_ascii_case(key) {
+            return Err(Failure::new(
+                ErrorCode::UntrustedKey,
+                format!("checkpoint is not signed by the log key {key}"),
+            ));
+        }
+        if !signed.verify() {
+            return Err(Failure::new(
+                ErrorCode::Signature,
+                "checkpoint signature is invalid",
+            ));
+        }
+        let Some(trusted) = self.checkpoint.as_ref().map(|t| &t.checkpoint) else {
+            return Ok(());
+        };
+        let observed = &signed.checkpoint;
+        if observed.size < trusted.size {
+            return Err(Failure::new(
+                ErrorCode::Consistency,
+                format!(
+                    "checkpoint for {} leaves is older than the trusted {}",
+                    observed.size, trusted.size
+                ),
+            ));
+        }
+        if observed.size == trusted.size {
+            if !observed.root.eq_ignore_ascii_case(&trusted.root) {
+                return Err(Failure::new(
+                    ErrorCode::Fork,
+                    format!("root changed at size {}", observed.size),
+                ));
+            }
+            return Ok(());
+        }
+        let Some(proof) = consistency else {
+            return Err(Failure::new(
+                ErrorCode::Consistency,
+                format!(
+                    "a consistency proof from size {} to {} is required",
+                    trusted.size, observed.size
+                ),
+            ));
+        };
+        too_many("consistency hashes", proof.path.len(), 2 * MAX_PATH_LEN)?;
+        let matches = proof.old_size == trusted.size
+            && proof.new_size == observed.size
+            && proof.old_root.eq_ignore_ascii_case(&trusted.root)
+            && proof.new_root.eq_ignore_ascii_case(&observed.root);
+        if !matches {
+            return Err(Failure::new(
+                ErrorCode::Consistency,
+                format!(
+                    "consistency proof is not from size {} to {}",
+                    trusted.size, observed.size
+                ),
+            ));
+        }
+        if !verify_consistency(proof) {
+            return Err(Failure::new(
+                ErrorCode::Fork,
+                format!(
+                    "checkpoint at size {} does not extend the trusted tree",
+                    observed.size
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reality_core::{
+        checkpoint::Checkpoint,
+        consistency::consistency_proof,
+        leaf_hash, root,
+        signer::{KeyFileSigner, Signer},
+    };
+
+    fn head(signer: &KeyFileSigner, leaves: &[[u8; 32]]) -> SignedCheckpoint {
+        Checkpoint {
+            size: leaves.len() as u64,
+            root: hex::encode(root(leaves)),
+            timestamp_nanos: "1".into(),
+        }
+        .sign(signer)
+        .unwrap()
+    }
+
+    #[test]
+    fn advances_only_along_one_history() {
+        let signer = KeyFileSigner::from_seed([4u8; 32]);
+        let leaves: Vec<[u8; 32]> = (0..8u8).map(|i| leaf_hash(&[i])).collect();
+        let mut state = TrustedCheckpoint::new(None).0;
+
+        // First use pins the checkpoint and its key.
+        assert!(state.advance(Ok(head(&signer, &leaves[..3])), None).valid);
+        assert_eq!(state.log_public_key, Some(hex::encode(signer.public_key())));
+
+        let grown = head(&signer, &leaves[..6]);
+        let missing = state.advance(Ok(grown.clone()), None);
+        assert!(!missing.valid && !missing.fork && missing.size == 3);
+        let proof = consistency_proof(&leaves, 3, 6).unwrap();
+        let advanced = state.advance(Ok(grown), Some(Ok(proof)));
+        assert!(advanced.valid);
+        assert_eq!(advanced.size, 6);
+
+        let mut forked_leaves = leaves.clone();
+        forked_leaves[1] = leaf_hash(b"rewritten");
+        let fork = state.advance(Ok(head(&signer, &forked_leaves[..6])), None);
+        assert!(fork.fork && !fork.valid);
+
+        let forked = head(&signer, &forked_leaves);
+        let mut lying = consistency_proof(&forked_leaves, 6, forked_leaves.len()).unwrap();
+        lying.old_root = advanced.root.clone();
+        assert!(state.advance(Ok(forked), Some(Ok(lying))).fork);
+
+        let other = KeyFileSigner::from_seed([5u8; 32]);
+        let impostor = state.advance(Ok(head(&other, &leaves[..6])), None);
+        assert!(!impostor.valid && !impostor.fork);
+        assert_eq!(impostor.code, Some(ErrorCode::UntrustedKey));
+        assert_eq!(state.checkpoint.as_ref().unwrap().checkpoint.size, 6);
+
+        let restored = restore_state(&TrustedCheckpoint(state.clone()).serialize()).unwrap();
+        assert_eq!(restored, state);
+        let mut tampered = state;
+        tampered.checkpoint.as_mut().unwrap().checkpoint.size = 7;
+        assert!(restore_state(&serde_json::to_string(&tampered).unwrap()).is_err());
+    }
+}